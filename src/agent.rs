@@ -0,0 +1,257 @@
+use serde::Deserialize;
+use std::sync::{LazyLock, RwLock};
+
+use crate::db::{Database, Session};
+use crate::startup::{self, CommandValues};
+
+/// Profile a session runs when it names none
+pub const DEFAULT: &str = "claude";
+
+/// What Claude Code shows when it asks something, looked for in the last lines of its pane
+const CLAUDE_PROMPTS: &[&str] = &[
+    "Enter to select",
+    "Do you want to",
+    "yes/yes to all/no",
+    "Allow once",
+    "Allow always",
+    "(y/n)",
+    "[Y/n]",
+    "[y/N]",
+];
+
+/// Pane lines searched for a waiting pattern, counted from the bottom
+const WAITING_LINES: usize = 5;
+
+/// How a session's terminal is started, watched and restarted
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Agent {
+    pub name: String,
+    /// Program the agent runs as; its pane is the one running it. Empty for a plain shell
+    #[serde(default)]
+    pub binary: String,
+    /// Arguments to start it with, as a startup command template (`{name}`, `{branch}`, ...).
+    /// Without them the project's Startup command runs instead
+    #[serde(default)]
+    pub args: Option<String>,
+    /// Text in the last lines of the pane meaning the agent waits for an answer
+    #[serde(default)]
+    pub waiting: Vec<String>,
+    /// Arguments to start it again with once it exited, e.g. to resume its conversation.
+    /// Without them it restarts as it first started
+    #[serde(default)]
+    pub resume: Option<String>,
+}
+
+impl Agent {
+    /// Claude Code, started by the project's Startup command as sessions always were
+    pub fn claude() -> Self {
+        Self {
+            name: DEFAULT.to_string(),
+            binary: crate::ai::binary(),
+            args: None,
+            waiting: CLAUDE_PROMPTS.iter().map(|p| p.to_string()).collect(),
+            resume: None,
+        }
+    }
+
+    /// A plain shell: no startup command and nothing to wait on
+    pub fn shell() -> Self {
+        Self { name: "shell".to_string(), binary: String::new(), args: Some(String::new()), waiting: Vec::new(), resume: None }
+    }
+
+    /// Command name tmux reports for the agent's pane, if it runs a program
+    pub fn pane_command(&self) -> Option<String> {
+        let binary = self.binary.trim();
+        let name = std::path::Path::new(binary).file_name()?.to_string_lossy().into_owned();
+        Some(name).filter(|n| !n.is_empty())
+    }
+
+    /// The command a new session starts with; None when it's up to the project's Startup
+    /// command, empty for a plain shell
+    pub fn startup_command(&self, values: &CommandValues) -> Result<Option<String>, String> {
+        self.args.as_deref().map(|args| self.command_line(args, values)).transpose()
+    }
+
+    /// The command to respawn an exited pane with; None to rerun what it started with
+    pub fn resume_command(&self, values: &CommandValues) -> Result<Option<String>, String> {
+        self.resume.as_deref().map(|args| self.command_line(args, values)).transpose()
+    }
+
+    fn command_line(&self, args: &str, values: &CommandValues) -> Result<String, String> {
+        let args = startup::expand(args.trim(), values)?;
+        let binary = Some(self.binary.trim()).filter(|b| !b.is_empty()).map(startup::shell_quote);
+        Ok(binary.into_iter().chain(Some(args).filter(|a| !a.is_empty())).collect::<Vec<_>>().join(" "))
+    }
+
+    /// Whether pane `content` ends with one of the agent's waiting patterns
+    pub fn is_waiting(&self, content: &str) -> bool {
+        let last_lines: Vec<&str> = content.lines().rev().take(WAITING_LINES).collect();
+        let last_lines = last_lines.join("\n");
+        self.waiting.iter().any(|pattern| !pattern.is_empty() && last_lines.contains(pattern.as_str()))
+    }
+}
+
+/// The built-in profiles, default first
+pub fn builtins() -> Vec<Agent> {
+    vec![Agent::claude(), Agent::shell()]
+}
+
+/// The built-ins plus the profiles in an agents file (a JSON list); a profile named
+/// like a built-in replaces it
+pub fn parse(text: &str) -> Result<Vec<Agent>, String> {
+    let custom: Vec<Agent> = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let mut agents = builtins();
+    let mut seen = Vec::new();
+    for agent in custom {
+        let name = agent.name.trim();
+        if name.is_empty() {
+            return Err("An agent has no name".to_string());
+        }
+        if seen.contains(&name.to_lowercase()) {
+            return Err(format!("Agent \"{}\" is defined twice", name));
+        }
+        seen.push(name.to_lowercase());
+        let agent = Agent { name: name.to_string(), ..agent };
+        match agents.iter_mut().find(|a| a.name.eq_ignore_ascii_case(&agent.name)) {
+            Some(builtin) => *builtin = agent,
+            None => agents.push(agent),
+        }
+    }
+    Ok(agents)
+}
+
+/// Profiles from the agents file, with the problem reading it if there was one (the
+/// built-ins are used then). A missing file is no problem
+fn load() -> (Vec<Agent>, Option<String>) {
+    let path = match Database::agents_path() {
+        Ok(path) => path,
+        Err(e) => return (builtins(), Some(e.to_string())),
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => match parse(&text) {
+            Ok(agents) => (agents, None),
+            Err(e) => (builtins(), Some(format!("{}: {}", path.display(), e))),
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => (builtins(), None),
+        Err(e) => (builtins(), Some(format!("{}: {}", path.display(), e))),
+    }
+}
+
+/// Profiles as last loaded, shared by the board, the CLI and the tmux poller
+static AGENTS: LazyLock<RwLock<(Vec<Agent>, Option<String>)>> = LazyLock::new(|| RwLock::new(load()));
+
+/// Every profile, default first
+pub fn all() -> Vec<Agent> {
+    AGENTS.read().map(|agents| agents.0.clone()).unwrap_or_else(|_| builtins())
+}
+
+/// The problem with the agents file when it was last read
+pub fn load_error() -> Option<String> {
+    AGENTS.read().ok().and_then(|agents| agents.1.clone())
+}
+
+/// Read the agents file again; returns the problem with it, if any, in which case the
+/// profiles loaded before stay
+pub fn reload() -> Option<String> {
+    let (loaded, error) = load();
+    if let Ok(mut agents) = AGENTS.write() {
+        if error.is_none() {
+            agents.0 = loaded;
+        }
+        agents.1 = error.clone();
+    }
+    error
+}
+
+/// Problems with the templates of the loaded profiles, which would stop their sessions
+/// from starting
+pub fn template_problems() -> Vec<String> {
+    let values = CommandValues::sample();
+    all()
+        .iter()
+        .filter_map(|agent| {
+            let error = agent.startup_command(&values).err().or_else(|| agent.resume_command(&values).err())?;
+            Some(format!("Agent {}: {}", agent.name, error))
+        })
+        .collect()
+}
+
+/// A profile by name, falling back to the default one (e.g. after it was removed)
+pub fn named(name: Option<&str>) -> Agent {
+    let agents = all();
+    let found = name.and_then(|name| agents.iter().find(|a| a.name.eq_ignore_ascii_case(name)));
+    found.or_else(|| agents.first()).cloned().unwrap_or_else(Agent::claude)
+}
+
+/// The profile a session runs
+pub fn for_session(session: &Session) -> Agent {
+    named(session.agent.as_deref())
+}
+
+/// Pane commands of every profile that runs a program, for finding agent panes
+pub fn pane_commands() -> Vec<String> {
+    all().iter().filter_map(Agent::pane_command).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let agents = parse("[]").unwrap();
+        assert_eq!(agents, builtins());
+        assert_eq!(agents[0].name, DEFAULT);
+
+        let agents = parse(
+            r#"[
+                {"name": "aider", "binary": "aider", "args": "--file {path}/TODO.md", "waiting": ["(Y)es/(N)o"], "resume": "--restore-chat-history"},
+                {"name": "Shell", "binary": "bash", "args": "-l"}
+            ]"#,
+        )
+        .unwrap();
+        let names: Vec<&str> = agents.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["claude", "Shell", "aider"]);
+        assert_eq!(agents[1].binary, "bash");
+        assert!(agents[1].waiting.is_empty());
+
+        assert!(parse("{").is_err());
+        assert_eq!(parse(r#"[{"name": " "}]"#).unwrap_err(), "An agent has no name");
+        assert!(parse(r#"[{"name": "a"}, {"name": "A"}]"#).unwrap_err().contains("twice"));
+    }
+
+    #[test]
+    fn test_commands() {
+        let values = CommandValues::sample();
+        // Claude leaves starting to the project's Startup command; a shell starts nothing
+        assert_eq!(Agent::claude().startup_command(&values).unwrap(), None);
+        assert_eq!(Agent::claude().resume_command(&values).unwrap(), None);
+        assert_eq!(Agent::shell().startup_command(&values).unwrap().as_deref(), Some(""));
+        assert_eq!(Agent::shell().pane_command(), None);
+
+        let aider = Agent {
+            name: "aider".to_string(),
+            binary: "/opt/my tools/aider".to_string(),
+            args: Some("--message {name}".to_string()),
+            waiting: Vec::new(),
+            resume: Some("--restore-chat-history".to_string()),
+        };
+        assert_eq!(
+            aider.startup_command(&values).unwrap().as_deref(),
+            Some("'/opt/my tools/aider' --message 'Fix login redirect'")
+        );
+        assert_eq!(aider.resume_command(&values).unwrap().as_deref(), Some("'/opt/my tools/aider' --restore-chat-history"));
+        assert_eq!(aider.pane_command().as_deref(), Some("aider"));
+        let broken = Agent { args: Some("{nmae}".to_string()), ..aider };
+        assert!(broken.startup_command(&values).is_err());
+    }
+
+    #[test]
+    fn test_is_waiting() {
+        let claude = Agent::claude();
+        assert!(claude.is_waiting("output\n\nDo you want to proceed?\n❯ 1. Yes\n  2. No\n"));
+        assert!(!claude.is_waiting("Do you want to\n1\n2\n3\n4\n5\n"));
+        assert!(!claude.is_waiting("> "));
+        assert!(!Agent::shell().is_waiting("Continue? (y/n)"));
+    }
+}
@@ -1,20 +1,257 @@
 use color_eyre::{eyre::eyre, Result};
+use crate::{context, logging};
+use std::path::Path;
 use std::process::{Command, Stdio};
 use std::time::Duration;
 use std::io::Read;
 
+/// Model alias asked for by field fills
+const FILL_MODEL: &str = "haiku";
+
+/// Environment variable naming the claude binary, for installs outside PATH
+pub const BINARY_ENV: &str = "WORKBENCH_CLAUDE";
+
+/// Shown wherever AI features are unavailable
+pub const MISSING_MESSAGE: &str = "claude CLI not found: install it or set WORKBENCH_CLAUDE to its path";
+
+/// The claude binary to run: `$WORKBENCH_CLAUDE`, else `claude` from PATH
+pub fn binary() -> String {
+    std::env::var(BINARY_ENV)
+        .ok()
+        .filter(|b| !b.trim().is_empty())
+        .unwrap_or_else(|| "claude".to_string())
+}
+
+/// Check if the claude CLI is installed, without running it (cheap enough for startup)
+pub fn is_available() -> bool {
+    let binary = binary();
+    if binary.contains('/') {
+        return is_executable(Path::new(&binary));
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| is_executable(&dir.join(&binary))))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// A temp dir named for the test holding an executable `name` script with the given
+/// body, standing in for claude, tmux and the like
+#[cfg(all(test, unix))]
+pub fn fake_command(test_name: &str, name: &str, body: &str) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::env::temp_dir().join(format!("workbench-{}-{}", test_name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join(name);
+    std::fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    dir
+}
+
+/// What a claude call reported using. Older CLIs leave parts out, so each may be missing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub model: Option<String>,
+    /// Input, output and cache tokens together
+    pub tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+}
+
+/// Field values from a fill, with what producing them cost
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub values: Vec<String>,
+    pub usage: Usage,
+}
+
+/// Where the card being filled stands. Both parts are told to claude and can be used in
+/// field hints as `{project}` and `{status}`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CardContext {
+    pub project: Option<String>,
+    /// The card's column, as labelled in the project
+    pub status: Option<String>,
+}
+
 /// Use Claude CLI to fill in field values based on session name and context
 pub fn fill_fields(
     session_name: &str,
     fields: &[(String, String)], // (name, description) pairs
     pane_content: Option<&str>,
-) -> Result<Vec<String>> {
+    max_context_chars: usize,
+    card: &CardContext,
+) -> Result<Fill> {
+    let prompt = build_prompt(session_name, fields, pane_content, max_context_chars, card);
+
+    let mut command = Command::new(binary());
+    command.args(["-p", &prompt, "--output-format", "json", "--model", FILL_MODEL, "--max-turns", "1"]);
+    let stdout = run_claude(command).inspect_err(|e| tracing::error!(error = %e, "claude fill failed"))?;
+
+    let mut values = parse_response(&stdout).inspect_err(|e| {
+        tracing::error!(error = %e, stdout = %logging::truncate(&stdout), "unexpected claude output")
+    })?;
+
+    // Ensure we have the right number of values
+    values.resize(fields.len(), String::new());
+
+    Ok(Fill { values, usage: parse_usage(&stdout, FILL_MODEL) })
+}
+
+/// Run a prepared claude command with a timeout, returning its stdout
+/// Fails with the exit status and stderr if claude exits unsuccessfully
+fn run_claude(mut command: Command) -> Result<String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => eyre!("{}", MISSING_MESSAGE),
+            _ => eyre!("Failed to run claude: {}", e),
+        })?;
+
+    // Drain both pipes on threads so a chatty child can't block on a full pipe
+    let stdout_reader = child.stdout.take().map(|mut out| {
+        std::thread::spawn(move || {
+            let mut buf = String::new();
+            out.read_to_string(&mut buf).ok();
+            buf
+        })
+    });
+    let stderr_reader = child.stderr.take().map(|mut err| {
+        std::thread::spawn(move || {
+            let mut buf = String::new();
+            err.read_to_string(&mut buf).ok();
+            buf
+        })
+    });
+
+    // Wait with timeout (30 seconds)
+    let start = std::time::Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {
+                if start.elapsed() > Duration::from_secs(30) {
+                    tracing::error!("claude timed out after 30s");
+                    let _ = child.kill();
+                    return Err(eyre!("claude command timed out"));
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(eyre!("Error waiting for claude: {}", e)),
+        }
+    };
+
+    let stdout = stdout_reader.and_then(|h| h.join().ok()).unwrap_or_default();
+    let stderr = stderr_reader.and_then(|h| h.join().ok()).unwrap_or_default();
+    tracing::debug!(
+        elapsed_ms = start.elapsed().as_millis() as u64,
+        status = %status,
+        stdout = %logging::truncate(&stdout),
+        stderr = %logging::truncate(&stderr),
+        "claude finished"
+    );
+
+    if !status.success() {
+        let code = status
+            .code()
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "a signal".to_string());
+        let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        return Err(if detail.is_empty() {
+            eyre!("claude exited with {}", code)
+        } else {
+            eyre!("claude exited with {}: {}", code, detail)
+        });
+    }
+
+    Ok(stdout)
+}
+
+/// Parse claude's `--output-format json` output into the array of field values
+fn parse_response(stdout: &str) -> Result<Vec<String>> {
+    let response: serde_json::Value = serde_json::from_str(stdout)
+        .map_err(|_| eyre!("Failed to parse claude output as JSON"))?;
+
+    // Extract the result text from claude's JSON output
+    let text = response["result"]
+        .as_str()
+        .ok_or_else(|| eyre!("No result in claude output"))?;
+
+    if response["is_error"].as_bool() == Some(true) {
+        return Err(eyre!("claude returned an error: {}", text));
+    }
+
+    // Parse JSON array from the result
+    serde_json::from_str(text.trim())
+        .map_err(|_| eyre!("Failed to parse AI response as JSON array"))
+}
+
+/// Read the usage and cost claude reports next to its result. Never fails: whatever is
+/// missing stays None, and the model falls back to the alias that was asked for
+fn parse_usage(stdout: &str, requested_model: &str) -> Usage {
+    let Ok(response) = serde_json::from_str::<serde_json::Value>(stdout) else {
+        return Usage { model: Some(requested_model.to_string()), ..Usage::default() };
+    };
+    let tokens = response["usage"].as_object().and_then(|usage| {
+        let counts: Vec<i64> = ["input_tokens", "output_tokens", "cache_creation_input_tokens", "cache_read_input_tokens"]
+            .iter()
+            .filter_map(|key| usage.get(*key).and_then(|v| v.as_i64()))
+            .collect();
+        (!counts.is_empty()).then(|| counts.iter().sum())
+    });
+    let model = response["modelUsage"]
+        .as_object()
+        .and_then(|models| models.keys().next().cloned())
+        .unwrap_or_else(|| requested_model.to_string());
+    Usage {
+        model: Some(model),
+        tokens,
+        cost_usd: response["total_cost_usd"].as_f64().or_else(|| response["cost_usd"].as_f64()),
+    }
+}
+
+/// Fill `{project}` and `{status}` in a field hint; other braces are left alone, and so
+/// are these when the card doesn't say
+fn expand_hint(hint: &str, card: &CardContext) -> String {
+    let mut hint = hint.to_string();
+    for (placeholder, value) in [("{project}", &card.project), ("{status}", &card.status)] {
+        if let Some(value) = value {
+            hint = hint.replace(placeholder, value);
+        }
+    }
+    hint
+}
+
+/// Build the fill prompt, with pane content trimmed to `max_context_chars`
+fn build_prompt(
+    session_name: &str,
+    fields: &[(String, String)],
+    pane_content: Option<&str>,
+    max_context_chars: usize,
+    card: &CardContext,
+) -> String {
     let fields_desc: String = fields
         .iter()
         .enumerate()
         .map(|(i, (name, desc))| {
+            let desc = expand_hint(desc, card);
+            let desc = desc.trim();
             if desc.is_empty() {
                 format!("{}. {}", i + 1, name)
+            } else if desc.contains('\n') {
+                // Indent multi-line descriptions so they stay under their field number
+                let indented: Vec<String> = desc.lines().map(|line| format!("   {}", line.trim_end())).collect();
+                format!("{}. {}:\n{}", i + 1, name, indented.join("\n"))
             } else {
                 format!("{}. {} ({})", i + 1, name, desc)
             }
@@ -22,27 +259,24 @@ pub fn fill_fields(
         .collect::<Vec<_>>()
         .join("\n");
 
-    let context = if let Some(content) = pane_content {
-        // Take last 2000 chars of pane content for context (handle UTF-8 properly)
-        let char_count = content.chars().count();
-        let trimmed = if char_count > 2000 {
-            content.chars().skip(char_count - 2000).collect::<String>()
-        } else {
-            content.to_string()
-        };
-        format!("\n\nTerminal content:\n{}", trimmed)
-    } else {
-        String::new()
+    // Where the card stands decides what a field should hold (a plan early on, a PR link
+    // in review), so say it when known
+    let card_desc = match (&card.project, &card.status) {
+        (Some(project), Some(status)) => format!("\nThe card is in the \"{}\" column of project \"{}\".\n", status, project),
+        (None, Some(status)) => format!("\nThe card is in the \"{}\" column.\n", status),
+        (Some(project), None) => format!("\nThe card belongs to project \"{}\".\n", project),
+        (None, None) => String::new(),
     };
 
-    let prompt = format!(
-        r#"You are a simple JSON generator. Do NOT use any tools, search, or external lookups.
+    let render = |context: &str| {
+        format!(
+            r#"You are a simple JSON generator. Do NOT use any tools, search, or external lookups.
 
 The user has provided this input:
 ---
 {}
 ---
-
+{}
 Match this input to the following fields. The input might be:
 - A value that belongs in one of the fields (put it there)
 - Contains multiple pieces of info (extract and place in appropriate fields)
@@ -53,53 +287,154 @@ Fields to fill:
 {}
 {}
 Respond with ONLY a JSON array of strings, one value per field in order. Use empty string "" for fields that don't match. Example: ["value1", "", "value3"]"#,
-        session_name, fields_desc, context
-    );
+            session_name, card_desc, fields_desc, context
+        )
+    };
 
-    let mut child = Command::new("claude")
-        .args(["-p", &prompt, "--output-format", "json", "--model", "haiku", "--max-turns", "1"])
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .spawn()
-        .map_err(|e| eyre!("Failed to run claude: {}", e))?;
+    let base = render("");
+    let Some(content) = pane_content.filter(|c| !c.trim().is_empty()) else {
+        return base;
+    };
 
-    // Wait with timeout (30 seconds)
-    let start = std::time::Instant::now();
-    loop {
-        match child.try_wait() {
-            Ok(Some(_status)) => break,
-            Ok(None) => {
-                if start.elapsed() > Duration::from_secs(30) {
-                    let _ = child.kill();
-                    return Err(eyre!("claude command timed out"));
-                }
-                std::thread::sleep(Duration::from_millis(100));
-            }
-            Err(e) => return Err(eyre!("Error waiting for claude: {}", e)),
-        }
+    // Keep the most recent output, minus spinners and banners
+    let trimmed = context::trim(content, max_context_chars);
+    if trimmed.is_empty() {
+        base
+    } else {
+        render(&format!("\n\nTerminal content:\n{}", trimmed))
     }
+}
 
-    let mut stdout = String::new();
-    if let Some(mut out) = child.stdout.take() {
-        out.read_to_string(&mut stdout).ok();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[cfg(unix)]
+    fn claude_on_path(dir: &PathBuf) -> Command {
+        let mut command = Command::new("claude");
+        command.env("PATH", dir);
+        command
     }
 
-    let response: serde_json::Value = serde_json::from_str(&stdout)
-        .map_err(|_| eyre!("Failed to parse claude output as JSON"))?;
+    #[cfg(unix)]
+    #[test]
+    fn test_run_claude_reports_exit_status_and_stderr() {
+        let dir = fake_command("exit-1", "claude", "echo 'Please run /login' >&2\nexit 1");
+        let err = run_claude(claude_on_path(&dir)).unwrap_err();
+        assert_eq!(err.to_string(), "claude exited with 1: Please run /login");
+        std::fs::remove_dir_all(dir).ok();
+    }
 
-    // Extract the result text from claude's JSON output
-    let text = response["result"]
-        .as_str()
-        .ok_or_else(|| eyre!("No result in claude output"))?;
+    #[test]
+    fn test_run_claude_missing_binary() {
+        let err = run_claude(Command::new("workbench-no-such-claude")).unwrap_err();
+        assert_eq!(err.to_string(), MISSING_MESSAGE);
+    }
 
-    // Parse JSON array from the result
-    let values: Vec<String> = serde_json::from_str(text.trim())
-        .map_err(|_| eyre!("Failed to parse AI response as JSON array"))?;
+    #[cfg(unix)]
+    #[test]
+    fn test_run_claude_success() {
+        let dir = fake_command("ok", "claude", r#"echo '{"result": "[\"ABC-123\", \"\"]"}'"#);
+        let stdout = run_claude(claude_on_path(&dir)).unwrap();
+        assert_eq!(parse_response(&stdout).unwrap(), vec!["ABC-123".to_string(), String::new()]);
+        std::fs::remove_dir_all(dir).ok();
+    }
 
-    // Ensure we have the right number of values
-    let mut result = values;
-    result.resize(fields.len(), String::new());
+    #[test]
+    fn test_parse_usage() {
+        let stdout = r#"{"result": "[]", "total_cost_usd": 0.0021,
+            "usage": {"input_tokens": 900, "output_tokens": 40, "cache_read_input_tokens": 60},
+            "modelUsage": {"claude-haiku-4-5": {}}}"#;
+        assert_eq!(
+            parse_usage(stdout, "haiku"),
+            Usage { model: Some("claude-haiku-4-5".to_string()), tokens: Some(1000), cost_usd: Some(0.0021) }
+        );
+        // An older CLI without usage fields still counts the call
+        assert_eq!(
+            parse_usage(r#"{"result": "[]"}"#, "haiku"),
+            Usage { model: Some("haiku".to_string()), tokens: None, cost_usd: None }
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_trims_context() {
+        let fields = vec![("Ticket".to_string(), "Ticket ID".to_string())];
+        let base = build_prompt("ABC-123", &fields, None, 1000, &CardContext::default()).chars().count();
+        let huge = "line of output\n".repeat(1000);
+        let prompt = build_prompt("ABC-123", &fields, Some(&huge), 1000, &CardContext::default());
+        assert!(prompt.chars().count() <= base + "\n\nTerminal content:\n".len() + 1000);
+        assert!(prompt.contains("Terminal content:\nline of output\n"));
+
+        let prompt = build_prompt("ABC-123", &fields, None, 1000, &CardContext::default());
+        assert!(!prompt.contains("Terminal content:"));
+    }
+
+    #[test]
+    fn test_build_prompt_indents_multiline_descriptions() {
+        let fields = vec![
+            ("Ticket".to_string(), "Ticket ID".to_string()),
+            ("Summary".to_string(), "One sentence.\nNo markdown.".to_string()),
+            ("PR URL".to_string(), String::new()),
+        ];
+        let prompt = build_prompt("ABC-123", &fields, None, 1000, &CardContext::default());
+        assert!(prompt.contains("1. Ticket (Ticket ID)\n2. Summary:\n   One sentence.\n   No markdown.\n3. PR URL\n"));
+    }
+
+    #[test]
+    fn test_build_prompt_without_card() {
+        let fields = vec![("PR URL".to_string(), "Link to the pull request".to_string())];
+        let prompt = build_prompt("Fix login", &fields, None, 1000, &CardContext::default());
+        assert_eq!(prompt, r#"You are a simple JSON generator. Do NOT use any tools, search, or external lookups.
+
+The user has provided this input:
+---
+Fix login
+---
+
+Match this input to the following fields. The input might be:
+- A value that belongs in one of the fields (put it there)
+- Contains multiple pieces of info (extract and place in appropriate fields)
+- A ticket ID like "ABC-123" (put in ticket/ID fields)
+- A description or details (put in description fields)
+
+Fields to fill:
+1. PR URL (Link to the pull request)
 
-    Ok(result)
+Respond with ONLY a JSON array of strings, one value per field in order. Use empty string "" for fields that don't match. Example: ["value1", "", "value3"]"#);
+    }
+
+    #[test]
+    fn test_build_prompt_with_card() {
+        let fields = vec![
+            ("PR URL".to_string(), "In {status}, this should be the PR URL".to_string()),
+            ("Notes".to_string(), "Anything else about {project}; keep {braces}".to_string()),
+        ];
+        let card = CardContext { project: Some("api".to_string()), status: Some("Review".to_string()) };
+        let prompt = build_prompt("Fix login", &fields, None, 1000, &card);
+        assert_eq!(prompt, r#"You are a simple JSON generator. Do NOT use any tools, search, or external lookups.
+
+The user has provided this input:
+---
+Fix login
+---
+
+The card is in the "Review" column of project "api".
+
+Match this input to the following fields. The input might be:
+- A value that belongs in one of the fields (put it there)
+- Contains multiple pieces of info (extract and place in appropriate fields)
+- A ticket ID like "ABC-123" (put in ticket/ID fields)
+- A description or details (put in description fields)
+
+Fields to fill:
+1. PR URL (In Review, this should be the PR URL)
+2. Notes (Anything else about api; keep {braces})
+
+Respond with ONLY a JSON array of strings, one value per field in order. Use empty string "" for fields that don't match. Example: ["value1", "", "value3"]"#);
+
+        // Without a column, the hint keeps its placeholder rather than reading oddly
+        let card = CardContext { project: Some("api".to_string()), status: None };
+        assert!(build_prompt("Fix login", &fields, None, 1000, &card).contains("1. PR URL (In {status}, this should be the PR URL)"));
+    }
 }
@@ -1,26 +1,83 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, LazyLock};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime};
 
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use regex::{Captures, Regex};
 
-use crate::db::{Comment, Database, Field, Project, Session, Status};
-use crate::git::{self, DirtyStatus};
-use crate::tmux;
+use crate::agent::{self, Agent};
+use crate::db::{
+    self, ColumnSort, Comment, Database, Field, Project, PromptTemplate, Session, Shortcut, Status, StatusOverride, Transcript,
+    TrashedSession, ValueSource, WatchAction, WatchRule,
+};
+use crate::db_handle::{DbHandle, Queries};
+use crate::desktop;
+use crate::due;
+use crate::blockers;
+use crate::card::{self, CardLine};
+use crate::changes::{self, Change};
+use crate::context;
+use crate::git::{self, DirtyStatus, GitClient};
+use crate::jira;
+use crate::notes;
+use crate::redact;
+use crate::repo_config;
+use crate::startup;
+use crate::status::{self, Snapshot};
+use crate::tmux::{self, TmuxClient};
+use crate::transcript;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum View {
     #[default]
     Kanban,
     Settings,
+    /// Flat list of sessions needing attention, most urgent first
+    Queue,
+    /// Every project with its waiting sessions and running terminals
+    Overview,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SettingsTab {
+    #[default]
+    Fields,
+    CardLayout,
+    Statuses,
+    Project,
+    Shortcuts,
+    Watch,
+    Prompts,
+    Trash,
+}
+
+impl SettingsTab {
+    pub fn next(self) -> Self {
+        match self {
+            SettingsTab::Fields => SettingsTab::CardLayout,
+            SettingsTab::CardLayout => SettingsTab::Statuses,
+            SettingsTab::Statuses => SettingsTab::Project,
+            SettingsTab::Project => SettingsTab::Shortcuts,
+            SettingsTab::Shortcuts => SettingsTab::Watch,
+            SettingsTab::Watch => SettingsTab::Prompts,
+            SettingsTab::Prompts => SettingsTab::Trash,
+            SettingsTab::Trash => SettingsTab::Fields,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     Normal,
     NewSession,
+    /// Asking a card just created in a column for that column's fields, one at a time
+    NewCardField,
     EditSession,
     MoveSession,
     ConfirmDelete,
@@ -32,6 +89,95 @@ pub enum InputMode {
     EditFieldDesc,
     ViewComments,
     NewComment,
+    ConfirmSeedFields,
+    EditStatusLabel,
+    WorktreeMaintenance,
+    EditProjectSetting,
+    ConfirmPurgeSession,
+    /// A card is picked up and moves with h/l until dropped
+    Grab,
+    Snooze,
+    /// Setting the selected card's due date (`C`)
+    DueDate,
+    /// Typing many new cards at once, one per line (`I`)
+    PlanSessions,
+    /// Offer to relink a project whose repo moved to the current directory
+    ConfirmRelocate,
+    /// Offer to apply the repo's new or changed `.workbench.toml`
+    RepoConfig,
+    /// Number hints shown on cards; a digit attaches to that card
+    Hints,
+    CardActions,
+    /// Enter on a session whose agent has exited: respawn or clean up
+    DeadPane,
+    /// Enter on a stopped session whose worktree is gone: recreate it or start elsewhere
+    ConfirmRecreateWorktree,
+    /// Enter found a terminal with the card's name that isn't linked to it
+    ConfirmAdoptTerminal,
+    /// Restarting a card's terminal, fresh or resumed
+    ConfirmRestartTerminal,
+    /// Ticking off the entry checklist of the column a card is moving into
+    MoveChecklist,
+    /// Settings: editing a column's entry checklist as `item; item; ...`
+    EditStatusChecklist,
+    /// Settings: editing the fields a card created in a column is asked for, `name; name`
+    EditStatusNewCardFields,
+    /// `q` pressed while sessions are waiting for input
+    ConfirmQuit,
+    /// Attaching from inside tmux to a session another client already shows
+    ConfirmSharedAttach,
+    /// Choosing which fields a batch AI fill should fill
+    BatchFillFields,
+    /// Batch AI fill progress
+    BatchFill,
+    /// Settings: defining a shortcut as `name | column | field`
+    NewShortcut,
+    /// Numbered shortcut list behind `N`
+    PickShortcut,
+    /// Settings: defining a watch rule as `regex | action`
+    NewWatchRule,
+    /// Settings: picking a project to copy field definitions from
+    CopyFieldsFrom,
+    /// Prompting for the picked shortcut's field value
+    ShortcutValue,
+    /// Settings: choosing a line to add to the card layout
+    PickCardLine,
+    /// Environment variables of a session
+    SessionEnv,
+    /// Typing `KEY=VALUE` in the environment popup
+    NewEnvVar,
+    /// Offer to push changed variables into the running tmux session
+    ConfirmApplyEnv,
+    /// Settings: naming a prompt template (new or edited)
+    PromptTemplateName,
+    /// Settings: writing a prompt template's body
+    PromptTemplateBody,
+    /// Choosing a template to insert into the AI prompt
+    PickPromptTemplate,
+    /// Commits and diff stat of a session branch against the base
+    BranchChanges,
+    /// Landing a Review card whose PR merged, one step at a time
+    Land,
+    /// Settings: output of the startup command tried in a throwaway session
+    StartupTrial,
+    /// Naming a card the selected one waits on
+    BlockedBy,
+    /// `;`: choosing the field to set on the selected card
+    PickQuickField,
+    /// `;`: typing the new value of one field
+    QuickFieldValue,
+    /// What the last refresh from outside workbench changed on the board
+    BoardChanges,
+    /// Stored settings a reload found invalid (their previous values stay in use)
+    ReloadProblems,
+    /// Transcripts saved for the selected session
+    Transcripts,
+    /// Reading one transcript
+    TranscriptView,
+    /// Numbered list of the links in the peeked pane (`o` while peeking)
+    PickUrl,
+    /// The selected session's scratchpad (`p`)
+    Notes,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -41,17 +187,713 @@ pub enum EditMode {
     AI,
 }
 
+/// How much tmux pane content to send along with an AI fill
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AiContext {
+    None,
+    #[default]
+    VisiblePane,
+    History,
+}
+
+impl AiContext {
+    pub fn next(self) -> Self {
+        match self {
+            AiContext::None => AiContext::VisiblePane,
+            AiContext::VisiblePane => AiContext::History,
+            AiContext::History => AiContext::None,
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        match self {
+            AiContext::None => AiContext::History,
+            AiContext::VisiblePane => AiContext::None,
+            AiContext::History => AiContext::VisiblePane,
+        }
+    }
+}
+
+/// Colors a status can be given in Settings, cycled with `c` ("" = default)
+pub const STATUS_COLORS: &[&str] = &["", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// Project setting: id of the card selected when the board last switched away
+const SETTING_LAST_SELECTED: &str = "last_selected_session";
+
+/// Project setting recording that the starter-fields prompt was answered
+const SETTING_SEED_FIELDS_PROMPTED: &str = "seed_fields_prompted";
+
+/// Project setting: fingerprint of the `.workbench.toml` last offered, so an unchanged
+/// file isn't offered again
+const SETTING_REPO_CONFIG_HASH: &str = "repo_config_hash";
+
+/// Project setting: prefix for new session branches (defaults to `wb/`)
+const SETTING_BRANCH_PREFIX: &str = "branch_prefix";
+
+/// Project setting: longest name part of a new branch, after the prefix (empty = default)
+const SETTING_BRANCH_MAX_LEN: &str = "branch_max_len";
+
+/// Project setting: `on` to start a session's terminal when it moves into In Progress
+const SETTING_AUTOSTART: &str = "autostart";
+
+/// Project setting: command run in each new session terminal, with placeholders such
+/// as `{branch}` filled in (empty = just a shell)
+const SETTING_STARTUP_COMMAND: &str = "startup_command";
+
+/// How long a trial run of the startup command may take before its output is shown
+const STARTUP_TRIAL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Project setting: `on` to leave blocked cards out of the `w` jump
+const SETTING_SKIP_BLOCKED: &str = "skip_blocked";
+
+/// Project setting: days after which a column's oldest card turns its header age red
+/// (empty = default, 0 = never)
+const SETTING_STALE_DAYS: &str = "stale_days";
+
+/// Age at which a column's oldest card is flagged when the project doesn't say
+pub const DEFAULT_STALE_DAYS: u64 = 14;
+
+/// Project setting: days after entering Done that a card leaves the board for the
+/// archive (empty = default, 0 = never)
+const SETTING_ARCHIVE_DAYS: &str = "archive_done_days";
+
+/// Done cards older than this are archived when the project doesn't say
+pub const DEFAULT_ARCHIVE_DAYS: u64 = 30;
+
+/// Archived Done cards loaded at a time when browsing them
+const ARCHIVE_PAGE: usize = 100;
+
+/// Project setting: `on` to save a session's scrollback when its tmux session is killed
+const SETTING_TRANSCRIPTS: &str = "transcripts";
+
+/// Project setting: size cap for a saved transcript in KiB (empty = default)
+const SETTING_TRANSCRIPT_MAX_KB: &str = "transcript_max_kb";
+
+/// Project setting: `on` to only log watch rule matches instead of acting on them
+const SETTING_WATCH_DRY_RUN: &str = "watch_dry_run";
+
+/// Project setting: minutes before a watch rule may fire again for the same session
+/// (empty = default)
+const SETTING_WATCH_COOLDOWN: &str = "watch_cooldown_mins";
+
+/// Watch rule cooldown when the project doesn't set one, in minutes
+pub const DEFAULT_WATCH_COOLDOWN_MINS: u64 = 10;
+
+/// Watch rule actions kept in the Settings > Watch log
+const WATCH_LOG_LEN: usize = 20;
+
+/// Project setting: start point for new session branches (empty = auto-detect)
+const SETTING_BASE_BRANCH: &str = "base_branch";
+
+/// Project setting: `origin` URL last seen for the project, used to recognize a moved repo
+const SETTING_REMOTE_URL: &str = "remote_url";
+
+/// Project setting: extra space-separated regexes redacted from AI pane context
+const SETTING_REDACT_PATTERNS: &str = "redact_patterns";
+
+/// Project setting: characters of pane content sent with an AI fill (empty = default)
+const SETTING_AI_CONTEXT_CHARS: &str = "ai_context_chars";
+
+/// Project setting: where a session's notes file goes, `{worktree}` and `{id}` filled in
+/// (empty = `{worktree}/.workbench-notes.md`)
+const SETTING_NOTES_PATH: &str = "notes_path";
+
+/// Project setting: `off` to stop recording how long each session's terminal is in use
+const SETTING_TRACK_TIME: &str = "track_time";
+
+/// A terminal shown by some client counts as in use until it has been quiet this long
+const IDLE_SECS: i64 = 5 * 60;
+
+/// Polls further apart than this mean the board was stopped, so the gap isn't counted
+const MAX_SAMPLE_GAP_SECS: i64 = 30;
+
+/// Project setting: Jira base URL, e.g. `https://acme.atlassian.net` (empty = Jira off)
+const SETTING_JIRA_URL: &str = "jira_base_url";
+
+/// Project setting: env var holding the Jira token, as `VAR` or `email:VAR` for Jira Cloud
+const SETTING_JIRA_AUTH: &str = "jira_auth";
+
+/// Project setting: comma-separated field names for the ticket key, summary and URL
+const SETTING_JIRA_FIELDS: &str = "jira_fields";
+
+/// Editable rows in the Project settings tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProjectSetting {
+    BranchPrefix,
+    BranchMaxLen,
+    BaseBranch,
+    StartupCommand,
+    Autostart,
+    SkipBlocked,
+    StaleDays,
+    ArchiveDays,
+    Transcripts,
+    TranscriptMaxKb,
+    WatchDryRun,
+    WatchCooldown,
+    RedactPatterns,
+    AiContextChars,
+    NotesPath,
+    TrackTime,
+    JiraUrl,
+    JiraAuth,
+    JiraFields,
+}
+
+impl ProjectSetting {
+    pub fn all() -> &'static [ProjectSetting] {
+        &[
+            ProjectSetting::BranchPrefix,
+            ProjectSetting::BranchMaxLen,
+            ProjectSetting::BaseBranch,
+            ProjectSetting::StartupCommand,
+            ProjectSetting::Autostart,
+            ProjectSetting::SkipBlocked,
+            ProjectSetting::StaleDays,
+            ProjectSetting::ArchiveDays,
+            ProjectSetting::Transcripts,
+            ProjectSetting::TranscriptMaxKb,
+            ProjectSetting::WatchDryRun,
+            ProjectSetting::WatchCooldown,
+            ProjectSetting::RedactPatterns,
+            ProjectSetting::AiContextChars,
+            ProjectSetting::NotesPath,
+            ProjectSetting::TrackTime,
+            ProjectSetting::JiraUrl,
+            ProjectSetting::JiraAuth,
+            ProjectSetting::JiraFields,
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProjectSetting::BranchPrefix => "Branch prefix",
+            ProjectSetting::BranchMaxLen => "Branch name length",
+            ProjectSetting::BaseBranch => "Base branch",
+            ProjectSetting::StartupCommand => "Startup command",
+            ProjectSetting::Autostart => "Auto-start in In Progress",
+            ProjectSetting::SkipBlocked => "Skip blocked cards in w",
+            ProjectSetting::StaleDays => "Stale after (days)",
+            ProjectSetting::ArchiveDays => "Archive Done after (days)",
+            ProjectSetting::Transcripts => "Save transcripts",
+            ProjectSetting::TranscriptMaxKb => "Transcript size cap (KiB)",
+            ProjectSetting::WatchDryRun => "Watch rules dry run",
+            ProjectSetting::WatchCooldown => "Watch cooldown (minutes)",
+            ProjectSetting::RedactPatterns => "Redact patterns",
+            ProjectSetting::AiContextChars => "AI context (characters)",
+            ProjectSetting::NotesPath => "Notes file",
+            ProjectSetting::TrackTime => "Track time",
+            ProjectSetting::JiraUrl => "Jira URL",
+            ProjectSetting::JiraAuth => "Jira token",
+            ProjectSetting::JiraFields => "Jira fields",
+        }
+    }
+
+    /// Whether a repo's `.workbench.toml` may set it. Not the ones that run commands,
+    /// send credentials somewhere, write files or decide what is redacted: a cloned repo
+    /// isn't trusted with those
+    pub fn repo_may_set(&self) -> bool {
+        !matches!(
+            self,
+            ProjectSetting::StartupCommand
+                | ProjectSetting::RedactPatterns
+                | ProjectSetting::NotesPath
+                | ProjectSetting::JiraUrl
+                | ProjectSetting::JiraAuth
+                | ProjectSetting::JiraFields
+        )
+    }
+
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            ProjectSetting::BranchPrefix => SETTING_BRANCH_PREFIX,
+            ProjectSetting::BranchMaxLen => SETTING_BRANCH_MAX_LEN,
+            ProjectSetting::BaseBranch => SETTING_BASE_BRANCH,
+            ProjectSetting::StartupCommand => SETTING_STARTUP_COMMAND,
+            ProjectSetting::Autostart => SETTING_AUTOSTART,
+            ProjectSetting::SkipBlocked => SETTING_SKIP_BLOCKED,
+            ProjectSetting::StaleDays => SETTING_STALE_DAYS,
+            ProjectSetting::ArchiveDays => SETTING_ARCHIVE_DAYS,
+            ProjectSetting::Transcripts => SETTING_TRANSCRIPTS,
+            ProjectSetting::TranscriptMaxKb => SETTING_TRANSCRIPT_MAX_KB,
+            ProjectSetting::WatchDryRun => SETTING_WATCH_DRY_RUN,
+            ProjectSetting::WatchCooldown => SETTING_WATCH_COOLDOWN,
+            ProjectSetting::RedactPatterns => SETTING_REDACT_PATTERNS,
+            ProjectSetting::AiContextChars => SETTING_AI_CONTEXT_CHARS,
+            ProjectSetting::NotesPath => SETTING_NOTES_PATH,
+            ProjectSetting::TrackTime => SETTING_TRACK_TIME,
+            ProjectSetting::JiraUrl => SETTING_JIRA_URL,
+            ProjectSetting::JiraAuth => SETTING_JIRA_AUTH,
+            ProjectSetting::JiraFields => SETTING_JIRA_FIELDS,
+        }
+    }
+}
+
+/// A card created in a column that asks for fields: the fields still to fill, in order
+pub struct NewCardPrompt {
+    pub session_id: i64,
+    pub session_name: String,
+    pub status: Status,
+    pub field_ids: Vec<i64>,
+    /// The field being asked for
+    pub index: usize,
+}
+
+/// The prompt for a repo's `.workbench.toml`, shown on opening the project
+pub struct RepoConfigPrompt {
+    /// Fingerprint of the file, stored once the prompt is answered
+    pub hash: String,
+    pub changes: Vec<repo_config::Change>,
+    /// Whether each change is applied: plain ones start ticked, conflicts unticked
+    pub ticked: Vec<bool>,
+    pub selected: usize,
+    /// Entries of the file that can't be applied, listed and left out
+    pub problems: Vec<String>,
+}
+
+/// Every project setting a `.workbench.toml` may carry, "" when unset
+pub fn repo_config_settings(db: &Database, project_id: i64) -> Result<BTreeMap<String, String>> {
+    ProjectSetting::all()
+        .iter()
+        .filter(|setting| setting.repo_may_set())
+        .map(|setting| Ok((setting.key().to_string(), db.get_project_setting(project_id, setting.key())?.unwrap_or_default())))
+        .collect()
+}
+
+/// The `D` overlay: a session branch compared with the project's base branch
+pub struct BranchChangesView {
+    pub session_name: String,
+    pub branch: String,
+    pub base: Option<String>,
+    /// None while git runs in the background
+    pub result: Option<Result<git::BranchChanges, String>>,
+    pub scroll: u16,
+}
+
+impl BranchChangesView {
+    /// Lines in the overlay body, for clamping the scroll
+    pub fn line_count(&self) -> usize {
+        match self.result {
+            Some(Ok(ref changes)) if !changes.log.is_empty() => changes.log.len() + changes.diff_stat.len() + 3,
+            Some(Err(ref e)) => e.lines().count(),
+            _ => 1,
+        }
+    }
+}
+
+/// The `p` overlay: a session's scratchpad, kept in its notes file when it has a worktree
+pub struct NotesEditor {
+    pub session_id: i64,
+    pub session_name: String,
+    /// None when the session has no worktree; the notes then only live in the database
+    pub path: Option<PathBuf>,
+    pub text: String,
+    /// The text as last loaded or saved, to tell whether there are edits
+    original: String,
+    /// Modification time of the notes file the editor last read or wrote
+    synced_mtime: Option<i64>,
+    /// The file changed on disk while the editor had edits; Ctrl+S overwrites it
+    pub conflict: bool,
+    /// Esc asked to throw the edits away
+    pub confirm_discard: bool,
+}
+
+impl NotesEditor {
+    pub fn edited(&self) -> bool {
+        self.text != self.original
+    }
+}
+
+/// One step of landing a Review card (`L`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LandStep {
+    /// Nothing destructive happens unless the worktree is clean and its branch pushed
+    Check,
+    PullBase,
+    StopTerminal,
+    RemoveWorktree,
+    DeleteBranch,
+    MoveToDone,
+}
+
+impl LandStep {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LandStep::Check => "Check the worktree is clean and pushed",
+            LandStep::PullBase => "Pull the base branch",
+            LandStep::StopTerminal => "Stop the terminal",
+            LandStep::RemoveWorktree => "Remove the worktree",
+            LandStep::DeleteBranch => "Delete the branch",
+            LandStep::MoveToDone => "Move the card to Done",
+        }
+    }
+}
+
+/// Where a landing step stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepOutcome {
+    Pending,
+    Done(String),
+    Failed(String),
+    Skipped,
+}
+
+/// The `L` popup: the steps that take a Review card whose PR merged to Done
+pub struct LandView {
+    pub session_id: i64,
+    pub session_name: String,
+    /// Each step with the command it runs, as shown
+    pub steps: Vec<(LandStep, String, StepOutcome)>,
+    /// The step to run next
+    pub current: usize,
+    /// The branch tip, read by the check, to find the merge commit once the branch is gone
+    tip: Option<String>,
+}
+
+impl LandView {
+    pub fn finished(&self) -> bool {
+        self.current >= self.steps.len()
+    }
+
+    pub fn failed(&self) -> bool {
+        matches!(self.steps.get(self.current), Some((_, _, StepOutcome::Failed(_))))
+    }
+
+    /// Whether anything ran yet
+    pub fn started(&self) -> bool {
+        self.steps.iter().any(|(_, _, outcome)| *outcome != StepOutcome::Pending)
+    }
+}
+
+/// A trial run of the startup command, shown over its edit popup
+pub struct StartupTrialView {
+    pub command: String,
+    /// None while the command runs in the background
+    pub result: Option<Result<tmux::TrialRun, String>>,
+    pub scroll: u16,
+}
+
+/// A transcript opened from the `T` list
+pub struct TranscriptView {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub scroll: u16,
+}
+
+/// Entries of the per-card action menu (`.`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardAction {
+    CopyWorktreePath,
+    CopyBranch,
+    RevealInFileManager,
+    CopyAttachCommand,
+    UseCheckedOutBranch,
+    CheckOutStoredBranch,
+    RestartTerminal,
+}
+
+impl CardAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CardAction::CopyWorktreePath => "Copy worktree path",
+            CardAction::CopyBranch => "Copy branch name",
+            CardAction::RevealInFileManager => "Reveal in file manager",
+            CardAction::CopyAttachCommand => "Copy tmux attach command",
+            CardAction::UseCheckedOutBranch => "Store the checked-out branch",
+            CardAction::CheckOutStoredBranch => "Check out the stored branch",
+            CardAction::RestartTerminal => "Restart the terminal",
+        }
+    }
+
+    /// Actions that apply to a session, given whether its tmux session is running and
+    /// what its worktree has checked out instead of the stored branch
+    pub fn available(session: &Session, tmux_active: bool, out_of_sync: Option<&git::Head>) -> Vec<CardAction> {
+        let mut actions = Vec::new();
+        if session.checkout_path.is_some() {
+            actions.push(CardAction::CopyWorktreePath);
+        }
+        if session.branch_name.is_some() {
+            actions.push(CardAction::CopyBranch);
+        }
+        if session.checkout_path.is_some() {
+            actions.push(CardAction::RevealInFileManager);
+        }
+        if tmux_active {
+            actions.push(CardAction::CopyAttachCommand);
+        }
+        // A detached HEAD is never stored, only left by checking the branch out again
+        if let Some(git::Head::Branch(_)) = out_of_sync {
+            actions.push(CardAction::UseCheckedOutBranch);
+        }
+        if out_of_sync.is_some() && session.branch_name.is_some() {
+            actions.push(CardAction::CheckOutStoredBranch);
+        }
+        if tmux_active {
+            actions.push(CardAction::RestartTerminal);
+        }
+        actions
+    }
+}
+
+/// Starter fields offered to projects that have none: (name, description)
+const STARTER_FIELDS: &[(&str, &str)] = &[
+    ("Ticket", "Ticket identifier like ABC-123"),
+    ("Summary", "One-line summary of the work"),
+    ("PR URL", "Pull request URL"),
+];
+
+const DEFAULT_AI_HISTORY_LINES: usize = 200;
+const AI_HISTORY_LINES_STEP: usize = 50;
+const MAX_AI_HISTORY_LINES: usize = 5000;
+
+/// A board move held back until the target column's entry checklist is done
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecklistMove {
+    pub session_id: i64,
+    pub from: Status,
+    pub to: Status,
+    /// A grabbed card already sits in `to`; cancelling puts it back in `from`
+    pub carried: bool,
+}
+
+/// A session worktree listed in the maintenance popup
+#[derive(Debug, Clone)]
+pub struct WorktreeEntry {
+    pub session_id: i64,
+    pub session_name: String,
+    pub path: PathBuf,
+    pub size: Option<u64>,
+    /// Session is Done and its branch is merged, so the worktree is safe to prune
+    pub candidate: bool,
+    pub selected: bool,
+}
+
+/// Result of sizing one worktree on the background thread
+struct WorktreeScan {
+    session_id: i64,
+    size: Option<u64>,
+    merged: bool,
+}
+
+/// HEADs read on the background thread, by session; `None` where git couldn't tell
+type WorktreeHeads = Vec<(i64, Option<git::Head>)>;
+
+/// Progress of one session in a batch AI fill
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchFillState {
+    Pending,
+    Running,
+    /// Number of empty fields that got a value
+    Updated(usize),
+    Failed(String),
+}
+
+/// A session queued for batch AI fill
+#[derive(Debug, Clone)]
+pub struct BatchFillEntry {
+    pub session_id: i64,
+    pub session_name: String,
+    pub state: BatchFillState,
+}
+
+/// Message from the batch fill thread
+enum BatchFillEvent {
+    Started(i64),
+    Finished(i64, Result<crate::ai::Fill, String>),
+}
+
+/// Jira fetch progress shown in the edit popup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JiraFetch {
+    Running(String),
+    Done(String),
+    Failed(String),
+}
+
+/// Edit-popup field indices filled from Jira: ticket key, summary, URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JiraFieldMap {
+    pub key: usize,
+    pub summary: Option<usize>,
+    pub url: Option<usize>,
+}
+
+/// Resolve the Jira fields setting (`Ticket, Summary, Ticket URL`) against the project's
+/// fields. Empty means not configured
+pub fn resolve_jira_fields(fields: &[Field], spec: &str) -> Result<Option<JiraFieldMap>, String> {
+    if spec.trim().is_empty() {
+        return Ok(None);
+    }
+    let names: Vec<&str> = spec.split(',').map(str::trim).collect();
+    if names.len() > 3 {
+        return Err("Give at most three fields: key, summary, URL".to_string());
+    }
+    let index = |name: &str| -> Result<Option<usize>, String> {
+        if name.is_empty() {
+            return Ok(None);
+        }
+        fields
+            .iter()
+            .position(|f| f.name.eq_ignore_ascii_case(name))
+            .map(Some)
+            .ok_or_else(|| format!("Unknown field \"{}\"", name))
+    };
+    let key = index(names[0])?.ok_or("The first field (ticket key) is required")?;
+    Ok(Some(JiraFieldMap {
+        key,
+        summary: index(names.get(1).copied().unwrap_or(""))?,
+        url: index(names.get(2).copied().unwrap_or(""))?,
+    }))
+}
+
+/// Why follow mode picked the session it is peeking at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowReason {
+    RecentOutput,
+    StartedWaiting,
+}
+
+impl FollowReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            FollowReason::RecentOutput => "most recent output",
+            FollowReason::StartedWaiting => "started waiting",
+        }
+    }
+}
+
+/// When a tmux session started waiting for input, tolerant of brief flaps
+#[derive(Debug, Clone, Copy)]
+pub struct WaitingClock {
+    pub since: Instant,
+    /// Consecutive refreshes in which the session was not seen waiting
+    missed: u8,
+}
+
+/// Refreshes a session may be seen not-waiting before its clock resets
+const WAITING_GRACE_REFRESHES: u8 = 2;
+
+/// How often tmux state (active/waiting sessions) is re-read in the background of the loop
+const TMUX_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the board renews its hold on the project, so others see it is open
+const LOCK_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a reload can't change, named in its problems popup: the log and database are
+/// opened once, and the flags only come with a new command line
+pub const RESTART_ONLY: &[&str] = &[crate::logging::LOG_ENV, db::DB_ENV, "--db-path", "--serve", "--status-file", "--no-color"];
+
+/// Environment variable overriding the input poll interval, in milliseconds
+pub const POLL_ENV: &str = "WORKBENCH_POLL_MS";
+
+/// Input poll interval while the board is in use
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Without input or changes for this long, polling slows to `IDLE_POLL_INTERVAL`
+const IDLE_AFTER: Duration = Duration::from_secs(3);
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// ...and after this long, to `DEEP_IDLE_POLL_INTERVAL`
+const DEEP_IDLE_AFTER: Duration = Duration::from_secs(30);
+const DEEP_IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shortest time between two frames; changes in between are drawn together
+const MIN_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Frames counted for the rate in the frame stats overlay
+const FRAME_RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// How long the header highlights the project name after a switch
+pub const PROJECT_FLASH: Duration = Duration::from_secs(2);
+
+/// Trashed sessions older than this are purged on startup
+pub const TRASH_RETENTION_DAYS: u32 = 30;
+
+/// In Progress sessions untouched for this long show up in the review queue
+const STALE_IN_PROGRESS_SECS: i64 = 24 * 60 * 60;
+
+/// Project setting: the card layout, e.g. `branch,fields` (see `card::parse_layout`)
+const SETTING_CARD_LAYOUT: &str = "card_layout";
+
+/// Extras for card lines that need more than the session row
+#[derive(Debug, Clone, Default)]
+pub struct CardDetails {
+    pub dirty: Option<DirtyStatus>,
+    pub last_output: Option<String>,
+}
+
+/// Cards marked as recently attached
+const RECENT_MARKERS: usize = 3;
+
+/// Board state carried across a blocking tmux attach
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewState {
+    pub session_id: Option<i64>,
+    pub column: usize,
+    pub row: usize,
+    pub peek_active: bool,
+}
+
+/// Where the selection belongs after the board changed: on the same session if it
+/// is still shown, otherwise at the old position clamped to what's left
+pub fn locate_selection(columns: &[Vec<i64>], session_id: Option<i64>, column: usize, row: usize) -> (usize, usize) {
+    if let Some(id) = session_id {
+        for (c, ids) in columns.iter().enumerate() {
+            if let Some(r) = ids.iter().position(|s| *s == id) {
+                return (c, r);
+            }
+        }
+    }
+    let column = column.min(columns.len().saturating_sub(1));
+    let len = columns.get(column).map_or(0, Vec::len);
+    (column, row.min(len.saturating_sub(1)))
+}
+
+/// One row of the review queue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueItem {
+    pub session_id: i64,
+    /// Why the session is in the queue, e.g. "waiting 23m"
+    pub reason: String,
+}
+
+/// One project in the overview
 #[derive(Debug, Clone)]
+pub struct OverviewProject {
+    pub project: Project,
+    /// Terminals running; panes whose process exited don't count
+    pub active: usize,
+    /// Sessions waiting for input, by name
+    pub waiting: Vec<OverviewSession>,
+}
+
+/// A session waiting for input, as listed in the overview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverviewSession {
+    pub id: i64,
+    pub name: String,
+    pub tmux_name: String,
+}
+
+/// AI fill result and the number of secrets redacted from the pane context
+pub type AiFillResult = (Result<crate::ai::Fill, String>, usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AppAction {
     None,
     AttachTmux(String),
+    /// Show the session in a read-only window of the current tmux session
+    AttachReadOnly(String),
 }
 
 pub struct App {
     pub should_quit: bool,
-    pub db: Database,
+    pub db: DbHandle,
+    /// tmux and git as the board runs them; fakes in tests
+    pub tmux: Arc<dyn TmuxClient>,
+    pub git: Arc<dyn GitClient>,
     pub project: Project,
-    pub repo_root: Option<String>,
+    pub repo_root: Option<PathBuf>,
     pub sessions: Vec<Session>,
     pub selected_column: usize,
     pub selected_row: usize,
@@ -59,850 +901,1916 @@ pub struct App {
     pub input_buffer: String,
     pub active_tmux_sessions: HashSet<String>,
     pub sessions_waiting_input: HashSet<String>,
+    pub waiting_since: HashMap<String, WaitingClock>,
+    last_tmux_refresh: Instant,
+    /// When the last poll credited active time, in unix seconds
+    time_sampled_at: Option<i64>,
+    /// Active time recorded per session, as of the last poll
+    pub time_spent: HashMap<i64, i64>,
+    /// Field values of the board's sessions, keyed by (session, field), so drawing a
+    /// frame doesn't query the database per card
+    field_values: HashMap<(i64, i64), (String, ValueSource)>,
+    /// Input poll interval while active (`WORKBENCH_POLL_MS`)
+    poll_interval: Duration,
+    /// Last keypress or visible change; polling backs off the longer ago it was
+    last_activity: Instant,
+    /// Something shown changed since the last frame; the loop skips drawing otherwise
+    pub needs_redraw: bool,
+    /// When something shown changes by itself (e.g. a highlight fading), to redraw then
+    redraw_at: Option<Instant>,
+    last_frame: Option<Instant>,
+    /// Frames drawn since the board started
+    pub frames: u64,
+    recent_frames: VecDeque<Instant>,
+    /// The frame stats overlay (F12)
+    pub show_frame_stats: bool,
+    /// Window title format (`WORKBENCH_TITLE`), None when the title is left alone
+    title_format: Option<String>,
+    /// Title last sent to the terminal, so it's only sent again when it changes
+    shown_title: Option<String>,
     pub editing_session_id: Option<i64>,
     pub moving_session_id: Option<i64>,
+    pub grabbed_session_id: Option<i64>,
+    /// Column the grabbed card was picked up from; auto-start waits for the drop
+    grabbed_from: Option<Status>,
+    pub snoozing_session_id: Option<i64>,
+    /// Card whose due date is being set (`C`)
+    pub due_session_id: Option<i64>,
+    /// Today in local time, counted in days like due dates; updated on each tmux poll
+    pub today: i64,
+    /// Columns sorted by due date, soonest first (`S`)
+    pub sort_by_due: bool,
+    /// Board restricted to cards due by the end of the week, overdue ones included (`W`)
+    pub due_this_week: bool,
+    /// Card whose blockers are being named (`b`)
+    pub blocking_session_id: Option<i64>,
+    /// Card and field being set with `;`
+    pub quick_field: Option<(i64, i64)>,
+    pub selected_quick_field: usize,
+    /// Field last set with `;`, offered first next time
+    last_quick_field_id: Option<i64>,
+    /// Cards marked with `v`; `;` then sets the field on all of them
+    pub marked_sessions: HashSet<i64>,
+    /// `;` on marked cards leaves the ones that already have a value alone (Tab)
+    pub quick_field_only_empty: bool,
+    /// Blocked cards and any loop in their links, worked out on each refresh
+    pub block_state: blockers::BlockState,
+    /// Cards whose last blocker just finished, shown as unblocked until the next tmux poll
+    pub just_unblocked: HashSet<i64>,
+    unblocked_at: Option<Instant>,
+    /// Session whose missing worktree Enter offered to recreate
+    pub recreate_worktree_session_id: Option<i64>,
+    /// Session and the unlinked tmux session holding its name that Enter offered to adopt
+    pub adopt_terminal: Option<(i64, String)>,
+    /// Session whose terminal the card actions offered to restart
+    pub restart_session_id: Option<i64>,
+    /// Project that appears to have moved here, offered on startup
+    pub relocate_candidate: Option<Project>,
+    /// The repo's `.workbench.toml`, offered on opening the project
+    pub repo_config: Option<RepoConfigPrompt>,
+    /// The project shown before this one, for Ctrl+^ to switch back to
+    pub previous_project_id: Option<i64>,
+    /// When the board last switched projects; the header highlights the name briefly
+    pub project_flash: Option<Instant>,
+    /// Sessions labelled 1-9 while hints are shown, in reading order
+    pub hint_targets: Vec<i64>,
+    pub card_actions: Vec<CardAction>,
+    pub selected_card_action: usize,
+    /// Show snoozed cards on the board (with their wake time)
+    pub show_snoozed: bool,
+    /// Who "mine" means: git `user.name`, else `$USER`
+    pub current_owner: Option<String>,
+    /// Board restricted to cards owned by `current_owner` (`M`)
+    pub mine_only: bool,
+    /// Board restricted to pinned cards (`P`)
+    pub pinned_only: bool,
+    /// The selected column fills the board with full-detail cards (`=`)
+    pub zoomed: bool,
+    /// Whether the claude CLI was found; AI mode and batch fill are off without it
+    pub ai_available: bool,
     pub deleting_session_id: Option<i64>,
+    pub trashed_sessions: Vec<TrashedSession>,
+    pub selected_trash: usize,
+    pub purging_session_id: Option<i64>,
     pub deleting_dirty_status: Option<DirtyStatus>,
+    /// Session a new card is being spun off from (via `n` in the peek overlay)
+    pub spinoff_source_id: Option<i64>,
+    /// Column a card named in the New Session popup goes to, when it asks for fields
+    /// right after the name; None for Planned
+    pub new_session_status: Option<Status>,
+    /// The fields a card just created is being asked for, one popup each
+    pub new_card_prompt: Option<NewCardPrompt>,
     pub peek_active: bool,
+    /// Pane picked with `]`/`[` in the peek overlay: (session id, pane id). Only applies
+    /// while that session is peeked; otherwise the watched pane is shown
+    pub peek_pane: Option<(i64, String)>,
+    /// What the peek overlay shows, re-captured every PEEK_REFRESH_INTERVAL
+    pub peek_capture: Option<PeekCapture>,
+    /// Links offered by the `o` picker, fixed when it opens
+    pub url_choices: Vec<String>,
+    pub selected_url: usize,
+    /// Follow mode: the peek tracks the most recently active session instead of the selection
+    pub follow_active: bool,
+    pub follow_target: Option<(i64, FollowReason)>,
+    /// Activity and foreground command per tmux session, from the periodic refresh
+    pub pane_states: HashMap<String, tmux::PaneState>,
+    /// Why the last tmux poll failed (e.g. server not running); stored names are kept meanwhile
+    pub tmux_error: Option<String>,
+    /// Another board with the project open, from the advisory lock
+    pub lock_holder: Option<db::ProjectLock>,
+    /// Keys that change the board are refused while another board has it open
+    pub read_only: bool,
+    /// The board runs inside tmux, so attaching switches this client over
+    pub inside_tmux: bool,
+    /// Session waiting on the shared-attach popup
+    pub shared_attach: Option<String>,
+    lock_hostname: String,
+    last_lock_heartbeat: Instant,
     pub edit_row: usize,
     pub edit_session_name: String,
+    pub edit_owner: String,
+    /// Agent profile picked in the edit popup, by name
+    pub edit_agent: String,
     pub edit_field_values: Vec<String>,
+    /// Who wrote each of `edit_field_values`, saved along with them
+    pub edit_field_sources: Vec<ValueSource>,
+    /// Values the current field row already holds on other cards, offered after Tab;
+    /// empty when the dropdown is closed
+    pub edit_completions: Vec<String>,
+    pub selected_completion: usize,
     pub edit_mode: EditMode,
     pub ai_input: String,
     pub ai_running: bool,
     pub ai_error: Option<String>,
-    pub ai_result_rx: Option<Receiver<Result<Vec<String>, String>>>,
+    /// Last Jira fetch from the edit popup: in progress, done or failed
+    pub jira_fetch: Option<JiraFetch>,
+    jira_rx: Option<Receiver<Result<jira::JiraIssue, String>>>,
+    pub show_ai_error: bool,
+    pub ai_result_rx: Option<Receiver<AiFillResult>>,
+    pub ai_context: AiContext,
+    pub ai_history_lines: usize,
+    /// Pane context limit for fills from the edit popup until it closes; None for the
+    /// project's
+    pub ai_context_chars: Option<usize>,
     pub view: View,
+    pub overview: Vec<OverviewProject>,
+    /// Row selected in the overview: each project's row, then its waiting sessions
+    pub selected_overview: usize,
+    /// Archived projects are listed in the overview too (`A` there)
+    pub show_archived_projects: bool,
+    pub theme: crate::theme::Theme,
+    /// `--no-color` was passed; reloads keep the monochrome theme then
+    pub no_color: bool,
+    pub settings_tab: SettingsTab,
     pub fields: Vec<Field>,
+    pub status_overrides: Vec<StatusOverride>,
+    pub selected_status: usize,
+    /// Move waiting on the checklist popup, with the items' ticks
+    pub checklist_move: Option<ChecklistMove>,
+    pub checklist_checked: Vec<bool>,
+    pub selected_checklist_item: usize,
+    pub selected_project_setting: usize,
+    pub shortcuts: Vec<Shortcut>,
+    /// Most recently attached sessions, newest first (marked on their cards)
+    pub recent_session_ids: Vec<i64>,
+    pub selected_shortcut: usize,
+    /// Settings > Watch: rules matched against new pane output on each tmux poll
+    pub watch_rules: Vec<WatchRule>,
+    watch_regexes: HashMap<i64, Regex>,
+    pub selected_watch_rule: usize,
+    /// Recent watch rule actions (and dry-run matches), newest first
+    pub watch_log: Vec<db::Event>,
+    /// Last pane capture per session, to tell what it printed since the previous poll
+    watch_seen: HashMap<i64, Vec<String>>,
+    /// When each (rule, session) pair last fired, for the cooldown
+    watch_fired: HashMap<(i64, i64), Instant>,
+    /// What each card shows, line by line
+    pub card_layout: Vec<CardLine>,
+    pub selected_card_line: usize,
+    pub selected_card_line_choice: usize,
+    /// Per column (indexed like `Status::all()`), since when its oldest shown card has waited.
+    /// Cached on refresh so the headers don't rescan sessions every frame
+    pub column_oldest: Vec<Option<i64>>,
+    /// Header ages at or past this many days are flagged (0 = never)
+    pub stale_days: u64,
+    /// Done cards this many days past entering Done stay off the board (0 = never)
+    pub archive_days: u64,
+    /// Archived Done cards shown too (`O`), loaded a page at a time
+    pub show_archived: bool,
+    archived_loaded: usize,
+    /// Archived cards in the project, shown or not
+    pub archived_count: usize,
+    /// Per-session extras for the card layout, refreshed with tmux (only the kinds in use)
+    pub card_details: HashMap<i64, CardDetails>,
+    /// What each session's worktree has checked out, refreshed with tmux
+    pub worktree_heads: HashMap<i64, git::Head>,
+    /// When each worktree's HEAD last moved, so unmoved ones aren't read again
+    worktree_head_stamps: HashMap<i64, SystemTime>,
+    worktree_heads_rx: Option<Receiver<WorktreeHeads>>,
+    /// Shortcut whose field value is being prompted for
+    pub picked_shortcut: Option<usize>,
+    pub prompt_templates: Vec<PromptTemplate>,
+    pub branch_changes: Option<BranchChangesView>,
+    pub land: Option<LandView>,
+    branch_changes_rx: Option<Receiver<Result<git::BranchChanges, String>>>,
+    pub startup_trial: Option<StartupTrialView>,
+    pub notes_editor: Option<NotesEditor>,
+    /// Changes the last refresh found that workbench itself didn't make, for `R`
+    pub board_changes: Vec<Change>,
+    /// `PRAGMA data_version` at the last load; moves when another process writes
+    data_version: i64,
+    /// Set by SIGHUP or SIGUSR1; the settings are reloaded once the board is back in Normal mode
+    pub reload_signal: Arc<AtomicBool>,
+    /// The project settings in use, each checked as the Settings tab checks it; a key is
+    /// missing when the setting is unset
+    project_settings: HashMap<ProjectSetting, String>,
+    /// Why the last reload kept some previous settings
+    pub reload_problems: Vec<String>,
+    startup_trial_rx: Option<Receiver<Result<tmux::TrialRun, String>>>,
+    /// The `T` list: transcripts of one session with their file sizes (None = file missing)
+    pub transcripts: Vec<(Transcript, Option<u64>)>,
+    pub transcripts_session_name: String,
+    pub selected_transcript: usize,
+    pub transcript_view: Option<TranscriptView>,
+    pub selected_prompt_template: usize,
+    /// Template being edited; None while creating one
+    pub editing_prompt_template_id: Option<i64>,
+    pub detected_base_branch: Option<String>,
+    pub input_error: Option<String>,
     pub selected_field: usize,
+    /// Other projects offered by "copy fields from project", with their fields
+    pub copy_sources: Vec<(Project, Vec<Field>)>,
+    pub selected_copy_source: usize,
     pub editing_field_id: Option<i64>,
     pub deleting_field_id: Option<i64>,
     pub new_field_name: String,
     pub new_field_desc: String,
+    /// Field whose description is shown in full in the settings list
+    pub expanded_field_id: Option<i64>,
     pub status_message: Option<String>,
     pub comments: Vec<Comment>,
     pub comments_session_id: Option<i64>,
     pub new_comment_text: String,
     pub comments_scroll: usize,
+    /// Session whose environment popup is open, its variables and the keys changed since opening
+    pub env_session_id: Option<i64>,
+    pub session_env: Vec<(String, String)>,
+    pub selected_env: usize,
+    pub env_revealed: bool,
+    env_changed_keys: Vec<String>,
+    /// The variable the input popup edits, None when it adds one
+    editing_env_key: Option<String>,
+    pub write_status_file: bool,
+    pub duplicate_name_warning: bool,
+    /// Esc in the plan popup asked to throw its lines away
+    pub confirm_discard_plan: bool,
+    pub worktree_entries: Vec<WorktreeEntry>,
+    pub selected_worktree: usize,
+    pub confirm_worktree_prune: bool,
+    worktree_scan_rx: Option<Receiver<WorktreeScan>>,
+    worktree_scan_cancel: Option<Arc<AtomicBool>>,
+    /// Batch fill: which fields (parallel to `fields`) to fill, and the cursor in that list
+    pub batch_fill_selected: Vec<bool>,
+    pub selected_batch_field: usize,
+    pub batch_fill_entries: Vec<BatchFillEntry>,
+    batch_fill_field_ids: Vec<i64>,
+    batch_fill_rx: Option<Receiver<BatchFillEvent>>,
+    batch_fill_cancel: Option<Arc<AtomicBool>>,
 }
 
-impl App {
-    pub fn new() -> Result<Self> {
-        let db = Database::new()?;
-        let cwd = std::env::current_dir()?;
-        let cwd_str = cwd.to_string_lossy().to_string();
+/// The input poll interval: `WORKBENCH_POLL_MS` when it holds 10 to 1000, else 100ms
+fn poll_interval_from_env() -> Duration {
+    std::env::var(POLL_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|ms| (10..=1000).contains(ms))
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_POLL_INTERVAL)
+}
 
-        // Use git repo root for project identity (if in a git repo)
-        let repo_root = git::get_repo_root(&cwd_str);
-        let project_path = repo_root.as_ref().unwrap_or(&cwd_str).clone();
+/// How long to wait for input, given the active poll interval and how long the board
+/// has been idle. A keypress still ends the wait at once; this only bounds how late a
+/// background change is noticed
+fn idle_poll_interval(active: Duration, idle_for: Duration) -> Duration {
+    let interval = if idle_for >= DEEP_IDLE_AFTER {
+        DEEP_IDLE_POLL_INTERVAL
+    } else if idle_for >= IDLE_AFTER {
+        IDLE_POLL_INTERVAL
+    } else {
+        active
+    };
+    interval.max(active)
+}
 
-        let project_name = std::path::Path::new(&project_path)
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("unknown")
-            .to_string();
+/// Board keys that only look around, allowed in read-only mode: moving the selection,
+/// peeking and following, filters and views that change nothing, refreshing and quitting
+fn is_read_only_key(key: &KeyEvent, peeking: bool) -> bool {
+    match key.code {
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        KeyCode::Char('[' | ']') => peeking,
+        KeyCode::Char('q' | 'U' | 'h' | 'j' | 'k' | 'l' | ' ' | 'F' | 'w' | 'Z' | '=' | 'r' | 'R' | 'T') => true,
+        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => true,
+        _ => false,
+    }
+}
 
-        let project = db.get_or_create_project(&project_name, &project_path)?;
-        let sessions = db.list_sessions(project.id)?;
-        let fields = db.list_fields(project.id)?;
-        let active_tmux_sessions: HashSet<String> = tmux::list_workbench_sessions().into_iter().collect();
+/// Ctrl+^ as vim has it; terminals report it as Ctrl+6 or Ctrl+^
+fn is_switch_project_key(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && matches!(key.code, KeyCode::Char('^') | KeyCode::Char('6'))
+}
 
-        // Check which sessions are waiting for user input
-        let sessions_waiting_input: HashSet<String> = active_tmux_sessions
-            .iter()
-            .filter(|name| tmux::is_waiting_for_input(name))
-            .cloned()
-            .collect();
+/// Resolve the project for the current directory, creating it if needed
+/// Returns the project and the git repo root (if in a git repo)
+pub fn current_project(db: &Database) -> Result<(Project, Option<PathBuf>)> {
+    let cwd = std::env::current_dir()?;
 
-        Ok(Self {
-            should_quit: false,
-            db,
-            project,
-            repo_root,
-            sessions,
-            selected_column: 0,
-            selected_row: 0,
-            input_mode: InputMode::Normal,
-            input_buffer: String::new(),
-            active_tmux_sessions,
-            sessions_waiting_input,
-            editing_session_id: None,
-            moving_session_id: None,
-            deleting_session_id: None,
-            deleting_dirty_status: None,
-            peek_active: false,
-            edit_row: 0,
-            edit_session_name: String::new(),
-            edit_field_values: Vec::new(),
-            edit_mode: EditMode::default(),
-            ai_input: String::new(),
-            ai_running: false,
-            ai_error: None,
-            ai_result_rx: None,
-            view: View::default(),
-            fields,
-            selected_field: 0,
-            editing_field_id: None,
-            deleting_field_id: None,
-            new_field_name: String::new(),
-            new_field_desc: String::new(),
-            status_message: None,
-            comments: Vec::new(),
-            comments_session_id: None,
-            new_comment_text: String::new(),
-            comments_scroll: 0,
-        })
-    }
+    // Use git repo root for project identity (if in a git repo)
+    let repo_root = git::get_repo_root(&cwd);
+    let project_path = repo_root.clone().unwrap_or(cwd);
 
-    pub fn sessions_by_status(&self, status: Status) -> Vec<&Session> {
-        self.sessions
-            .iter()
-            .filter(|s| s.status == status)
-            .collect()
-    }
+    // The name is only for display; the path keeps its exact bytes
+    let project_name = project_path
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unknown".to_string());
 
-    pub fn selected_session(&self) -> Option<&Session> {
-        let status = Status::all().get(self.selected_column)?;
-        let sessions = self.sessions_by_status(*status);
-        sessions.get(self.selected_row).copied()
+    let project = db.get_or_create_project(&project_name, &project_path)?;
+    if let Some(remote) = repo_root.as_deref().and_then(git::get_remote_url) {
+        db.set_project_setting(project.id, SETTING_REMOTE_URL, &remote)?;
     }
+    Ok((project, repo_root))
+}
 
-    pub fn refresh_sessions(&mut self) -> Result<()> {
-        self.sessions = self.db.list_sessions(self.project.id)?;
-        self.refresh_tmux_sessions();
-        Ok(())
+/// For a brand-new, empty project: an existing project whose directory is gone and
+/// that looks like the same repo (same `origin` URL, or same name when either has none)
+pub fn relocation_candidate(db: &Database, project: &Project) -> Result<Option<Project>> {
+    if !db.project_is_empty(project.id)? {
+        return Ok(None);
     }
-
-    pub fn refresh_fields(&mut self) -> Result<()> {
-        self.fields = self.db.list_fields(self.project.id)?;
-        Ok(())
+    let remote = db.get_project_setting(project.id, SETTING_REMOTE_URL)?;
+    for other in db.list_projects()? {
+        if other.id == project.id || Path::new(&other.path).exists() {
+            continue;
+        }
+        let other_remote = db.get_project_setting(other.id, SETTING_REMOTE_URL)?;
+        let same_repo = match (&remote, &other_remote) {
+            (Some(a), Some(b)) => a == b,
+            _ => other.name == project.name,
+        };
+        if same_repo {
+            return Ok(Some(other));
+        }
     }
+    Ok(None)
+}
 
-    pub fn refresh_tmux_sessions(&mut self) {
-        self.active_tmux_sessions = tmux::list_workbench_sessions().into_iter().collect();
+/// Parse `KEY=VALUE` typed into the environment popup
+pub fn parse_env_assignment(input: &str) -> Result<(String, String), String> {
+    let (key, value) = input.split_once('=').ok_or("Use KEY=VALUE")?;
+    let key = key.trim();
+    let valid = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("\"{}\" isn't a valid variable name", key));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Name fragments that mark a variable as secret
+const SECRET_ENV_HINTS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL", "API_KEY", "PRIVATE"];
+
+/// Whether a variable's value is masked until revealed: secret-sounding names, or
+/// values the redactor would catch (e.g. a password in `DATABASE_URL`)
+pub fn is_secret_env(key: &str, value: &str) -> bool {
+    let key = key.to_uppercase();
+    SECRET_ENV_HINTS.iter().any(|hint| key.contains(hint)) || redact::redact(value, &[]).1 > 0
+}
+
+/// Check whether a session with this name already exists (case-insensitive)
+pub fn has_session_named(sessions: &[Session], name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    sessions.iter().any(|s| s.name.trim().to_lowercase() == name)
+}
+
+/// The session's tmux session, creating it (in the worktree, if the session has one)
+/// when it isn't running. Shared by `Enter` on the board and `workbench attach`
+pub fn ensure_tmux_session(db: &dyn Queries, tmux: &dyn TmuxClient, git: &dyn GitClient, project: &Project, session: &Session) -> Result<String> {
+    // Use existing tmux_window if available, otherwise generate new name
+    if let Some(ref tmux_name) = session.tmux_window && tmux.session_exists(tmux_name) {
+        return Ok(tmux_name.clone());
+    }
 
-        // Check which sessions are waiting for user input
-        self.sessions_waiting_input.clear();
-        for name in &self.active_tmux_sessions {
-            if tmux::is_waiting_for_input(name) {
-                self.sessions_waiting_input.insert(name.clone());
+    // Generate tmux session name, ensuring uniqueness
+    let base_name = tmux::session_name(project.id, session.id);
+    let tmux_name = if tmux.session_exists(&base_name) {
+        // Name collision - add timestamp suffix for uniqueness
+        let ts = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        format!("{}-{}", base_name, ts)
+    } else {
+        base_name
+    };
+
+    // Create a new tmux session
+    let (working_dir, startup) = terminal_start(db, git, project, session, false)?;
+    let startup = Some(startup.as_str()).filter(|c| !c.is_empty());
+    tmux.create_session(&tmux_name, &working_dir, &db.list_session_env(session.id)?, startup)?;
+    db.set_tmux_session(session.id, &tmux_name)?;
+    Ok(tmux_name)
+}
+
+/// Where a session's terminal starts and what it runs there: the agent's command (its
+/// resume command with `resume`, when it has one), else the project's startup command
+fn terminal_start(db: &dyn Queries, git: &dyn GitClient, project: &Project, session: &Session, resume: bool) -> Result<(PathBuf, String)> {
+    // Start in the worktree while it's still there, otherwise in the project directory
+    let working_dir = match usable_checkout(git, project, session) {
+        Some(path) => path,
+        None => {
+            if let Some(missing) = &session.checkout_path {
+                tracing::warn!(session_id = session.id, path = %missing.display(), "worktree is gone, starting in the project directory");
             }
+            &project.path
         }
+    };
 
-        // Clean up stale tmux references in the database
-        for session in &self.sessions {
-            if let Some(ref tmux_name) = session.tmux_window {
-                if !self.active_tmux_sessions.contains(tmux_name) {
-                    let _ = self.db.clear_tmux_session(session.id);
-                }
-            }
+    let values = command_values(project, session, working_dir);
+    let agent = agent::for_session(session);
+    let resumed = if resume { agent.resume_command(&values) } else { Ok(None) };
+    let command = resumed.and_then(|resumed| match resumed {
+        Some(command) => Ok(Some(command)),
+        None => agent.startup_command(&values),
+    });
+    let startup = match command.map_err(|e| eyre!("Agent {}: {}", agent.name, e))? {
+        Some(command) => command,
+        None => {
+            let template = db.get_project_setting(project.id, SETTING_STARTUP_COMMAND)?.unwrap_or_default();
+            startup::expand(template.trim(), &values).map_err(|e| eyre!("Startup command: {}", e))?
         }
+    };
+    Ok((working_dir.to_path_buf(), startup))
+}
+
+/// Kill a session's running terminal and start it again under the same name, directory,
+/// environment and command, so the card keeps its link throughout. If the new one can't
+/// start, the session forgets the name
+pub fn restart_tmux_session(
+    db: &dyn Queries,
+    tmux: &dyn TmuxClient,
+    git: &dyn GitClient,
+    project: &Project,
+    session: &Session,
+    resume: bool,
+) -> Result<String> {
+    let tmux_name = session.tmux_window.clone().ok_or_else(|| eyre!("{} has no terminal to restart", session.name))?;
+    // Worked out first, so a broken command leaves the old terminal running
+    let (working_dir, startup) = terminal_start(db, git, project, session, resume)?;
+    let env = db.list_session_env(session.id)?;
+    tmux.kill_session(&tmux_name);
+    let startup = Some(startup.as_str()).filter(|c| !c.is_empty());
+    if let Err(e) = tmux.create_session(&tmux_name, &working_dir, &env, startup) {
+        db.clear_tmux_session(session.id)?;
+        return Err(eyre!("Killed the terminal but couldn't start it again: {}", e));
     }
+    Ok(tmux_name)
+}
 
-    pub fn has_active_terminal(&self, session: &Session) -> bool {
-        session.tmux_window.as_ref()
-            .map(|name| self.active_tmux_sessions.contains(name))
-            .unwrap_or(false)
+/// A running tmux session that holds `session`'s terminal name without being linked to
+/// it, whose pane sits in the session's worktree or the project directory: most likely
+/// its own terminal after the link was lost, so Enter offers to adopt it
+fn adoptable_terminal(tmux: &dyn TmuxClient, project: &Project, session: &Session) -> Option<String> {
+    if session.tmux_window.as_deref().is_some_and(|name| tmux.session_exists(name)) {
+        return None;
+    }
+    let name = tmux::session_name(project.id, session.id);
+    if !tmux.session_exists(&name) {
+        return None;
+    }
+    let cwd = tmux.pane_cwd(&name)?;
+    let same_dir = |dir: &Path| cwd == dir || std::fs::canonicalize(dir).is_ok_and(|dir| cwd == dir);
+    let ours = session.checkout_path.as_deref().is_some_and(same_dir) || same_dir(&project.path);
+    ours.then_some(name)
+}
+
+/// Keep a notes file just created in a worktree out of git's view, so it doesn't make the
+/// worktree dirty or end up in the agent's commits
+fn exclude_notes_file(path: &Path) {
+    if let Err(e) = git::exclude_locally(path) {
+        tracing::warn!(path = %path.display(), error = %e, "could not exclude the notes file from git");
     }
+}
 
-    pub fn is_waiting_for_input(&self, session: &Session) -> bool {
-        session.tmux_window.as_ref()
-            .map(|name| self.sessions_waiting_input.contains(name))
-            .unwrap_or(false)
+/// Startup command placeholder values for a session whose terminal starts in `working_dir`
+fn command_values(project: &Project, session: &Session, working_dir: &Path) -> startup::CommandValues {
+    startup::CommandValues {
+        name: session.name.clone(),
+        branch: session.branch_name.clone().unwrap_or_default(),
+        path: working_dir.to_string_lossy().into_owned(),
+        project: project.name.clone(),
+        id: session.id.to_string(),
     }
+}
 
-    pub fn handle_events(&mut self) -> Result<AppAction> {
-        // Check for AI results from background thread
-        self.check_ai_result();
+/// The session's worktree, when it still exists on disk and git still lists it. After a
+/// reboot or a manual `git worktree remove` the stored path can point nowhere
+pub fn usable_checkout<'a>(git: &dyn GitClient, project: &Project, session: &'a Session) -> Option<&'a Path> {
+    session.checkout_path.as_deref().filter(|path| git.is_registered_worktree(&project.path, path))
+}
 
-        if event::poll(Duration::from_millis(100))? {
-            match event::read()? {
-                Event::Key(key) => {
-                    // Clear status message on any keypress
-                    self.status_message = None;
+/// Add a session's missing worktree back at its stored path and branch; the branch is
+/// made again from the base branch if it was deleted too
+pub fn recreate_worktree(db: &dyn Queries, git: &dyn GitClient, project_id: i64, repo_root: &Path, session: &Session) -> Result<()> {
+    let (Some(path), Some(branch)) = (&session.checkout_path, &session.branch_name) else {
+        return Err(eyre!("The session has no worktree to recreate"));
+    };
+    // A directory deleted by hand is still registered until pruned
+    git.prune_worktrees(repo_root)?;
+    let base_branch = base_branch(db, git, project_id, repo_root)?;
+    git.create_worktree(repo_root, branch, path, base_branch.as_deref())
+}
 
-                    // Ignore key events while AI is running
-                    if self.ai_running {
-                        return Ok(AppAction::None);
-                    }
-                    match self.input_mode {
-                        InputMode::Normal => {
-                            match self.view {
-                                View::Kanban => return self.handle_normal_key(key),
-                                View::Settings => self.handle_settings_key(key)?,
-                            }
-                        }
-                        InputMode::NewSession => self.handle_input_key(key)?,
-                        InputMode::EditSession => self.handle_edit_session_key(key)?,
-                        InputMode::MoveSession => self.handle_move_key(key)?,
-                        InputMode::ConfirmDelete => self.handle_confirm_delete_key(key)?,
-                        InputMode::ConfirmDeleteDirty => self.handle_confirm_delete_dirty_key(key)?,
-                        InputMode::ConfirmDeleteField => self.handle_confirm_delete_field_key(key)?,
-                        InputMode::NewFieldName => self.handle_new_field_name_key(key)?,
-                        InputMode::NewFieldDesc => self.handle_new_field_desc_key(key)?,
-                        InputMode::EditFieldName => self.handle_edit_field_name_key(key)?,
-                        InputMode::EditFieldDesc => self.handle_edit_field_desc_key(key)?,
-                        InputMode::ViewComments => self.handle_view_comments_key(key)?,
-                        InputMode::NewComment => self.handle_new_comment_key(key)?,
-                    }
-                }
-                Event::Paste(text) => {
-                    if !self.ai_running {
-                        self.handle_paste(&text);
-                    }
-                }
-                _ => {}
-            }
+/// Whether moving a session into In Progress starts its terminal (the Auto-start setting)
+pub fn autostart_enabled(db: &dyn Queries, project_id: i64) -> Result<bool> {
+    Ok(db.get_project_setting(project_id, SETTING_AUTOSTART)?.as_deref() == Some("on"))
+}
+
+/// Start a session without attaching: its worktree when it has none yet, then its tmux
+/// session. Nothing is undone on failure; the error names the step that failed
+pub fn autostart_session(
+    db: &dyn Queries,
+    tmux: &dyn TmuxClient,
+    git: &dyn GitClient,
+    project: &Project,
+    repo_root: Option<&Path>,
+    session: &Session,
+) -> Result<String> {
+    let mut session = session.clone();
+    if session.checkout_path.is_none() && repo_root.is_some() {
+        if let Some(e) = create_worktree_for(db, git, project.id, repo_root, &session)? {
+            return Err(eyre!("Auto-start failed creating the worktree: {}", e));
         }
-        Ok(AppAction::None)
+        session = db.get_live_session(project.id, session.id)?
+            .ok_or_else(|| eyre!("Auto-start failed: the session was removed"))?;
     }
+    if !tmux.is_available() {
+        return Err(eyre!("Auto-start failed starting tmux: tmux is not installed"));
+    }
+    ensure_tmux_session(db, tmux, git, project, &session).map_err(|e| eyre!("Auto-start failed starting tmux: {}", e))
+}
 
-    fn handle_normal_key(&mut self, key: KeyEvent) -> Result<AppAction> {
-        match key.code {
-            KeyCode::Char('q') => self.should_quit = true,
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true
-            }
-            KeyCode::Char('n') => {
-                self.input_mode = InputMode::NewSession;
-                self.input_buffer.clear();
-            }
-            KeyCode::Char('h') | KeyCode::Left => {
-                if self.selected_column > 0 {
-                    self.selected_column -= 1;
-                    self.clamp_row();
-                }
-            }
-            KeyCode::Char('l') | KeyCode::Right => {
-                if self.selected_column < Status::all().len() - 1 {
-                    self.selected_column += 1;
-                    self.clamp_row();
-                }
-            }
-            KeyCode::Char('j') | KeyCode::Down => {
-                let status = Status::all()[self.selected_column];
-                let count = self.sessions_by_status(status).len();
-                if self.selected_row < count.saturating_sub(1) {
-                    self.selected_row += 1;
-                }
-            }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if self.selected_row > 0 {
-                    self.selected_row -= 1;
-                }
-            }
-            KeyCode::Char('m') => {
-                if let Some(session) = self.selected_session() {
-                    self.moving_session_id = Some(session.id);
-                    self.input_mode = InputMode::MoveSession;
-                }
-            }
-            KeyCode::Char('d') => {
-                if let Some(session) = self.selected_session() {
-                    let session_id = session.id;
-                    let checkout_path = session.checkout_path.clone();
-                    self.deleting_session_id = Some(session_id);
-                    // Check if worktree is dirty
-                    if let Some(checkout_path) = checkout_path {
-                        if let Some(dirty_status) = git::get_dirty_status(&checkout_path) {
-                            if dirty_status.is_dirty() {
-                                self.deleting_dirty_status = Some(dirty_status);
-                                self.input_mode = InputMode::ConfirmDeleteDirty;
-                                return Ok(AppAction::None);
-                            }
-                        }
-                    }
-                    self.input_mode = InputMode::ConfirmDelete;
-                }
-            }
-            KeyCode::Char('r') => {
-                self.refresh_sessions()?;
-            }
-            KeyCode::Char('e') => {
-                if let Some(session) = self.selected_session() {
-                    let session_id = session.id;
-                    let session_name = session.name.clone();
-                    self.editing_session_id = Some(session_id);
-                    self.edit_session_name = session_name.clone();
-                    self.edit_row = 0;
-                    self.input_buffer = session_name;
-                    // Load field values
-                    let field_values: Vec<String> = self.fields.iter().map(|f| {
-                        self.db.get_session_field_value(session_id, f.id).unwrap_or_default()
-                    }).collect();
-                    self.edit_field_values = field_values;
-                    self.input_mode = InputMode::EditSession;
-                }
-            }
-            KeyCode::Enter => {
-                return self.handle_enter_key();
-            }
-            KeyCode::Char(' ') => {
-                if self.selected_session().and_then(|s| s.tmux_window.as_ref()).is_some() {
-                    self.peek_active = !self.peek_active;
-                }
-            }
-            KeyCode::Char('s') => {
-                self.view = View::Settings;
-                self.selected_field = 0;
-            }
-            KeyCode::Char('x') => {
-                self.cleanup_orphaned_tmux_sessions();
-            }
-            KeyCode::Char('c') => {
-                if let Some(session) = self.selected_session() {
-                    let session_id = session.id;
-                    self.comments_session_id = Some(session_id);
-                    self.comments = self.db.list_comments(session_id).unwrap_or_default();
-                    self.comments_scroll = 0;
-                    self.input_mode = InputMode::ViewComments;
-                }
-            }
-            _ => {}
+/// Result of looking a session up by id or name
+#[derive(Debug)]
+pub enum SessionMatch<'a> {
+    One(&'a Session),
+    Ambiguous(Vec<&'a Session>),
+    NotFound,
+}
+
+/// Lowercased words of a name, so `fix-auth` and `Fix auth` compare equal
+fn match_key(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `query`'s characters appear in order in `name` (ignoring spaces)
+fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut chars = name.chars();
+    query.chars().filter(|c| *c != ' ').all(|q| chars.any(|c| c == q))
+}
+
+/// Find a session for `workbench attach`: an id always wins, then an exact name, then
+/// names containing the query, then names containing its letters in order
+pub fn match_session<'a>(sessions: &'a [Session], query: &str) -> SessionMatch<'a> {
+    if let Ok(id) = query.trim().parse::<i64>() && let Some(session) = sessions.iter().find(|s| s.id == id) {
+        return SessionMatch::One(session);
+    }
+
+    let query = match_key(query);
+    if query.is_empty() {
+        return SessionMatch::NotFound;
+    }
+    let passes: [&dyn Fn(&str) -> bool; 3] = [
+        &|name| name == query,
+        &|name| name.contains(&query),
+        &|name| is_subsequence(&query, name),
+    ];
+    for matches in passes {
+        let found: Vec<&Session> = sessions.iter().filter(|s| matches(&match_key(&s.name))).collect();
+        match found.len() {
+            0 => continue,
+            1 => return SessionMatch::One(found[0]),
+            _ => return SessionMatch::Ambiguous(found),
         }
-        Ok(AppAction::None)
     }
+    SessionMatch::NotFound
+}
 
-    fn cleanup_orphaned_tmux_sessions(&mut self) {
-        // Get all tmux sessions for this project
-        let tmux_sessions = tmux::list_project_sessions(self.project.id);
+/// Edit popup rows: the name, the owner, the agent, then one per custom field
+pub const OWNER_ROW: usize = 1;
+pub const AGENT_ROW: usize = 2;
+pub const FIRST_FIELD_ROW: usize = 3;
 
-        // Get all tmux names that are tracked in the database
-        let tracked: std::collections::HashSet<String> = self.sessions
-            .iter()
-            .filter_map(|s| s.tmux_window.clone())
-            .collect();
+/// Owner given to new sessions: git `user.name`, falling back to `$USER`
+pub fn default_owner(git: &dyn GitClient, repo_root: Option<&Path>) -> Option<String> {
+    git.config_value(repo_root, "user.name")
+        .or_else(|| std::env::var("USER").ok().filter(|user| !user.trim().is_empty()))
+}
 
-        // Kill any tmux session that isn't tracked
-        let mut killed = 0;
-        for tmux_name in tmux_sessions {
-            if !tracked.contains(&tmux_name) {
-                if tmux::kill_session(&tmux_name) {
-                    killed += 1;
-                }
-            }
-        }
+/// Create a session in `status` and, when in a git repo, its branch and worktree
+/// Returns the session and the worktree error, if worktree creation failed
+pub fn create_session_with_worktree(
+    db: &dyn Queries,
+    git: &dyn GitClient,
+    project_id: i64,
+    repo_root: Option<&Path>,
+    name: &str,
+    status: Status,
+) -> Result<(Session, Option<String>)> {
+    let owner = default_owner(git, repo_root);
+    let session = db.create_session_with(project_id, name, status, None, owner.as_deref())?;
+    let worktree_error = create_worktree_for(db, git, project_id, repo_root, &session)?;
+    Ok((session, worktree_error))
+}
 
-        // Set status message
-        self.status_message = Some(if killed == 0 {
-            "No orphaned sessions found".to_string()
-        } else {
-            format!("Cleaned up {} orphaned session{}", killed, if killed == 1 { "" } else { "s" })
-        });
+/// Create a session and start its terminal in one step (`workbench new --start`). The
+/// worktree and tmux session can't take part in a database transaction, so any failure
+/// undoes the steps before it and no half-made session is left behind
+pub fn create_and_start_session(
+    db: &dyn Queries,
+    tmux: &dyn TmuxClient,
+    git: &dyn GitClient,
+    project: &Project,
+    repo_root: Option<&Path>,
+    name: &str,
+) -> Result<(Session, String)> {
+    if !tmux.is_available() {
+        return Err(eyre!("tmux is not installed"));
+    }
+    let (session, worktree_error) = create_session_with_worktree(db, git, project.id, repo_root, name, Status::Planned)?;
+    let started = (|| {
+        if let Some(e) = worktree_error {
+            return Err(eyre!("Failed creating the worktree: {}", e));
+        }
+        let session = db.get_live_session(project.id, session.id)?
+            .ok_or_else(|| eyre!("The session was removed while starting"))?;
+        let tmux_name = ensure_tmux_session(db, tmux, git, project, &session)
+            .map_err(|e| eyre!("Failed starting tmux: {}", e))?;
+        Ok((session, tmux_name))
+    })();
+    if started.is_err() {
+        discard_new_session(db, tmux, git, project.id, repo_root, session.id);
+    }
+    started
+}
 
-        // Refresh the session list
-        self.refresh_tmux_sessions();
+/// Undo a session that failed to start: its tmux session, its worktree (the branch is
+/// kept, it may have existed before), then its rows
+fn discard_new_session(
+    db: &dyn Queries,
+    tmux: &dyn TmuxClient,
+    git: &dyn GitClient,
+    project_id: i64,
+    repo_root: Option<&Path>,
+    session_id: i64,
+) {
+    let session = db.get_live_session(project_id, session_id).ok().flatten();
+    let tmux_name = session.as_ref()
+        .and_then(|s| s.tmux_window.clone())
+        .unwrap_or_else(|| tmux::session_name(project_id, session_id));
+    tmux.kill_session(&tmux_name);
+    if let (Some(repo_root), Some(path)) = (repo_root, session.as_ref().and_then(|s| s.checkout_path.as_deref()))
+        && let Err(e) = git.remove_worktree(repo_root, path, true)
+    {
+        tracing::warn!(session_id, path = %path.display(), error = %e, "could not remove the worktree of a discarded session");
     }
+    if let Err(e) = db.purge_session(session_id) {
+        tracing::error!(session_id, error = %e, "could not remove a discarded session");
+    }
+}
 
-    fn handle_enter_key(&mut self) -> Result<AppAction> {
-        if !tmux::is_available() {
-            // tmux not installed, do nothing
-            return Ok(AppAction::None);
+/// Create the git worktree for a new session (when in a git repo), returning the error text on failure
+fn create_worktree_for(
+    db: &dyn Queries,
+    git: &dyn GitClient,
+    project_id: i64,
+    repo_root: Option<&Path>,
+    session: &Session,
+) -> Result<Option<String>> {
+    let name = session.name.as_str();
+    if let Some(repo_root) = repo_root {
+        let prefix = db.get_project_setting(project_id, SETTING_BRANCH_PREFIX)?
+            .unwrap_or_else(|| git::DEFAULT_BRANCH_PREFIX.to_string());
+        let base_branch = base_branch(db, git, project_id, repo_root)?;
+        let max_len = db.get_project_setting(project_id, SETTING_BRANCH_MAX_LEN)?
+            .and_then(|v| parse_branch_max_len(&v).ok().flatten())
+            .unwrap_or(git::DEFAULT_BRANCH_MAX_LEN);
+        let mut branch_name = git::sanitize_branch_name_with_prefix(name, &prefix, max_len);
+        if !git.is_valid_branch_name(&branch_name) {
+            let fallback = format!("{}session-{}", prefix, session.id);
+            tracing::warn!(branch = %branch_name, fallback = %fallback, "sanitized branch name rejected by git");
+            branch_name = if git.is_valid_branch_name(&fallback) {
+                fallback
+            } else {
+                format!("{}session-{}", git::DEFAULT_BRANCH_PREFIX, session.id)
+            };
         }
+        let worktree_path = git::generate_worktree_path(repo_root, &branch_name);
 
-        let Some(session) = self.selected_session() else {
-            return Ok(AppAction::None);
-        };
+        match git.create_worktree(repo_root, &branch_name, &worktree_path, base_branch.as_deref()) {
+            Ok(()) => {
+                db.update_session_worktree(session.id, &worktree_path, &branch_name)?;
+            }
+            Err(e) => {
+                tracing::error!(branch = %branch_name, path = %worktree_path.display(), error = %e, "worktree creation failed");
+                return Ok(Some(e.to_string()));
+            }
+        }
+    }
 
-        let session_id = session.id;
+    Ok(None)
+}
 
-        // Use existing tmux_window if available, otherwise generate new name
-        if let Some(ref tmux_name) = session.tmux_window {
-            if tmux::session_exists(tmux_name) {
-                return Ok(AppAction::AttachTmux(tmux_name.clone()));
-            }
+/// Branch new session branches start from: the Base branch setting, else `origin/HEAD`
+fn base_branch(db: &dyn Queries, git: &dyn GitClient, project_id: i64, repo_root: &Path) -> Result<Option<String>> {
+    Ok(db.get_project_setting(project_id, SETTING_BASE_BRANCH)?
+        .filter(|b| !b.is_empty())
+        .or_else(|| git.detect_default_branch(repo_root)))
+}
+
+/// Checklist items typed as `tests pass; PR linked`: trimmed, empty ones and repeats dropped
+pub fn parse_checklist(text: &str) -> Vec<String> {
+    let mut items: Vec<String> = Vec::new();
+    for item in text.split([';', '\n']).map(str::trim).filter(|item| !item.is_empty()) {
+        if !items.iter().any(|seen| seen == item) {
+            items.push(item.to_string());
         }
+    }
+    items
+}
 
-        // Generate tmux session name, ensuring uniqueness
-        let base_name = tmux::session_name(self.project.id, session_id);
-        let tmux_name = if tmux::session_exists(&base_name) {
-            // Name collision - add timestamp suffix for uniqueness
-            let ts = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            format!("{}-{}", base_name, ts)
-        } else {
-            base_name
-        };
+/// Parse the transcript size cap setting; empty means the default (None)
+fn parse_transcript_max_kb(value: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<u64>() {
+        Ok(kb) if kb > 0 => Ok(Some(kb)),
+        _ => Err("Enter a size in KiB, e.g. 1024".to_string()),
+    }
+}
 
-        // Use checkout_path (worktree) if available, otherwise fall back to project path
-        let working_dir = session.checkout_path.as_ref().unwrap_or(&self.project.path);
+/// Parse the AI context setting; empty means the default (None)
+fn parse_ai_context_chars(value: &str) -> Result<Option<usize>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<usize>() {
+        Ok(chars) if (context::MIN_CHARS..=context::MAX_CHARS).contains(&chars) => Ok(Some(chars)),
+        _ => Err(format!("Enter a number of characters from {} to {}", context::MIN_CHARS, context::MAX_CHARS)),
+    }
+}
 
-        // Create a new tmux session
-        tmux::create_session(&tmux_name, working_dir)?;
-        self.db.set_tmux_session(session_id, &tmux_name)?;
-        self.active_tmux_sessions.insert(tmux_name.clone());
+/// Parse the watch cooldown setting; empty means the default (None), 0 lets a rule fire
+/// on every poll that shows a new match
+fn parse_watch_cooldown(value: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value.parse::<u64>().map(Some).map_err(|_| "Enter a number of minutes".to_string())
+}
 
-        Ok(AppAction::AttachTmux(tmux_name))
+/// Parse a days setting (stale age, archive age); empty means the default (None), 0 never
+fn parse_days(value: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
     }
+    value.parse::<u64>().map(Some).map_err(|_| "Enter a number of days (0 = never)".to_string())
+}
 
-    fn handle_input_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.input_buffer.clear();
-            }
-            KeyCode::Enter => {
-                if !self.input_buffer.is_empty() {
-                    let session = self.db.create_session(self.project.id, &self.input_buffer)?;
-
-                    // Create git worktree if we're in a git repo
-                    if let Some(ref repo_root) = self.repo_root {
-                        let branch_name = git::sanitize_branch_name(&self.input_buffer);
-                        let worktree_path = git::generate_worktree_path(repo_root, &branch_name);
-
-                        match git::create_worktree(repo_root, &branch_name, &worktree_path) {
-                            Ok(()) => {
-                                self.db.update_session_worktree(session.id, &worktree_path, &branch_name)?;
-                            }
-                            Err(e) => {
-                                self.status_message = Some(format!("Worktree error: {}", e));
-                            }
-                        }
-                    }
+/// Since when the oldest card of a column has been waiting: its creation in Planned, its
+/// last column change elsewhere. None for an empty column
+pub fn oldest_card_since(sessions: &[&Session], status: Status) -> Option<i64> {
+    sessions
+        .iter()
+        .map(|s| if status == Status::Planned { s.created_at } else { s.status_changed_at })
+        .min()
+}
 
-                    self.refresh_sessions()?;
+/// Parse the branch name length setting; empty means the default (None)
+fn parse_branch_max_len(value: &str) -> Result<Option<usize>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    match value.parse::<usize>() {
+        Ok(len) if git::BRANCH_MAX_LEN_RANGE.contains(&len) => Ok(Some(len)),
+        _ => Err(format!(
+            "Enter a number from {} to {}",
+            git::BRANCH_MAX_LEN_RANGE.start(),
+            git::BRANCH_MAX_LEN_RANGE.end()
+        )),
+    }
+}
+
+/// Writes for a batch fill: suggested values for fields that are still empty.
+/// Values the user already entered are never overwritten
+fn fill_empty_values(field_ids: &[i64], current: &[String], suggested: &[String]) -> Vec<(i64, String)> {
+    field_ids
+        .iter()
+        .zip(current)
+        .zip(suggested)
+        .filter(|((_, current), suggested)| current.trim().is_empty() && !suggested.trim().is_empty())
+        .map(|((id, _), suggested)| (*id, suggested.trim().to_string()))
+        .collect()
+}
+
+/// Longest name prefilled from a pane line
+const SPINOFF_NAME_MAX_CHARS: usize = 80;
+
+/// Last line of pane output that reads like text, without prompt/box-drawing decoration,
+/// truncated for use as a session name
+pub fn last_meaningful_line(content: &str) -> Option<String> {
+    let line = content.lines().rev().find_map(|line| {
+        let text = line
+            .trim_matches(|c: char| c.is_whitespace() || "│┃|╭╮╰╯─━>❯›$#*•·⏺✻".contains(c))
+            .trim();
+        let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+        (letters >= 3).then_some(text)
+    })?;
+    let mut name: String = line.chars().take(SPINOFF_NAME_MAX_CHARS).collect();
+    if line.chars().count() > SPINOFF_NAME_MAX_CHARS {
+        name = format!("{}…", name.trim_end());
+    }
+    Some(name)
+}
+
+static URL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"https?://[^\s<>"'`]+"#).expect("url pattern"));
+
+/// Byte ranges of the URLs in a line of pane output. Trailing punctuation and closing
+/// brackets without an opening one are left out, so `(see https://x.y/z).` gives `https://x.y/z`
+pub fn find_urls(line: &str) -> Vec<Range<usize>> {
+    URL.find_iter(line)
+        .map(|m| {
+            let mut url = m.as_str();
+            loop {
+                let unmatched = |open: char, close: char| {
+                    url.ends_with(close) && url.matches(open).count() < url.matches(close).count()
+                };
+                if url.ends_with(['.', ',', ';', ':', '!', '?']) || unmatched('(', ')') || unmatched('[', ']') {
+                    url = &url[..url.len() - 1];
+                } else {
+                    break;
                 }
-                self.input_mode = InputMode::Normal;
-                self.input_buffer.clear();
-            }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
             }
-            KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+            m.start()..m.start() + url.len()
+        })
+        .filter(|range| range.len() > "https://".len())
+        .collect()
+}
+
+/// How often the peek overlay re-captures the pane
+const PEEK_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// One capture of the peeked pane. Links are found once per capture rather than on every
+/// frame, on the joined (`-J`) capture so a URL the terminal wrapped is still one link
+#[derive(Debug, Clone)]
+pub struct PeekCapture {
+    pub session_id: i64,
+    /// The `]`/`[` pick the capture was taken with
+    chosen_pane: Option<String>,
+    /// ` [%id command]` for split sessions, empty otherwise
+    pub pane_label: String,
+    pub lines: Vec<String>,
+    /// URL byte ranges per line of `lines`
+    pub links: Vec<Vec<Range<usize>>>,
+    /// Every distinct URL, in the order it first appears
+    pub urls: Vec<String>,
+    taken: Instant,
+}
+
+impl PeekCapture {
+    fn new(session_id: i64, chosen_pane: Option<String>, pane_label: String, content: &str) -> Self {
+        let lines: Vec<String> = content.lines().map(str::to_string).collect();
+        let links: Vec<Vec<Range<usize>>> = lines.iter().map(|line| find_urls(line)).collect();
+        let mut urls: Vec<String> = Vec::new();
+        for (line, ranges) in lines.iter().zip(&links) {
+            for range in ranges {
+                let url = &line[range.clone()];
+                if !urls.iter().any(|u| u == url) {
+                    urls.push(url.to_string());
+                }
             }
-            _ => {}
         }
-        Ok(())
+        Self { session_id, chosen_pane, pane_label, lines, links, urls, taken: Instant::now() }
     }
+}
 
-    fn handle_edit_session_key(&mut self, key: KeyEvent) -> Result<()> {
-        let total_rows = 1 + self.fields.len(); // name + custom fields
-
-        match key.code {
-            KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.input_buffer.clear();
-                self.editing_session_id = None;
-                self.edit_session_name.clear();
-                self.edit_field_values.clear();
-                self.edit_mode = EditMode::Manual;
-                self.ai_input.clear();
-            }
-            // Shift+Tab cycles between Manual and AI mode
-            KeyCode::BackTab if key.modifiers.contains(KeyModifiers::SHIFT) => {
-                if self.edit_mode == EditMode::Manual {
-                    self.save_current_edit_row();
-                }
-                self.edit_mode = match self.edit_mode {
-                    EditMode::Manual => EditMode::AI,
-                    EditMode::AI => EditMode::Manual,
-                };
-                if self.edit_mode == EditMode::Manual {
-                    self.load_current_edit_row();
-                }
-            }
-            _ => {
-                match self.edit_mode {
-                    EditMode::Manual => self.handle_manual_edit_key(key, total_rows)?,
-                    EditMode::AI => self.handle_ai_edit_key(key)?,
+/// Shift+Enter inserts a line break in multi-line editors. Terminals that can't report
+/// Shift+Enter get Alt+Enter and Ctrl+J instead
+fn is_newline_key(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Enter => key.modifiers.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT),
+        KeyCode::Char('j') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([A-Za-z0-9_]+)\}").expect("placeholder pattern"));
+
+/// How a field is named in a prompt template: "PR URL" -> `pr_url`
+pub fn placeholder_key(name: &str) -> String {
+    let key: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    key.trim_matches('_').to_string()
+}
+
+/// Fill a template's `{placeholder}`s from (key, value) pairs, matching keys case-insensitively.
+/// Unknown placeholders expand to nothing and are returned so the caller can warn
+pub fn expand_template(body: &str, values: &[(String, String)]) -> (String, Vec<String>) {
+    let mut unknown = Vec::new();
+    let text = PLACEHOLDER.replace_all(body, |caps: &Captures| {
+        let name = &caps[1];
+        match values.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some((_, value)) => value.trim().to_string(),
+            None => {
+                if !unknown.iter().any(|u| u == name) {
+                    unknown.push(name.to_string());
                 }
+                String::new()
             }
         }
-        Ok(())
+    });
+    (text.into_owned(), unknown)
+}
+
+/// The column named by its key (`in_progress`) or its label, case-insensitively
+pub fn resolve_column(column_labels: &[(Status, String)], input: &str) -> Option<Status> {
+    column_labels
+        .iter()
+        .find(|(status, label)| input.eq_ignore_ascii_case(label) || input.eq_ignore_ascii_case(status.as_str()))
+        .map(|(status, _)| *status)
+}
+
+/// Parse a shortcut definition `name | column | field`; the field part is optional.
+/// Columns match by key or label and fields by name, both case-insensitively
+pub fn parse_shortcut_spec(
+    spec: &str,
+    column_labels: &[(Status, String)],
+    fields: &[Field],
+) -> Result<(String, Status, Option<i64>), String> {
+    let parts: Vec<&str> = spec.split('|').map(str::trim).collect();
+    if !(2..=3).contains(&parts.len()) {
+        return Err("Use: name | column | field (field optional)".to_string());
     }
+    let name = parts[0];
+    if name.is_empty() {
+        return Err("Shortcut name cannot be empty".to_string());
+    }
+    let status = resolve_column(column_labels, parts[1]).ok_or_else(|| format!("Unknown column \"{}\"", parts[1]))?;
+    let field_id = match parts.get(2).filter(|f| !f.is_empty()) {
+        Some(field) => Some(
+            fields
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(field))
+                .map(|f| f.id)
+                .ok_or_else(|| format!("Unknown field \"{}\"", field))?,
+        ),
+        None => None,
+    };
+    Ok((name.to_string(), status, field_id))
+}
 
-    fn handle_manual_edit_key(&mut self, key: KeyEvent, total_rows: usize) -> Result<()> {
-        match key.code {
-            KeyCode::Tab | KeyCode::Down => {
-                self.save_current_edit_row();
-                if self.edit_row < total_rows - 1 {
-                    self.edit_row += 1;
-                } else {
-                    self.edit_row = 0;
-                }
-                self.load_current_edit_row();
-            }
-            KeyCode::BackTab | KeyCode::Up => {
-                self.save_current_edit_row();
-                if self.edit_row > 0 {
-                    self.edit_row -= 1;
-                } else {
-                    self.edit_row = total_rows - 1;
+/// A card typed into the plan popup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedSession {
+    pub name: String,
+    pub status: Status,
+    pub tags: Vec<String>,
+}
+
+/// Parse the plan popup: every non-blank line is a card, Planned unless it ends in
+/// `@column` (matched like shortcut columns), plus any trailing `#tags`. Suffixes come
+/// off the end in any order; `#123` is left in the name as an issue number
+pub fn parse_plan(text: &str, column_labels: &[(Status, String)]) -> Result<Vec<PlannedSession>, String> {
+    let mut planned = Vec::new();
+    for (number, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line)) {
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let mut status = None;
+        let mut tags = Vec::new();
+        while let Some(word) = words.last() {
+            if let Some(column) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+                if status.is_some() {
+                    return Err(format!("Line {}: more than one @column", number));
                 }
-                self.load_current_edit_row();
+                let found = resolve_column(column_labels, column);
+                status = Some(found.ok_or_else(|| format!("Line {}: unknown column \"{}\"", number, column))?);
+            } else if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty() && !t.chars().all(|c| c.is_ascii_digit())) {
+                tags.insert(0, tag.to_string());
+            } else {
+                break;
             }
-            KeyCode::Enter => {
-                self.save_current_edit_row();
-                self.save_and_close_edit()?;
+            words.pop();
+        }
+        if words.is_empty() {
+            return Err(format!("Line {}: no name before the suffixes", number));
+        }
+        planned.push(PlannedSession { name: words.join(" "), status: status.unwrap_or(Status::Planned), tags });
+    }
+    Ok(planned)
+}
+
+/// Parse a watch rule `regex | move <column>`, `regex | set <field>` or `regex | notify`.
+/// The action is after the last `|`, so the regex may use alternation. `set` takes the
+/// regex's first capture group, so the regex needs one
+pub fn parse_watch_rule_spec(
+    spec: &str,
+    column_labels: &[(Status, String)],
+    fields: &[Field],
+) -> Result<(String, WatchAction), String> {
+    let usage = || "Use: regex | move <column>, regex | set <field> or regex | notify".to_string();
+    let (pattern, action) = spec.rsplit_once('|').ok_or_else(usage)?;
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err("The regex cannot be empty".to_string());
+    }
+    let regex = Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+    let action = action.trim();
+    let (verb, target) = action.split_once(char::is_whitespace).unwrap_or((action, ""));
+    let target = target.trim();
+    let action = match verb.to_lowercase().as_str() {
+        "move" => WatchAction::Move(
+            resolve_column(column_labels, target).ok_or_else(|| format!("Unknown column \"{}\"", target))?,
+        ),
+        "set" => {
+            let field = fields
+                .iter()
+                .find(|f| f.name.eq_ignore_ascii_case(target))
+                .ok_or_else(|| format!("Unknown field \"{}\"", target))?;
+            if regex.captures_len() < 2 {
+                return Err("set needs a capture group, e.g. PR: (\\S+)".to_string());
             }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
+            WatchAction::SetField(field.id)
+        }
+        "notify" if target.is_empty() => WatchAction::Notify,
+        _ => return Err(usage()),
+    };
+    Ok((pattern.to_string(), action))
+}
+
+/// Non-blank lines of a pane capture that weren't in the previous capture. Screens scroll
+/// and agents redraw, so this counts lines rather than diffing positions; a prompt box or
+/// status bar that stays put never reads as new output
+pub fn new_pane_lines<'a>(previous: &[String], current: &'a [String]) -> Vec<&'a str> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for line in previous {
+        *seen.entry(line.as_str()).or_default() += 1;
+    }
+    current
+        .iter()
+        .map(String::as_str)
+        .filter(|line| !line.trim().is_empty())
+        .filter(|line| match seen.get_mut(line) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
             }
-            KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+            _ => true,
+        })
+        .collect()
+}
+
+impl App {
+    pub fn new() -> Result<Self> {
+        let db = Database::new()?;
+        let (project, repo_root) = current_project(&db)?;
+        for (repo, checkout) in db.purge_trash_older_than(TRASH_RETENTION_DAYS)? {
+            if let Err(e) = git::remove_worktree(&repo, &checkout, true) {
+                tracing::warn!(path = %checkout.display(), error = %e, "could not remove the worktree of a purged session");
             }
-            _ => {}
         }
+        let previous_project_id = db.record_active_project(project.id)?;
+
+        // A moved repo shows up as a new, empty project; offer to relink the old one first
+        let relocate_candidate = relocation_candidate(&db, &project)?;
+        let mut app = Self::for_project(DbHandle::spawn(db), Arc::new(tmux::SystemTmux), Arc::new(git::SystemGit), project, repo_root)?;
+        app.previous_project_id = previous_project_id;
+        if let Some(e) = agent::load_error() {
+            app.status_message = Some(format!("Agents: {} (using the built-in ones)", e));
+        }
+        if relocate_candidate.is_some() {
+            // Relinking changes the project; the file is offered on the next open
+            app.repo_config = None;
+            app.relocate_candidate = relocate_candidate;
+            app.input_mode = InputMode::ConfirmRelocate;
+        }
+        Ok(app)
+    }
+
+    /// A board for the project at `path`, driven by the given tmux and git rather than
+    /// the real ones, and never looking at the working directory
+    #[cfg(test)]
+    pub fn new_for_test(db: Database, tmux: Arc<dyn TmuxClient>, git: Arc<dyn GitClient>, path: &Path) -> Result<Self> {
+        let repo_root = git.get_repo_root(path);
+        let project_path = repo_root.clone().unwrap_or_else(|| path.to_path_buf());
+        let name = project_path.file_name().map_or("unknown".into(), |n| n.to_string_lossy().into_owned());
+        let project = db.get_or_create_project(&name, &project_path)?;
+        let mut app = Self::for_project(DbHandle::spawn(db), tmux, git, project, repo_root)?;
+        app.ai_available = false;
+        Ok(app)
+    }
+
+    /// The board of `project`, freshly loaded
+    fn for_project(
+        db: DbHandle,
+        tmux: Arc<dyn TmuxClient>,
+        git: Arc<dyn GitClient>,
+        project: Project,
+        repo_root: Option<PathBuf>,
+    ) -> Result<Self> {
+        let current_owner = default_owner(&*git, repo_root.as_deref());
+        let archive_days = Self::load_archive_days(&db, project.id)?;
+        let sessions = db.list_board_sessions(project.id, archive_days)?;
+        let archived_count = db.count_archived_sessions(project.id, archive_days)?;
+        let fields = db.list_fields(project.id)?;
+        let status_overrides = db.list_status_overrides(project.id)?;
+        let shortcuts = db.list_shortcuts(project.id)?;
+        let prompt_templates = db.list_prompt_templates(project.id)?;
+        let card_layout = card::parse_layout(db.get_project_setting(project.id, SETTING_CARD_LAYOUT)?.as_deref());
+        let stale_days = Self::load_stale_days(&db, project.id)?;
+        let (pane_states, tmux_error) = match tmux.poll_workbench_panes() {
+            Ok(states) => (states, None),
+            Err(e) => (HashMap::new(), Some(e.to_string())),
+        };
+        let active_tmux_sessions = live_sessions(&pane_states);
+        let sessions_waiting_input = waiting_sessions(&*tmux, &pane_states, &sessions);
+
+        let input_mode = Self::initial_input_mode(&db, &project, &fields)?;
+        let data_version = db.data_version()?;
+        let today = db.local_today()?;
+
+        let mut app = Self {
+            should_quit: false,
+            db,
+            tmux,
+            git,
+            project,
+            repo_root,
+            previous_project_id: None,
+            project_flash: None,
+            sessions,
+            selected_column: 0,
+            selected_row: 0,
+            input_mode,
+            input_buffer: String::new(),
+            active_tmux_sessions,
+            waiting_since: HashMap::new(),
+            sessions_waiting_input,
+            last_tmux_refresh: Instant::now(),
+            time_sampled_at: None,
+            time_spent: HashMap::new(),
+            field_values: HashMap::new(),
+            poll_interval: poll_interval_from_env(),
+            last_activity: Instant::now(),
+            needs_redraw: true,
+            redraw_at: None,
+            last_frame: None,
+            frames: 0,
+            recent_frames: VecDeque::new(),
+            show_frame_stats: false,
+            title_format: status::title_format_from_env(),
+            shown_title: None,
+            editing_session_id: None,
+            moving_session_id: None,
+            grabbed_session_id: None,
+            grabbed_from: None,
+            snoozing_session_id: None,
+            blocking_session_id: None,
+            quick_field: None,
+            selected_quick_field: 0,
+            last_quick_field_id: None,
+            marked_sessions: HashSet::new(),
+            quick_field_only_empty: false,
+            block_state: blockers::BlockState::default(),
+            just_unblocked: HashSet::new(),
+            unblocked_at: None,
+            recreate_worktree_session_id: None,
+            adopt_terminal: None,
+            restart_session_id: None,
+            relocate_candidate: None,
+            repo_config: None,
+            hint_targets: Vec::new(),
+            card_actions: Vec::new(),
+            selected_card_action: 0,
+            show_snoozed: false,
+            due_session_id: None,
+            today,
+            sort_by_due: false,
+            due_this_week: false,
+            current_owner,
+            mine_only: false,
+            pinned_only: false,
+            zoomed: false,
+            ai_available: crate::ai::is_available(),
+            deleting_session_id: None,
+            trashed_sessions: Vec::new(),
+            selected_trash: 0,
+            purging_session_id: None,
+            deleting_dirty_status: None,
+            peek_active: false,
+            spinoff_source_id: None,
+            new_session_status: None,
+            new_card_prompt: None,
+            follow_active: false,
+            follow_target: None,
+            peek_pane: None,
+            peek_capture: None,
+            url_choices: Vec::new(),
+            selected_url: 0,
+            pane_states,
+            tmux_error,
+            lock_holder: None,
+            read_only: false,
+            inside_tmux: tmux::is_inside_tmux(),
+            shared_attach: None,
+            lock_hostname: db::hostname(),
+            last_lock_heartbeat: Instant::now(),
+            edit_row: 0,
+            edit_session_name: String::new(),
+            edit_owner: String::new(),
+            edit_agent: String::new(),
+            edit_field_values: Vec::new(),
+            edit_field_sources: Vec::new(),
+            edit_completions: Vec::new(),
+            selected_completion: 0,
+            edit_mode: EditMode::default(),
+            ai_input: String::new(),
+            ai_running: false,
+            ai_error: None,
+            jira_fetch: None,
+            jira_rx: None,
+            show_ai_error: false,
+            ai_result_rx: None,
+            ai_context: AiContext::default(),
+            ai_history_lines: DEFAULT_AI_HISTORY_LINES,
+            ai_context_chars: None,
+            view: View::default(),
+            overview: Vec::new(),
+            selected_overview: 0,
+            show_archived_projects: false,
+            theme: crate::theme::Theme::default(),
+            no_color: false,
+            settings_tab: SettingsTab::default(),
+            fields,
+            status_overrides,
+            selected_status: 0,
+            checklist_move: None,
+            checklist_checked: Vec::new(),
+            selected_checklist_item: 0,
+            selected_project_setting: 0,
+            shortcuts,
+            watch_rules: Vec::new(),
+            watch_regexes: HashMap::new(),
+            selected_watch_rule: 0,
+            watch_log: Vec::new(),
+            watch_seen: HashMap::new(),
+            watch_fired: HashMap::new(),
+            recent_session_ids: Vec::new(),
+            selected_shortcut: 0,
+            card_layout,
+            column_oldest: Vec::new(),
+            stale_days,
+            archive_days,
+            show_archived: false,
+            archived_loaded: 0,
+            archived_count,
+            selected_card_line: 0,
+            selected_card_line_choice: 0,
+            card_details: HashMap::new(),
+            worktree_heads: HashMap::new(),
+            worktree_head_stamps: HashMap::new(),
+            worktree_heads_rx: None,
+            picked_shortcut: None,
+            prompt_templates,
+            branch_changes: None,
+            notes_editor: None,
+            land: None,
+            branch_changes_rx: None,
+            startup_trial: None,
+            board_changes: Vec::new(),
+            data_version,
+            reload_signal: Arc::new(AtomicBool::new(false)),
+            reload_problems: Vec::new(),
+            project_settings: HashMap::new(),
+            startup_trial_rx: None,
+            transcripts: Vec::new(),
+            transcripts_session_name: String::new(),
+            selected_transcript: 0,
+            transcript_view: None,
+            selected_prompt_template: 0,
+            editing_prompt_template_id: None,
+            detected_base_branch: None,
+            input_error: None,
+            selected_field: 0,
+            copy_sources: Vec::new(),
+            selected_copy_source: 0,
+            editing_field_id: None,
+            deleting_field_id: None,
+            new_field_name: String::new(),
+            new_field_desc: String::new(),
+            expanded_field_id: None,
+            status_message: None,
+            comments: Vec::new(),
+            comments_session_id: None,
+            env_session_id: None,
+            session_env: Vec::new(),
+            selected_env: 0,
+            env_revealed: false,
+            env_changed_keys: Vec::new(),
+            editing_env_key: None,
+            new_comment_text: String::new(),
+            comments_scroll: 0,
+            write_status_file: false,
+            duplicate_name_warning: false,
+            confirm_discard_plan: false,
+            worktree_entries: Vec::new(),
+            selected_worktree: 0,
+            confirm_worktree_prune: false,
+            worktree_scan_rx: None,
+            worktree_scan_cancel: None,
+            batch_fill_selected: Vec::new(),
+            selected_batch_field: 0,
+            batch_fill_entries: Vec::new(),
+            batch_fill_field_ids: Vec::new(),
+            batch_fill_rx: None,
+            batch_fill_cancel: None,
+        };
+        app.refresh_column_ages();
+        app.refresh_time_spent();
+        for problem in app.load_project_settings()? {
+            tracing::warn!(%problem, "ignoring an invalid project setting");
+        }
+        app.refresh_watch_rules()?;
+        app.refresh_blockers()?;
+        app.hold_lock()?;
+        if !app.read_only {
+            app.check_repo_config()?;
+        }
+        Ok(app)
+    }
+
+    /// Renew the board's hold on the project and note who else has it open. Read-only
+    /// mode ends by itself once the other board is gone
+    fn hold_lock(&mut self) -> Result<()> {
+        let hostname = self.lock_hostname.clone();
+        let holder = self.db.hold_project_lock(self.project.id, &hostname, std::process::id(), db::unix_now())?;
+        if holder.is_none() && self.read_only {
+            self.read_only = false;
+            self.status_message = Some("The other board closed; edits are back on".to_string());
+        }
+        self.lock_holder = holder;
+        self.last_lock_heartbeat = Instant::now();
         Ok(())
     }
 
-    fn handle_ai_edit_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Tab | KeyCode::Down | KeyCode::Up | KeyCode::BackTab => {
-                // In AI mode, navigation just scrolls through fields (read-only view)
-                let total_rows = 1 + self.fields.len();
-                match key.code {
-                    KeyCode::Tab | KeyCode::Down => {
-                        if self.edit_row < total_rows - 1 {
-                            self.edit_row += 1;
-                        } else {
-                            self.edit_row = 0;
-                        }
-                    }
-                    KeyCode::BackTab | KeyCode::Up => {
-                        if self.edit_row > 0 {
-                            self.edit_row -= 1;
-                        } else {
-                            self.edit_row = total_rows - 1;
-                        }
-                    }
-                    _ => {}
-                }
+    /// Give up the hold on the project, on quit or when switching projects
+    pub fn release_lock(&self) {
+        let _ = self.db.release_project_lock(self.project.id, &self.lock_hostname, std::process::id());
+    }
+
+    fn load_stale_days(db: &DbHandle, project_id: i64) -> Result<u64> {
+        let value = db.get_project_setting(project_id, SETTING_STALE_DAYS)?;
+        Ok(value.and_then(|v| parse_days(&v).ok().flatten()).unwrap_or(DEFAULT_STALE_DAYS))
+    }
+
+    fn load_archive_days(db: &DbHandle, project_id: i64) -> Result<u64> {
+        let value = db.get_project_setting(project_id, SETTING_ARCHIVE_DAYS)?;
+        Ok(value.and_then(|v| parse_days(&v).ok().flatten()).unwrap_or(DEFAULT_ARCHIVE_DAYS))
+    }
+
+    /// Sessions for the board: archived Done cards only while browsing them, as many
+    /// pages as were loaded before
+    fn load_sessions(&mut self) -> Result<Vec<Session>> {
+        let mut sessions = self.db.list_board_sessions(self.project.id, self.archive_days)?;
+        self.archived_count = self.db.count_archived_sessions(self.project.id, self.archive_days)?;
+        if self.show_archived && self.archived_count > 0 {
+            let limit = self.archived_loaded.max(ARCHIVE_PAGE);
+            let archived = self.db.list_archived_sessions(self.project.id, self.archive_days, limit, 0)?;
+            self.archived_loaded = archived.len();
+            sessions.extend(archived);
+        }
+        Ok(sessions)
+    }
+
+    /// Append the next page of archived Done cards
+    fn load_more_archived(&mut self) -> Result<()> {
+        let page = self.db.list_archived_sessions(self.project.id, self.archive_days, ARCHIVE_PAGE, self.archived_loaded)?;
+        self.archived_loaded += page.len();
+        self.sessions.extend(page);
+        self.refresh_column_ages();
+        Ok(())
+    }
+
+    /// Recompute the header ages; after a refresh and whenever the board's filters change
+    fn refresh_column_ages(&mut self) {
+        self.column_oldest = Status::all()
+            .iter()
+            .map(|status| oldest_card_since(&self.sessions_by_status(*status), *status))
+            .collect();
+    }
+
+    /// Offer starter fields to projects that have none, until answered once
+    fn initial_input_mode(db: &DbHandle, project: &Project, fields: &[Field]) -> Result<InputMode> {
+        if fields.is_empty() && db.get_project_setting(project.id, SETTING_SEED_FIELDS_PROMPTED)?.is_none() {
+            Ok(InputMode::ConfirmSeedFields)
+        } else {
+            Ok(InputMode::Normal)
+        }
+    }
+
+    /// Offer the repo's `.workbench.toml` when it is new or changed since it was last
+    /// answered. A file with nothing to change is marked seen without asking
+    fn check_repo_config(&mut self) -> Result<()> {
+        let Ok(text) = std::fs::read_to_string(self.project.path.join(repo_config::FILE_NAME)) else {
+            return Ok(());
+        };
+        let hash = repo_config::hash(&text);
+        if self.db.get_project_setting(self.project.id, SETTING_REPO_CONFIG_HASH)?.as_deref() == Some(hash.as_str()) {
+            return Ok(());
+        }
+        let mut config = match repo_config::parse(&text) {
+            Ok(config) => config,
+            Err(e) => {
+                self.status_message = Some(format!("{}: {}", repo_config::FILE_NAME, e.trim()));
+                return Ok(());
             }
-            KeyCode::Enter => {
-                // Run AI fill with the ai_input prompt
-                if !self.ai_input.is_empty() {
-                    self.run_ai_fill();
-                }
+        };
+        let project_id = self.project.id;
+        let settings = self.db.call(move |db| repo_config_settings(db, project_id))??;
+        let mut problems = Vec::new();
+        config.settings.retain(|key, _| {
+            let local_only = ProjectSetting::all().iter().any(|s| s.key() == key && !s.repo_may_set());
+            if local_only {
+                problems.push(format!("{} is only set here, in Settings > Project", key));
             }
-            KeyCode::Backspace => {
-                self.ai_input.pop();
+            !local_only
+        });
+        let (mut changes, plan_problems) = repo_config::plan(&config, &self.fields, &self.status_overrides, &settings);
+        problems.extend(plan_problems);
+
+        // Jira fields may name fields the file itself adds
+        let mut known_fields = self.fields.clone();
+        known_fields.extend(config.fields.iter().map(|definition| Field {
+            id: 0,
+            name: definition.name.trim().to_string(),
+            description: String::new(),
+            display_order: 0,
+            visible: true,
+            in_ai: true,
+            in_edit: true,
+            wrap: false,
+        }));
+        changes.retain(|change| {
+            let repo_config::Action::Setting(ref key, ref value) = change.action else { return true };
+            let Some(setting) = ProjectSetting::all().iter().find(|s| s.key() == key) else { return true };
+            let validation = match setting {
+                ProjectSetting::JiraFields => resolve_jira_fields(&known_fields, value).map(|_| ()),
+                _ => self.validate_project_setting(*setting, value),
+            };
+            match validation {
+                Ok(()) => true,
+                Err(e) => {
+                    problems.push(format!("{}: {} (\"{}\")", key, e, value));
+                    false
+                }
             }
-            KeyCode::Char(c) => {
-                self.ai_input.push(c);
+        });
+
+        if changes.is_empty() {
+            self.db.set_project_setting(project_id, SETTING_REPO_CONFIG_HASH, &hash)?;
+            if let Some(problem) = problems.first() {
+                self.status_message = Some(format!("{}: {}", repo_config::FILE_NAME, problem));
             }
-            _ => {}
+            return Ok(());
         }
+        let ticked = changes.iter().map(|change| !change.is_conflict()).collect();
+        self.repo_config = Some(RepoConfigPrompt { hash, changes, ticked, selected: 0, problems });
+        self.input_mode = InputMode::RepoConfig;
         Ok(())
     }
 
-    pub fn handle_paste(&mut self, text: &str) {
-        match self.input_mode {
-            InputMode::EditSession if self.edit_mode == EditMode::AI => {
-                self.ai_input.push_str(text);
-            }
-            InputMode::EditSession => {
-                self.input_buffer.push_str(text);
-            }
-            InputMode::NewSession => {
-                self.input_buffer.push_str(text);
-            }
-            InputMode::NewFieldName => {
-                self.new_field_name.push_str(text);
+    fn handle_repo_config_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(prompt) = self.repo_config.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if prompt.selected + 1 < prompt.changes.len() => {
+                prompt.selected += 1;
             }
-            InputMode::NewFieldDesc => {
-                self.new_field_desc.push_str(text);
+            KeyCode::Char('k') | KeyCode::Up => {
+                prompt.selected = prompt.selected.saturating_sub(1);
             }
-            InputMode::EditFieldName => {
-                self.new_field_name.push_str(text);
+            KeyCode::Char(' ') => {
+                if let Some(ticked) = prompt.ticked.get_mut(prompt.selected) {
+                    *ticked = !*ticked;
+                }
             }
-            InputMode::EditFieldDesc => {
-                self.new_field_desc.push_str(text);
+            KeyCode::Enter => {
+                let Some(prompt) = self.repo_config.take() else { return Ok(()) };
+                let actions: Vec<repo_config::Action> = prompt
+                    .changes
+                    .iter()
+                    .zip(&prompt.ticked)
+                    .filter(|(_, ticked)| **ticked)
+                    .map(|(change, _)| change.action.clone())
+                    .collect();
+                let kept = prompt.ticked.iter().filter(|ticked| !**ticked).count();
+                let summary = repo_config::summary(&actions);
+                let project_id = self.project.id;
+                self.db.call(move |db| repo_config::apply(db, project_id, &actions))??;
+                self.db.set_project_setting(project_id, SETTING_REPO_CONFIG_HASH, &prompt.hash)?;
+
+                self.refresh_fields()?;
+                self.refresh_status_overrides()?;
+                // The plan only holds values that passed the same checks
+                self.load_project_settings()?;
+                self.stale_days = Self::load_stale_days(&self.db, project_id)?;
+                self.archive_days = Self::load_archive_days(&self.db, project_id)?;
+                self.refresh_sessions()?;
+                self.clamp_row();
+                let applied = match summary.as_str() {
+                    "" => format!("Left the project as it was ({} not applied)", repo_config::FILE_NAME),
+                    summary => format!("Applied {}: {}", repo_config::FILE_NAME, summary),
+                };
+                self.status_message = Some(match kept {
+                    0 => applied,
+                    kept => format!("{}; kept {} local value{}", applied, kept, if kept == 1 { "" } else { "s" }),
+                });
+                self.input_mode = Self::initial_input_mode(&self.db, &self.project, &self.fields)?;
             }
-            InputMode::NewComment => {
-                self.new_comment_text.push_str(text);
+            KeyCode::Esc | KeyCode::Char('n') => {
+                if let Some(prompt) = self.repo_config.take() {
+                    self.db.set_project_setting(self.project.id, SETTING_REPO_CONFIG_HASH, &prompt.hash)?;
+                }
+                self.status_message = Some(format!("Skipped {} until it changes", repo_config::FILE_NAME));
+                self.input_mode = Self::initial_input_mode(&self.db, &self.project, &self.fields)?;
             }
             _ => {}
         }
+        Ok(())
     }
 
-    fn save_and_close_edit(&mut self) -> Result<()> {
-        if let Some(session_id) = self.editing_session_id {
-            if !self.edit_session_name.is_empty() {
-                self.db.update_session_name(session_id, &self.edit_session_name)?;
-            }
-            for (i, field) in self.fields.iter().enumerate() {
-                if let Some(value) = self.edit_field_values.get(i) {
-                    self.db.set_session_field_value(session_id, field.id, value)?;
+    fn handle_confirm_relocate_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                if let Some(old) = self.relocate_candidate.take() {
+                    let new = self.project.clone();
+                    self.db.delete_empty_project(new.id)?;
+                    self.db.update_project_path(old.id, &new.name, &new.path)?;
+                    if let Some(remote) = self.repo_root.as_deref().and_then(|root| self.git.get_remote_url(root)) {
+                        self.db.set_project_setting(old.id, SETTING_REMOTE_URL, &remote)?;
+                    }
+                    self.status_message = Some(format!("Relinked project from {}", old.path.display()));
+                    self.project = Project { name: new.name, path: new.path, ..old };
+                    self.refresh_fields()?;
+                    self.refresh_status_overrides()?;
+                    self.refresh_sessions()?;
+                    self.clamp_row();
                 }
+                self.input_mode = InputMode::Normal;
             }
-            self.refresh_sessions()?;
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.relocate_candidate = None;
+                self.input_mode = Self::initial_input_mode(&self.db, &self.project, &self.fields)?;
+            }
+            _ => {}
         }
-        self.input_mode = InputMode::Normal;
-        self.input_buffer.clear();
-        self.editing_session_id = None;
-        self.edit_session_name.clear();
-        self.edit_field_values.clear();
-        self.edit_mode = EditMode::Manual;
-        self.ai_input.clear();
         Ok(())
     }
 
-    fn run_ai_fill(&mut self) {
-        use crate::ai;
-
-        let fields: Vec<(String, String)> = self.fields
+    /// The cards of a column as shown: pinned ones first, otherwise oldest first
+    pub fn sessions_by_status(&self, status: Status) -> Vec<&Session> {
+        let now = db::unix_now();
+        let mut sessions: Vec<&Session> = self.sessions
             .iter()
-            .map(|f| (f.name.clone(), f.description.clone()))
+            .filter(|s| s.status == status)
+            .filter(|s| self.show_snoozed || snoozed_for(s, now).is_none())
+            .filter(|s| !self.mine_only || self.is_mine(s))
+            .filter(|s| !self.pinned_only || s.pinned)
+            .filter(|s| !self.due_this_week || s.due.is_some_and(|day| due::due_this_week(day, self.today)))
             .collect();
-
-        if fields.is_empty() {
-            return;
+        // Sorts are stable, so ties keep creation order and cards don't swap on every poll
+        if self.sort_by_due {
+            sessions.sort_by_key(|s| (!s.pinned, s.due.is_none(), s.due));
+        } else if self.column_sort(status) == ColumnSort::Activity {
+            sessions.sort_by_key(|s| (!s.pinned, std::cmp::Reverse(self.last_activity(s))));
+        } else {
+            sessions.sort_by_key(|s| !s.pinned);
         }
+        sessions
+    }
 
-        // Get tmux pane content if available
-        let pane_content: Option<String> = self.editing_session_id
-            .and_then(|id| self.sessions.iter().find(|s| s.id == id))
-            .and_then(|s| s.tmux_window.as_ref())
-            .and_then(|name| tmux::capture_pane_content(name));
-
-        // Use ai_input as the prompt, with session name as context
-        let prompt = format!("{}\nSession name: {}", self.ai_input, self.edit_session_name);
-        let num_fields = self.edit_field_values.len();
+    /// When the session last did something: its terminal's last activity while it has a
+    /// live one, else when the card last changed
+    fn last_activity(&self, session: &Session) -> i64 {
+        session
+            .tmux_window
+            .as_ref()
+            .filter(|name| self.active_tmux_sessions.contains(*name))
+            .and_then(|name| self.pane_states.get(name))
+            .map_or(session.updated_at, |pane| pane.activity)
+    }
 
-        // Create channel for receiving results
-        let (tx, rx) = mpsc::channel();
-        self.ai_result_rx = Some(rx);
-        self.ai_running = true;
+    pub fn is_mine(&self, session: &Session) -> bool {
+        match (&session.owner, &self.current_owner) {
+            (Some(owner), Some(me)) => owner.trim().eq_ignore_ascii_case(me.trim()),
+            _ => false,
+        }
+    }
 
-        // Clear any previous error
-        self.ai_error = None;
+    /// The session the move popup acts on
+    pub fn moving_session(&self) -> Option<&Session> {
+        let id = self.moving_session_id?;
+        self.sessions.iter().find(|s| s.id == id)
+    }
 
-        // Spawn background thread
-        thread::spawn(move || {
-            let result = ai::fill_fields(&prompt, &fields, pane_content.as_deref())
-                .map(|mut values| {
-                    values.resize(num_fields, String::new());
-                    values
-                })
-                .map_err(|e| e.to_string());
-            let _ = tx.send(result);
-        });
+    pub fn selected_session(&self) -> Option<&Session> {
+        let status = Status::all().get(self.selected_column)?;
+        let sessions = self.sessions_by_status(*status);
+        sessions.get(self.selected_row).copied()
     }
 
-    fn check_ai_result(&mut self) {
-        if let Some(ref rx) = self.ai_result_rx {
-            if let Ok(result) = rx.try_recv() {
-                match result {
-                    Ok(values) => {
-                        // Update field values with AI suggestions
-                        for (i, value) in values.into_iter().enumerate() {
-                            if i < self.edit_field_values.len() {
-                                self.edit_field_values[i] = value;
-                            }
-                        }
-                        self.ai_error = None;
-                    }
-                    Err(e) => {
-                        self.ai_error = Some(e);
-                    }
-                }
-                // Switch back to manual mode to review/edit
-                self.edit_mode = EditMode::Manual;
-                self.edit_row = 0;
-                self.load_current_edit_row();
-                self.ai_input.clear();
-                self.ai_running = false;
-                self.ai_result_rx = None;
-            }
+    pub fn refresh_sessions(&mut self) -> Result<()> {
+        let woke = self.db.wake_snoozed_sessions(self.project.id)?;
+        if !woke.is_empty() {
+            self.status_message = Some(format!("Woke up: {}", woke.join(", ")));
+        }
+        self.sessions = self.load_sessions()?;
+        let sessions = &self.sessions;
+        self.marked_sessions.retain(|id| sessions.iter().any(|s| s.id == *id));
+        self.recent_session_ids = self.db.recent_attaches(self.project.id, RECENT_MARKERS)?;
+        self.refresh_field_values();
+        self.refresh_tmux_sessions();
+        self.refresh_column_ages();
+        self.refresh_blockers()?;
+        if self.input_mode == InputMode::MoveSession && self.moving_session().is_none() {
+            self.input_mode = InputMode::Normal;
+            self.moving_session_id = None;
+            self.status_message = Some("That session no longer exists".to_string());
         }
+        Ok(())
     }
 
-    fn save_current_edit_row(&mut self) {
-        if self.edit_row == 0 {
-            self.edit_session_name = self.input_buffer.clone();
-        } else {
-            let field_idx = self.edit_row - 1;
-            if field_idx < self.edit_field_values.len() {
-                self.edit_field_values[field_idx] = self.input_buffer.clone();
-            }
+    /// Reload for changes made outside workbench (another `workbench` command, an agent
+    /// while attached), reporting what they moved. Refreshes after the app's own actions
+    /// go through `refresh_sessions` and report nothing
+    pub fn refresh_external(&mut self) -> Result<()> {
+        let before = std::mem::take(&mut self.sessions);
+        self.refresh_sessions()?;
+        self.data_version = self.db.data_version()?;
+        let found = changes::diff(&before, &self.sessions);
+        if !found.is_empty() {
+            self.status_message = Some(format!("{} (external), R: details", changes::summary(&found)));
+            self.board_changes = found;
         }
+        Ok(())
     }
 
-    fn load_current_edit_row(&mut self) {
-        if self.edit_row == 0 {
-            self.input_buffer = self.edit_session_name.clone();
-        } else {
-            let field_idx = self.edit_row - 1;
-            if let Some(value) = self.edit_field_values.get(field_idx) {
-                self.input_buffer = value.clone();
-            } else {
-                self.input_buffer.clear();
-            }
+    /// Work out which cards are blocked. Cards that were blocked and no longer are, while
+    /// still open, are flagged for a moment; a new loop of links is reported once
+    fn refresh_blockers(&mut self) -> Result<()> {
+        let links = self.db.list_blockers(self.project.id)?;
+        let open: HashSet<i64> = self.sessions.iter().filter(|s| s.status != Status::Done).map(|s| s.id).collect();
+        let state = blockers::compute(&links, &open);
+        let freed: Vec<i64> = self
+            .block_state
+            .blocked
+            .keys()
+            .filter(|id| open.contains(id) && !state.blocked.contains_key(id))
+            .copied()
+            .collect();
+        if !freed.is_empty() {
+            self.just_unblocked.extend(freed);
+            self.unblocked_at = Some(Instant::now());
+        }
+        if state.cycle.is_some() && state.cycle != self.block_state.cycle {
+            let names: Vec<&str> = state.cycle.iter().flatten().map(|id| self.session_name(*id)).collect();
+            self.status_message = Some(format!("Blocker loop: {} (its last link is ignored)", names.join(" → ")));
         }
+        self.block_state = state;
+        Ok(())
     }
 
-    fn handle_move_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.moving_session_id = None;
-            }
-            KeyCode::Char(c @ '1'..='4') => {
-                let idx = (c as usize) - ('1' as usize);
-                let statuses = Status::all();
-                if idx < statuses.len() {
-                    if let Some(session_id) = self.moving_session_id {
-                        self.db.update_session_status(session_id, statuses[idx])?;
-                        self.refresh_sessions()?;
-                    }
-                }
-                self.input_mode = InputMode::Normal;
-                self.moving_session_id = None;
+    /// Open cards the session waits on, if any
+    pub fn blocked_by(&self, session_id: i64) -> Option<&[i64]> {
+        self.block_state.blocked.get(&session_id).map(Vec::as_slice)
+    }
+
+    fn session_name(&self, session_id: i64) -> &str {
+        self.sessions.iter().find(|s| s.id == session_id).map(|s| s.name.as_str()).unwrap_or("?")
+    }
+
+    /// Names of the cards the selected card is directly marked as blocked by
+    pub fn blocker_names(&self, session_id: i64) -> Result<Vec<String>> {
+        Ok(self
+            .db
+            .list_blockers(self.project.id)?
+            .into_iter()
+            .filter(|(id, _)| *id == session_id)
+            .map(|(_, blocker)| self.session_name(blocker).to_string())
+            .collect())
+    }
+
+    /// Attach to a session's tmux session, remembering it for `Ctrl+O`
+    fn attach(&mut self, session_id: i64, tmux_name: String) -> Result<AppAction> {
+        self.db.record_attach(self.project.id, session_id)?;
+        self.recent_session_ids = self.db.recent_attaches(self.project.id, RECENT_MARKERS)?;
+        Ok(AppAction::AttachTmux(tmux_name))
+    }
+
+    /// Re-attach to the last attached session, whatever is selected
+    fn jump_to_last_attached(&mut self) -> Result<AppAction> {
+        let Some(&session_id) = self.recent_session_ids.first() else {
+            self.status_message = Some("No session attached yet".to_string());
+            return Ok(AppAction::None);
+        };
+        let Some(session) = self.sessions.iter().find(|s| s.id == session_id) else {
+            return Ok(AppAction::None);
+        };
+        let name = session.name.clone();
+        let live = session
+            .tmux_window
+            .clone()
+            .filter(|t| self.tmux.session_exists(t) && self.exit_status(session).is_none());
+        self.select_session(session_id);
+        match live {
+            Some(tmux_name) => self.attach(session_id, tmux_name),
+            None => {
+                self.status_message = Some(format!("\"{}\" has no running terminal (Enter to start one)", name));
+                Ok(AppAction::None)
             }
-            _ => {}
         }
+    }
+
+    pub fn refresh_prompt_templates(&mut self) -> Result<()> {
+        self.prompt_templates = self.db.list_prompt_templates(self.project.id)?;
+        self.selected_prompt_template =
+            self.selected_prompt_template.min(self.prompt_templates.len().saturating_sub(1));
         Ok(())
     }
 
-    fn handle_confirm_delete_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                if let Some(session_id) = self.deleting_session_id {
-                    // Find session to get its details before deletion
-                    if let Some(session) = self.sessions.iter().find(|s| s.id == session_id) {
-                        // Kill associated tmux session
-                        if let Some(ref tmux_name) = session.tmux_window {
-                            tmux::kill_session(tmux_name);
-                        }
-                        // Remove worktree if it exists
-                        if let (Some(repo_root), Some(checkout_path)) = (&self.repo_root, &session.checkout_path) {
-                            let _ = git::remove_worktree(repo_root, checkout_path, false);
-                        }
-                    }
-                    self.db.delete_session(session_id)?;
-                    self.refresh_sessions()?;
-                    self.clamp_row();
-                }
-                self.input_mode = InputMode::Normal;
-                self.deleting_session_id = None;
-            }
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.deleting_session_id = None;
-            }
-            _ => {}
-        }
+    pub fn refresh_watch_rules(&mut self) -> Result<()> {
+        self.watch_rules = self.db.list_watch_rules(self.project.id)?;
+        self.selected_watch_rule = self.selected_watch_rule.min(self.watch_rules.len().saturating_sub(1));
+        // Patterns are checked when a rule is added; one that no longer compiles just never fires
+        self.watch_regexes = self
+            .watch_rules
+            .iter()
+            .filter_map(|rule| Some((rule.id, Regex::new(&rule.pattern).ok()?)))
+            .collect();
+        self.watch_log = self.db.recent_events(self.project.id, "watch", WATCH_LOG_LEN)?;
         Ok(())
     }
 
-    fn handle_confirm_delete_dirty_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                if let Some(session_id) = self.deleting_session_id {
-                    // Find session to get its details before deletion
-                    if let Some(session) = self.sessions.iter().find(|s| s.id == session_id) {
-                        // Kill associated tmux session
-                        if let Some(ref tmux_name) = session.tmux_window {
-                            tmux::kill_session(tmux_name);
+    /// Match the watch rules against what each live pane printed since the previous poll and
+    /// act on them, or only log the match in dry-run mode. Every action is logged and shown
+    /// in the status line. True when a card changed
+    fn run_watch_rules(&mut self) -> Result<bool> {
+        if self.watch_rules.is_empty() {
+            self.watch_seen.clear();
+            return Ok(false);
+        }
+        let dry_run = self.project_setting(ProjectSetting::WatchDryRun).as_deref() == Some("on");
+        let cooldown_mins = self.project_setting(ProjectSetting::WatchCooldown)
+            .and_then(|v| parse_watch_cooldown(&v).ok().flatten())
+            .unwrap_or(DEFAULT_WATCH_COOLDOWN_MINS);
+        let cooldown = Duration::from_secs(cooldown_mins * 60);
+
+        let live: Vec<(Session, String)> = self.sessions
+            .iter()
+            .filter_map(|s| {
+                let name = s.tmux_window.clone().filter(|n| self.pane_states.contains_key(n))?;
+                Some((s.clone(), name))
+            })
+            .collect();
+        self.watch_seen.retain(|id, _| live.iter().any(|(s, _)| s.id == *id));
+
+        let mut announced = Vec::new();
+        let mut changed = false;
+        for (session, tmux_name) in live {
+            let Some(content) = self.tmux.capture_pane_content(&tmux_name, None) else { continue };
+            let current: Vec<String> = content.lines().map(str::to_string).collect();
+            let previous = self.watch_seen.insert(session.id, current.clone());
+            // The first capture is the baseline, so output from before workbench was watching doesn't fire
+            let Some(previous) = previous else { continue };
+            let new_output = new_pane_lines(&previous, &current).join("\n");
+            if new_output.is_empty() {
+                continue;
+            }
+            for rule in self.watch_rules.clone() {
+                let Some(captures) = self.watch_regexes.get(&rule.id).and_then(|r| r.captures(&new_output)) else {
+                    continue;
+                };
+                if self.watch_fired.get(&(rule.id, session.id)).is_some_and(|at| at.elapsed() < cooldown) {
+                    continue;
+                }
+                self.watch_fired.insert((rule.id, session.id), Instant::now());
+                let matched = captures[0].trim().to_string();
+                let captured = captures.get(1).map(|m| m.as_str().trim().to_string());
+                let Some(description) = self.watch_action_description(&session, rule.action, &matched, captured.as_deref()) else {
+                    continue;
+                };
+                if !dry_run {
+                    match rule.action {
+                        WatchAction::Move(status) => self.move_session_to(&session, status)?,
+                        WatchAction::SetField(field_id) => {
+                            let value = captured.as_deref().unwrap_or_default();
+                            self.set_field_value(session.id, field_id, value, ValueSource::Import)?;
                         }
-                        // Force remove dirty worktree
-                        if let (Some(repo_root), Some(checkout_path)) = (&self.repo_root, &session.checkout_path) {
-                            let _ = git::remove_worktree(repo_root, checkout_path, true);
+                        // Ring the terminal bell; the status line says why
+                        WatchAction::Notify => {
+                            use std::io::Write;
+                            let mut stdout = std::io::stdout();
+                            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
                         }
                     }
-                    self.db.delete_session(session_id)?;
-                    self.refresh_sessions()?;
-                    self.clamp_row();
+                    changed |= rule.action != WatchAction::Notify;
                 }
-                self.input_mode = InputMode::Normal;
-                self.deleting_session_id = None;
-                self.deleting_dirty_status = None;
-            }
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.deleting_session_id = None;
-                self.deleting_dirty_status = None;
+                let kind = if dry_run { "watch_dry_run" } else { "watch" };
+                let detail = format!("/{}/: {}", rule.pattern, description);
+                tracing::info!(session_id = session.id, rule_id = rule.id, dry_run, "{}", detail);
+                self.db.log_event(self.project.id, Some(session.id), kind, &detail)?;
+                announced.push(description);
             }
-            _ => {}
         }
-        Ok(())
+        if !announced.is_empty() {
+            let prefix = if dry_run { "Watch (dry run, not applied)" } else { "Watch" };
+            self.status_message = Some(format!("{}: {}", prefix, announced.join("; ")));
+            self.watch_log = self.db.recent_events(self.project.id, "watch", WATCH_LOG_LEN)?;
+        }
+        Ok(changed)
     }
 
-    fn handle_confirm_delete_field_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                if let Some(field_id) = self.deleting_field_id {
-                    self.db.delete_field(field_id)?;
-                    self.refresh_fields()?;
-                    if self.selected_field >= self.fields.len() && self.selected_field > 0 {
-                        self.selected_field -= 1;
-                    }
-                }
-                self.input_mode = InputMode::Normal;
-                self.deleting_field_id = None;
+    /// What a watch rule does to `session`, or None when there is nothing to do: the card
+    /// is already in the column, or the field was deleted or the capture is empty
+    fn watch_action_description(
+        &self,
+        session: &Session,
+        action: WatchAction,
+        matched: &str,
+        captured: Option<&str>,
+    ) -> Option<String> {
+        match action {
+            WatchAction::Move(status) => (session.status != status)
+                .then(|| format!("moved {} to {}", session.name, self.status_label(status))),
+            WatchAction::SetField(field_id) => {
+                let field = self.fields.iter().find(|f| f.id == field_id)?;
+                let value = captured.filter(|v| !v.is_empty())?;
+                Some(format!("set {} of {} to {}", field.name, session.name, value))
             }
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.deleting_field_id = None;
+            WatchAction::Notify => {
+                let mut shown: String = matched.chars().take(SPINOFF_NAME_MAX_CHARS).collect();
+                if matched.chars().count() > SPINOFF_NAME_MAX_CHARS {
+                    shown.push('…');
+                }
+                Some(format!("{} printed \"{}\"", session.name, shown))
             }
-            _ => {}
         }
-        Ok(())
     }
 
-    fn handle_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_watch_settings_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
-            KeyCode::Char('q') | KeyCode::Esc => {
-                self.view = View::Kanban;
-            }
-            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.should_quit = true;
-            }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if !self.fields.is_empty() && self.selected_field < self.fields.len() - 1 {
-                    self.selected_field += 1;
-                }
+            KeyCode::Char('j') | KeyCode::Down if self.selected_watch_rule + 1 < self.watch_rules.len() => {
+                self.selected_watch_rule += 1;
             }
             KeyCode::Char('k') | KeyCode::Up => {
-                if self.selected_field > 0 {
-                    self.selected_field -= 1;
-                }
+                self.selected_watch_rule = self.selected_watch_rule.saturating_sub(1);
             }
             KeyCode::Char('n') => {
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
-                self.input_mode = InputMode::NewFieldName;
-            }
-            KeyCode::Char('e') => {
-                if let Some(field) = self.fields.get(self.selected_field) {
-                    self.editing_field_id = Some(field.id);
-                    self.new_field_name = field.name.clone();
-                    self.new_field_desc = field.description.clone();
-                    self.input_mode = InputMode::EditFieldName;
-                }
+                self.input_buffer.clear();
+                self.input_error = None;
+                self.input_mode = InputMode::NewWatchRule;
             }
             KeyCode::Char('d') => {
-                if let Some(field) = self.fields.get(self.selected_field) {
-                    self.deleting_field_id = Some(field.id);
-                    self.input_mode = InputMode::ConfirmDeleteField;
-                }
-            }
-            KeyCode::Char('K') => {
-                if let Some(field) = self.fields.get(self.selected_field) {
-                    self.db.move_field_up(self.project.id, field.id)?;
-                    self.refresh_fields()?;
-                    if self.selected_field > 0 {
-                        self.selected_field -= 1;
-                    }
-                }
-            }
-            KeyCode::Char('J') => {
-                if let Some(field) = self.fields.get(self.selected_field) {
-                    self.db.move_field_down(self.project.id, field.id)?;
-                    self.refresh_fields()?;
-                    if self.selected_field < self.fields.len() - 1 {
-                        self.selected_field += 1;
-                    }
-                }
-            }
-            KeyCode::Char('v') => {
-                if let Some(field) = self.fields.get(self.selected_field) {
-                    self.db.toggle_field_visibility(field.id)?;
-                    self.refresh_fields()?;
+                if let Some(rule) = self.watch_rules.get(self.selected_watch_rule) {
+                    self.db.delete_watch_rule(rule.id)?;
+                    self.refresh_watch_rules()?;
                 }
             }
             _ => {}
@@ -910,127 +2818,371 @@ impl App {
         Ok(())
     }
 
-    fn handle_new_field_name_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_new_watch_rule_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
             KeyCode::Esc => {
                 self.input_mode = InputMode::Normal;
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
+                self.input_buffer.clear();
+                self.input_error = None;
             }
             KeyCode::Enter => {
-                if !self.new_field_name.is_empty() {
-                    self.input_mode = InputMode::NewFieldDesc;
+                let column_labels: Vec<(Status, String)> =
+                    Status::all().iter().map(|s| (*s, self.status_label(*s).to_string())).collect();
+                match parse_watch_rule_spec(&self.input_buffer, &column_labels, &self.fields) {
+                    Ok((pattern, action)) => {
+                        self.db.create_watch_rule(self.project.id, &pattern, action)?;
+                        self.refresh_watch_rules()?;
+                        self.selected_watch_rule = self.watch_rules.len().saturating_sub(1);
+                        self.input_mode = InputMode::Normal;
+                        self.input_buffer.clear();
+                        self.input_error = None;
+                    }
+                    Err(e) => self.input_error = Some(e),
                 }
             }
             KeyCode::Backspace => {
-                self.new_field_name.pop();
+                self.input_buffer.pop();
+                self.input_error = None;
             }
             KeyCode::Char(c) => {
-                self.new_field_name.push(c);
+                self.input_buffer.push(c);
+                self.input_error = None;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_new_field_desc_key(&mut self, key: KeyEvent) -> Result<()> {
+    pub fn refresh_shortcuts(&mut self) -> Result<()> {
+        self.shortcuts = self.db.list_shortcuts(self.project.id)?;
+        self.selected_shortcut = self.selected_shortcut.min(self.shortcuts.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Why a shortcut can't be used, if it references something that no longer exists
+    pub fn shortcut_problem(&self, shortcut: &Shortcut) -> Option<&'static str> {
+        match shortcut.field_id {
+            Some(id) if !self.fields.iter().any(|f| f.id == id) => Some("field was deleted"),
+            _ => None,
+        }
+    }
+
+    /// Name of the field a shortcut prompts for
+    pub fn shortcut_field_name(&self, shortcut: &Shortcut) -> Option<&str> {
+        let id = shortcut.field_id?;
+        self.fields.iter().find(|f| f.id == id).map(|f| f.name.as_str())
+    }
+
+    fn run_shortcut(&mut self, index: usize, value: Option<&str>) -> Result<()> {
+        let Some(shortcut) = self.shortcuts.get(index).cloned() else {
+            return Ok(());
+        };
+        let field_value = shortcut.field_id.zip(value);
+        let owner = self.current_owner.clone();
+        let session =
+            self.db.create_session_with(self.project.id, &shortcut.name, shortcut.status, field_value, owner.as_deref())?;
+        let worktree_error = create_worktree_for(&self.db, &*self.git, self.project.id, self.repo_root.as_deref(), &session)?;
+        self.refresh_sessions()?;
+        self.select_session(session.id);
+        self.status_message = Some(match worktree_error {
+            Some(e) => format!("Worktree error: {}", e),
+            None => format!("Created \"{}\" in {}", shortcut.name, self.status_label(shortcut.status)),
+        });
+        Ok(())
+    }
+
+    fn handle_pick_shortcut_key(&mut self, key: KeyEvent) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        let KeyCode::Char(c) = key.code else { return Ok(()) };
+        let Some(index) = c.to_digit(10).and_then(|d| (d as usize).checked_sub(1)) else {
+            return Ok(());
+        };
+        let Some(shortcut) = self.shortcuts.get(index) else { return Ok(()) };
+
+        if let Some(problem) = self.shortcut_problem(shortcut) {
+            self.status_message = Some(format!("Shortcut \"{}\" is disabled: {}", shortcut.name, problem));
+        } else if shortcut.field_id.is_some() {
+            self.picked_shortcut = Some(index);
+            self.input_buffer.clear();
+            self.input_mode = InputMode::ShortcutValue;
+        } else {
+            self.run_shortcut(index, None)?;
+        }
+        Ok(())
+    }
+
+    fn handle_shortcut_value_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
             KeyCode::Esc => {
                 self.input_mode = InputMode::Normal;
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
+                self.input_buffer.clear();
+                self.picked_shortcut = None;
             }
             KeyCode::Enter => {
-                self.db.create_field(self.project.id, &self.new_field_name, &self.new_field_desc)?;
-                self.refresh_fields()?;
+                if let Some(index) = self.picked_shortcut.take() {
+                    let value = self.input_buffer.trim().to_string();
+                    self.run_shortcut(index, Some(&value))?;
+                }
                 self.input_mode = InputMode::Normal;
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
+                self.input_buffer.clear();
             }
             KeyCode::Backspace => {
-                self.new_field_desc.pop();
+                self.input_buffer.pop();
             }
             KeyCode::Char(c) => {
-                self.new_field_desc.push(c);
+                self.input_buffer.push(c);
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_edit_field_name_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn save_card_layout(&mut self) -> Result<()> {
+        self.db.set_project_setting(self.project.id, SETTING_CARD_LAYOUT, &card::format_layout(&self.card_layout))?;
+        self.selected_card_line = self.selected_card_line.min(self.card_layout.len().saturating_sub(1));
+        self.refresh_card_details();
+        Ok(())
+    }
+
+    fn handle_card_layout_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        let selected = self.selected_card_line;
         match key.code {
-            KeyCode::Esc => {
-                self.input_mode = InputMode::Normal;
-                self.editing_field_id = None;
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
+            KeyCode::Char('j') | KeyCode::Down if selected + 1 < self.card_layout.len() => {
+                self.selected_card_line += 1;
             }
-            KeyCode::Enter => {
-                if !self.new_field_name.is_empty() {
-                    self.input_mode = InputMode::EditFieldDesc;
-                }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_card_line = selected.saturating_sub(1);
             }
-            KeyCode::Backspace => {
-                self.new_field_name.pop();
+            KeyCode::Char('J') if selected + 1 < self.card_layout.len() => {
+                self.card_layout.swap(selected, selected + 1);
+                self.selected_card_line += 1;
+                self.save_card_layout()?;
             }
-            KeyCode::Char(c) => {
-                self.new_field_name.push(c);
+            KeyCode::Char('K') if selected > 0 && selected < self.card_layout.len() => {
+                self.card_layout.swap(selected, selected - 1);
+                self.selected_card_line -= 1;
+                self.save_card_layout()?;
+            }
+            KeyCode::Char('n') => {
+                if self.card_layout.len() >= card::MAX_CARD_LINES {
+                    self.status_message = Some(format!("Cards show at most {} lines", card::MAX_CARD_LINES));
+                } else {
+                    self.selected_card_line_choice = 0;
+                    self.input_mode = InputMode::PickCardLine;
+                }
+            }
+            KeyCode::Char('d') if selected < self.card_layout.len() => {
+                self.card_layout.remove(selected);
+                self.save_card_layout()?;
+            }
+            KeyCode::Char('r') => {
+                self.card_layout = card::default_layout();
+                self.save_card_layout()?;
+                self.status_message = Some("Card layout reset".to_string());
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_edit_field_desc_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_pick_card_line_key(&mut self, key: KeyEvent) -> Result<()> {
+        let choices = CardLine::choices(&self.fields);
         match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_card_line_choice + 1 < choices.len() => {
+                self.selected_card_line_choice += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_card_line_choice = self.selected_card_line_choice.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                if let Some(line) = choices.get(self.selected_card_line_choice) {
+                    // Insert below the selected line
+                    let at = (self.selected_card_line + 1).min(self.card_layout.len());
+                    self.card_layout.insert(at, *line);
+                    self.selected_card_line = at;
+                    self.save_card_layout()?;
+                }
+            }
             KeyCode::Esc => {
                 self.input_mode = InputMode::Normal;
-                self.editing_field_id = None;
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
             }
-            KeyCode::Enter => {
-                if let Some(field_id) = self.editing_field_id {
-                    self.db.update_field(field_id, &self.new_field_name, &self.new_field_desc)?;
-                    self.refresh_fields()?;
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Gather dirty status and last output lines, when the card layout shows them
+    fn refresh_card_details(&mut self) {
+        let wants_dirty = self.card_layout.contains(&CardLine::Dirty);
+        let wants_output = self.card_layout.contains(&CardLine::LastOutput);
+        self.card_details.clear();
+        if !wants_dirty && !wants_output {
+            return;
+        }
+        for session in &self.sessions {
+            let dirty = session
+                .checkout_path
+                .as_deref()
+                .filter(|_| wants_dirty)
+                .and_then(|path| self.git.get_dirty_status(path));
+            let last_output = session
+                .tmux_window
+                .as_ref()
+                .filter(|name| wants_output && self.pane_states.contains_key(*name))
+                .and_then(|name| self.tmux.capture_pane_content(name, None))
+                .as_deref()
+                .and_then(last_meaningful_line);
+            self.card_details.insert(session.id, CardDetails { dirty, last_output });
+        }
+    }
+
+    /// Re-read, on a worker thread, the HEAD of each open card's worktree whose HEAD
+    /// has moved since the last read; gone worktrees and Done cards drop theirs
+    fn refresh_worktree_heads(&mut self) {
+        if self.worktree_heads_rx.is_some() {
+            return;
+        }
+        let mut stamps = HashMap::new();
+        let mut jobs = Vec::new();
+        for session in self.sessions.iter().filter(|s| s.status != Status::Done) {
+            let Some(path) = session.checkout_path.as_ref() else { continue };
+            let Some(stamp) = self.git.head_modified(path) else { continue };
+            if self.worktree_head_stamps.get(&session.id) != Some(&stamp) {
+                jobs.push((session.id, path.clone()));
+            }
+            stamps.insert(session.id, stamp);
+        }
+        self.worktree_heads.retain(|id, _| stamps.contains_key(id));
+        self.worktree_head_stamps = stamps;
+        if jobs.is_empty() {
+            return;
+        }
+
+        let git = self.git.clone();
+        let (tx, rx) = mpsc::channel();
+        self.worktree_heads_rx = Some(rx);
+        thread::spawn(move || {
+            let heads = jobs.into_iter().map(|(session_id, path)| (session_id, git.head(&path))).collect();
+            let _ = tx.send(heads);
+        });
+    }
+
+    fn check_worktree_heads(&mut self) {
+        let Some(ref rx) = self.worktree_heads_rx else { return };
+        let heads = match rx.try_recv() {
+            Ok(heads) => heads,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Vec::new(),
+        };
+        self.worktree_heads_rx = None;
+        self.needs_redraw = true;
+        for (session_id, head) in heads {
+            // A card that went Done or lost its worktree meanwhile keeps no HEAD
+            match head.filter(|_| self.worktree_head_stamps.contains_key(&session_id)) {
+                Some(head) => self.worktree_heads.insert(session_id, head),
+                None => self.worktree_heads.remove(&session_id),
+            };
+        }
+    }
+
+    /// What a session's worktree has checked out when that isn't its stored branch, as
+    /// last seen by the tmux refresh
+    pub fn branch_out_of_sync(&self, session: &Session) -> Option<&git::Head> {
+        let head = self.worktree_heads.get(&session.id)?;
+        match head {
+            git::Head::Branch(branch) if session.branch_name.as_deref() == Some(branch.as_str()) => None,
+            _ => Some(head),
+        }
+    }
+
+    fn handle_shortcuts_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_shortcut + 1 < self.shortcuts.len() => {
+                self.selected_shortcut += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_shortcut = self.selected_shortcut.saturating_sub(1);
+            }
+            KeyCode::Char('n') => {
+                self.input_buffer.clear();
+                self.input_error = None;
+                self.input_mode = InputMode::NewShortcut;
+            }
+            KeyCode::Char('d') => {
+                if let Some(shortcut) = self.shortcuts.get(self.selected_shortcut) {
+                    self.db.delete_shortcut(shortcut.id)?;
+                    self.refresh_shortcuts()?;
                 }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_new_shortcut_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
                 self.input_mode = InputMode::Normal;
-                self.editing_field_id = None;
-                self.new_field_name.clear();
-                self.new_field_desc.clear();
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                let column_labels: Vec<(Status, String)> =
+                    Status::all().iter().map(|s| (*s, self.status_label(*s).to_string())).collect();
+                match parse_shortcut_spec(&self.input_buffer, &column_labels, &self.fields) {
+                    Ok((name, status, field_id)) => {
+                        self.db.create_shortcut(self.project.id, &name, status, field_id)?;
+                        self.refresh_shortcuts()?;
+                        self.selected_shortcut = self.shortcuts.len().saturating_sub(1);
+                        self.input_mode = InputMode::Normal;
+                        self.input_buffer.clear();
+                        self.input_error = None;
+                    }
+                    Err(e) => self.input_error = Some(e),
+                }
             }
             KeyCode::Backspace => {
-                self.new_field_desc.pop();
+                self.input_buffer.pop();
+                self.input_error = None;
             }
             KeyCode::Char(c) => {
-                self.new_field_desc.push(c);
+                self.input_buffer.push(c);
+                self.input_error = None;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn handle_view_comments_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_prompts_settings_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
-            KeyCode::Esc | KeyCode::Char('q') => {
-                self.input_mode = InputMode::Normal;
-                self.comments_session_id = None;
-                self.comments.clear();
-                self.comments_scroll = 0;
+            KeyCode::Char('j') | KeyCode::Down if self.selected_prompt_template + 1 < self.prompt_templates.len() => {
+                self.selected_prompt_template += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_prompt_template = self.selected_prompt_template.saturating_sub(1);
             }
             KeyCode::Char('n') => {
-                self.new_comment_text.clear();
-                self.input_mode = InputMode::NewComment;
+                self.editing_prompt_template_id = None;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+                self.input_error = None;
+                self.input_mode = InputMode::PromptTemplateName;
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if self.comments_scroll < self.comments.len().saturating_sub(1) {
-                    self.comments_scroll += 1;
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(template) = self.prompt_templates.get(self.selected_prompt_template) {
+                    self.editing_prompt_template_id = Some(template.id);
+                    self.new_field_name = template.name.clone();
+                    self.new_field_desc = template.body.clone();
+                    self.input_error = None;
+                    self.input_mode = InputMode::PromptTemplateName;
                 }
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if self.comments_scroll > 0 {
-                    self.comments_scroll -= 1;
+            KeyCode::Char('d') => {
+                if let Some(template) = self.prompt_templates.get(self.selected_prompt_template) {
+                    self.db.delete_prompt_template(template.id)?;
+                    self.refresh_prompt_templates()?;
                 }
             }
             _ => {}
@@ -1038,41 +3190,6994 @@ impl App {
         Ok(())
     }
 
-    fn handle_new_comment_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn close_prompt_template_popup(&mut self) {
+        self.input_mode = InputMode::Normal;
+        self.editing_prompt_template_id = None;
+        self.new_field_name.clear();
+        self.new_field_desc.clear();
+        self.input_error = None;
+    }
+
+    fn handle_prompt_template_name_key(&mut self, key: KeyEvent) {
         match key.code {
-            KeyCode::Esc => {
-                self.new_comment_text.clear();
-                self.input_mode = InputMode::ViewComments;
+            KeyCode::Esc => self.close_prompt_template_popup(),
+            KeyCode::Enter => {
+                self.new_field_name = self.new_field_name.trim().to_string();
+                let name = &self.new_field_name;
+                let editing = self.editing_prompt_template_id;
+                if name.is_empty() {
+                    self.input_error = Some("Name cannot be empty".to_string());
+                } else if self.prompt_templates.iter().any(|t| Some(t.id) != editing && t.name.eq_ignore_ascii_case(name)) {
+                    self.input_error = Some(format!("A template named \"{}\" already exists", name));
+                } else {
+                    self.input_mode = InputMode::PromptTemplateBody;
+                }
+            }
+            KeyCode::Backspace => {
+                self.new_field_name.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.new_field_name.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_prompt_template_body_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => self.close_prompt_template_popup(),
+            _ if is_newline_key(&key) => {
+                self.new_field_desc.push('\n');
             }
             KeyCode::Enter => {
-                if !self.new_comment_text.is_empty() {
-                    if let Some(session_id) = self.comments_session_id {
-                        self.db.create_comment(session_id, &self.new_comment_text)?;
-                        self.comments = self.db.list_comments(session_id).unwrap_or_default();
-                        self.comments_scroll = 0;
-                    }
+                let body = self.new_field_desc.trim();
+                if body.is_empty() {
+                    self.input_error = Some("Prompt cannot be empty".to_string());
+                    return Ok(());
                 }
-                self.new_comment_text.clear();
-                self.input_mode = InputMode::ViewComments;
+                match self.editing_prompt_template_id {
+                    Some(id) => self.db.update_prompt_template(id, &self.new_field_name, body)?,
+                    None => self.db.create_prompt_template(self.project.id, &self.new_field_name, body)?,
+                }
+                let name = self.new_field_name.clone();
+                self.refresh_prompt_templates()?;
+                if let Some(idx) = self.prompt_templates.iter().position(|t| t.name == name) {
+                    self.selected_prompt_template = idx;
+                }
+                self.close_prompt_template_popup();
             }
             KeyCode::Backspace => {
-                self.new_comment_text.pop();
+                self.new_field_desc.pop();
+                self.input_error = None;
             }
             KeyCode::Char(c) => {
-                self.new_comment_text.push(c);
+                self.new_field_desc.push(c);
+                self.input_error = None;
             }
             _ => {}
         }
         Ok(())
     }
 
-    fn clamp_row(&mut self) {
+    /// Template picker over the edit popup's AI mode; Enter appends the expanded text to the prompt
+    fn handle_pick_prompt_template_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_prompt_template + 1 < self.prompt_templates.len() => {
+                self.selected_prompt_template += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_prompt_template = self.selected_prompt_template.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some(template) = self.prompt_templates.get(self.selected_prompt_template) {
+                    let mut values = vec![("session_name".to_string(), self.edit_session_name.clone())];
+                    values.extend(
+                        self.fields
+                            .iter()
+                            .zip(&self.edit_field_values)
+                            .map(|(field, value)| (placeholder_key(&field.name), value.clone())),
+                    );
+                    let (text, unknown) = expand_template(&template.body, &values);
+                    if !self.ai_input.is_empty() && !self.ai_input.ends_with(char::is_whitespace) {
+                        self.ai_input.push(' ');
+                    }
+                    self.ai_input.push_str(&text);
+                    if !unknown.is_empty() {
+                        let names = unknown.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ");
+                        self.status_message = Some(format!("Unknown placeholders left empty: {}", names));
+                    }
+                }
+                self.input_mode = InputMode::EditSession;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => self.input_mode = InputMode::EditSession,
+            _ => {}
+        }
+    }
+
+    pub fn refresh_fields(&mut self) -> Result<()> {
+        self.fields = self.db.list_fields(self.project.id)?;
+        Ok(())
+    }
+
+    pub fn refresh_status_overrides(&mut self) -> Result<()> {
+        self.status_overrides = self.db.list_status_overrides(self.project.id)?;
+        Ok(())
+    }
+
+    /// How the column orders its cards after the pinned ones
+    pub fn column_sort(&self, status: Status) -> ColumnSort {
+        self.status_overrides.iter().find(|o| o.status == status).map_or(ColumnSort::Created, |o| o.sort)
+    }
+
+    /// Items a card must tick off to enter the column; empty when the move is instant
+    pub fn status_checklist(&self, status: Status) -> &[String] {
+        self.status_overrides.iter().find(|o| o.status == status).map_or(&[], |o| o.checklist.as_slice())
+    }
+
+    /// Fields a card created in the column is asked for after its name, leaving out
+    /// deleted ones; empty for the single name prompt
+    pub fn new_card_fields(&self, status: Status) -> Vec<&Field> {
+        let ids = self.status_overrides.iter().find(|o| o.status == status).map_or(&[][..], |o| o.new_card_fields.as_slice());
+        ids.iter().filter_map(|id| self.fields.iter().find(|f| f.id == *id)).collect()
+    }
+
+    /// The card's project and column as the AI fill sees them
+    fn card_context(&self, session: &Session) -> crate::ai::CardContext {
+        crate::ai::CardContext {
+            project: Some(self.project.name.clone()),
+            status: Some(self.status_label(session.status).to_string()),
+        }
+    }
+
+    /// Display label for a status, using this project's override if set
+    pub fn status_label(&self, status: Status) -> &str {
+        db::resolve_status_label(&self.status_overrides, status)
+    }
+
+    /// Color name for a status, if this project set one
+    pub fn status_color(&self, status: Status) -> Option<&str> {
+        self.status_overrides
+            .iter()
+            .find(|o| o.status == status && !o.color.is_empty())
+            .map(|o| o.color.as_str())
+    }
+
+    pub fn refresh_tmux_sessions(&mut self) {
+        // Activity-sorted columns reorder as terminals work; the selection follows its card
+        let selected = self.selected_session().map(|s| s.id);
+        let listing = self.tmux.poll_workbench_panes();
+        for session_id in stale_tmux_sessions(&self.sessions, listing.as_ref().ok()) {
+            let _ = self.db.clear_tmux_session(session_id);
+        }
+        (self.pane_states, self.tmux_error) = match listing {
+            Ok(states) => (states, None),
+            Err(e) => (HashMap::new(), Some(e.to_string())),
+        };
+        self.active_tmux_sessions = live_sessions(&self.pane_states);
+        self.sessions_waiting_input = waiting_sessions(&*self.tmux, &self.pane_states, &self.sessions);
+        if let Ok(today) = self.db.local_today() {
+            self.today = today;
+        }
+        self.update_waiting_clocks();
+        self.refresh_card_details();
+        self.refresh_worktree_heads();
+        self.sync_notes_files();
+        self.sample_time();
+        self.refresh_time_spent();
         let status = Status::all()[self.selected_column];
-        let count = self.sessions_by_status(status).len();
-        if count == 0 {
-            self.selected_row = 0;
-        } else if self.selected_row >= count {
-            self.selected_row = count - 1;
+        if let Some(id) = selected.filter(|_| self.column_sort(status) == ColumnSort::Activity)
+            && let Some(row) = self.sessions_by_status(status).iter().position(|s| s.id == id)
+        {
+            self.selected_row = row;
+        }
+        self.last_tmux_refresh = Instant::now();
+        if self.follow_active {
+            self.update_follow_target();
+        }
+        if self.view == View::Overview && let Err(e) = self.refresh_overview() {
+            tracing::warn!(error = %e, "could not refresh the overview");
+        }
+
+        self.write_status_snapshot();
+    }
+
+    /// Whether the project records time spent; on unless turned off
+    fn tracks_time(&self) -> bool {
+        self.project_setting(ProjectSetting::TrackTime).as_deref() != Some("off")
+    }
+
+    /// Credit the time since the last poll to each session a client shows whose terminal
+    /// had activity lately
+    fn sample_time(&mut self) {
+        let now = db::unix_now();
+        let elapsed = self.time_sampled_at.replace(now).map_or(0, |at| now - at);
+        if elapsed <= 0 || elapsed > MAX_SAMPLE_GAP_SECS || !self.tracks_time() {
+            return;
+        }
+        for session in &self.sessions {
+            let Some(pane) = session.tmux_window.as_ref().and_then(|name| self.pane_states.get(name)) else {
+                continue;
+            };
+            if pane.clients > 0 && now - pane.activity <= IDLE_SECS
+                && let Err(e) = self.db.add_time_sample(session.id, now, elapsed)
+            {
+                tracing::warn!(session_id = session.id, error = %e, "could not record time");
+            }
+        }
+    }
+
+    /// Reload the field value cache; after every refresh and each batch write
+    fn refresh_field_values(&mut self) {
+        match self.db.field_values_by_session(self.project.id) {
+            Ok(values) => self.field_values = values,
+            Err(e) => tracing::warn!(error = %e, "could not load field values"),
+        }
+    }
+
+    /// A session's value for a field as of the last refresh, empty when unset
+    pub fn field_value(&self, session_id: i64, field_id: i64) -> &str {
+        self.field_values.get(&(session_id, field_id)).map_or("", |(value, _)| value.as_str())
+    }
+
+    /// Who wrote a session's value for a field; unset values count as typed in
+    pub fn field_source(&self, session_id: i64, field_id: i64) -> ValueSource {
+        self.field_values.get(&(session_id, field_id)).map(|(_, source)| *source).unwrap_or_default()
+    }
+
+    /// Write a field value and keep the cache in step without reloading the board
+    fn set_field_value(&mut self, session_id: i64, field_id: i64, value: &str, source: ValueSource) -> Result<()> {
+        self.db.set_session_field_value(session_id, field_id, value, source)?;
+        self.field_values.insert((session_id, field_id), (value.to_string(), source));
+        Ok(())
+    }
+
+    fn refresh_time_spent(&mut self) {
+        match self.db.time_spent_by_session(self.project.id) {
+            Ok(spent) => self.time_spent = spent,
+            Err(e) => tracing::warn!(error = %e, "could not load time spent"),
+        }
+    }
+
+    /// Credit an attach outside tmux, which stops the board from polling, to its session:
+    /// from `since` until the terminal's last activity plus the idle allowance
+    pub fn credit_attached_time(&mut self, tmux_name: &str, since: i64) {
+        self.refresh_tmux_sessions();
+        let Some(session) = self.sessions.iter().find(|s| s.tmux_window.as_deref() == Some(tmux_name)) else {
+            return;
+        };
+        let Some(pane) = self.pane_states.get(tmux_name) else { return };
+        let now = db::unix_now();
+        let seconds = now.min(pane.activity + IDLE_SECS) - since;
+        if seconds > 0 && self.tracks_time() && let Err(e) = self.db.add_time_sample(session.id, now, seconds) {
+            tracing::warn!(session_id = session.id, error = %e, "could not record time");
+        }
+        self.refresh_time_spent();
+    }
+
+    /// Start clocks for newly waiting sessions; drop clocks only after the session
+    /// has been seen not waiting for a few consecutive refreshes
+    fn update_waiting_clocks(&mut self) {
+        let now = Instant::now();
+        for name in &self.sessions_waiting_input {
+            self.waiting_since
+                .entry(name.clone())
+                .and_modify(|clock| clock.missed = 0)
+                .or_insert(WaitingClock { since: now, missed: 0 });
+        }
+        let waiting = &self.sessions_waiting_input;
+        self.waiting_since.retain(|name, clock| {
+            if waiting.contains(name) {
+                return true;
+            }
+            clock.missed += 1;
+            clock.missed < WAITING_GRACE_REFRESHES
+        });
+    }
+
+    /// How long a session has been waiting for input, if it is waiting
+    pub fn waiting_duration(&self, session: &Session) -> Option<Duration> {
+        let name = session.tmux_window.as_ref()?;
+        if !self.sessions_waiting_input.contains(name) {
+            return None;
+        }
+        self.waiting_since.get(name).map(|clock| clock.since.elapsed())
+    }
+
+    /// Session shown in the peek overlay: the follow target, or else the selection
+    pub fn peek_session(&self) -> Option<&Session> {
+        if self.follow_active {
+            let (id, _) = self.follow_target?;
+            return self.sessions.iter().find(|s| s.id == id);
+        }
+        self.selected_session()
+    }
+
+    fn update_follow_target(&mut self) {
+        let now = db::unix_now();
+        let candidates: Vec<(i64, Option<i64>, Option<i64>)> = self.sessions
+            .iter()
+            .filter_map(|s| {
+                let name = s.tmux_window.as_ref().filter(|n| self.active_tmux_sessions.contains(*n))?;
+                let activity = self.pane_states.get(name).map(|p| p.activity);
+                let waiting_since = self.waiting_duration(s).map(|d| now - d.as_secs() as i64);
+                Some((s.id, activity, waiting_since))
+            })
+            .collect();
+        // Keep the old target when nothing is active so the overlay doesn't go blank
+        if let Some(target) = pick_follow_target(&candidates) {
+            self.follow_target = Some(target);
+        }
+    }
+
+    /// The pane picked for `session_id` in the peek overlay, if any
+    pub fn peek_pane_id(&self, session_id: i64) -> Option<&str> {
+        self.peek_pane.as_ref().filter(|(id, _)| *id == session_id).map(|(_, pane)| pane.as_str())
+    }
+
+    /// Step the peek overlay to the next or previous pane of the peeked session
+    fn cycle_peek_pane(&mut self, step: isize) {
+        let Some(session) = self.peek_session() else { return };
+        let Some(ref tmux_name) = session.tmux_window else { return };
+        let session_id = session.id;
+        let panes = self.tmux.list_panes(tmux_name);
+        if panes.len() < 2 {
+            self.status_message = Some("The session has a single pane".to_string());
+            return;
+        }
+        let current = tmux::resolve_pane(&panes, self.peek_pane_id(session_id))
+            .and_then(|pane| panes.iter().position(|p| p.id == pane.id))
+            .unwrap_or(0);
+        let next = (current as isize + step).rem_euclid(panes.len() as isize) as usize;
+        self.peek_pane = Some((session_id, panes[next].id.clone()));
+    }
+
+    /// Re-capture the peeked pane when the peek moved to another session or pane, or the
+    /// last capture is older than PEEK_REFRESH_INTERVAL. Called before each draw
+    pub fn refresh_peek_capture(&mut self) {
+        let target = self.peek_session().filter(|_| self.peek_active).and_then(|s| {
+            let name = s.tmux_window.clone()?;
+            Some((s.id, name, self.peek_pane_id(s.id).map(str::to_string)))
+        });
+        let Some((session_id, tmux_name, chosen_pane)) = target else {
+            if self.peek_capture.take().is_some() {
+                self.needs_redraw = true;
+            }
+            return;
+        };
+        if self.peek_capture.as_ref().is_some_and(|c| {
+            c.session_id == session_id && c.chosen_pane == chosen_pane && c.taken.elapsed() < PEEK_REFRESH_INTERVAL
+        }) {
+            return;
+        }
+        let panes = self.tmux.list_panes(&tmux_name);
+        let pane = tmux::resolve_pane(&panes, chosen_pane.as_deref()).filter(|_| panes.len() > 1);
+        let pane_label = pane.map(|p| format!(" [{} {}]", p.id, p.command)).unwrap_or_default();
+        let content = self.tmux.capture_pane_joined(&tmux_name, pane.map(|p| p.id.as_str()))
+            .unwrap_or_else(|| "(no content)".to_string());
+        let capture = PeekCapture::new(session_id, chosen_pane, pane_label, &content);
+        if self.peek_capture.as_ref().is_none_or(|old| {
+            old.session_id != capture.session_id || old.pane_label != capture.pane_label || old.lines != capture.lines
+        }) {
+            self.needs_redraw = true;
+        }
+        self.peek_capture = Some(capture);
+    }
+
+    fn open_url_picker(&mut self) {
+        let urls = self.peek_capture.as_ref().map(|c| c.urls.clone()).unwrap_or_default();
+        if urls.is_empty() {
+            self.status_message = Some("No links in the pane".to_string());
+            return;
+        }
+        self.url_choices = urls;
+        self.selected_url = 0;
+        self.input_mode = InputMode::PickUrl;
+    }
+
+    fn open_picked_url(&mut self, idx: usize) {
+        let Some(url) = self.url_choices.get(idx) else { return };
+        self.status_message = Some(match desktop::open_url(url) {
+            Ok(()) => format!("Opened {}", url),
+            Err(e) => format!("Could not open link: {}", e),
+        });
+        self.input_mode = InputMode::Normal;
+    }
+
+    fn handle_pick_url_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_url + 1 < self.url_choices.len() => {
+                self.selected_url += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_url = self.selected_url.saturating_sub(1);
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                self.open_picked_url(c as usize - '1' as usize);
+            }
+            KeyCode::Enter => {
+                self.open_picked_url(self.selected_url);
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_follow(&mut self) {
+        if self.follow_active {
+            self.follow_active = false;
+            self.peek_active = false;
+            return;
+        }
+        self.follow_active = true;
+        self.peek_active = true;
+        self.follow_target = None;
+        self.update_follow_target();
+    }
+
+    /// The selection as it is now, for `restore_view_state` once the board is back
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            session_id: self.selected_session().map(|s| s.id),
+            column: self.selected_column,
+            row: self.selected_row,
+            peek_active: self.peek_active,
+        }
+    }
+
+    /// Put the selection back on the same session after the board was reloaded
+    pub fn restore_view_state(&mut self, state: ViewState) {
+        let columns: Vec<Vec<i64>> = Status::all()
+            .iter()
+            .map(|status| self.sessions_by_status(*status).iter().map(|s| s.id).collect())
+            .collect();
+        (self.selected_column, self.selected_row) =
+            locate_selection(&columns, state.session_id, state.column, state.row);
+        self.peek_active = state.peek_active && self.selected_session().is_some_and(|s| s.tmux_window.is_some());
+    }
+
+    /// Move the board selection to the given session
+    pub fn select_session(&mut self, session_id: i64) {
+        let Some(session) = self.sessions.iter().find(|s| s.id == session_id) else { return };
+        let status = session.status;
+        let Some(column) = Status::all().iter().position(|s| *s == status) else { return };
+        let row = self.sessions_by_status(status)
+            .iter()
+            .position(|s| s.id == session_id)
+            .unwrap_or(0);
+        self.selected_column = column;
+        self.selected_row = row;
+    }
+
+    /// Jump to the next waiting session, longest-waiting first, wrapping around
+    fn jump_to_next_waiting(&mut self) {
+        let skip_blocked = self.project_setting(ProjectSetting::SkipBlocked).as_deref() == Some("on");
+        let mut waiting: Vec<(i64, Duration)> = self.sessions
+            .iter()
+            .filter(|s| !(skip_blocked && self.blocked_by(s.id).is_some()))
+            .filter_map(|s| self.waiting_duration(s).map(|d| (s.id, d)))
+            .collect();
+        if waiting.is_empty() {
+            self.status_message = Some(match skip_blocked {
+                true => "No unblocked sessions waiting for input".to_string(),
+                false => "No sessions waiting for input".to_string(),
+            });
+            return;
+        }
+        waiting.sort_by_key(|(_, d)| std::cmp::Reverse(*d));
+
+        let current = self.selected_session().map(|s| s.id);
+        let next = match waiting.iter().position(|(id, _)| Some(*id) == current) {
+            Some(i) => waiting[(i + 1) % waiting.len()].0,
+            None => waiting[0].0,
+        };
+        self.select_session(next);
+    }
+
+    /// Sessions needing attention, most urgent first
+    pub fn queue(&self) -> Vec<QueueItem> {
+        let waiting: HashMap<i64, Duration> = self.sessions
+            .iter()
+            .filter_map(|s| self.waiting_duration(s).map(|d| (s.id, d)))
+            .collect();
+        build_queue(&self.sessions, &waiting, db::unix_now())
+    }
+
+    fn open_queue(&mut self) {
+        self.view = View::Queue;
+        self.peek_active = false;
+        self.follow_active = false;
+        let queue = self.queue();
+        let current = self.selected_session().map(|s| s.id);
+        if !queue.iter().any(|item| Some(item.session_id) == current) && let Some(first) = queue.first() {
+            self.select_session(first.session_id);
+        }
+    }
+
+    /// Queue navigation moves the board selection, so actions reuse the board handlers
+    fn handle_queue_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Tab | KeyCode::Char('Q') => {
+                self.view = View::Kanban;
+                self.peek_active = false;
+                self.follow_active = false;
+            }
+            KeyCode::Char('j') | KeyCode::Down | KeyCode::Char('k') | KeyCode::Up => {
+                let queue = self.queue();
+                if queue.is_empty() {
+                    return Ok(AppAction::None);
+                }
+                let current = self.selected_session().map(|s| s.id);
+                let index = queue.iter().position(|item| Some(item.session_id) == current);
+                let next = match (index, key.code) {
+                    (None, _) => 0,
+                    (Some(i), KeyCode::Char('j') | KeyCode::Down) => (i + 1).min(queue.len() - 1),
+                    (Some(i), _) => i.saturating_sub(1),
+                };
+                self.select_session(queue[next].session_id);
+            }
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Char('l') | KeyCode::Right
+            | KeyCode::Char('w') | KeyCode::Char('s') | KeyCode::Char('X') | KeyCode::Char('g')
+            | KeyCode::Char('f') | KeyCode::Char('\'') => {}
+            _ => return self.handle_normal_key(key),
+        }
+        Ok(AppAction::None)
+    }
+
+    /// Show every project's waiting sessions, starting on this project's first one
+    pub fn open_overview(&mut self) -> Result<()> {
+        self.view = View::Overview;
+        self.peek_active = false;
+        self.follow_active = false;
+        self.refresh_overview()?;
+        self.select_overview_project(self.project.id);
+        Ok(())
+    }
+
+    /// Select a project's row in the overview, or the first row if it isn't listed
+    fn select_overview_project(&mut self, project_id: i64) {
+        let row = self.overview_rows().iter().position(|(project, waiting)| {
+            waiting.is_none() && self.overview[*project].project.id == project_id
+        });
+        self.selected_overview = row.unwrap_or(0);
+    }
+
+    /// Rebuild the overview from the last tmux poll, which lists every project's
+    /// terminals. Only projects with someone waiting are looked up in the database
+    pub fn refresh_overview(&mut self) -> Result<()> {
+        let mut active: HashMap<i64, usize> = HashMap::new();
+        let mut waiting: HashMap<i64, HashSet<i64>> = HashMap::new();
+        for (name, pane) in &self.pane_states {
+            let Some((project_id, session_id)) = tmux::parse_session_name(name) else { continue };
+            if pane.exit_status.is_none() {
+                *active.entry(project_id).or_default() += 1;
+            }
+            if self.sessions_waiting_input.contains(name) {
+                waiting.entry(project_id).or_default().insert(session_id);
+            }
+        }
+
+        let mut overview = Vec::new();
+        for project in self.db.list_projects()? {
+            // The open project is listed even when archived
+            if project.archived && !self.show_archived_projects && project.id != self.project.id {
+                continue;
+            }
+            let mut sessions = Vec::new();
+            if let Some(ids) = waiting.get(&project.id) {
+                let listed = if project.id == self.project.id { self.sessions.clone() } else { self.db.list_sessions(project.id)? };
+                sessions = listed
+                    .into_iter()
+                    .filter(|s| ids.contains(&s.id))
+                    .filter_map(|s| Some(OverviewSession { id: s.id, tmux_name: s.tmux_window?, name: s.name }))
+                    .collect();
+                sessions.sort_by_key(|s| s.name.to_lowercase());
+            }
+            let active = active.get(&project.id).copied().unwrap_or(0);
+            overview.push(OverviewProject { project, active, waiting: sessions });
+        }
+        self.overview = overview;
+        self.selected_overview = self.selected_overview.min(self.overview_rows().len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Overview rows in order, as (project index, waiting session index)
+    pub fn overview_rows(&self) -> Vec<(usize, Option<usize>)> {
+        self.overview
+            .iter()
+            .enumerate()
+            .flat_map(|(i, entry)| std::iter::once((i, None)).chain((0..entry.waiting.len()).map(move |w| (i, Some(w)))))
+            .collect()
+    }
+
+    fn handle_overview_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        let rows = self.overview_rows();
+        let selected = rows.get(self.selected_overview).copied();
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('V') => {
+                self.view = View::Kanban;
+            }
+            KeyCode::Char('q') => self.request_quit(),
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.selected_overview = (self.selected_overview + 1).min(rows.len().saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_overview = self.selected_overview.saturating_sub(1);
+            }
+            // Enter attaches straight to a waiting session, wherever it lives
+            KeyCode::Enter if selected.is_some_and(|(_, waiting)| waiting.is_some()) => {
+                let Some((project, Some(waiting))) = selected else { return Ok(AppAction::None) };
+                return Ok(AppAction::AttachTmux(self.overview[project].waiting[waiting].tmux_name.clone()));
+            }
+            KeyCode::Enter | KeyCode::Char('o') | KeyCode::Char('l') | KeyCode::Right => {
+                let Some((project, waiting)) = selected else { return Ok(AppAction::None) };
+                let target = self.overview[project].project.clone();
+                let session_id = waiting.map(|w| self.overview[project].waiting[w].id);
+                if target.id == self.project.id {
+                    self.view = View::Kanban;
+                    if let Some(id) = session_id {
+                        self.select_session(id);
+                    }
+                } else {
+                    self.switch_to_project(target, session_id)?;
+                }
+            }
+            KeyCode::Char('*') => {
+                let Some((project, _)) = selected else { return Ok(AppAction::None) };
+                let project = self.overview[project].project.clone();
+                self.db.set_project_pinned(project.id, !project.pinned)?;
+                self.refresh_overview()?;
+                self.select_overview_project(project.id);
+                self.status_message = Some(match project.pinned {
+                    true => format!("Unpinned {}", project.name),
+                    false => format!("Pinned {}", project.name),
+                });
+            }
+            KeyCode::Char('a') => {
+                let Some((project, _)) = selected else { return Ok(AppAction::None) };
+                let project = self.overview[project].project.clone();
+                self.db.set_project_archived(project.id, !project.archived)?;
+                self.refresh_overview()?;
+                self.select_overview_project(project.id);
+                self.status_message = Some(match (project.archived, self.show_archived_projects) {
+                    (true, _) => format!("Restored {}", project.name),
+                    (false, true) => format!("Archived {}", project.name),
+                    (false, false) => format!("Archived {} (A: show archived projects)", project.name),
+                });
+            }
+            KeyCode::Char('A') => {
+                let project_id = selected.map(|(project, _)| self.overview[project].project.id);
+                self.show_archived_projects = !self.show_archived_projects;
+                self.refresh_overview()?;
+                self.select_overview_project(project_id.unwrap_or(self.project.id));
+            }
+            _ => {}
+        }
+        Ok(AppAction::None)
+    }
+
+    /// Write the JSON status snapshot if enabled with `--status-file`
+    pub fn write_status_snapshot(&self) {
+        if !self.write_status_file {
+            return;
+        }
+        let snapshot = Snapshot::build(
+            &self.project,
+            &self.sessions,
+            &self.status_overrides,
+            &self.sessions_waiting_input,
+            self.today,
+        );
+        let _ = status::write_status_file(&snapshot);
+    }
+
+    pub fn has_active_terminal(&self, session: &Session) -> bool {
+        session.tmux_window.as_ref()
+            .map(|name| self.active_tmux_sessions.contains(name))
+            .unwrap_or(false)
+    }
+
+    /// Exit status of the session's agent if its pane died and was retained
+    pub fn exit_status(&self, session: &Session) -> Option<i32> {
+        let name = session.tmux_window.as_ref()?;
+        self.pane_states.get(name)?.exit_status
+    }
+
+    /// Foreground command of the session's tmux pane, if it has one running
+    pub fn pane_command(&self, session: &Session) -> Option<&str> {
+        let name = session.tmux_window.as_ref()?;
+        self.pane_states.get(name).map(|p| p.command.as_str())
+    }
+
+    pub fn is_waiting_for_input(&self, session: &Session) -> bool {
+        session.tmux_window.as_ref()
+            .map(|name| self.sessions_waiting_input.contains(name))
+            .unwrap_or(false)
+    }
+
+    /// The window title, when it differs from the one last returned. `forget_title`
+    /// makes the next call return it again (the terminal's title was restored meanwhile)
+    pub fn title_change(&mut self) -> Option<String> {
+        let format = self.title_format.as_deref()?;
+        let running = self.sessions.iter().filter(|s| self.has_active_terminal(s)).count();
+        let waiting = self.sessions.iter().filter(|s| self.is_waiting_for_input(s)).count();
+        let title = status::window_title(format, &self.project.name, running, waiting);
+        if self.shown_title.as_ref() == Some(&title) {
+            return None;
+        }
+        self.shown_title = Some(title.clone());
+        Some(title)
+    }
+
+    pub fn forget_title(&mut self) {
+        self.shown_title = None;
+    }
+
+    /// Note input or a visible change: redraw, and poll at full speed again
+    fn mark_active(&mut self) {
+        self.needs_redraw = true;
+        self.last_activity = Instant::now();
+    }
+
+    /// Redraw at `at` (or earlier, if another redraw is due before)
+    fn redraw_at(&mut self, at: Instant) {
+        self.redraw_at = Some(self.redraw_at.map_or(at, |due| due.min(at)));
+    }
+
+    /// Whether to draw now: something changed, and the last frame is old enough
+    pub fn should_draw(&self) -> bool {
+        self.needs_redraw && self.last_frame.is_none_or(|at| at.elapsed() >= MIN_FRAME_INTERVAL)
+    }
+
+    /// Count a drawn frame
+    pub fn frame_drawn(&mut self) {
+        let now = Instant::now();
+        self.needs_redraw = false;
+        self.last_frame = Some(now);
+        self.frames += 1;
+        self.recent_frames.push_back(now);
+        while self.recent_frames.front().is_some_and(|at| now.duration_since(*at) > FRAME_RATE_WINDOW) {
+            self.recent_frames.pop_front();
+        }
+    }
+
+    /// Frames per second over the last `FRAME_RATE_WINDOW`
+    pub fn frame_rate(&self) -> f64 {
+        self.recent_frames.len() as f64 / FRAME_RATE_WINDOW.as_secs_f64()
+    }
+
+    /// Whether a background job (AI, Jira, worktree scan, branch diff, batch fill) is running
+    fn background_busy(&self) -> bool {
+        self.ai_result_rx.is_some()
+            || self.jira_rx.is_some()
+            || self.worktree_scan_rx.is_some()
+            || self.branch_changes_rx.is_some()
+            || self.startup_trial_rx.is_some()
+            || self.batch_fill_rx.is_some()
+    }
+
+    /// How long to wait for input: the backed-off interval, but never past the next
+    /// tmux refresh, peek re-capture or timed redraw, nor a frame held back by the cap
+    fn poll_timeout(&self) -> Duration {
+        let mut timeout = idle_poll_interval(self.poll_interval, self.last_activity.elapsed())
+            .min(TMUX_REFRESH_INTERVAL.saturating_sub(self.last_tmux_refresh.elapsed()));
+        if self.peek_active {
+            timeout = timeout.min(PEEK_REFRESH_INTERVAL);
+        }
+        if let Some(at) = self.redraw_at {
+            timeout = timeout.min(at.saturating_duration_since(Instant::now()));
+        }
+        if let (true, Some(at)) = (self.needs_redraw, self.last_frame) {
+            timeout = timeout.min(MIN_FRAME_INTERVAL.saturating_sub(at.elapsed()));
+        }
+        timeout
+    }
+
+    pub fn handle_events(&mut self) -> Result<AppAction> {
+        // A job running now may finish below; keep polling fast until its result is in.
+        // Each result asks for its own redraw
+        if self.background_busy() {
+            self.last_activity = Instant::now();
+        }
+        if self.redraw_at.is_some_and(|at| at <= Instant::now()) {
+            self.redraw_at = None;
+            self.needs_redraw = true;
+        }
+        // Check for AI results from background thread
+        self.check_ai_result();
+        self.check_jira_result();
+        self.check_worktree_scan();
+        self.check_branch_changes();
+        self.check_worktree_heads();
+        self.check_startup_trial();
+        self.check_batch_fill()?;
+
+        // A reload asked for with a signal waits until no popup holds ids it could invalidate
+        if self.input_mode == InputMode::Normal && self.reload_signal.swap(false, Ordering::Relaxed) {
+            self.reload_settings()?;
+            self.needs_redraw = true;
+        }
+
+        if self.last_lock_heartbeat.elapsed() >= LOCK_HEARTBEAT_INTERVAL {
+            let had_holder = self.lock_holder.is_some();
+            self.hold_lock()?;
+            self.needs_redraw |= had_holder != self.lock_holder.is_some();
+        }
+
+        // Keep active/waiting indicators (and waiting clocks) current
+        if self.last_tmux_refresh.elapsed() >= TMUX_REFRESH_INTERVAL {
+            let before = (self.active_tmux_sessions.clone(), self.sessions_waiting_input.clone());
+            let now = db::unix_now();
+            if self.db.data_version()? != self.data_version {
+                self.refresh_external()?;
+            } else if self.sessions.iter().any(|s| s.snoozed_until.is_some_and(|t| t <= now)) {
+                self.refresh_sessions()?;
+            } else {
+                self.refresh_tmux_sessions();
+            }
+            if self.run_watch_rules()? {
+                self.refresh_sessions()?;
+            }
+            // The unblocked mark lasts one poll
+            if self.unblocked_at.is_some_and(|at| at.elapsed() >= TMUX_REFRESH_INTERVAL) {
+                self.just_unblocked.clear();
+                self.unblocked_at = None;
+            }
+            // Waiting clocks tick on every refresh; a session starting or stopping
+            // counts as activity
+            self.needs_redraw = true;
+            if before != (self.active_tmux_sessions.clone(), self.sessions_waiting_input.clone()) {
+                self.mark_active();
+            }
+        }
+
+        if event::poll(self.poll_timeout())? {
+            let event = event::read()?;
+            match event {
+                Event::Key(_) | Event::Paste(_) => self.mark_active(),
+                Event::Resize(_, _) => self.needs_redraw = true,
+                _ => {}
+            }
+            match event {
+                Event::Key(key) => return self.handle_key(key),
+                Event::Paste(text) if !self.ai_running => {
+                    self.handle_paste(&text);
+                }
+                _ => {}
+            }
+        }
+        Ok(AppAction::None)
+    }
+
+    /// Act on a key press in whatever mode and view the board is in. Inside tmux, an
+    /// attach to a session another client already shows asks first
+    pub fn handle_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        let confirmed = self.input_mode == InputMode::ConfirmSharedAttach;
+        let action = self.dispatch_key(key)?;
+        match action {
+            AppAction::AttachTmux(ref name) if !confirmed && self.inside_tmux => {
+                let clients = self.pane_states.get(name).map_or(0, |pane| pane.clients);
+                if clients == 0 {
+                    return Ok(action);
+                }
+                self.shared_attach = Some(name.clone());
+                self.input_mode = InputMode::ConfirmSharedAttach;
+                Ok(AppAction::None)
+            }
+            _ => Ok(action),
+        }
+    }
+
+    fn dispatch_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        // Clear status message on any keypress
+        self.status_message = None;
+
+        // Ignore key events while AI is running
+        if self.ai_running {
+            return Ok(AppAction::None);
+        }
+        // Caught in any mode, so an open popup can refuse it rather than
+        // typing into it
+        if is_switch_project_key(&key) {
+            self.switch_to_previous_project()?;
+            return Ok(AppAction::None);
+        }
+        // Hidden: how often the board redraws
+        if key.code == KeyCode::F(12) {
+            self.show_frame_stats = !self.show_frame_stats;
+            return Ok(AppAction::None);
+        }
+        if self.input_mode == InputMode::Normal
+            && key.code == KeyCode::Char('r')
+            && key.modifiers.contains(KeyModifiers::CONTROL)
+        {
+            self.reload_settings()?;
+            return Ok(AppAction::None);
+        }
+        match self.input_mode {
+            InputMode::Normal => {
+                match self.view {
+                    View::Kanban if self.read_only && !is_read_only_key(&key, self.peek_active) => {
+                        let host = self.lock_holder.as_ref().map_or("another board", |h| h.hostname.as_str());
+                        self.status_message = Some(format!("Read-only while open on {} (U: allow edits)", host));
+                    }
+                    View::Kanban => return self.handle_normal_key(key),
+                    View::Settings => self.handle_settings_key(key)?,
+                    View::Queue => return self.handle_queue_key(key),
+                    View::Overview => return self.handle_overview_key(key),
+                }
+            }
+            InputMode::NewSession => self.handle_input_key(key)?,
+            InputMode::NewCardField => self.handle_new_card_field_key(key)?,
+            InputMode::PlanSessions => self.handle_plan_sessions_key(key)?,
+            InputMode::Notes => self.handle_notes_key(key)?,
+            InputMode::EditSession => self.handle_edit_session_key(key)?,
+            InputMode::MoveSession => self.handle_move_key(key)?,
+            InputMode::ConfirmDelete => self.handle_confirm_delete_key(key)?,
+            InputMode::ConfirmDeleteDirty => self.handle_confirm_delete_dirty_key(key)?,
+            InputMode::ConfirmDeleteField => self.handle_confirm_delete_field_key(key)?,
+            InputMode::NewFieldName => self.handle_new_field_name_key(key)?,
+            InputMode::NewFieldDesc => self.handle_new_field_desc_key(key)?,
+            InputMode::EditFieldName => self.handle_edit_field_name_key(key)?,
+            InputMode::EditFieldDesc => self.handle_edit_field_desc_key(key)?,
+            InputMode::ViewComments => self.handle_view_comments_key(key)?,
+            InputMode::NewComment => self.handle_new_comment_key(key)?,
+            InputMode::SessionEnv => self.handle_session_env_key(key)?,
+            InputMode::NewEnvVar => self.handle_new_env_var_key(key)?,
+            InputMode::ConfirmApplyEnv => self.handle_confirm_apply_env_key(key)?,
+            InputMode::ConfirmSeedFields => self.handle_confirm_seed_fields_key(key)?,
+            InputMode::EditStatusLabel => self.handle_edit_status_label_key(key)?,
+            InputMode::WorktreeMaintenance => self.handle_worktree_maintenance_key(key)?,
+            InputMode::EditProjectSetting => self.handle_edit_project_setting_key(key)?,
+            InputMode::ConfirmPurgeSession => self.handle_confirm_purge_session_key(key)?,
+            InputMode::Grab => self.handle_grab_key(key)?,
+            InputMode::Snooze => self.handle_snooze_key(key)?,
+            InputMode::DueDate => self.handle_due_date_key(key)?,
+            InputMode::BlockedBy => self.handle_blocked_by_key(key)?,
+            InputMode::PickQuickField => self.handle_pick_quick_field_key(key)?,
+            InputMode::QuickFieldValue => self.handle_quick_field_value_key(key)?,
+            InputMode::ConfirmRelocate => self.handle_confirm_relocate_key(key)?,
+            InputMode::RepoConfig => self.handle_repo_config_key(key)?,
+            InputMode::Hints => return self.handle_hints_key(key),
+            InputMode::CardActions => self.handle_card_actions_key(key)?,
+            InputMode::DeadPane => return self.handle_dead_pane_key(key),
+            InputMode::ConfirmRecreateWorktree => return self.handle_recreate_worktree_key(key),
+            InputMode::ConfirmAdoptTerminal => return self.handle_adopt_terminal_key(key),
+            InputMode::ConfirmRestartTerminal => self.handle_restart_terminal_key(key)?,
+            InputMode::MoveChecklist => self.handle_move_checklist_key(key)?,
+            InputMode::EditStatusChecklist => self.handle_edit_status_checklist_key(key)?,
+            InputMode::EditStatusNewCardFields => self.handle_edit_status_new_card_fields_key(key)?,
+            InputMode::ConfirmQuit => self.handle_confirm_quit_key(key),
+            InputMode::ConfirmSharedAttach => return Ok(self.handle_confirm_shared_attach_key(key)),
+            InputMode::NewShortcut => self.handle_new_shortcut_key(key)?,
+            InputMode::BatchFillFields => self.handle_batch_fill_fields_key(key),
+            InputMode::BatchFill => self.handle_batch_fill_key(key),
+            InputMode::PickShortcut => self.handle_pick_shortcut_key(key)?,
+            InputMode::NewWatchRule => self.handle_new_watch_rule_key(key)?,
+            InputMode::CopyFieldsFrom => self.handle_copy_fields_from_key(key)?,
+            InputMode::PickCardLine => self.handle_pick_card_line_key(key)?,
+            InputMode::ShortcutValue => self.handle_shortcut_value_key(key)?,
+            InputMode::PromptTemplateName => self.handle_prompt_template_name_key(key),
+            InputMode::PromptTemplateBody => self.handle_prompt_template_body_key(key)?,
+            InputMode::PickPromptTemplate => self.handle_pick_prompt_template_key(key),
+            InputMode::BranchChanges => self.handle_branch_changes_key(key),
+            InputMode::Land => self.handle_land_key(key)?,
+            InputMode::StartupTrial => self.handle_startup_trial_key(key),
+            InputMode::ReloadProblems => {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::BoardChanges => {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') | KeyCode::Char('R')) {
+                    self.input_mode = InputMode::Normal;
+                }
+            }
+            InputMode::Transcripts => self.handle_transcripts_key(key),
+            InputMode::TranscriptView => self.handle_transcript_view_key(key),
+            InputMode::PickUrl => self.handle_pick_url_key(key),
+        }
+        Ok(AppAction::None)
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        match key.code {
+            KeyCode::Char('q') => self.request_quit(),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true
+            }
+            KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return self.jump_to_last_attached();
+            }
+            KeyCode::Char('`') => {
+                return self.jump_to_last_attached();
+            }
+            KeyCode::Char('n') => {
+                self.input_mode = InputMode::NewSession;
+                self.input_buffer.clear();
+                // A column that asks for fields takes the card; others leave it in Planned
+                let status = Status::all()[self.selected_column];
+                self.new_session_status = Some(status).filter(|s| !self.new_card_fields(*s).is_empty());
+                // From the peek overlay, spin off a follow-up prefilled from the pane
+                if self.peek_active && let Some(session) = self.peek_session() {
+                    let source_id = session.id;
+                    let pane = self.peek_pane_id(source_id);
+                    let content = session.tmux_window.as_deref().and_then(|name| self.tmux.capture_pane_content(name, pane));
+                    self.input_buffer = content.as_deref().and_then(last_meaningful_line).unwrap_or_default();
+                    self.spinoff_source_id = Some(source_id);
+                }
+            }
+            KeyCode::Char('U') => {
+                self.read_only = !self.read_only;
+                self.status_message = Some(match self.read_only {
+                    true => "Read-only: keys that change the board are off (U: allow edits)".to_string(),
+                    false => "Edits allowed".to_string(),
+                });
+            }
+            KeyCode::Char('I') => {
+                self.input_mode = InputMode::PlanSessions;
+                self.input_buffer.clear();
+                self.input_error = None;
+                self.confirm_discard_plan = false;
+            }
+            KeyCode::Char('N') => {
+                if self.shortcuts.is_empty() {
+                    self.status_message = Some("No shortcuts yet (add them in Settings > Shortcuts)".to_string());
+                } else {
+                    self.input_mode = InputMode::PickShortcut;
+                }
+            }
+            KeyCode::Char('h') | KeyCode::Left if self.selected_column > 0 => {
+                self.selected_column -= 1;
+                self.clamp_row();
+            }
+            KeyCode::Char('l') | KeyCode::Right if self.selected_column < Status::all().len() - 1 => {
+                self.selected_column += 1;
+                self.clamp_row();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                let status = Status::all()[self.selected_column];
+                let count = self.sessions_by_status(status).len();
+                if self.selected_row < count.saturating_sub(1) {
+                    self.selected_row += 1;
+                }
+                // Browsing the archive: fetch the next page on reaching the last card
+                if status == Status::Done && self.show_archived && self.selected_row + 1 >= count
+                    && self.archived_loaded < self.archived_count
+                {
+                    self.load_more_archived()?;
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_row > 0 => {
+                self.selected_row -= 1;
+            }
+            KeyCode::Char('m') => {
+                if let Some(session) = self.selected_session() {
+                    self.moving_session_id = Some(session.id);
+                    self.input_mode = InputMode::MoveSession;
+                }
+            }
+            KeyCode::Char('g') => {
+                if let Some((id, status)) = self.selected_session().map(|s| (s.id, s.status)) {
+                    self.grabbed_session_id = Some(id);
+                    self.grabbed_from = Some(status);
+                    self.input_mode = InputMode::Grab;
+                }
+            }
+            KeyCode::Char(';') => self.open_quick_field(),
+            KeyCode::Char('v') => {
+                if let Some(session_id) = self.selected_session().map(|s| s.id) {
+                    if !self.marked_sessions.remove(&session_id) {
+                        self.marked_sessions.insert(session_id);
+                    }
+                    self.status_message = Some(match self.marked_sessions.len() {
+                        0 => "No cards marked".to_string(),
+                        n => format!("{} marked (;: set a field on all, Esc: clear marks)", n),
+                    });
+                }
+            }
+            KeyCode::Esc if !self.marked_sessions.is_empty() => {
+                self.marked_sessions.clear();
+                self.status_message = Some("Marks cleared".to_string());
+            }
+            KeyCode::Char('b') => {
+                if let Some(session_id) = self.selected_session().map(|s| s.id) {
+                    self.blocking_session_id = Some(session_id);
+                    self.input_buffer.clear();
+                    self.input_error = None;
+                    self.input_mode = InputMode::BlockedBy;
+                }
+            }
+            KeyCode::Char('z') => {
+                if let Some(session) = self.selected_session() {
+                    let session_id = session.id;
+                    let running = self.has_active_terminal(session);
+                    self.snoozing_session_id = Some(session_id);
+                    self.input_error = running
+                        .then(|| "This session has a running terminal; it keeps running while snoozed".to_string());
+                    self.input_buffer.clear();
+                    self.input_mode = InputMode::Snooze;
+                }
+            }
+            KeyCode::Char('\'') | KeyCode::Char('f') => {
+                let (width, height) = crossterm::terminal::size().unwrap_or((0, 0));
+                self.hint_targets = crate::ui::visible_card_order(self, ratatui::layout::Rect::new(0, 0, width, height));
+                self.hint_targets.truncate(9);
+                if !self.hint_targets.is_empty() {
+                    self.input_mode = InputMode::Hints;
+                }
+            }
+            KeyCode::Char('.') => {
+                if let Some(session) = self.selected_session() {
+                    let actions =
+                        CardAction::available(session, self.has_active_terminal(session), self.branch_out_of_sync(session));
+                    if actions.is_empty() {
+                        self.status_message = Some("No actions for this session".to_string());
+                    } else {
+                        self.card_actions = actions;
+                        self.selected_card_action = 0;
+                        self.input_mode = InputMode::CardActions;
+                    }
+                }
+            }
+            KeyCode::Char('Z') => {
+                self.show_snoozed = !self.show_snoozed;
+                self.refresh_column_ages();
+                self.clamp_row();
+            }
+            KeyCode::Char('*') => {
+                let selected = self.selected_session().map(|s| s.id);
+                if let Some(session) = selected.map(|id| self.fresh_session(id)).transpose()?.flatten() {
+                    self.db.set_session_pinned(session.id, !session.pinned)?;
+                    self.refresh_sessions()?;
+                    // The card jumps to (or from) the top of its column; keep it selected
+                    self.select_session(session.id);
+                    self.clamp_row();
+                    let verb = if session.pinned { "Unpinned" } else { "Pinned" };
+                    self.status_message = Some(format!("{} {}", verb, session.name));
+                }
+            }
+            KeyCode::Char('O') => {
+                if self.archived_count == 0 && !self.show_archived {
+                    self.status_message = Some("No archived Done cards".to_string());
+                } else {
+                    self.show_archived = !self.show_archived;
+                    self.archived_loaded = 0;
+                    self.refresh_sessions()?;
+                    self.clamp_row();
+                }
+            }
+            KeyCode::Char('P') => {
+                self.pinned_only = !self.pinned_only;
+                self.refresh_column_ages();
+                self.clamp_row();
+            }
+            KeyCode::Char('C') => {
+                if let Some((id, due)) = self.selected_session().map(|s| (s.id, s.due)) {
+                    self.due_session_id = Some(id);
+                    self.input_buffer = due.map(due::format).unwrap_or_default();
+                    self.input_error = None;
+                    self.input_mode = InputMode::DueDate;
+                }
+            }
+            KeyCode::Char('S') => {
+                self.sort_by_due = !self.sort_by_due;
+                self.status_message = Some(match self.sort_by_due {
+                    true => "Sorted by due date (S: by age)".to_string(),
+                    false => "Sorted by age".to_string(),
+                });
+            }
+            KeyCode::Char('W') => {
+                self.due_this_week = !self.due_this_week;
+                self.refresh_column_ages();
+                self.clamp_row();
+            }
+            KeyCode::Char('M') => {
+                if self.current_owner.is_none() {
+                    self.status_message = Some("Don't know who you are: set git user.name or $USER".to_string());
+                } else {
+                    self.mine_only = !self.mine_only;
+                    self.refresh_column_ages();
+                    self.clamp_row();
+                }
+            }
+            KeyCode::Char('d') => {
+                let selected = self.selected_session().map(|s| s.id);
+                if let Some(session) = selected.map(|id| self.fresh_session(id)).transpose()?.flatten() {
+                    let session_id = session.id;
+                    let checkout_path = session.checkout_path.clone();
+                    self.deleting_session_id = Some(session_id);
+                    // Check if worktree is dirty
+                    if let Some(checkout_path) = checkout_path
+                        && let Some(dirty_status) = self.git.get_dirty_status(&checkout_path)
+                            && dirty_status.is_dirty()
+                        {
+                            self.deleting_dirty_status = Some(dirty_status);
+                            self.input_mode = InputMode::ConfirmDeleteDirty;
+                            return Ok(AppAction::None);
+                        }
+                    self.input_mode = InputMode::ConfirmDelete;
+                }
+            }
+            KeyCode::Char('r') => {
+                self.refresh_external()?;
+            }
+            KeyCode::Char('R') => {
+                if self.board_changes.is_empty() {
+                    self.status_message = Some("Nothing changed from outside workbench yet".to_string());
+                } else {
+                    self.input_mode = InputMode::BoardChanges;
+                }
+            }
+            KeyCode::Char('e') => {
+                let selected = self.selected_session().map(|s| s.id);
+                if let Some(session) = selected.map(|id| self.fresh_session(id)).transpose()?.flatten() {
+                    let session_id = session.id;
+                    let session_name = session.name.clone();
+                    self.editing_session_id = Some(session_id);
+                    self.edit_session_name = session_name.clone();
+                    self.edit_owner = session.owner.clone().unwrap_or_default();
+                    self.edit_agent = agent::for_session(&session).name;
+                    self.edit_row = 0;
+                    self.input_buffer = session_name;
+                    // Load field values
+                    let field_values: Vec<String> = self.fields.iter().map(|f| {
+                        self.db.get_session_field_value(session_id, f.id).unwrap_or_default()
+                    }).collect();
+                    self.edit_field_values = field_values;
+                    self.edit_field_sources = self.fields.iter().map(|f| {
+                        self.db.get_session_field_source(session_id, f.id).unwrap_or_default()
+                    }).collect();
+                    self.jira_fetch = None;
+                    self.input_mode = InputMode::EditSession;
+                }
+            }
+            KeyCode::Enter => {
+                return self.handle_enter_key();
+            }
+            KeyCode::Char(' ') => {
+                if self.follow_active {
+                    // Drop back to a normal peek of the selected session
+                    self.follow_active = false;
+                    self.peek_active = self.selected_session().and_then(|s| s.tmux_window.as_ref()).is_some();
+                } else if self.selected_session().and_then(|s| s.tmux_window.as_ref()).is_some() {
+                    self.peek_active = !self.peek_active;
+                }
+            }
+            KeyCode::Char(c @ (']' | '[')) if self.peek_active => {
+                self.cycle_peek_pane(if c == ']' { 1 } else { -1 });
+            }
+            KeyCode::Char(c @ (']' | '[' | '}' | '{')) => {
+                self.step_selected_session(c)?;
+            }
+            KeyCode::Char('o') if self.peek_active => {
+                self.open_url_picker();
+            }
+            KeyCode::Char('F') => {
+                self.toggle_follow();
+            }
+            KeyCode::Char('s') => {
+                self.view = View::Settings;
+                self.selected_field = 0;
+                self.detected_base_branch = self.repo_root.as_deref().and_then(|root| self.git.detect_default_branch(root));
+            }
+            KeyCode::Char('x') => {
+                self.cleanup_orphaned_tmux_sessions()?;
+            }
+            KeyCode::Char('w') => {
+                self.jump_to_next_waiting();
+            }
+            KeyCode::Tab | KeyCode::Char('Q') => {
+                self.open_queue();
+            }
+            KeyCode::Char('V') => {
+                self.open_overview()?;
+            }
+            KeyCode::Char('X') => {
+                self.open_worktree_maintenance();
+            }
+            KeyCode::Char('D') => {
+                self.open_branch_changes()?;
+            }
+            KeyCode::Char('L') => {
+                self.open_land()?;
+            }
+            KeyCode::Char('T') => {
+                self.open_transcripts()?;
+            }
+            KeyCode::Char('p') => {
+                self.open_notes()?;
+            }
+            KeyCode::Char('=') => {
+                self.zoomed = !self.zoomed;
+            }
+            KeyCode::Char('A') => {
+                self.open_batch_fill();
+            }
+            KeyCode::Char('E') => {
+                if let Some(session) = self.selected_session() {
+                    let session_id = session.id;
+                    self.env_session_id = Some(session_id);
+                    self.session_env = self.db.list_session_env(session_id)?;
+                    self.selected_env = 0;
+                    self.env_revealed = false;
+                    self.env_changed_keys.clear();
+                    self.input_mode = InputMode::SessionEnv;
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(session) = self.selected_session() {
+                    let session_id = session.id;
+                    self.comments_session_id = Some(session_id);
+                    self.comments = self.db.list_comments(session_id).unwrap_or_default();
+                    self.comments_scroll = 0;
+                    self.input_mode = InputMode::ViewComments;
+                }
+            }
+            _ => {}
+        }
+        Ok(AppAction::None)
+    }
+
+    fn cleanup_orphaned_tmux_sessions(&mut self) -> Result<()> {
+        // Get all tmux sessions for this project
+        let tmux_sessions = self.tmux.list_project_sessions(self.project.id);
+
+        // Get all tmux names that are tracked in the database
+        let tracked: std::collections::HashSet<String> = self.sessions
+            .iter()
+            .filter_map(|s| s.tmux_window.clone())
+            .collect();
+
+        // Kill any tmux session that isn't tracked
+        let mut killed = 0;
+        for tmux_name in tmux_sessions {
+            if !tracked.contains(&tmux_name) && self.tmux.kill_session(&tmux_name) {
+                killed += 1;
+            }
+        }
+
+        // Kill tracked sessions whose agent has exited (panes kept by remain-on-exit)
+        let mut exited = 0;
+        for session in &self.sessions {
+            if let (Some(tmux_name), Some(_)) = (&session.tmux_window, self.exit_status(session)) {
+                self.save_transcript(session.id, tmux_name);
+                if self.tmux.kill_session(tmux_name) {
+                    let _ = self.db.clear_tmux_session(session.id);
+                    exited += 1;
+                }
+            }
+        }
+
+        // Set status message
+        self.status_message = Some(match (killed, exited) {
+            (0, 0) => "No orphaned sessions found".to_string(),
+            (killed, 0) => format!("Cleaned up {} orphaned session{}", killed, if killed == 1 { "" } else { "s" }),
+            (killed, exited) => format!(
+                "Cleaned up {} orphaned and {} exited session{}",
+                killed,
+                exited,
+                if killed + exited == 1 { "" } else { "s" }
+            ),
+        });
+        if exited > 0 {
+            self.refresh_sessions()?;
+        }
+
+        // Refresh the session list
+        self.refresh_tmux_sessions();
+        Ok(())
+    }
+
+    /// Pick which fields to batch fill; visible fields are preselected
+    fn open_batch_fill(&mut self) {
+        if !self.ai_available {
+            self.status_message = Some(crate::ai::MISSING_MESSAGE.to_string());
+            return;
+        }
+        if self.fields.is_empty() {
+            self.status_message = Some("No custom fields to fill (add them in settings)".to_string());
+            return;
+        }
+        self.batch_fill_selected = self.fields.iter().map(|f| f.visible && f.in_ai).collect();
+        self.selected_batch_field = 0;
+        self.batch_fill_entries.clear();
+        self.input_mode = InputMode::BatchFillFields;
+    }
+
+    /// Indexes into `fields` of the rows the edit popup shows after the fixed ones
+    pub fn edit_field_indices(&self) -> Vec<usize> {
+        self.fields.iter().enumerate().filter(|(_, f)| f.in_edit).map(|(i, _)| i).collect()
+    }
+
+    /// Indexes into `fields` of the fields AI fill asks claude for, in the order sent
+    fn ai_field_indices(&self) -> Vec<usize> {
+        self.fields.iter().enumerate().filter(|(_, f)| f.in_ai).map(|(i, _)| i).collect()
+    }
+
+    /// Sessions with an empty value in any of `field_ids`
+    pub fn batch_fill_candidates(&self, field_ids: &[i64]) -> Vec<&Session> {
+        self.sessions
+            .iter()
+            .filter(|s| field_ids.iter().any(|f| self.field_value(s.id, *f).trim().is_empty()))
+            .collect()
+    }
+
+    fn selected_batch_field_ids(&self) -> Vec<i64> {
+        self.fields
+            .iter()
+            .zip(&self.batch_fill_selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(f, _)| f.id)
+            .collect()
+    }
+
+    fn handle_batch_fill_fields_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.selected_batch_field + 1 < self.fields.len() => {
+                self.selected_batch_field += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_batch_field = self.selected_batch_field.saturating_sub(1);
+            }
+            KeyCode::Char(' ') => {
+                if let Some(field) = self.fields.get(self.selected_batch_field).filter(|f| !f.in_ai) {
+                    self.status_message = Some(format!("{} is left out of AI fill (a in Settings > Fields)", field.name));
+                } else if let Some(selected) = self.batch_fill_selected.get_mut(self.selected_batch_field) {
+                    *selected = !*selected;
+                }
+            }
+            KeyCode::Enter => self.start_batch_fill(),
+            _ => {}
+        }
+    }
+
+    /// Fill the chosen fields of every session missing one, one claude call at a time
+    fn start_batch_fill(&mut self) {
+        let field_ids = self.selected_batch_field_ids();
+        let candidates: Vec<(i64, String, Option<String>, crate::ai::CardContext)> = self
+            .batch_fill_candidates(&field_ids)
+            .into_iter()
+            .map(|s| (s.id, s.name.clone(), s.tmux_window.clone(), self.card_context(s)))
+            .collect();
+        if field_ids.is_empty() || candidates.is_empty() {
+            self.status_message = Some("Nothing to fill".to_string());
+            self.input_mode = InputMode::Normal;
+            return;
+        }
+
+        self.batch_fill_entries = candidates
+            .iter()
+            .map(|(id, name, _, _)| BatchFillEntry {
+                session_id: *id,
+                session_name: name.clone(),
+                state: BatchFillState::Pending,
+            })
+            .collect();
+        let fields: Vec<(String, String)> = self.fields
+            .iter()
+            .filter(|f| field_ids.contains(&f.id))
+            .map(|f| (f.name.clone(), f.description.clone()))
+            .collect();
+        self.batch_fill_field_ids = field_ids;
+        self.input_mode = InputMode::BatchFill;
+
+        let redact_patterns = self.project_setting(ProjectSetting::RedactPatterns).unwrap_or_default();
+        let max_context_chars = self.project_ai_context_chars();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        self.batch_fill_rx = Some(rx);
+        self.batch_fill_cancel = Some(cancel.clone());
+
+        let tmux = self.tmux.clone();
+        thread::spawn(move || {
+            let extra = redact::compile_patterns(&redact_patterns).unwrap_or_default();
+            for (session_id, name, tmux_name, card) in candidates {
+                if cancel.load(Ordering::Relaxed) || tx.send(BatchFillEvent::Started(session_id)).is_err() {
+                    return;
+                }
+                let pane_content = tmux_name
+                    .as_deref()
+                    .and_then(|name| tmux.capture_pane_content(name, None))
+                    .map(|content| redact::redact(&content, &extra).0);
+                let result = crate::ai::fill_fields(&name, &fields, pane_content.as_deref(), max_context_chars, &card).map_err(|e| e.to_string());
+                if cancel.load(Ordering::Relaxed) || tx.send(BatchFillEvent::Finished(session_id, result)).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    fn cancel_batch_fill(&mut self) {
+        if let Some(cancel) = self.batch_fill_cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.batch_fill_rx = None;
+    }
+
+    pub fn batch_fill_running(&self) -> bool {
+        self.batch_fill_rx.is_some()
+    }
+
+    fn check_batch_fill(&mut self) -> Result<()> {
+        let Some(ref rx) = self.batch_fill_rx else { return Ok(()) };
+        let events: Vec<BatchFillEvent> = rx.try_iter().collect();
+        if events.is_empty() {
+            return Ok(());
+        }
+        self.needs_redraw = true;
+
+        let mut updated_any = false;
+        for event in events {
+            let (session_id, state) = match event {
+                BatchFillEvent::Started(id) => (id, BatchFillState::Running),
+                BatchFillEvent::Finished(id, Err(e)) => (id, BatchFillState::Failed(e)),
+                BatchFillEvent::Finished(id, Ok(fill)) => {
+                    self.record_ai_usage(Some(id), "batch_fill", &fill.usage);
+                    let current: Vec<String> = self.batch_fill_field_ids
+                        .iter()
+                        .map(|f| self.db.get_session_field_value(id, *f).unwrap_or_default())
+                        .collect();
+                    let writes = fill_empty_values(&self.batch_fill_field_ids, &current, &fill.values);
+                    for (field_id, value) in &writes {
+                        self.set_field_value(id, *field_id, value, ValueSource::Ai)?;
+                    }
+                    updated_any |= !writes.is_empty();
+                    (id, BatchFillState::Updated(writes.len()))
+                }
+            };
+            if let Some(entry) = self.batch_fill_entries.iter_mut().find(|e| e.session_id == session_id) {
+                entry.state = state;
+            }
+        }
+        if updated_any {
+            self.refresh_sessions()?;
+        }
+
+        let finished = self.batch_fill_entries
+            .iter()
+            .all(|e| matches!(e.state, BatchFillState::Updated(_) | BatchFillState::Failed(_)));
+        if finished {
+            self.batch_fill_cancel = None;
+            self.batch_fill_rx = None;
+            let (updated, failed) = self.batch_fill_summary();
+            self.status_message = Some(format!("Batch fill: {} updated, {} failed", updated, failed));
+        }
+        Ok(())
+    }
+
+    /// (sessions that got at least one value, sessions that failed)
+    pub fn batch_fill_summary(&self) -> (usize, usize) {
+        let updated = self.batch_fill_entries.iter().filter(|e| matches!(e.state, BatchFillState::Updated(n) if n > 0)).count();
+        let failed = self.batch_fill_entries.iter().filter(|e| matches!(e.state, BatchFillState::Failed(_))).count();
+        (updated, failed)
+    }
+
+    fn handle_batch_fill_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Enter if self.batch_fill_running() => {}
+            KeyCode::Char('c') | KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => {
+                if self.batch_fill_running() {
+                    self.cancel_batch_fill();
+                    let (updated, failed) = self.batch_fill_summary();
+                    self.status_message = Some(format!("Batch fill cancelled: {} updated, {} failed", updated, failed));
+                }
+                self.batch_fill_entries.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the `D` overlay for the selected session and run git in the background,
+    /// in the worktree when it still exists and the main repo otherwise
+    /// Plan the landing of the selected Review card; nothing runs until Enter
+    fn open_land(&mut self) -> Result<()> {
+        let Some(session) = self.selected_session().cloned() else { return Ok(()) };
+        if session.status != Status::Review {
+            self.status_message = Some(format!("Only {} cards can be landed", self.status_label(Status::Review)));
+            return Ok(());
+        }
+        let Some(repo_root) = self.repo_root.clone() else {
+            self.status_message = Some("Landing needs the project's git repository".to_string());
+            return Ok(());
+        };
+        let Some(branch) = session.branch_name.clone() else {
+            self.status_message = Some("This session has no branch".to_string());
+            return Ok(());
+        };
+        let base = match self.git.head(&repo_root) {
+            Some(head) => head.to_string(),
+            None => "the main checkout".to_string(),
+        };
+
+        let mut steps = vec![
+            (LandStep::Check, format!("git status, git rev-list {} --not --remotes", branch), StepOutcome::Pending),
+            (LandStep::PullBase, format!("git pull --ff-only ({} in {})", base, repo_root.display()), StepOutcome::Pending),
+        ];
+        if let Some(name) = session.tmux_window.as_deref().filter(|name| self.tmux.session_exists(name)) {
+            steps.push((LandStep::StopTerminal, format!("tmux kill-session -t {}", name), StepOutcome::Pending));
+        }
+        if let Some(path) = session.checkout_path.as_deref() {
+            steps.push((LandStep::RemoveWorktree, format!("git worktree remove {}", path.display()), StepOutcome::Pending));
+        }
+        steps.push((LandStep::DeleteBranch, format!("git branch -D {}", branch), StepOutcome::Pending));
+        let done = format!("move to {}, note the merge commit", self.status_label(Status::Done));
+        steps.push((LandStep::MoveToDone, done, StepOutcome::Pending));
+
+        self.land = Some(LandView { session_id: session.id, session_name: session.name, steps, current: 0, tip: None });
+        self.input_mode = InputMode::Land;
+        Ok(())
+    }
+
+    fn handle_land_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(view) = self.land.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                if view.started() && !view.finished() {
+                    self.status_message = Some(format!("Stopped landing {} partway", view.session_name));
+                }
+                self.land = None;
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter if view.finished() => {
+                self.land = None;
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter if !view.started() => self.run_land_steps()?,
+            KeyCode::Char('r') if view.failed() => self.run_land_steps()?,
+            KeyCode::Char('s') if view.failed() => {
+                if view.steps[view.current].0 == LandStep::Check {
+                    self.status_message = Some("The check can't be skipped: fix the worktree and retry".to_string());
+                    return Ok(());
+                }
+                view.steps[view.current].2 = StepOutcome::Skipped;
+                view.current += 1;
+                self.run_land_steps()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Run the landing steps from the current one, stopping at the first that fails
+    fn run_land_steps(&mut self) -> Result<()> {
+        while let Some(step) = self.land.as_ref().and_then(|view| view.steps.get(view.current)).map(|(step, _, _)| *step) {
+            let outcome = self.run_land_step(step)?;
+            let Some(view) = self.land.as_mut() else { break };
+            let failed = outcome.is_err();
+            view.steps[view.current].2 = match outcome {
+                Ok(text) => StepOutcome::Done(text),
+                Err(e) => StepOutcome::Failed(e),
+            };
+            if failed {
+                return Ok(());
+            }
+            view.current += 1;
+        }
+        self.refresh_sessions()?;
+        if let Some(view) = self.land.as_ref().filter(|view| view.finished()) {
+            self.status_message = Some(format!("Landed {}", view.session_name));
+        }
+        Ok(())
+    }
+
+    /// One landing step: what it did, or why it failed
+    fn run_land_step(&mut self, step: LandStep) -> Result<Result<String, String>> {
+        let Some(session_id) = self.land.as_ref().map(|view| view.session_id) else {
+            return Ok(Err("Nothing to land".to_string()));
+        };
+        let Some(session) = self.db.get_live_session(self.project.id, session_id)? else {
+            return Ok(Err("The session no longer exists".to_string()));
+        };
+        let Some(repo_root) = self.repo_root.clone() else { return Ok(Err("No git repository".to_string())) };
+        let branch = session.branch_name.clone().unwrap_or_default();
+        let outcome = match step {
+            LandStep::Check => {
+                let dirty = session.checkout_path.as_deref().and_then(|path| self.git.get_dirty_status(path));
+                if let Some(dirty) = dirty.filter(DirtyStatus::is_dirty) {
+                    return Ok(Err(format!(
+                        "Uncommitted changes: {} staged, {} modified, {} untracked",
+                        dirty.staged, dirty.unstaged, dirty.untracked
+                    )));
+                }
+                match self.git.unpushed_commits(&repo_root, &branch) {
+                    Ok(0) => {
+                        let tip = self.git.short_commit(&repo_root, &branch);
+                        let text = format!("Clean, {} pushed at {}", branch, tip.as_deref().unwrap_or("?"));
+                        if let Some(view) = self.land.as_mut() {
+                            view.tip = tip;
+                        }
+                        Ok(text)
+                    }
+                    Ok(n) => Err(format!("{} commit{} on {} not pushed", n, if n == 1 { "" } else { "s" }, branch)),
+                    Err(e) => Err(e),
+                }
+            }
+            LandStep::PullBase => self.git.pull(&repo_root).map_err(|e| e.to_string()),
+            LandStep::StopTerminal => match session.tmux_window.as_deref() {
+                Some(name) => {
+                    self.save_transcript(session.id, name);
+                    self.tmux.kill_session(name);
+                    self.active_tmux_sessions.remove(name);
+                    Ok(format!("Stopped {}", name))
+                }
+                None => Ok("Not running".to_string()),
+            },
+            LandStep::RemoveWorktree => match session.checkout_path.as_deref() {
+                Some(path) => match self.git.remove_worktree(&repo_root, path, false) {
+                    Ok(()) => {
+                        self.db.clear_session_worktree(session.id)?;
+                        Ok(format!("Removed {}", path.display()))
+                    }
+                    Err(e) => Err(e.to_string()),
+                },
+                None => Ok("No worktree".to_string()),
+            },
+            // The check made sure a remote has every commit, so nothing is lost by forcing;
+            // git can't see squash and rebase merges otherwise
+            LandStep::DeleteBranch => {
+                self.git.delete_branch(&repo_root, &branch, true).map(|_| format!("Deleted {}", branch)).map_err(|e| e.to_string())
+            }
+            LandStep::MoveToDone => {
+                let tip = self.land.as_ref().and_then(|view| view.tip.clone());
+                let note = match tip.as_deref().and_then(|tip| self.git.merge_commit(&repo_root, tip)) {
+                    Some(merge) => format!("Landed: merge commit {}", merge),
+                    None => {
+                        let head = self.git.short_commit(&repo_root, "HEAD").unwrap_or_else(|| "?".to_string());
+                        let base = self.git.head(&repo_root).map_or_else(|| "the base".to_string(), |h| h.to_string());
+                        format!("Landed: no merge commit found (squash or rebase?), {} at {}", base, head)
+                    }
+                };
+                self.db.create_comment(session.id, &note)?;
+                self.move_session_to(&session, Status::Done)?;
+                Ok(format!("Moved to {}; {}", self.status_label(Status::Done), note))
+            }
+        };
+        Ok(outcome)
+    }
+
+    fn open_branch_changes(&mut self) -> Result<()> {
+        let Some(session) = self.selected_session() else { return Ok(()) };
+        let Some(branch) = session.branch_name.clone() else {
+            self.status_message = Some("This session has no branch".to_string());
+            return Ok(());
+        };
+        let session_name = session.name.clone();
+        let dir = session
+            .checkout_path
+            .clone()
+            .filter(|path| path.exists())
+            .or_else(|| self.repo_root.clone());
+        let base = self.project_setting(ProjectSetting::BaseBranch)
+            .filter(|b| !b.is_empty())
+            .or_else(|| self.repo_root.as_deref().and_then(|root| self.git.detect_default_branch(root)));
+
+        let result = match (&dir, &base) {
+            (None, _) => Some(Err("Neither the worktree nor the main repository is available".to_string())),
+            (_, None) => Some(Err("No base branch to compare with: set one in Settings > Project".to_string())),
+            (Some(dir), Some(base)) => {
+                let (tx, rx) = mpsc::channel();
+                let (git, dir, base, branch) = (self.git.clone(), dir.clone(), base.clone(), branch.clone());
+                thread::spawn(move || {
+                    let _ = tx.send(git.branch_changes(&dir, &base, &branch));
+                });
+                self.branch_changes_rx = Some(rx);
+                None
+            }
+        };
+        self.branch_changes = Some(BranchChangesView { session_name, branch, base, result, scroll: 0 });
+        self.input_mode = InputMode::BranchChanges;
+        Ok(())
+    }
+
+    fn check_branch_changes(&mut self) {
+        let Some(ref rx) = self.branch_changes_rx else { return };
+        let Ok(result) = rx.try_recv() else { return };
+        self.branch_changes_rx = None;
+        self.needs_redraw = true;
+        if let Some(view) = self.branch_changes.as_mut() {
+            view.result = Some(result);
+        }
+    }
+
+    fn handle_branch_changes_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.branch_changes.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if (view.scroll as usize) + 1 < view.line_count() => {
+                view.scroll += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                view.scroll = view.scroll.saturating_sub(1);
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('D') => {
+                // A result still in flight is dropped with the receiver
+                self.branch_changes = None;
+                self.branch_changes_rx = None;
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    /// The notes file of a session, when it has a worktree on disk and the project's path
+    /// template is usable
+    fn notes_path(&self, session: &Session) -> Option<PathBuf> {
+        let worktree = session.checkout_path.as_deref().filter(|path| path.is_dir())?;
+        let template = self.project_setting(ProjectSetting::NotesPath).unwrap_or_default();
+        notes::path_for(&template, worktree, session.id).ok()
+    }
+
+    /// Open the selected session's scratchpad. A notes file changed since the last sync
+    /// wins over the database copy; a missing one is created from it
+    fn open_notes(&mut self) -> Result<()> {
+        let Some(session_id) = self.selected_session().map(|s| s.id) else {
+            return Ok(());
+        };
+        let Some(session) = self.fresh_session(session_id)? else {
+            return Ok(());
+        };
+        let stored = self.db.get_session_notes(session_id)?;
+        let path = self.notes_path(&session);
+        let (text, synced_mtime) = match path.as_deref() {
+            Some(path) => match notes::read(path) {
+                Some((text, mtime)) if Some(mtime) != stored.file_mtime => {
+                    self.db.set_session_notes(session_id, &text, Some(mtime))?;
+                    (text, Some(mtime))
+                }
+                Some((_, mtime)) => (stored.text, Some(mtime)),
+                None => match notes::write(path, &stored.text) {
+                    Ok(mtime) => {
+                        exclude_notes_file(path);
+                        self.db.set_session_notes(session_id, &stored.text, Some(mtime))?;
+                        (stored.text, Some(mtime))
+                    }
+                    Err(e) => {
+                        tracing::warn!(path = %path.display(), error = %e, "could not create the notes file");
+                        (stored.text, None)
+                    }
+                },
+            },
+            None => (stored.text, None),
+        };
+        self.notes_editor = Some(NotesEditor {
+            session_id,
+            session_name: session.name,
+            path,
+            original: text.clone(),
+            text,
+            synced_mtime,
+            conflict: false,
+            confirm_discard: false,
+        });
+        self.input_mode = InputMode::Notes;
+        Ok(())
+    }
+
+    fn handle_notes_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(editor) = self.notes_editor.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        if editor.confirm_discard {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.close_notes(),
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => editor.confirm_discard = false,
+                _ => {}
+            }
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc => {
+                if editor.edited() {
+                    editor.confirm_discard = true;
+                } else {
+                    self.close_notes();
+                }
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => self.save_notes()?,
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => self.reload_notes()?,
+            KeyCode::Enter => editor.text.push('\n'),
+            _ if is_newline_key(&key) => editor.text.push('\n'),
+            KeyCode::Backspace => {
+                editor.text.pop();
+            }
+            KeyCode::Char(c) => editor.text.push(c),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn close_notes(&mut self) {
+        self.notes_editor = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Write the scratchpad to its file and the database. A file changed on disk since
+    /// the editor read it is only overwritten once the conflict has been shown
+    fn save_notes(&mut self) -> Result<()> {
+        let Some(editor) = self.notes_editor.as_mut() else {
+            return Ok(());
+        };
+        let mut file_mtime = None;
+        if let Some(path) = editor.path.as_deref() {
+            let changed = notes::disk_change(editor.synced_mtime, notes::mtime(path), true) == notes::DiskChange::Conflict;
+            if changed && !editor.conflict {
+                editor.conflict = true;
+                self.status_message = Some("The notes file changed on disk (Ctrl+S again: overwrite, Ctrl+R: reload)".to_string());
+                return Ok(());
+            }
+            let created = !path.exists();
+            match notes::write(path, &editor.text) {
+                Ok(mtime) => {
+                    if created {
+                        exclude_notes_file(path);
+                    }
+                    file_mtime = Some(mtime);
+                }
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "could not write the notes file");
+                    self.status_message = Some(format!("Could not write {}: {} (saved in workbench only)", path.display(), e));
+                }
+            }
+        }
+        self.db.set_session_notes(editor.session_id, &editor.text, file_mtime)?;
+        if file_mtime.is_some() || editor.path.is_none() {
+            self.status_message = Some(format!("Notes saved for '{}'", editor.session_name));
+        }
+        editor.original = editor.text.clone();
+        editor.synced_mtime = file_mtime;
+        editor.conflict = false;
+        Ok(())
+    }
+
+    /// Throw the editor's text away for the notes file's
+    fn reload_notes(&mut self) -> Result<()> {
+        let Some(editor) = self.notes_editor.as_mut() else {
+            return Ok(());
+        };
+        let Some((text, mtime)) = editor.path.as_deref().and_then(notes::read) else {
+            self.status_message = Some("No notes file to reload".to_string());
+            return Ok(());
+        };
+        self.db.set_session_notes(editor.session_id, &text, Some(mtime))?;
+        editor.original = text.clone();
+        editor.text = text;
+        editor.synced_mtime = Some(mtime);
+        editor.conflict = false;
+        Ok(())
+    }
+
+    /// Mirror notes files changed outside workbench (an agent editing them) into the
+    /// database, and into the open editor unless it has edits of its own
+    fn sync_notes_files(&mut self) {
+        let synced = match self.db.list_notes_mtimes(self.project.id) {
+            Ok(synced) => synced,
+            Err(e) => {
+                tracing::warn!(error = %e, "could not list notes files");
+                return;
+            }
+        };
+        for session in &self.sessions {
+            let Some(&synced_mtime) = synced.get(&session.id) else { continue };
+            let Some(path) = self.notes_path(session) else { continue };
+            // Most files haven't changed; only those that have are read
+            if notes::mtime(&path).is_none_or(|mtime| mtime == synced_mtime) {
+                continue;
+            }
+            let Some((text, mtime)) = notes::read(&path) else { continue };
+            if let Err(e) = self.db.set_session_notes(session.id, &text, Some(mtime)) {
+                tracing::warn!(session_id = session.id, error = %e, "could not store the notes file");
+            }
+        }
+
+        let Some(editor) = self.notes_editor.as_mut() else { return };
+        let Some(path) = editor.path.as_deref() else { return };
+        match notes::disk_change(editor.synced_mtime, notes::mtime(path), editor.edited()) {
+            notes::DiskChange::None => {}
+            notes::DiskChange::Reload => {
+                if let Some((text, mtime)) = notes::read(path) {
+                    editor.original = text.clone();
+                    editor.text = text;
+                    editor.synced_mtime = Some(mtime);
+                    self.needs_redraw = true;
+                }
+            }
+            notes::DiskChange::Conflict => {
+                if !editor.conflict {
+                    editor.conflict = true;
+                    self.needs_redraw = true;
+                }
+            }
+        }
+    }
+
+    /// Save the session's whole scrollback before its tmux session is killed, when the
+    /// project keeps transcripts. Failures are logged and never block the kill
+    fn save_transcript(&self, session_id: i64, tmux_name: &str) {
+        let save = || -> Result<()> {
+            if self.project_setting(ProjectSetting::Transcripts).as_deref() != Some("on") {
+                return Ok(());
+            }
+            let Some(text) = self.tmux.capture_scrollback(tmux_name) else {
+                tracing::warn!(session_id, tmux_name, "no scrollback to save");
+                return Ok(());
+            };
+            let max_kb = self.project_setting(ProjectSetting::TranscriptMaxKb)
+                .and_then(|v| parse_transcript_max_kb(&v).ok().flatten())
+                .unwrap_or(transcript::DEFAULT_MAX_KB);
+            let path = transcript::path_for(&Database::transcripts_dir()?, self.project.id, session_id, db::unix_now());
+            self.db.add_transcript(session_id, &path.to_string_lossy())?;
+            transcript::write_in_background(path, text, (max_kb * 1024) as usize);
+            Ok(())
+        };
+        if let Err(e) = save() {
+            tracing::error!(session_id, error = %e, "could not save transcript");
+        }
+    }
+
+    fn open_transcripts(&mut self) -> Result<()> {
+        let Some(session_id) = self.selected_session().map(|s| s.id) else {
+            return Ok(());
+        };
+        let Some(session) = self.fresh_session(session_id)? else {
+            return Ok(());
+        };
+        let transcripts = self.db.list_transcripts(session_id)?;
+        if transcripts.is_empty() {
+            self.status_message = Some("No transcripts for this session (Settings > Project > Save transcripts)".to_string());
+            return Ok(());
+        }
+        self.transcripts = transcripts
+            .into_iter()
+            .map(|t| {
+                let size = std::fs::metadata(&t.path).ok().map(|m| m.len());
+                (t, size)
+            })
+            .collect();
+        self.transcripts_session_name = session.name;
+        self.selected_transcript = 0;
+        self.input_mode = InputMode::Transcripts;
+        Ok(())
+    }
+
+    fn handle_transcripts_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_transcript + 1 < self.transcripts.len() => {
+                self.selected_transcript += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_transcript = self.selected_transcript.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                let Some((transcript, _)) = self.transcripts.get(self.selected_transcript) else { return };
+                match std::fs::read_to_string(&transcript.path) {
+                    Ok(text) => {
+                        self.transcript_view = Some(TranscriptView {
+                            title: format!("{} ({})", self.transcripts_session_name, transcript.created_at),
+                            lines: text.lines().map(String::from).collect(),
+                            scroll: 0,
+                        });
+                        self.input_mode = InputMode::TranscriptView;
+                    }
+                    Err(e) => {
+                        self.status_message = Some(format!("Could not read {}: {}", transcript.path, e));
+                    }
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('T') => {
+                self.transcripts.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_transcript_view_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.transcript_view.as_mut() else {
+            self.input_mode = InputMode::Transcripts;
+            return;
+        };
+        let last = view.lines.len().saturating_sub(1).min(u16::MAX as usize) as u16;
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => view.scroll = (view.scroll + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => view.scroll = view.scroll.saturating_add(20).min(last),
+            KeyCode::PageUp => view.scroll = view.scroll.saturating_sub(20),
+            KeyCode::Char('g') | KeyCode::Home => view.scroll = 0,
+            KeyCode::Char('G') | KeyCode::End => view.scroll = last,
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.transcript_view = None;
+                self.input_mode = InputMode::Transcripts;
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the worktree maintenance popup and start sizing worktrees in the background
+    fn open_worktree_maintenance(&mut self) {
+        self.cancel_worktree_scan();
+
+        self.worktree_entries = self.sessions
+            .iter()
+            .filter_map(|s| {
+                s.checkout_path.as_ref().map(|path| WorktreeEntry {
+                    session_id: s.id,
+                    session_name: s.name.clone(),
+                    path: path.clone(),
+                    size: None,
+                    candidate: false,
+                    selected: false,
+                })
+            })
+            .collect();
+        self.selected_worktree = 0;
+        self.confirm_worktree_prune = false;
+        self.input_mode = InputMode::WorktreeMaintenance;
+
+        let jobs: Vec<(i64, PathBuf, Option<String>)> = self.sessions
+            .iter()
+            .filter_map(|s| s.checkout_path.as_ref().map(|p| (s.id, p.clone(), s.branch_name.clone())))
+            .collect();
+        let (git, repo_root) = (self.git.clone(), self.repo_root.clone());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        self.worktree_scan_rx = Some(rx);
+        self.worktree_scan_cancel = Some(cancel.clone());
+
+        thread::spawn(move || {
+            for (session_id, path, branch_name) in jobs {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let merged = match (&repo_root, &branch_name) {
+                    (Some(root), Some(branch)) => git.is_branch_merged(root, branch),
+                    _ => false,
+                };
+                let size = git::dir_size(&path, &cancel);
+                if cancel.load(Ordering::Relaxed) || tx.send(WorktreeScan { session_id, size, merged }).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    fn cancel_worktree_scan(&mut self) {
+        if let Some(cancel) = self.worktree_scan_cancel.take() {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.worktree_scan_rx = None;
+    }
+
+    fn check_worktree_scan(&mut self) {
+        let Some(ref rx) = self.worktree_scan_rx else { return };
+        let results: Vec<WorktreeScan> = rx.try_iter().collect();
+        if results.is_empty() {
+            return;
+        }
+        self.needs_redraw = true;
+
+        let selected_id = self.worktree_entries.get(self.selected_worktree).map(|e| e.session_id);
+        for scan in results {
+            let is_done = self.sessions
+                .iter()
+                .any(|s| s.id == scan.session_id && s.status == Status::Done);
+            if let Some(entry) = self.worktree_entries.iter_mut().find(|e| e.session_id == scan.session_id) {
+                entry.size = scan.size;
+                entry.candidate = is_done && scan.merged;
+            }
+        }
+
+        // Largest first, unsized entries last; keep the cursor on the same entry
+        self.worktree_entries.sort_by_key(|e| std::cmp::Reverse(e.size));
+        if let Some(id) = selected_id {
+            self.selected_worktree = self.worktree_entries
+                .iter()
+                .position(|e| e.session_id == id)
+                .unwrap_or(0);
+        }
+    }
+
+    fn handle_worktree_maintenance_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.confirm_worktree_prune {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    self.prune_selected_worktrees()?;
+                    self.confirm_worktree_prune = false;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                    self.confirm_worktree_prune = false;
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.cancel_worktree_scan();
+                self.worktree_entries.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.selected_worktree < self.worktree_entries.len().saturating_sub(1) => {
+                self.selected_worktree += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_worktree > 0 => {
+                self.selected_worktree -= 1;
+            }
+            KeyCode::Char(' ') => {
+                if let Some(entry) = self.worktree_entries.get_mut(self.selected_worktree) {
+                    entry.selected = !entry.selected;
+                }
+            }
+            KeyCode::Char('a') => {
+                // Select all suggested candidates
+                for entry in &mut self.worktree_entries {
+                    entry.selected = entry.candidate;
+                }
+            }
+            KeyCode::Enter | KeyCode::Char('d') if self.worktree_entries.iter().any(|e| e.selected) => {
+                self.confirm_worktree_prune = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn prune_selected_worktrees(&mut self) -> Result<()> {
+        let Some(repo_root) = self.repo_root.clone() else {
+            self.status_message = Some("Not in a git repository".to_string());
+            return Ok(());
+        };
+
+        let mut removed = 0;
+        let mut failed = 0;
+        for entry in self.worktree_entries.iter().filter(|e| e.selected) {
+            match self.git.remove_worktree(&repo_root, &entry.path, false) {
+                Ok(()) => {
+                    self.db.clear_session_worktree(entry.session_id)?;
+                    removed += 1;
+                }
+                Err(_) => failed += 1,
+            }
+        }
+        self.worktree_entries.retain(|e| !e.selected || Path::new(&e.path).exists());
+        self.selected_worktree = self.selected_worktree.min(self.worktree_entries.len().saturating_sub(1));
+        self.refresh_sessions()?;
+
+        self.status_message = Some(if failed == 0 {
+            format!("Removed {} worktree{}", removed, if removed == 1 { "" } else { "s" })
+        } else {
+            format!("Removed {} worktree{}, {} failed (uncommitted changes?)", removed, if removed == 1 { "" } else { "s" }, failed)
+        });
+        Ok(())
+    }
+
+    fn handle_enter_key(&mut self) -> Result<AppAction> {
+        if !self.tmux.is_available() {
+            // tmux not installed, do nothing
+            return Ok(AppAction::None);
+        }
+
+        let Some(session_id) = self.selected_session().map(|s| s.id) else {
+            return Ok(AppAction::None);
+        };
+        let Some(session) = self.fresh_session(session_id)? else {
+            return Ok(AppAction::None);
+        };
+
+        // A retained pane whose agent exited: offer respawn or cleanup instead of attaching
+        if self.exit_status(&session).is_some() {
+            self.input_mode = InputMode::DeadPane;
+            return Ok(AppAction::None);
+        }
+
+        // Starting afresh (e.g. after a reboot) in a worktree that has since gone away
+        let running = session.tmux_window.as_deref().is_some_and(|name| self.tmux.session_exists(name));
+        if !running && session.checkout_path.is_some() && usable_checkout(&*self.git, &self.project, &session).is_none() {
+            if session.branch_name.is_some() && self.repo_root.is_some() {
+                self.recreate_worktree_session_id = Some(session_id);
+                self.input_mode = InputMode::ConfirmRecreateWorktree;
+                return Ok(AppAction::None);
+            }
+            self.status_message = Some("Worktree is gone; started in the project directory".to_string());
+        }
+
+        // Its own terminal, still running after the link to it was lost
+        if let Some(tmux_name) = adoptable_terminal(&*self.tmux, &self.project, &session) {
+            self.adopt_terminal = Some((session_id, tmux_name));
+            self.input_mode = InputMode::ConfirmAdoptTerminal;
+            return Ok(AppAction::None);
+        }
+
+        let tmux_name = ensure_tmux_session(&self.db, &*self.tmux, &*self.git, &self.project, &session)?;
+        self.active_tmux_sessions.insert(tmux_name.clone());
+        self.attach(session_id, tmux_name)
+    }
+
+    /// Save a column change made on the board, auto-starting the session's terminal when it
+    /// enters In Progress and the project asks for that
+    fn move_session_to(&mut self, session: &Session, status: Status) -> Result<()> {
+        self.db.update_session_status(session.id, status)?;
+        if status == Status::InProgress && session.status != Status::InProgress {
+            self.autostart(session)?;
+        }
+        Ok(())
+    }
+
+    fn autostart(&mut self, session: &Session) -> Result<()> {
+        if !autostart_enabled(&self.db, self.project.id)? {
+            return Ok(());
+        }
+        match autostart_session(&self.db, &*self.tmux, &*self.git, &self.project, self.repo_root.as_deref(), session) {
+            Ok(tmux_name) => {
+                self.active_tmux_sessions.insert(tmux_name);
+                self.status_message = Some(format!("Started {}", session.name));
+            }
+            Err(e) => {
+                tracing::error!(session_id = session.id, error = %e, "auto-start failed");
+                self.status_message = Some(e.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The stored row for `session_id`, or None with a status message if it was removed.
+    /// Key handlers capture the selected id once and look it up here, so a refresh that
+    /// removes or reorders cards can't redirect the action to a different session
+    fn fresh_session(&mut self, session_id: i64) -> Result<Option<Session>> {
+        let session = self.db.get_live_session(self.project.id, session_id)?;
+        if session.is_none() {
+            self.status_message = Some("That session no longer exists".to_string());
+            self.refresh_sessions()?;
+            self.clamp_row();
+        }
+        Ok(session)
+    }
+
+    fn handle_input_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+                self.duplicate_name_warning = false;
+                self.spinoff_source_id = None;
+                self.new_session_status = None;
+            }
+            KeyCode::Enter => {
+                let mut asks_fields = false;
+                if !self.input_buffer.is_empty() {
+                    // Ask for a second Enter before creating a same-named session
+                    if !self.duplicate_name_warning && has_session_named(&self.sessions, &self.input_buffer) {
+                        self.duplicate_name_warning = true;
+                        return Ok(());
+                    }
+
+                    let view_state = self.view_state();
+                    // A card named in another column starts in Planned and moves there once its
+                    // fields are asked for, so the column's checklist and auto-start apply
+                    let (session, worktree_error) = create_session_with_worktree(
+                        &self.db,
+                        &*self.git,
+                        self.project.id,
+                        self.repo_root.as_deref(),
+                        &self.input_buffer,
+                        Status::Planned,
+                    )?;
+                    if let Some(e) = worktree_error {
+                        self.status_message = Some(format!("Worktree error: {}", e));
+                    }
+
+                    let source = self.spinoff_source_id.and_then(|id| self.sessions.iter().find(|s| s.id == id));
+                    if let Some(source) = source {
+                        self.db.create_comment(session.id, &format!("Spun off from: {} (#{})", source.name, source.id))?;
+                        if self.status_message.is_none() {
+                            self.status_message = Some(format!("Created \"{}\" in {}", session.name, self.status_label(session.status)));
+                        }
+                    }
+
+                    self.refresh_sessions()?;
+                    // Keep the selection (and any peek) on the session it was on
+                    self.restore_view_state(view_state);
+
+                    let status = self.new_session_status.unwrap_or(Status::Planned);
+                    let field_ids: Vec<i64> = self.new_card_fields(status).iter().map(|f| f.id).collect();
+                    if self.new_session_status.is_some() && !field_ids.is_empty() {
+                        let session_name = session.name.clone();
+                        self.new_card_prompt = Some(NewCardPrompt { session_id: session.id, session_name, status, field_ids, index: 0 });
+                        asks_fields = true;
+                    }
+                }
+                self.input_mode = if asks_fields { InputMode::NewCardField } else { InputMode::Normal };
+                self.input_buffer.clear();
+                self.input_error = None;
+                self.duplicate_name_warning = false;
+                self.spinoff_source_id = None;
+                self.new_session_status = None;
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.duplicate_name_warning = false;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.duplicate_name_warning = false;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// One popup of the chain after naming a card: Enter stores the value (empty leaves
+    /// the field unset) and asks for the next field, Esc skips the rest
+    fn handle_new_card_field_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(prompt) = self.new_card_prompt.as_mut() else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.finish_new_card_prompt()?,
+            KeyCode::Enter => {
+                let (session_id, field_id) = (prompt.session_id, prompt.field_ids.get(prompt.index).copied());
+                prompt.index += 1;
+                let done = prompt.index >= prompt.field_ids.len();
+                let value = self.input_buffer.trim().to_string();
+                if let (false, Some(field_id)) = (value.is_empty(), field_id) {
+                    self.set_field_value(session_id, field_id, &value, ValueSource::Manual)?;
+                }
+                self.input_buffer.clear();
+                if done {
+                    self.finish_new_card_prompt()?;
+                }
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Move the card into the column it was created in, as `m` would: the column's
+    /// checklist may still hold it in Planned
+    fn finish_new_card_prompt(&mut self) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        self.input_buffer.clear();
+        let Some(prompt) = self.new_card_prompt.take() else {
+            return self.refresh_sessions();
+        };
+        let Some(session) = self.fresh_session(prompt.session_id)? else {
+            return Ok(());
+        };
+        self.request_move(&session, prompt.status)?;
+        if self.input_mode == InputMode::Normal {
+            self.select_session(session.id);
+            if self.status_message.is_none() {
+                self.status_message = Some(format!("Created \"{}\" in {}", prompt.session_name, self.status_label(prompt.status)));
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_plan_sessions_key(&mut self, key: KeyEvent) -> Result<()> {
+        if self.confirm_discard_plan {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => {
+                    self.input_mode = InputMode::Normal;
+                    self.input_buffer.clear();
+                    self.input_error = None;
+                    self.confirm_discard_plan = false;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => self.confirm_discard_plan = false,
+                _ => {}
+            }
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc => {
+                if self.input_buffer.trim().is_empty() {
+                    self.input_mode = InputMode::Normal;
+                    self.input_buffer.clear();
+                    self.input_error = None;
+                } else {
+                    self.confirm_discard_plan = true;
+                }
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => self.create_planned_sessions()?,
+            KeyCode::Enter => {
+                self.input_buffer.push('\n');
+                self.input_error = None;
+            }
+            _ if is_newline_key(&key) => {
+                self.input_buffer.push('\n');
+                self.input_error = None;
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Create the plan popup's cards in one go, skipping names already on the board (or
+    /// typed twice), then their worktrees
+    fn create_planned_sessions(&mut self) -> Result<()> {
+        let column_labels: Vec<(Status, String)> =
+            Status::all().iter().map(|s| (*s, self.status_label(*s).to_string())).collect();
+        let planned = match parse_plan(&self.input_buffer, &column_labels) {
+            Ok(planned) => planned,
+            Err(e) => {
+                self.input_error = Some(e);
+                return Ok(());
+            }
+        };
+        if planned.is_empty() {
+            self.input_error = Some("Type one card name per line".to_string());
+            return Ok(());
+        }
+
+        // Compared like `has_session_named`, against the board and the lines above
+        let mut taken: Vec<String> = self.sessions.iter().map(|s| s.name.trim().to_lowercase()).collect();
+        let mut new = Vec::new();
+        for card in &planned {
+            let key = card.name.to_lowercase();
+            if !taken.contains(&key) {
+                taken.push(key);
+                new.push((card.name.clone(), card.status));
+            }
+        }
+        let skipped = planned.len() - new.len();
+
+        let created = self.db.create_sessions(self.project.id, &new, self.current_owner.as_deref())?;
+        let mut worktree_errors = 0;
+        for session in &created {
+            if create_worktree_for(&self.db, &*self.git, self.project.id, self.repo_root.as_deref(), session)?.is_some() {
+                worktree_errors += 1;
+            }
+        }
+
+        self.input_mode = InputMode::Normal;
+        self.input_buffer.clear();
+        self.input_error = None;
+        self.refresh_sessions()?;
+        if let Some(first) = created.first() {
+            self.select_session(first.id);
+        }
+
+        let mut message = format!("Created {} session{}", created.len(), if created.len() == 1 { "" } else { "s" });
+        if skipped > 0 {
+            message.push_str(&format!(", {} duplicate{} skipped", skipped, if skipped == 1 { "" } else { "s" }));
+        }
+        if worktree_errors > 0 {
+            message.push_str(&format!(", {} without a worktree (see the log)", worktree_errors));
+        }
+        if planned.iter().any(|card| !card.tags.is_empty()) {
+            message.push_str("; #tags ignored, sessions have no tags yet");
+        }
+        self.status_message = Some(message);
+        Ok(())
+    }
+
+    fn handle_edit_session_key(&mut self, key: KeyEvent) -> Result<()> {
+        let total_rows = FIRST_FIELD_ROW + self.edit_field_indices().len();
+
+        // The full AI error popup swallows keys until closed
+        if self.show_ai_error {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('e')) {
+                self.show_ai_error = false;
+            }
+            return Ok(());
+        }
+        if !self.edit_completions.is_empty() && self.handle_completion_key(key) {
+            return Ok(());
+        }
+
+        match key.code {
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.show_ai_error = self.ai_error.is_some();
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.edit_mode == EditMode::Manual {
+                    self.save_current_edit_row();
+                    self.start_jira_fetch();
+                } else if self.prompt_templates.is_empty() {
+                    self.status_message = Some("No prompt templates yet (add them in Settings > Prompts)".to_string());
+                } else {
+                    self.selected_prompt_template = 0;
+                    self.input_mode = InputMode::PickPromptTemplate;
+                }
+            }
+            // Vouch for a value someone else wrote, without changing it
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if self.edit_mode == EditMode::Manual {
+                    self.save_current_edit_row();
+                    if let Some(source) = self.current_edit_field().and_then(|i| self.edit_field_sources.get_mut(i)) {
+                        *source = ValueSource::Manual;
+                    }
+                }
+            }
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+                self.editing_session_id = None;
+                self.edit_session_name.clear();
+                self.edit_owner.clear();
+                self.edit_agent.clear();
+                self.edit_field_values.clear();
+                self.edit_field_sources.clear();
+                self.edit_mode = EditMode::Manual;
+                self.ai_input.clear();
+                self.ai_context_chars = None;
+            }
+            KeyCode::BackTab if key.modifiers.contains(KeyModifiers::SHIFT) && !self.ai_available => {
+                self.status_message = Some(crate::ai::MISSING_MESSAGE.to_string());
+            }
+            // Shift+Tab cycles between Manual and AI mode
+            KeyCode::BackTab if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                if self.edit_mode == EditMode::Manual {
+                    self.save_current_edit_row();
+                }
+                self.edit_mode = match self.edit_mode {
+                    EditMode::Manual => EditMode::AI,
+                    EditMode::AI => EditMode::Manual,
+                };
+                if self.edit_mode == EditMode::Manual {
+                    self.load_current_edit_row();
+                }
+            }
+            _ => {
+                match self.edit_mode {
+                    EditMode::Manual => self.handle_manual_edit_key(key, total_rows)?,
+                    EditMode::AI => self.handle_ai_edit_key(key)?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_manual_edit_key(&mut self, key: KeyEvent, total_rows: usize) -> Result<()> {
+        match key.code {
+            // Tab on a partly typed field offers the values other cards have
+            KeyCode::Tab if self.open_completions()? => {}
+            KeyCode::Tab | KeyCode::Down => {
+                self.save_current_edit_row();
+                if self.edit_row < total_rows - 1 {
+                    self.edit_row += 1;
+                } else {
+                    self.edit_row = 0;
+                }
+                self.load_current_edit_row();
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                self.save_current_edit_row();
+                if self.edit_row > 0 {
+                    self.edit_row -= 1;
+                } else {
+                    self.edit_row = total_rows - 1;
+                }
+                self.load_current_edit_row();
+            }
+            KeyCode::Enter => {
+                self.save_current_edit_row();
+                self.save_and_close_edit()?;
+            }
+            // The agent row is a choice: ←→ step through the profiles, typing does nothing
+            KeyCode::Left | KeyCode::Right if self.edit_row == AGENT_ROW => {
+                let names: Vec<String> = agent::all().into_iter().map(|a| a.name).collect();
+                let current = names.iter().position(|n| n.eq_ignore_ascii_case(&self.edit_agent)).unwrap_or(0);
+                let next = if key.code == KeyCode::Right {
+                    (current + 1) % names.len()
+                } else {
+                    (current + names.len() - 1) % names.len()
+                };
+                self.edit_agent = names[next].clone();
+            }
+            _ if self.edit_row == AGENT_ROW => {}
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Show the field's earlier values starting with what is typed, unless there are none
+    /// besides the typed value itself. Returns whether the dropdown opened
+    fn open_completions(&mut self) -> Result<bool> {
+        let prefix = self.input_buffer.trim();
+        let Some(field_id) = self.current_edit_field().map(|i| self.fields[i].id) else {
+            return Ok(false);
+        };
+        if prefix.is_empty() {
+            return Ok(false);
+        }
+        let mut values = self.db.distinct_field_values(field_id, prefix)?;
+        values.retain(|value| value != prefix);
+        self.selected_completion = 0;
+        self.edit_completions = values;
+        Ok(!self.edit_completions.is_empty())
+    }
+
+    /// j/k pick from the open dropdown and Enter takes the value. Other keys close it
+    /// and, except Esc, go on to the popup. Returns whether the key was used up
+    fn handle_completion_key(&mut self, key: KeyEvent) -> bool {
+        let count = self.edit_completions.len();
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.selected_completion = (self.selected_completion + 1) % count,
+            KeyCode::Char('k') | KeyCode::Up => self.selected_completion = (self.selected_completion + count - 1) % count,
+            KeyCode::Enter => {
+                self.input_buffer = self.edit_completions.swap_remove(self.selected_completion);
+                self.edit_completions.clear();
+            }
+            KeyCode::Esc => self.edit_completions.clear(),
+            _ => {
+                self.edit_completions.clear();
+                return false;
+            }
+        }
+        true
+    }
+
+    fn handle_ai_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            // Ctrl+Up/Down double or halve the pane context for this popup's fills
+            KeyCode::Up | KeyCode::Down if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let chars = self.ai_context_limit();
+                let chars = if key.code == KeyCode::Up { chars * 2 } else { chars / 2 };
+                self.ai_context_chars = Some(chars.clamp(context::MIN_CHARS, context::MAX_CHARS));
+            }
+            KeyCode::Tab | KeyCode::Down | KeyCode::Up | KeyCode::BackTab => {
+                // In AI mode, navigation just scrolls through fields (read-only view)
+                let total_rows = FIRST_FIELD_ROW + self.edit_field_indices().len();
+                match key.code {
+                    KeyCode::Tab | KeyCode::Down => {
+                        if self.edit_row < total_rows - 1 {
+                            self.edit_row += 1;
+                        } else {
+                            self.edit_row = 0;
+                        }
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        if self.edit_row > 0 {
+                            self.edit_row -= 1;
+                        } else {
+                            self.edit_row = total_rows - 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            // Left/Right choose how much pane content goes along with the prompt
+            KeyCode::Left => {
+                self.ai_context = self.ai_context.prev();
+            }
+            KeyCode::Right => {
+                self.ai_context = self.ai_context.next();
+            }
+            // PageUp/PageDown adjust the number of history lines
+            KeyCode::PageUp if self.ai_context == AiContext::History => {
+                self.ai_history_lines = (self.ai_history_lines + AI_HISTORY_LINES_STEP).min(MAX_AI_HISTORY_LINES);
+            }
+            KeyCode::PageDown if self.ai_context == AiContext::History => {
+                self.ai_history_lines = self.ai_history_lines.saturating_sub(AI_HISTORY_LINES_STEP).max(AI_HISTORY_LINES_STEP);
+            }
+            // Run AI fill with the ai_input prompt
+            KeyCode::Enter if !self.ai_input.is_empty() => {
+                self.run_ai_fill();
+            }
+            KeyCode::Backspace => {
+                self.ai_input.pop();
+            }
+            KeyCode::Char(c) => {
+                self.ai_input.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn handle_paste(&mut self, text: &str) {
+        match self.input_mode {
+            InputMode::EditSession if self.edit_mode == EditMode::AI => {
+                self.ai_input.push_str(text);
+            }
+            InputMode::EditSession if self.edit_row == AGENT_ROW => {}
+            InputMode::EditSession => {
+                self.edit_completions.clear();
+                self.input_buffer.push_str(text);
+            }
+            InputMode::NewSession => {
+                self.input_buffer.push_str(text);
+                self.duplicate_name_warning = false;
+            }
+            InputMode::NewCardField => {
+                self.input_buffer.push_str(text.trim_end_matches(['\r', '\n']));
+            }
+            InputMode::NewFieldName | InputMode::EditFieldName => {
+                self.new_field_name.push_str(text);
+                self.input_error = None;
+            }
+            InputMode::NewFieldDesc | InputMode::EditFieldDesc => {
+                self.new_field_desc.push_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+            }
+            InputMode::NewComment => {
+                self.new_comment_text.push_str(text);
+            }
+            InputMode::PromptTemplateName => {
+                self.new_field_name.push_str(text.trim_end_matches(['\r', '\n']));
+                self.input_error = None;
+            }
+            InputMode::PromptTemplateBody => {
+                self.new_field_desc.push_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+            }
+            InputMode::PlanSessions if !self.confirm_discard_plan => {
+                self.input_buffer.push_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+                self.input_error = None;
+            }
+            InputMode::Notes => {
+                if let Some(editor) = self.notes_editor.as_mut().filter(|e| !e.confirm_discard) {
+                    editor.text.push_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+                }
+            }
+            InputMode::NewEnvVar => {
+                self.input_buffer.push_str(text.trim_end_matches(['\r', '\n']));
+                self.input_error = None;
+            }
+            InputMode::EditStatusNewCardFields => {
+                self.input_buffer.push_str(text);
+                self.input_error = None;
+            }
+            InputMode::EditStatusLabel | InputMode::EditStatusChecklist | InputMode::EditProjectSetting | InputMode::Snooze | InputMode::DueDate | InputMode::BlockedBy | InputMode::QuickFieldValue => {
+                self.input_buffer.push_str(text);
+            }
+            _ => {}
+        }
+    }
+
+    fn save_and_close_edit(&mut self) -> Result<()> {
+        let editing = self.editing_session_id.map(|id| self.fresh_session(id)).transpose()?.flatten();
+        if let Some(session_id) = editing.map(|s| s.id) {
+            if !self.edit_session_name.is_empty() {
+                self.db.update_session_name(session_id, &self.edit_session_name)?;
+            }
+            let owner = self.edit_owner.trim();
+            self.db.update_session_owner(session_id, (!owner.is_empty()).then_some(owner))?;
+            let agent = Some(self.edit_agent.as_str()).filter(|a| !a.is_empty() && *a != agent::DEFAULT);
+            self.db.update_session_agent(session_id, agent)?;
+            for (i, field) in self.fields.iter().enumerate() {
+                if let Some(value) = self.edit_field_values.get(i) {
+                    let source = self.edit_field_sources.get(i).copied().unwrap_or_default();
+                    self.db.set_session_field_value(session_id, field.id, value, source)?;
+                }
+            }
+            self.refresh_sessions()?;
+        }
+        self.input_mode = InputMode::Normal;
+        self.input_buffer.clear();
+        self.editing_session_id = None;
+        self.edit_session_name.clear();
+        self.edit_owner.clear();
+        self.edit_agent.clear();
+        self.edit_field_values.clear();
+        self.edit_field_sources.clear();
+        self.edit_mode = EditMode::Manual;
+        self.ai_input.clear();
+        self.ai_context_chars = None;
+        Ok(())
+    }
+
+    fn run_ai_fill(&mut self) {
+        use crate::ai;
+
+        let fields: Vec<(String, String)> = self.fields
+            .iter()
+            .filter(|f| f.in_ai)
+            .map(|f| (f.name.clone(), f.description.clone()))
+            .collect();
+
+        if fields.is_empty() {
+            self.status_message = Some("Every field is left out of AI fill (a in Settings > Fields)".to_string());
+            return;
+        }
+
+        // Get tmux pane content for the selected context, if available
+        let editing = self.editing_session_id.and_then(|id| self.sessions.iter().find(|s| s.id == id));
+        let tmux_name = editing.and_then(|s| s.tmux_window.as_ref());
+        let card = match editing {
+            Some(session) => self.card_context(session),
+            None => ai::CardContext { project: Some(self.project.name.clone()), status: None },
+        };
+        let pane_content: Option<String> = match self.ai_context {
+            AiContext::None => None,
+            AiContext::VisiblePane => tmux_name.and_then(|name| self.tmux.capture_pane_content(name, None)),
+            AiContext::History => tmux_name.and_then(|name| self.tmux.capture_pane_history(name, self.ai_history_lines)),
+        };
+
+        // Use ai_input as the prompt, with session name as context
+        let prompt = format!("{}\nSession name: {}", self.ai_input, self.edit_session_name);
+        let num_fields = fields.len();
+        let redact_patterns = self.project_setting(ProjectSetting::RedactPatterns).unwrap_or_default();
+        let max_context_chars = self.ai_context_limit();
+
+        // Create channel for receiving results
+        let (tx, rx) = mpsc::channel();
+        self.ai_result_rx = Some(rx);
+        self.ai_running = true;
+
+        // Clear any previous error
+        self.ai_error = None;
+
+        // Spawn background thread
+        thread::spawn(move || {
+            // Patterns were validated when saved; skip them rather than fail if that changed
+            let extra = redact::compile_patterns(&redact_patterns).unwrap_or_default();
+            let (pane_content, redactions) = match pane_content {
+                Some(content) => {
+                    let (content, count) = redact::redact(&content, &extra);
+                    (Some(content), count)
+                }
+                None => (None, 0),
+            };
+            let result = ai::fill_fields(&prompt, &fields, pane_content.as_deref(), max_context_chars, &card)
+                .map(|mut fill| {
+                    fill.values.resize(num_fields, String::new());
+                    fill
+                })
+                .map_err(|e| e.to_string());
+            let _ = tx.send((result, redactions));
+        });
+    }
+
+    /// Characters of pane content the project sends with an AI fill
+    pub fn project_ai_context_chars(&self) -> usize {
+        self.project_setting(ProjectSetting::AiContextChars)
+            .and_then(|v| parse_ai_context_chars(&v).ok().flatten())
+            .unwrap_or(context::DEFAULT_MAX_CHARS)
+    }
+
+    /// Characters of pane content the next fill from the edit popup sends
+    pub fn ai_context_limit(&self) -> usize {
+        self.ai_context_chars.unwrap_or_else(|| self.project_ai_context_chars())
+    }
+
+    /// Jira settings, when both the connection and the field mapping are configured
+    pub fn jira_setup(&self) -> Option<(jira::JiraConfig, JiraFieldMap)> {
+        let config = jira::JiraConfig::from_settings(
+            &self.project_setting(ProjectSetting::JiraUrl).unwrap_or_default(),
+            &self.project_setting(ProjectSetting::JiraAuth).unwrap_or_default(),
+        )?;
+        let spec = self.project_setting(ProjectSetting::JiraFields).unwrap_or_default();
+        let fields = resolve_jira_fields(&self.fields, &spec).ok().flatten()?;
+        Some((config, fields))
+    }
+
+    /// Look up the ticket key typed into the key field; inert unless Jira is configured
+    fn start_jira_fetch(&mut self) {
+        let Some((config, fields)) = self.jira_setup() else { return };
+        if self.jira_rx.is_some() {
+            return;
+        }
+        let input = self.edit_field_values.get(fields.key).cloned().unwrap_or_default();
+        let Some(key) = jira::parse_ticket_key(&input) else {
+            let field = &self.fields[fields.key].name;
+            self.jira_fetch = Some(JiraFetch::Failed(format!("Enter a ticket key like PROJ-123 in {}", field)));
+            return;
+        };
+
+        let (tx, rx) = mpsc::channel();
+        self.jira_rx = Some(rx);
+        self.jira_fetch = Some(JiraFetch::Running(key.clone()));
+        thread::spawn(move || {
+            let _ = tx.send(jira::fetch_issue(&config, &key).map_err(|e| e.to_string()));
+        });
+    }
+
+    fn check_jira_result(&mut self) {
+        let Some(ref rx) = self.jira_rx else { return };
+        let Ok(result) = rx.try_recv() else { return };
+        self.jira_rx = None;
+        self.needs_redraw = true;
+
+        // The popup may have been closed while the request was in flight
+        if self.input_mode != InputMode::EditSession {
+            self.jira_fetch = None;
+            return;
+        }
+        match result {
+            Ok(issue) => {
+                if let Some((_, fields)) = self.jira_setup() {
+                    let mut set = |index: Option<usize>, value: &str| {
+                        if let Some(i) = index.filter(|i| *i < self.edit_field_values.len()) {
+                            self.edit_field_values[i] = value.to_string();
+                            self.edit_field_sources[i] = ValueSource::Import;
+                        }
+                    };
+                    set(Some(fields.key), &issue.key);
+                    set(fields.summary, &issue.summary);
+                    set(fields.url, &issue.url);
+                    self.load_current_edit_row();
+                }
+                self.jira_fetch = Some(JiraFetch::Done(issue.key));
+            }
+            Err(e) => self.jira_fetch = Some(JiraFetch::Failed(e)),
+        }
+    }
+
+    /// Log a claude call for `workbench ai-usage`; a failed write never fails the fill
+    fn record_ai_usage(&self, session_id: Option<i64>, purpose: &str, usage: &crate::ai::Usage) {
+        let recorded = self.db.record_ai_usage(
+            self.project.id,
+            session_id,
+            purpose,
+            usage.model.as_deref(),
+            usage.tokens,
+            usage.cost_usd,
+        );
+        if let Err(e) = recorded {
+            tracing::error!(error = %e, "could not record AI usage");
+        }
+    }
+
+    fn check_ai_result(&mut self) {
+        if let Some(ref rx) = self.ai_result_rx && let Ok((result, redactions)) = rx.try_recv() {
+            self.needs_redraw = true;
+            let redacted_note = match redactions {
+                0 => String::new(),
+                1 => " (1 secret redacted from context)".to_string(),
+                n => format!(" ({} secrets redacted from context)", n),
+            };
+            match result {
+                Ok(fill) => {
+                    self.record_ai_usage(self.editing_session_id, "fill", &fill.usage);
+                    // Update field values with AI suggestions, in the order the fields were sent
+                    for (i, value) in self.ai_field_indices().into_iter().zip(fill.values) {
+                        if i < self.edit_field_values.len() {
+                            self.edit_field_values[i] = value;
+                            self.edit_field_sources[i] = ValueSource::Ai;
+                        }
+                    }
+                    self.ai_error = None;
+                    if redactions > 0 {
+                        self.status_message = Some(format!("AI fill done{}", redacted_note));
+                    }
+                }
+                Err(e) => {
+                    self.ai_error = Some(format!("{}{}", e, redacted_note));
+                    // claude went missing since startup: show the whole message and stop offering AI mode
+                    if !crate::ai::is_available() {
+                        self.ai_available = false;
+                        self.show_ai_error = true;
+                    }
+                }
+            }
+            // Switch back to manual mode to review/edit
+            self.edit_mode = EditMode::Manual;
+            self.edit_row = 0;
+            self.load_current_edit_row();
+            self.ai_input.clear();
+            self.ai_running = false;
+            self.ai_result_rx = None;
+        }
+    }
+
+    /// Index into `fields` of the edit popup row being typed in, if it is a field
+    pub fn current_edit_field(&self) -> Option<usize> {
+        let row = self.edit_row.checked_sub(FIRST_FIELD_ROW)?;
+        self.edit_field_indices().get(row).copied()
+    }
+
+    fn save_current_edit_row(&mut self) {
+        if self.edit_row == 0 {
+            self.edit_session_name = self.input_buffer.clone();
+        } else if self.edit_row == OWNER_ROW {
+            self.edit_owner = self.input_buffer.clone();
+        } else if self.edit_row == AGENT_ROW {
+            // Chosen with ←→, not typed
+        } else if let Some(&field_idx) = self.edit_field_indices().get(self.edit_row - FIRST_FIELD_ROW) {
+            // Typing over a value makes it yours
+            if field_idx < self.edit_field_values.len() && self.edit_field_values[field_idx] != self.input_buffer {
+                self.edit_field_values[field_idx] = self.input_buffer.clone();
+                self.edit_field_sources[field_idx] = ValueSource::Manual;
+            }
+        }
+    }
+
+    fn load_current_edit_row(&mut self) {
+        if self.edit_row == 0 {
+            self.input_buffer = self.edit_session_name.clone();
+        } else if self.edit_row == OWNER_ROW {
+            self.input_buffer = self.edit_owner.clone();
+        } else if self.edit_row == AGENT_ROW {
+            self.input_buffer.clear();
+        } else {
+            let field_idx = self.edit_field_indices().get(self.edit_row - FIRST_FIELD_ROW).copied();
+            match field_idx.and_then(|i| self.edit_field_values.get(i)) {
+                Some(value) => self.input_buffer = value.clone(),
+                None => self.input_buffer.clear(),
+            }
+        }
+    }
+
+    fn handle_move_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.moving_session_id = None;
+            }
+            KeyCode::Char(c @ '1'..='4') => {
+                self.input_mode = InputMode::Normal;
+                let idx = (c as usize) - ('1' as usize);
+                let statuses = Status::all();
+                if idx < statuses.len()
+                    && let Some(session_id) = self.moving_session_id.take()
+                        && let Some(session) = self.fresh_session(session_id)?
+                    {
+                        self.request_move(&session, statuses[idx])?;
+                    }
+                self.moving_session_id = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Move a card as the move popup does: through the target column's checklist when it
+    /// has one, else right away
+    fn request_move(&mut self, session: &Session, to: Status) -> Result<()> {
+        if session.status != to && !self.status_checklist(to).is_empty() {
+            let pending = ChecklistMove { session_id: session.id, from: session.status, to, carried: false };
+            return self.open_checklist(pending);
+        }
+        self.move_session_to(session, to)?;
+        self.refresh_sessions()
+    }
+
+    /// `]`/`[`: move the selected card one column right or left; `}`/`{` (with Shift)
+    /// to the last or first column. The selection follows the card
+    fn step_selected_session(&mut self, key: char) -> Result<()> {
+        let selected = self.selected_session().map(|s| s.id);
+        let Some(session) = selected.map(|id| self.fresh_session(id)).transpose()?.flatten() else {
+            return Ok(());
+        };
+        let statuses = Status::all();
+        let current = statuses.iter().position(|s| *s == session.status).unwrap_or(0);
+        let target = match key {
+            ']' => current + 1,
+            '[' => current.wrapping_sub(1),
+            '}' => statuses.len() - 1,
+            _ => 0,
+        };
+        let Some(&to) = statuses.get(target).filter(|_| target != current) else {
+            let edge = if matches!(key, ']' | '}') { "last" } else { "first" };
+            self.status_message = Some(format!("{} is already in the {} column", session.name, edge));
+            return Ok(());
+        };
+        self.request_move(&session, to)?;
+        if self.input_mode == InputMode::Normal {
+            self.select_session(session.id);
+            self.clamp_row();
+            // Auto-start may have said more already
+            if self.status_message.is_none() {
+                self.status_message = Some(format!("Moved {} to {}", session.name, self.status_label(to)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Show the checklist of the column a card is moving into, with the ticks saved
+    /// the last time this card tried to enter it
+    fn open_checklist(&mut self, pending: ChecklistMove) -> Result<()> {
+        let checked = self.db.checked_items(pending.session_id, pending.to)?;
+        self.checklist_checked = self.status_checklist(pending.to).iter().map(|item| checked.contains(item)).collect();
+        self.selected_checklist_item = 0;
+        self.checklist_move = Some(pending);
+        self.input_mode = InputMode::MoveChecklist;
+        Ok(())
+    }
+
+    fn handle_move_checklist_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(pending) = self.checklist_move else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        let items = self.status_checklist(pending.to).to_vec();
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_checklist_item + 1 < items.len() => {
+                self.selected_checklist_item += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_checklist_item = self.selected_checklist_item.saturating_sub(1);
+            }
+            KeyCode::Char(' ') => {
+                let idx = self.selected_checklist_item;
+                if let (Some(item), Some(checked)) = (items.get(idx), self.checklist_checked.get_mut(idx)) {
+                    *checked = !*checked;
+                    self.db.set_item_checked(pending.session_id, pending.to, item, *checked)?;
+                }
+            }
+            KeyCode::Enter => {
+                let open = self.checklist_checked.iter().filter(|c| !**c).count();
+                if open > 0 {
+                    self.status_message = Some(format!("{} item(s) not checked; o moves anyway", open));
+                } else {
+                    self.finish_checklist_move(pending, Vec::new())?;
+                }
+            }
+            KeyCode::Char('o') => {
+                let skipped = items.into_iter()
+                    .zip(&self.checklist_checked)
+                    .filter(|(_, checked)| !**checked)
+                    .map(|(item, _)| item)
+                    .collect();
+                self.finish_checklist_move(pending, skipped)?;
+            }
+            KeyCode::Esc => {
+                self.checklist_move = None;
+                self.input_mode = InputMode::Normal;
+                if pending.carried {
+                    self.db.update_session_status(pending.session_id, pending.from)?;
+                    self.refresh_sessions()?;
+                    self.select_session(pending.session_id);
+                }
+                self.status_message = Some("Move cancelled".to_string());
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Complete a move held by its checklist. Skipped items are noted on the card
+    /// and in the event log
+    fn finish_checklist_move(&mut self, pending: ChecklistMove, skipped: Vec<String>) -> Result<()> {
+        self.checklist_move = None;
+        self.input_mode = InputMode::Normal;
+        let Some(session) = self.fresh_session(pending.session_id)? else {
+            return Ok(());
+        };
+        self.db.update_session_status(session.id, pending.to)?;
+        if pending.to == Status::InProgress && pending.from != Status::InProgress {
+            self.autostart(&session)?;
+        }
+        if !skipped.is_empty() {
+            let note = format!("Entered {} skipping checklist: {}", self.status_label(pending.to), skipped.join(", "));
+            self.db.log_event(self.project.id, Some(session.id), "checklist_skipped", &note)?;
+            self.db.create_comment(session.id, &note)?;
+            self.status_message = Some(format!("Moved {}, {} checklist item(s) skipped", session.name, skipped.len()));
+        }
+        self.refresh_sessions()?;
+        self.select_session(session.id);
+        Ok(())
+    }
+
+    /// Swap the board for the previously shown project, keeping each one's selection
+    fn switch_to_previous_project(&mut self) -> Result<()> {
+        let Some(target) = self.previous_project_id.map(|id| self.db.get_project(id)).transpose()?.flatten() else {
+            self.status_message = Some("No other project opened yet".to_string());
+            return Ok(());
+        };
+        self.switch_to_project(target, None)
+    }
+
+    /// Open another project's board in place of this one, with `session_id` selected
+    /// (else whatever was selected there last)
+    fn switch_to_project(&mut self, target: Project, session_id: Option<i64>) -> Result<()> {
+        let busy = self.input_mode != InputMode::Normal
+            || !matches!(self.view, View::Kanban | View::Overview)
+            || self.peek_active
+            || self.grabbed_session_id.is_some();
+        if busy {
+            self.status_message = Some("Close the open popup before switching projects".to_string());
+            return Ok(());
+        }
+        if self.background_busy() {
+            self.status_message = Some("Wait for the background job to finish before switching projects".to_string());
+            return Ok(());
+        }
+        if !target.path.is_dir() {
+            self.status_message = Some(format!("{} is gone: {}", target.name, target.path.display()));
+            return Ok(());
+        }
+
+        let selected = self.selected_session().map(|s| s.id.to_string()).unwrap_or_default();
+        self.db.set_project_setting(self.project.id, SETTING_LAST_SELECTED, &selected)?;
+        std::env::set_current_dir(&target.path)?;
+        let repo_root = self.git.get_repo_root(&target.path);
+        let db = Database::new()?;
+        let previous = db.record_active_project(target.id)?;
+        let mut app = Self::for_project(DbHandle::spawn(db), self.tmux.clone(), self.git.clone(), target, repo_root)?;
+        app.previous_project_id = previous;
+        app.theme = self.theme;
+        app.no_color = self.no_color;
+        app.write_status_file = self.write_status_file;
+        app.reload_signal = Arc::clone(&self.reload_signal);
+        let last_selected = app.db.get_project_setting(app.project.id, SETTING_LAST_SELECTED)?.and_then(|v| v.parse().ok());
+        if let Some(id) = session_id.or(last_selected) {
+            app.select_session(id);
+        }
+        app.status_message = Some(format!("Switched to {}", app.project.name));
+        app.project_flash = Some(Instant::now());
+        app.redraw_at(Instant::now() + PROJECT_FLASH);
+        self.release_lock();
+        *self = app;
+        Ok(())
+    }
+
+    /// Quit, unless sessions are waiting for input; then ask first. Ctrl+C always quits
+    fn request_quit(&mut self) {
+        if self.last_tmux_refresh.elapsed() >= TMUX_REFRESH_INTERVAL {
+            self.refresh_tmux_sessions();
+        }
+        if self.waiting_session_names().is_empty() {
+            self.should_quit = true;
+        } else {
+            self.input_mode = InputMode::ConfirmQuit;
+        }
+    }
+
+    /// Names of the sessions currently waiting for input
+    pub fn waiting_session_names(&self) -> Vec<&str> {
+        self.sessions
+            .iter()
+            .filter(|s| self.is_waiting_for_input(s))
+            .map(|s| s.name.as_str())
+            .collect()
+    }
+
+    fn handle_confirm_quit_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => self.should_quit = true,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+    }
+
+    /// `s` switches this client over anyway, `r` opens a read-only window instead
+    fn handle_confirm_shared_attach_key(&mut self, key: KeyEvent) -> AppAction {
+        let action = match key.code {
+            KeyCode::Char('s') | KeyCode::Char('S') | KeyCode::Enter => AppAction::AttachTmux,
+            KeyCode::Char('r') | KeyCode::Char('R') => AppAction::AttachReadOnly,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.shared_attach = None;
+                self.input_mode = InputMode::Normal;
+                return AppAction::None;
+            }
+            _ => return AppAction::None,
+        };
+        self.input_mode = InputMode::Normal;
+        self.shared_attach.take().map_or(AppAction::None, action)
+    }
+
+    fn handle_dead_pane_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        self.input_mode = InputMode::Normal;
+        let Some(session) = self.selected_session() else {
+            return Ok(AppAction::None);
+        };
+        let session_id = session.id;
+        let Some(tmux_name) = session.tmux_window.clone() else {
+            return Ok(AppAction::None);
+        };
+
+        match key.code {
+            KeyCode::Char('r') | KeyCode::Enter => {
+                let agent = agent::for_session(session);
+                let values = command_values(&self.project, session, self.session_working_dir(session));
+                let resume = agent.resume_command(&values).map_err(|e| eyre!("Agent {}: {}", agent.name, e))?;
+                self.tmux.respawn_pane(&tmux_name, resume.as_deref())?;
+                self.refresh_tmux_sessions();
+                return self.attach(session_id, tmux_name);
+            }
+            KeyCode::Char('x') => {
+                self.save_transcript(session_id, &tmux_name);
+                self.tmux.kill_session(&tmux_name);
+                self.db.clear_tmux_session(session_id)?;
+                self.peek_active = false;
+                self.refresh_sessions()?;
+                self.refresh_tmux_sessions();
+                self.status_message = Some("Cleaned up exited session".to_string());
+            }
+            _ => {}
+        }
+        Ok(AppAction::None)
+    }
+
+    fn handle_recreate_worktree_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        self.input_mode = InputMode::Normal;
+        let Some(session_id) = self.recreate_worktree_session_id.take() else {
+            return Ok(AppAction::None);
+        };
+        let recreate = match key.code {
+            KeyCode::Char('y') | KeyCode::Enter => true,
+            KeyCode::Char('n') => false,
+            _ => return Ok(AppAction::None),
+        };
+        let Some(session) = self.fresh_session(session_id)? else {
+            return Ok(AppAction::None);
+        };
+
+        let repo_root = self.repo_root.as_deref().filter(|_| recreate);
+        let recreated = repo_root.map(|root| recreate_worktree(&self.db, &*self.git, self.project.id, root, &session));
+        self.status_message = Some(match recreated {
+            Some(Ok(())) => format!("Recreated the worktree of {}", session.name),
+            Some(Err(e)) => {
+                tracing::error!(session_id, error = %e, "worktree recreation failed");
+                format!("{}; started in the project directory", e)
+            }
+            None => "Started in the project directory".to_string(),
+        });
+        let tmux_name = ensure_tmux_session(&self.db, &*self.tmux, &*self.git, &self.project, &session)?;
+        self.active_tmux_sessions.insert(tmux_name.clone());
+        self.attach(session_id, tmux_name)
+    }
+
+    /// `a` links the card to the terminal holding its name, `c` starts a new one beside it
+    fn handle_adopt_terminal_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        let adopt = match key.code {
+            KeyCode::Char('a') | KeyCode::Enter => true,
+            KeyCode::Char('c') => false,
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.adopt_terminal = None;
+                return Ok(AppAction::None);
+            }
+            _ => return Ok(AppAction::None),
+        };
+        self.input_mode = InputMode::Normal;
+        let Some((session_id, tmux_name)) = self.adopt_terminal.take() else {
+            return Ok(AppAction::None);
+        };
+        let Some(session) = self.fresh_session(session_id)? else {
+            return Ok(AppAction::None);
+        };
+
+        let tmux_name = if adopt {
+            self.db.set_tmux_session(session_id, &tmux_name)?;
+            self.status_message = Some(format!("Adopted the running terminal of {}", session.name));
+            tmux_name
+        } else {
+            ensure_tmux_session(&self.db, &*self.tmux, &*self.git, &self.project, &session)?
+        };
+        self.active_tmux_sessions.insert(tmux_name.clone());
+        self.attach(session_id, tmux_name)
+    }
+
+    /// Whether restarting the session's terminal can resume its agent's conversation
+    pub fn can_resume(&self, session: &Session) -> bool {
+        agent::for_session(session).resume.is_some()
+    }
+
+    /// `f` restarts the terminal fresh, `r` with the agent's resume command
+    fn handle_restart_terminal_key(&mut self, key: KeyEvent) -> Result<()> {
+        let resume = match key.code {
+            KeyCode::Char('f') | KeyCode::Enter => false,
+            KeyCode::Char('r') => true,
+            KeyCode::Char('n') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.restart_session_id = None;
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        let Some(session_id) = self.restart_session_id else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        let Some(session) = self.fresh_session(session_id)? else {
+            self.input_mode = InputMode::Normal;
+            self.restart_session_id = None;
+            return Ok(());
+        };
+        if resume && !self.can_resume(&session) {
+            return Ok(());
+        }
+        self.input_mode = InputMode::Normal;
+        self.restart_session_id = None;
+        if let Some(ref tmux_name) = session.tmux_window {
+            self.save_transcript(session_id, tmux_name);
+        }
+
+        match restart_tmux_session(&self.db, &*self.tmux, &*self.git, &self.project, &session, resume) {
+            Ok(_) => {
+                self.status_message =
+                    Some(format!("Restarted the terminal of {}{}", session.name, if resume { ", resumed" } else { "" }));
+            }
+            Err(e) => {
+                tracing::error!(session_id, error = %e, "terminal restart failed");
+                self.status_message = Some(e.to_string());
+                self.refresh_sessions()?;
+            }
+        }
+        self.refresh_tmux_sessions();
+        Ok(())
+    }
+
+    fn handle_card_actions_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.selected_card_action + 1 < self.card_actions.len() => {
+                self.selected_card_action += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_card_action = self.selected_card_action.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                self.input_mode = InputMode::Normal;
+                let Some(action) = self.card_actions.get(self.selected_card_action).copied() else {
+                    return Ok(());
+                };
+                let Some(session) = self.selected_session().cloned() else { return Ok(()) };
+                let result = match action {
+                    CardAction::RestartTerminal => {
+                        self.restart_session_id = Some(session.id);
+                        self.input_mode = InputMode::ConfirmRestartTerminal;
+                        None
+                    }
+                    CardAction::CopyWorktreePath => session.checkout_path.as_deref().map(|path| {
+                        let path = path.to_string_lossy();
+                        desktop::copy_to_clipboard(&path).map(|_| format!("Copied {}", path))
+                    }),
+                    CardAction::CopyBranch => session.branch_name.as_deref()
+                        .map(|branch| desktop::copy_to_clipboard(branch).map(|_| format!("Copied {}", branch))),
+                    CardAction::RevealInFileManager => session.checkout_path.as_deref()
+                        .map(|path| desktop::reveal_in_file_manager(path).map(|_| format!("Opened {}", path.display()))),
+                    CardAction::CopyAttachCommand => session.tmux_window.as_deref().map(|name| {
+                        let command = format!("tmux attach -t {}", name);
+                        desktop::copy_to_clipboard(&command).map(|_| format!("Copied \"{}\"", command))
+                    }),
+                    CardAction::UseCheckedOutBranch => match self.branch_out_of_sync(&session).cloned() {
+                        Some(git::Head::Branch(branch)) => Some(
+                            self.db
+                                .update_session_branch(session.id, &branch)
+                                .and_then(|_| self.refresh_sessions())
+                                .map(|_| format!("Stored branch is now {}", branch)),
+                        ),
+                        _ => None,
+                    },
+                    CardAction::CheckOutStoredBranch => {
+                        session.checkout_path.as_deref().zip(session.branch_name.as_deref()).map(|(path, branch)| {
+                            let result = self.git.checkout_branch(path, branch).map(|_| format!("Checked out {}", branch));
+                            match self.git.head(path) {
+                                Some(head) => self.worktree_heads.insert(session.id, head),
+                                None => self.worktree_heads.remove(&session.id),
+                            };
+                            result
+                        })
+                    }
+                };
+                self.status_message = result.map(|r| r.unwrap_or_else(|e| e.to_string()));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_hints_key(&mut self, key: KeyEvent) -> Result<AppAction> {
+        let target = match key.code {
+            KeyCode::Char(c @ '1'..='9') => self.hint_targets.get(c as usize - '1' as usize).copied(),
+            _ => None,
+        };
+        self.input_mode = InputMode::Normal;
+        self.hint_targets.clear();
+        match target {
+            Some(session_id) => {
+                self.select_session(session_id);
+                self.handle_enter_key()
+            }
+            None => Ok(AppAction::None),
+        }
+    }
+
+    /// `;`: set one field of the selected card, or of every marked card, without the edit
+    /// popup. The field picker starts on the field set last; with a single field it is skipped
+    fn open_quick_field(&mut self) {
+        let Some(session_id) = self.selected_session().map(|s| s.id) else { return };
+        if self.fields.is_empty() {
+            self.status_message = Some("No fields yet (add them in Settings > Fields)".to_string());
+            return;
+        }
+        self.selected_quick_field = self
+            .last_quick_field_id
+            .and_then(|id| self.fields.iter().position(|f| f.id == id))
+            .unwrap_or(0);
+        self.quick_field = Some((session_id, self.fields[self.selected_quick_field].id));
+        if self.fields.len() == 1 {
+            self.start_quick_field_value();
+        } else {
+            self.input_mode = InputMode::PickQuickField;
+        }
+    }
+
+    fn handle_pick_quick_field_key(&mut self, key: KeyEvent) -> Result<()> {
+        let count = self.fields.len();
+        match key.code {
+            KeyCode::Esc => {
+                self.quick_field = None;
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down if count > 0 => {
+                self.selected_quick_field = (self.selected_quick_field + 1) % count;
+            }
+            KeyCode::Char('k') | KeyCode::Up if count > 0 => {
+                self.selected_quick_field = (self.selected_quick_field + count - 1) % count;
+            }
+            KeyCode::Char(c @ '1'..='9') if (c as usize - '1' as usize) < count => {
+                self.selected_quick_field = c as usize - '1' as usize;
+                self.start_quick_field_value();
+            }
+            KeyCode::Enter if count > 0 => self.start_quick_field_value(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Type the picked field's value, starting from the current one
+    fn start_quick_field_value(&mut self) {
+        let (Some((session_id, _)), Some(field)) = (self.quick_field, self.fields.get(self.selected_quick_field)) else {
+            self.input_mode = InputMode::Normal;
+            return;
+        };
+        self.quick_field = Some((session_id, field.id));
+        // Marked cards may all differ, so their value starts out empty
+        self.input_buffer = match self.marked_sessions.is_empty() {
+            true => self.field_value(session_id, field.id).to_string(),
+            false => String::new(),
+        };
+        self.quick_field_only_empty = false;
+        self.input_error = None;
+        self.input_mode = InputMode::QuickFieldValue;
+    }
+
+    /// Set the field on every marked card in one transaction, reporting how many changed
+    fn set_marked_field(&mut self, field_id: i64) -> Result<()> {
+        let ids: Vec<i64> = self.sessions.iter().map(|s| s.id).filter(|id| self.marked_sessions.contains(id)).collect();
+        let value = self.input_buffer.trim();
+        let set = self.db.set_field_value_for_sessions(&ids, field_id, value, ValueSource::Manual, self.quick_field_only_empty)?;
+        self.refresh_field_values();
+        let name = self.fields.iter().find(|f| f.id == field_id).map_or("the field", |f| f.name.as_str());
+        let skipped = ids.len() - set;
+        self.status_message = Some(match skipped {
+            0 => format!("Set {} on {} card{}", name, set, if set == 1 { "" } else { "s" }),
+            _ => format!("Set {} on {} card{}, {} already had a value", name, set, if set == 1 { "" } else { "s" }, skipped),
+        });
+        Ok(())
+    }
+
+    fn handle_quick_field_value_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.quick_field = None;
+                self.input_buffer.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                // Cards read their field values as they're drawn, so the next frame shows
+                // the change without reloading the board
+                if let Some((session_id, field_id)) = self.quick_field.take() {
+                    if self.marked_sessions.is_empty() {
+                        let value = self.input_buffer.trim().to_string();
+                        self.set_field_value(session_id, field_id, &value, ValueSource::Manual)?;
+                    } else {
+                        self.set_marked_field(field_id)?;
+                    }
+                    self.last_quick_field_id = Some(field_id);
+                }
+                self.input_buffer.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Tab if !self.marked_sessions.is_empty() => {
+                self.quick_field_only_empty = !self.quick_field_only_empty;
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Name a card the one being edited waits on; an empty name clears its blockers
+    fn handle_blocked_by_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.blocking_session_id = None;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                let Some(session_id) = self.blocking_session_id else {
+                    self.input_mode = InputMode::Normal;
+                    return Ok(());
+                };
+                let query = self.input_buffer.trim();
+                if query.is_empty() {
+                    self.db.clear_blockers(session_id)?;
+                    self.status_message = Some("Cleared blockers".to_string());
+                } else {
+                    let blocker = match match_session(&self.sessions, query) {
+                        SessionMatch::One(session) if session.id == session_id => {
+                            Err("A card can't block itself".to_string())
+                        }
+                        SessionMatch::One(session) => Ok((session.id, session.name.clone())),
+                        SessionMatch::Ambiguous(found) => Err(format!(
+                            "Matches {}",
+                            found.iter().map(|s| s.name.as_str()).collect::<Vec<_>>().join(", ")
+                        )),
+                        SessionMatch::NotFound => Err("No card matches".to_string()),
+                    };
+                    match blocker {
+                        Ok((blocker_id, name)) => {
+                            self.db.add_blocker(session_id, blocker_id)?;
+                            self.status_message = Some(format!("Blocked by {}", name));
+                        }
+                        Err(e) => {
+                            self.input_error = Some(e);
+                            return Ok(());
+                        }
+                    }
+                }
+                self.refresh_sessions()?;
+                self.input_mode = InputMode::Normal;
+                self.blocking_session_id = None;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_due_date_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.due_session_id = None;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                let due = match due::parse(&self.input_buffer, self.today) {
+                    Ok(due) => due,
+                    Err(e) => {
+                        self.input_error = Some(e);
+                        return Ok(());
+                    }
+                };
+                if let Some(session_id) = self.due_session_id {
+                    self.db.set_session_due(session_id, due)?;
+                    self.refresh_sessions()?;
+                    self.clamp_row();
+                    self.status_message = Some(match due {
+                        Some(day) => format!("Due {}", due::format(day)),
+                        None => "Cleared the due date".to_string(),
+                    });
+                }
+                self.input_mode = InputMode::Normal;
+                self.due_session_id = None;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_snooze_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.snoozing_session_id = None;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                let until = match parse_snooze(&self.input_buffer, db::unix_now()) {
+                    Ok(until) => until,
+                    Err(e) => {
+                        self.input_error = Some(e);
+                        return Ok(());
+                    }
+                };
+                if let Some(session_id) = self.snoozing_session_id {
+                    self.db.set_session_snooze(session_id, until)?;
+                    self.refresh_sessions()?;
+                    self.clamp_row();
+                    self.status_message = Some(match until {
+                        Some(until) => format!(
+                            "Snoozed for {} (Z: show snoozed)",
+                            format_duration(Duration::from_secs((until - db::unix_now()).max(0) as u64))
+                        ),
+                        None => "Woke session".to_string(),
+                    });
+                }
+                self.input_mode = InputMode::Normal;
+                self.snoozing_session_id = None;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Grab mode: h/l carry the card across columns, saving each step
+    fn handle_grab_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(session_id) = self.grabbed_session_id else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Enter | KeyCode::Esc | KeyCode::Char('g') => {
+                self.input_mode = InputMode::Normal;
+                self.grabbed_session_id = None;
+                let from = self.grabbed_from.take();
+                if let Some(session) = self.fresh_session(session_id)? {
+                    let to = session.status;
+                    match from {
+                        Some(from) if from != to && !self.status_checklist(to).is_empty() => {
+                            self.open_checklist(ChecklistMove { session_id, from, to, carried: true })?;
+                        }
+                        _ if to == Status::InProgress && from != Some(Status::InProgress) => self.autostart(&session)?,
+                        _ => {}
+                    }
+                }
+            }
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Char('l') | KeyCode::Right => {
+                let statuses = Status::all();
+                let left = matches!(key.code, KeyCode::Char('h') | KeyCode::Left);
+                let target = if left {
+                    self.selected_column.checked_sub(1)
+                } else {
+                    Some(self.selected_column + 1).filter(|c| *c < statuses.len())
+                };
+                if let Some(column) = target {
+                    self.db.update_session_status(session_id, statuses[column])?;
+                    self.refresh_sessions()?;
+                    self.select_session(session_id);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_delete_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let deleting = self.deleting_session_id.map(|id| self.fresh_session(id)).transpose()?.flatten();
+                if let Some(session) = deleting {
+                    // Kill associated tmux session
+                    if let Some(ref tmux_name) = session.tmux_window {
+                        self.save_transcript(session.id, tmux_name);
+                        self.tmux.kill_session(tmux_name);
+                    }
+                    // The worktree stays until the session is purged from the trash
+                    self.db.trash_session(session.id)?;
+                    self.refresh_sessions()?;
+                    self.clamp_row();
+                    self.status_message = Some("Moved to trash (restore from Settings > Trash)".to_string());
+                }
+                self.input_mode = InputMode::Normal;
+                self.deleting_session_id = None;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.deleting_session_id = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_delete_dirty_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                let deleting = self.deleting_session_id.map(|id| self.fresh_session(id)).transpose()?.flatten();
+                if let Some(session) = deleting {
+                    // Kill associated tmux session
+                    if let Some(ref tmux_name) = session.tmux_window {
+                        self.save_transcript(session.id, tmux_name);
+                        self.tmux.kill_session(tmux_name);
+                    }
+                    // The worktree stays until the session is purged from the trash
+                    self.db.trash_session(session.id)?;
+                    self.refresh_sessions()?;
+                    self.clamp_row();
+                    self.status_message = Some("Moved to trash (restore from Settings > Trash)".to_string());
+                }
+                self.input_mode = InputMode::Normal;
+                self.deleting_session_id = None;
+                self.deleting_dirty_status = None;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.deleting_session_id = None;
+                self.deleting_dirty_status = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_delete_field_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                if let Some(field_id) = self.deleting_field_id {
+                    self.db.delete_field(field_id)?;
+                    self.refresh_fields()?;
+                    if self.selected_field >= self.fields.len() && self.selected_field > 0 {
+                        self.selected_field -= 1;
+                    }
+                }
+                self.input_mode = InputMode::Normal;
+                self.deleting_field_id = None;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.deleting_field_id = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_seed_fields_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                for (name, description) in STARTER_FIELDS {
+                    self.db.create_field(self.project.id, name, description)?;
+                }
+                self.db.set_project_setting(self.project.id, SETTING_SEED_FIELDS_PROMPTED, "1")?;
+                self.refresh_fields()?;
+                self.input_mode = InputMode::Normal;
+                self.status_message = Some("Created default fields (press s to edit)".to_string());
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.db.set_project_setting(self.project.id, SETTING_SEED_FIELDS_PROMPTED, "1")?;
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.view = View::Kanban;
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Tab => {
+                self.settings_tab = self.settings_tab.next();
+                if self.settings_tab == SettingsTab::Trash {
+                    self.refresh_trash()?;
+                }
+            }
+            KeyCode::Char('B') => {
+                self.status_message = Some(match self.db.backup_timestamped() {
+                    Ok(path) => format!("Backed up to {}", path.display()),
+                    Err(e) => format!("Backup failed: {}", e),
+                });
+            }
+            _ => match self.settings_tab {
+                SettingsTab::Fields => self.handle_fields_settings_key(key)?,
+                SettingsTab::CardLayout => self.handle_card_layout_settings_key(key)?,
+                SettingsTab::Statuses => self.handle_statuses_settings_key(key)?,
+                SettingsTab::Project => self.handle_project_settings_key(key)?,
+                SettingsTab::Shortcuts => self.handle_shortcuts_settings_key(key)?,
+                SettingsTab::Watch => self.handle_watch_settings_key(key)?,
+                SettingsTab::Prompts => self.handle_prompts_settings_key(key)?,
+                SettingsTab::Trash => self.handle_trash_settings_key(key)?,
+            },
+        }
+        Ok(())
+    }
+
+    pub fn refresh_trash(&mut self) -> Result<()> {
+        self.trashed_sessions = self.db.list_trashed_sessions(self.project.id)?;
+        if self.selected_trash >= self.trashed_sessions.len() {
+            self.selected_trash = self.trashed_sessions.len().saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    fn handle_trash_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_trash + 1 < self.trashed_sessions.len() => {
+                self.selected_trash += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_trash > 0 => {
+                self.selected_trash -= 1;
+            }
+            KeyCode::Char('u') => {
+                if let Some(trashed) = self.trashed_sessions.get(self.selected_trash) {
+                    let name = trashed.session.name.clone();
+                    self.db.restore_session(trashed.session.id)?;
+                    self.refresh_trash()?;
+                    self.refresh_sessions()?;
+                    self.status_message = Some(format!("Restored \"{}\"", name));
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(trashed) = self.trashed_sessions.get(self.selected_trash) {
+                    self.purging_session_id = Some(trashed.session.id);
+                    self.input_mode = InputMode::ConfirmPurgeSession;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_purge_session_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                if let Some(session_id) = self.purging_session_id {
+                    let checkout = self.trashed_sessions.iter()
+                        .find(|t| t.session.id == session_id)
+                        .and_then(|t| t.session.checkout_path.clone());
+                    // Purging is what loses the work, so a dirty worktree goes too
+                    if let (Some(repo_root), Some(checkout_path)) = (&self.repo_root, &checkout) {
+                        let _ = self.git.remove_worktree(repo_root, checkout_path, true);
+                    }
+                    self.db.purge_session(session_id)?;
+                    self.refresh_trash()?;
+                }
+                self.input_mode = InputMode::Normal;
+                self.purging_session_id = None;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.purging_session_id = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_statuses_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        let statuses = Status::all();
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_status < statuses.len() - 1 => {
+                self.selected_status += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_status > 0 => {
+                self.selected_status -= 1;
+            }
+            KeyCode::Char('e') => {
+                let status = statuses[self.selected_status];
+                self.input_buffer = self.status_overrides
+                    .iter()
+                    .find(|o| o.status == status)
+                    .map(|o| o.label.clone())
+                    .unwrap_or_default();
+                self.input_mode = InputMode::EditStatusLabel;
+            }
+            KeyCode::Char('c') => {
+                let status = statuses[self.selected_status];
+                let current = self.status_color(status).unwrap_or("");
+                let idx = STATUS_COLORS.iter().position(|c| *c == current).unwrap_or(0);
+                let next = STATUS_COLORS[(idx + 1) % STATUS_COLORS.len()];
+                self.db.set_status_color(self.project.id, status, next)?;
+                self.refresh_status_overrides()?;
+            }
+            KeyCode::Char('l') => {
+                let status = statuses[self.selected_status];
+                self.input_buffer = self.status_checklist(status).join("; ");
+                self.input_mode = InputMode::EditStatusChecklist;
+            }
+            KeyCode::Char('f') => {
+                let status = statuses[self.selected_status];
+                let names: Vec<&str> = self.new_card_fields(status).iter().map(|f| f.name.as_str()).collect();
+                self.input_buffer = names.join("; ");
+                self.input_error = None;
+                self.input_mode = InputMode::EditStatusNewCardFields;
+            }
+            KeyCode::Char('o') => {
+                let status = statuses[self.selected_status];
+                let sort = match self.column_sort(status) {
+                    ColumnSort::Created => ColumnSort::Activity,
+                    ColumnSort::Activity => ColumnSort::Created,
+                };
+                self.db.set_status_sort(self.project.id, status, sort)?;
+                self.refresh_status_overrides()?;
+            }
+            KeyCode::Char('r') => {
+                let status = statuses[self.selected_status];
+                self.db.set_status_label(self.project.id, status, "")?;
+                self.db.set_status_color(self.project.id, status, "")?;
+                self.refresh_status_overrides()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Stored value of a project setting (None when unset)
+    pub fn project_setting(&self, setting: ProjectSetting) -> Option<String> {
+        self.project_settings.get(&setting).cloned()
+    }
+
+    /// Read the stored project settings and use the ones that validate; the rest keep
+    /// the value in use, and why each was passed over is returned
+    fn load_project_settings(&mut self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        for setting in ProjectSetting::all() {
+            let Some(value) = self.db.get_project_setting(self.project.id, setting.key())? else {
+                self.project_settings.remove(setting);
+                continue;
+            };
+            match self.validate_project_setting(*setting, value.trim()) {
+                Ok(()) => {
+                    self.project_settings.insert(*setting, value);
+                }
+                Err(e) => problems.push(format!("{}: {} (\"{}\")", setting.label(), e, value)),
+            }
+        }
+        Ok(problems)
+    }
+
+    fn handle_project_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        let settings = ProjectSetting::all();
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_project_setting < settings.len() - 1 => {
+                self.selected_project_setting += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_project_setting > 0 => {
+                self.selected_project_setting -= 1;
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                let setting = settings[self.selected_project_setting];
+                self.input_buffer = self.project_setting(setting).unwrap_or_else(|| match setting {
+                    ProjectSetting::BranchPrefix => git::DEFAULT_BRANCH_PREFIX.to_string(),
+                    _ => String::new(),
+                });
+                self.input_error = None;
+                self.input_mode = InputMode::EditProjectSetting;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Re-read everything the board keeps from the project's settings, which another
+    /// workbench or a hand edit of the database may have changed, re-resolve the theme,
+    /// poll interval and title from the environment, and look for claude again. A stored
+    /// value that no longer validates is listed in a popup and the value in use is kept
+    pub fn reload_settings(&mut self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        let fields = self.db.list_fields(self.project.id)?;
+        let bad_field = fields.iter().find_map(|f| validate_field_name(&fields, &f.name, Some(f.id)).err());
+        match bad_field {
+            Some(e) => problems.push(format!("Fields: {} (kept the fields loaded before)", e)),
+            None => self.fields = fields,
+        }
+        let overrides = self.db.list_status_overrides(self.project.id)?;
+        match overrides.iter().find(|o| !STATUS_COLORS.contains(&o.color.as_str())) {
+            Some(o) => problems.push(format!(
+                "Columns: unknown color \"{}\" for {} (kept the columns loaded before)",
+                o.color,
+                o.status.label()
+            )),
+            None => self.status_overrides = overrides,
+        }
+        let shortcuts = self.db.list_shortcuts(self.project.id)?;
+        match shortcuts.iter().find_map(|s| Some((s, self.shortcut_problem(s)?))) {
+            Some((s, e)) => problems.push(format!("Shortcut \"{}\": {} (kept the shortcuts loaded before)", s.name, e)),
+            None => {
+                self.shortcuts = shortcuts;
+                self.selected_shortcut = self.selected_shortcut.min(self.shortcuts.len().saturating_sub(1));
+            }
+        }
+        self.refresh_prompt_templates()?;
+        let rules = self.db.list_watch_rules(self.project.id)?;
+        match rules.iter().find(|rule| Regex::new(&rule.pattern).is_err()) {
+            Some(rule) => problems.push(format!(
+                "Watch rule /{}/: pattern doesn't compile (kept the rules loaded before)",
+                rule.pattern
+            )),
+            None => self.refresh_watch_rules()?,
+        }
+        let layout = self.db.get_project_setting(self.project.id, SETTING_CARD_LAYOUT)?;
+        match card::unknown_layout_token(layout.as_deref()) {
+            Some(token) => problems.push(format!("Card layout: unknown line \"{}\" (kept the layout in use)", token)),
+            None => self.card_layout = card::parse_layout(layout.as_deref()),
+        }
+
+        problems.extend(self.load_project_settings()?);
+        self.stale_days = self.project_setting(ProjectSetting::StaleDays)
+            .and_then(|v| parse_days(&v).ok().flatten())
+            .unwrap_or(DEFAULT_STALE_DAYS);
+        self.archive_days = self.project_setting(ProjectSetting::ArchiveDays)
+            .and_then(|v| parse_days(&v).ok().flatten())
+            .unwrap_or(DEFAULT_ARCHIVE_DAYS);
+        if let Some(e) = agent::reload() {
+            problems.push(format!("Agents: {} (kept the profiles loaded before)", e));
+        }
+        problems.extend(agent::template_problems());
+        self.ai_available = crate::ai::is_available();
+        self.theme = crate::theme::Theme::detect(self.no_color);
+        self.poll_interval = poll_interval_from_env();
+        self.title_format = status::title_format_from_env();
+        self.refresh_sessions()?;
+        self.refresh_card_details();
+        self.clamp_row();
+
+        if problems.is_empty() {
+            self.status_message = Some("Settings reloaded".to_string());
+        } else {
+            self.reload_problems = problems;
+            self.input_mode = InputMode::ReloadProblems;
+        }
+        Ok(())
+    }
+
+    /// Why a value can't be stored for `setting`, if it can't
+    fn validate_project_setting(&self, setting: ProjectSetting, value: &str) -> Result<(), String> {
+        match setting {
+            ProjectSetting::BranchPrefix => git::validate_branch_prefix(value),
+            ProjectSetting::BranchMaxLen => parse_branch_max_len(value).map(|_| ()),
+            ProjectSetting::BaseBranch if value.chars().any(char::is_whitespace) => {
+                Err("Branch cannot contain spaces".to_string())
+            }
+            ProjectSetting::BaseBranch => Ok(()),
+            ProjectSetting::StartupCommand => startup::expand(value, &startup::CommandValues::sample()).map(|_| ()),
+            ProjectSetting::Autostart if !matches!(value, "" | "on" | "off") => {
+                Err("Use on or off".to_string())
+            }
+            ProjectSetting::Autostart => Ok(()),
+            ProjectSetting::SkipBlocked if !matches!(value, "" | "on" | "off") => {
+                Err("Use on or off".to_string())
+            }
+            ProjectSetting::SkipBlocked => Ok(()),
+            ProjectSetting::StaleDays | ProjectSetting::ArchiveDays => parse_days(value).map(|_| ()),
+            ProjectSetting::Transcripts if !matches!(value, "" | "on" | "off") => {
+                Err("Use on or off".to_string())
+            }
+            ProjectSetting::Transcripts => Ok(()),
+            ProjectSetting::TranscriptMaxKb => parse_transcript_max_kb(value).map(|_| ()),
+            ProjectSetting::WatchDryRun if !matches!(value, "" | "on" | "off") => {
+                Err("Use on or off".to_string())
+            }
+            ProjectSetting::WatchDryRun => Ok(()),
+            ProjectSetting::WatchCooldown => parse_watch_cooldown(value).map(|_| ()),
+            ProjectSetting::RedactPatterns => redact::compile_patterns(value).map(|_| ()),
+            ProjectSetting::AiContextChars => parse_ai_context_chars(value).map(|_| ()),
+            ProjectSetting::NotesPath => notes::path_for(value, Path::new("/w"), 1).map(|_| ()),
+            ProjectSetting::TrackTime if !matches!(value, "" | "on" | "off") => {
+                Err("Use on or off".to_string())
+            }
+            ProjectSetting::TrackTime => Ok(()),
+            ProjectSetting::JiraUrl if !value.is_empty() && !value.starts_with("http") => {
+                Err("Use a full URL, e.g. https://acme.atlassian.net".to_string())
+            }
+            ProjectSetting::JiraAuth if value.chars().any(char::is_whitespace) => {
+                Err("Use VAR or email:VAR (the token itself stays in the environment)".to_string())
+            }
+            ProjectSetting::JiraFields => resolve_jira_fields(&self.fields, value).map(|_| ()),
+            ProjectSetting::JiraUrl | ProjectSetting::JiraAuth => Ok(()),
+        }
+    }
+
+    fn handle_edit_project_setting_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                let setting = ProjectSetting::all()[self.selected_project_setting];
+                let value = self.input_buffer.trim().to_string();
+                let validation = self.validate_project_setting(setting, &value);
+                if let Err(e) = validation {
+                    self.input_error = Some(e);
+                    return Ok(());
+                }
+                self.db.set_project_setting(self.project.id, setting.key(), &value)?;
+                self.project_settings.insert(setting, value);
+                if setting == ProjectSetting::StaleDays {
+                    self.stale_days = Self::load_stale_days(&self.db, self.project.id)?;
+                }
+                if setting == ProjectSetting::ArchiveDays {
+                    self.archive_days = Self::load_archive_days(&self.db, self.project.id)?;
+                    self.refresh_sessions()?;
+                }
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Char('t')
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && ProjectSetting::all()[self.selected_project_setting] == ProjectSetting::StartupCommand =>
+            {
+                self.start_startup_trial();
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// What the startup command's placeholders stand for: the selected session's values
+    /// when there is one, else samples
+    pub fn startup_command_values(&self) -> startup::CommandValues {
+        match self.selected_session() {
+            Some(session) => command_values(&self.project, session, self.session_working_dir(session)),
+            None => startup::CommandValues::sample(),
+        }
+    }
+
+    /// Where a session's terminal would start, for previews: its worktree while the
+    /// directory is there, else the project (cheaper than `usable_checkout`, for redraws)
+    fn session_working_dir<'a>(&'a self, session: &'a Session) -> &'a Path {
+        session.checkout_path.as_deref().filter(|path| path.is_dir()).unwrap_or(&self.project.path)
+    }
+
+    /// Try the command being edited in a throwaway tmux session, in the background
+    fn start_startup_trial(&mut self) {
+        let values = self.startup_command_values();
+        let dir = match self.selected_session() {
+            Some(session) => self.session_working_dir(session).to_path_buf(),
+            None => self.project.path.clone(),
+        };
+        let (command, result) = match startup::expand(self.input_buffer.trim(), &values) {
+            Ok(command) if command.is_empty() => {
+                self.input_error = Some("Nothing to run".to_string());
+                return;
+            }
+            Ok(command) => {
+                let (tx, rx) = mpsc::channel();
+                let (tmux, trial) = (self.tmux.clone(), command.clone());
+                thread::spawn(move || {
+                    let _ = tx.send(tmux.trial_run(&trial, &dir, STARTUP_TRIAL_TIMEOUT).map_err(|e| e.to_string()));
+                });
+                self.startup_trial_rx = Some(rx);
+                (command, None)
+            }
+            Err(e) => {
+                self.input_error = Some(e);
+                return;
+            }
+        };
+        self.startup_trial = Some(StartupTrialView { command, result, scroll: 0 });
+        self.input_mode = InputMode::StartupTrial;
+    }
+
+    fn check_startup_trial(&mut self) {
+        let Some(ref rx) = self.startup_trial_rx else { return };
+        let Ok(result) = rx.try_recv() else { return };
+        self.startup_trial_rx = None;
+        self.needs_redraw = true;
+        if let Some(view) = self.startup_trial.as_mut() {
+            view.result = Some(result);
+        }
+    }
+
+    fn handle_startup_trial_key(&mut self, key: KeyEvent) {
+        let Some(view) = self.startup_trial.as_mut() else {
+            self.input_mode = InputMode::EditProjectSetting;
+            return;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => view.scroll = view.scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => view.scroll = view.scroll.saturating_sub(1),
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => {
+                // A run still going finishes (and kills its session) on its own thread
+                self.startup_trial = None;
+                self.startup_trial_rx = None;
+                self.input_mode = InputMode::EditProjectSetting;
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_edit_status_label_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+            }
+            KeyCode::Enter => {
+                // An empty label clears the override and falls back to the built-in label
+                let status = Status::all()[self.selected_status];
+                self.db.set_status_label(self.project.id, status, self.input_buffer.trim())?;
+                self.refresh_status_overrides()?;
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_edit_status_checklist_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+            }
+            KeyCode::Enter => {
+                let status = Status::all()[self.selected_status];
+                let items = parse_checklist(&self.input_buffer);
+                self.db.set_status_checklist(self.project.id, status, &items)?;
+                self.refresh_status_overrides()?;
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_edit_status_new_card_fields_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                let mut field_ids = Vec::new();
+                for name in parse_checklist(&self.input_buffer) {
+                    match self.fields.iter().find(|f| f.name.eq_ignore_ascii_case(&name)) {
+                        Some(field) if !field_ids.contains(&field.id) => field_ids.push(field.id),
+                        Some(_) => {}
+                        None => {
+                            self.input_error = Some(format!("Unknown field \"{}\"", name));
+                            return Ok(());
+                        }
+                    }
+                }
+                let status = Status::all()[self.selected_status];
+                self.db.set_status_new_card_fields(self.project.id, status, &field_ids)?;
+                self.refresh_status_overrides()?;
+                self.input_mode = InputMode::Normal;
+                self.input_buffer.clear();
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_fields_settings_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if !self.fields.is_empty() && self.selected_field < self.fields.len() - 1 => {
+                self.selected_field += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.selected_field > 0 => {
+                self.selected_field -= 1;
+            }
+            KeyCode::Char('n') => {
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+                self.input_mode = InputMode::NewFieldName;
+            }
+            KeyCode::Char('e') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.editing_field_id = Some(field.id);
+                    self.new_field_name = field.name.clone();
+                    self.new_field_desc = field.description.clone();
+                    self.input_mode = InputMode::EditFieldName;
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.expanded_field_id = match self.expanded_field_id {
+                        Some(id) if id == field.id => None,
+                        _ => Some(field.id),
+                    };
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.deleting_field_id = Some(field.id);
+                    self.input_mode = InputMode::ConfirmDeleteField;
+                }
+            }
+            KeyCode::Char('K') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.db.move_field_up(self.project.id, field.id)?;
+                    self.refresh_fields()?;
+                    if self.selected_field > 0 {
+                        self.selected_field -= 1;
+                    }
+                }
+            }
+            KeyCode::Char('J') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.db.move_field_down(self.project.id, field.id)?;
+                    self.refresh_fields()?;
+                    if self.selected_field < self.fields.len() - 1 {
+                        self.selected_field += 1;
+                    }
+                }
+            }
+            KeyCode::Char('v') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.db.toggle_field_visibility(field.id)?;
+                    self.refresh_fields()?;
+                }
+            }
+            KeyCode::Char('p') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.db.toggle_field_in_edit(field.id)?;
+                    self.refresh_fields()?;
+                }
+            }
+            KeyCode::Char('a') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.db.toggle_field_in_ai(field.id)?;
+                    self.refresh_fields()?;
+                }
+            }
+            KeyCode::Char('w') => {
+                if let Some(field) = self.fields.get(self.selected_field) {
+                    self.db.toggle_field_wrap(field.id)?;
+                    self.refresh_fields()?;
+                }
+            }
+            KeyCode::Char('c') => {
+                self.copy_sources = Vec::new();
+                for project in self.db.list_projects()? {
+                    if project.id == self.project.id {
+                        continue;
+                    }
+                    let fields = self.db.list_fields(project.id)?;
+                    if !fields.is_empty() {
+                        self.copy_sources.push((project, fields));
+                    }
+                }
+                if self.copy_sources.is_empty() {
+                    self.status_message = Some("No other project has fields to copy".to_string());
+                } else {
+                    self.selected_copy_source = 0;
+                    self.input_mode = InputMode::CopyFieldsFrom;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_copy_fields_from_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down if self.selected_copy_source + 1 < self.copy_sources.len() => {
+                self.selected_copy_source += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_copy_source = self.selected_copy_source.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                let Some((source, fields)) = self.copy_sources.get(self.selected_copy_source) else {
+                    return Ok(());
+                };
+                let definitions: Vec<db::FieldDefinition> = fields.iter().map(Into::into).collect();
+                let source_name = source.name.clone();
+                self.status_message = Some(match self.db.import_fields(self.project.id, &definitions) {
+                    Ok(import) if import.skipped.is_empty() => {
+                        format!("Copied {} field(s) from {}", import.created.len(), source_name)
+                    }
+                    Ok(import) => format!(
+                        "Copied {} field(s) from {}; skipped existing: {}",
+                        import.created.len(),
+                        source_name,
+                        import.skipped.join(", ")
+                    ),
+                    Err(e) => format!("Nothing copied: {}", e),
+                });
+                self.refresh_fields()?;
+                self.copy_sources.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.copy_sources.clear();
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_new_field_name_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                self.new_field_name = self.new_field_name.trim().to_string();
+                match validate_field_name(&self.fields, &self.new_field_name, None) {
+                    Ok(()) => self.input_mode = InputMode::NewFieldDesc,
+                    Err(e) => self.input_error = Some(e),
+                }
+            }
+            KeyCode::Backspace => {
+                self.new_field_name.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.new_field_name.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_new_field_desc_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+            }
+            _ if is_newline_key(&key) => {
+                self.new_field_desc.push('\n');
+            }
+            KeyCode::Enter => {
+                self.db.create_field(self.project.id, &self.new_field_name, &self.new_field_desc)?;
+                self.refresh_fields()?;
+                self.input_mode = InputMode::Normal;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+            }
+            KeyCode::Backspace => {
+                self.new_field_desc.pop();
+            }
+            KeyCode::Char(c) => {
+                self.new_field_desc.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_edit_field_name_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.editing_field_id = None;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+                self.input_error = None;
+            }
+            KeyCode::Enter => {
+                self.new_field_name = self.new_field_name.trim().to_string();
+                match validate_field_name(&self.fields, &self.new_field_name, self.editing_field_id) {
+                    Ok(()) => self.input_mode = InputMode::EditFieldDesc,
+                    Err(e) => self.input_error = Some(e),
+                }
+            }
+            KeyCode::Backspace => {
+                self.new_field_name.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.new_field_name.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_edit_field_desc_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_mode = InputMode::Normal;
+                self.editing_field_id = None;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+            }
+            _ if is_newline_key(&key) => {
+                self.new_field_desc.push('\n');
+            }
+            KeyCode::Enter => {
+                if let Some(field_id) = self.editing_field_id {
+                    self.db.update_field(field_id, &self.new_field_name, &self.new_field_desc)?;
+                    self.refresh_fields()?;
+                }
+                self.input_mode = InputMode::Normal;
+                self.editing_field_id = None;
+                self.new_field_name.clear();
+                self.new_field_desc.clear();
+            }
+            KeyCode::Backspace => {
+                self.new_field_desc.pop();
+            }
+            KeyCode::Char(c) => {
+                self.new_field_desc.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_view_comments_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.input_mode = InputMode::Normal;
+                self.comments_session_id = None;
+                self.comments.clear();
+                self.comments_scroll = 0;
+            }
+            KeyCode::Char('n') => {
+                self.new_comment_text.clear();
+                self.input_mode = InputMode::NewComment;
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.comments_scroll < self.comments.len().saturating_sub(1) => {
+                self.comments_scroll += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up if self.comments_scroll > 0 => {
+                self.comments_scroll -= 1;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_new_comment_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.new_comment_text.clear();
+                self.input_mode = InputMode::ViewComments;
+            }
+            KeyCode::Enter => {
+                if !self.new_comment_text.is_empty() && let Some(session_id) = self.comments_session_id {
+                    self.db.create_comment(session_id, &self.new_comment_text)?;
+                    self.comments = self.db.list_comments(session_id).unwrap_or_default();
+                    self.comments_scroll = 0;
+                }
+                self.new_comment_text.clear();
+                self.input_mode = InputMode::ViewComments;
+            }
+            KeyCode::Backspace => {
+                self.new_comment_text.pop();
+            }
+            KeyCode::Char(c) => {
+                self.new_comment_text.push(c);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_session_env_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(session_id) = self.env_session_id else {
+            self.input_mode = InputMode::Normal;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                // Variables only reach tmux when it starts the session; offer to update a running one
+                let live = self.sessions.iter().find(|s| s.id == session_id).is_some_and(|s| self.has_active_terminal(s));
+                self.input_mode = if live && !self.env_changed_keys.is_empty() {
+                    InputMode::ConfirmApplyEnv
+                } else {
+                    InputMode::Normal
+                };
+            }
+            KeyCode::Char('j') | KeyCode::Down if self.selected_env + 1 < self.session_env.len() => {
+                self.selected_env += 1;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.selected_env = self.selected_env.saturating_sub(1);
+            }
+            KeyCode::Char('n') => {
+                self.input_buffer.clear();
+                self.input_error = None;
+                self.editing_env_key = None;
+                self.input_mode = InputMode::NewEnvVar;
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some((key, value)) = self.session_env.get(self.selected_env) {
+                    self.input_buffer = format!("{}={}", key, value);
+                    self.input_error = None;
+                    self.editing_env_key = Some(key.clone());
+                    self.input_mode = InputMode::NewEnvVar;
+                }
+            }
+            KeyCode::Char('d') if self.selected_env < self.session_env.len() => {
+                let (key, _) = self.session_env.remove(self.selected_env);
+                self.db.delete_session_env(session_id, &key)?;
+                self.selected_env = self.selected_env.min(self.session_env.len().saturating_sub(1));
+                self.env_changed_keys.push(key);
+            }
+            KeyCode::Char('v') => {
+                self.env_revealed = !self.env_revealed;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_new_env_var_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.input_buffer.clear();
+                self.input_error = None;
+                self.input_mode = InputMode::SessionEnv;
+            }
+            KeyCode::Enter => {
+                let Some(session_id) = self.env_session_id else { return Ok(()) };
+                match parse_env_assignment(&self.input_buffer) {
+                    Ok((key, value)) => {
+                        let replacing = self.editing_env_key.take();
+                        self.db.set_session_env(session_id, &key, &value, replacing.as_deref())?;
+                        self.session_env = self.db.list_session_env(session_id)?;
+                        self.selected_env = self.session_env.iter().position(|(k, _)| *k == key).unwrap_or(0);
+                        // A renamed variable is removed from a running session too
+                        self.env_changed_keys.extend(replacing.filter(|old| *old != key));
+                        self.env_changed_keys.push(key);
+                        self.input_buffer.clear();
+                        self.input_error = None;
+                        self.input_mode = InputMode::SessionEnv;
+                    }
+                    Err(e) => self.input_error = Some(e),
+                }
+            }
+            KeyCode::Backspace => {
+                self.input_buffer.pop();
+                self.input_error = None;
+            }
+            KeyCode::Char(c) => {
+                self.input_buffer.push(c);
+                self.input_error = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_confirm_apply_env_key(&mut self, key: KeyEvent) -> Result<()> {
+        self.input_mode = InputMode::Normal;
+        let changed = std::mem::take(&mut self.env_changed_keys);
+        if !matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter) {
+            return Ok(());
+        }
+        let tmux_name = self
+            .env_session_id
+            .and_then(|id| self.sessions.iter().find(|s| s.id == id))
+            .and_then(|s| s.tmux_window.clone());
+        let Some(tmux_name) = tmux_name else { return Ok(()) };
+
+        let mut keys = changed;
+        keys.sort();
+        keys.dedup();
+        for key in &keys {
+            let value = self.session_env.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+            if let Err(e) = self.tmux.set_environment(&tmux_name, key, value) {
+                self.status_message = Some(e.to_string());
+                return Ok(());
+            }
+        }
+        self.status_message = Some(format!(
+            "Updated {} variable(s); running shells keep their old values, new panes get the new ones",
+            keys.len()
+        ));
+        Ok(())
+    }
+
+    fn clamp_row(&mut self) {
+        let status = Status::all()[self.selected_column];
+        let count = self.sessions_by_status(status).len();
+        if count == 0 {
+            self.selected_row = 0;
+        } else if self.selected_row >= count {
+            self.selected_row = count - 1;
+        }
+    }
+}
+
+/// Compact human duration for badges, e.g. "45s", "12m", "3h", "2d"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 60 * 60 {
+        format!("{}m", secs / 60)
+    } else if secs < 24 * 60 * 60 {
+        format!("{}h", secs / (60 * 60))
+    } else {
+        format!("{}d", secs / (24 * 60 * 60))
+    }
+}
+
+/// Recorded time as `3h 40m`, `40m`, or `<1m`
+pub fn format_time_spent(seconds: i64) -> String {
+    let minutes = seconds / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "<1m".to_string(),
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+/// Sessions whose stored tmux name is gone. Only a successful listing can prove that;
+/// when tmux couldn't list at all (`None`, e.g. server down) nothing is stale.
+/// Dead but retained panes are still listed, so they keep their names
+fn stale_tmux_sessions(sessions: &[Session], listing: Option<&HashMap<String, tmux::PaneState>>) -> Vec<i64> {
+    let Some(listing) = listing else { return Vec::new() };
+    sessions
+        .iter()
+        .filter(|s| s.tmux_window.as_ref().is_some_and(|name| !listing.contains_key(name)))
+        .map(|s| s.id)
+        .collect()
+}
+
+/// Sessions with a running pane; panes retained after their process exited don't count
+fn live_sessions(pane_states: &HashMap<String, tmux::PaneState>) -> HashSet<String> {
+    pane_states
+        .iter()
+        .filter(|(_, pane)| pane.exit_status.is_none())
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Sessions whose pane shows one of their agent's input prompts; bare shells are skipped
+/// since nothing in them can be asking for input
+fn waiting_sessions(tmux: &dyn TmuxClient, pane_states: &HashMap<String, tmux::PaneState>, sessions: &[Session]) -> HashSet<String> {
+    let agents: HashMap<&str, Agent> = sessions
+        .iter()
+        .filter_map(|s| Some((s.tmux_window.as_deref()?, agent::for_session(s))))
+        .collect();
+    pane_states
+        .iter()
+        .filter(|(_, pane)| pane.exit_status.is_none() && !tmux::is_shell(&pane.command))
+        .filter(|(name, _)| match agents.get(name.as_str()) {
+            Some(agent) => tmux.is_waiting_for_input(name, agent),
+            None => tmux.is_waiting_for_input(name, &agent::named(None)),
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// How much longer a session stays snoozed, if it is snoozed
+pub fn snoozed_for(session: &Session, now: i64) -> Option<Duration> {
+    session
+        .snoozed_until
+        .filter(|until| *until > now)
+        .map(|until| Duration::from_secs((until - now) as u64))
+}
+
+/// Parse a snooze input: "3h", "1d", "2w" or a date "YYYY-MM-DD" (midnight UTC)
+/// An empty input means wake now (None)
+pub fn parse_snooze(input: &str, now: i64) -> Result<Option<i64>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("Expected e.g. 3h, 1d, 2w or YYYY-MM-DD, got \"{}\"", input);
+
+    let until = if let Some((y, rest)) = input.split_once('-') {
+        let (m, d) = rest.split_once('-').ok_or_else(invalid)?;
+        let (y, m, d): (i64, u32, u32) = (
+            y.parse().map_err(|_| invalid())?,
+            m.parse().map_err(|_| invalid())?,
+            d.parse().map_err(|_| invalid())?,
+        );
+        if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+            return Err(invalid());
+        }
+        due::days_from_civil(y, m, d) * 24 * 60 * 60
+    } else {
+        let split = input.len() - input.chars().last().map_or(0, char::len_utf8);
+        let count: i64 = input[..split].parse().map_err(|_| invalid())?;
+        let unit = match &input[split..] {
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        now + count * unit
+    };
+
+    if until <= now {
+        return Err("Snooze time must be in the future".to_string());
+    }
+    Ok(Some(until))
+}
+
+/// Check a field name is non-empty and not already used in the project (case-insensitive)
+/// `editing` is the field being renamed, which may keep its own name
+pub fn validate_field_name(fields: &[Field], name: &str, editing: Option<i64>) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Name cannot be empty".to_string());
+    }
+    if fields.iter().any(|f| Some(f.id) != editing && f.name.trim().eq_ignore_ascii_case(name)) {
+        return Err(format!("A field named \"{}\" already exists", name));
+    }
+    Ok(())
+}
+
+/// Pick the session to follow from (id, last activity, started waiting at), all unix seconds
+/// Whichever event happened most recently wins
+pub fn pick_follow_target(candidates: &[(i64, Option<i64>, Option<i64>)]) -> Option<(i64, FollowReason)> {
+    candidates
+        .iter()
+        .flat_map(|(id, activity, waiting_since)| {
+            [
+                activity.map(|t| (t, *id, FollowReason::RecentOutput)),
+                waiting_since.map(|t| (t, *id, FollowReason::StartedWaiting)),
+            ]
+        })
+        .flatten()
+        .max_by_key(|(t, _, reason)| (*t, *reason == FollowReason::StartedWaiting))
+        .map(|(_, id, reason)| (id, reason))
+}
+
+/// Order sessions needing attention: waiting for input (longest first), then Review
+/// (longest in status first), then In Progress sessions idle for over a day
+pub fn build_queue(sessions: &[Session], waiting: &HashMap<i64, Duration>, now: i64) -> Vec<QueueItem> {
+    let age = |since: i64| Duration::from_secs(now.saturating_sub(since).max(0) as u64);
+
+    let mut waiting_items: Vec<(&Session, Duration)> = sessions
+        .iter()
+        .filter_map(|s| waiting.get(&s.id).map(|d| (s, *d)))
+        .collect();
+    waiting_items.sort_by_key(|(_, d)| std::cmp::Reverse(*d));
+
+    let mut review: Vec<&Session> = sessions
+        .iter()
+        .filter(|s| s.status == Status::Review && !waiting.contains_key(&s.id))
+        .collect();
+    review.sort_by_key(|s| s.status_changed_at);
+
+    let mut idle: Vec<&Session> = sessions
+        .iter()
+        .filter(|s| {
+            s.status == Status::InProgress
+                && !waiting.contains_key(&s.id)
+                && now - s.updated_at > STALE_IN_PROGRESS_SECS
+        })
+        .collect();
+    idle.sort_by_key(|s| s.updated_at);
+
+    waiting_items
+        .into_iter()
+        .map(|(s, d)| QueueItem { session_id: s.id, reason: format!("waiting {}", format_duration(d)) })
+        .chain(review.into_iter().map(|s| QueueItem {
+            session_id: s.id,
+            reason: format!("in review {}", format_duration(age(s.status_changed_at))),
+        }))
+        .chain(idle.into_iter().map(|s| QueueItem {
+            session_id: s.id,
+            reason: format!("idle {}", format_duration(age(s.updated_at))),
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn session(id: i64, status: Status, updated_at: i64, status_changed_at: i64) -> Session {
+        Session {
+            id,
+            name: format!("session {}", id),
+            status,
+            tmux_window: None,
+            checkout_path: None,
+            branch_name: None,
+            created_at: 0,
+            updated_at,
+            status_changed_at,
+            snoozed_until: None,
+            owner: None,
+            pinned: false,
+            agent: None,
+            due: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_field_name() {
+        let fields = vec![Field::fixture(1, "Ticket"), Field::fixture(2, "Summary")];
+        assert!(validate_field_name(&fields, "   ", None).is_err());
+        assert!(validate_field_name(&fields, "ticket", None).is_err());
+        assert!(validate_field_name(&fields, "PR URL", None).is_ok());
+        // Renaming a field to its own name is fine, to another field's name is not
+        assert!(validate_field_name(&fields, "Ticket", Some(1)).is_ok());
+        assert!(validate_field_name(&fields, "Summary", Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_oldest_card_since() {
+        let mut old = session(1, Status::Planned, 0, 500);
+        old.created_at = 100;
+        let mut new = session(2, Status::Planned, 0, 50);
+        new.created_at = 300;
+        // Planned ages from creation, other columns from the last column change
+        assert_eq!(oldest_card_since(&[&new, &old], Status::Planned), Some(100));
+        assert_eq!(oldest_card_since(&[&new, &old], Status::Review), Some(50));
+        assert_eq!(oldest_card_since(&[], Status::Planned), None);
+    }
+
+    #[test]
+    fn test_card_actions_omit_inapplicable_entries() {
+        let mut s = session(1, Status::InProgress, 0, 0);
+        assert!(CardAction::available(&s, false, None).is_empty());
+
+        s.branch_name = Some("wb/fix".to_string());
+        assert_eq!(CardAction::available(&s, false, None), vec![CardAction::CopyBranch]);
+
+        s.checkout_path = Some(PathBuf::from("/tmp/fix"));
+        let detached = git::Head::Detached("abc1234".to_string());
+        assert_eq!(CardAction::available(&s, false, Some(&detached)).last(), Some(&CardAction::CheckOutStoredBranch));
+        assert_eq!(
+            CardAction::available(&s, true, None),
+            vec![
+                CardAction::CopyWorktreePath,
+                CardAction::CopyBranch,
+                CardAction::RevealInFileManager,
+                CardAction::CopyAttachCommand,
+                CardAction::RestartTerminal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_template() {
+        let values = vec![
+            ("session_name".to_string(), "auth-fix".to_string()),
+            (placeholder_key("Ticket"), "PROJ-7 ".to_string()),
+            (placeholder_key(" PR URL "), String::new()),
+        ];
+        assert_eq!(placeholder_key(" PR URL "), "pr_url");
+        let (text, unknown) = expand_template("{session_name}: {Ticket} {pr_url}{owner}, {owner} {x y}", &values);
+        assert_eq!(text, "auth-fix: PROJ-7 ,  {x y}");
+        assert_eq!(unknown, vec!["owner".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_shortcut_spec() {
+        let labels: Vec<(Status, String)> = Status::all().iter().map(|s| (*s, s.label().to_string())).collect();
+        let fields = vec![Field::fixture(7, "PR URL")];
+
+        assert_eq!(
+            parse_shortcut_spec("Review PR | review | pr url", &labels, &fields),
+            Ok(("Review PR".to_string(), Status::Review, Some(7)))
+        );
+        assert_eq!(
+            parse_shortcut_spec("Spike | In Progress", &labels, &fields),
+            Ok(("Spike".to_string(), Status::InProgress, None))
+        );
+        assert!(parse_shortcut_spec("Spike", &labels, &fields).is_err());
+        assert!(parse_shortcut_spec("Spike | backlog", &labels, &fields).is_err());
+        assert!(parse_shortcut_spec("Spike | done | Ticket", &labels, &fields).is_err());
+    }
+
+    #[test]
+    fn test_parse_plan() {
+        let labels: Vec<(Status, String)> = Status::all().iter().map(|s| (*s, s.label().to_string())).collect();
+        let card = |name: &str, status: Status, tags: &[&str]| PlannedSession {
+            name: name.to_string(),
+            status,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        };
+
+        let text = "Fix login\n\n   \n  Write docs  @review\nSpike #infra @in_progress #q3\nBump deps @Done\nFix #123\n\t\n";
+        assert_eq!(
+            parse_plan(text, &labels),
+            Ok(vec![
+                card("Fix login", Status::Planned, &[]),
+                card("Write docs", Status::Review, &[]),
+                card("Spike", Status::InProgress, &["infra", "q3"]),
+                card("Bump deps", Status::Done, &[]),
+                card("Fix #123", Status::Planned, &[]),
+            ])
+        );
+        // Only trailing words are suffixes, and a lone @ or # is part of the name
+        assert_eq!(parse_plan("Email @sam about #auth now @", &labels), Ok(vec![card("Email @sam about #auth now @", Status::Planned, &[])]));
+        assert_eq!(parse_plan("", &labels), Ok(vec![]));
+        assert_eq!(parse_plan(" \n\t\n", &labels), Ok(vec![]));
+
+        // Errors name the line as typed, blank lines included
+        assert_eq!(parse_plan("a\n\nb @backlog", &labels), Err("Line 3: unknown column \"backlog\"".to_string()));
+        assert_eq!(parse_plan("a @review @done", &labels), Err("Line 1: more than one @column".to_string()));
+        assert_eq!(parse_plan("@review #x", &labels), Err("Line 1: no name before the suffixes".to_string()));
+    }
+
+    #[test]
+    fn test_parse_watch_rule_spec() {
+        let labels: Vec<(Status, String)> = Status::all().iter().map(|s| (*s, s.label().to_string())).collect();
+        let fields = vec![Field::fixture(7, "PR URL")];
+
+        assert_eq!(
+            parse_watch_rule_spec("All tasks (done|complete) | move Review", &labels, &fields),
+            Ok(("All tasks (done|complete)".to_string(), WatchAction::Move(Status::Review)))
+        );
+        assert_eq!(
+            parse_watch_rule_spec(r"PR: (https://\S+) | set pr url", &labels, &fields),
+            Ok((r"PR: (https://\S+)".to_string(), WatchAction::SetField(7)))
+        );
+        assert_eq!(parse_watch_rule_spec("error | NOTIFY", &labels, &fields), Ok(("error".to_string(), WatchAction::Notify)));
+        assert!(parse_watch_rule_spec("PR: https | set PR URL", &labels, &fields).is_err());
+        assert!(parse_watch_rule_spec("(unclosed | notify", &labels, &fields).is_err());
+        assert!(parse_watch_rule_spec("done | move Backlog", &labels, &fields).is_err());
+        assert!(parse_watch_rule_spec("done", &labels, &fields).is_err());
+    }
+
+    #[test]
+    fn test_new_pane_lines() {
+        let lines = |text: &str| text.lines().map(str::to_string).collect::<Vec<_>>();
+        let before = lines("⏺ Running tests\n\n╭────╮\n│ >  │\n╰────╯");
+        // The screen scrolled up one line and the prompt box stayed put
+        let after = lines("\n✅ All tasks complete\n╭────╮\n│ >  │\n╰────╯");
+        assert_eq!(new_pane_lines(&before, &after), vec!["✅ All tasks complete"]);
+        assert!(new_pane_lines(&after, &after).is_empty());
+        // A repeat of a line already on screen still counts
+        assert_eq!(new_pane_lines(&lines("ok"), &lines("ok\nok")), vec!["ok"]);
+    }
+
+    #[test]
+    fn test_locate_selection() {
+        let columns = vec![vec![1, 2], vec![3], vec![], vec![4, 5, 6]];
+        // Same session, wherever it moved
+        assert_eq!(locate_selection(&columns, Some(5), 0, 0), (3, 1));
+        assert_eq!(locate_selection(&columns, Some(3), 3, 2), (1, 0));
+        // Gone: keep the old spot, clamped to the column
+        assert_eq!(locate_selection(&columns, Some(9), 3, 7), (3, 2));
+        assert_eq!(locate_selection(&columns, Some(9), 2, 1), (2, 0));
+        assert_eq!(locate_selection(&columns, None, 0, 1), (0, 1));
+        assert_eq!(locate_selection(&[], None, 2, 1), (0, 0));
+    }
+
+    #[test]
+    fn test_stale_tmux_sessions() {
+        let mut a = session(1, Status::InProgress, 0, 0);
+        a.tmux_window = Some("workbench-1-1".to_string());
+        let mut b = session(2, Status::InProgress, 0, 0);
+        b.tmux_window = Some("workbench-1-2".to_string());
+        let c = session(3, Status::Planned, 0, 0);
+        let sessions = vec![a, b, c];
+
+        let pane = tmux::PaneState { activity: 0, clients: 0, command: "claude".to_string(), exit_status: None };
+        let listing = HashMap::from([("workbench-1-1".to_string(), pane)]);
+        assert_eq!(stale_tmux_sessions(&sessions, Some(&listing)), vec![2]);
+
+        // An empty listing from a running server means every stored name is gone
+        assert_eq!(stale_tmux_sessions(&sessions, Some(&HashMap::new())), vec![1, 2]);
+
+        // No listing at all (server down): keep everything
+        assert!(stale_tmux_sessions(&sessions, None).is_empty());
+    }
+
+    #[test]
+    fn test_last_meaningful_line() {
+        let pane = "⏺ Fixed the auth bug.\n\n  Should I also fix the flaky login test?\n\n╭──────────╮\n│ >        │\n╰──────────╯\n";
+        assert_eq!(last_meaningful_line(pane).as_deref(), Some("Should I also fix the flaky login test?"));
+        assert_eq!(last_meaningful_line("$ \n> \n---\n"), None);
+
+        let long = "word ".repeat(40);
+        let name = last_meaningful_line(&long).unwrap();
+        assert!(name.ends_with('…'));
+        assert!(name.chars().count() <= SPINOFF_NAME_MAX_CHARS + 1);
+    }
+
+    #[test]
+    fn test_find_urls() {
+        let urls = |line: &str| find_urls(line).into_iter().map(|r| line[r].to_string()).collect::<Vec<_>>();
+        assert_eq!(urls("PR: https://github.com/acme/app/pull/12."), vec!["https://github.com/acme/app/pull/12"]);
+        assert_eq!(urls("(see https://ci.acme.dev/runs/4)"), vec!["https://ci.acme.dev/runs/4"]);
+        assert_eq!(urls("https://en.wikipedia.org/wiki/Rust_(language)"), vec!["https://en.wikipedia.org/wiki/Rust_(language)"]);
+        assert_eq!(urls("[a](http://x.io/a), <https://y.io>"), vec!["http://x.io/a", "https://y.io"]);
+        assert!(urls("no links, just https:// here").is_empty());
+
+        let capture = PeekCapture::new(1, None, String::new(), "https://a.io/1\nagain https://a.io/1 and https://b.io");
+        assert_eq!(capture.urls, vec!["https://a.io/1", "https://b.io"]);
+        assert_eq!(capture.links[1], vec![6..20, 25..37]);
+    }
+
+    #[test]
+    fn test_fill_empty_values() {
+        let ids = [1, 2, 3, 4];
+        let current = ["".to_string(), "kept".to_string(), " ".to_string(), "".to_string()];
+        let suggested = ["Auth".to_string(), "new".to_string(), " Fix login ".to_string(), "".to_string()];
+        assert_eq!(
+            fill_empty_values(&ids, &current, &suggested),
+            vec![(1, "Auth".to_string()), (3, "Fix login".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_match_session() {
+        let named = |id: i64, name: &str| Session { name: name.to_string(), ..session(id, Status::Planned, 0, 0) };
+        let sessions = vec![named(3, "Fix auth"), named(17, "Fix auth tokens"), named(21, "42"), named(8, "Update docs")];
+        let id = |m: SessionMatch| match m {
+            SessionMatch::One(s) => Some(s.id),
+            _ => None,
+        };
+
+        assert_eq!(id(match_session(&sessions, "fix-auth")), Some(3));
+        assert_eq!(id(match_session(&sessions, "17")), Some(17));
+        assert_eq!(id(match_session(&sessions, "42")), Some(21));
+        assert_eq!(id(match_session(&sessions, "tokens")), Some(17));
+        assert_eq!(id(match_session(&sessions, "updocs")), Some(8));
+        match match_session(&sessions, "fix") {
+            SessionMatch::Ambiguous(found) => assert_eq!(found.len(), 2),
+            other => panic!("expected ambiguous, got {:?}", other),
+        }
+        assert!(matches!(match_session(&sessions, "deploy"), SessionMatch::NotFound));
+    }
+
+    #[test]
+    fn test_env_assignment_and_masking() {
+        assert_eq!(parse_env_assignment("AWS_PROFILE=staging"), Ok(("AWS_PROFILE".to_string(), "staging".to_string())));
+        assert_eq!(parse_env_assignment(" OPTS =a=b"), Ok(("OPTS".to_string(), "a=b".to_string())));
+        assert!(parse_env_assignment("AWS_PROFILE").is_err());
+        assert!(parse_env_assignment("1X=y").is_err());
+        assert!(parse_env_assignment("MY-VAR=y").is_err());
+
+        assert!(!is_secret_env("AWS_PROFILE", "staging"));
+        assert!(is_secret_env("github_token", "abc"));
+        assert!(is_secret_env("DATABASE_URL", "postgres://app:hunter2@db/app"));
+        assert!(!is_secret_env("DATABASE_URL", "postgres://localhost/app"));
+    }
+
+    #[test]
+    fn test_resolve_jira_fields() {
+        let fields = vec![Field::fixture(0, "Ticket"), Field::fixture(1, "Summary"), Field::fixture(2, "Ticket URL")];
+
+        assert_eq!(resolve_jira_fields(&fields, " "), Ok(None));
+        assert_eq!(
+            resolve_jira_fields(&fields, "ticket, Summary, ticket url"),
+            Ok(Some(JiraFieldMap { key: 0, summary: Some(1), url: Some(2) }))
+        );
+        assert_eq!(
+            resolve_jira_fields(&fields, "Ticket,,Ticket URL"),
+            Ok(Some(JiraFieldMap { key: 0, summary: None, url: Some(2) }))
+        );
+        assert!(resolve_jira_fields(&fields, "Ticket, Title").is_err());
+        assert!(resolve_jira_fields(&fields, ", Summary").is_err());
+    }
+
+    #[test]
+    fn test_parse_snooze() {
+        let now = 1_000_000;
+        assert_eq!(parse_snooze("", now), Ok(None));
+        assert_eq!(parse_snooze("3h", now), Ok(Some(now + 3 * 60 * 60)));
+        assert_eq!(parse_snooze(" 1w ", now), Ok(Some(now + 7 * 24 * 60 * 60)));
+        assert_eq!(parse_snooze("2030-01-01", now), Ok(Some(1_893_456_000)));
+        assert!(parse_snooze("1970-01-02", 2 * 24 * 60 * 60).is_err());
+        assert!(parse_snooze("soon", now).is_err());
+        assert!(parse_snooze("2030-13-01", now).is_err());
+        assert!(parse_snooze("0d", now).is_err());
+    }
+
+    #[test]
+    fn test_pick_follow_target() {
+        assert_eq!(pick_follow_target(&[]), None);
+        assert_eq!(pick_follow_target(&[(1, None, None)]), None);
+
+        let candidates = [(1, Some(100), None), (2, Some(90), Some(120)), (3, Some(110), None)];
+        assert_eq!(pick_follow_target(&candidates), Some((2, FollowReason::StartedWaiting)));
+
+        let candidates = [(1, Some(130), None), (2, Some(90), Some(120))];
+        assert_eq!(pick_follow_target(&candidates), Some((1, FollowReason::RecentOutput)));
+    }
+
+    #[test]
+    fn test_build_queue_order() {
+        let now = 10 * 24 * 60 * 60;
+        let day = 24 * 60 * 60;
+        let sessions = vec![
+            session(1, Status::InProgress, now - 3 * day, 0),
+            session(2, Status::Review, now, now - day),
+            session(3, Status::Review, now, now - 2 * day),
+            session(4, Status::InProgress, now - 60, 0),
+            session(5, Status::Planned, now, now),
+            session(6, Status::InProgress, now, now),
+        ];
+        let waiting = HashMap::from([(6, Duration::from_secs(23 * 60))]);
+
+        let queue = build_queue(&sessions, &waiting, now);
+        let order: Vec<(i64, &str)> = queue.iter().map(|i| (i.session_id, i.reason.as_str())).collect();
+        assert_eq!(
+            order,
+            vec![(6, "waiting 23m"), (3, "in review 2d"), (2, "in review 1d"), (1, "idle 3d")]
+        );
+    }
+
+    #[test]
+    fn test_parse_checklist() {
+        assert_eq!(parse_checklist(" tests pass ;PR linked;; tests pass"), vec!["tests pass", "PR linked"]);
+        assert!(parse_checklist(" ; ").is_empty());
+    }
+
+    #[test]
+    fn test_idle_poll_interval() {
+        let fast = Duration::from_millis(100);
+        assert_eq!(idle_poll_interval(fast, Duration::ZERO), fast);
+        assert_eq!(idle_poll_interval(fast, Duration::from_secs(5)), IDLE_POLL_INTERVAL);
+        assert_eq!(idle_poll_interval(fast, Duration::from_secs(60)), DEEP_IDLE_POLL_INTERVAL);
+        // Backing off never polls faster than configured
+        let slow = Duration::from_millis(800);
+        assert_eq!(idle_poll_interval(slow, Duration::from_secs(5)), slow);
+    }
+
+    #[test]
+    fn test_missing_worktree_falls_back_and_recreates() {
+        if !git::is_available() {
+            return;
+        }
+        let repo = std::env::temp_dir().join(format!("wb-gone-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        std::fs::create_dir_all(&repo).unwrap();
+        git::init_test_repo(&repo);
+
+        let root = git::get_repo_root(&repo).unwrap();
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("repo", &root).unwrap();
+        let (session, error) = create_session_with_worktree(&db, &git::SystemGit, project.id, Some(&root), "Fix thing", Status::Planned).unwrap();
+        assert_eq!(error, None);
+        let session = db.get_live_session(project.id, session.id).unwrap().unwrap();
+        let checkout = session.checkout_path.clone().unwrap();
+        assert_eq!(usable_checkout(&git::SystemGit, &project, &session), Some(checkout.as_path()));
+
+        // Deleted by hand: git still lists it, but there's nothing to start in
+        std::fs::remove_dir_all(&checkout).unwrap();
+        assert_eq!(usable_checkout(&git::SystemGit, &project, &session), None);
+        // A plain directory at the path isn't a worktree either
+        std::fs::create_dir_all(&checkout).unwrap();
+        assert_eq!(usable_checkout(&git::SystemGit, &project, &session), None);
+        std::fs::remove_dir_all(&checkout).unwrap();
+
+        recreate_worktree(&db, &git::SystemGit, project.id, &root, &session).unwrap();
+        assert_eq!(usable_checkout(&git::SystemGit, &project, &session), Some(checkout.as_path()));
+
+        git::remove_worktree(&root, &checkout, true).unwrap();
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_session_in_non_utf8_repo() {
+        use std::os::unix::ffi::OsStrExt;
+
+        if !git::is_available() {
+            return;
+        }
+        let name = std::ffi::OsStr::from_bytes(&[b"wb-\xff-".as_slice(), std::process::id().to_string().as_bytes()].concat()).to_os_string();
+        let repo = std::env::temp_dir().join(&name);
+        let _ = std::fs::remove_dir_all(&repo);
+        // Some filesystems only accept UTF-8 names
+        if std::fs::create_dir_all(&repo).is_err() {
+            return;
+        }
+
+        /// Removes the worktree and the repo however the test ends
+        struct Cleanup(PathBuf);
+        impl Drop for Cleanup {
+            fn drop(&mut self) {
+                let checkout = git::generate_worktree_path(&self.0, "wb/fix-thing");
+                let _ = git::remove_worktree(&self.0, &checkout, true);
+                let _ = std::fs::remove_dir_all(&checkout);
+                let _ = std::fs::remove_dir_all(&self.0);
+            }
+        }
+        let _cleanup = Cleanup(repo.clone());
+        git::init_test_repo(&repo);
+
+        let root = git::get_repo_root(&repo).unwrap();
+        assert_eq!(root.file_name(), Some(name.as_os_str()));
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("repo", &root).unwrap();
+        let (session, error) = create_session_with_worktree(&db, &git::SystemGit, project.id, Some(&root), "Fix thing", Status::Planned).unwrap();
+        assert_eq!(error, None);
+
+        // The database hands back the exact bytes, and git sees the worktree there
+        assert_eq!(db.list_projects().unwrap()[0].path, root);
+        let session = db.get_live_session(project.id, session.id).unwrap().unwrap();
+        let checkout = session.checkout_path.clone().unwrap();
+        assert_eq!(checkout, git::generate_worktree_path(&root, "wb/fix-thing"));
+        assert_eq!(git::get_repo_root(&checkout), Some(checkout.clone()));
+        assert!(!git::get_dirty_status(&checkout).is_some_and(|s| s.is_dirty()));
+
+        if tmux::is_available() {
+            let _server = tmux::TestServer::start("non-utf8-repo");
+            tmux::create_session("wbtest-utf8", &checkout, &[], Some(tmux::TEST_STARTUP)).unwrap();
+            assert!(tmux::wait_ready("wbtest-utf8"));
+            assert_eq!(tmux::get_pane_cwd("wbtest-utf8"), Some(checkout.clone()));
+        }
+
+        git::remove_worktree(&root, &checkout, true).unwrap();
+    }
+
+    /// tmux without a server: sessions are kept in a list, and each pane shows what
+    /// `screens` holds for its session
+    #[derive(Default)]
+    struct FakeTmux {
+        sessions: Mutex<Vec<(String, PathBuf, Option<String>)>>,
+        screens: Mutex<HashMap<String, String>>,
+        killed: Mutex<Vec<String>>,
+        /// Last activity per session; 0 when unset
+        activity: Mutex<HashMap<String, i64>>,
+        /// Clients attached per session; 0 when unset
+        clients: Mutex<HashMap<String, u32>>,
+        /// Starting a session fails, e.g. tmux lost its server
+        fail_create: Mutex<bool>,
+    }
+
+    impl FakeTmux {
+        fn session_dirs(&self) -> Vec<(String, PathBuf)> {
+            self.sessions.lock().unwrap().iter().map(|(name, dir, _)| (name.clone(), dir.clone())).collect()
+        }
+    }
+
+    impl TmuxClient for FakeTmux {
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        fn session_exists(&self, name: &str) -> bool {
+            self.sessions.lock().unwrap().iter().any(|(n, _, _)| n == name)
+        }
+
+        fn create_session(&self, name: &str, working_dir: &Path, _env: &[(String, String)], startup: Option<&str>) -> Result<()> {
+            if *self.fail_create.lock().unwrap() {
+                return Err(eyre!("no server running"));
+            }
+            let session = (name.to_string(), working_dir.to_path_buf(), startup.map(str::to_string));
+            self.sessions.lock().unwrap().push(session);
+            Ok(())
+        }
+
+        fn kill_session(&self, name: &str) -> bool {
+            let mut sessions = self.sessions.lock().unwrap();
+            let before = sessions.len();
+            sessions.retain(|(n, _, _)| n != name);
+            self.killed.lock().unwrap().push(name.to_string());
+            sessions.len() < before
+        }
+
+        fn respawn_pane(&self, _name: &str, _command: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+
+        fn set_environment(&self, _name: &str, _key: &str, _value: Option<&str>) -> Result<()> {
+            Ok(())
+        }
+
+        fn trial_run(&self, startup: &str, _working_dir: &Path, _timeout: Duration) -> Result<tmux::TrialRun> {
+            Ok(tmux::TrialRun { output: startup.to_string(), finished: true, exit_status: Some(0) })
+        }
+
+        fn poll_workbench_panes(&self) -> Result<HashMap<String, tmux::PaneState>> {
+            let activity = self.activity.lock().unwrap();
+            let clients = self.clients.lock().unwrap();
+            let pane = |name: &String| tmux::PaneState {
+                activity: activity.get(name).copied().unwrap_or(0),
+                clients: clients.get(name).copied().unwrap_or(0),
+                command: "claude".to_string(),
+                exit_status: None,
+            };
+            Ok(self.sessions.lock().unwrap().iter().map(|(name, _, _)| (name.clone(), pane(name))).collect())
+        }
+
+        fn list_project_sessions(&self, project_id: i64) -> Vec<String> {
+            let prefix = format!("workbench-{}-", project_id);
+            self.sessions.lock().unwrap().iter().map(|(n, _, _)| n.clone()).filter(|n| n.starts_with(&prefix)).collect()
+        }
+
+        fn list_panes(&self, _name: &str) -> Vec<tmux::Pane> {
+            Vec::new()
+        }
+
+        fn capture_pane_content(&self, name: &str, _pane: Option<&str>) -> Option<String> {
+            self.screens.lock().unwrap().get(name).cloned()
+        }
+
+        fn pane_cwd(&self, name: &str) -> Option<PathBuf> {
+            self.sessions.lock().unwrap().iter().find(|(n, _, _)| n == name).map(|(_, dir, _)| dir.clone())
+        }
+
+        fn capture_pane_joined(&self, name: &str, pane: Option<&str>) -> Option<String> {
+            self.capture_pane_content(name, pane)
+        }
+
+        fn capture_pane_history(&self, name: &str, _lines: usize) -> Option<String> {
+            self.capture_pane_content(name, None)
+        }
+
+        fn capture_scrollback(&self, name: &str) -> Option<String> {
+            self.capture_pane_content(name, None)
+        }
+    }
+
+    /// git for a repository at `root` that only exists in memory
+    #[derive(Default)]
+    struct FakeGit {
+        root: PathBuf,
+        worktrees: Mutex<Vec<(String, PathBuf)>>,
+        removed: Mutex<Vec<PathBuf>>,
+        /// Checkouts switched away from the branch their worktree was made with
+        heads: Mutex<HashMap<PathBuf, git::Head>>,
+        /// Times a worktree's HEAD was read
+        head_reads: Mutex<usize>,
+        /// Commits every branch has that no remote has
+        unpushed: Mutex<usize>,
+        /// Pulls that fail before one succeeds
+        failing_pulls: Mutex<usize>,
+        deleted_branches: Mutex<Vec<String>>,
+    }
+
+    impl FakeGit {
+        fn new(root: &str) -> Self {
+            Self { root: PathBuf::from(root), ..Self::default() }
+        }
+
+        fn current_head(&self, path: &Path) -> Option<git::Head> {
+            if let Some(head) = self.heads.lock().unwrap().get(path) {
+                return Some(head.clone());
+            }
+            let worktrees = self.worktrees.lock().unwrap();
+            worktrees.iter().find(|(_, p)| p == path).map(|(branch, _)| git::Head::Branch(branch.clone()))
+        }
+    }
+
+    impl GitClient for FakeGit {
+        fn get_repo_root(&self, path: &Path) -> Option<PathBuf> {
+            Some(self.root.clone()).filter(|root| path.starts_with(root))
+        }
+
+        fn get_remote_url(&self, _repo_path: &Path) -> Option<String> {
+            None
+        }
+
+        fn config_value(&self, _dir: Option<&Path>, key: &str) -> Option<String> {
+            Some("Test User".to_string()).filter(|_| key == "user.name")
+        }
+
+        fn detect_default_branch(&self, _repo_path: &Path) -> Option<String> {
+            Some("main".to_string())
+        }
+
+        fn is_valid_branch_name(&self, branch_name: &str) -> bool {
+            !branch_name.is_empty() && !branch_name.contains(' ')
+        }
+
+        fn create_worktree(&self, _repo_path: &Path, branch_name: &str, worktree_path: &Path, _start_point: Option<&str>) -> Result<()> {
+            self.worktrees.lock().unwrap().push((branch_name.to_string(), worktree_path.to_path_buf()));
+            Ok(())
+        }
+
+        fn remove_worktree(&self, _repo_path: &Path, worktree_path: &Path, _force: bool) -> Result<()> {
+            self.worktrees.lock().unwrap().retain(|(_, path)| path != worktree_path);
+            self.removed.lock().unwrap().push(worktree_path.to_path_buf());
+            Ok(())
+        }
+
+        fn prune_worktrees(&self, _repo_path: &Path) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_registered_worktree(&self, _repo_path: &Path, path: &Path) -> bool {
+            self.worktrees.lock().unwrap().iter().any(|(_, p)| p == path)
+        }
+
+        fn is_branch_merged(&self, _repo_path: &Path, _branch_name: &str) -> bool {
+            false
+        }
+
+        fn branch_changes(&self, _dir: &Path, _base: &str, _branch: &str) -> Result<git::BranchChanges, String> {
+            Err("not in this test".to_string())
+        }
+
+        fn get_dirty_status(&self, _path: &Path) -> Option<DirtyStatus> {
+            None
+        }
+
+        fn head(&self, path: &Path) -> Option<git::Head> {
+            *self.head_reads.lock().unwrap() += 1;
+            self.current_head(path)
+        }
+
+        fn head_modified(&self, path: &Path) -> Option<SystemTime> {
+            // The HEAD file is rewritten whenever what it names changes
+            use std::hash::{DefaultHasher, Hash, Hasher};
+            let mut hasher = DefaultHasher::new();
+            format!("{:?}", self.current_head(path)?).hash(&mut hasher);
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(hasher.finish() % 1_000_000_000))
+        }
+
+        fn checkout_branch(&self, path: &Path, branch_name: &str) -> Result<()> {
+            self.heads.lock().unwrap().insert(path.to_path_buf(), git::Head::Branch(branch_name.to_string()));
+            Ok(())
+        }
+
+        fn pull(&self, _repo_path: &Path) -> Result<String> {
+            let mut failing = self.failing_pulls.lock().unwrap();
+            if *failing > 0 {
+                *failing -= 1;
+                return Err(eyre!("git pull failed: Could not resolve host"));
+            }
+            Ok("Already up to date.".to_string())
+        }
+
+        fn delete_branch(&self, _repo_path: &Path, branch_name: &str, _force: bool) -> Result<()> {
+            self.deleted_branches.lock().unwrap().push(branch_name.to_string());
+            Ok(())
+        }
+
+        fn unpushed_commits(&self, _repo_path: &Path, _branch_name: &str) -> Result<usize, String> {
+            Ok(*self.unpushed.lock().unwrap())
+        }
+
+        fn short_commit(&self, _repo_path: &Path, rev: &str) -> Option<String> {
+            Some(if rev == "HEAD" { "beef123" } else { "abc1234" }.to_string())
+        }
+
+        fn merge_commit(&self, _repo_path: &Path, _commit: &str) -> Option<String> {
+            Some("fee0123".to_string())
+        }
+    }
+
+    const TEST_REPO: &str = "/nonexistent/api";
+
+    /// A board on fresh fakes, past the starter fields prompt
+    fn test_board() -> (App, Arc<FakeTmux>, Arc<FakeGit>) {
+        let tmux = Arc::new(FakeTmux::default());
+        let git = Arc::new(FakeGit::new(TEST_REPO));
+        let db = Database::open_in_memory().unwrap();
+        let mut app = App::new_for_test(db, tmux.clone(), git.clone(), Path::new(TEST_REPO)).unwrap();
+        app.inside_tmux = false;
+        assert_eq!(app.input_mode, InputMode::ConfirmSeedFields);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+        (app, tmux, git)
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> AppAction {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE)).unwrap()
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    /// The session as stored, not as the board last loaded it
+    fn stored(app: &App, session_id: i64) -> Session {
+        app.db.get_live_session(app.project.id, session_id).unwrap().unwrap()
+    }
+
+    /// Wait for the worktree HEAD reads a tmux refresh started
+    fn settle_worktree_heads(app: &mut App) {
+        while app.worktree_heads_rx.is_some() {
+            app.check_worktree_heads();
+            thread::yield_now();
+        }
+    }
+
+    fn new_session(app: &mut App, name: &str) -> i64 {
+        press(app, KeyCode::Char('n'));
+        type_text(app, name);
+        press(app, KeyCode::Enter);
+        app.selected_session().unwrap().id
+    }
+
+    #[test]
+    fn test_keys_create_session_with_worktree() {
+        let (mut app, tmux, git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+
+        let session = stored(&app, id);
+        assert_eq!(session.name, "Fix login");
+        assert_eq!(session.status, Status::Planned);
+        assert_eq!(session.owner.as_deref(), Some("Test User"));
+        assert_eq!(session.branch_name.as_deref(), Some("wb/fix-login"));
+        let checkout = git::generate_worktree_path(Path::new(TEST_REPO), "wb/fix-login");
+        assert_eq!(session.checkout_path.as_deref(), Some(checkout.as_path()));
+        assert_eq!(*git.worktrees.lock().unwrap(), vec![("wb/fix-login".to_string(), checkout)]);
+        assert!(tmux.session_dirs().is_empty());
+
+        // The same name again needs a second Enter
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "fix login");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.duplicate_name_warning);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_new_card_fields() {
+        let (mut app, _, _) = test_board();
+        let pr_url = app.db.create_field(app.project.id, "PR URL", "").unwrap();
+        let reviewer = app.db.create_field(app.project.id, "Reviewer", "").unwrap();
+        app.refresh_fields().unwrap();
+
+        // Review asks for both; unknown names are refused
+        app.view = View::Settings;
+        app.settings_tab = SettingsTab::Statuses;
+        app.selected_status = 2;
+        press(&mut app, KeyCode::Char('f'));
+        type_text(&mut app, "pr url; Owner");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_error.as_deref(), Some("Unknown field \"Owner\""));
+        for _ in "Owner".chars() {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "reviewer");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        let asked: Vec<i64> = app.new_card_fields(Status::Review).iter().map(|f| f.id).collect();
+        assert_eq!(asked, [pr_url.id, reviewer.id]);
+        app.view = View::Kanban;
+
+        // Other columns keep the single name prompt
+        let id = new_session(&mut app, "Fix login");
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(stored(&app, id).status, Status::Planned);
+
+        // In Review the card goes there and each field is asked for; an empty one stays unset
+        app.selected_column = 2;
+        app.status_message = None;
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.new_session_status, Some(Status::Review));
+        type_text(&mut app, "Review #412");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::NewCardField);
+        let review = app.new_card_prompt.as_ref().unwrap().session_id;
+        assert_eq!(stored(&app, review).status, Status::Planned);
+        app.handle_paste("https://github.com/acme/shop/pull/412\n");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.new_card_prompt.as_ref().unwrap().index, 1);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.new_card_prompt.is_none());
+        assert_eq!(app.status_message.as_deref(), Some("Created \"Review #412\" in Review"));
+        assert_eq!(app.db.get_session_field_value(review, pr_url.id).unwrap(), "https://github.com/acme/shop/pull/412");
+        assert_eq!(app.db.get_session_field_value(review, reviewer.id).unwrap(), "");
+
+        // Esc skips the rest of the chain and keeps the card
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "Review #413");
+        press(&mut app, KeyCode::Enter);
+        let skipped = app.new_card_prompt.as_ref().unwrap().session_id;
+        type_text(&mut app, "half typed");
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.input_buffer.is_empty());
+        assert_eq!(stored(&app, skipped).status, Status::Review);
+        assert_eq!(app.db.get_session_field_value(skipped, pr_url.id).unwrap(), "");
+
+        // Esc on the name creates nothing
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "Review #414");
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.new_session_status, None);
+        assert_eq!(app.sessions.len(), 3);
+
+        // The move into the column goes through its entry checklist, like `m`
+        app.db.set_status_checklist(app.project.id, Status::Review, &["Tests pass".to_string()]).unwrap();
+        app.refresh_status_overrides().unwrap();
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "Review #415");
+        press(&mut app, KeyCode::Enter);
+        let checked = app.new_card_prompt.as_ref().unwrap().session_id;
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.input_mode, InputMode::MoveChecklist);
+        assert_eq!(stored(&app, checked).status, Status::Planned);
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(stored(&app, checked).status, Status::Review);
+    }
+
+    #[test]
+    fn test_enter_starts_terminal_once() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let checkout = stored(&app, id).checkout_path.unwrap();
+
+        let tmux_name = tmux::session_name(app.project.id, id);
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::AttachTmux(tmux_name.clone()));
+        assert_eq!(tmux.session_dirs(), vec![(tmux_name.clone(), checkout)]);
+        assert_eq!(stored(&app, id).tmux_window.as_deref(), Some(tmux_name.as_str()));
+
+        // Running already: attached again, not started twice
+        app.refresh_sessions().unwrap();
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::AttachTmux(tmux_name));
+        assert_eq!(tmux.session_dirs().len(), 1);
+    }
+
+    #[test]
+    fn test_adoptable_terminal() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let checkout = stored(&app, id).checkout_path.unwrap();
+        let tmux_name = tmux::session_name(app.project.id, id);
+        let adoptable = |app: &App| adoptable_terminal(&*app.tmux, &app.project, &stored(app, id));
+        let move_pane = |dir: &Path| tmux.sessions.lock().unwrap()[0].1 = dir.to_path_buf();
+        assert_eq!(adoptable(&app), None);
+
+        // Linked and running: nothing to adopt
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(adoptable(&app), None);
+
+        // The link is lost while the terminal runs on, in the worktree or the project
+        app.db.clear_tmux_session(id).unwrap();
+        assert_eq!(adoptable(&app), Some(tmux_name.clone()));
+        move_pane(Path::new(TEST_REPO));
+        assert_eq!(adoptable(&app), Some(tmux_name));
+
+        // Somewhere else, it is somebody else's
+        move_pane(Path::new("/tmp"));
+        assert_eq!(adoptable(&app), None);
+        move_pane(&checkout.join("src"));
+        assert_eq!(adoptable(&app), None);
+    }
+
+    #[test]
+    fn test_enter_offers_to_adopt_lost_terminal() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let tmux_name = tmux::session_name(app.project.id, id);
+        press(&mut app, KeyCode::Enter);
+        app.db.clear_tmux_session(id).unwrap();
+
+        // Cancelling leaves both alone
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::None);
+        assert_eq!(app.input_mode, InputMode::ConfirmAdoptTerminal);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!((app.input_mode, app.adopt_terminal.clone()), (InputMode::Normal, None));
+        assert_eq!(stored(&app, id).tmux_window, None);
+
+        // Adopting links the card to it, without starting another
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(press(&mut app, KeyCode::Char('a')), AppAction::AttachTmux(tmux_name.clone()));
+        assert_eq!(stored(&app, id).tmux_window.as_deref(), Some(tmux_name.as_str()));
+        assert_eq!(tmux.session_dirs().len(), 1);
+
+        // Creating a new one starts it beside the old
+        app.db.clear_tmux_session(id).unwrap();
+        press(&mut app, KeyCode::Enter);
+        let AppAction::AttachTmux(created) = press(&mut app, KeyCode::Char('c')) else {
+            panic!("expected an attach");
+        };
+        assert!(created.starts_with(&format!("{}-", tmux_name)));
+        assert_eq!(stored(&app, id).tmux_window, Some(created));
+        assert_eq!(tmux.session_dirs().len(), 2);
+    }
+
+    #[test]
+    fn test_restart_terminal() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        app.db.set_project_setting(app.project.id, SETTING_STARTUP_COMMAND, "make dev {branch}").unwrap();
+        let tmux_name = tmux::session_name(app.project.id, id);
+        press(&mut app, KeyCode::Enter);
+        app.refresh_sessions().unwrap();
+        app.refresh_tmux_sessions();
+        tmux.sessions.lock().unwrap()[0].1 = PathBuf::from("/moved/away");
+        let restart = |app: &mut App| {
+            press(app, KeyCode::Char('.'));
+            let row = app.card_actions.iter().position(|a| *a == CardAction::RestartTerminal).unwrap();
+            app.selected_card_action = row;
+            press(app, KeyCode::Enter);
+            assert_eq!(app.input_mode, InputMode::ConfirmRestartTerminal);
+        };
+
+        // Esc keeps the terminal as it is
+        restart(&mut app);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(tmux.session_dirs()[0].1, PathBuf::from("/moved/away"));
+
+        // Started again under the same name, where and how it first started; claude can't resume
+        restart(&mut app);
+        press(&mut app, KeyCode::Char('r'));
+        assert_eq!(app.input_mode, InputMode::ConfirmRestartTerminal);
+        press(&mut app, KeyCode::Char('f'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+        let checkout = stored(&app, id).checkout_path.unwrap();
+        let sessions = tmux.sessions.lock().unwrap().clone();
+        assert_eq!(sessions, vec![(tmux_name.clone(), checkout, Some("make dev wb/fix-login".to_string()))]);
+        assert_eq!(stored(&app, id).tmux_window.as_deref(), Some(tmux_name.as_str()));
+        assert_eq!(app.status_message.as_deref(), Some("Restarted the terminal of Fix login"));
+
+        // When it can't start again, the card lets go of the dead name
+        *tmux.fail_create.lock().unwrap() = true;
+        restart(&mut app);
+        press(&mut app, KeyCode::Enter);
+        assert!(tmux.session_dirs().is_empty());
+        assert_eq!(stored(&app, id).tmux_window, None);
+        assert!(app.status_message.as_deref().unwrap().contains("no server running"));
+    }
+
+    #[test]
+    fn test_reload_keeps_invalid_settings() {
+        let (mut app, _, _) = test_board();
+        app.db.create_field(app.project.id, "Ticket", "").unwrap();
+        app.db.create_field(app.project.id, "Area", "").unwrap();
+        app.db.set_project_setting(app.project.id, SETTING_STALE_DAYS, "3").unwrap();
+        app.db.set_project_setting(app.project.id, SETTING_CARD_LAYOUT, "branch,age").unwrap();
+        app.no_color = true;
+        app.reload_settings().unwrap();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.stale_days, 3);
+        assert_eq!(app.fields.len(), 2);
+        assert_eq!(app.theme, crate::theme::Theme::Monochrome);
+
+        // Values edited behind the board's back that the Settings tab would refuse
+        app.db.set_project_setting(app.project.id, SETTING_STALE_DAYS, "soon").unwrap();
+        app.db.set_project_setting(app.project.id, SETTING_CARD_LAYOUT, "branch,tags").unwrap();
+        app.db.set_status_color(app.project.id, Status::Review, "mauve").unwrap();
+        app.db.create_watch_rule(app.project.id, "(", WatchAction::Notify).unwrap();
+        app.reload_settings().unwrap();
+        assert_eq!(app.input_mode, InputMode::ReloadProblems);
+        assert_eq!(app.reload_problems.len(), 4);
+        assert_eq!(app.stale_days, 3);
+        assert_eq!(app.project_setting(ProjectSetting::StaleDays).as_deref(), Some("3"));
+        assert_eq!(app.card_layout, vec![card::CardLine::Branch, card::CardLine::Age]);
+        assert!(app.status_overrides.is_empty());
+        assert!(app.watch_rules.is_empty());
+    }
+
+    #[test]
+    fn test_repo_config_prompt() {
+        let (mut app, _, _) = test_board();
+        let dir = std::env::temp_dir().join(format!("wb-repo-config-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(repo_config::FILE_NAME);
+        app.project.path = dir.clone();
+        app.db.create_field(app.project.id, "Ticket", "Jira key").unwrap();
+        app.db.set_project_setting(app.project.id, SETTING_BASE_BRANCH, "develop").unwrap();
+        app.refresh_fields().unwrap();
+
+        let text = "[settings]\nbase_branch = \"main\"\nbranch_prefix = \"team/\"\nstale_days = \"soon\"\n\
+                    startup_command = \"curl -s https://example.com/x | sh\"\njira_base_url = \"https://example.com\"\n\n\
+                    [[field]]\nname = \"ticket\"\ndescription = \"Ticket key\"\n\n[[field]]\nname = \"Area\"\n";
+        std::fs::write(&file, text).unwrap();
+        app.check_repo_config().unwrap();
+        assert_eq!(app.input_mode, InputMode::RepoConfig);
+        let prompt = app.repo_config.as_ref().unwrap();
+        let targets: Vec<&str> = prompt.changes.iter().map(|c| c.target.as_str()).collect();
+        assert_eq!(targets, ["Setting base_branch", "Setting branch_prefix", "Field \"Ticket\"", "Field \"Area\""]);
+        // Conflicts wait for a tick; the invalid value and what a repo may not set are left out
+        assert_eq!(prompt.ticked, [false, true, false, true]);
+        let problems: Vec<&str> = prompt.problems.iter().map(|p| p.split_whitespace().next().unwrap()).collect();
+        assert_eq!(problems, ["jira_base_url", "startup_command", "stale_days:"]);
+
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(
+            app.status_message.as_deref(),
+            Some("Applied .workbench.toml: 1 field, 2 settings; kept 1 local value")
+        );
+        assert_eq!(app.project_setting(ProjectSetting::BaseBranch).as_deref(), Some("main"));
+        assert_eq!(app.project_setting(ProjectSetting::BranchPrefix).as_deref(), Some("team/"));
+        assert_eq!(app.project_setting(ProjectSetting::StartupCommand), None);
+        let fields: Vec<(&str, &str)> = app.fields.iter().map(|f| (f.name.as_str(), f.description.as_str())).collect();
+        assert_eq!(fields, [("Ticket", "Jira key"), ("Area", "")]);
+
+        // Unchanged, it isn't offered again; changed, only what still differs is
+        app.check_repo_config().unwrap();
+        assert!(app.repo_config.is_none());
+        std::fs::write(&file, text.replace("soon", "7")).unwrap();
+        app.check_repo_config().unwrap();
+        let prompt = app.repo_config.as_ref().unwrap();
+        let targets: Vec<&str> = prompt.changes.iter().map(|c| c.target.as_str()).collect();
+        assert_eq!(targets, ["Setting stale_days", "Field \"Ticket\""]);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.project_setting(ProjectSetting::StaleDays), None);
+        app.check_repo_config().unwrap();
+        assert!(app.repo_config.is_none());
+
+        std::fs::write(&file, "[settings\n").unwrap();
+        app.check_repo_config().unwrap();
+        assert!(app.repo_config.is_none());
+        assert!(app.status_message.as_deref().unwrap().starts_with(".workbench.toml: "));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_shared_attach_asks_first() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let tmux_name = tmux::session_name(app.project.id, id);
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::AttachTmux(tmux_name.clone()));
+        tmux.clients.lock().unwrap().insert(tmux_name.clone(), 1);
+        app.refresh_tmux_sessions();
+
+        // Outside tmux the attach is a new client, nothing to ask
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::AttachTmux(tmux_name.clone()));
+
+        app.inside_tmux = true;
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::None);
+        assert_eq!(app.input_mode, InputMode::ConfirmSharedAttach);
+        assert_eq!(press(&mut app, KeyCode::Esc), AppAction::None);
+        assert_eq!((app.input_mode, app.shared_attach.as_deref()), (InputMode::Normal, None));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(press(&mut app, KeyCode::Char('r')), AppAction::AttachReadOnly(tmux_name.clone()));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(press(&mut app, KeyCode::Char('s')), AppAction::AttachTmux(tmux_name.clone()));
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        // Nobody else attached: straight through
+        tmux.clients.lock().unwrap().clear();
+        app.refresh_tmux_sessions();
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::AttachTmux(tmux_name));
+    }
+
+    #[test]
+    fn test_move_then_delete_cleans_up() {
+        let (mut app, tmux, git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        press(&mut app, KeyCode::Enter);
+        app.refresh_sessions().unwrap();
+
+        press(&mut app, KeyCode::Char('m'));
+        press(&mut app, KeyCode::Char('3'));
+        assert_eq!(stored(&app, id).status, Status::Review);
+
+        app.selected_column = 2;
+        app.selected_row = 0;
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.input_mode, InputMode::ConfirmDelete);
+        press(&mut app, KeyCode::Char('y'));
+        assert!(app.db.get_live_session(app.project.id, id).unwrap().is_none());
+        assert!(app.sessions.is_empty());
+        assert_eq!(*tmux.killed.lock().unwrap(), vec![tmux::session_name(app.project.id, id)]);
+        // The worktree waits in the trash with the card...
+        assert!(git.removed.lock().unwrap().is_empty());
+        assert_eq!(git.worktrees.lock().unwrap().len(), 1);
+
+        // ...and goes when the card is purged
+        app.view = View::Settings;
+        app.settings_tab = SettingsTab::Trash;
+        app.refresh_trash().unwrap();
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(app.input_mode, InputMode::ConfirmPurgeSession);
+        press(&mut app, KeyCode::Char('y'));
+        assert!(app.trashed_sessions.is_empty());
+        assert_eq!(git.removed.lock().unwrap().len(), 1);
+        assert!(git.worktrees.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_plan_sessions_keys() {
+        let (mut app, _tmux, git) = test_board();
+        new_session(&mut app, "Fix login");
+        let ctrl_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL);
+
+        // `N` stays the shortcut picker
+        press(&mut app, KeyCode::Char('N'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.as_deref().unwrap().starts_with("No shortcuts yet"));
+
+        press(&mut app, KeyCode::Char('I'));
+        for line in ["Write docs @review", "fix LOGIN", "", "Spike #infra", "write docs"] {
+            type_text(&mut app, line);
+            press(&mut app, KeyCode::Enter);
+        }
+        app.handle_key(ctrl_s).unwrap();
+        assert_eq!(app.input_mode, InputMode::Normal);
+        let message = app.status_message.clone().unwrap();
+        assert!(message.starts_with("Created 2 sessions, 2 duplicates skipped"), "{}", message);
+        let names: Vec<(&str, Status)> = app.sessions.iter().map(|s| (s.name.as_str(), s.status)).collect();
+        assert!(names.contains(&("Write docs", Status::Review)));
+        assert!(names.contains(&("Spike", Status::Planned)));
+        assert_eq!(git.worktrees.lock().unwrap().len(), 3);
+
+        // A bad line keeps everything typed; Esc asks before throwing it away
+        press(&mut app, KeyCode::Char('I'));
+        type_text(&mut app, "Later @someday");
+        app.handle_key(ctrl_s).unwrap();
+        assert_eq!(app.input_mode, InputMode::PlanSessions);
+        assert!(app.input_error.as_deref().unwrap().contains("someday"));
+        press(&mut app, KeyCode::Esc);
+        assert!(app.confirm_discard_plan);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.input_buffer, "Later @someday");
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.sessions.len(), 3);
+    }
+
+    #[test]
+    fn test_notes() {
+        let (mut app, _tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let worktree = std::env::temp_dir().join(format!("wb-notes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&worktree);
+        std::fs::create_dir_all(&worktree).unwrap();
+        app.db.update_session_worktree(id, &worktree, "wb/fix-login").unwrap();
+        app.refresh_sessions().unwrap();
+        let path = worktree.join(".workbench-notes.md");
+        let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        // Stand in for an agent editing the file, with an mtime that can't tie
+        let edit_outside = |text: &str, secs: u64| {
+            std::fs::write(&path, text).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(std::time::SystemTime::now() + Duration::from_secs(secs)).unwrap();
+        };
+
+        // Opening creates the file; saving writes it and the database
+        press(&mut app, KeyCode::Char('p'));
+        assert_eq!(app.input_mode, InputMode::Notes);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        type_text(&mut app, "todo");
+        press(&mut app, KeyCode::Enter);
+        type_text(&mut app, "tests");
+        app.handle_key(ctrl('s')).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "todo\ntests");
+        assert_eq!(app.db.get_session_notes(id).unwrap().text, "todo\ntests");
+
+        // A change on disk reloads an unedited editor...
+        edit_outside("agent wrote", 5);
+        app.refresh_tmux_sessions();
+        let editor = app.notes_editor.as_ref().unwrap();
+        assert_eq!(editor.text, "agent wrote");
+        assert!(!editor.conflict);
+        assert_eq!(app.db.get_session_notes(id).unwrap().text, "agent wrote");
+
+        // ...and is flagged when both sides changed; saving then overwrites the file
+        type_text(&mut app, "!");
+        edit_outside("agent again", 10);
+        app.refresh_tmux_sessions();
+        assert!(app.notes_editor.as_ref().unwrap().conflict);
+        assert_eq!(app.notes_editor.as_ref().unwrap().text, "agent wrote!");
+        app.handle_key(ctrl('s')).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "agent wrote!");
+        assert!(!app.notes_editor.as_ref().unwrap().conflict);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        // Closed, the file is still mirrored into the database
+        edit_outside("from the agent", 15);
+        app.refresh_tmux_sessions();
+        assert_eq!(app.db.get_session_notes(id).unwrap().text, "from the agent");
+
+        // The notes outlive the worktree
+        std::fs::remove_dir_all(&worktree).unwrap();
+        press(&mut app, KeyCode::Char('p'));
+        let editor = app.notes_editor.as_ref().unwrap();
+        assert_eq!(editor.path, None);
+        assert_eq!(editor.text, "from the agent");
+        type_text(&mut app, " and me");
+        press(&mut app, KeyCode::Esc);
+        assert!(app.notes_editor.as_ref().unwrap().confirm_discard);
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.db.get_session_notes(id).unwrap().text, "from the agent");
+
+        assert!(app.validate_project_setting(ProjectSetting::NotesPath, "").is_ok());
+        assert!(app.validate_project_setting(ProjectSetting::NotesPath, "/tmp/notes-{id}.md").is_ok());
+        assert!(app.validate_project_setting(ProjectSetting::NotesPath, "{branch}.md").is_err());
+    }
+
+    #[test]
+    fn test_land_review_card() {
+        let (mut app, tmux, git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        press(&mut app, KeyCode::Enter);
+        app.db.update_session_status(id, Status::Review).unwrap();
+        app.refresh_sessions().unwrap();
+        app.select_session(id);
+        let branch = stored(&app, id).branch_name.unwrap();
+
+        // Unpushed commits stop everything at the check, which can't be skipped
+        *git.unpushed.lock().unwrap() = 2;
+        press(&mut app, KeyCode::Char('L'));
+        assert_eq!(app.input_mode, InputMode::Land);
+        press(&mut app, KeyCode::Enter);
+        assert!(app.land.as_ref().unwrap().failed());
+        press(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.land.as_ref().unwrap().current, 0);
+        assert!(tmux.killed.lock().unwrap().is_empty());
+        assert!(git.removed.lock().unwrap().is_empty());
+
+        // Once pushed, a failed pull can be retried, then the rest runs
+        *git.unpushed.lock().unwrap() = 0;
+        *git.failing_pulls.lock().unwrap() = 2;
+        press(&mut app, KeyCode::Char('r'));
+        let view = app.land.as_ref().unwrap();
+        assert_eq!(view.current, 1);
+        assert!(matches!(view.steps[1].2, StepOutcome::Failed(ref e) if e.contains("Could not resolve host")));
+        press(&mut app, KeyCode::Char('r'));
+        assert_eq!(app.land.as_ref().unwrap().current, 1);
+        press(&mut app, KeyCode::Char('r'));
+        assert!(app.land.as_ref().unwrap().finished());
+
+        let session = stored(&app, id);
+        assert_eq!(session.status, Status::Done);
+        assert_eq!(session.checkout_path, None);
+        assert_eq!(*tmux.killed.lock().unwrap(), vec![tmux::session_name(app.project.id, id)]);
+        assert_eq!(*git.deleted_branches.lock().unwrap(), vec![branch]);
+        let comments = app.db.list_comments(id).unwrap();
+        assert!(comments.iter().any(|c| c.text.contains("merge commit fee0123")));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        // Only Review cards land
+        press(&mut app, KeyCode::Char('L'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn test_branch_sync_from_worktree() {
+        let (mut app, _tmux, git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let path = stored(&app, id).checkout_path.unwrap();
+        app.refresh_tmux_sessions();
+        settle_worktree_heads(&mut app);
+        assert_eq!(app.branch_out_of_sync(&stored(&app, id)), None);
+        // A HEAD that hasn't moved isn't read again
+        let reads = *git.head_reads.lock().unwrap();
+        app.refresh_tmux_sessions();
+        assert!(app.worktree_heads_rx.is_none());
+        assert_eq!(*git.head_reads.lock().unwrap(), reads);
+
+        // Switched branches in the worktree: offered to store it or switch back
+        git.heads.lock().unwrap().insert(path.clone(), git::Head::Branch("wb/other".to_string()));
+        assert_eq!(app.branch_out_of_sync(&stored(&app, id)), None, "only the refresh looks at git");
+        app.refresh_tmux_sessions();
+        settle_worktree_heads(&mut app);
+        press(&mut app, KeyCode::Char('.'));
+        assert_eq!(app.card_actions[app.card_actions.len() - 2..], [CardAction::UseCheckedOutBranch, CardAction::CheckOutStoredBranch]);
+        app.selected_card_action = app.card_actions.len() - 2;
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(stored(&app, id).branch_name.as_deref(), Some("wb/other"));
+        assert_eq!(app.branch_out_of_sync(&stored(&app, id)), None);
+
+        // A detached HEAD is never stored, only checked out of
+        git.heads.lock().unwrap().insert(path.clone(), git::Head::Detached("abc1234".to_string()));
+        app.refresh_tmux_sessions();
+        settle_worktree_heads(&mut app);
+        press(&mut app, KeyCode::Char('.'));
+        assert!(!app.card_actions.contains(&CardAction::UseCheckedOutBranch));
+        assert_eq!(app.card_actions.last(), Some(&CardAction::CheckOutStoredBranch));
+        app.selected_card_action = app.card_actions.len() - 1;
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(git.heads.lock().unwrap()[&path], git::Head::Branch("wb/other".to_string()));
+        assert_eq!(app.branch_out_of_sync(&stored(&app, id)), None);
+
+        // Done cards aren't checked
+        app.db.update_session_status(id, Status::Done).unwrap();
+        app.refresh_sessions().unwrap();
+        app.refresh_tmux_sessions();
+        assert!(app.worktree_heads_rx.is_none());
+        assert!(app.worktree_heads.is_empty());
+    }
+
+    #[test]
+    fn test_due_date_keys() {
+        let (mut app, _tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+
+        press(&mut app, KeyCode::Char('C'));
+        type_text(&mut app, "soon");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::DueDate);
+        assert!(app.input_error.is_some());
+
+        app.input_buffer.clear();
+        type_text(&mut app, "+3d");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(stored(&app, id).due, Some(app.today + 3));
+
+        // Opening it again shows the date; emptying it clears it
+        press(&mut app, KeyCode::Char('C'));
+        assert_eq!(app.input_buffer, due::format(app.today + 3));
+        app.input_buffer.clear();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(stored(&app, id).due, None);
+    }
+
+    #[test]
+    fn test_waiting_session_from_pane() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let AppAction::AttachTmux(tmux_name) = press(&mut app, KeyCode::Enter) else {
+            panic!("Enter didn't attach");
+        };
+        app.refresh_sessions().unwrap();
+        app.refresh_tmux_sessions();
+        assert!(app.active_tmux_sessions.contains(&tmux_name));
+        assert!(app.sessions_waiting_input.is_empty());
+
+        tmux.screens.lock().unwrap().insert(tmux_name.clone(), "Do you want to proceed?\n❯ 1. Yes\n".to_string());
+        app.refresh_tmux_sessions();
+        assert!(app.sessions_waiting_input.contains(&tmux_name));
+        assert!(app.is_waiting_for_input(&stored(&app, id)));
+    }
+
+    #[test]
+    fn test_edit_popup_skips_fields_kept_out() {
+        let (mut app, _tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let ticket = app.db.create_field(app.project.id, "Ticket", "").unwrap();
+        let notes = app.db.create_field(app.project.id, "Notes", "").unwrap();
+        let pr = app.db.create_field(app.project.id, "PR", "").unwrap();
+        app.db.set_session_field_value(id, notes.id, "kept", ValueSource::Manual).unwrap();
+        app.db.toggle_field_in_edit(notes.id).unwrap();
+        app.db.toggle_field_in_ai(pr.id).unwrap();
+        app.refresh_fields().unwrap();
+        assert_eq!(app.edit_field_indices(), vec![0, 2]);
+        assert_eq!(app.ai_field_indices(), vec![0, 1]);
+
+        // Name, owner and agent rows, then Ticket and PR; Notes is skipped and kept
+        press(&mut app, KeyCode::Char('e'));
+        for _ in 0..FIRST_FIELD_ROW {
+            press(&mut app, KeyCode::Tab);
+        }
+        type_text(&mut app, "PROJ-1");
+        press(&mut app, KeyCode::Tab);
+        type_text(&mut app, "#42");
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.edit_row, 0);
+        press(&mut app, KeyCode::Enter);
+
+        let value = |field_id| app.db.get_session_field_value(id, field_id).unwrap();
+        assert_eq!(value(ticket.id), "PROJ-1");
+        assert_eq!(value(notes.id), "kept");
+        assert_eq!(value(pr.id), "#42");
+    }
+
+    #[test]
+    fn test_set_field_on_marked_cards() {
+        let (mut app, _tmux, _git) = test_board();
+        let names = ["Fix login", "Write docs", "Spike"].map(|name| (name.to_string(), Status::Planned));
+        let created = app.db.create_sessions(app.project.id, &names, None).unwrap();
+        let [login, docs, spike] = [0, 1, 2].map(|i| created[i].id);
+        app.refresh_sessions().unwrap();
+        let epic = app.db.create_field(app.project.id, "Epic", "").unwrap();
+        app.refresh_fields().unwrap();
+        app.db.set_session_field_value(docs, epic.id, "Old epic", ValueSource::Ai).unwrap();
+        for id in [login, docs] {
+            app.select_session(id);
+            press(&mut app, KeyCode::Char('v'));
+        }
+        assert_eq!(app.status_message.as_deref(), Some("2 marked (;: set a field on all, Esc: clear marks)"));
+
+        // One value for every marked card, the unmarked one untouched
+        press(&mut app, KeyCode::Char(';'));
+        assert_eq!(app.input_mode, InputMode::QuickFieldValue);
+        assert_eq!(app.input_buffer, "");
+        type_text(&mut app, "Checkout v2");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.status_message.as_deref(), Some("Set Epic on 2 cards"));
+        let value = |app: &App, id| app.db.get_session_field_value(id, epic.id).unwrap();
+        assert_eq!(value(&app, login), "Checkout v2");
+        assert_eq!(value(&app, docs), "Checkout v2");
+        assert_eq!(app.db.get_session_field_source(docs, epic.id).unwrap(), ValueSource::Manual);
+        assert_eq!(value(&app, spike), "");
+
+        // Tab leaves cards that have a value alone
+        app.select_session(spike);
+        press(&mut app, KeyCode::Char('v'));
+        press(&mut app, KeyCode::Char(';'));
+        press(&mut app, KeyCode::Tab);
+        assert!(app.quick_field_only_empty);
+        type_text(&mut app, "Backlog");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.status_message.as_deref(), Some("Set Epic on 1 card, 2 already had a value"));
+        assert_eq!(value(&app, login), "Checkout v2");
+        assert_eq!(value(&app, spike), "Backlog");
+
+        // Esc clears the marks; `;` is back to the selected card alone
+        press(&mut app, KeyCode::Esc);
+        assert!(app.marked_sessions.is_empty());
+        press(&mut app, KeyCode::Char(';'));
+        assert_eq!(app.input_buffer, "Backlog");
+    }
+
+    #[test]
+    fn test_sort_column_by_activity() {
+        let (mut app, tmux, _git) = test_board();
+        let names = ["Fix login", "Write docs", "Spike"].map(|name| (name.to_string(), Status::InProgress));
+        let created = app.db.create_sessions(app.project.id, &names, None).unwrap();
+        let [login, docs, spike] = [0, 1, 2].map(|i| created[i].id);
+        let (now, project_id) = (db::unix_now(), app.project.id);
+        let set_activity = |id: i64, at: i64| {
+            tmux.activity.lock().unwrap().insert(tmux::session_name(project_id, id), at);
+        };
+        for id in [login, docs] {
+            let name = tmux::session_name(project_id, id);
+            app.db.set_tmux_session(id, &name).unwrap();
+            tmux.sessions.lock().unwrap().push((name, PathBuf::from("/tmp"), None));
+        }
+        // Live terminals go by their activity, Spike (no terminal) by when it was created
+        set_activity(login, now + 100);
+        set_activity(docs, now + 200);
+        app.refresh_sessions().unwrap();
+        let order = |app: &App| app.sessions_by_status(Status::InProgress).iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(order(&app), vec![login, docs, spike]);
+
+        app.selected_status = 1;
+        app.handle_statuses_settings_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.column_sort(Status::InProgress), ColumnSort::Activity);
+        assert_eq!(order(&app), vec![docs, login, spike]);
+
+        // The selection stays on its card as the column reorders
+        app.select_session(docs);
+        set_activity(login, now + 300);
+        app.refresh_tmux_sessions();
+        assert_eq!(order(&app), vec![login, docs, spike]);
+        assert_eq!(app.selected_session().map(|s| s.id), Some(docs));
+
+        // Ties keep creation order; pinned cards still come first
+        set_activity(docs, now + 300);
+        app.db.set_session_pinned(spike, true).unwrap();
+        app.refresh_sessions().unwrap();
+        assert_eq!(order(&app), vec![spike, login, docs]);
+
+        app.handle_statuses_settings_key(KeyEvent::new(KeyCode::Char('o'), KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.column_sort(Status::InProgress), ColumnSort::Created);
+    }
+
+    #[test]
+    fn test_time_tracking() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix auth");
+        let tmux_name = tmux::session_name(app.project.id, id);
+        press(&mut app, KeyCode::Enter);
+        app.refresh_sessions().unwrap();
+        let now = db::unix_now();
+        tmux.activity.lock().unwrap().insert(tmux_name.clone(), now);
+        let spent = |app: &App| app.time_spent.get(&id).copied().unwrap_or(0);
+        let poll_after = |app: &mut App, secs: i64| {
+            app.time_sampled_at = Some(db::unix_now() - secs);
+            app.refresh_tmux_sessions();
+        };
+
+        // Running but not shown by any client: not in use
+        poll_after(&mut app, 10);
+        assert_eq!(spent(&app), 0);
+
+        tmux.clients.lock().unwrap().insert(tmux_name.clone(), 1);
+        poll_after(&mut app, 10);
+        assert!((10..=11).contains(&spent(&app)), "{}", spent(&app));
+
+        // A long gap means the board wasn't polling
+        poll_after(&mut app, 600);
+        assert!(spent(&app) <= 11);
+
+        // Quiet for too long, or tracking turned off
+        tmux.activity.lock().unwrap().insert(tmux_name.clone(), now - IDLE_SECS - 60);
+        poll_after(&mut app, 10);
+        assert!(spent(&app) <= 11);
+        tmux.activity.lock().unwrap().insert(tmux_name.clone(), now);
+        app.db.set_project_setting(app.project.id, SETTING_TRACK_TIME, "off").unwrap();
+        app.load_project_settings().unwrap();
+        poll_after(&mut app, 10);
+        assert!(spent(&app) <= 11);
+
+        // An attach outside tmux counts until the terminal's last output, plus the allowance
+        app.db.set_project_setting(app.project.id, SETTING_TRACK_TIME, "").unwrap();
+        app.load_project_settings().unwrap();
+        let before = spent(&app);
+        tmux.activity.lock().unwrap().insert(tmux_name.clone(), now - IDLE_SECS - 100);
+        app.credit_attached_time(&tmux_name, now - IDLE_SECS - 400);
+        assert_eq!(spent(&app) - before, 600);
+
+        assert_eq!(format_time_spent(30), "<1m");
+        assert_eq!(format_time_spent(40 * 60), "40m");
+        assert_eq!(format_time_spent(3 * 3600), "3h");
+        assert_eq!(format_time_spent(3 * 3600 + 40 * 60 + 59), "3h 40m");
+    }
+
+    #[test]
+    fn test_bracket_keys_step_columns() {
+        let (mut app, _tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        new_session(&mut app, "Write docs");
+        app.select_session(id);
+
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(stored(&app, id).status, Status::InProgress);
+        assert_eq!(app.selected_session().map(|s| s.id), Some(id));
+        assert_eq!(app.status_message.as_deref(), Some("Moved Fix login to In Progress"));
+
+        press(&mut app, KeyCode::Char('}'));
+        assert_eq!(stored(&app, id).status, Status::Done);
+        assert_eq!((app.selected_column, app.selected_session().map(|s| s.id)), (3, Some(id)));
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(stored(&app, id).status, Status::Done);
+        assert_eq!(app.status_message.as_deref(), Some("Fix login is already in the last column"));
+
+        press(&mut app, KeyCode::Char('['));
+        assert_eq!(stored(&app, id).status, Status::Review);
+        press(&mut app, KeyCode::Char('{'));
+        assert_eq!(stored(&app, id).status, Status::Planned);
+        assert_eq!(app.selected_session().map(|s| s.id), Some(id));
+
+        // A column with an entry checklist asks first, as the move popup does
+        app.db.set_status_checklist(app.project.id, Status::InProgress, &["Ticket linked".to_string()]).unwrap();
+        app.refresh_status_overrides().unwrap();
+        press(&mut app, KeyCode::Char(']'));
+        assert_eq!(app.input_mode, InputMode::MoveChecklist);
+        assert_eq!(stored(&app, id).status, Status::Planned);
+    }
+
+    #[test]
+    fn test_read_only_while_open_elsewhere() {
+        let (mut app, _tmux, _git) = test_board();
+        new_session(&mut app, "Fix login");
+        let project_id = app.project.id;
+        assert_eq!(app.lock_holder, None);
+
+        app.db.hold_project_lock(project_id, "desktop", 4242, db::unix_now()).unwrap();
+        app.hold_lock().unwrap();
+        assert_eq!(app.lock_holder.as_ref().map(|h| h.hostname.as_str()), Some("desktop"));
+
+        // Read-only refuses keys that change the board, not those that look around
+        press(&mut app, KeyCode::Char('U'));
+        assert!(app.read_only);
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert!(app.status_message.as_deref().unwrap().contains("desktop"));
+        press(&mut app, KeyCode::Char('l'));
+        assert_eq!(app.selected_column, 1);
+
+        // Once the other board quits, edits are back on by themselves
+        app.db.release_project_lock(project_id, "desktop", 4242).unwrap();
+        app.hold_lock().unwrap();
+        assert!(!app.read_only && app.lock_holder.is_none());
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.input_mode, InputMode::NewSession);
+    }
+
+    #[test]
+    fn test_edit_popup_keeps_value_sources() {
+        let (mut app, _tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let summary = app.db.create_field(app.project.id, "Summary", "").unwrap();
+        let notes = app.db.create_field(app.project.id, "Notes", "").unwrap();
+        let ticket = app.db.create_field(app.project.id, "Ticket", "").unwrap();
+        for field in [&summary, &notes, &ticket] {
+            app.db.set_session_field_value(id, field.id, "from claude", ValueSource::Ai).unwrap();
+        }
+        app.refresh_fields().unwrap();
+        let source = |app: &App, field_id| app.db.get_session_field_source(id, field_id).unwrap();
+
+        // Saving untouched values keeps them marked as AI-written
+        press(&mut app, KeyCode::Char('e'));
+        assert_eq!(app.edit_field_sources, vec![ValueSource::Ai; 3]);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(source(&app, summary.id), ValueSource::Ai);
+
+        // Typing over one and vouching for another both make them manual
+        press(&mut app, KeyCode::Char('e'));
+        for _ in 0..FIRST_FIELD_ROW {
+            press(&mut app, KeyCode::Tab);
+        }
+        type_text(&mut app, "!");
+        press(&mut app, KeyCode::Tab);
+        app.handle_key(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.edit_field_sources, vec![ValueSource::Manual, ValueSource::Manual, ValueSource::Ai]);
+        press(&mut app, KeyCode::Enter);
+
+        assert_eq!(app.db.get_session_field_value(id, notes.id).unwrap(), "from claude");
+        assert_eq!(source(&app, summary.id), ValueSource::Manual);
+        assert_eq!(source(&app, notes.id), ValueSource::Manual);
+        assert_eq!(source(&app, ticket.id), ValueSource::Ai);
+    }
+
+    #[test]
+    fn test_edit_popup_completes_field_values() {
+        let (mut app, _tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        let component = app.db.create_field(app.project.id, "Component", "").unwrap();
+        let names = ["Auth", "Docs", "Cache"].map(|name| (name.to_string(), Status::Planned));
+        let created = app.db.create_sessions(app.project.id, &names, None).unwrap();
+        for (session, value) in created.iter().zip(["Backend", "Backend", "Billing"]) {
+            app.db.set_session_field_value(session.id, component.id, value, ValueSource::Manual).unwrap();
+        }
+        app.refresh_sessions().unwrap();
+        app.refresh_fields().unwrap();
+        app.select_session(id);
+
+        press(&mut app, KeyCode::Char('e'));
+        for _ in 0..FIRST_FIELD_ROW {
+            press(&mut app, KeyCode::Tab);
+        }
+        type_text(&mut app, "b");
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.edit_completions, vec!["Backend", "Billing"]);
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Enter);
+        assert!(app.edit_completions.is_empty());
+        assert_eq!((app.input_mode, app.input_buffer.as_str()), (InputMode::EditSession, "Billing"));
+
+        // A full value has nothing left to offer, so Tab moves on as usual
+        press(&mut app, KeyCode::Tab);
+        assert!(app.edit_completions.is_empty());
+        assert_eq!(app.edit_row, 0);
+
+        // Esc closes the dropdown, not the popup; typing goes on past it
+        for _ in 0..FIRST_FIELD_ROW {
+            press(&mut app, KeyCode::Tab);
+        }
+        app.input_buffer.clear();
+        type_text(&mut app, "ba");
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.edit_completions, vec!["Backend"]);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.input_mode, InputMode::EditSession);
+        press(&mut app, KeyCode::Tab);
+        type_text(&mut app, "r");
+        assert!(app.edit_completions.is_empty());
+        assert_eq!(app.input_buffer, "bar");
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.input_mode, InputMode::Normal);
+        assert_eq!(app.db.get_session_field_value(id, component.id).unwrap(), "");
+    }
+
+    #[test]
+    fn test_ai_context_limit() {
+        let (mut app, _tmux, _git) = test_board();
+        new_session(&mut app, "Fix login");
+        assert_eq!(app.ai_context_limit(), context::DEFAULT_MAX_CHARS);
+        assert!(app.validate_project_setting(ProjectSetting::AiContextChars, "20000").is_ok());
+        assert!(app.validate_project_setting(ProjectSetting::AiContextChars, "").is_ok());
+        assert!(app.validate_project_setting(ProjectSetting::AiContextChars, "10").is_err());
+        assert!(app.validate_project_setting(ProjectSetting::AiContextChars, "lots").is_err());
+        app.db.set_project_setting(app.project.id, SETTING_AI_CONTEXT_CHARS, "20000").unwrap();
+        app.load_project_settings().unwrap();
+        assert_eq!(app.ai_context_limit(), 20_000);
+
+        // Ctrl+Up/Down change it for the open popup only
+        app.ai_available = true;
+        press(&mut app, KeyCode::Char('e'));
+        app.handle_key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)).unwrap();
+        assert_eq!(app.edit_mode, EditMode::AI);
+        app.handle_key(KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.ai_context_limit(), 40_000);
+        for _ in 0..3 {
+            app.handle_key(KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL)).unwrap();
+        }
+        assert_eq!(app.ai_context_limit(), context::MAX_CHARS);
+        app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.ai_context_limit(), context::MAX_CHARS / 2);
+        assert_eq!(app.edit_row, 0);
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.ai_context_limit(), 20_000);
+    }
+
+    #[test]
+    fn test_overview() {
+        let (mut app, tmux, _git) = test_board();
+        let id = new_session(&mut app, "Fix login");
+        new_session(&mut app, "Docs");
+        let AppAction::AttachTmux(here) = press(&mut app, KeyCode::Enter) else {
+            panic!("Enter didn't attach");
+        };
+        app.refresh_sessions().unwrap();
+        let api = app.db.call(|db| db.get_or_create_project("api", Path::new("/tmp/wb-overview-api"))).unwrap().unwrap();
+        let other = app.db.create_sessions(api.id, &[("Rate limits".to_string(), Status::Planned)], None).unwrap().remove(0);
+        let there = tmux::session_name(api.id, other.id);
+        app.db.set_tmux_session(other.id, &there).unwrap();
+        tmux.sessions.lock().unwrap().push((there.clone(), PathBuf::from("/tmp"), None));
+        for name in [&here, &there] {
+            tmux.screens.lock().unwrap().insert(name.clone(), "Do you want to proceed?\n❯ 1. Yes\n".to_string());
+        }
+        app.refresh_tmux_sessions();
+        // Opened after api was created, so listed before it
+        let project_id = app.project.id;
+        app.db.call(move |db| db.record_active_project(project_id)).unwrap().unwrap();
+
+        press(&mut app, KeyCode::Char('V'));
+        assert_eq!(app.view, View::Overview);
+        let summary: Vec<(&str, usize, Vec<&str>)> = app
+            .overview
+            .iter()
+            .map(|p| (p.project.name.as_str(), p.active, p.waiting.iter().map(|s| s.name.as_str()).collect()))
+            .collect();
+        assert_eq!(summary, vec![(app.project.name.as_str(), 1, vec!["Fix login"]), ("api", 1, vec!["Rate limits"])]);
+        assert_eq!(app.overview_rows(), vec![(0, None), (0, Some(0)), (1, None), (1, Some(0))]);
+        assert_eq!(app.selected_overview, 0);
+
+        // Enter attaches to a waiting session in another project without leaving this board
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Char('j'));
+        }
+        assert_eq!(press(&mut app, KeyCode::Enter), AppAction::AttachTmux(there));
+
+        // Opening one of this project's entries goes back to the board with it selected
+        app.selected_column = 3;
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('k'));
+        press(&mut app, KeyCode::Char('o'));
+        assert_eq!(app.view, View::Kanban);
+        assert_eq!(app.selected_session().map(|s| s.id), Some(id));
+
+        // Pinning moves a project to the top and keeps it selected
+        press(&mut app, KeyCode::Char('V'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('j'));
+        press(&mut app, KeyCode::Char('*'));
+        assert!(app.overview[0].project.pinned);
+        assert_eq!(app.overview[0].project.name, "api");
+        assert_eq!(app.selected_overview, 0);
+
+        // Archiving hides it until archived projects are shown; this board is always listed
+        press(&mut app, KeyCode::Char('a'));
+        assert_eq!(app.overview.len(), 1);
+        assert!(app.status_message.as_deref().unwrap().starts_with("Archived api"));
+        press(&mut app, KeyCode::Char('a'));
+        assert!(app.overview[0].project.archived);
+        assert_eq!(app.overview.len(), 1);
+        press(&mut app, KeyCode::Char('A'));
+        let listed: Vec<(&str, bool)> = app.overview.iter().map(|p| (p.project.name.as_str(), p.project.archived)).collect();
+        assert_eq!(listed, vec![("api", true), (app.project.name.as_str(), true)]);
+        // The selection stays on this board's row
+        press(&mut app, KeyCode::Char('a'));
+        assert!(!app.overview[1].project.archived);
+        assert_eq!(app.status_message.clone(), Some(format!("Restored {}", app.project.name)));
+    }
+
+    #[test]
+    fn test_frame_cap() {
+        let (mut app, _tmux, _git) = test_board();
+        assert!(app.should_draw());
+        app.frame_drawn();
+        assert!(!app.should_draw());
+        assert_eq!(app.frames, 1);
+
+        // A change right after a frame waits for the cap; the poll wakes up for it
+        app.needs_redraw = true;
+        app.last_frame = Some(Instant::now());
+        assert!(!app.should_draw());
+        assert!(app.poll_timeout() <= MIN_FRAME_INTERVAL);
+        app.last_frame = Some(Instant::now() - MIN_FRAME_INTERVAL);
+        assert!(app.should_draw());
+        app.frame_drawn();
+        assert_eq!(app.frame_rate(), 2.0 / FRAME_RATE_WINDOW.as_secs_f64());
+
+        // A timed redraw bounds the poll too
+        app.redraw_at(Instant::now() + Duration::from_millis(50));
+        app.redraw_at(Instant::now() + Duration::from_secs(5));
+        assert!(app.poll_timeout() <= Duration::from_millis(50));
+
+        press(&mut app, KeyCode::F(12));
+        assert!(app.show_frame_stats);
+        assert_eq!(app.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn test_hint_targets_follow_zoom() {
+        let (mut app, _, _) = test_board();
+        for i in 0..12 {
+            new_session(&mut app, &format!("Task {}", i));
+        }
+        let ids: Vec<i64> = app.sessions.iter().map(|s| s.id).collect();
+        app.db.update_session_status(ids[1], Status::Review).unwrap();
+        app.refresh_sessions().unwrap();
+        let area = ratatui::layout::Rect::new(0, 0, 160, 40);
+
+        // Side by side: row by row across the columns
+        let order = crate::ui::visible_card_order(&app, area);
+        assert_eq!(&order[..3], &[ids[0], ids[1], ids[2]]);
+
+        // Zoomed, only the selected column counts, scrolled to keep the selection in view
+        app.select_session(ids[11]);
+        press(&mut app, KeyCode::Char('='));
+        let order = crate::ui::visible_card_order(&app, area);
+        assert!(!order.contains(&ids[1]) && !order.contains(&ids[0]));
+        assert_eq!(order.last(), Some(&ids[11]));
+    }
+
+    #[test]
+    fn test_hint_targets_count_wrapped_cards() {
+        let (mut app, _, _) = test_board();
+        for i in 0..12 {
+            new_session(&mut app, &format!("Task {}", i));
+        }
+        let field = app.db.create_field(app.project.id, "Next step", "").unwrap();
+        app.db.toggle_field_wrap(field.id).unwrap();
+        app.refresh_fields().unwrap();
+        let area = ratatui::layout::Rect::new(0, 0, 160, 40);
+        let short = crate::ui::visible_card_order(&app, area).len();
+
+        // Values wrapped over several lines make the cards taller, so fewer are on screen
+        for session in app.sessions.clone() {
+            app.db.set_session_field_value(session.id, field.id, &"word ".repeat(40), ValueSource::Manual).unwrap();
         }
+        app.refresh_sessions().unwrap();
+        let tall = crate::ui::visible_card_order(&app, area).len();
+        assert!(tall < short, "{} cards with wrapped values, {} without", tall, short);
     }
 }
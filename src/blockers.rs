@@ -0,0 +1,144 @@
+use std::collections::{HashMap, HashSet};
+
+/// Which cards wait on others, worked out from the "blocked by" links
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockState {
+    /// Open card -> the open cards it waits on, directly or through other open cards
+    pub blocked: HashMap<i64, Vec<i64>>,
+    /// A loop of links found on the way, as the ids around it (first id repeated at
+    /// the end). The link closing it is ignored
+    pub cycle: Option<Vec<i64>>,
+}
+
+/// Walk the `(card, blocker)` links between `open` cards (not Done). A Done blocker no
+/// longer blocks, and neither does anything behind it. A loop doesn't hang the walk: the
+/// link that closes it is dropped and the loop reported
+pub fn compute(links: &[(i64, i64)], open: &HashSet<i64>) -> BlockState {
+    let mut edges: HashMap<i64, Vec<i64>> = HashMap::new();
+    for &(card, blocker) in links {
+        if card != blocker && open.contains(&card) && open.contains(&blocker) {
+            edges.entry(card).or_default().push(blocker);
+        }
+    }
+    for blockers in edges.values_mut() {
+        blockers.sort_unstable();
+        blockers.dedup();
+    }
+
+    let mut cards: Vec<i64> = edges.keys().copied().collect();
+    cards.sort_unstable();
+    let mut cut = HashSet::new();
+    let mut cycle = None;
+    let mut finished = HashSet::new();
+    for &card in &cards {
+        let mut path = Vec::new();
+        find_loops(card, &edges, &mut path, &mut finished, &mut cut, &mut cycle);
+    }
+
+    let mut blocked = HashMap::new();
+    for &card in &cards {
+        let mut seen = HashSet::from([card]);
+        let mut stack = vec![card];
+        let mut waits_on = Vec::new();
+        while let Some(next) = stack.pop() {
+            for &blocker in edges.get(&next).into_iter().flatten() {
+                if !cut.contains(&(next, blocker)) && seen.insert(blocker) {
+                    waits_on.push(blocker);
+                    stack.push(blocker);
+                }
+            }
+        }
+        if !waits_on.is_empty() {
+            waits_on.sort_unstable();
+            blocked.insert(card, waits_on);
+        }
+    }
+    BlockState { blocked, cycle }
+}
+
+/// Depth-first search from `card`, cutting every link back into the current path
+fn find_loops(
+    card: i64,
+    edges: &HashMap<i64, Vec<i64>>,
+    path: &mut Vec<i64>,
+    finished: &mut HashSet<i64>,
+    cut: &mut HashSet<(i64, i64)>,
+    cycle: &mut Option<Vec<i64>>,
+) {
+    if finished.contains(&card) {
+        return;
+    }
+    path.push(card);
+    for &blocker in edges.get(&card).into_iter().flatten() {
+        if let Some(start) = path.iter().position(|id| *id == blocker) {
+            cut.insert((card, blocker));
+            if cycle.is_none() {
+                let mut ids = path[start..].to_vec();
+                ids.push(blocker);
+                *cycle = Some(ids);
+            }
+        } else {
+            find_loops(blocker, edges, path, finished, cut, cycle);
+        }
+    }
+    path.pop();
+    finished.insert(card);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(ids: &[i64]) -> HashSet<i64> {
+        ids.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_chain() {
+        // 1 waits on 2, which waits on 3
+        let links = [(1, 2), (2, 3)];
+        let state = compute(&links, &open(&[1, 2, 3]));
+        assert_eq!(state.blocked[&1], vec![2, 3]);
+        assert_eq!(state.blocked[&2], vec![3]);
+        assert!(!state.blocked.contains_key(&3));
+        assert_eq!(state.cycle, None);
+
+        // Once 3 is done, 2 is free; once 2 is done too, so is 1
+        let state = compute(&links, &open(&[1, 2]));
+        assert_eq!(state.blocked[&1], vec![2]);
+        assert!(!state.blocked.contains_key(&2));
+        assert!(compute(&links, &open(&[1])).blocked.is_empty());
+    }
+
+    #[test]
+    fn test_diamond() {
+        // 1 waits on 2 and 3, both waiting on 4
+        let links = [(1, 2), (1, 3), (2, 4), (3, 4)];
+        let state = compute(&links, &open(&[1, 2, 3, 4]));
+        assert_eq!(state.blocked[&1], vec![2, 3, 4]);
+        assert_eq!(state.blocked[&2], vec![4]);
+        assert_eq!(state.blocked[&3], vec![4]);
+        assert_eq!(state.cycle, None);
+
+        // A Done blocker hides what is behind it
+        let state = compute(&links, &open(&[1, 3, 4]));
+        assert_eq!(state.blocked[&1], vec![3, 4]);
+    }
+
+    #[test]
+    fn test_cycle() {
+        let links = [(1, 2), (2, 3), (3, 1), (4, 1)];
+        let state = compute(&links, &open(&[1, 2, 3, 4]));
+        assert_eq!(state.cycle, Some(vec![1, 2, 3, 1]));
+        // The closing link 3 -> 1 is ignored
+        assert_eq!(state.blocked[&1], vec![2, 3]);
+        assert_eq!(state.blocked[&2], vec![3]);
+        assert!(!state.blocked.contains_key(&3));
+        assert_eq!(state.blocked[&4], vec![1, 2, 3]);
+
+        // Blocking itself is ignored outright
+        let state = compute(&[(5, 5)], &open(&[5]));
+        assert!(state.blocked.is_empty());
+        assert_eq!(state.cycle, None);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::db::Field;
+
+/// Most lines a card layout can list
+pub const MAX_CARD_LINES: usize = 6;
+
+/// Most lines a field set to wrap takes on a card
+pub const MAX_WRAPPED_LINES: usize = 3;
+
+/// One entry of a project's card layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardLine {
+    /// Worktree branch
+    Branch,
+    /// Every visible custom field, one line each (the `v` toggle in settings)
+    VisibleFields,
+    /// A single custom field, whether or not it is visible
+    Field(i64),
+    /// Time since creation and time in the current column
+    Age,
+    /// Last meaningful line of the tmux pane
+    LastOutput,
+    /// Uncommitted changes in the worktree
+    Dirty,
+}
+
+impl CardLine {
+    /// Lines this entry adds to the card
+    pub fn height(self, fields: &[Field]) -> u16 {
+        match self {
+            CardLine::VisibleFields => fields.iter().filter(|f| f.visible).count() as u16,
+            CardLine::Field(id) if !fields.iter().any(|f| f.id == id) => 0,
+            _ => 1,
+        }
+    }
+
+    pub fn label(self, fields: &[Field]) -> String {
+        match self {
+            CardLine::Branch => "Branch".to_string(),
+            CardLine::VisibleFields => "Visible fields".to_string(),
+            CardLine::Field(id) => match fields.iter().find(|f| f.id == id) {
+                Some(field) => format!("Field: {}", field.name),
+                None => "Field: (deleted)".to_string(),
+            },
+            CardLine::Age => "Age".to_string(),
+            CardLine::LastOutput => "Last output line".to_string(),
+            CardLine::Dirty => "Uncommitted changes".to_string(),
+        }
+    }
+
+    /// Everything that can be added to a layout
+    pub fn choices(fields: &[Field]) -> Vec<CardLine> {
+        let mut choices = vec![CardLine::Branch, CardLine::VisibleFields];
+        choices.extend(fields.iter().map(|f| CardLine::Field(f.id)));
+        choices.extend([CardLine::Age, CardLine::LastOutput, CardLine::Dirty]);
+        choices
+    }
+
+    fn token(self) -> String {
+        match self {
+            CardLine::Branch => "branch".to_string(),
+            CardLine::VisibleFields => "fields".to_string(),
+            CardLine::Field(id) => format!("field:{}", id),
+            CardLine::Age => "age".to_string(),
+            CardLine::LastOutput => "output".to_string(),
+            CardLine::Dirty => "dirty".to_string(),
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            "branch" => CardLine::Branch,
+            "fields" => CardLine::VisibleFields,
+            "age" => CardLine::Age,
+            "output" => CardLine::LastOutput,
+            "dirty" => CardLine::Dirty,
+            _ => CardLine::Field(token.strip_prefix("field:")?.parse().ok()?),
+        })
+    }
+}
+
+/// The layout cards had before layouts were configurable: branch, then visible fields
+pub fn default_layout() -> Vec<CardLine> {
+    vec![CardLine::Branch, CardLine::VisibleFields]
+}
+
+/// Parse the stored setting (`branch,fields,age`); unknown entries are skipped and
+/// an unset setting gives the default layout
+pub fn parse_layout(spec: Option<&str>) -> Vec<CardLine> {
+    match spec {
+        Some(spec) => spec
+            .split(',')
+            .filter_map(|token| CardLine::from_token(token.trim()))
+            .take(MAX_CARD_LINES)
+            .collect(),
+        None => default_layout(),
+    }
+}
+
+/// The first entry of a stored layout that `parse_layout` would skip
+pub fn unknown_layout_token(spec: Option<&str>) -> Option<&str> {
+    spec?.split(',').map(str::trim).find(|token| !token.is_empty() && CardLine::from_token(token).is_none())
+}
+
+pub fn format_layout(layout: &[CardLine]) -> String {
+    layout.iter().map(|line| line.token()).collect::<Vec<_>>().join(",")
+}
+
+/// Rows a card takes on the board: borders, the layout's lines, and one spare row
+pub fn card_height(layout: &[CardLine], fields: &[Field]) -> u16 {
+    3 + layout.iter().map(|line| line.height(fields)).sum::<u16>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(id: i64, visible: bool) -> Field {
+        Field {
+            id,
+            name: format!("F{}", id),
+            description: String::new(),
+            display_order: id,
+            visible,
+            in_ai: true,
+            in_edit: true,
+            wrap: false,
+        }
+    }
+
+    #[test]
+    fn test_layout_round_trip() {
+        let layout = vec![CardLine::Age, CardLine::Field(7), CardLine::Dirty, CardLine::LastOutput];
+        assert_eq!(parse_layout(Some(&format_layout(&layout))), layout);
+        assert_eq!(parse_layout(None), default_layout());
+        assert_eq!(parse_layout(Some("")), vec![]);
+        assert_eq!(parse_layout(Some("branch, tags, field:x, age")), vec![CardLine::Branch, CardLine::Age]);
+        assert_eq!(unknown_layout_token(Some("branch, tags, field:x, age")), Some("tags"));
+        assert_eq!(unknown_layout_token(Some("")), None);
+    }
+
+    #[test]
+    fn test_default_height_matches_fixed_layout() {
+        let fields = vec![field(1, true), field(2, false), field(3, true)];
+        // The board used to size cards as 4 + visible fields
+        assert_eq!(card_height(&default_layout(), &fields), 4 + 2);
+        assert_eq!(card_height(&[CardLine::Field(2), CardLine::Field(9)], &fields), 4);
+    }
+}
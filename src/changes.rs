@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use crate::db::{Session, Status};
+
+/// One difference between two loads of the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added { id: i64, name: String },
+    Removed { id: i64, name: String },
+    Moved { id: i64, name: String, from: Status, to: Status },
+    Renamed { id: i64, from: String, to: String },
+}
+
+/// What changed between two snapshots of the session list, matched by id: new
+/// sessions in `after` order, then removed ones, then each kept session's move and
+/// rename. Anything else a session carries is ignored
+pub fn diff(before: &[Session], after: &[Session]) -> Vec<Change> {
+    let old: HashMap<i64, &Session> = before.iter().map(|s| (s.id, s)).collect();
+    let new: HashMap<i64, &Session> = after.iter().map(|s| (s.id, s)).collect();
+    let mut changes: Vec<Change> = after
+        .iter()
+        .filter(|s| !old.contains_key(&s.id))
+        .map(|s| Change::Added { id: s.id, name: s.name.clone() })
+        .collect();
+    changes.extend(
+        before
+            .iter()
+            .filter(|s| !new.contains_key(&s.id))
+            .map(|s| Change::Removed { id: s.id, name: s.name.clone() }),
+    );
+    for session in after {
+        let Some(was) = old.get(&session.id) else { continue };
+        if was.status != session.status {
+            changes.push(Change::Moved { id: session.id, name: session.name.clone(), from: was.status, to: session.status });
+        }
+        if was.name != session.name {
+            changes.push(Change::Renamed { id: session.id, from: was.name.clone(), to: session.name.clone() });
+        }
+    }
+    changes
+}
+
+/// Counts per kind, e.g. `+1 new, 2 moved`
+pub fn summary(changes: &[Change]) -> String {
+    let count = |kind: fn(&Change) -> bool| changes.iter().filter(|c| kind(c)).count();
+    let parts = [
+        (count(|c| matches!(c, Change::Added { .. })), "+", "new"),
+        (count(|c| matches!(c, Change::Removed { .. })), "-", "removed"),
+        (count(|c| matches!(c, Change::Moved { .. })), "", "moved"),
+        (count(|c| matches!(c, Change::Renamed { .. })), "", "renamed"),
+    ];
+    parts
+        .iter()
+        .filter(|(n, _, _)| *n > 0)
+        .map(|(n, sign, label)| format!("{}{} {}", sign, n, label))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: i64, name: &str, status: Status) -> Session {
+        Session {
+            id,
+            name: name.to_string(),
+            status,
+            checkout_path: None,
+            branch_name: None,
+            tmux_window: None,
+            created_at: 0,
+            updated_at: 0,
+            status_changed_at: 0,
+            snoozed_until: None,
+            owner: None,
+            pinned: false,
+            agent: None,
+            due: None,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = vec![
+            session(1, "Auth", Status::Planned),
+            session(2, "Docs", Status::InProgress),
+            session(3, "Old", Status::Done),
+        ];
+        let mut after = vec![
+            session(1, "Auth", Status::Review),
+            session(2, "Write docs", Status::Done),
+            session(4, "New", Status::Planned),
+        ];
+        after[0].updated_at = 99;
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes, vec![
+            Change::Added { id: 4, name: "New".to_string() },
+            Change::Removed { id: 3, name: "Old".to_string() },
+            Change::Moved { id: 1, name: "Auth".to_string(), from: Status::Planned, to: Status::Review },
+            Change::Moved { id: 2, name: "Write docs".to_string(), from: Status::InProgress, to: Status::Done },
+            Change::Renamed { id: 2, from: "Docs".to_string(), to: "Write docs".to_string() },
+        ]);
+        assert_eq!(summary(&changes), "+1 new, -1 removed, 2 moved, 1 renamed");
+
+        // Other fields, and the order, don't count
+        let reordered: Vec<Session> = after.iter().rev().cloned().collect();
+        assert!(diff(&after, &reordered).is_empty());
+        assert_eq!(summary(&[]), "");
+    }
+}
@@ -0,0 +1,348 @@
+use color_eyre::{eyre::eyre, Result};
+
+pub const USAGE: &str = "\
+Usage:
+  workbench [--status-file] [--no-color] [--overview] [--serve]
+                              Run the board (optionally writing a status file, without
+                              colors, opening on the overview of every project, or
+                              answering editor plugins on a local socket)
+  workbench status [--json]   Print the board status and exit
+  workbench add <name> [--allow-duplicate] [--start]
+                              Create a session (and its worktree); --start also starts
+                              its terminal and attaches. `new` is an alias
+  workbench attach <name|id>  Attach to a session's terminal, starting it if needed
+  workbench move <name|id> <column> [--no-autostart]
+                              Move a session to a column (--no-autostart: don't start
+                              its terminal when the project auto-starts In Progress)
+  workbench sessions [--names]
+                              List sessions (--names: one name per line, for completion)
+  workbench ai-usage [--weekly]
+                              AI fill calls, tokens and cost per day (or week)
+  workbench time [--last]     Approximate time each session's terminal was in use this
+                              week (--last: the week before)
+  workbench fields export [path]
+                              Write the project's field definitions as JSON (default: stdout)
+  workbench fields import <path|->
+                              Add the fields from an export that the project doesn't have
+  workbench init [--force]    Write the project's fields, columns and settings to
+                              .workbench.toml at its root, for teammates' boards to offer
+                              (--force: replace the file if there is one)
+  workbench backup [path]     Back up the database (default: timestamped, next to it)
+  workbench restore <path>    Replace the database with a backup
+  workbench doctor [--verbose] Check dependencies and paths (--verbose: recent log errors)
+  workbench help              Show this message
+
+Every command takes --db-path <file> to use another database (also WORKBENCH_DB).
+
+Set WORKBENCH_LOG=debug to log tmux/git/claude commands to a file.
+Set WORKBENCH_TITLE to a window title format ({project}, {sessions}, {waiting}, {badge}) or off.
+Set WORKBENCH_POLL_MS (10-1000, default 100) to change how often the board polls for input.
+Set NO_COLOR (or pass --no-color) for the monochrome theme.
+Send the board SIGHUP or SIGUSR1 (or press Ctrl+R in it) to reload the project's settings.";
+
+/// Command selected by the command-line arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Run the interactive board
+    Tui { write_status: bool, no_color: bool, overview: bool, serve: bool },
+    /// Print a snapshot of the board and exit
+    Status { json: bool },
+    /// Create a session without opening the board, optionally starting and attaching to it
+    Add { name: String, allow_duplicate: bool, start: bool },
+    /// Attach to a session's tmux session without opening the board
+    Attach { query: String },
+    /// Move a session to another column
+    Move { query: String, column: String, no_autostart: bool },
+    /// List the project's sessions
+    Sessions { names: bool },
+    /// Print AI usage totals per day or week
+    AiUsage { weekly: bool },
+    /// Print the time spent per session in a week, counted back from this one
+    Time { weeks_ago: u32 },
+    /// Write the project's field definitions to a path, or stdout if none given
+    FieldsExport { path: Option<String> },
+    /// Add fields from an export file (`-` for stdin)
+    FieldsImport { path: String },
+    /// Write the project's setup to `.workbench.toml`, replacing one only when forced
+    Init { force: bool },
+    /// Back up the database to a path, or a timestamped file if none given
+    Backup { path: Option<String> },
+    /// Replace the database with a backup
+    Restore { path: String },
+    /// Check the environment and print useful paths
+    Doctor { verbose: bool },
+    Help,
+}
+
+/// Parse command-line arguments (excluding the program name)
+pub fn parse(args: &[String]) -> Result<Command> {
+    let mut args = args.iter().map(String::as_str);
+
+    match args.next() {
+        None => Ok(Command::Tui { write_status: false, no_color: false, overview: false, serve: false }),
+        Some(first @ ("--status-file" | "--no-color" | "--overview" | "--serve")) => {
+            let mut write_status = false;
+            let mut no_color = false;
+            let mut overview = false;
+            let mut serve = false;
+            for arg in std::iter::once(first).chain(args) {
+                match arg {
+                    "--status-file" => write_status = true,
+                    "--no-color" => no_color = true,
+                    "--overview" => overview = true,
+                    "--serve" => serve = true,
+                    other => return Err(eyre!("Unexpected argument: {}", other)),
+                }
+            }
+            Ok(Command::Tui { write_status, no_color, overview, serve })
+        }
+        Some("status") => {
+            let mut json = false;
+            for arg in args {
+                match arg {
+                    "--json" => json = true,
+                    other => return Err(eyre!("Unknown argument for status: {}", other)),
+                }
+            }
+            Ok(Command::Status { json })
+        }
+        Some("add" | "new") => {
+            let mut name = None;
+            let mut allow_duplicate = false;
+            let mut start = false;
+            for arg in args {
+                match arg {
+                    "--allow-duplicate" => allow_duplicate = true,
+                    "--start" => start = true,
+                    other if other.starts_with("--") => {
+                        return Err(eyre!("Unknown argument for add: {}", other));
+                    }
+                    other if name.is_none() => name = Some(other.to_string()),
+                    other => return Err(eyre!("Unexpected argument: {}", other)),
+                }
+            }
+            let name = name.ok_or_else(|| eyre!("Missing session name\n\n{}", USAGE))?;
+            Ok(Command::Add { name, allow_duplicate, start })
+        }
+        Some("attach") => {
+            let query = args.next().ok_or_else(|| eyre!("Missing session name or id\n\n{}", USAGE))?;
+            let query = query.to_string();
+            reject_extra(args)?;
+            Ok(Command::Attach { query })
+        }
+        Some("move") => {
+            let mut positional = Vec::new();
+            let mut no_autostart = false;
+            for arg in args {
+                match arg {
+                    "--no-autostart" => no_autostart = true,
+                    other if other.starts_with("--") => {
+                        return Err(eyre!("Unknown argument for move: {}", other));
+                    }
+                    other => positional.push(other.to_string()),
+                }
+            }
+            let [query, column]: [String; 2] = positional
+                .try_into()
+                .map_err(|_| eyre!("Use: workbench move <name|id> <column>\n\n{}", USAGE))?;
+            Ok(Command::Move { query, column, no_autostart })
+        }
+        Some("sessions") => {
+            let mut names = false;
+            for arg in args {
+                match arg {
+                    "--names" => names = true,
+                    other => return Err(eyre!("Unknown argument for sessions: {}", other)),
+                }
+            }
+            Ok(Command::Sessions { names })
+        }
+        Some("ai-usage") => {
+            let mut weekly = false;
+            for arg in args {
+                match arg {
+                    "--weekly" => weekly = true,
+                    other => return Err(eyre!("Unknown argument for ai-usage: {}", other)),
+                }
+            }
+            Ok(Command::AiUsage { weekly })
+        }
+        Some("time") => {
+            let mut weeks_ago = 0;
+            for arg in args {
+                match arg {
+                    "--last" => weeks_ago = 1,
+                    other => return Err(eyre!("Unknown argument for time: {}", other)),
+                }
+            }
+            Ok(Command::Time { weeks_ago })
+        }
+        Some("fields") => match args.next() {
+            Some("export") => {
+                let path = args.next().map(String::from);
+                reject_extra(args)?;
+                Ok(Command::FieldsExport { path })
+            }
+            Some("import") => {
+                let path = args.next().ok_or_else(|| eyre!("Missing file to import (- for stdin)\n\n{}", USAGE))?;
+                let path = path.to_string();
+                reject_extra(args)?;
+                Ok(Command::FieldsImport { path })
+            }
+            _ => Err(eyre!("Use: workbench fields export [path] | import <path|->\n\n{}", USAGE)),
+        },
+        Some("init") => {
+            let mut force = false;
+            for arg in args {
+                match arg {
+                    "--force" => force = true,
+                    other => return Err(eyre!("Unknown argument for init: {}", other)),
+                }
+            }
+            Ok(Command::Init { force })
+        }
+        Some("backup") => {
+            let path = args.next().map(String::from);
+            reject_extra(args)?;
+            Ok(Command::Backup { path })
+        }
+        Some("restore") => {
+            let path = args.next().ok_or_else(|| eyre!("Missing backup path\n\n{}", USAGE))?;
+            let path = path.to_string();
+            reject_extra(args)?;
+            Ok(Command::Restore { path })
+        }
+        Some("doctor") => {
+            let mut verbose = false;
+            for arg in args {
+                match arg {
+                    "--verbose" | "-v" => verbose = true,
+                    other => return Err(eyre!("Unknown argument for doctor: {}", other)),
+                }
+            }
+            Ok(Command::Doctor { verbose })
+        }
+        Some("help") | Some("-h") | Some("--help") => Ok(Command::Help),
+        Some(other) => Err(eyre!("Unknown argument: {}\n\n{}", other, USAGE)),
+    }
+}
+
+/// Take `--db-path <file>` (or `--db-path=<file>`) out of the arguments, wherever it is
+pub fn take_db_path(args: &mut Vec<String>) -> Result<Option<String>> {
+    let Some(index) = args.iter().position(|arg| arg == "--db-path" || arg.starts_with("--db-path=")) else {
+        return Ok(None);
+    };
+    let arg = args.remove(index);
+    let path = match arg.strip_prefix("--db-path=") {
+        Some(path) => path.to_string(),
+        None if index < args.len() => args.remove(index),
+        None => return Err(eyre!("Missing file after --db-path")),
+    };
+    if path.is_empty() {
+        return Err(eyre!("Missing file after --db-path"));
+    }
+    if args.iter().any(|arg| arg == "--db-path" || arg.starts_with("--db-path=")) {
+        return Err(eyre!("--db-path given twice"));
+    }
+    Ok(Some(path))
+}
+
+fn reject_extra<'a>(mut args: impl Iterator<Item = &'a str>) -> Result<()> {
+    match args.next() {
+        Some(arg) => Err(eyre!("Unexpected argument: {}", arg)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&args(&[])).unwrap(), Command::Tui { write_status: false, no_color: false, overview: false, serve: false });
+        assert_eq!(
+            parse(&args(&["--status-file"])).unwrap(),
+            Command::Tui { write_status: true, no_color: false, overview: false, serve: false }
+        );
+        assert_eq!(
+            parse(&args(&["--no-color", "--status-file"])).unwrap(),
+            Command::Tui { write_status: true, no_color: true, overview: false, serve: false }
+        );
+        assert_eq!(
+            parse(&args(&["--overview"])).unwrap(),
+            Command::Tui { write_status: false, no_color: false, overview: true, serve: false }
+        );
+        assert_eq!(
+            parse(&args(&["--serve", "--overview"])).unwrap(),
+            Command::Tui { write_status: false, no_color: false, overview: true, serve: true }
+        );
+        assert!(parse(&args(&["--no-color", "status"])).is_err());
+        assert_eq!(parse(&args(&["status"])).unwrap(), Command::Status { json: false });
+        assert_eq!(parse(&args(&["status", "--json"])).unwrap(), Command::Status { json: true });
+        assert!(parse(&args(&["status", "--yaml"])).is_err());
+        assert_eq!(
+            parse(&args(&["add", "Fix auth", "--allow-duplicate"])).unwrap(),
+            Command::Add { name: "Fix auth".to_string(), allow_duplicate: true, start: false }
+        );
+        assert_eq!(
+            parse(&args(&["new", "Fix flaky CI", "--start"])).unwrap(),
+            Command::Add { name: "Fix flaky CI".to_string(), allow_duplicate: false, start: true }
+        );
+        assert!(parse(&args(&["add"])).is_err());
+        assert_eq!(parse(&args(&["attach", "17"])).unwrap(), Command::Attach { query: "17".to_string() });
+        assert!(parse(&args(&["attach"])).is_err());
+        assert!(parse(&args(&["attach", "fix", "auth"])).is_err());
+        assert_eq!(
+            parse(&args(&["move", "fix auth", "Review", "--no-autostart"])).unwrap(),
+            Command::Move { query: "fix auth".to_string(), column: "Review".to_string(), no_autostart: true }
+        );
+        assert!(parse(&args(&["move", "fix auth"])).is_err());
+        assert_eq!(parse(&args(&["sessions", "--names"])).unwrap(), Command::Sessions { names: true });
+        assert_eq!(parse(&args(&["ai-usage", "--weekly"])).unwrap(), Command::AiUsage { weekly: true });
+        assert_eq!(parse(&args(&["time"])).unwrap(), Command::Time { weeks_ago: 0 });
+        assert_eq!(parse(&args(&["time", "--last"])).unwrap(), Command::Time { weeks_ago: 1 });
+        assert!(parse(&args(&["time", "--weekly"])).is_err());
+        assert_eq!(parse(&args(&["fields", "export"])).unwrap(), Command::FieldsExport { path: None });
+        assert_eq!(
+            parse(&args(&["fields", "import", "-"])).unwrap(),
+            Command::FieldsImport { path: "-".to_string() }
+        );
+        assert!(parse(&args(&["fields", "import"])).is_err());
+        assert!(parse(&args(&["fields"])).is_err());
+        assert_eq!(parse(&args(&["init"])).unwrap(), Command::Init { force: false });
+        assert_eq!(parse(&args(&["init", "--force"])).unwrap(), Command::Init { force: true });
+        assert!(parse(&args(&["init", "workbench.toml"])).is_err());
+        assert_eq!(parse(&args(&["backup"])).unwrap(), Command::Backup { path: None });
+        assert_eq!(
+            parse(&args(&["restore", "wb.db"])).unwrap(),
+            Command::Restore { path: "wb.db".to_string() }
+        );
+        assert!(parse(&args(&["restore"])).is_err());
+        assert_eq!(parse(&args(&["doctor", "--verbose"])).unwrap(), Command::Doctor { verbose: true });
+        assert!(parse(&args(&["bogus"])).is_err());
+    }
+
+    #[test]
+    fn test_take_db_path() {
+        let mut list = args(&["--db-path", "/tmp/wb.db", "status", "--json"]);
+        assert_eq!(take_db_path(&mut list).unwrap().as_deref(), Some("/tmp/wb.db"));
+        assert_eq!(parse(&list).unwrap(), Command::Status { json: true });
+
+        let mut list = args(&["--no-color", "--db-path=board.db"]);
+        assert_eq!(take_db_path(&mut list).unwrap().as_deref(), Some("board.db"));
+        assert_eq!(list, args(&["--no-color"]));
+
+        let mut list = args(&["sessions"]);
+        assert_eq!(take_db_path(&mut list).unwrap(), None);
+        assert_eq!(list, args(&["sessions"]));
+
+        assert!(take_db_path(&mut args(&["status", "--db-path"])).is_err());
+        assert!(take_db_path(&mut args(&["--db-path="])).is_err());
+        assert!(take_db_path(&mut args(&["--db-path", "a.db", "--db-path", "b.db"])).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Pane content sent with an AI fill when the project doesn't set a limit, in characters
+pub const DEFAULT_MAX_CHARS: usize = 8_000;
+
+/// Smallest and largest limits the setting and the edit popup allow
+pub const MIN_CHARS: usize = 500;
+pub const MAX_CHARS: usize = 100_000;
+
+/// Escape sequences (colors, cursor moves, window titles), in case a capture kept them
+static ANSI: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]").expect("ANSI pattern")
+});
+
+/// Glyphs Claude Code cycles through in front of its status line while it works
+const SPINNER_GLYPHS: &[char] = &['✻', '✽', '✶', '✳', '✢', '·', '*'];
+
+/// Cut pane `content` down to at most `max_chars` for a prompt. Escape sequences and
+/// spinner frames always go and runs of blank lines become one. Over the limit, banner
+/// and box-art lines go first (oldest first), then the oldest lines, always whole; only
+/// a single line longer than the limit is cut, keeping its end
+pub fn trim(content: &str, max_chars: usize) -> String {
+    let plain = ANSI.replace_all(content, "");
+    let lines = collapse_blank_lines(plain.lines().map(str::trim_end).filter(|line| !is_spinner_frame(line)));
+    let mut total = char_count(&lines);
+
+    let lines = if total > max_chars {
+        let mut kept = Vec::with_capacity(lines.len());
+        for line in lines {
+            if total > max_chars && is_noise(line) {
+                total = total.saturating_sub(line.chars().count() + 1);
+            } else {
+                kept.push(line);
+            }
+        }
+        collapse_blank_lines(kept)
+    } else {
+        lines
+    };
+
+    let mut total = char_count(&lines);
+    let mut start = 0;
+    while start + 1 < lines.len() && (total > max_chars || lines[start].is_empty()) {
+        total -= lines[start].chars().count() + 1;
+        start += 1;
+    }
+    let text = lines[start..].join("\n");
+    let count = text.chars().count();
+    if count > max_chars {
+        text.chars().skip(count - max_chars).collect()
+    } else {
+        text
+    }
+}
+
+/// Lines with every run of blank lines made one, and none at either end
+fn collapse_blank_lines<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut collapsed: Vec<&str> = Vec::new();
+    for line in lines {
+        let blank = line.trim().is_empty();
+        if blank && collapsed.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        collapsed.push(if blank { "" } else { line });
+    }
+    if collapsed.last() == Some(&"") {
+        collapsed.pop();
+    }
+    collapsed
+}
+
+/// Characters in the lines joined by newlines
+fn char_count(lines: &[&str]) -> usize {
+    lines.iter().map(|line| line.chars().count() + 1).sum::<usize>().saturating_sub(1)
+}
+
+/// A spinner's status line, e.g. `✻ Thinking… (esc to interrupt)`, or a braille spinner
+/// frame from a build tool
+fn is_spinner_frame(line: &str) -> bool {
+    let mut chars = line.trim_start().chars();
+    let Some(glyph) = chars.next() else { return false };
+    if ('\u{2800}'..='\u{28FF}').contains(&glyph) {
+        return true;
+    }
+    let rest = chars.as_str().trim_start();
+    // `*` and `·` also start list items, so the status has to look like one
+    SPINNER_GLYPHS.contains(&glyph)
+        && (rest.contains("esc to interrupt") || rest.split_whitespace().next().is_some_and(|word| word.ends_with('…')))
+}
+
+/// Banner and box art: frames, rules and separators, where letters and digits make up
+/// under a third of what's printed
+fn is_noise(line: &str) -> bool {
+    let printed: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    let words = printed.iter().filter(|c| c.is_alphanumeric()).count();
+    let boxed = printed.iter().any(|c| ('\u{2500}'..='\u{259F}').contains(c));
+    words * 3 < printed.len() && (printed.len() >= 4 || boxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE_SESSION: &str = include_str!("../tests/fixtures/claude_session.txt");
+    const RUST_PANIC: &str = include_str!("../tests/fixtures/rust_panic.txt");
+
+    #[test]
+    fn test_strips_spinners_and_blank_runs() {
+        let trimmed = trim(CLAUDE_SESSION, MAX_CHARS);
+        assert!(!trimmed.contains("esc to interrupt"));
+        assert!(!trimmed.contains("\n\n\n"));
+        // Under the limit, the banner and real content stay
+        assert!(trimmed.contains("Welcome to Claude Code!"));
+        assert!(trimmed.contains("KeyError: 'save10'"));
+        assert!(trimmed.ends_with("? for shortcuts"));
+        // List items aren't spinners
+        assert_eq!(trim("* Thinking about it\n· done", MAX_CHARS), "* Thinking about it\n· done");
+
+        let trimmed = trim(RUST_PANIC, MAX_CHARS);
+        assert!(!trimmed.contains("Compiling"));
+        assert!(trimmed.starts_with("$ cargo test -p billing\n    Finished"));
+        assert_eq!(trim("\x1b[31merror\x1b[0m: boom\x1b]0;title\x07", MAX_CHARS), "error: boom");
+    }
+
+    #[test]
+    fn test_drops_noise_before_content() {
+        let full = trim(CLAUDE_SESSION, MAX_CHARS);
+        let trimmed = trim(CLAUDE_SESSION, full.chars().count() - 600);
+        assert!(trimmed.chars().count() <= full.chars().count() - 600);
+        // The banner frame and the test output's rules went, not the start of the session
+        assert!(!trimmed.starts_with('╭'));
+        assert!(!trimmed.contains("_ _ _"));
+        assert!(trimmed.contains("Welcome to Claude Code!"));
+        assert!(trimmed.contains("> Fix the failing checkout test"));
+        assert!(trimmed.contains("https://github.com/acme/shop/pull/412"));
+    }
+
+    #[test]
+    fn test_keeps_whole_recent_lines() {
+        let trimmed = trim(RUST_PANIC, 400);
+        assert!(trimmed.chars().count() <= 400);
+        assert!(trimmed.ends_with("error: test failed, to rerun pass `-p billing --lib`"));
+        // The oldest lines went, whole
+        let full = trim(RUST_PANIC, MAX_CHARS);
+        assert!(trimmed.lines().all(|line| full.lines().any(|l| l == line)));
+
+        // A single line over the limit keeps its end
+        assert_eq!(trim("abcdef", 3), "def");
+        assert_eq!(trim("", 10), "");
+    }
+}
@@ -1,7 +1,57 @@
-use color_eyre::Result;
-use rusqlite::{Connection, params};
+use color_eyre::{eyre::eyre, Result};
+use rusqlite::types::{FromSql, FromSqlResult, Value, ValueRef};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+
+/// Stored in `PRAGMA user_version`; bump when the schema changes incompatibly
+const SCHEMA_VERSION: i64 = 1;
+
+/// `app_state` keys: the project the board showed last, and the one before it
+const STATE_LAST_PROJECT: &str = "last_project_id";
+const STATE_PREVIOUS_PROJECT: &str = "previous_project_id";
+
+/// Number of timestamped backups kept next to the database
+const MAX_BACKUPS: usize = 5;
+
+/// Environment variable naming the database file; `--db-path` wins over it
+pub const DB_ENV: &str = "WORKBENCH_DB";
+
+/// The database file given with `--db-path`, for the rest of the process
+static DB_PATH_FLAG: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+/// Where the database path came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DbPathSource {
+    Flag,
+    Env,
+    Default,
+}
+
+/// The database file: the `--db-path` flag, else `$WORKBENCH_DB` (when not empty), else
+/// `workbench.db` under the data dir
+fn resolve_db_path(
+    flag: Option<&Path>,
+    env: Option<&std::ffi::OsStr>,
+    data_dir: impl FnOnce() -> Result<PathBuf>,
+) -> Result<(PathBuf, DbPathSource)> {
+    if let Some(path) = flag {
+        return Ok((path.to_path_buf(), DbPathSource::Flag));
+    }
+    if let Some(path) = env.filter(|p| !p.is_empty()) {
+        return Ok((PathBuf::from(path), DbPathSource::Env));
+    }
+    Ok((data_dir()?.join("workbench.db"), DbPathSource::Default))
+}
+
+/// Attach history rows kept per project
+const ATTACH_HISTORY_LIMIT: i64 = 100;
+
+/// Values offered when completing a field
+const FIELD_VALUE_SUGGESTIONS: usize = 8;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
@@ -45,45 +95,403 @@ impl Status {
     }
 }
 
+/// Who wrote a field value: typed in, filled by AI, taken from a tracker or pane output,
+/// or preset by a shortcut
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueSource {
+    #[default]
+    Manual,
+    Ai,
+    Import,
+    Default,
+}
+
+impl ValueSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValueSource::Manual => "manual",
+            ValueSource::Ai => "ai",
+            ValueSource::Import => "import",
+            ValueSource::Default => "default",
+        }
+    }
+
+    /// Unknown sources read as typed in, like rows from before sources were kept
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "ai" => ValueSource::Ai,
+            "import" => ValueSource::Import,
+            "default" => ValueSource::Default,
+            _ => ValueSource::Manual,
+        }
+    }
+}
+
+/// How a column orders its cards, after pinned ones
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnSort {
+    /// Oldest card first
+    #[default]
+    Created,
+    /// Most recent terminal activity first; cards without a live terminal by when they
+    /// last changed
+    Activity,
+}
+
+impl ColumnSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColumnSort::Created => "",
+            ColumnSort::Activity => "activity",
+        }
+    }
+
+    /// Unknown modes read as the default order
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "activity" => ColumnSort::Activity,
+            _ => ColumnSort::Created,
+        }
+    }
+}
+
+/// Per-project display override for a built-in status (empty strings fall back to defaults)
+#[derive(Debug, Clone)]
+pub struct StatusOverride {
+    pub status: Status,
+    pub label: String,
+    pub color: String,
+    /// Items to tick off before a card may enter the column; empty for an instant move
+    pub checklist: Vec<String>,
+    pub sort: ColumnSort,
+    /// Fields asked for, in order, right after naming a card created in the column
+    pub new_card_fields: Vec<i64>,
+}
+
+/// Resolve the display label for a status, preferring a non-empty project override
+pub fn resolve_status_label(overrides: &[StatusOverride], status: Status) -> &str {
+    overrides
+        .iter()
+        .find(|o| o.status == status && !o.label.trim().is_empty())
+        .map(|o| o.label.as_str())
+        .unwrap_or_else(|| status.label())
+}
+
 #[derive(Debug, Clone)]
 pub struct Project {
     pub id: i64,
     pub name: String,
-    pub path: String,
+    pub path: PathBuf,
+    /// Listed first in the overview
+    pub pinned: bool,
+    /// Left out of the overview unless archived projects are shown; nothing is deleted
+    pub archived: bool,
+    /// Unix time the project was last opened, None if never since this was recorded
+    pub last_opened_at: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Session {
     pub id: i64,
-    pub project_id: i64,
     pub name: String,
     pub status: Status,
-    pub checkout_path: Option<String>,
+    pub checkout_path: Option<PathBuf>,
     pub branch_name: Option<String>,
-    pub ticket_id: Option<String>,
-    pub ticket_url: Option<String>,
     pub tmux_window: Option<String>,
-    pub claude_session_id: Option<String>,
+    /// Unix timestamps (seconds)
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub status_changed_at: i64,
+    /// Hidden from the board until this unix time
+    pub snoozed_until: Option<i64>,
+    /// Who the card belongs to (free text, usually a git user name)
+    pub owner: Option<String>,
+    /// Part of today's focus: starred and listed first in its column
+    pub pinned: bool,
+    /// Agent profile the session runs; None for the default
+    pub agent: Option<String>,
+    /// Day it is due, counted from 1970-01-01
+    pub due: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Field {
     pub id: i64,
-    pub project_id: i64,
     pub name: String,
     pub description: String,
     pub display_order: i64,
+    /// Shown on cards (the layout's visible fields)
+    pub visible: bool,
+    /// Sent to claude for AI fill
+    pub in_ai: bool,
+    /// A row of the edit popup
+    pub in_edit: bool,
+    /// Wraps onto a few card lines instead of being cut off
+    pub wrap: bool,
+}
+
+#[cfg(test)]
+impl Field {
+    /// A shown, AI-filled, editable field, ordered by its id
+    pub fn fixture(id: i64, name: &str) -> Self {
+        Field {
+            id,
+            name: name.to_string(),
+            description: String::new(),
+            display_order: id,
+            visible: true,
+            in_ai: true,
+            in_edit: true,
+            wrap: false,
+        }
+    }
+}
+
+/// A field without its project, as copied between projects or kept in an export file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "visible_by_default")]
     pub visible: bool,
+    #[serde(default = "visible_by_default")]
+    pub in_ai: bool,
+    #[serde(default = "visible_by_default")]
+    pub in_edit: bool,
+    #[serde(default)]
+    pub wrap: bool,
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+impl From<&Field> for FieldDefinition {
+    fn from(field: &Field) -> Self {
+        Self {
+            name: field.name.clone(),
+            description: field.description.clone(),
+            visible: field.visible,
+            in_ai: field.in_ai,
+            in_edit: field.in_edit,
+            wrap: field.wrap,
+        }
+    }
+}
+
+/// Format version written by `workbench fields export`
+const FIELDS_FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct FieldsFile {
+    version: u32,
+    fields: Vec<FieldDefinition>,
+}
+
+/// The export file for a project's fields, in display order
+pub fn fields_to_json(fields: &[Field]) -> Result<String> {
+    let file = FieldsFile { version: FIELDS_FILE_VERSION, fields: fields.iter().map(Into::into).collect() };
+    Ok(serde_json::to_string_pretty(&file)?)
+}
+
+pub fn fields_from_json(text: &str) -> Result<Vec<FieldDefinition>> {
+    let file: FieldsFile = serde_json::from_str(text).map_err(|e| eyre!("Not a fields export: {}", e))?;
+    if file.version > FIELDS_FILE_VERSION {
+        return Err(eyre!("Fields export version {} is newer than this workbench understands", file.version));
+    }
+    Ok(file.fields)
+}
+
+/// Names created and skipped (already present) by `import_fields`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FieldImport {
+    pub created: Vec<String>,
+    pub skipped: Vec<String>,
 }
 
+/// A session in the trash, with when it was deleted (unix seconds)
 #[derive(Debug, Clone)]
-pub struct Comment {
+pub struct TrashedSession {
+    pub session: Session,
+    pub deleted_at: i64,
+}
+
+/// Reusable AI instruction with `{placeholder}`s for field values
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    pub id: i64,
+    pub name: String,
+    pub body: String,
+}
+
+/// What a watch rule does when its pattern shows up in new pane output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAction {
+    Move(Status),
+    /// Set the field to the pattern's first capture group; may point at a deleted field
+    SetField(i64),
+    Notify,
+}
+
+/// Per-project rule matched against what a session's pane prints
+#[derive(Debug, Clone)]
+pub struct WatchRule {
     pub id: i64,
+    pub pattern: String,
+    pub action: WatchAction,
+}
+
+/// Something workbench did on its own, e.g. a watch rule moving a card
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub kind: String,
+    pub detail: String,
+    /// Local time
+    pub created_at: String,
+}
+
+/// AI calls made in one day or week
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiUsageTotal {
+    /// The day, or the Monday starting the week (`YYYY-MM-DD`, local time)
+    pub period: String,
+    pub calls: i64,
+    pub tokens: i64,
+    pub cost_usd: f64,
+    /// Calls whose output carried no cost
+    pub unpriced: i64,
+}
+
+/// Active time of one session within a week
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeTotal {
     pub session_id: i64,
+    pub name: String,
+    pub seconds: i64,
+}
+
+/// Active time per session over one week, most first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeReport {
+    /// The Monday starting the week (`YYYY-MM-DD`, local time)
+    pub week_of: String,
+    pub totals: Vec<TimeTotal>,
+}
+
+/// Seconds without a heartbeat after which a board's hold on a project is taken over
+pub const LOCK_STALE_SECS: i64 = 60;
+
+/// A session's scratchpad, as last saved or read from its notes file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionNotes {
+    pub text: String,
+    /// Modification time (unix ms) of the notes file when `text` was last synced with
+    /// it; None while the notes only live in the database
+    pub file_mtime: Option<i64>,
+}
+
+/// A board holding a project open; only advisory, nothing stops a second board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectLock {
+    pub hostname: String,
+    pub pid: u32,
+    /// Unix seconds
+    pub heartbeat: i64,
+}
+
+/// Scrollback saved when a session's tmux session was killed
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub path: String,
+    pub created_at: String,
+}
+
+/// Quick-create preset: a session name, target column and optionally one field to prompt for
+#[derive(Debug, Clone)]
+pub struct Shortcut {
+    pub id: i64,
+    pub name: String,
+    pub status: Status,
+    /// May point at a field that has since been deleted
+    pub field_id: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comment {
     pub text: String,
     pub created_at: String,
 }
 
+/// A filesystem path as stored in a TEXT column. Paths that aren't valid UTF-8 are
+/// stored as a BLOB of their raw bytes, so they come back exactly as they went in
+struct SqlPath(PathBuf);
+
+impl FromSql for SqlPath {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Text(bytes) | ValueRef::Blob(bytes) => Ok(SqlPath(crate::paths::from_bytes(bytes))),
+            _ => Err(rusqlite::types::FromSqlError::InvalidType),
+        }
+    }
+}
+
+/// The value to bind for a path column (see `SqlPath`)
+fn path_value(path: &Path) -> Value {
+    match path.to_str() {
+        Some(text) => Value::Text(text.to_string()),
+        None => Value::Blob(path.as_os_str().as_encoded_bytes().to_vec()),
+    }
+}
+
+/// Columns read by `session_from_row`, in order
+const SESSION_COLUMNS: &str = "id, name, status, checkout_path, branch_name, tmux_window,
+    CAST(strftime('%s', created_at) AS INTEGER),
+    CAST(strftime('%s', updated_at) AS INTEGER),
+    CAST(strftime('%s', COALESCE(status_changed_at, created_at)) AS INTEGER),
+    CAST(strftime('%s', snoozed_until) AS INTEGER),
+    owner, pinned, agent,
+    CAST(julianday(due_on) - 2440587.5 AS INTEGER)";
+
+/// Columns read by `project_from_row`, in order
+const PROJECT_COLUMNS: &str = "id, name, path, pinned, archived, CAST(strftime('%s', last_opened_at) AS INTEGER)";
+
+fn project_from_row(row: &rusqlite::Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        path: row.get::<_, SqlPath>(2)?.0,
+        pinned: row.get(3)?,
+        archived: row.get(4)?,
+        last_opened_at: row.get(5)?,
+    })
+}
+
+fn session_from_row(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    let status_str: String = row.get(2)?;
+    Ok(Session {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        status: Status::from_str(&status_str).unwrap_or(Status::Planned),
+        checkout_path: row.get::<_, Option<SqlPath>>(3)?.map(|p| p.0),
+        branch_name: row.get(4)?,
+        tmux_window: row.get(5)?,
+        created_at: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
+        updated_at: row.get::<_, Option<i64>>(7)?.unwrap_or(0),
+        status_changed_at: row.get::<_, Option<i64>>(8)?.unwrap_or(0),
+        snoozed_until: row.get(9)?,
+        owner: row.get(10)?,
+        pinned: row.get(11)?,
+        agent: row.get(12)?,
+        due: row.get(13)?,
+    })
+}
+
+/// SQLite date modifier for `days` ago, e.g. `-30 days`
+fn archive_cutoff(days: u64) -> String {
+    format!("-{} days", days)
+}
+
 pub struct Database {
     conn: Connection,
 }
@@ -95,6 +503,7 @@ impl Database {
             std::fs::create_dir_all(parent)?;
         }
 
+        tracing::debug!(path = %db_path.display(), "opening database");
         let conn = Connection::open(&db_path)?;
         let db = Self { conn };
         db.init_schema()?;
@@ -102,18 +511,267 @@ impl Database {
         Ok(db)
     }
 
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        let db = Self { conn: Connection::open_in_memory()? };
+        db.init_schema()?;
+        db.run_migrations();
+        Ok(db)
+    }
+
+    /// Give every field after the first of a name (ignoring case) in its project a free
+    /// " (2)", " (3)"... suffix, returning how many were renamed
+    fn rename_duplicate_fields(&self) -> Result<usize> {
+        let mut stmt = self.conn.prepare(
+            "SELECT f.id, f.project_id, f.name FROM fields f
+             WHERE EXISTS (
+                 SELECT 1 FROM fields g
+                 WHERE g.project_id = f.project_id AND g.name = f.name COLLATE NOCASE AND g.id < f.id
+             )
+             ORDER BY f.id",
+        )?;
+        let duplicates = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (id, project_id, name) in &duplicates {
+            let taken = |candidate: &str| -> Result<bool> {
+                Ok(self.conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM fields WHERE project_id = ?1 AND name = ?2 COLLATE NOCASE)",
+                    params![project_id, candidate],
+                    |row| row.get(0),
+                )?)
+            };
+            let mut n = 2;
+            while taken(&format!("{} ({})", name, n))? {
+                n += 1;
+            }
+            self.conn.execute(
+                "UPDATE fields SET name = ?1 WHERE id = ?2",
+                params![format!("{} ({})", name, n), id],
+            )?;
+        }
+        Ok(duplicates.len())
+    }
+
     fn run_migrations(&self) {
         // Add visible column to fields if it doesn't exist
         let _ = self.conn.execute(
             "ALTER TABLE fields ADD COLUMN visible INTEGER NOT NULL DEFAULT 1",
             [],
         );
+        // Whether AI fill and the edit popup include a field
+        let _ = self.conn.execute("ALTER TABLE fields ADD COLUMN in_ai INTEGER NOT NULL DEFAULT 1", []);
+        let _ = self.conn.execute("ALTER TABLE fields ADD COLUMN in_edit INTEGER NOT NULL DEFAULT 1", []);
+        // Whether a field wraps onto several card lines
+        let _ = self.conn.execute("ALTER TABLE fields ADD COLUMN wrap INTEGER NOT NULL DEFAULT 0", []);
+
+        // Track when a session last changed column
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN status_changed_at TEXT", []);
+
+        // Field names are unique per project; older databases may already have duplicates,
+        // which are renamed so the index can go on
+        match self.rename_duplicate_fields() {
+            Ok(0) => {}
+            Ok(renamed) => tracing::warn!(renamed, "renamed fields that shared a name with another in their project"),
+            Err(e) => tracing::warn!(error = %e, "could not rename duplicate field names"),
+        }
+        if let Err(e) = self.conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_fields_project_name ON fields(project_id, name COLLATE NOCASE)",
+            [],
+        ) {
+            tracing::warn!(error = %e, "field names are not enforced unique");
+        }
+
+        // Soft-deleted sessions live in the trash until purged
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN deleted_at TEXT", []);
+
+        // Snoozed sessions are hidden from the board until this time
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN snoozed_until TEXT", []);
+
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN owner TEXT", []);
+
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0", []);
+
+        // Agent profile a session runs, by name
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN agent TEXT", []);
+
+        // Due date, as YYYY-MM-DD
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN due_on TEXT", []);
+
+        // The board lists a project's sessions by column, and cards look up their values
+        let _ = self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_project_status ON sessions(project_id, status)",
+            [],
+        );
+        let _ = self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_session_field_values_session ON session_field_values(session_id)",
+            [],
+        );
+
+        // Completing a field from the values it already holds, per keystroke
+        let _ = self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_session_field_values_field ON session_field_values(field_id, value)",
+            [],
+        );
+
+        // Who wrote each field value; values from before were typed in
+        let _ = self.conn.execute(
+            "ALTER TABLE session_field_values ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'",
+            [],
+        );
+
+        // Project list order and hiding
+        let _ = self.conn.execute("ALTER TABLE projects ADD COLUMN last_opened_at TEXT", []);
+        let _ = self.conn.execute("ALTER TABLE projects ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0", []);
+        let _ = self.conn.execute("ALTER TABLE projects ADD COLUMN archived INTEGER NOT NULL DEFAULT 0", []);
+
+        // Scratchpad, mirroring the session's notes file when it has one
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN notes TEXT NOT NULL DEFAULT ''", []);
+        let _ = self.conn.execute("ALTER TABLE sessions ADD COLUMN notes_mtime INTEGER", []);
+
+        // Entry checklists, one item per line
+        let _ = self.conn.execute("ALTER TABLE status_overrides ADD COLUMN checklist TEXT NOT NULL DEFAULT ''", []);
+
+        // Column order mode, empty for the default
+        let _ = self.conn.execute("ALTER TABLE status_overrides ADD COLUMN sort TEXT NOT NULL DEFAULT ''", []);
+
+        // Fields a card created in the column is asked for, one id per line
+        let _ = self.conn.execute("ALTER TABLE status_overrides ADD COLUMN new_card_fields TEXT NOT NULL DEFAULT ''", []);
+
+        // Stamp the schema version so backups can be recognized as workbench databases
+        let _ = self.conn.pragma_update(None, "user_version", SCHEMA_VERSION);
     }
 
-    fn db_path() -> Result<PathBuf> {
+    fn data_dir() -> Result<PathBuf> {
         let data_dir = dirs::data_dir()
-            .ok_or_else(|| color_eyre::eyre::eyre!("Could not find data directory"))?;
-        Ok(data_dir.join("workbench").join("workbench.db"))
+            .ok_or_else(|| eyre!("Could not find data directory"))?;
+        Ok(data_dir.join("workbench"))
+    }
+
+    /// Use `path` as the database for the rest of the process (`--db-path`)
+    pub fn set_db_path(path: &Path) -> Result<()> {
+        DB_PATH_FLAG.set(path.to_path_buf()).map_err(|_| eyre!("The database path is already set"))
+    }
+
+    pub fn db_path() -> Result<PathBuf> {
+        Ok(Self::db_path_with_source()?.0)
+    }
+
+    /// The database path and what chose it, for `workbench doctor`. Relative paths are
+    /// taken from the current directory
+    pub fn db_path_with_source() -> Result<(PathBuf, DbPathSource)> {
+        let env = std::env::var_os(DB_ENV);
+        let (path, source) = resolve_db_path(DB_PATH_FLAG.get().map(PathBuf::as_path), env.as_deref(), Self::data_dir)?;
+        Ok((std::path::absolute(path)?, source))
+    }
+
+    /// Timestamped backups, in a `backups` directory next to the database
+    pub fn backups_dir() -> Result<PathBuf> {
+        let db_path = Self::db_path()?;
+        Ok(db_path.parent().unwrap_or(Path::new(".")).join("backups"))
+    }
+
+    /// Saved scrollback, under the state dir since it can be thrown away
+    pub fn transcripts_dir() -> Result<PathBuf> {
+        let state_dir = paths::state_dir().ok_or_else(|| eyre!("Could not find state directory"))?;
+        Ok(state_dir.join("transcripts"))
+    }
+
+    /// Agent profiles, under the data dir
+    pub fn agents_path() -> Result<PathBuf> {
+        Ok(Self::data_dir()?.join("agents.json"))
+    }
+
+    /// Write a consistent copy of the database to `path` (which must not exist)
+    pub fn backup_to(&self, path: &Path) -> Result<()> {
+        // SQLite takes the file name as text; a lossy copy would write somewhere else
+        let target = path.to_str().ok_or_else(|| eyre!("Backup path is not valid UTF-8: {}", path.display()))?;
+        if path.exists() {
+            return Err(eyre!("Backup target already exists: {}", path.display()));
+        }
+        if let Some(parent) = path.parent() && !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+        tracing::debug!(path = %path.display(), "backing up database");
+        self.conn.execute("VACUUM INTO ?1", params![target])?;
+        Ok(())
+    }
+
+    /// Back up to a timestamped file next to the database, keeping the newest `MAX_BACKUPS`
+    pub fn backup_timestamped(&self) -> Result<PathBuf> {
+        let stamp: String = self.conn.query_row(
+            "SELECT strftime('%Y%m%d-%H%M%S', 'now', 'localtime')",
+            [],
+            |row| row.get(0),
+        )?;
+        let dir = Self::backups_dir()?;
+        let mut path = dir.join(format!("workbench-{}.db", stamp));
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("workbench-{}-{}.db", stamp, n));
+            n += 1;
+        }
+        self.backup_to(&path)?;
+        Self::rotate_backups(&dir)?;
+        Ok(path)
+    }
+
+    fn rotate_backups(dir: &Path) -> Result<()> {
+        let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("workbench-") && n.ends_with(".db"))
+            })
+            .collect();
+        // Timestamped names sort chronologically
+        backups.sort();
+        let excess = backups.len().saturating_sub(MAX_BACKUPS);
+        for old in &backups[..excess] {
+            std::fs::remove_file(old)?;
+        }
+        Ok(())
+    }
+
+    /// Check that a file is a workbench database this version can open
+    fn verify_backup(path: &Path) -> Result<()> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| eyre!("Cannot open {}: {}", path.display(), e))?;
+        let version: i64 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|_| eyre!("{} is not a SQLite database", path.display()))?;
+        if version == 0 {
+            return Err(eyre!("{} is not a workbench database (no schema version)", path.display()));
+        }
+        if version > SCHEMA_VERSION {
+            return Err(eyre!("{} was written by a newer workbench (schema {})", path.display(), version));
+        }
+        let tables: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('projects', 'sessions', 'fields')",
+            [],
+            |row| row.get(0),
+        )?;
+        if tables != 3 {
+            return Err(eyre!("{} is missing workbench tables", path.display()));
+        }
+        Ok(())
+    }
+
+    /// Replace the live database with a backup, after verifying it and
+    /// saving the current database as a timestamped backup
+    /// Returns the path of the safety backup
+    pub fn restore_from(path: &Path) -> Result<PathBuf> {
+        Self::verify_backup(path)?;
+        let db_path = Self::db_path()?;
+        if InstanceMarker::others_running(&db_path)? {
+            return Err(eyre!("Another workbench instance is running; quit it before restoring"));
+        }
+
+        let safety = Self::new()?.backup_timestamped()?;
+        restore_file(&db_path, path)?;
+        Ok(safety)
     }
 
     fn init_schema(&self) -> Result<()> {
@@ -138,6 +796,9 @@ impl Database {
                 claude_session_id TEXT,
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                status_changed_at TEXT,
+                deleted_at TEXT,
+                snoozed_until TEXT,
                 FOREIGN KEY (project_id) REFERENCES projects(id)
             );
 
@@ -161,6 +822,29 @@ impl Database {
                 UNIQUE(session_id, field_id)
             );
 
+            CREATE TABLE IF NOT EXISTS app_state (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS project_settings (
+                project_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (project_id, key),
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS status_overrides (
+                project_id INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                label TEXT NOT NULL DEFAULT '',
+                color TEXT NOT NULL DEFAULT '',
+                checklist TEXT NOT NULL DEFAULT '',
+                PRIMARY KEY (project_id, status),
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
+
             CREATE TABLE IF NOT EXISTS comments (
                 id INTEGER PRIMARY KEY,
                 session_id INTEGER NOT NULL,
@@ -168,268 +852,1355 @@ impl Database {
                 created_at TEXT DEFAULT CURRENT_TIMESTAMP,
                 FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
             );
-            ",
-        )?;
-        Ok(())
-    }
-
-    pub fn get_or_create_project(&self, name: &str, path: &str) -> Result<Project> {
-        if let Some(project) = self.get_project_by_path(path)? {
-            return Ok(project);
-        }
 
-        self.conn.execute(
-            "INSERT INTO projects (name, path) VALUES (?1, ?2)",
-            params![name, path],
-        )?;
-
-        let id = self.conn.last_insert_rowid();
-        Ok(Project {
-            id,
-            name: name.to_string(),
-            path: path.to_string(),
-        })
-    }
+            CREATE TABLE IF NOT EXISTS shortcuts (
+                id INTEGER PRIMARY KEY,
+                project_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'planned',
+                field_id INTEGER,
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
 
-    fn get_project_by_path(&self, path: &str) -> Result<Option<Project>> {
-        let mut stmt = self.conn.prepare("SELECT id, name, path FROM projects WHERE path = ?1")?;
-        let mut rows = stmt.query(params![path])?;
+            CREATE TABLE IF NOT EXISTS prompt_templates (
+                id INTEGER PRIMARY KEY,
+                project_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                body TEXT NOT NULL,
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
 
+            CREATE TABLE IF NOT EXISTS session_env (
+                session_id INTEGER NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                PRIMARY KEY (session_id, key),
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS transcripts (
+                id INTEGER PRIMARY KEY,
+                session_id INTEGER NOT NULL,
+                path TEXT NOT NULL,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS watch_rules (
+                id INTEGER PRIMARY KEY,
+                project_id INTEGER NOT NULL,
+                pattern TEXT NOT NULL,
+                action TEXT NOT NULL,
+                status TEXT,
+                field_id INTEGER,
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS checklist_checks (
+                session_id INTEGER NOT NULL,
+                status TEXT NOT NULL,
+                item TEXT NOT NULL,
+                PRIMARY KEY (session_id, status, item),
+                FOREIGN KEY (session_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS session_blockers (
+                session_id INTEGER NOT NULL,
+                blocker_id INTEGER NOT NULL,
+                PRIMARY KEY (session_id, blocker_id),
+                FOREIGN KEY (session_id) REFERENCES sessions(id),
+                FOREIGN KEY (blocker_id) REFERENCES sessions(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS events (
+                id INTEGER PRIMARY KEY,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                project_id INTEGER NOT NULL,
+                session_id INTEGER,
+                kind TEXT NOT NULL,
+                detail TEXT NOT NULL,
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS ai_usage (
+                id INTEGER PRIMARY KEY,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                project_id INTEGER NOT NULL,
+                session_id INTEGER,
+                purpose TEXT NOT NULL,
+                model TEXT,
+                tokens INTEGER,
+                cost_usd REAL,
+                FOREIGN KEY (project_id) REFERENCES projects(id)
+            );
+
+            CREATE TABLE IF NOT EXISTS attach_history (
+                id INTEGER PRIMARY KEY,
+                project_id INTEGER NOT NULL,
+                session_id INTEGER NOT NULL,
+                attached_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS time_samples (
+                session_id INTEGER NOT NULL,
+                hour INTEGER NOT NULL,
+                seconds INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (session_id, hour),
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS locks (
+                project_id INTEGER NOT NULL,
+                hostname TEXT NOT NULL,
+                pid INTEGER NOT NULL,
+                heartbeat INTEGER NOT NULL,
+                PRIMARY KEY (project_id, hostname, pid),
+                FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
+            );
+            ",
+        )?;
+        Ok(())
+    }
+
+    /// The project at `path`, created if new, marked as opened now
+    pub fn get_or_create_project(&self, name: &str, path: &Path) -> Result<Project> {
+        if let Some(project) = self.get_project_by_path(path)? {
+            self.touch_project(project.id)?;
+            return Ok(Project { last_opened_at: Some(unix_now()), ..project });
+        }
+
+        self.conn.execute(
+            "INSERT INTO projects (name, path, last_opened_at) VALUES (?1, ?2, datetime('now'))",
+            params![name, path_value(path)],
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        Ok(Project {
+            id,
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            pinned: false,
+            archived: false,
+            last_opened_at: Some(unix_now()),
+        })
+    }
+
+    fn get_project_by_path(&self, path: &Path) -> Result<Option<Project>> {
+        let project = self.conn
+            .query_row(
+                &format!("SELECT {} FROM projects WHERE path = ?1", PROJECT_COLUMNS),
+                params![path_value(path)],
+                project_from_row,
+            )
+            .optional()?;
+        Ok(project)
+    }
+
+    /// Every project, archived ones included: pinned first, then the most recently
+    /// opened, then the oldest
+    pub fn list_projects(&self) -> Result<Vec<Project>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM projects ORDER BY pinned DESC, last_opened_at IS NULL, last_opened_at DESC, id",
+            PROJECT_COLUMNS
+        ))?;
+        let projects = stmt.query_map([], project_from_row)?;
+        projects.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Mark a project as opened now, for the overview's order
+    pub fn touch_project(&self, project_id: i64) -> Result<()> {
+        self.conn.execute("UPDATE projects SET last_opened_at = datetime('now') WHERE id = ?1", params![project_id])?;
+        Ok(())
+    }
+
+    pub fn set_project_pinned(&self, project_id: i64, pinned: bool) -> Result<()> {
+        self.conn.execute("UPDATE projects SET pinned = ?1 WHERE id = ?2", params![pinned, project_id])?;
+        Ok(())
+    }
+
+    pub fn set_project_archived(&self, project_id: i64, archived: bool) -> Result<()> {
+        self.conn.execute("UPDATE projects SET archived = ?1 WHERE id = ?2", params![archived, project_id])?;
+        Ok(())
+    }
+
+    /// Point a project at a new location (the repo was moved), forgetting
+    /// session checkouts that no longer exist on disk
+    pub fn update_project_path(&self, project_id: i64, name: &str, path: &Path) -> Result<()> {
+        self.conn.execute(
+            "UPDATE projects SET name = ?1, path = ?2 WHERE id = ?3",
+            params![name, path_value(path), project_id],
+        )?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, checkout_path FROM sessions WHERE project_id = ?1 AND checkout_path IS NOT NULL",
+        )?;
+        let checkouts = stmt
+            .query_map(params![project_id], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, SqlPath>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (session_id, checkout_path) in checkouts {
+            if !checkout_path.0.exists() {
+                self.clear_session_worktree(session_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete a project that has no sessions or fields (e.g. one created by mistake)
+    pub fn delete_empty_project(&self, project_id: i64) -> Result<()> {
+        if !self.project_is_empty(project_id)? {
+            return Err(eyre!("Project {} is not empty", project_id));
+        }
+        self.conn.execute("DELETE FROM project_settings WHERE project_id = ?1", params![project_id])?;
+        self.conn.execute("DELETE FROM status_overrides WHERE project_id = ?1", params![project_id])?;
+        self.conn.execute("DELETE FROM projects WHERE id = ?1", params![project_id])?;
+        Ok(())
+    }
+
+    /// Whether a project has any sessions (including trashed ones) or fields
+    pub fn project_is_empty(&self, project_id: i64) -> Result<bool> {
+        let used: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sessions WHERE project_id = ?1)
+                 OR EXISTS(SELECT 1 FROM fields WHERE project_id = ?1)",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+        Ok(!used)
+    }
+
+    /// Changes whenever another connection (e.g. a `workbench` command) commits, never
+    /// for this connection's own writes
+    pub fn data_version(&self) -> Result<i64> {
+        Ok(self.conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+    }
+
+    /// Note `project_id` as the project in use, returning the one used before it, to
+    /// switch back to. Reopening the same project keeps the one from before that
+    pub fn record_active_project(&self, project_id: i64) -> Result<Option<i64>> {
+        let state = |key: &str| -> Result<Option<i64>> {
+            let value: Option<String> = self.conn
+                .query_row("SELECT value FROM app_state WHERE key = ?1", params![key], |row| row.get(0))
+                .optional()?;
+            Ok(value.and_then(|v| v.parse().ok()))
+        };
+        let previous = match state(STATE_LAST_PROJECT)? {
+            Some(last) if last != project_id => Some(last),
+            _ => state(STATE_PREVIOUS_PROJECT)?.filter(|id| *id != project_id),
+        };
+        self.touch_project(project_id)?;
+        let mut pairs = vec![(STATE_LAST_PROJECT, project_id)];
+        pairs.extend(previous.map(|id| (STATE_PREVIOUS_PROJECT, id)));
+        for (key, id) in pairs {
+            self.conn.execute(
+                "INSERT INTO app_state (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = ?2",
+                params![key, id.to_string()],
+            )?;
+        }
+        Ok(previous)
+    }
+
+    pub fn get_project(&self, project_id: i64) -> Result<Option<Project>> {
+        Ok(self.list_projects()?.into_iter().find(|p| p.id == project_id))
+    }
+
+    pub fn get_project_setting(&self, project_id: i64, key: &str) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT value FROM project_settings WHERE project_id = ?1 AND key = ?2",
+        )?;
+        let mut rows = stmt.query(params![project_id, key])?;
         if let Some(row) = rows.next()? {
-            Ok(Some(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                path: row.get(2)?,
-            }))
+            Ok(Some(row.get(0)?))
         } else {
             Ok(None)
         }
     }
 
-    pub fn list_sessions(&self, project_id: i64) -> Result<Vec<Session>> {
+    pub fn set_project_setting(&self, project_id: i64, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO project_settings (project_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, key) DO UPDATE SET value = ?3",
+            params![project_id, key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_status_overrides(&self, project_id: i64) -> Result<Vec<StatusOverride>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT status, label, color, checklist, sort, new_card_fields FROM status_overrides WHERE project_id = ?1",
+        )?;
+        let overrides = stmt.query_map(params![project_id], |row| {
+            let status_str: String = row.get(0)?;
+            Ok((status_str, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get::<_, String>(5)?))
+        })?;
+
+        let mut result = Vec::new();
+        for row in overrides {
+            let (status_str, label, color, checklist, sort, new_card_fields): (String, String, String, String, String, String) = row?;
+            if let Some(status) = Status::from_str(&status_str) {
+                let checklist = checklist.lines().map(String::from).collect();
+                let sort = ColumnSort::from_str(&sort);
+                let new_card_fields = new_card_fields.lines().filter_map(|id| id.parse().ok()).collect();
+                result.push(StatusOverride { status, label, color, checklist, sort, new_card_fields });
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn set_status_label(&self, project_id: i64, status: Status, label: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO status_overrides (project_id, status, label) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, status) DO UPDATE SET label = ?3",
+            params![project_id, status.as_str(), label],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_status_color(&self, project_id: i64, status: Status, color: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO status_overrides (project_id, status, color) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, status) DO UPDATE SET color = ?3",
+            params![project_id, status.as_str(), color],
+        )?;
+        Ok(())
+    }
+
+    /// Replace the entry checklist of a column; no items removes it
+    pub fn set_status_checklist(&self, project_id: i64, status: Status, items: &[String]) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO status_overrides (project_id, status, checklist) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, status) DO UPDATE SET checklist = ?3",
+            params![project_id, status.as_str(), items.join("\n")],
+        )?;
+        Ok(())
+    }
+
+    /// Replace the fields asked for after naming a card created in the column; none
+    /// keeps the single name prompt
+    pub fn set_status_new_card_fields(&self, project_id: i64, status: Status, field_ids: &[i64]) -> Result<()> {
+        let ids: Vec<String> = field_ids.iter().map(i64::to_string).collect();
+        self.conn.execute(
+            "INSERT INTO status_overrides (project_id, status, new_card_fields) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, status) DO UPDATE SET new_card_fields = ?3",
+            params![project_id, status.as_str(), ids.join("\n")],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_status_sort(&self, project_id: i64, status: Status, sort: ColumnSort) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO status_overrides (project_id, status, sort) VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, status) DO UPDATE SET sort = ?3",
+            params![project_id, status.as_str(), sort.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Checklist items already ticked for a session's move into `status`
+    pub fn checked_items(&self, session_id: i64, status: Status) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT item FROM checklist_checks WHERE session_id = ?1 AND status = ?2 ORDER BY rowid",
+        )?;
+        let items = stmt.query_map(params![session_id, status.as_str()], |row| row.get(0))?;
+        items.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn set_item_checked(&self, session_id: i64, status: Status, item: &str, checked: bool) -> Result<()> {
+        if checked {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO checklist_checks (session_id, status, item) VALUES (?1, ?2, ?3)",
+                params![session_id, status.as_str(), item],
+            )?;
+        } else {
+            self.conn.execute(
+                "DELETE FROM checklist_checks WHERE session_id = ?1 AND status = ?2 AND item = ?3",
+                params![session_id, status.as_str(), item],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Note that `session_id` can't finish before `blocker_id` does
+    pub fn add_blocker(&self, session_id: i64, blocker_id: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO session_blockers (session_id, blocker_id) VALUES (?1, ?2)",
+            params![session_id, blocker_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_blockers(&self, session_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM session_blockers WHERE session_id = ?1", params![session_id])?;
+        Ok(())
+    }
+
+    /// Every (session, blocker) link between the project's sessions outside the trash
+    pub fn list_blockers(&self, project_id: i64) -> Result<Vec<(i64, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT b.session_id, b.blocker_id FROM session_blockers b
+             JOIN sessions s ON s.id = b.session_id
+             JOIN sessions blocker ON blocker.id = b.blocker_id
+             WHERE s.project_id = ?1 AND s.deleted_at IS NULL AND blocker.deleted_at IS NULL
+             ORDER BY b.session_id, b.blocker_id",
+        )?;
+        let links = stmt.query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        links.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn list_sessions(&self, project_id: i64) -> Result<Vec<Session>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE project_id = ?1 AND deleted_at IS NULL ORDER BY id",
+            SESSION_COLUMNS
+        ))?;
+
+        let sessions = stmt.query_map(params![project_id], session_from_row)?;
+        sessions.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Sessions for the board: all live ones except Done cards that entered Done more than
+    /// `archive_days` ago (0 keeps them all)
+    pub fn list_board_sessions(&self, project_id: i64, archive_days: u64) -> Result<Vec<Session>> {
+        if archive_days == 0 {
+            return self.list_sessions(project_id);
+        }
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE project_id = ?1 AND deleted_at IS NULL
+             AND NOT (status = 'done' AND COALESCE(status_changed_at, created_at) < datetime('now', ?2))
+             ORDER BY id",
+            SESSION_COLUMNS
+        ))?;
+        let sessions = stmt.query_map(params![project_id, archive_cutoff(archive_days)], session_from_row)?;
+        sessions.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// One page of the Done cards `list_board_sessions` leaves out, most recently done first
+    pub fn list_archived_sessions(&self, project_id: i64, archive_days: u64, limit: usize, offset: usize) -> Result<Vec<Session>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE project_id = ?1 AND deleted_at IS NULL
+             AND status = 'done' AND COALESCE(status_changed_at, created_at) < datetime('now', ?2)
+             ORDER BY COALESCE(status_changed_at, created_at) DESC, id DESC LIMIT ?3 OFFSET ?4",
+            SESSION_COLUMNS
+        ))?;
+        let sessions = stmt.query_map(
+            params![project_id, archive_cutoff(archive_days), limit as i64, offset as i64],
+            session_from_row,
+        )?;
+        sessions.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// How many Done cards `list_board_sessions` leaves out
+    pub fn count_archived_sessions(&self, project_id: i64, archive_days: u64) -> Result<usize> {
+        if archive_days == 0 {
+            return Ok(0);
+        }
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sessions WHERE project_id = ?1 AND deleted_at IS NULL
+             AND status = 'done' AND COALESCE(status_changed_at, created_at) < datetime('now', ?2)",
+            params![project_id, archive_cutoff(archive_days)],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// A session as it is stored now, if it still exists in the project and isn't trashed
+    pub fn get_live_session(&self, project_id: i64, session_id: i64) -> Result<Option<Session>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM sessions WHERE id = ?1 AND project_id = ?2 AND deleted_at IS NULL",
+            SESSION_COLUMNS
+        ))?;
+        let mut rows = stmt.query_map(params![session_id, project_id], session_from_row)?;
+        rows.next().transpose().map_err(Into::into)
+    }
+
+    /// Trashed sessions for a project, most recently deleted first
+    pub fn list_trashed_sessions(&self, project_id: i64) -> Result<Vec<TrashedSession>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, CAST(strftime('%s', deleted_at) AS INTEGER)
+             FROM sessions WHERE project_id = ?1 AND deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC, id DESC",
+            SESSION_COLUMNS
+        ))?;
+
+        let sessions = stmt.query_map(params![project_id], |row| {
+            Ok(TrashedSession {
+                session: session_from_row(row)?,
+                // The column after SESSION_COLUMNS
+                deleted_at: row.get::<_, Option<i64>>(14)?.unwrap_or(0),
+            })
+        })?;
+        sessions.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    #[cfg(test)]
+    pub fn create_session(&self, project_id: i64, name: &str) -> Result<Session> {
+        self.create_session_with(project_id, name, Status::Planned, None, None)
+    }
+
+    /// Create several sessions, each in its own column, in a single transaction
+    pub fn create_sessions(&self, project_id: i64, sessions: &[(String, Status)], owner: Option<&str>) -> Result<Vec<Session>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut ids = Vec::new();
+        for (name, status) in sessions {
+            tx.execute(
+                "INSERT INTO sessions (project_id, name, status, owner) VALUES (?1, ?2, ?3, ?4)",
+                params![project_id, name, status.as_str(), owner],
+            )?;
+            ids.push(tx.last_insert_rowid());
+        }
+        tx.commit()?;
+        ids.into_iter()
+            .map(|id| self.get_live_session(project_id, id)?.ok_or_else(|| eyre!("Session {} vanished after creation", id)))
+            .collect()
+    }
+
+    /// Create a session in `status`, optionally with one field value, in a single transaction
+    pub fn create_session_with(
+        &self,
+        project_id: i64,
+        name: &str,
+        status: Status,
+        field_value: Option<(i64, &str)>,
+        owner: Option<&str>,
+    ) -> Result<Session> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO sessions (project_id, name, status, owner) VALUES (?1, ?2, ?3, ?4)",
+            params![project_id, name, status.as_str(), owner],
+        )?;
+        let id = tx.last_insert_rowid();
+        if let Some((field_id, value)) = field_value {
+            tx.execute(
+                "INSERT INTO session_field_values (session_id, field_id, value, source) VALUES (?1, ?2, ?3, ?4)",
+                params![id, field_id, value, ValueSource::Default.as_str()],
+            )?;
+        }
+        tx.commit()?;
+
+        let now = unix_now();
+        Ok(Session {
+            id,
+            name: name.to_string(),
+            status,
+            checkout_path: None,
+            branch_name: None,
+            tmux_window: None,
+            created_at: now,
+            updated_at: now,
+            status_changed_at: now,
+            snoozed_until: None,
+            owner: owner.map(String::from),
+            pinned: false,
+            agent: None,
+            due: None,
+        })
+    }
+
+    /// Set or (with None) clear the owner of a session
+    pub fn update_session_owner(&self, session_id: i64, owner: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET owner = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![owner, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set the agent profile of a session, or (with None) go back to the default
+    pub fn update_session_agent(&self, session_id: i64, agent: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET agent = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![agent, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Set or (with None) clear the day a session is due, counted from 1970-01-01
+    pub fn set_session_due(&self, session_id: i64, due: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET due_on = date(?1 * 86400, 'unixepoch'), updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![due, session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_session_notes(&self, session_id: i64) -> Result<SessionNotes> {
+        let notes = self.conn.query_row(
+            "SELECT notes, notes_mtime FROM sessions WHERE id = ?1",
+            params![session_id],
+            |row| Ok(SessionNotes { text: row.get(0)?, file_mtime: row.get(1)? }),
+        )?;
+        Ok(notes)
+    }
+
+    /// Store a session's scratchpad, with the notes file's modification time when it
+    /// was written to or read from one
+    pub fn set_session_notes(&self, session_id: i64, text: &str, file_mtime: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET notes = ?1, notes_mtime = ?2 WHERE id = ?3",
+            params![text, file_mtime, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Sessions of a project whose notes were synced with a file, with that file's
+    /// modification time then
+    pub fn list_notes_mtimes(&self, project_id: i64) -> Result<HashMap<i64, i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, notes_mtime FROM sessions WHERE project_id = ?1 AND notes_mtime IS NOT NULL AND deleted_at IS NULL",
+        )?;
+        let rows = stmt.query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<HashMap<_, _>, _>>().map_err(Into::into)
+    }
+
+    /// Today in local time, counted in days from 1970-01-01 like due dates
+    pub fn local_today(&self) -> Result<i64> {
+        Ok(self.conn.query_row(
+            "SELECT CAST(julianday(date('now', 'localtime')) - 2440587.5 AS INTEGER)",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    pub fn update_session_status(&self, session_id: i64, status: Status) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET status = ?1, updated_at = CURRENT_TIMESTAMP, status_changed_at = CURRENT_TIMESTAMP
+             WHERE id = ?2 AND status != ?1",
+            params![status.as_str(), session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_session_name(&self, session_id: i64, name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET name = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![name, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Move a session to the trash; its tmux session is gone, so forget it. The worktree
+    /// stays on disk until the session is purged
+    pub fn trash_session(&self, session_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET deleted_at = CURRENT_TIMESTAMP, tmux_window = NULL
+             WHERE id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Pin a session to the top of its column, or unpin it
+    pub fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()> {
+        self.conn.execute("UPDATE sessions SET pinned = ?1 WHERE id = ?2", params![pinned, session_id])?;
+        Ok(())
+    }
+
+    /// Snooze a session until a unix time, or wake it with None
+    pub fn set_session_snooze(&self, session_id: i64, until: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET snoozed_until = datetime(?1, 'unixepoch') WHERE id = ?2",
+            params![until, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Clear snoozes that have expired, returning the names of the sessions that woke up
+    pub fn wake_snoozed_sessions(&self, project_id: i64) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name FROM sessions
+             WHERE project_id = ?1 AND deleted_at IS NULL AND snoozed_until <= CURRENT_TIMESTAMP
+             ORDER BY id",
+        )?;
+        let names = stmt
+            .query_map(params![project_id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if !names.is_empty() {
+            self.conn.execute(
+                "UPDATE sessions SET snoozed_until = NULL
+                 WHERE project_id = ?1 AND snoozed_until <= CURRENT_TIMESTAMP",
+                params![project_id],
+            )?;
+        }
+        Ok(names)
+    }
+
+    pub fn restore_session(&self, session_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET deleted_at = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Permanently delete a session with its field values, comments and transcripts
+    pub fn purge_session(&self, session_id: i64) -> Result<()> {
+        let transcripts = self.list_transcripts(session_id)?;
+        // Foreign keys aren't enforced, so remove dependent rows explicitly
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM transcripts WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM events WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM session_field_values WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM comments WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM attach_history WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM session_env WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM checklist_checks WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM time_samples WHERE session_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM session_blockers WHERE session_id = ?1 OR blocker_id = ?1", params![session_id])?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", params![session_id])?;
+        tx.commit()?;
+        for transcript in transcripts {
+            let _ = std::fs::remove_file(&transcript.path);
+        }
+        Ok(())
+    }
+
+    /// Purge sessions that have been in the trash longer than `days`, returning the
+    /// (repo, worktree) of each purged checkout for the caller to remove
+    pub fn purge_trash_older_than(&self, days: u32) -> Result<Vec<(PathBuf, PathBuf)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, p.path, s.checkout_path FROM sessions s JOIN projects p ON p.id = s.project_id
+             WHERE s.deleted_at IS NOT NULL AND s.deleted_at < datetime('now', ?1)",
+        )?;
+        let expired = stmt
+            .query_map(params![format!("-{} days", days)], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, SqlPath>(1)?, row.get::<_, Option<SqlPath>>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut checkouts = Vec::new();
+        for (id, repo, checkout) in expired {
+            self.purge_session(id)?;
+            if let Some(checkout) = checkout {
+                checkouts.push((repo.0, checkout.0));
+            }
+        }
+        Ok(checkouts)
+    }
+
+    pub fn set_tmux_session(&self, session_id: i64, tmux_name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET tmux_window = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![tmux_name, session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_tmux_session(&self, session_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET tmux_window = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_session_worktree(&self, session_id: i64, checkout_path: &Path, branch_name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET checkout_path = ?1, branch_name = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+            params![path_value(checkout_path), branch_name, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Store the branch a session's worktree has checked out, keeping its path
+    pub fn update_session_branch(&self, session_id: i64, branch_name: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET branch_name = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![branch_name, session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_session_worktree(&self, session_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET checkout_path = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![session_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_fields(&self, project_id: i64) -> Result<Vec<Field>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, description, display_order, visible, in_ai, in_edit, wrap
+             FROM fields WHERE project_id = ?1 ORDER BY display_order, id",
+        )?;
+
+        let fields = stmt.query_map(params![project_id], |row| {
+            Ok(Field {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                display_order: row.get(3)?,
+                visible: row.get::<_, i64>(4)? != 0,
+                in_ai: row.get::<_, i64>(5)? != 0,
+                in_edit: row.get::<_, i64>(6)? != 0,
+                wrap: row.get::<_, i64>(7)? != 0,
+            })
+        })?;
+
+        fields.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn create_field(&self, project_id: i64, name: &str, description: &str) -> Result<Field> {
+        self.ensure_field_name_free(project_id, name, None)?;
+        let max_order: i64 = self.conn.query_row(
+            "SELECT COALESCE(MAX(display_order), -1) FROM fields WHERE project_id = ?1",
+            params![project_id],
+            |row| row.get(0),
+        )?;
+
+        self.conn.execute(
+            "INSERT INTO fields (project_id, name, description, display_order) VALUES (?1, ?2, ?3, ?4)",
+            params![project_id, name, description, max_order + 1],
+        )?;
+
+        let id = self.conn.last_insert_rowid();
+        Ok(Field {
+            id,
+            name: name.to_string(),
+            description: description.to_string(),
+            display_order: max_order + 1,
+            visible: true,
+            in_ai: true,
+            in_edit: true,
+            wrap: false,
+        })
+    }
+
+    pub fn update_field(&self, field_id: i64, name: &str, description: &str) -> Result<()> {
+        let project_id: i64 = self.conn.query_row(
+            "SELECT project_id FROM fields WHERE id = ?1",
+            params![field_id],
+            |row| row.get(0),
+        )?;
+        self.ensure_field_name_free(project_id, name, Some(field_id))?;
+        self.conn.execute(
+            "UPDATE fields SET name = ?1, description = ?2 WHERE id = ?3",
+            params![name, description, field_id],
+        )?;
+        Ok(())
+    }
+
+    /// Reject empty names and names already used by another field in the project
+    fn ensure_field_name_free(&self, project_id: i64, name: &str, except_id: Option<i64>) -> Result<()> {
+        if name.trim().is_empty() {
+            return Err(eyre!("Field name cannot be empty"));
+        }
+        let taken: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM fields WHERE project_id = ?1 AND name = ?2 COLLATE NOCASE AND id != ?3)",
+            params![project_id, name.trim(), except_id.unwrap_or(-1)],
+            |row| row.get(0),
+        )?;
+        if taken {
+            return Err(eyre!("A field named \"{}\" already exists", name.trim()));
+        }
+        Ok(())
+    }
+
+    /// Add the definitions a project doesn't have yet, in order, after its own fields.
+    /// Names it already has (case-insensitively) are skipped. All or nothing: an error
+    /// leaves the project's fields as they were
+    pub fn import_fields(&self, project_id: i64, definitions: &[FieldDefinition]) -> Result<FieldImport> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut import = FieldImport::default();
+        for (idx, definition) in definitions.iter().enumerate() {
+            let name = definition.name.trim();
+            if name.is_empty() {
+                return Err(eyre!("Field {} has no name", idx + 1));
+            }
+            if self.ensure_field_name_free(project_id, name, None).is_err() {
+                import.skipped.push(name.to_string());
+                continue;
+            }
+            let field = self.create_field(project_id, name, &definition.description)?;
+            if !definition.visible {
+                self.toggle_field_visibility(field.id)?;
+            }
+            if !definition.in_ai {
+                self.toggle_field_in_ai(field.id)?;
+            }
+            if !definition.in_edit {
+                self.toggle_field_in_edit(field.id)?;
+            }
+            if definition.wrap {
+                self.toggle_field_wrap(field.id)?;
+            }
+            import.created.push(name.to_string());
+        }
+        tx.commit()?;
+        Ok(import)
+    }
+
+    pub fn toggle_field_visibility(&self, field_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE fields SET visible = NOT visible WHERE id = ?1",
+            params![field_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn toggle_field_in_ai(&self, field_id: i64) -> Result<()> {
+        self.conn.execute("UPDATE fields SET in_ai = NOT in_ai WHERE id = ?1", params![field_id])?;
+        Ok(())
+    }
+
+    pub fn toggle_field_in_edit(&self, field_id: i64) -> Result<()> {
+        self.conn.execute("UPDATE fields SET in_edit = NOT in_edit WHERE id = ?1", params![field_id])?;
+        Ok(())
+    }
+
+    pub fn toggle_field_wrap(&self, field_id: i64) -> Result<()> {
+        self.conn.execute("UPDATE fields SET wrap = NOT wrap WHERE id = ?1", params![field_id])?;
+        Ok(())
+    }
+
+    pub fn delete_field(&self, field_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM fields WHERE id = ?1", params![field_id])?;
+        Ok(())
+    }
+
+    pub fn move_field_up(&self, project_id: i64, field_id: i64) -> Result<()> {
+        let fields = self.list_fields(project_id)?;
+        let idx = fields.iter().position(|f| f.id == field_id);
+        if let Some(i) = idx && i > 0 {
+            let prev_id = fields[i - 1].id;
+            let prev_order = fields[i - 1].display_order;
+            let curr_order = fields[i].display_order;
+            self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![prev_order, field_id])?;
+            self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![curr_order, prev_id])?;
+        }
+        Ok(())
+    }
+
+    pub fn move_field_down(&self, project_id: i64, field_id: i64) -> Result<()> {
+        let fields = self.list_fields(project_id)?;
+        let idx = fields.iter().position(|f| f.id == field_id);
+        if let Some(i) = idx && i < fields.len() - 1 {
+            let next_id = fields[i + 1].id;
+            let next_order = fields[i + 1].display_order;
+            let curr_order = fields[i].display_order;
+            self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![next_order, field_id])?;
+            self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![curr_order, next_id])?;
+        }
+        Ok(())
+    }
+
+    pub fn get_session_field_value(&self, session_id: i64, field_id: i64) -> Result<String> {
+        let result: Result<String, _> = self.conn.query_row(
+            "SELECT value FROM session_field_values WHERE session_id = ?1 AND field_id = ?2",
+            params![session_id, field_id],
+            |row| row.get(0),
+        );
+        Ok(result.unwrap_or_default())
+    }
+
+    /// Who wrote a value; unset values count as typed in
+    pub fn get_session_field_source(&self, session_id: i64, field_id: i64) -> Result<ValueSource> {
+        let result: Result<String, _> = self.conn.query_row(
+            "SELECT source FROM session_field_values WHERE session_id = ?1 AND field_id = ?2",
+            params![session_id, field_id],
+            |row| row.get(0),
+        );
+        Ok(result.map(|s| ValueSource::from_str(&s)).unwrap_or_default())
+    }
+
+    pub fn set_session_field_value(&self, session_id: i64, field_id: i64, value: &str, source: ValueSource) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO session_field_values (session_id, field_id, value, source) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(session_id, field_id) DO UPDATE SET value = ?3, source = ?4",
+            params![session_id, field_id, value, source.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Set one field on many sessions in a single transaction, returning how many were
+    /// set. With `only_empty`, sessions that already have a value keep it
+    pub fn set_field_value_for_sessions(
+        &self,
+        session_ids: &[i64],
+        field_id: i64,
+        value: &str,
+        source: ValueSource,
+        only_empty: bool,
+    ) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut set = 0;
+        for &session_id in session_ids {
+            if only_empty && !self.get_session_field_value(session_id, field_id)?.trim().is_empty() {
+                continue;
+            }
+            self.set_session_field_value(session_id, field_id, value, source)?;
+            set += 1;
+        }
+        tx.commit()?;
+        Ok(set)
+    }
+
+    /// Distinct non-empty values a field already holds that start with `prefix`
+    /// (ASCII case ignored), the most used first, at most `FIELD_VALUE_SUGGESTIONS`
+    pub fn distinct_field_values(&self, field_id: i64, prefix: &str) -> Result<Vec<String>> {
+        let pattern = format!("{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let mut stmt = self.conn.prepare(
+            "SELECT value FROM session_field_values
+             WHERE field_id = ?1 AND value <> '' AND value LIKE ?2 ESCAPE '\\'
+             GROUP BY value ORDER BY COUNT(*) DESC, value LIMIT ?3",
+        )?;
+        let values = stmt.query_map(params![field_id, pattern, FIELD_VALUE_SUGGESTIONS as i64], |row| row.get(0))?;
+        values.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn get_all_session_field_values(&self, session_id: i64) -> Result<Vec<(i64, String, ValueSource)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT field_id, value, source FROM session_field_values WHERE session_id = ?1",
+        )?;
+        let values = stmt.query_map(params![session_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, ValueSource::from_str(&row.get::<_, String>(2)?)))
+        })?;
+        values.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Every field value set on the project's live sessions, keyed by (session, field)
+    pub fn field_values_by_session(&self, project_id: i64) -> Result<HashMap<(i64, i64), (String, ValueSource)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT v.session_id, v.field_id, v.value, v.source FROM session_field_values v
+             JOIN sessions s ON s.id = v.session_id WHERE s.project_id = ?1 AND s.deleted_at IS NULL",
+        )?;
+        let values = stmt.query_map(params![project_id], |row| {
+            Ok(((row.get(0)?, row.get(1)?), (row.get(2)?, ValueSource::from_str(&row.get::<_, String>(3)?))))
+        })?;
+        values.collect::<Result<HashMap<_, _>, _>>().map_err(Into::into)
+    }
+
+    pub fn list_shortcuts(&self, project_id: i64) -> Result<Vec<Shortcut>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, status, field_id FROM shortcuts WHERE project_id = ?1 ORDER BY id",
+        )?;
+        let shortcuts = stmt.query_map(params![project_id], |row| {
+            let status: String = row.get(2)?;
+            Ok(Shortcut {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                status: Status::from_str(&status).unwrap_or(Status::Planned),
+                field_id: row.get(3)?,
+            })
+        })?;
+        shortcuts.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn create_shortcut(&self, project_id: i64, name: &str, status: Status, field_id: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO shortcuts (project_id, name, status, field_id) VALUES (?1, ?2, ?3, ?4)",
+            params![project_id, name, status.as_str(), field_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_shortcut(&self, shortcut_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM shortcuts WHERE id = ?1", params![shortcut_id])?;
+        Ok(())
+    }
+
+    pub fn list_watch_rules(&self, project_id: i64) -> Result<Vec<WatchRule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, pattern, action, status, field_id FROM watch_rules WHERE project_id = ?1 ORDER BY id",
+        )?;
+        let rules = stmt.query_map(params![project_id], |row| {
+            let action: String = row.get(2)?;
+            let status: Option<String> = row.get(3)?;
+            let field_id: Option<i64> = row.get(4)?;
+            let action = match (action.as_str(), field_id) {
+                ("set", Some(field_id)) => WatchAction::SetField(field_id),
+                ("move", _) => WatchAction::Move(
+                    status.as_deref().and_then(Status::from_str).unwrap_or(Status::Review),
+                ),
+                _ => WatchAction::Notify,
+            };
+            Ok(WatchRule { id: row.get(0)?, pattern: row.get(1)?, action })
+        })?;
+        rules.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn create_watch_rule(&self, project_id: i64, pattern: &str, action: WatchAction) -> Result<()> {
+        let (name, status, field_id) = match action {
+            WatchAction::Move(status) => ("move", Some(status.as_str()), None),
+            WatchAction::SetField(field_id) => ("set", None, Some(field_id)),
+            WatchAction::Notify => ("notify", None, None),
+        };
+        self.conn.execute(
+            "INSERT INTO watch_rules (project_id, pattern, action, status, field_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![project_id, pattern, name, status, field_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_watch_rule(&self, rule_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM watch_rules WHERE id = ?1", params![rule_id])?;
+        Ok(())
+    }
+
+    pub fn log_event(&self, project_id: i64, session_id: Option<i64>, kind: &str, detail: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO events (project_id, session_id, kind, detail) VALUES (?1, ?2, ?3, ?4)",
+            params![project_id, session_id, kind, detail],
+        )?;
+        Ok(())
+    }
+
+    /// Latest events whose kind starts with `kind_prefix`, newest first
+    pub fn recent_events(&self, project_id: i64, kind_prefix: &str, limit: usize) -> Result<Vec<Event>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, project_id, name, status, checkout_path, branch_name,
-                    ticket_id, ticket_url, tmux_window, claude_session_id
-             FROM sessions WHERE project_id = ?1 ORDER BY id",
+            "SELECT kind, detail, datetime(created_at, 'localtime') FROM events
+             WHERE project_id = ?1 AND substr(kind, 1, length(?2)) = ?2 ORDER BY id DESC LIMIT ?3",
         )?;
+        let events = stmt.query_map(params![project_id, kind_prefix, limit as i64], |row| {
+            Ok(Event { kind: row.get(0)?, detail: row.get(1)?, created_at: row.get(2)? })
+        })?;
+        events.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
 
-        let sessions = stmt.query_map(params![project_id], |row| {
-            let status_str: String = row.get(3)?;
-            Ok(Session {
+    pub fn list_prompt_templates(&self, project_id: i64) -> Result<Vec<PromptTemplate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, body FROM prompt_templates WHERE project_id = ?1 ORDER BY name COLLATE NOCASE",
+        )?;
+        let templates = stmt.query_map(params![project_id], |row| {
+            Ok(PromptTemplate {
                 id: row.get(0)?,
-                project_id: row.get(1)?,
-                name: row.get(2)?,
-                status: Status::from_str(&status_str).unwrap_or(Status::Planned),
-                checkout_path: row.get(4)?,
-                branch_name: row.get(5)?,
-                ticket_id: row.get(6)?,
-                ticket_url: row.get(7)?,
-                tmux_window: row.get(8)?,
-                claude_session_id: row.get(9)?,
+                name: row.get(1)?,
+                body: row.get(2)?,
             })
         })?;
-
-        sessions.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        templates.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    pub fn create_session(&self, project_id: i64, name: &str) -> Result<Session> {
+    pub fn create_prompt_template(&self, project_id: i64, name: &str, body: &str) -> Result<()> {
         self.conn.execute(
-            "INSERT INTO sessions (project_id, name, status) VALUES (?1, ?2, 'planned')",
-            params![project_id, name],
+            "INSERT INTO prompt_templates (project_id, name, body) VALUES (?1, ?2, ?3)",
+            params![project_id, name, body],
         )?;
-
-        let id = self.conn.last_insert_rowid();
-        Ok(Session {
-            id,
-            project_id,
-            name: name.to_string(),
-            status: Status::Planned,
-            checkout_path: None,
-            branch_name: None,
-            ticket_id: None,
-            ticket_url: None,
-            tmux_window: None,
-            claude_session_id: None,
-        })
+        Ok(())
     }
 
-    pub fn update_session_status(&self, session_id: i64, status: Status) -> Result<()> {
+    pub fn update_prompt_template(&self, template_id: i64, name: &str, body: &str) -> Result<()> {
         self.conn.execute(
-            "UPDATE sessions SET status = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params![status.as_str(), session_id],
+            "UPDATE prompt_templates SET name = ?1, body = ?2 WHERE id = ?3",
+            params![name, body, template_id],
         )?;
         Ok(())
     }
 
-    pub fn update_session_name(&self, session_id: i64, name: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE sessions SET name = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params![name, session_id],
-        )?;
+    pub fn delete_prompt_template(&self, template_id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM prompt_templates WHERE id = ?1", params![template_id])?;
         Ok(())
     }
 
-    pub fn delete_session(&self, session_id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM sessions WHERE id = ?1", params![session_id])?;
-        Ok(())
+    /// Environment variables for a session's tmux session, sorted by key
+    pub fn list_session_env(&self, session_id: i64) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare("SELECT key, value FROM session_env WHERE session_id = ?1 ORDER BY key")?;
+        let vars = stmt.query_map(params![session_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        vars.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    pub fn set_tmux_session(&self, session_id: i64, tmux_name: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE sessions SET tmux_window = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params![tmux_name, session_id],
+    /// Set a variable, in place of `replacing` when it was edited under another name
+    pub fn set_session_env(&self, session_id: i64, key: &str, value: &str, replacing: Option<&str>) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        if let Some(old) = replacing.filter(|old| *old != key) {
+            tx.execute("DELETE FROM session_env WHERE session_id = ?1 AND key = ?2", params![session_id, old])?;
+        }
+        tx.execute(
+            "INSERT INTO session_env (session_id, key, value) VALUES (?1, ?2, ?3)
+             ON CONFLICT(session_id, key) DO UPDATE SET value = ?3",
+            params![session_id, key, value],
         )?;
+        tx.commit()?;
         Ok(())
     }
 
-    pub fn clear_tmux_session(&self, session_id: i64) -> Result<()> {
+    pub fn delete_session_env(&self, session_id: i64, key: &str) -> Result<()> {
         self.conn.execute(
-            "UPDATE sessions SET tmux_window = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
-            params![session_id],
+            "DELETE FROM session_env WHERE session_id = ?1 AND key = ?2",
+            params![session_id, key],
         )?;
         Ok(())
     }
 
-    pub fn update_session_worktree(&self, session_id: i64, checkout_path: &str, branch_name: &str) -> Result<()> {
+    /// Record an attach, dropping the project's oldest rows past the cap
+    pub fn record_attach(&self, project_id: i64, session_id: i64) -> Result<()> {
         self.conn.execute(
-            "UPDATE sessions SET checkout_path = ?1, branch_name = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
-            params![checkout_path, branch_name, session_id],
+            "INSERT INTO attach_history (project_id, session_id) VALUES (?1, ?2)",
+            params![project_id, session_id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM attach_history WHERE project_id = ?1 AND id NOT IN
+             (SELECT id FROM attach_history WHERE project_id = ?1 ORDER BY id DESC LIMIT ?2)",
+            params![project_id, ATTACH_HISTORY_LIMIT],
         )?;
         Ok(())
     }
 
-    pub fn list_fields(&self, project_id: i64) -> Result<Vec<Field>> {
+    /// Distinct live sessions by most recent attach, newest first
+    pub fn recent_attaches(&self, project_id: i64, limit: usize) -> Result<Vec<i64>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, project_id, name, description, display_order, visible
-             FROM fields WHERE project_id = ?1 ORDER BY display_order, id",
+            "SELECT h.session_id FROM attach_history h JOIN sessions s ON s.id = h.session_id
+             WHERE h.project_id = ?1 AND s.deleted_at IS NULL
+             GROUP BY h.session_id ORDER BY MAX(h.id) DESC LIMIT ?2",
         )?;
-
-        let fields = stmt.query_map(params![project_id], |row| {
-            Ok(Field {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                name: row.get(2)?,
-                description: row.get(3)?,
-                display_order: row.get(4)?,
-                visible: row.get::<_, i64>(5)? != 0,
-            })
-        })?;
-
-        fields.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        let ids = stmt.query_map(params![project_id, limit as i64], |row| row.get(0))?;
+        ids.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    pub fn create_field(&self, project_id: i64, name: &str, description: &str) -> Result<Field> {
-        let max_order: i64 = self.conn.query_row(
-            "SELECT COALESCE(MAX(display_order), -1) FROM fields WHERE project_id = ?1",
-            params![project_id],
-            |row| row.get(0),
+    /// Renew this board's hold on a project and return the freshest other board holding
+    /// it, if any. Holds without a heartbeat for `LOCK_STALE_SECS`, and those of dead
+    /// processes on this host, are dropped on the way
+    pub fn hold_project_lock(&self, project_id: i64, hostname: &str, pid: u32, now: i64) -> Result<Option<ProjectLock>> {
+        self.conn.execute(
+            "DELETE FROM locks WHERE project_id = ?1 AND heartbeat < ?2",
+            params![project_id, now - LOCK_STALE_SECS],
         )?;
-
         self.conn.execute(
-            "INSERT INTO fields (project_id, name, description, display_order) VALUES (?1, ?2, ?3, ?4)",
-            params![project_id, name, description, max_order + 1],
+            "INSERT INTO locks (project_id, hostname, pid, heartbeat) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(project_id, hostname, pid) DO UPDATE SET heartbeat = ?4",
+            params![project_id, hostname, pid, now],
         )?;
-
-        let id = self.conn.last_insert_rowid();
-        Ok(Field {
-            id,
-            project_id,
-            name: name.to_string(),
-            description: description.to_string(),
-            display_order: max_order + 1,
-            visible: true,
-        })
+        let mut stmt = self.conn.prepare(
+            "SELECT hostname, pid, heartbeat FROM locks
+             WHERE project_id = ?1 AND NOT (hostname = ?2 AND pid = ?3) ORDER BY heartbeat DESC",
+        )?;
+        let others = stmt
+            .query_map(params![project_id, hostname, pid], |row| {
+                Ok(ProjectLock { hostname: row.get(0)?, pid: row.get(1)?, heartbeat: row.get(2)? })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        for other in others {
+            if other.hostname != hostname || process_alive(other.pid) {
+                return Ok(Some(other));
+            }
+            self.release_project_lock(project_id, &other.hostname, other.pid)?;
+        }
+        Ok(None)
     }
 
-    pub fn update_field(&self, field_id: i64, name: &str, description: &str) -> Result<()> {
+    pub fn release_project_lock(&self, project_id: i64, hostname: &str, pid: u32) -> Result<()> {
         self.conn.execute(
-            "UPDATE fields SET name = ?1, description = ?2 WHERE id = ?3",
-            params![name, description, field_id],
+            "DELETE FROM locks WHERE project_id = ?1 AND hostname = ?2 AND pid = ?3",
+            params![project_id, hostname, pid],
         )?;
         Ok(())
     }
 
-    pub fn toggle_field_visibility(&self, field_id: i64) -> Result<()> {
+    /// Log one claude call; missing usage values are stored as NULL
+    pub fn record_ai_usage(
+        &self,
+        project_id: i64,
+        session_id: Option<i64>,
+        purpose: &str,
+        model: Option<&str>,
+        tokens: Option<i64>,
+        cost_usd: Option<f64>,
+    ) -> Result<()> {
         self.conn.execute(
-            "UPDATE fields SET visible = NOT visible WHERE id = ?1",
-            params![field_id],
+            "INSERT INTO ai_usage (project_id, session_id, purpose, model, tokens, cost_usd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![project_id, session_id, purpose, model, tokens, cost_usd],
         )?;
         Ok(())
     }
 
-    pub fn delete_field(&self, field_id: i64) -> Result<()> {
-        self.conn.execute("DELETE FROM fields WHERE id = ?1", params![field_id])?;
+    /// Add `seconds` of active time to a session, in the hour bucket holding `at` (unix
+    /// seconds)
+    pub fn add_time_sample(&self, session_id: i64, at: i64, seconds: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO time_samples (session_id, hour, seconds) VALUES (?1, ?2, ?3)
+             ON CONFLICT(session_id, hour) DO UPDATE SET seconds = seconds + ?3",
+            params![session_id, at - at.rem_euclid(3600), seconds],
+        )?;
         Ok(())
     }
 
-    pub fn move_field_up(&self, project_id: i64, field_id: i64) -> Result<()> {
-        let fields = self.list_fields(project_id)?;
-        let idx = fields.iter().position(|f| f.id == field_id);
-        if let Some(i) = idx {
-            if i > 0 {
-                let prev_id = fields[i - 1].id;
-                let prev_order = fields[i - 1].display_order;
-                let curr_order = fields[i].display_order;
-                self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![prev_order, field_id])?;
-                self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![curr_order, prev_id])?;
-            }
-        }
-        Ok(())
+    /// All active time recorded for each of the project's sessions that has any, in seconds
+    pub fn time_spent_by_session(&self, project_id: i64) -> Result<HashMap<i64, i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.session_id, SUM(t.seconds) FROM time_samples t JOIN sessions s ON s.id = t.session_id
+             WHERE s.project_id = ?1 GROUP BY t.session_id",
+        )?;
+        let totals = stmt.query_map(params![project_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        totals.collect::<Result<HashMap<_, _>, _>>().map_err(Into::into)
     }
 
-    pub fn move_field_down(&self, project_id: i64, field_id: i64) -> Result<()> {
-        let fields = self.list_fields(project_id)?;
-        let idx = fields.iter().position(|f| f.id == field_id);
-        if let Some(i) = idx {
-            if i < fields.len() - 1 {
-                let next_id = fields[i + 1].id;
-                let next_order = fields[i + 1].display_order;
-                let curr_order = fields[i].display_order;
-                self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![next_order, field_id])?;
-                self.conn.execute("UPDATE fields SET display_order = ?1 WHERE id = ?2", params![curr_order, next_id])?;
-            }
-        }
-        Ok(())
+    /// Active time per session in the week `weeks_ago` weeks before this one (weeks start
+    /// on Monday, local time), trashed sessions included
+    pub fn time_report(&self, project_id: i64, weeks_ago: u32) -> Result<TimeReport> {
+        // Monday of the week: 'weekday 0' moves forward to Sunday
+        let (week_of, from, to): (String, i64, i64) = self.conn.query_row(
+            "SELECT monday, CAST(strftime('%s', monday, 'utc') AS INTEGER),
+                    CAST(strftime('%s', monday, '+7 days', 'utc') AS INTEGER)
+             FROM (SELECT date('now', 'localtime', 'weekday 0', '-6 days', ?1) AS monday)",
+            params![format!("-{} days", weeks_ago * 7)],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.name, SUM(t.seconds) AS total FROM time_samples t JOIN sessions s ON s.id = t.session_id
+             WHERE s.project_id = ?1 AND t.hour >= ?2 AND t.hour < ?3
+             GROUP BY s.id ORDER BY total DESC, s.name",
+        )?;
+        let totals = stmt.query_map(params![project_id, from, to], |row| {
+            Ok(TimeTotal { session_id: row.get(0)?, name: row.get(1)?, seconds: row.get(2)? })
+        })?;
+        Ok(TimeReport { week_of, totals: totals.collect::<Result<Vec<_>, _>>()? })
     }
 
-    pub fn get_session_field_value(&self, session_id: i64, field_id: i64) -> Result<String> {
-        let result: Result<String, _> = self.conn.query_row(
-            "SELECT value FROM session_field_values WHERE session_id = ?1 AND field_id = ?2",
-            params![session_id, field_id],
-            |row| row.get(0),
-        );
-        Ok(result.unwrap_or_default())
+    /// The project's AI usage per day (or per week), newest first
+    pub fn ai_usage_totals(&self, project_id: i64, weekly: bool, limit: usize) -> Result<Vec<AiUsageTotal>> {
+        let period = if weekly {
+            // Monday of the week: 'weekday 0' moves forward to Sunday
+            "date(created_at, 'localtime', 'weekday 0', '-6 days')"
+        } else {
+            "date(created_at, 'localtime')"
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} AS period, COUNT(*), COALESCE(SUM(tokens), 0), COALESCE(SUM(cost_usd), 0.0),
+                    SUM(cost_usd IS NULL)
+             FROM ai_usage WHERE project_id = ?1
+             GROUP BY period ORDER BY period DESC LIMIT ?2",
+            period
+        ))?;
+        let totals = stmt.query_map(params![project_id, limit as i64], |row| {
+            Ok(AiUsageTotal {
+                period: row.get(0)?,
+                calls: row.get(1)?,
+                tokens: row.get(2)?,
+                cost_usd: row.get(3)?,
+                unpriced: row.get(4)?,
+            })
+        })?;
+        totals.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    pub fn set_session_field_value(&self, session_id: i64, field_id: i64, value: &str) -> Result<()> {
-        self.conn.execute(
-            "INSERT INTO session_field_values (session_id, field_id, value) VALUES (?1, ?2, ?3)
-             ON CONFLICT(session_id, field_id) DO UPDATE SET value = ?3",
-            params![session_id, field_id, value],
+    /// A session's transcripts, newest first
+    pub fn list_transcripts(&self, session_id: i64) -> Result<Vec<Transcript>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, created_at FROM transcripts WHERE session_id = ?1 ORDER BY id DESC",
         )?;
-        Ok(())
+        let transcripts = stmt.query_map(params![session_id], |row| {
+            Ok(Transcript {
+                path: row.get(0)?,
+                created_at: row.get(1)?,
+            })
+        })?;
+        transcripts.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    pub fn get_all_session_field_values(&self, session_id: i64) -> Result<Vec<(i64, String)>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT field_id, value FROM session_field_values WHERE session_id = ?1",
+    pub fn add_transcript(&self, session_id: i64, path: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO transcripts (session_id, path) VALUES (?1, ?2)",
+            params![session_id, path],
         )?;
-        let values = stmt.query_map(params![session_id], |row| {
-            Ok((row.get(0)?, row.get(1)?))
-        })?;
-        values.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        Ok(())
     }
 
     pub fn list_comments(&self, session_id: i64) -> Result<Vec<Comment>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, session_id, text, created_at FROM comments WHERE session_id = ?1 ORDER BY created_at DESC",
+            "SELECT text, created_at FROM comments WHERE session_id = ?1 ORDER BY created_at DESC",
         )?;
         let comments = stmt.query_map(params![session_id], |row| {
             Ok(Comment {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                text: row.get(2)?,
-                created_at: row.get(3)?,
+                text: row.get(0)?,
+                created_at: row.get(1)?,
             })
         })?;
         comments.collect::<Result<Vec<_>, _>>().map_err(Into::into)
@@ -447,10 +2218,686 @@ impl Database {
             |row| row.get(0),
         )?;
         Ok(Comment {
-            id,
-            session_id,
             text: text.to_string(),
             created_at,
         })
     }
 }
+
+/// Current time as Unix seconds
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Copy the database at `backup` over the one at `live` through SQLite's backup API, so
+/// the live file is written under its own lock and journal: a crash midway leaves it as
+/// it was, and its `-wal`/`-journal` files stay consistent with it
+fn restore_file(live: &Path, backup: &Path) -> Result<()> {
+    let mut conn = Connection::open(live)?;
+    conn.restore(rusqlite::DatabaseName::Main, backup, None::<fn(rusqlite::backup::Progress)>)?;
+    Ok(())
+}
+
+/// Marks a running TUI instance with a pid file, removed on drop. Markers sit next to the
+/// database they're for, so boards sharing one file see each other however they named it
+/// (data dir, `--db-path` or `WORKBENCH_DB`)
+pub struct InstanceMarker {
+    path: PathBuf,
+}
+
+impl InstanceMarker {
+    /// `<database>.instances` beside the database, in its directory with symlinks and
+    /// `..` resolved (the file itself may not exist yet)
+    fn instances_dir(db_path: &Path) -> PathBuf {
+        let dir = db_path.parent().and_then(|dir| std::fs::canonicalize(dir).ok());
+        let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".instances");
+        match dir {
+            Some(dir) => dir.join(name),
+            None => db_path.with_file_name(name),
+        }
+    }
+
+    pub fn create(db_path: &Path) -> Result<Self> {
+        let dir = Self::instances_dir(db_path);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(std::process::id().to_string());
+        std::fs::write(&path, "")?;
+        Ok(Self { path })
+    }
+
+    /// Whether another live workbench process holds a marker for the database at
+    /// `db_path` (stale markers are removed)
+    pub fn others_running(db_path: &Path) -> Result<bool> {
+        let dir = Self::instances_dir(db_path);
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return Ok(false);
+        };
+        let own_pid = std::process::id();
+        for entry in entries.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else {
+                continue;
+            };
+            if pid == own_pid {
+                continue;
+            }
+            if process_alive(pid) {
+                return Ok(true);
+            }
+            let _ = std::fs::remove_file(entry.path());
+        }
+        Ok(false)
+    }
+}
+
+impl Drop for InstanceMarker {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// This machine's name, for telling boards on other machines apart
+pub fn hostname() -> String {
+    let from_file = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok();
+    let name = from_file.or_else(|| {
+        let output = std::process::Command::new("hostname").output().ok()?;
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    });
+    name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).unwrap_or_else(|| "unknown".to_string())
+}
+
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_db_path() {
+        let data_dir = || Ok(PathBuf::from("/data/workbench"));
+        let env = std::ffi::OsStr::new("/env/wb.db");
+        assert_eq!(
+            resolve_db_path(Some(Path::new("/flag/wb.db")), Some(env), data_dir).unwrap(),
+            (PathBuf::from("/flag/wb.db"), DbPathSource::Flag)
+        );
+        assert_eq!(resolve_db_path(None, Some(env), data_dir).unwrap(), (PathBuf::from("/env/wb.db"), DbPathSource::Env));
+        // An empty variable counts as unset; the data dir is only looked up when needed
+        assert_eq!(
+            resolve_db_path(None, Some(std::ffi::OsStr::new("")), data_dir).unwrap(),
+            (PathBuf::from("/data/workbench/workbench.db"), DbPathSource::Default)
+        );
+        assert!(resolve_db_path(Some(Path::new("/flag/wb.db")), None, || Err(eyre!("no data dir"))).is_ok());
+    }
+
+    #[test]
+    fn test_update_project_path_relinks_project() {
+        let db = Database::open_in_memory().unwrap();
+        let old = db.get_or_create_project("api", Path::new("/nonexistent/Code/api")).unwrap();
+        let session = db.create_session(old.id, "Fix auth").unwrap();
+        db.update_session_worktree(session.id, Path::new("/nonexistent/Code/api-worktrees/fix-auth"), "wb/fix-auth")
+            .unwrap();
+        let kept = db.create_session(old.id, "Keep checkout").unwrap();
+        let existing = std::env::temp_dir();
+        db.update_session_worktree(kept.id, &existing, "wb/keep").unwrap();
+
+        let new = db.get_or_create_project("api", Path::new("/nonexistent/Work/api")).unwrap();
+        assert!(db.project_is_empty(new.id).unwrap());
+        db.delete_empty_project(new.id).unwrap();
+        db.update_project_path(old.id, "api", Path::new("/nonexistent/Work/api")).unwrap();
+
+        let relinked = db.get_or_create_project("api", Path::new("/nonexistent/Work/api")).unwrap();
+        assert_eq!(relinked.id, old.id);
+        let sessions = db.list_sessions(old.id).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].checkout_path, None);
+        assert_eq!(sessions[0].branch_name.as_deref(), Some("wb/fix-auth"));
+        assert_eq!(sessions[1].checkout_path.as_deref(), Some(existing.as_path()));
+        assert!(db.delete_empty_project(old.id).is_err());
+    }
+
+    #[test]
+    fn test_session_due() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let session = db.create_session(project.id, "Release").unwrap();
+        assert_eq!(session.due, None);
+        db.set_session_due(session.id, Some(19_905)).unwrap();
+        assert_eq!(db.list_sessions(project.id).unwrap()[0].due, Some(19_905));
+        let stored: String = db.conn
+            .query_row("SELECT due_on FROM sessions WHERE id = ?1", params![session.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, "2024-07-01");
+        db.set_session_due(session.id, None).unwrap();
+        assert_eq!(db.get_live_session(project.id, session.id).unwrap().unwrap().due, None);
+        assert!(db.local_today().unwrap() > 19_905);
+    }
+
+    #[test]
+    fn test_session_agent() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let session = db.create_session(project.id, "Refactor").unwrap();
+        assert_eq!(session.agent, None);
+        db.update_session_agent(session.id, Some("aider")).unwrap();
+        assert_eq!(db.list_sessions(project.id).unwrap()[0].agent.as_deref(), Some("aider"));
+        db.update_session_agent(session.id, None).unwrap();
+        assert_eq!(db.get_live_session(project.id, session.id).unwrap().unwrap().agent, None);
+    }
+
+    #[test]
+    fn test_session_env() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let session = db.create_session(project.id, "Refactor").unwrap();
+        db.set_session_env(session.id, "AWS_PROFILE", "dev", None).unwrap();
+        db.set_session_env(session.id, "AWS_PROFILE", "staging", Some("AWS_PROFILE")).unwrap();
+        // Renamed, the old name goes
+        db.set_session_env(session.id, "AWS_DEFAULT_PROFILE", "staging", Some("AWS_PROFILE")).unwrap();
+        assert_eq!(
+            db.list_session_env(session.id).unwrap(),
+            vec![("AWS_DEFAULT_PROFILE".to_string(), "staging".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_live_session_lookup_survives_concurrent_removal() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let first = db.create_session(project.id, "First").unwrap();
+        let second = db.create_session(project.id, "Second").unwrap();
+
+        // The key handler captures the selected id (row 0)...
+        let selected_id = db.list_sessions(project.id).unwrap()[0].id;
+        // ...a background refresh then sees the session removed...
+        db.trash_session(first.id).unwrap();
+        // ...so row 0 now points at another card, but the id lookup doesn't follow it
+        assert_eq!(db.list_sessions(project.id).unwrap()[0].id, second.id);
+        assert!(db.get_live_session(project.id, selected_id).unwrap().is_none());
+        assert_eq!(db.get_live_session(project.id, second.id).unwrap().unwrap().name, "Second");
+
+        let other = db.get_or_create_project("web", Path::new("/nonexistent/web")).unwrap();
+        assert!(db.get_live_session(other.id, second.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_duplicate_field_names_renamed_before_unique_index() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let other = db.get_or_create_project("web", Path::new("/nonexistent/web")).unwrap();
+        // A database from before the index, with duplicates in it
+        db.conn.execute("DROP INDEX idx_fields_project_name", []).unwrap();
+        for (project_id, name) in [(project.id, "Epic"), (project.id, "epic"), (project.id, "Epic (2)"), (project.id, "Epic"), (other.id, "Epic")] {
+            db.conn
+                .execute("INSERT INTO fields (project_id, name, description) VALUES (?1, ?2, '')", params![project_id, name])
+                .unwrap();
+        }
+
+        db.run_migrations();
+        let names: Vec<String> = db.list_fields(project.id).unwrap().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["Epic", "epic (3)", "Epic (2)", "Epic (4)"]);
+        assert_eq!(db.list_fields(other.id).unwrap()[0].name, "Epic");
+        // The index is in place now
+        assert!(db.conn
+            .execute("INSERT INTO fields (project_id, name, description) VALUES (?1, 'EPIC', '')", params![project.id])
+            .is_err());
+    }
+
+    #[test]
+    fn test_trash_keeps_checkout_until_purge() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let session = db.create_session(project.id, "Fix login").unwrap();
+        let checkout = Path::new("/nonexistent/api-worktrees/fix-login");
+        db.update_session_worktree(session.id, checkout, "fix-login").unwrap();
+        db.set_tmux_session(session.id, "wb-1-1").unwrap();
+
+        db.trash_session(session.id).unwrap();
+        let trashed = db.list_trashed_sessions(project.id).unwrap();
+        assert_eq!(trashed[0].session.checkout_path.as_deref(), Some(checkout));
+        assert_eq!(trashed[0].session.tmux_window, None);
+
+        db.restore_session(session.id).unwrap();
+        let restored = db.get_live_session(project.id, session.id).unwrap().unwrap();
+        assert_eq!(restored.checkout_path.as_deref(), Some(checkout));
+        assert_eq!(restored.branch_name.as_deref(), Some("fix-login"));
+
+        // Expired trash hands its checkouts back so the worktrees can be removed
+        db.trash_session(session.id).unwrap();
+        db.conn.execute("UPDATE sessions SET deleted_at = datetime('now', '-31 days')", []).unwrap();
+        assert_eq!(
+            db.purge_trash_older_than(30).unwrap(),
+            vec![(PathBuf::from("/nonexistent/api"), checkout.to_path_buf())]
+        );
+        assert!(db.list_trashed_sessions(project.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pinned_sessions_persist() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let a = db.create_session(project.id, "A").unwrap();
+        let b = db.create_session(project.id, "B").unwrap();
+        assert!(!a.pinned);
+
+        db.set_session_pinned(b.id, true).unwrap();
+        let pinned: Vec<bool> = db.list_sessions(project.id).unwrap().iter().map(|s| s.pinned).collect();
+        assert_eq!(pinned, vec![false, true]);
+        db.set_session_pinned(b.id, false).unwrap();
+        assert!(!db.get_live_session(project.id, b.id).unwrap().unwrap().pinned);
+    }
+
+    #[test]
+    fn test_checklists() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let items = vec!["tests pass".to_string(), "PR linked".to_string()];
+        db.set_status_checklist(project.id, Status::Done, &items).unwrap();
+        let overrides = db.list_status_overrides(project.id).unwrap();
+        assert_eq!(overrides[0].checklist, items);
+        assert_eq!(resolve_status_label(&overrides, Status::Done), "Done");
+
+        // Ticks are kept per session and per column entered
+        let session = db.create_session(project.id, "A").unwrap();
+        db.set_item_checked(session.id, Status::Done, "PR linked", true).unwrap();
+        db.set_item_checked(session.id, Status::Done, "PR linked", true).unwrap();
+        assert_eq!(db.checked_items(session.id, Status::Done).unwrap(), vec!["PR linked"]);
+        assert!(db.checked_items(session.id, Status::Review).unwrap().is_empty());
+        db.set_item_checked(session.id, Status::Done, "PR linked", false).unwrap();
+        assert!(db.checked_items(session.id, Status::Done).unwrap().is_empty());
+
+        db.set_status_checklist(project.id, Status::Done, &[]).unwrap();
+        assert!(db.list_status_overrides(project.id).unwrap()[0].checklist.is_empty());
+
+        // The sort mode sits alongside, defaulting to creation order
+        assert_eq!(db.list_status_overrides(project.id).unwrap()[0].sort, ColumnSort::Created);
+        db.set_status_sort(project.id, Status::Done, ColumnSort::Activity).unwrap();
+        assert_eq!(db.list_status_overrides(project.id).unwrap()[0].sort, ColumnSort::Activity);
+
+        // So do the fields a new card is asked for, in order
+        db.set_status_new_card_fields(project.id, Status::Done, &[7, 3]).unwrap();
+        assert_eq!(db.list_status_overrides(project.id).unwrap()[0].new_card_fields, vec![7, 3]);
+        assert_eq!(db.list_status_overrides(project.id).unwrap()[0].checklist, Vec::<String>::new());
+        db.set_status_new_card_fields(project.id, Status::Done, &[]).unwrap();
+        assert!(db.list_status_overrides(project.id).unwrap()[0].new_card_fields.is_empty());
+    }
+
+    #[test]
+    fn test_board_sessions_leave_out_old_done() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        for i in 0..1000 {
+            let session = db.create_session(project.id, &format!("S{}", i)).unwrap();
+            if i % 10 != 0 {
+                db.update_session_status(session.id, Status::Done).unwrap();
+            }
+        }
+        // 800 were done two months ago, 100 this week
+        db.conn.execute(
+            "UPDATE sessions SET status_changed_at = datetime('now', '-60 days') WHERE status = 'done' AND id % 10 < 9",
+            [],
+        ).unwrap();
+        db.conn.execute(
+            "UPDATE sessions SET status_changed_at = datetime('now', '-3 days') WHERE status = 'done' AND id % 10 = 9",
+            [],
+        ).unwrap();
+
+        let board = db.list_board_sessions(project.id, 30).unwrap();
+        assert_eq!(board.len(), 200);
+        assert_eq!(board.iter().filter(|s| s.status == Status::Done).count(), 100);
+        assert_eq!(db.list_board_sessions(project.id, 0).unwrap().len(), 1000);
+        assert_eq!(db.count_archived_sessions(project.id, 30).unwrap(), 800);
+
+        // Paging walks every archived card once
+        let mut seen = std::collections::HashSet::new();
+        for page in 0..9 {
+            let rows = db.list_archived_sessions(project.id, 30, 100, page * 100).unwrap();
+            assert_eq!(rows.len(), if page < 8 { 100 } else { 0 });
+            seen.extend(rows.iter().map(|s| s.id));
+        }
+        assert_eq!(seen.len(), 800);
+    }
+
+    #[test]
+    fn test_blockers() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let a = db.create_session(project.id, "A").unwrap();
+        let b = db.create_session(project.id, "B").unwrap();
+        let c = db.create_session(project.id, "C").unwrap();
+        db.add_blocker(a.id, b.id).unwrap();
+        db.add_blocker(a.id, b.id).unwrap();
+        db.add_blocker(a.id, c.id).unwrap();
+        db.add_blocker(b.id, c.id).unwrap();
+        assert_eq!(db.list_blockers(project.id).unwrap(), vec![(a.id, b.id), (a.id, c.id), (b.id, c.id)]);
+
+        db.clear_blockers(b.id).unwrap();
+        db.purge_session(c.id).unwrap();
+        assert_eq!(db.list_blockers(project.id).unwrap(), vec![(a.id, b.id)]);
+    }
+
+    #[test]
+    fn test_record_active_project() {
+        let db = Database::open_in_memory().unwrap();
+        assert_eq!(db.record_active_project(1).unwrap(), None);
+        assert_eq!(db.record_active_project(1).unwrap(), None);
+        assert_eq!(db.record_active_project(2).unwrap(), Some(1));
+        // Reopening the same project still remembers the other one
+        assert_eq!(db.record_active_project(2).unwrap(), Some(1));
+        assert_eq!(db.record_active_project(1).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn test_project_order() {
+        let db = Database::open_in_memory().unwrap();
+        let names = |db: &Database| db.list_projects().unwrap().into_iter().map(|p| p.name).collect::<Vec<_>>();
+        let opened = |db: &Database, id: i64, at: &str| {
+            db.conn.execute("UPDATE projects SET last_opened_at = ?1 WHERE id = ?2", params![at, id]).unwrap();
+        };
+        let api = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let web = db.get_or_create_project("web", Path::new("/nonexistent/web")).unwrap();
+        let spike = db.get_or_create_project("spike", Path::new("/nonexistent/spike")).unwrap();
+        opened(&db, api.id, "2024-07-01 09:00:00");
+        opened(&db, web.id, "2024-07-03 09:00:00");
+        db.conn.execute("UPDATE projects SET last_opened_at = NULL WHERE id = ?1", params![spike.id]).unwrap();
+        assert_eq!(names(&db), ["web", "api", "spike"]);
+
+        // Pinned first; archived ones are still listed, flagged
+        db.set_project_pinned(spike.id, true).unwrap();
+        db.set_project_archived(web.id, true).unwrap();
+        assert_eq!(names(&db), ["spike", "web", "api"]);
+        assert!(db.get_project(web.id).unwrap().unwrap().archived);
+
+        // Every launch counts as an open
+        let reopened = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        assert!(reopened.last_opened_at.unwrap() >= unix_now() - 5);
+        assert_eq!(names(&db), ["spike", "api", "web"]);
+        assert_eq!(db.get_project(api.id).unwrap().unwrap().last_opened_at, reopened.last_opened_at);
+    }
+
+    #[test]
+    fn test_attach_history() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let a = db.create_session(project.id, "A").unwrap();
+        let b = db.create_session(project.id, "B").unwrap();
+        let c = db.create_session(project.id, "C").unwrap();
+
+        for id in [a.id, b.id, a.id, c.id] {
+            db.record_attach(project.id, id).unwrap();
+        }
+        assert_eq!(db.recent_attaches(project.id, 3).unwrap(), vec![c.id, a.id, b.id]);
+        assert_eq!(db.recent_attaches(project.id, 1).unwrap(), vec![c.id]);
+
+        for _ in 0..ATTACH_HISTORY_LIMIT {
+            db.record_attach(project.id, b.id).unwrap();
+        }
+        let rows: i64 = db.conn.query_row("SELECT COUNT(*) FROM attach_history", [], |r| r.get(0)).unwrap();
+        assert_eq!(rows, ATTACH_HISTORY_LIMIT);
+        assert_eq!(db.recent_attaches(project.id, 3).unwrap(), vec![b.id]);
+    }
+
+    #[test]
+    fn test_backup_to() {
+        let db = Database::open_in_memory().unwrap();
+        db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let dir = std::env::temp_dir().join(format!("workbench-backup-{}", std::process::id()));
+        let path = dir.join("copy.db");
+        db.backup_to(&path).unwrap();
+        let copy = Connection::open(&path).unwrap();
+        let projects: i64 = copy.query_row("SELECT COUNT(*) FROM projects", [], |r| r.get(0)).unwrap();
+        assert_eq!(projects, 1);
+        assert!(db.backup_to(&path).is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = dir.join(std::ffi::OsStr::from_bytes(b"copy-\xff.db"));
+            assert!(db.backup_to(&path).unwrap_err().to_string().contains("not valid UTF-8"));
+            assert!(!path.exists());
+        }
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_restore_file() {
+        let dir = std::env::temp_dir().join(format!("workbench-restore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (live, backup) = (dir.join("live.db"), dir.join("backup.db"));
+        let db = Database::open_in_memory().unwrap();
+        db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        db.backup_to(&backup).unwrap();
+        db.get_or_create_project("web", Path::new("/nonexistent/web")).unwrap();
+        db.backup_to(&live).unwrap();
+
+        // Another board on the same file, named another way, holds it
+        #[cfg(unix)]
+        {
+            let mut other = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+            let marker = InstanceMarker::create(&dir.join(".").join("live.db")).unwrap();
+            std::fs::rename(&marker.path, marker.path.with_file_name(other.id().to_string())).unwrap();
+            assert!(InstanceMarker::others_running(&live).unwrap());
+            assert!(!InstanceMarker::others_running(&backup).unwrap());
+            other.kill().unwrap();
+            other.wait().unwrap();
+            assert!(!InstanceMarker::others_running(&live).unwrap());
+        }
+
+        restore_file(&live, &backup).unwrap();
+        let conn = Connection::open(&live).unwrap();
+        let projects: i64 = conn.query_row("SELECT COUNT(*) FROM projects", [], |r| r.get(0)).unwrap();
+        assert_eq!(projects, 1);
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_field_value_sources() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let field = db.create_field(project.id, "Summary", "").unwrap();
+        let session = db.create_session_with(project.id, "Auth", Status::Planned, Some((field.id, "preset")), None).unwrap();
+        assert_eq!(db.get_session_field_source(session.id, field.id).unwrap(), ValueSource::Default);
+
+        db.set_session_field_value(session.id, field.id, "Fix the redirect", ValueSource::Ai).unwrap();
+        assert_eq!(db.get_all_session_field_values(session.id).unwrap(), vec![(
+            field.id,
+            "Fix the redirect".to_string(),
+            ValueSource::Ai
+        )]);
+
+        // Rows written without a source, as before sources were kept, read as typed in
+        let other = db.create_field(project.id, "Notes", "").unwrap();
+        assert_eq!(db.get_session_field_source(session.id, other.id).unwrap(), ValueSource::Manual);
+        db.conn
+            .execute(
+                "INSERT INTO session_field_values (session_id, field_id, value) VALUES (?1, ?2, 'old')",
+                params![session.id, other.id],
+            )
+            .unwrap();
+        assert_eq!(db.get_session_field_source(session.id, other.id).unwrap(), ValueSource::Manual);
+
+        let values = db.field_values_by_session(project.id).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[&(session.id, field.id)], ("Fix the redirect".to_string(), ValueSource::Ai));
+        db.trash_session(session.id).unwrap();
+        assert!(db.field_values_by_session(project.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_distinct_field_values() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let component = db.create_field(project.id, "Component", "").unwrap();
+        let other = db.create_field(project.id, "Epic", "").unwrap();
+        for (name, value) in [("A", "Backend"), ("B", "billing"), ("C", "Backend"), ("D", "Frontend"), ("E", "")] {
+            let session = db.create_session(project.id, name).unwrap();
+            db.set_session_field_value(session.id, component.id, value, ValueSource::Manual).unwrap();
+            db.set_session_field_value(session.id, other.id, "Bugs", ValueSource::Manual).unwrap();
+        }
+
+        // Most used first, case ignored, other fields and empty values left out
+        assert_eq!(db.distinct_field_values(component.id, "b").unwrap(), vec!["Backend", "billing"]);
+        assert_eq!(db.distinct_field_values(component.id, "").unwrap(), vec!["Backend", "Frontend", "billing"]);
+        assert!(db.distinct_field_values(component.id, "%").unwrap().is_empty());
+
+        let plan: String = db
+            .conn
+            .query_row(
+                "EXPLAIN QUERY PLAN SELECT value FROM session_field_values WHERE field_id = 1 AND value LIKE 'b%' GROUP BY value",
+                [],
+                |row| row.get(3),
+            )
+            .unwrap();
+        assert!(plan.contains("idx_session_field_values_field"), "{}", plan);
+    }
+
+    #[test]
+    fn test_project_lock() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let now = 1_000_000;
+        assert_eq!(db.hold_project_lock(project.id, "laptop", 10, now).unwrap(), None);
+
+        // A board on another machine sees the first one, and the other way round
+        let laptop = ProjectLock { hostname: "laptop".to_string(), pid: 10, heartbeat: now };
+        assert_eq!(db.hold_project_lock(project.id, "desktop", 20, now + 5).unwrap(), Some(laptop));
+        let desktop = db.hold_project_lock(project.id, "laptop", 10, now + 6).unwrap().unwrap();
+        assert_eq!((desktop.hostname.as_str(), desktop.heartbeat), ("desktop", now + 5));
+
+        // A hold without a heartbeat for a minute is taken over
+        assert_eq!(db.hold_project_lock(project.id, "laptop", 10, now + 5 + LOCK_STALE_SECS + 1).unwrap(), None);
+
+        // Released on quit
+        db.hold_project_lock(project.id, "desktop", 20, now + 100).unwrap();
+        db.release_project_lock(project.id, "desktop", 20).unwrap();
+        assert_eq!(db.hold_project_lock(project.id, "laptop", 10, now + 101).unwrap(), None);
+    }
+
+    #[test]
+    fn test_import_fields() {
+        let db = Database::open_in_memory().unwrap();
+        let source = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let target = db.get_or_create_project("web", Path::new("/nonexistent/web")).unwrap();
+        db.create_field(source.id, "Ticket", "Jira key").unwrap();
+        let hidden = db.create_field(source.id, "Notes", "Free text").unwrap();
+        db.toggle_field_visibility(hidden.id).unwrap();
+        db.toggle_field_in_ai(hidden.id).unwrap();
+        db.toggle_field_wrap(hidden.id).unwrap();
+        db.create_field(source.id, "PR URL", "").unwrap();
+        db.create_field(target.id, "pr url", "already here").unwrap();
+
+        let json = fields_to_json(&db.list_fields(source.id).unwrap()).unwrap();
+        let definitions = fields_from_json(&json).unwrap();
+        let import = db.import_fields(target.id, &definitions).unwrap();
+        assert_eq!(import.created, vec!["Ticket", "Notes"]);
+        assert_eq!(import.skipped, vec!["PR URL"]);
+        let fields = db.list_fields(target.id).unwrap();
+        let summary: Vec<(&str, bool, bool, bool, bool)> =
+            fields.iter().map(|f| (f.name.as_str(), f.visible, f.in_ai, f.in_edit, f.wrap)).collect();
+        assert_eq!(summary, vec![
+            ("pr url", true, true, true, false),
+            ("Ticket", true, true, true, false),
+            ("Notes", false, false, true, true),
+        ]);
+        // Files written before the AI, edit and wrap toggles include the field everywhere, cut off
+        let old: Vec<FieldDefinition> = serde_json::from_str(r#"[{"name": "Old", "visible": false}]"#).unwrap();
+        assert!(!old[0].visible && old[0].in_ai && old[0].in_edit && !old[0].wrap);
+
+        // A bad entry part-way through leaves nothing behind
+        let mut broken = definitions.clone();
+        let definition = |name: &str| FieldDefinition {
+            name: name.to_string(),
+            description: String::new(),
+            visible: true,
+            in_ai: true,
+            in_edit: true,
+            wrap: false,
+        };
+        broken.insert(0, definition("Owner"));
+        broken.push(definition(" "));
+        assert!(db.import_fields(target.id, &broken).is_err());
+        assert_eq!(db.list_fields(target.id).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_watch_rules_round_trip() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        db.create_watch_rule(project.id, "All tasks complete", WatchAction::Move(Status::Review)).unwrap();
+        db.create_watch_rule(project.id, r"PR: (\S+)", WatchAction::SetField(7)).unwrap();
+        db.create_watch_rule(project.id, "error", WatchAction::Notify).unwrap();
+        let actions: Vec<WatchAction> = db.list_watch_rules(project.id).unwrap().iter().map(|r| r.action).collect();
+        assert_eq!(actions, vec![WatchAction::Move(Status::Review), WatchAction::SetField(7), WatchAction::Notify]);
+
+        db.log_event(project.id, Some(1), "watch", "moved A to Review").unwrap();
+        db.log_event(project.id, None, "other", "ignored").unwrap();
+        db.log_event(project.id, Some(2), "watch_dry_run", "would move B").unwrap();
+        let events = db.recent_events(project.id, "watch", 10).unwrap();
+        assert_eq!(events.iter().map(|e| e.detail.as_str()).collect::<Vec<_>>(), vec!["would move B", "moved A to Review"]);
+    }
+
+    #[test]
+    fn test_ai_usage_totals() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        db.record_ai_usage(project.id, Some(1), "fill", Some("haiku"), Some(1000), Some(0.002)).unwrap();
+        db.record_ai_usage(project.id, None, "batch_fill", None, None, None).unwrap();
+        db.conn.execute("UPDATE ai_usage SET created_at = '2026-01-07 12:00:00' WHERE id = 1", []).unwrap();
+        db.conn.execute("UPDATE ai_usage SET created_at = '2026-01-05 12:00:00' WHERE id = 2", []).unwrap();
+
+        let daily = db.ai_usage_totals(project.id, false, 10).unwrap();
+        assert_eq!(daily.len(), 2);
+        assert_eq!((daily[0].calls, daily[0].tokens, daily[0].unpriced), (1, 1000, 0));
+        assert_eq!((daily[1].calls, daily[1].tokens, daily[1].unpriced), (1, 0, 1));
+
+        // Monday the 5th and Wednesday the 7th share a week
+        let weekly = db.ai_usage_totals(project.id, true, 10).unwrap();
+        assert_eq!(weekly.len(), 1);
+        assert_eq!((weekly[0].calls, weekly[0].tokens, weekly[0].cost_usd), (2, 1000, 0.002));
+    }
+
+    #[test]
+    fn test_time_report() {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let auth = db.create_session(project.id, "Fix auth").unwrap();
+        let docs = db.create_session(project.id, "Write docs").unwrap();
+        let now = unix_now();
+
+        // Samples in one hour share a row
+        db.add_time_sample(auth.id, now, 120).unwrap();
+        db.add_time_sample(auth.id, now, 60).unwrap();
+        db.add_time_sample(docs.id, now, 600).unwrap();
+        db.add_time_sample(auth.id, now - 14 * 24 * 60 * 60, 3600).unwrap();
+        let rows: i64 = db.conn.query_row("SELECT COUNT(*) FROM time_samples", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 3);
+        assert_eq!(db.time_spent_by_session(project.id).unwrap(), HashMap::from([(auth.id, 3780), (docs.id, 600)]));
+
+        let report = db.time_report(project.id, 0).unwrap();
+        assert_eq!(report.totals, vec![
+            TimeTotal { session_id: docs.id, name: "Write docs".to_string(), seconds: 600 },
+            TimeTotal { session_id: auth.id, name: "Fix auth".to_string(), seconds: 180 },
+        ]);
+        let two_weeks_ago = db.time_report(project.id, 2).unwrap();
+        assert_eq!(two_weeks_ago.totals.iter().map(|t| t.seconds).collect::<Vec<_>>(), vec![3600]);
+        assert!(two_weeks_ago.week_of < report.week_of);
+
+        // Purging a session takes its samples with it
+        db.purge_session(auth.id).unwrap();
+        let rows: i64 = db.conn.query_row("SELECT COUNT(*) FROM time_samples", [], |row| row.get(0)).unwrap();
+        assert_eq!(rows, 1);
+    }
+}
@@ -0,0 +1,411 @@
+use color_eyre::{eyre::eyre, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use crate::db::{
+    ColumnSort, Comment, Database, Event, Field, FieldDefinition, FieldImport, Project, ProjectLock, PromptTemplate,
+    Session, SessionNotes, Shortcut, Status, StatusOverride, Transcript, TrashedSession, ValueSource, WatchAction, WatchRule,
+};
+
+type Job = Box<dyn FnOnce(&Database) + Send>;
+
+/// The database, owned by a thread of its own. Every call is a message to that thread,
+/// so calls from anywhere run one at a time in the order they were sent. Cloning gives
+/// another handle to the same connection; the thread exits when the last one is dropped
+#[derive(Clone)]
+pub struct DbHandle {
+    jobs: Sender<Job>,
+}
+
+impl DbHandle {
+    /// Move `db` onto a new thread and return the handle to it
+    pub fn spawn(db: Database) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("workbench-db".to_string())
+            .spawn(move || {
+                for job in queue {
+                    job(&db);
+                }
+            })
+            .expect("failed to start the database thread");
+        Self { jobs }
+    }
+
+    /// Queue `f` to run against the database; its result arrives on the returned channel,
+    /// so a background job can go on with other work meanwhile. If the thread has stopped,
+    /// the channel closes without a result
+    pub fn send<R: Send + 'static>(&self, f: impl FnOnce(&Database) -> R + Send + 'static) -> Receiver<R> {
+        let (reply, result) = mpsc::sync_channel(1);
+        // A failed send drops the job and with it `reply`
+        let _ = self.jobs.send(Box::new(move |db| {
+            let _ = reply.send(f(db));
+        }));
+        result
+    }
+
+    /// Run `f` against the database and wait for its result. Fails when the thread has
+    /// stopped, say after a panic in an earlier call
+    pub fn call<R: Send + 'static>(&self, f: impl FnOnce(&Database) -> R + Send + 'static) -> Result<R> {
+        self.send(f).recv().map_err(|_| eyre!("The database thread has stopped"))
+    }
+}
+
+/// An argument as passed to a `Database` method, turned into something that can be
+/// sent to the database thread
+trait Own {
+    type Owned: Lend + Send + 'static;
+    fn own(self) -> Self::Owned;
+}
+
+/// The owned form of an argument, borrowed back for the `Database` method
+trait Lend {
+    type Ref<'a>
+    where
+        Self: 'a;
+    fn lend(&self) -> Self::Ref<'_>;
+}
+
+macro_rules! by_value {
+    ($($ty:ty),*) => {$(
+        impl Own for $ty {
+            type Owned = $ty;
+            fn own(self) -> $ty {
+                self
+            }
+        }
+
+        impl Lend for $ty {
+            type Ref<'a> = $ty;
+            fn lend(&self) -> $ty {
+                *self
+            }
+        }
+    )*};
+}
+
+by_value!(i64, u32, u64, usize, bool, Status, ColumnSort, ValueSource, WatchAction, Option<i64>, Option<f64>);
+
+impl Own for &str {
+    type Owned = String;
+    fn own(self) -> String {
+        self.to_string()
+    }
+}
+
+impl Lend for String {
+    type Ref<'a> = &'a str;
+    fn lend(&self) -> &str {
+        self
+    }
+}
+
+impl Own for Option<&str> {
+    type Owned = Option<String>;
+    fn own(self) -> Option<String> {
+        self.map(str::to_string)
+    }
+}
+
+impl Lend for Option<String> {
+    type Ref<'a> = Option<&'a str>;
+    fn lend(&self) -> Option<&str> {
+        self.as_deref()
+    }
+}
+
+impl Own for Option<(i64, &str)> {
+    type Owned = Option<(i64, String)>;
+    fn own(self) -> Option<(i64, String)> {
+        self.map(|(id, value)| (id, value.to_string()))
+    }
+}
+
+impl Lend for Option<(i64, String)> {
+    type Ref<'a> = Option<(i64, &'a str)>;
+    fn lend(&self) -> Option<(i64, &str)> {
+        self.as_ref().map(|(id, value)| (*id, value.as_str()))
+    }
+}
+
+impl Own for &Path {
+    type Owned = PathBuf;
+    fn own(self) -> PathBuf {
+        self.to_path_buf()
+    }
+}
+
+impl Lend for PathBuf {
+    type Ref<'a> = &'a Path;
+    fn lend(&self) -> &Path {
+        self
+    }
+}
+
+impl<T: Clone + Send + 'static> Own for &[T] {
+    type Owned = Vec<T>;
+    fn own(self) -> Vec<T> {
+        self.to_vec()
+    }
+}
+
+impl<T: 'static> Lend for Vec<T> {
+    type Ref<'a> = &'a [T];
+    fn lend(&self) -> &[T] {
+        self
+    }
+}
+
+/// Give `DbHandle` each listed `Database` method with the same signature, sending the
+/// call to the database thread
+macro_rules! forward {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        impl DbHandle {
+            $(
+                pub fn $name(&self $(, $arg: $ty)*) -> $ret {
+                    $(let $arg = Own::own($arg);)*
+                    self.call(move |db| db.$name($(Lend::lend(&$arg)),*))?
+                }
+            )*
+        }
+    };
+}
+
+forward! {
+    fn backup_timestamped(&self) -> Result<PathBuf>;
+    fn list_projects(&self) -> Result<Vec<Project>>;
+    fn set_project_pinned(&self, project_id: i64, pinned: bool) -> Result<()>;
+    fn set_project_archived(&self, project_id: i64, archived: bool) -> Result<()>;
+    fn update_project_path(&self, project_id: i64, name: &str, path: &Path) -> Result<()>;
+    fn delete_empty_project(&self, project_id: i64) -> Result<()>;
+    fn data_version(&self) -> Result<i64>;
+    fn get_project(&self, project_id: i64) -> Result<Option<Project>>;
+    fn get_project_setting(&self, project_id: i64, key: &str) -> Result<Option<String>>;
+    fn set_project_setting(&self, project_id: i64, key: &str, value: &str) -> Result<()>;
+    fn list_status_overrides(&self, project_id: i64) -> Result<Vec<StatusOverride>>;
+    fn set_status_label(&self, project_id: i64, status: Status, label: &str) -> Result<()>;
+    fn set_status_color(&self, project_id: i64, status: Status, color: &str) -> Result<()>;
+    fn set_status_checklist(&self, project_id: i64, status: Status, items: &[String]) -> Result<()>;
+    fn set_status_sort(&self, project_id: i64, status: Status, sort: ColumnSort) -> Result<()>;
+    fn set_status_new_card_fields(&self, project_id: i64, status: Status, field_ids: &[i64]) -> Result<()>;
+    fn checked_items(&self, session_id: i64, status: Status) -> Result<Vec<String>>;
+    fn set_item_checked(&self, session_id: i64, status: Status, item: &str, checked: bool) -> Result<()>;
+    fn add_blocker(&self, session_id: i64, blocker_id: i64) -> Result<()>;
+    fn clear_blockers(&self, session_id: i64) -> Result<()>;
+    fn list_blockers(&self, project_id: i64) -> Result<Vec<(i64, i64)>>;
+    fn list_sessions(&self, project_id: i64) -> Result<Vec<Session>>;
+    fn list_board_sessions(&self, project_id: i64, archive_days: u64) -> Result<Vec<Session>>;
+    fn list_archived_sessions(&self, project_id: i64, archive_days: u64, limit: usize, offset: usize) -> Result<Vec<Session>>;
+    fn count_archived_sessions(&self, project_id: i64, archive_days: u64) -> Result<usize>;
+    fn get_live_session(&self, project_id: i64, session_id: i64) -> Result<Option<Session>>;
+    fn list_trashed_sessions(&self, project_id: i64) -> Result<Vec<TrashedSession>>;
+    fn create_session_with(&self, project_id: i64, name: &str, status: Status, field_value: Option<(i64, &str)>, owner: Option<&str>) -> Result<Session>;
+    fn create_sessions(&self, project_id: i64, sessions: &[(String, Status)], owner: Option<&str>) -> Result<Vec<Session>>;
+    fn update_session_owner(&self, session_id: i64, owner: Option<&str>) -> Result<()>;
+    fn update_session_agent(&self, session_id: i64, agent: Option<&str>) -> Result<()>;
+    fn set_session_due(&self, session_id: i64, due: Option<i64>) -> Result<()>;
+    fn get_session_notes(&self, session_id: i64) -> Result<SessionNotes>;
+    fn set_session_notes(&self, session_id: i64, text: &str, file_mtime: Option<i64>) -> Result<()>;
+    fn list_notes_mtimes(&self, project_id: i64) -> Result<HashMap<i64, i64>>;
+    fn local_today(&self) -> Result<i64>;
+    fn update_session_status(&self, session_id: i64, status: Status) -> Result<()>;
+    fn update_session_name(&self, session_id: i64, name: &str) -> Result<()>;
+    fn trash_session(&self, session_id: i64) -> Result<()>;
+    fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()>;
+    fn set_session_snooze(&self, session_id: i64, until: Option<i64>) -> Result<()>;
+    fn wake_snoozed_sessions(&self, project_id: i64) -> Result<Vec<String>>;
+    fn restore_session(&self, session_id: i64) -> Result<()>;
+    fn purge_session(&self, session_id: i64) -> Result<()>;
+    fn set_tmux_session(&self, session_id: i64, tmux_name: &str) -> Result<()>;
+    fn clear_tmux_session(&self, session_id: i64) -> Result<()>;
+    fn update_session_worktree(&self, session_id: i64, checkout_path: &Path, branch_name: &str) -> Result<()>;
+    fn update_session_branch(&self, session_id: i64, branch_name: &str) -> Result<()>;
+    fn clear_session_worktree(&self, session_id: i64) -> Result<()>;
+    fn list_fields(&self, project_id: i64) -> Result<Vec<Field>>;
+    fn create_field(&self, project_id: i64, name: &str, description: &str) -> Result<Field>;
+    fn update_field(&self, field_id: i64, name: &str, description: &str) -> Result<()>;
+    fn import_fields(&self, project_id: i64, definitions: &[FieldDefinition]) -> Result<FieldImport>;
+    fn toggle_field_visibility(&self, field_id: i64) -> Result<()>;
+    fn toggle_field_in_ai(&self, field_id: i64) -> Result<()>;
+    fn toggle_field_in_edit(&self, field_id: i64) -> Result<()>;
+    fn toggle_field_wrap(&self, field_id: i64) -> Result<()>;
+    fn delete_field(&self, field_id: i64) -> Result<()>;
+    fn move_field_up(&self, project_id: i64, field_id: i64) -> Result<()>;
+    fn move_field_down(&self, project_id: i64, field_id: i64) -> Result<()>;
+    fn get_session_field_value(&self, session_id: i64, field_id: i64) -> Result<String>;
+    fn get_all_session_field_values(&self, session_id: i64) -> Result<Vec<(i64, String, ValueSource)>>;
+    fn hold_project_lock(&self, project_id: i64, hostname: &str, pid: u32, now: i64) -> Result<Option<ProjectLock>>;
+    fn release_project_lock(&self, project_id: i64, hostname: &str, pid: u32) -> Result<()>;
+    fn get_session_field_source(&self, session_id: i64, field_id: i64) -> Result<ValueSource>;
+    fn set_session_field_value(&self, session_id: i64, field_id: i64, value: &str, source: ValueSource) -> Result<()>;
+    fn set_field_value_for_sessions(&self, session_ids: &[i64], field_id: i64, value: &str, source: ValueSource, only_empty: bool) -> Result<usize>;
+    fn distinct_field_values(&self, field_id: i64, prefix: &str) -> Result<Vec<String>>;
+    fn field_values_by_session(&self, project_id: i64) -> Result<HashMap<(i64, i64), (String, ValueSource)>>;
+    fn list_shortcuts(&self, project_id: i64) -> Result<Vec<Shortcut>>;
+    fn create_shortcut(&self, project_id: i64, name: &str, status: Status, field_id: Option<i64>) -> Result<()>;
+    fn delete_shortcut(&self, shortcut_id: i64) -> Result<()>;
+    fn list_watch_rules(&self, project_id: i64) -> Result<Vec<WatchRule>>;
+    fn create_watch_rule(&self, project_id: i64, pattern: &str, action: WatchAction) -> Result<()>;
+    fn delete_watch_rule(&self, rule_id: i64) -> Result<()>;
+    fn log_event(&self, project_id: i64, session_id: Option<i64>, kind: &str, detail: &str) -> Result<()>;
+    fn recent_events(&self, project_id: i64, kind_prefix: &str, limit: usize) -> Result<Vec<Event>>;
+    fn list_prompt_templates(&self, project_id: i64) -> Result<Vec<PromptTemplate>>;
+    fn create_prompt_template(&self, project_id: i64, name: &str, body: &str) -> Result<()>;
+    fn update_prompt_template(&self, template_id: i64, name: &str, body: &str) -> Result<()>;
+    fn delete_prompt_template(&self, template_id: i64) -> Result<()>;
+    fn list_session_env(&self, session_id: i64) -> Result<Vec<(String, String)>>;
+    fn set_session_env(&self, session_id: i64, key: &str, value: &str, replacing: Option<&str>) -> Result<()>;
+    fn delete_session_env(&self, session_id: i64, key: &str) -> Result<()>;
+    fn record_attach(&self, project_id: i64, session_id: i64) -> Result<()>;
+    fn recent_attaches(&self, project_id: i64, limit: usize) -> Result<Vec<i64>>;
+    fn record_ai_usage(&self, project_id: i64, session_id: Option<i64>, purpose: &str, model: Option<&str>, tokens: Option<i64>, cost_usd: Option<f64>) -> Result<()>;
+    fn add_time_sample(&self, session_id: i64, at: i64, seconds: i64) -> Result<()>;
+    fn time_spent_by_session(&self, project_id: i64) -> Result<HashMap<i64, i64>>;
+    fn list_transcripts(&self, session_id: i64) -> Result<Vec<Transcript>>;
+    fn add_transcript(&self, session_id: i64, path: &str) -> Result<()>;
+    fn list_comments(&self, session_id: i64) -> Result<Vec<Comment>>;
+    fn create_comment(&self, session_id: i64, text: &str) -> Result<Comment>;
+}
+
+/// Give `Queries` each listed method, implemented by `Database` and `DbHandle` alike
+macro_rules! queries {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        pub trait Queries {
+            $(fn $name(&self $(, $arg: $ty)*) -> $ret;)*
+        }
+
+        impl Queries for Database {
+            $(
+                fn $name(&self $(, $arg: $ty)*) -> $ret {
+                    Database::$name(self $(, $arg)*)
+                }
+            )*
+        }
+
+        impl Queries for DbHandle {
+            $(
+                fn $name(&self $(, $arg: $ty)*) -> $ret {
+                    DbHandle::$name(self $(, $arg)*)
+                }
+            )*
+        }
+    };
+}
+
+// What starting terminals, making worktrees and answering socket requests needs. The
+// board and its socket server send each query on its own, so git and tmux run on the
+// caller's thread and never hold up the database thread; the CLI passes its `Database`
+// straight in
+queries! {
+    fn get_project_setting(&self, project_id: i64, key: &str) -> Result<Option<String>>;
+    fn list_status_overrides(&self, project_id: i64) -> Result<Vec<StatusOverride>>;
+    fn list_sessions(&self, project_id: i64) -> Result<Vec<Session>>;
+    fn get_live_session(&self, project_id: i64, session_id: i64) -> Result<Option<Session>>;
+    fn create_session_with(&self, project_id: i64, name: &str, status: Status, field_value: Option<(i64, &str)>, owner: Option<&str>) -> Result<Session>;
+    fn purge_session(&self, session_id: i64) -> Result<()>;
+    fn set_tmux_session(&self, session_id: i64, tmux_name: &str) -> Result<()>;
+    fn update_session_status(&self, session_id: i64, status: Status) -> Result<()>;
+    fn clear_tmux_session(&self, session_id: i64) -> Result<()>;
+    fn update_session_worktree(&self, session_id: i64, checkout_path: &Path, branch_name: &str) -> Result<()>;
+    fn list_fields(&self, project_id: i64) -> Result<Vec<Field>>;
+    fn get_all_session_field_values(&self, session_id: i64) -> Result<Vec<(i64, String, ValueSource)>>;
+    fn list_session_env(&self, session_id: i64) -> Result<Vec<(String, String)>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle() -> (DbHandle, i64) {
+        let db = Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        (DbHandle::spawn(db), project.id)
+    }
+
+    #[test]
+    fn test_calls_match_database() {
+        let (db, project_id) = handle();
+        let session = db.create_session_with(project_id, "Auth", Status::Review, None, Some("sam")).unwrap();
+        let field = db.create_field(project_id, "Goal", "What done looks like").unwrap();
+        db.set_session_field_value(session.id, field.id, "ship it", ValueSource::Manual).unwrap();
+        assert_eq!(db.get_session_field_value(session.id, field.id).unwrap(), "ship it");
+        assert_eq!(db.list_sessions(project_id).unwrap()[0].owner.as_deref(), Some("sam"));
+        db.set_status_checklist(project_id, Status::Review, &["Tests pass".to_string()]).unwrap();
+        assert_eq!(db.list_status_overrides(project_id).unwrap()[0].checklist, vec!["Tests pass".to_string()]);
+
+        // Errors come back from the thread as they would from the connection
+        assert!(db.create_field(project_id, "goal", "").is_err());
+        assert_eq!(db.call(move |db| db.list_fields(project_id).map(|f| f.len())).unwrap().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_stopped_thread_is_an_error() {
+        let (db, project_id) = handle();
+        assert!(db.call(|_| panic!("query failed")).is_err());
+        assert!(db.list_fields(project_id).is_err());
+    }
+
+    #[test]
+    fn test_concurrent_reads_and_writes() {
+        let (db, project_id) = handle();
+        let field = db.create_field(project_id, "Counter", "").unwrap();
+        let threads: Vec<_> = (0..8)
+            .map(|t| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for i in 0..50 {
+                        let session = db.create_session_with(project_id, &format!("t{} s{}", t, i), Status::Planned, None, None).unwrap();
+                        db.set_session_field_value(session.id, field.id, &i.to_string(), ValueSource::Manual).unwrap();
+                        assert_eq!(db.get_session_field_value(session.id, field.id).unwrap(), i.to_string());
+                        db.update_session_status(session.id, Status::InProgress).unwrap();
+                        let listed = db.list_sessions(project_id).unwrap();
+                        assert!(listed.iter().any(|s| s.id == session.id && s.status == Status::InProgress));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let sessions = db.list_sessions(project_id).unwrap();
+        assert_eq!(sessions.len(), 400);
+        assert!(sessions.iter().all(|s| s.status == Status::InProgress));
+    }
+
+    #[test]
+    fn test_send_keeps_order() {
+        let (db, project_id) = handle();
+        let write = db.send(move |db| db.set_project_setting(project_id, "theme", "light"));
+        let read = db.send(move |db| db.get_project_setting(project_id, "theme"));
+        assert_eq!(read.recv().unwrap().unwrap().as_deref(), Some("light"));
+        write.recv().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_read_modify_write_in_one_call() {
+        // A closure runs start to finish before any other call, so counters don't lose
+        // updates even with many writers
+        let (db, project_id) = handle();
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        db.call(move |db| {
+                            let count: u32 = db.get_project_setting(project_id, "count")?.map_or(0, |v| v.parse().unwrap());
+                            db.set_project_setting(project_id, "count", &(count + 1).to_string())
+                        })
+                        .unwrap()
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(db.get_project_setting(project_id, "count").unwrap().as_deref(), Some("800"));
+    }
+}
@@ -0,0 +1,76 @@
+use color_eyre::{eyre::eyre, Result};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::logging::Traced;
+
+/// Clipboard tools tried in order: (program, args)
+const CLIPBOARD_TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Copy text to the system clipboard using the first available tool
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    for (program, args) in CLIPBOARD_TOOLS {
+        let Ok(mut child) = Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            tracing::debug!(tool = program, "copied to clipboard");
+            return Ok(());
+        }
+    }
+    Err(eyre!("No clipboard tool found (install wl-copy, xclip or xsel)"))
+}
+
+/// Open a URL in the default browser
+pub fn open_url(url: &str) -> Result<()> {
+    let program = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    let status = Command::new(program)
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .traced_status()
+        .map_err(|e| eyre!("Failed to run {}: {}", program, e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(eyre!("{} exited with {}", program, status))
+    }
+}
+
+/// Show a path in the platform file manager
+pub fn reveal_in_file_manager(path: &Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else {
+        let mut command = Command::new("xdg-open");
+        command.arg(path);
+        command
+    };
+    let status = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .traced_status()
+        .map_err(|e| eyre!("Failed to open file manager: {}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(eyre!("File manager exited with {}", status))
+    }
+}
@@ -0,0 +1,162 @@
+/// Parse a due date: `YYYY-MM-DD`, `today`, `tomorrow`, `+3d` or `+2w`, as days since
+/// 1970-01-01. `today` is the current local day in the same count. An empty input
+/// clears the due date (None)
+pub fn parse(input: &str, today: i64) -> Result<Option<i64>, String> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("Expected YYYY-MM-DD, today, tomorrow, +3d or +2w, got \"{}\"", input);
+    let day = match input.as_str() {
+        "today" => today,
+        "tomorrow" => today + 1,
+        _ => match input.strip_prefix('+') {
+            Some(offset) => {
+                let split = offset.len() - offset.chars().last().map_or(0, char::len_utf8);
+                let count: i64 = offset[..split].parse().map_err(|_| invalid())?;
+                let days = match &offset[split..] {
+                    "d" => Some(count),
+                    "w" => count.checked_mul(7),
+                    _ => return Err(invalid()),
+                };
+                days.and_then(|days| today.checked_add(days)).ok_or_else(invalid)?
+            }
+            None => parse_date(&input).ok_or_else(invalid)?,
+        },
+    };
+    // Far-off offsets would overflow the date arithmetic that shows them
+    if !(days_from_civil(0, 1, 1)..=days_from_civil(9999, 12, 31)).contains(&day) {
+        return Err(invalid());
+    }
+    Ok(Some(day))
+}
+
+/// A `YYYY-MM-DD` date as days since 1970-01-01
+pub fn parse_date(input: &str) -> Option<i64> {
+    let mut parts = input.splitn(3, '-');
+    let (y, m, d) = (parts.next()?, parts.next()?, parts.next()?);
+    let (y, m, d): (i64, u32, u32) = (y.parse().ok()?, m.parse().ok()?, d.parse().ok()?);
+    if !(0..=9999).contains(&y) || !(1..=12).contains(&m) || !(1..=days_in_month(y, m)).contains(&d) {
+        return None;
+    }
+    Some(days_from_civil(y, m, d))
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let month = month as i64;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The date of a day counted from 1970-01-01, as (year, month, day)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The canonical form of a due date, `YYYY-MM-DD`
+pub fn format(day: i64) -> String {
+    let (y, m, d) = civil_from_days(day);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+/// A due date as shown on a card: `today`, `tomorrow`, the month and day within the
+/// current year, else the full date
+pub fn badge(day: i64, today: i64) -> String {
+    match day - today {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        _ if civil_from_days(day).0 == civil_from_days(today).0 => format(day)[5..].to_string(),
+        _ => format(day),
+    }
+}
+
+/// Whether a card due on `day` is due by the end of this week (Sunday), overdue ones included
+pub fn due_this_week(day: i64, today: i64) -> bool {
+    // 1970-01-01 was a Thursday, so Monday is 0 after shifting by 3
+    let sunday = today - (today + 3).rem_euclid(7) + 6;
+    day <= sunday
+}
+
+/// Cards due today and cards overdue, for the agenda strip
+pub fn agenda(due: impl IntoIterator<Item = i64>, today: i64) -> (usize, usize) {
+    due.into_iter().fold((0, 0), |(due_today, overdue), day| {
+        (due_today + usize::from(day == today), overdue + usize::from(day < today))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // 2024-07-01 was a Monday
+        let today = days_from_civil(2024, 7, 1);
+        assert_eq!(parse("", today), Ok(None));
+        assert_eq!(parse("2024-07-04", today), Ok(Some(today + 3)));
+        assert_eq!(parse(" Today ", today), Ok(Some(today)));
+        assert_eq!(parse("tomorrow", today), Ok(Some(today + 1)));
+        assert_eq!(parse("+3d", today), Ok(Some(today + 3)));
+        assert_eq!(parse("+2w", today), Ok(Some(today + 14)));
+        // A past date is allowed, it is just overdue
+        assert_eq!(parse("2024-06-30", today), Ok(Some(today - 1)));
+        assert!(parse("2024-02-30", today).is_err());
+        assert!(parse("+3m", today).is_err());
+        assert!(parse("soon", today).is_err());
+        assert!(parse("2024-07", today).is_err());
+        assert!(parse(&format!("+{}w", i64::MAX / 2), today).is_err());
+        assert!(parse(&format!("+{}d", i64::MAX), today).is_err());
+        assert!(parse("+5000000w", today).is_err());
+        assert!(parse("99999999999999-01-01", today).is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(0), "1970-01-01");
+        assert_eq!(format(days_from_civil(2024, 2, 29)), "2024-02-29");
+        assert_eq!(format(days_from_civil(2000, 12, 31)), "2000-12-31");
+        assert_eq!(parse_date(&format(20_000)), Some(20_000));
+
+        let today = days_from_civil(2024, 12, 30);
+        assert_eq!(badge(today, today), "today");
+        assert_eq!(badge(today + 1, today), "tomorrow");
+        assert_eq!(badge(today - 5, today), "12-25");
+        assert_eq!(badge(today + 3, today), "2025-01-02");
+    }
+
+    #[test]
+    fn test_this_week_and_agenda() {
+        let monday = days_from_civil(2024, 7, 1);
+        let sunday = monday + 6;
+        for today in monday..=sunday {
+            assert!(due_this_week(sunday, today));
+            assert!(!due_this_week(sunday + 1, today));
+            assert!(due_this_week(monday - 10, today));
+        }
+        assert_eq!(agenda([monday, monday, monday - 1, monday + 1], monday), (2, 1));
+        assert_eq!(agenda([], monday), (0, 0));
+    }
+}
@@ -1,55 +1,138 @@
 use color_eyre::{eyre::eyre, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use crate::logging::Traced;
+use crate::paths;
 use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+
+/// Run git in `dir` as a test identity, failing the test when git fails
+#[cfg(test)]
+pub fn test_git(dir: &Path, args: &[&str]) {
+    let status = git_in(dir)
+        .args(["-c", "user.name=wb", "-c", "user.email=wb@example.com"])
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// Make `dir` a repo on `main` with one empty commit, for tests
+#[cfg(test)]
+pub fn init_test_repo(dir: &Path) {
+    test_git(dir, &["init", "-q", "-b", "main"]);
+    test_git(dir, &["commit", "-q", "--allow-empty", "-m", "base"]);
+}
+
+/// Check if git is installed and available
+pub fn is_available() -> bool {
+    Command::new("git")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .traced_status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// A git command run in `dir` (`git -C dir`). The path is passed as it is, not as text
+fn git_in(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir);
+    command
+}
 
 /// Get the root of the git repository containing the given path
-pub fn get_repo_root(path: &str) -> Option<String> {
-    let output = Command::new("git")
-        .args(["-C", path, "rev-parse", "--show-toplevel"])
-        .output()
+pub fn get_repo_root(path: &Path) -> Option<PathBuf> {
+    let output = git_in(path)
+        .args(["rev-parse", "--show-toplevel"])
+        .traced_output()
         .ok()?;
 
     if output.status.success() {
-        let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if root.is_empty() {
-            None
-        } else {
-            Some(root)
-        }
+        paths::from_output_line(&output.stdout)
     } else {
         None
     }
 }
 
 /// Check if a branch exists in the repository
-pub fn branch_exists(repo_path: &str, branch_name: &str) -> bool {
-    Command::new("git")
-        .args(["-C", repo_path, "show-ref", "--verify", "--quiet", &format!("refs/heads/{}", branch_name)])
+pub fn branch_exists(repo_path: &Path, branch_name: &str) -> bool {
+    git_in(repo_path)
+        .args(["show-ref", "--verify", "--quiet", &format!("refs/heads/{}", branch_name)])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .status()
+        .traced_status()
         .map(|s| s.success())
         .unwrap_or(false)
 }
 
+/// A git config value (e.g. `user.name`) as seen from `dir`, or globally without one
+pub fn config_value(dir: Option<&Path>, key: &str) -> Option<String> {
+    let mut command = match dir {
+        Some(dir) => git_in(dir),
+        None => Command::new("git"),
+    };
+    let output = command.args(["config", "--get", key]).traced_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+/// URL of the `origin` remote, if any
+pub fn get_remote_url(repo_path: &Path) -> Option<String> {
+    let output = git_in(repo_path)
+        .args(["remote", "get-url", "origin"])
+        .traced_output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!url.is_empty()).then_some(url)
+}
+
+/// Detect the remote default branch via `origin/HEAD`, e.g. "origin/main"
+pub fn detect_default_branch(repo_path: &Path) -> Option<String> {
+    let output = git_in(repo_path)
+        .args(["symbolic-ref", "--quiet", "refs/remotes/origin/HEAD"])
+        .traced_output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let full = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    full.strip_prefix("refs/remotes/").map(String::from)
+}
+
 /// Create a new git worktree
 /// If the branch already exists, checks it out; otherwise creates a new branch
-pub fn create_worktree(repo_path: &str, branch_name: &str, worktree_path: &str) -> Result<()> {
+/// from `start_point` (or the current HEAD when not given)
+pub fn create_worktree(repo_path: &Path, branch_name: &str, worktree_path: &Path, start_point: Option<&str>) -> Result<()> {
     // Check if worktree path already exists
-    if Path::new(worktree_path).exists() {
-        return Err(eyre!("Worktree path already exists: {}", worktree_path));
+    if worktree_path.exists() {
+        return Err(eyre!("Worktree path already exists: {}", worktree_path.display()));
     }
 
     let status = if branch_exists(repo_path, branch_name) {
         // Branch exists, check it out in the worktree
-        Command::new("git")
-            .args(["-C", repo_path, "worktree", "add", worktree_path, branch_name])
-            .status()?
+        git_in(repo_path)
+            .args(["worktree", "add"])
+            .arg(worktree_path)
+            .arg(branch_name)
+            .traced_status()?
     } else {
         // Create new branch in the worktree
-        Command::new("git")
-            .args(["-C", repo_path, "worktree", "add", "-b", branch_name, worktree_path])
-            .status()?
+        let mut command = git_in(repo_path);
+        command.args(["worktree", "add", "-b", branch_name]).arg(worktree_path);
+        if let Some(start_point) = start_point {
+            command.arg(start_point);
+        }
+        command.traced_status()?
     };
 
     if status.success() {
@@ -60,16 +143,13 @@ pub fn create_worktree(repo_path: &str, branch_name: &str, worktree_path: &str)
 }
 
 /// Remove a git worktree
-pub fn remove_worktree(repo_path: &str, worktree_path: &str, force: bool) -> Result<()> {
-    let mut args = vec!["-C", repo_path, "worktree", "remove"];
+pub fn remove_worktree(repo_path: &Path, worktree_path: &Path, force: bool) -> Result<()> {
+    let mut command = git_in(repo_path);
+    command.args(["worktree", "remove"]);
     if force {
-        args.push("--force");
+        command.arg("--force");
     }
-    args.push(worktree_path);
-
-    let status = Command::new("git")
-        .args(&args)
-        .status()?;
+    let status = command.arg(worktree_path).traced_status()?;
 
     if status.success() {
         Ok(())
@@ -78,6 +158,187 @@ pub fn remove_worktree(repo_path: &str, worktree_path: &str, force: bool) -> Res
     }
 }
 
+/// Paths of the repository's worktrees, the main one first (`git worktree list`).
+/// None when git can't be run or `repo_path` isn't in a repository
+pub fn list_worktrees(repo_path: &Path) -> Option<Vec<PathBuf>> {
+    let output = git_in(repo_path)
+        .args(["worktree", "list", "--porcelain", "-z"])
+        .traced_output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        output.stdout
+            .split(|b| *b == 0)
+            .filter_map(|field| field.strip_prefix(b"worktree "))
+            .map(paths::from_bytes)
+            .collect(),
+    )
+}
+
+/// Whether `path` is a checkout of the repository at `repo_path` that git still lists.
+/// When the worktrees can't be listed the directory is trusted as long as it exists
+pub fn is_registered_worktree(repo_path: &Path, path: &Path) -> bool {
+    if !path.is_dir() {
+        return false;
+    }
+    let Some(worktrees) = list_worktrees(repo_path) else {
+        return true;
+    };
+    let same = |a: &Path| a == path || std::fs::canonicalize(a).ok() == std::fs::canonicalize(path).ok();
+    // A plain directory left at a registered path isn't a checkout
+    get_repo_root(path).is_some_and(|top| same(&top)) && worktrees.iter().any(|w| same(w))
+}
+
+/// Forget worktrees whose directories are gone (`git worktree prune`), so one can be
+/// added at the same path again
+pub fn prune_worktrees(repo_path: &Path) -> Result<()> {
+    let status = git_in(repo_path).args(["worktree", "prune"]).traced_status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(eyre!("Failed to prune worktrees"))
+    }
+}
+
+/// Check if a branch has been merged into the repository's current HEAD
+pub fn is_branch_merged(repo_path: &Path, branch_name: &str) -> bool {
+    git_in(repo_path)
+        .args(["merge-base", "--is-ancestor", branch_name, "HEAD"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .traced_status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Git's message for a failed command: its stderr, else its stdout
+fn failure_message(output: &std::process::Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    match stderr.is_empty() {
+        true => String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        false => stderr,
+    }
+}
+
+/// Fast-forward what is checked out at `repo_path` from its upstream (`git pull --ff-only`).
+/// Returns git's last line, e.g. `Already up to date.`
+pub fn pull(repo_path: &Path) -> Result<String> {
+    // Never wait on a credentials prompt the board can't show
+    let output = git_in(repo_path).args(["pull", "--ff-only"]).env("GIT_TERMINAL_PROMPT", "0").traced_output()?;
+    if !output.status.success() {
+        return Err(eyre!("git pull failed: {}", failure_message(&output)));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().map(str::trim).rfind(|line| !line.is_empty()).unwrap_or("Already up to date.").to_string())
+}
+
+/// Delete a local branch. Without `force` git refuses one it doesn't see merged, which
+/// includes branches landed by a squash or rebase merge
+pub fn delete_branch(repo_path: &Path, branch_name: &str, force: bool) -> Result<()> {
+    let flag = if force { "-D" } else { "-d" };
+    let output = git_in(repo_path).args(["branch", flag, "--", branch_name]).traced_output()?;
+    if !output.status.success() {
+        return Err(eyre!("Failed to delete {}: {}", branch_name, failure_message(&output)));
+    }
+    Ok(())
+}
+
+/// How many commits of `branch_name` no remote-tracking branch has
+pub fn unpushed_commits(repo_path: &Path, branch_name: &str) -> Result<usize, String> {
+    let output = git_in(repo_path)
+        .args(["rev-list", "--count", branch_name, "--not", "--remotes", "--"])
+        .traced_output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(failure_message(&output));
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().map_err(|e| format!("Unexpected git output: {}", e))
+}
+
+/// The abbreviated commit `rev` names, e.g. a branch tip
+pub fn short_commit(repo_path: &Path, rev: &str) -> Option<String> {
+    let output = git_in(repo_path)
+        .args(["rev-parse", "--verify", "--quiet", "--short"])
+        .arg(format!("{}^{{commit}}", rev))
+        .traced_output()
+        .ok()?;
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(commit).filter(|c| output.status.success() && !c.is_empty())
+}
+
+/// The merge that brought `commit` into what is checked out at `repo_path`, abbreviated.
+/// None when there is none, e.g. after a squash or rebase merge
+pub fn merge_commit(repo_path: &Path, commit: &str) -> Option<String> {
+    let output = git_in(repo_path)
+        .args(["rev-list", "--ancestry-path", "--merges", "--reverse"])
+        .arg(format!("{}..HEAD", commit))
+        .traced_output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let first = String::from_utf8_lossy(&output.stdout).lines().next()?.trim().to_string();
+    short_commit(repo_path, &first)
+}
+
+/// What a session branch adds on top of its base
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchChanges {
+    /// `git log --oneline base..branch`, newest first
+    pub log: Vec<String>,
+    /// `git diff --stat base...branch`, ending with the summary line
+    pub diff_stat: Vec<String>,
+}
+
+/// Commits and changed files of `branch` since it left `base`, run in `dir`.
+/// The error is git's own message
+pub fn branch_changes(dir: &Path, base: &str, branch: &str) -> Result<BranchChanges, String> {
+    let log = git_lines(dir, &["log", "--oneline", &format!("{}..{}", base, branch), "--"])?;
+    let diff_stat = git_lines(dir, &["diff", "--stat", &format!("{}...{}", base, branch), "--"])?;
+    Ok(BranchChanges { log, diff_stat })
+}
+
+/// Output lines of a git command in `dir`, or its stderr when it fails
+fn git_lines(dir: &Path, args: &[&str]) -> Result<Vec<String>, String> {
+    let output = git_in(dir)
+        .args(args)
+        .traced_output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(if stderr.is_empty() { format!("git exited with {}", output.status) } else { stderr });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+}
+
+/// Total on-disk size of a directory tree in bytes, without following symlinks
+/// Returns None if the path doesn't exist or the walk was cancelled
+pub fn dir_size(root: &Path, cancel: &AtomicBool) -> Option<u64> {
+    if !root.exists() {
+        return None;
+    }
+
+    let mut total = 0u64;
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else { continue };
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else {
+                total += meta.len();
+            }
+        }
+    }
+    Some(total)
+}
+
 /// Information about dirty state in a worktree
 #[derive(Debug, Clone)]
 pub struct DirtyStatus {
@@ -92,21 +353,53 @@ impl DirtyStatus {
     }
 }
 
-/// Check if a worktree has uncommitted changes
-pub fn is_worktree_dirty(path: &str) -> bool {
-    get_dirty_status(path).map(|s| s.is_dirty()).unwrap_or(false)
+/// Keep `file` out of `git status` (and so out of the dirty checks) in the repo around
+/// it, through the repo's own `info/exclude`, shared by all its worktrees, rather than
+/// a tracked `.gitignore`. A file outside any repo is left alone
+pub fn exclude_locally(file: &Path) -> Result<()> {
+    let Some(dir) = file.parent() else { return Ok(()) };
+    let output = git_in(dir).args(["rev-parse", "--show-toplevel", "--git-path", "info/exclude"]).traced_output()?;
+    if !output.status.success() {
+        return Ok(());
+    }
+    let mut lines = output.stdout.split(|b| *b == b'\n').filter_map(paths::from_output_line);
+    let (Some(root), Some(exclude)) = (lines.next(), lines.next()) else {
+        return Err(eyre!("git rev-parse printed no repo root"));
+    };
+    let Ok(relative) = file.strip_prefix(&root) else { return Ok(()) };
+    let Some(relative) = relative.to_str() else { return Ok(()) };
+    // Anchored at the root, with glob characters taken literally
+    let mut pattern = String::from("/");
+    for c in relative.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+
+    let exclude = dir.join(exclude);
+    let existing = std::fs::read_to_string(&exclude).unwrap_or_default();
+    if existing.lines().any(|line| line == pattern) {
+        return Ok(());
+    }
+    if let Some(parent) = exclude.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+    std::fs::write(&exclude, format!("{}{}{}\n", existing, separator, pattern))?;
+    Ok(())
 }
 
 /// Get detailed dirty status for a worktree
-pub fn get_dirty_status(path: &str) -> Option<DirtyStatus> {
+pub fn get_dirty_status(path: &Path) -> Option<DirtyStatus> {
     // Check if path exists and is a git worktree
-    if !Path::new(path).exists() {
+    if !path.exists() {
         return None;
     }
 
-    let output = Command::new("git")
-        .args(["-C", path, "status", "--porcelain"])
-        .output()
+    let output = git_in(path)
+        .args(["status", "--porcelain"])
+        .traced_output()
         .ok()?;
 
     if !output.status.success() {
@@ -146,37 +439,109 @@ pub fn get_dirty_status(path: &str) -> Option<DirtyStatus> {
     })
 }
 
-/// Sanitize a session name into a valid git branch name
-/// "Fix Auth Bug" -> "wb/fix-auth-bug"
-pub fn sanitize_branch_name(session_name: &str) -> String {
-    let sanitized: String = session_name
-        .to_lowercase()
-        .chars()
-        .map(|c| {
-            if c.is_alphanumeric() {
-                c
-            } else {
-                '-'
-            }
-        })
-        .collect();
+/// What a worktree has checked out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Head {
+    Branch(String),
+    /// No branch, at this abbreviated commit
+    Detached(String),
+}
+
+impl std::fmt::Display for Head {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Head::Branch(branch) => f.write_str(branch),
+            Head::Detached(commit) => write!(f, "detached @ {}", commit),
+        }
+    }
+}
+
+/// The branch (or, when detached, the commit) checked out at `path`
+pub fn head(path: &Path) -> Option<Head> {
+    if !path.exists() {
+        return None;
+    }
+    let output = git_in(path).args(["symbolic-ref", "--quiet", "--short", "HEAD"]).traced_output().ok()?;
+    if output.status.success() {
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Some(branch).filter(|b| !b.is_empty()).map(Head::Branch);
+    }
+    let output = git_in(path).args(["rev-parse", "--short", "HEAD"]).traced_output().ok()?;
+    let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(commit).filter(|c| output.status.success() && !c.is_empty()).map(Head::Detached)
+}
 
-    // Remove consecutive dashes and trim dashes from ends
+/// When the HEAD of the checkout at `path` last moved (its HEAD file's mtime), or
+/// `None` if the checkout is gone. A worktree's `.git` is a file pointing at its git dir
+pub fn head_modified(path: &Path) -> Option<SystemTime> {
+    let dot_git = path.join(".git");
+    let git_dir = if dot_git.is_dir() {
+        dot_git
+    } else {
+        let link = std::fs::read_to_string(&dot_git).ok()?;
+        path.join(link.strip_prefix("gitdir:")?.trim())
+    };
+    std::fs::metadata(git_dir.join("HEAD")).and_then(|m| m.modified()).ok()
+}
+
+/// Check out an existing branch in the worktree at `path`
+pub fn checkout_branch(path: &Path, branch_name: &str) -> Result<()> {
+    let output = git_in(path).args(["checkout", "--quiet", branch_name, "--"]).traced_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(eyre!("Failed to check out {}: {}", branch_name, stderr.trim()));
+    }
+    Ok(())
+}
+
+/// Branch prefix used when a project doesn't configure one
+pub const DEFAULT_BRANCH_PREFIX: &str = "wb/";
+
+/// Check that a branch prefix is usable in a git ref
+pub fn validate_branch_prefix(prefix: &str) -> Result<(), String> {
+    if prefix.chars().any(char::is_whitespace) {
+        return Err("Prefix cannot contain spaces".to_string());
+    }
+    if prefix.contains("..") {
+        return Err("Prefix cannot contain '..'".to_string());
+    }
+    if prefix.starts_with('/') || prefix.starts_with('-') {
+        return Err("Prefix cannot start with '/' or '-'".to_string());
+    }
+    Ok(())
+}
+
+/// Longest name part of a branch (after the prefix) when a project doesn't configure one
+pub const DEFAULT_BRANCH_MAX_LEN: usize = 60;
+
+/// Bounds for a project's branch name length setting
+pub const BRANCH_MAX_LEN_RANGE: std::ops::RangeInclusive<usize> = 8..=200;
+
+/// Sanitize a session name into a git branch name under the given prefix, keeping at most
+/// `max_len` characters of the name: ("Fix Auth Bug", "tom/") -> "tom/fix-auth-bug".
+/// Only ASCII letters, digits and single dashes survive, so git's ref rules (no `..`, `@{`,
+/// `.lock`, leading `-` or `.`) hold by construction; accented letters are transliterated
+/// and other non-ASCII characters dropped
+pub fn sanitize_branch_name_with_prefix(session_name: &str, prefix: &str, max_len: usize) -> String {
     let mut result = String::new();
     let mut last_was_dash = true; // Start true to skip leading dashes
-    for c in sanitized.chars() {
-        if c == '-' {
-            if !last_was_dash {
-                result.push(c);
-                last_was_dash = true;
-            }
-        } else {
+    for c in session_name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
             result.push(c);
             last_was_dash = false;
+        } else if let Some(ascii) = transliterate(c) {
+            result.push_str(ascii);
+            last_was_dash = false;
+        } else if c.is_alphanumeric() {
+            // Letters of other scripts are dropped rather than turned into dashes
+        } else if !last_was_dash {
+            // Punctuation, whitespace and symbols (emoji) separate words
+            result.push('-');
+            last_was_dash = true;
         }
     }
 
-    // Remove trailing dash
+    result.truncate(max_len.max(1));
     while result.ends_with('-') {
         result.pop();
     }
@@ -185,23 +550,178 @@ pub fn sanitize_branch_name(session_name: &str) -> String {
         result = "session".to_string();
     }
 
-    format!("wb/{}", result)
+    format!("{}{}", prefix, result)
+}
+
+/// ASCII spelling of common accented Latin letters (lowercase input)
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+/// Whether git accepts a branch name (`git check-ref-format --branch`)
+pub fn is_valid_branch_name(branch_name: &str) -> bool {
+    Command::new("git")
+        .args(["check-ref-format", "--branch", branch_name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .traced_status()
+        .map(|s| s.success())
+        .unwrap_or(false)
 }
 
 /// Generate a worktree path based on repo path and branch name
 /// Repo at `/Users/tom/Code/myproject` + branch `wb/fix-auth-bug`:
 /// -> `/Users/tom/Code/myproject-fix-auth-bug/`
-pub fn generate_worktree_path(repo_path: &str, branch_name: &str) -> String {
-    // Extract the part after "wb/" prefix
-    let branch_suffix = branch_name.strip_prefix("wb/").unwrap_or(branch_name);
+pub fn generate_worktree_path(repo_path: &Path, branch_name: &str) -> PathBuf {
+    // Extract the part after "wb/" prefix; custom prefixes are flattened ("tom/x" -> "tom-x")
+    let branch_suffix = branch_name.strip_prefix(DEFAULT_BRANCH_PREFIX).unwrap_or(branch_name);
+
+    // Appended to the raw path, so a repo directory that isn't UTF-8 keeps its bytes
+    let mut path = repo_path.as_os_str().to_os_string();
+    path.push(format!("-{}", branch_suffix.replace('/', "-")));
+    PathBuf::from(path)
+}
+
+/// The git calls the board makes, so tests can drive it without repositories.
+/// `SystemGit` runs the real commands
+pub trait GitClient: Send + Sync {
+    fn get_repo_root(&self, path: &Path) -> Option<PathBuf>;
+    fn get_remote_url(&self, repo_path: &Path) -> Option<String>;
+    fn config_value(&self, dir: Option<&Path>, key: &str) -> Option<String>;
+    fn detect_default_branch(&self, repo_path: &Path) -> Option<String>;
+    fn is_valid_branch_name(&self, branch_name: &str) -> bool;
+    fn create_worktree(&self, repo_path: &Path, branch_name: &str, worktree_path: &Path, start_point: Option<&str>) -> Result<()>;
+    fn remove_worktree(&self, repo_path: &Path, worktree_path: &Path, force: bool) -> Result<()>;
+    fn prune_worktrees(&self, repo_path: &Path) -> Result<()>;
+    fn is_registered_worktree(&self, repo_path: &Path, path: &Path) -> bool;
+    fn is_branch_merged(&self, repo_path: &Path, branch_name: &str) -> bool;
+    fn branch_changes(&self, dir: &Path, base: &str, branch: &str) -> Result<BranchChanges, String>;
+    fn get_dirty_status(&self, path: &Path) -> Option<DirtyStatus>;
+    fn head(&self, path: &Path) -> Option<Head>;
+    fn head_modified(&self, path: &Path) -> Option<SystemTime>;
+    fn checkout_branch(&self, path: &Path, branch_name: &str) -> Result<()>;
+    fn pull(&self, repo_path: &Path) -> Result<String>;
+    fn delete_branch(&self, repo_path: &Path, branch_name: &str, force: bool) -> Result<()>;
+    fn unpushed_commits(&self, repo_path: &Path, branch_name: &str) -> Result<usize, String>;
+    fn short_commit(&self, repo_path: &Path, rev: &str) -> Option<String>;
+    fn merge_commit(&self, repo_path: &Path, commit: &str) -> Option<String>;
+}
+
+/// The git on `$PATH`
+pub struct SystemGit;
+
+impl GitClient for SystemGit {
+    fn get_repo_root(&self, path: &Path) -> Option<PathBuf> {
+        get_repo_root(path)
+    }
+
+    fn get_remote_url(&self, repo_path: &Path) -> Option<String> {
+        get_remote_url(repo_path)
+    }
+
+    fn config_value(&self, dir: Option<&Path>, key: &str) -> Option<String> {
+        config_value(dir, key)
+    }
+
+    fn detect_default_branch(&self, repo_path: &Path) -> Option<String> {
+        detect_default_branch(repo_path)
+    }
+
+    fn is_valid_branch_name(&self, branch_name: &str) -> bool {
+        is_valid_branch_name(branch_name)
+    }
+
+    fn create_worktree(&self, repo_path: &Path, branch_name: &str, worktree_path: &Path, start_point: Option<&str>) -> Result<()> {
+        create_worktree(repo_path, branch_name, worktree_path, start_point)
+    }
+
+    fn remove_worktree(&self, repo_path: &Path, worktree_path: &Path, force: bool) -> Result<()> {
+        remove_worktree(repo_path, worktree_path, force)
+    }
+
+    fn prune_worktrees(&self, repo_path: &Path) -> Result<()> {
+        prune_worktrees(repo_path)
+    }
+
+    fn is_registered_worktree(&self, repo_path: &Path, path: &Path) -> bool {
+        is_registered_worktree(repo_path, path)
+    }
+
+    fn is_branch_merged(&self, repo_path: &Path, branch_name: &str) -> bool {
+        is_branch_merged(repo_path, branch_name)
+    }
+
+    fn branch_changes(&self, dir: &Path, base: &str, branch: &str) -> Result<BranchChanges, String> {
+        branch_changes(dir, base, branch)
+    }
+
+    fn get_dirty_status(&self, path: &Path) -> Option<DirtyStatus> {
+        get_dirty_status(path)
+    }
+
+    fn head(&self, path: &Path) -> Option<Head> {
+        head(path)
+    }
+
+    fn head_modified(&self, path: &Path) -> Option<SystemTime> {
+        head_modified(path)
+    }
+
+    fn checkout_branch(&self, path: &Path, branch_name: &str) -> Result<()> {
+        checkout_branch(path, branch_name)
+    }
 
-    format!("{}-{}", repo_path, branch_suffix)
+    fn pull(&self, repo_path: &Path) -> Result<String> {
+        pull(repo_path)
+    }
+
+    fn delete_branch(&self, repo_path: &Path, branch_name: &str, force: bool) -> Result<()> {
+        delete_branch(repo_path, branch_name, force)
+    }
+
+    fn unpushed_commits(&self, repo_path: &Path, branch_name: &str) -> Result<usize, String> {
+        unpushed_commits(repo_path, branch_name)
+    }
+
+    fn short_commit(&self, repo_path: &Path, rev: &str) -> Option<String> {
+        short_commit(repo_path, rev)
+    }
+
+    fn merge_commit(&self, repo_path: &Path, commit: &str) -> Option<String> {
+        merge_commit(repo_path, commit)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Sanitize under the default prefix and length: "Fix Auth Bug" -> "wb/fix-auth-bug"
+    fn sanitize_branch_name(session_name: &str) -> String {
+        sanitize_branch_name_with_prefix(session_name, DEFAULT_BRANCH_PREFIX, DEFAULT_BRANCH_MAX_LEN)
+    }
+
     #[test]
     fn test_sanitize_branch_name() {
         assert_eq!(sanitize_branch_name("Fix Auth Bug"), "wb/fix-auth-bug");
@@ -212,15 +732,228 @@ mod tests {
         assert_eq!(sanitize_branch_name("special!@#chars"), "wb/special-chars");
     }
 
+    #[test]
+    fn test_sanitize_branch_name_with_prefix() {
+        assert_eq!(sanitize_branch_name_with_prefix("Fix Auth Bug", "tom/", 60), "tom/fix-auth-bug");
+        assert_eq!(sanitize_branch_name_with_prefix("Fix Auth Bug", "", 60), "fix-auth-bug");
+    }
+
+    #[test]
+    fn test_sanitize_branch_name_reserved_patterns() {
+        let cases = [
+            ("-rf --force", "wb/rf-force"),
+            ("..hidden", "wb/hidden"),
+            ("a..b", "wb/a-b"),
+            ("release.lock", "wb/release-lock"),
+            ("config.lock.", "wb/config-lock"),
+            ("email@{upstream}", "wb/email-upstream"),
+            ("@", "wb/session"),
+            ("ref~1^2:path?*[x]\\y", "wb/ref-1-2-path-x-y"),
+            ("tab\tand\nnewline", "wb/tab-and-newline"),
+            ("🚀🔥✨", "wb/session"),
+            ("fix 🐛 in login", "wb/fix-in-login"),
+            ("🐛fix", "wb/fix"),
+            ("Café Crème brûlée", "wb/cafe-creme-brulee"),
+            ("Straße Łódź", "wb/strasse-lodz"),
+            ("修复登录", "wb/session"),
+            ("login 修复 bug", "wb/login-bug"),
+            ("ÉCOLE", "wb/ecole"),
+            ("/leading/slash/", "wb/leading-slash"),
+            ("", "wb/session"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(sanitize_branch_name(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_branch_name_caps_length() {
+        let long = "word ".repeat(40);
+        let branch = sanitize_branch_name_with_prefix(&long, "wb/", 12);
+        assert_eq!(branch, "wb/word-word-wo");
+        // A cut that ends on a dash drops it
+        assert_eq!(sanitize_branch_name_with_prefix("abcdefghij-k", "", 11), "abcdefghij");
+        assert!(sanitize_branch_name(&long).len() <= DEFAULT_BRANCH_PREFIX.len() + DEFAULT_BRANCH_MAX_LEN);
+    }
+
+    #[test]
+    fn test_sanitized_names_pass_check_ref_format() {
+        if !is_available() {
+            return;
+        }
+        for input in ["-x", "a..b", "x.lock", "@{u}", "🚀", "Café", "ends with dot.", "HEAD"] {
+            let branch = sanitize_branch_name(input);
+            assert!(is_valid_branch_name(&branch), "{:?} -> {:?}", input, branch);
+        }
+        assert!(!is_valid_branch_name("wb/a..b"));
+        assert!(!is_valid_branch_name("-x"));
+    }
+
+    #[test]
+    fn test_validate_branch_prefix() {
+        assert!(validate_branch_prefix("wb/").is_ok());
+        assert!(validate_branch_prefix("feature/tom-").is_ok());
+        assert!(validate_branch_prefix("my prefix/").is_err());
+        assert!(validate_branch_prefix("a..b/").is_err());
+    }
+
     #[test]
     fn test_generate_worktree_path() {
         assert_eq!(
-            generate_worktree_path("/Users/tom/Code/myproject", "wb/fix-auth-bug"),
-            "/Users/tom/Code/myproject-fix-auth-bug"
+            generate_worktree_path(Path::new("/Users/tom/Code/myproject"), "wb/fix-auth-bug"),
+            Path::new("/Users/tom/Code/myproject-fix-auth-bug")
+        );
+        assert_eq!(
+            generate_worktree_path(Path::new("/home/user/repo"), "wb/new-feature"),
+            Path::new("/home/user/repo-new-feature")
         );
         assert_eq!(
-            generate_worktree_path("/home/user/repo", "wb/new-feature"),
-            "/home/user/repo-new-feature"
+            generate_worktree_path(Path::new("/home/user/repo"), "tom/new-feature"),
+            Path::new("/home/user/repo-tom-new-feature")
         );
     }
+
+    #[test]
+    fn test_branch_changes() {
+        if !is_available() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("workbench-branch-changes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        init_test_repo(&dir);
+        let git = |args: &[&str]| test_git(&dir, args);
+        git(&["checkout", "-q", "-b", "wb/feature"]);
+
+        assert_eq!(branch_changes(&dir, "main", "wb/feature").unwrap().log, Vec::<String>::new());
+
+        std::fs::write(dir.join("notes.txt"), "hello\n").unwrap();
+        git(&["add", "notes.txt"]);
+        git(&["commit", "-q", "-m", "Add notes"]);
+        let changes = branch_changes(&dir, "main", "wb/feature").unwrap();
+        assert_eq!(changes.log.len(), 1);
+        assert!(changes.log[0].ends_with("Add notes"));
+        assert!(changes.diff_stat.iter().any(|line| line.contains("notes.txt")));
+
+        let error = branch_changes(&dir, "origin/main", "wb/feature").unwrap_err();
+        assert!(error.contains("origin/main"), "{}", error);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_head() {
+        if !is_available() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("workbench-head-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        init_test_repo(&dir);
+        let git = |args: &[&str]| test_git(&dir, args);
+        git(&["branch", "wb/feature"]);
+        assert_eq!(head(&dir), Some(Head::Branch("main".to_string())));
+
+        checkout_branch(&dir, "wb/feature").unwrap();
+        assert_eq!(head(&dir), Some(Head::Branch("wb/feature".to_string())));
+        assert!(checkout_branch(&dir, "wb/missing").is_err());
+
+        git(&["checkout", "-q", "--detach"]);
+        let Some(Head::Detached(commit)) = head(&dir) else { panic!("expected a detached HEAD") };
+        assert!(!commit.is_empty());
+        assert_eq!(Head::Detached("abc1234".to_string()).to_string(), "detached @ abc1234");
+        assert_eq!(head(&dir.join("missing")), None);
+
+        // HEAD moves are seen through a linked worktree's `.git` file too
+        assert!(head_modified(&dir).is_some());
+        let linked = dir.join("linked");
+        create_worktree(&dir, "wb/linked", &linked, None).unwrap();
+        let before = head_modified(&linked).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        test_git(&linked, &["checkout", "-q", "--detach"]);
+        assert!(head_modified(&linked).unwrap() > before);
+        assert_eq!(head_modified(&dir.join("missing")), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_exclude_locally() {
+        if !is_available() {
+            return;
+        }
+        let root = std::env::temp_dir().join(format!("workbench-exclude-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (repo, worktree) = (root.join("repo"), root.join("repo-wb-notes"));
+        std::fs::create_dir_all(&repo).unwrap();
+        init_test_repo(&repo);
+        create_worktree(&repo, "wb/notes", &worktree, None).unwrap();
+
+        let notes = worktree.join(".workbench-notes.md");
+        std::fs::write(&notes, "todo\n").unwrap();
+        assert_eq!(get_dirty_status(&worktree).unwrap().untracked, 1);
+        exclude_locally(&notes).unwrap();
+        exclude_locally(&notes).unwrap();
+        assert!(!get_dirty_status(&worktree).unwrap().is_dirty());
+        // One line in the repo's shared exclude file covers the main checkout too
+        std::fs::write(repo.join(".workbench-notes.md"), "").unwrap();
+        assert!(!get_dirty_status(&repo).unwrap().is_dirty());
+        let exclude = std::fs::read_to_string(repo.join(".git/info/exclude")).unwrap();
+        assert_eq!(exclude.lines().filter(|line| *line == "/.workbench-notes.md").count(), 1);
+
+        // Files outside a repo are left alone
+        exclude_locally(&root.join("notes.md")).unwrap();
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_pull_and_delete_branch() {
+        if !is_available() {
+            return;
+        }
+        let root = std::env::temp_dir().join(format!("workbench-pull-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let (origin, clone) = (root.join("origin"), root.join("clone"));
+        std::fs::create_dir_all(&origin).unwrap();
+        init_test_repo(&origin);
+        let git = test_git;
+        git(&root, &["clone", "-q", "origin", "clone"]);
+
+        // Pull fast-forwards the checked-out branch
+        std::fs::write(origin.join("notes.txt"), "hello\n").unwrap();
+        git(&origin, &["add", "notes.txt"]);
+        git(&origin, &["commit", "-q", "-m", "Add notes"]);
+        pull(&clone).unwrap();
+        assert!(clone.join("notes.txt").exists());
+        assert_eq!(pull(&clone).unwrap(), "Already up to date.");
+
+        // A branch's commits count as unpushed until a remote has them
+        git(&clone, &["checkout", "-q", "-b", "wb/feature"]);
+        git(&clone, &["commit", "-q", "--allow-empty", "-m", "Feature"]);
+        assert_eq!(unpushed_commits(&clone, "wb/feature"), Ok(1));
+        git(&clone, &["push", "-q", "origin", "wb/feature"]);
+        assert_eq!(unpushed_commits(&clone, "wb/feature"), Ok(0));
+        assert!(unpushed_commits(&clone, "wb/missing").is_err());
+
+        // A merge is found from the branch tip; before it, there is none
+        let tip = short_commit(&clone, "wb/feature").unwrap();
+        git(&clone, &["checkout", "-q", "main"]);
+        assert_eq!(merge_commit(&clone, &tip), None);
+        git(&clone, &["merge", "-q", "--no-ff", "-m", "Merge feature", "wb/feature"]);
+        assert_eq!(merge_commit(&clone, &tip), short_commit(&clone, "HEAD"));
+
+        // Unmerged branches need force
+        git(&clone, &["branch", "wb/other", "HEAD~1"]);
+        git(&clone, &["checkout", "-q", "wb/other"]);
+        git(&clone, &["commit", "-q", "--allow-empty", "-m", "Other"]);
+        git(&clone, &["checkout", "-q", "main"]);
+        assert!(delete_branch(&clone, "wb/other", false).is_err());
+        delete_branch(&clone, "wb/other", true).unwrap();
+        delete_branch(&clone, "wb/feature", false).unwrap();
+        assert!(!branch_exists(&clone, "wb/feature"));
+        assert!(delete_branch(&clone, "wb/feature", true).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
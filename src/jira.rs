@@ -0,0 +1,143 @@
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// How long a Jira request may take before giving up
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+static TICKET_KEY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Z][A-Z0-9_]+-\d+$").expect("ticket key pattern"));
+
+/// Connection settings, from the project's Jira settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JiraConfig {
+    pub base_url: String,
+    /// Environment variable holding the API token
+    pub token_env: String,
+    /// Set for Jira Cloud (basic auth with email + token); Server/DC uses a bearer token
+    pub user: Option<String>,
+}
+
+impl JiraConfig {
+    /// Build from the settings; `auth` is `TOKEN_ENV` or `email:TOKEN_ENV`.
+    /// None when either is empty, which keeps the integration inert
+    pub fn from_settings(base_url: &str, auth: &str) -> Option<Self> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        let auth = auth.trim();
+        if base_url.is_empty() || auth.is_empty() {
+            return None;
+        }
+        let (user, token_env) = match auth.rsplit_once(':') {
+            Some((user, env)) => (Some(user.to_string()), env),
+            None => (None, auth),
+        };
+        Some(Self {
+            base_url: base_url.to_string(),
+            token_env: token_env.to_string(),
+            user,
+        })
+    }
+}
+
+/// The parts of an issue workbench fills in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JiraIssue {
+    pub key: String,
+    pub summary: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct IssueResponse {
+    key: String,
+    fields: IssueFields,
+}
+
+#[derive(Deserialize)]
+struct IssueFields {
+    #[serde(default)]
+    summary: String,
+}
+
+/// Normalize user input to a ticket key like `PROJ-123`, if it looks like one
+pub fn parse_ticket_key(input: &str) -> Option<String> {
+    let key = input.trim().to_uppercase();
+    TICKET_KEY.is_match(&key).then_some(key)
+}
+
+/// Fetch an issue (blocking; call from a background thread)
+pub fn fetch_issue(config: &JiraConfig, key: &str) -> Result<JiraIssue> {
+    let token = std::env::var(&config.token_env)
+        .map_err(|_| eyre!("${} is not set", config.token_env))?;
+
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let request = client
+        .get(format!("{}/rest/api/2/issue/{}", config.base_url, key))
+        .query(&[("fields", "summary")])
+        .header("Accept", "application/json");
+    let request = match config.user {
+        Some(ref user) => request.basic_auth(user, Some(token)),
+        None => request.bearer_auth(token),
+    };
+
+    let response = request.send().map_err(|e| {
+        tracing::error!(key, error = %e, "jira request failed");
+        eyre!("Network error: {}", e)
+    })?;
+    let status = response.status();
+    if !status.is_success() {
+        tracing::error!(key, status = %status, "jira returned an error");
+        return Err(eyre!(describe_status(status.as_u16(), key)));
+    }
+
+    let body: IssueResponse = response.json().map_err(|e| eyre!("Unexpected Jira response: {}", e))?;
+    Ok(JiraIssue {
+        url: format!("{}/browse/{}", config.base_url, body.key),
+        key: body.key,
+        summary: body.fields.summary,
+    })
+}
+
+fn describe_status(status: u16, key: &str) -> String {
+    match status {
+        401 => "401: Jira rejected the token".to_string(),
+        403 => format!("403: no permission to view {}", key),
+        404 => format!("404: {} not found", key),
+        other => format!("Jira returned {}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ticket_key() {
+        assert_eq!(parse_ticket_key(" proj-123 ").as_deref(), Some("PROJ-123"));
+        assert_eq!(parse_ticket_key("AB2-7").as_deref(), Some("AB2-7"));
+        assert_eq!(parse_ticket_key("PROJ-"), None);
+        assert_eq!(parse_ticket_key("https://acme.atlassian.net/browse/PROJ-1"), None);
+    }
+
+    #[test]
+    fn test_config_from_settings() {
+        assert_eq!(JiraConfig::from_settings("", "JIRA_TOKEN"), None);
+        assert_eq!(JiraConfig::from_settings("https://jira.acme.com", ""), None);
+        assert_eq!(
+            JiraConfig::from_settings("https://acme.atlassian.net/", "me@acme.com:JIRA_TOKEN"),
+            Some(JiraConfig {
+                base_url: "https://acme.atlassian.net".to_string(),
+                token_env: "JIRA_TOKEN".to_string(),
+                user: Some("me@acme.com".to_string()),
+            })
+        );
+        assert_eq!(
+            JiraConfig::from_settings("https://jira.acme.com", "JIRA_PAT").unwrap().user,
+            None
+        );
+    }
+}
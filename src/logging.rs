@@ -0,0 +1,159 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Output};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use tracing::Level;
+
+/// Environment variable selecting the log level (e.g. `WORKBENCH_LOG=debug`)
+pub const LOG_ENV: &str = "WORKBENCH_LOG";
+
+/// The log is rotated to `workbench.log.1` once it grows past this size
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Command output longer than this is truncated in the log
+const MAX_LOGGED_OUTPUT: usize = 500;
+
+/// Location of the log file: `~/.local/state/workbench/workbench.log`
+pub fn log_path() -> Option<PathBuf> {
+    Some(crate::paths::state_dir()?.join("workbench.log"))
+}
+
+/// Install a file logger if `WORKBENCH_LOG` names a level. Logs never go to
+/// stdout/stderr, which would corrupt the TUI
+pub fn init() {
+    let Ok(level) = std::env::var(LOG_ENV) else { return };
+    let Ok(level) = Level::from_str(level.trim()) else { return };
+    let Some(path) = log_path() else { return };
+    let Ok(writer) = RotatingFile::open(path) else { return };
+
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(false)
+        .with_writer(Mutex::new(writer))
+        .try_init();
+}
+
+/// Last `count` error-level lines from the log (including the rotated file)
+pub fn recent_errors(path: &Path, count: usize) -> Vec<String> {
+    let mut lines: Vec<String> = [rotated_path(path), path.to_path_buf()]
+        .iter()
+        .filter_map(|p| std::fs::read_to_string(p).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter(|line| line.contains(" ERROR "))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let skip = lines.len().saturating_sub(count);
+    lines.drain(..skip);
+    lines
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    path.with_extension("log.1")
+}
+
+/// Append-only log file that moves itself aside once it exceeds `MAX_LOG_BYTES`
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        std::fs::rename(&self.path, rotated_path(&self.path))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size + buf.len() as u64 > MAX_LOG_BYTES {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// `Command::output`/`Command::status` with the command line, duration and result logged
+pub trait Traced {
+    fn traced_output(&mut self) -> io::Result<Output>;
+    fn traced_status(&mut self) -> io::Result<ExitStatus>;
+}
+
+impl Traced for Command {
+    fn traced_output(&mut self) -> io::Result<Output> {
+        let start = Instant::now();
+        let result = self.output();
+        match &result {
+            Ok(output) => tracing::debug!(
+                command = %command_line(self),
+                elapsed_ms = start.elapsed().as_millis() as u64,
+                status = %output.status,
+                stdout = %truncate(&String::from_utf8_lossy(&output.stdout)),
+                stderr = %truncate(&String::from_utf8_lossy(&output.stderr)),
+                "ran command"
+            ),
+            Err(e) => tracing::error!(command = %command_line(self), error = %e, "failed to run command"),
+        }
+        result
+    }
+
+    fn traced_status(&mut self) -> io::Result<ExitStatus> {
+        let start = Instant::now();
+        let result = self.status();
+        match &result {
+            Ok(status) => tracing::debug!(
+                command = %command_line(self),
+                elapsed_ms = start.elapsed().as_millis() as u64,
+                status = %status,
+                "ran command"
+            ),
+            Err(e) => tracing::error!(command = %command_line(self), error = %e, "failed to run command"),
+        }
+        result
+    }
+}
+
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Trim logged output to `MAX_LOGGED_OUTPUT` chars, keeping the tail
+pub fn truncate(text: &str) -> String {
+    let text = text.trim();
+    let count = text.chars().count();
+    if count <= MAX_LOGGED_OUTPUT {
+        return text.to_string();
+    }
+    let tail: String = text.chars().skip(count - MAX_LOGGED_OUTPUT).collect();
+    format!("...{}", tail)
+}
@@ -1,43 +1,461 @@
+mod agent;
 mod ai;
 mod app;
+mod blockers;
+mod card;
+mod changes;
+mod context;
+mod cli;
 mod db;
+mod db_handle;
+mod desktop;
+mod due;
 mod git;
+mod jira;
+mod logging;
+mod notes;
+mod paths;
+mod redact;
+mod repo_config;
+#[cfg(unix)]
+mod serve;
+mod startup;
+mod status;
+mod theme;
 mod tmux;
+mod transcript;
 mod tui;
 mod ui;
 
 use app::AppAction;
-use color_eyre::Result;
+use cli::Command;
+use color_eyre::{eyre::eyre, Result};
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<()> {
     color_eyre::install()?;
+    logging::init();
 
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(path) = cli::take_db_path(&mut args)? {
+        db::Database::set_db_path(Path::new(&path))?;
+    }
+    match cli::parse(&args)? {
+        Command::Tui { write_status, no_color, overview, serve } => {
+            run_tui(write_status, no_color, overview, serve)
+        }
+        Command::Status { json } => print_status(json),
+        Command::Add { name, allow_duplicate, start } => add_session(&name, allow_duplicate, start),
+        Command::Attach { query } => attach(&query),
+        Command::Move { query, column, no_autostart } => move_session(&query, &column, no_autostart),
+        Command::Sessions { names } => list_sessions(names),
+        Command::AiUsage { weekly } => print_ai_usage(weekly),
+        Command::Time { weeks_ago } => print_time_report(weeks_ago),
+        Command::FieldsExport { path } => export_fields(path.as_deref()),
+        Command::FieldsImport { path } => import_fields(&path),
+        Command::Init { force } => write_repo_config(force),
+        Command::Backup { path } => backup(path.as_deref()),
+        Command::Restore { path } => {
+            let safety = db::Database::restore_from(Path::new(&path))?;
+            println!("Restored database from {}", path);
+            println!("Previous database saved to {}", safety.display());
+            Ok(())
+        }
+        Command::Doctor { verbose } => {
+            doctor(verbose);
+            Ok(())
+        }
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+    }
+}
+
+fn print_status(json: bool) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let sessions = db.list_sessions(project.id)?;
+    let overrides = db.list_status_overrides(project.id)?;
+    let snapshot = status::Snapshot::collect(&project, &sessions, &overrides, db.local_today()?);
+
+    if json {
+        println!("{}", snapshot.to_json()?);
+    } else {
+        println!("{}", snapshot.to_text());
+    }
+    Ok(())
+}
+
+fn print_ai_usage(weekly: bool) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let totals = db.ai_usage_totals(project.id, weekly, if weekly { 12 } else { 14 })?;
+    if totals.is_empty() {
+        println!("No AI calls recorded for {}", project.name);
+        return Ok(());
+    }
+
+    println!("{:<12}  {:>5}  {:>10}  {:>9}", if weekly { "Week of" } else { "Day" }, "Calls", "Tokens", "Cost");
+    for total in &totals {
+        let unpriced = match total.unpriced {
+            0 => String::new(),
+            n => format!("  ({} without cost)", n),
+        };
+        println!(
+            "{:<12}  {:>5}  {:>10}  {:>9}{}",
+            total.period,
+            total.calls,
+            total.tokens,
+            format!("${:.4}", total.cost_usd),
+            unpriced
+        );
+    }
+    let cost: f64 = totals.iter().map(|t| t.cost_usd).sum();
+    let calls: i64 = totals.iter().map(|t| t.calls).sum();
+    let tokens: i64 = totals.iter().map(|t| t.tokens).sum();
+    println!("{:<12}  {:>5}  {:>10}  {:>9}", "Total", calls, tokens, format!("${:.4}", cost));
+    Ok(())
+}
+
+fn print_time_report(weeks_ago: u32) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let report = db.time_report(project.id, weeks_ago)?;
+    println!("{}: time in use, week of {}", project.name, report.week_of);
+    println!("Approximate: while the board runs, each poll counts for a session whose terminal");
+    println!("a tmux client shows and that had output in the last 5 minutes. Set \"Track time\"");
+    println!("to off in the project settings to stop recording.");
+    println!();
+    if report.totals.is_empty() {
+        println!("No time recorded");
+        return Ok(());
+    }
+    let width = report.totals.iter().map(|t| t.name.chars().count()).max().unwrap_or(0) + 1;
+    for total in &report.totals {
+        println!("{:<width$} {:>7}", format!("{}:", total.name), app::format_time_spent(total.seconds), width = width);
+    }
+    let seconds: i64 = report.totals.iter().map(|t| t.seconds).sum();
+    println!("{:<width$} {:>7}", "Total:", app::format_time_spent(seconds), width = width);
+    Ok(())
+}
+
+fn export_fields(path: Option<&str>) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let json = db::fields_to_json(&db.list_fields(project.id)?)?;
+    match path {
+        Some(path) => {
+            std::fs::write(path, json + "\n")?;
+            eprintln!("Exported the fields of {} to {}", project.name, path);
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn import_fields(path: &str) -> Result<()> {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(path).map_err(|e| eyre!("Could not read {}: {}", path, e))?
+    };
+    let definitions = db::fields_from_json(&text)?;
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let import = db.import_fields(project.id, &definitions)?;
+    println!("Added {} field(s) to {}", import.created.len(), project.name);
+    if !import.skipped.is_empty() {
+        println!("Skipped (already present): {}", import.skipped.join(", "));
+    }
+    Ok(())
+}
+
+fn write_repo_config(force: bool) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let path = project.path.join(repo_config::FILE_NAME);
+    if path.exists() && !force {
+        return Err(eyre!("{} already exists (--force replaces it)", path.display()));
+    }
+    let config = repo_config::from_project(
+        &db.list_fields(project.id)?,
+        &db.list_status_overrides(project.id)?,
+        app::repo_config_settings(&db, project.id)?,
+    );
+    std::fs::write(&path, repo_config::to_toml(&config)?)?;
+    println!(
+        "Wrote {} fields, {} columns and {} settings of {} to {}",
+        config.fields.len(),
+        config.columns.len(),
+        config.settings.len(),
+        project.name,
+        path.display()
+    );
+    println!("Commit it; boards opening this repo offer to apply what differs.");
+    Ok(())
+}
+
+fn backup(path: Option<&str>) -> Result<()> {
+    let db = db::Database::new()?;
+    let written = match path {
+        Some(path) => {
+            db.backup_to(Path::new(path))?;
+            PathBuf::from(path)
+        }
+        None => db.backup_timestamped()?,
+    };
+    println!("Backed up database to {}", written.display());
+    Ok(())
+}
+
+fn doctor(verbose: bool) {
+    let check = |ok: bool| if ok { "ok" } else { "missing" };
+    println!("tmux:        {}", check(tmux::is_available()));
+    println!("git:         {}", check(git::is_available()));
+    if ai::is_available() {
+        println!("claude:      ok ({})", ai::binary());
+    } else {
+        println!("claude:      missing (install it or set {} to its path)", ai::BINARY_ENV);
+    }
+    match db::Database::db_path_with_source() {
+        Ok((path, source)) => {
+            let source = match source {
+                db::DbPathSource::Flag => " (from --db-path)",
+                db::DbPathSource::Env => &format!(" (from {})", db::DB_ENV),
+                db::DbPathSource::Default => "",
+            };
+            println!("database:    {}{}", path.display(), source);
+        }
+        Err(e) => println!("database:    {}", e),
+    }
+    match db::Database::backups_dir() {
+        Ok(path) => println!("backups:     {}", path.display()),
+        Err(e) => println!("backups:     {}", e),
+    }
+    match db::Database::transcripts_dir() {
+        Ok(path) => println!("transcripts: {}", path.display()),
+        Err(e) => println!("transcripts: {}", e),
+    }
+    println!("status file: {}", status::status_dir().join("project-<id>.json").display());
+
+    let Some(log_path) = logging::log_path() else {
+        println!("log:         no state directory");
+        return;
+    };
+    let enabled = std::env::var(logging::LOG_ENV).is_ok();
+    println!(
+        "log:         {}{}",
+        log_path.display(),
+        if enabled { "" } else { " (disabled, set WORKBENCH_LOG=debug)" }
+    );
+
+    if verbose {
+        let errors = logging::recent_errors(&log_path, 10);
+        if errors.is_empty() {
+            println!("No errors in the log");
+        } else {
+            println!("Recent errors:");
+            for line in errors {
+                println!("  {}", line);
+            }
+        }
+    }
+}
+
+fn attach(query: &str) -> Result<()> {
+    if !tmux::is_available() {
+        return Err(eyre!("tmux is not installed"));
+    }
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let sessions = db.list_sessions(project.id)?;
+    let session = find_session(&sessions, query)?;
+
+    let tmux_name = app::ensure_tmux_session(&db, &tmux::SystemTmux, &git::SystemGit, &project, session)?;
+    db.record_attach(project.id, session.id)?;
+    Err(tmux::exec_attach(&tmux_name))
+}
+
+fn find_session<'a>(sessions: &'a [db::Session], query: &str) -> Result<&'a db::Session> {
+    match app::match_session(sessions, query) {
+        app::SessionMatch::One(session) => Ok(session),
+        app::SessionMatch::Ambiguous(candidates) => {
+            let list: Vec<String> = candidates.iter().map(|s| format!("  {:>4}  {}", s.id, s.name)).collect();
+            Err(eyre!("\"{}\" matches several sessions:\n{}", query, list.join("\n")))
+        }
+        app::SessionMatch::NotFound => Err(eyre!("No session matches \"{}\"", query)),
+    }
+}
+
+fn move_session(query: &str, column: &str, no_autostart: bool) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, repo_root) = app::current_project(&db)?;
+    let sessions = db.list_sessions(project.id)?;
+    let session = find_session(&sessions, query)?;
+
+    let overrides = db.list_status_overrides(project.id)?;
+    let column_labels: Vec<(db::Status, String)> = db::Status::all()
+        .iter()
+        .map(|s| (*s, db::resolve_status_label(&overrides, *s).to_string()))
+        .collect();
+    let status = app::resolve_column(&column_labels, column.trim()).ok_or_else(|| {
+        let names: Vec<&str> = column_labels.iter().map(|(_, label)| label.as_str()).collect();
+        eyre!("Unknown column \"{}\" (columns: {})", column, names.join(", "))
+    })?;
+
+    db.update_session_status(session.id, status)?;
+    println!("Moved {} to {}", session.name, db::resolve_status_label(&overrides, status));
+
+    if status == db::Status::InProgress && session.status != status && !no_autostart && app::autostart_enabled(&db, project.id)? {
+        let tmux_name = app::autostart_session(&db, &tmux::SystemTmux, &git::SystemGit, &project, repo_root.as_deref(), session)?;
+        println!("Started tmux session {}", tmux_name);
+    }
+    Ok(())
+}
+
+fn list_sessions(names_only: bool) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, _) = app::current_project(&db)?;
+    let overrides = db.list_status_overrides(project.id)?;
+    for session in db.list_sessions(project.id)? {
+        if names_only {
+            println!("{}", session.name);
+        } else {
+            let status = db::resolve_status_label(&overrides, session.status);
+            println!("{:>4}  {:<12}  {}", session.id, status, session.name);
+        }
+    }
+    Ok(())
+}
+
+fn add_session(name: &str, allow_duplicate: bool, start: bool) -> Result<()> {
+    let db = db::Database::new()?;
+    let (project, repo_root) = app::current_project(&db)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(eyre!("Session name cannot be empty"));
+    }
+
+    let sessions = db.list_sessions(project.id)?;
+    if !allow_duplicate && app::has_session_named(&sessions, name) {
+        return Err(eyre!(
+            "A session named \"{}\" already exists (use --allow-duplicate to create it anyway)",
+            name
+        ));
+    }
+
+    if start {
+        let (session, tmux_name) = app::create_and_start_session(&db, &tmux::SystemTmux, &git::SystemGit, &project, repo_root.as_deref(), name)?;
+        db.record_attach(project.id, session.id)?;
+        return Err(tmux::exec_attach(&tmux_name));
+    }
+
+    let (session, worktree_error) =
+        app::create_session_with_worktree(&db, &git::SystemGit, project.id, repo_root.as_deref(), name, db::Status::Planned)?;
+    if let Some(e) = worktree_error {
+        eprintln!("Worktree error: {}", e);
+    }
+    println!("Created session \"{}\" ({})", session.name, session.id);
+    Ok(())
+}
+
+/// Serve the board's project on its socket, saying where (or why not) in the status line.
+/// The server stays on that project when the board switches to another
+#[cfg(unix)]
+fn start_server(app: &mut app::App) -> Option<serve::Server> {
+    let started = (|| {
+        let path = serve::socket_path(app.project.id)
+            .ok_or_else(|| color_eyre::eyre::eyre!("No runtime or state directory for the socket"))?;
+        let board = serve::Board {
+            project: app.project.clone(),
+            repo_root: app.repo_root.clone(),
+            tmux: std::sync::Arc::new(tmux::SystemTmux),
+            git: std::sync::Arc::new(git::SystemGit),
+        };
+        serve::Server::start(&path, db_handle::DbHandle::spawn(db::Database::new()?), board)
+    })();
+    match started {
+        Ok(server) => {
+            app.status_message = Some(format!("Serving on {}", server.path().display()));
+            Some(server)
+        }
+        Err(e) => {
+            app.status_message = Some(format!("Not serving: {}", e));
+            None
+        }
+    }
+}
+
+fn run_tui(write_status: bool, no_color: bool, overview: bool, serve: bool) -> Result<()> {
+    #[cfg(not(unix))]
+    if serve {
+        return Err(color_eyre::eyre::eyre!("--serve needs Unix sockets"));
+    }
+    let _instance = db::InstanceMarker::create(&db::Database::db_path()?)?;
     let mut terminal = tui::init()?;
     let mut app = app::App::new()?;
+    app.write_status_file = write_status;
+    app.no_color = no_color;
+    app.theme = theme::Theme::detect(no_color);
+    // `kill -HUP` (or `-USR1`) reloads the settings, like Ctrl+R. A closed terminal still
+    // ends the board: reading input from it fails
+    #[cfg(unix)]
+    for signal in [signal_hook::consts::SIGHUP, signal_hook::consts::SIGUSR1] {
+        signal_hook::flag::register(signal, std::sync::Arc::clone(&app.reload_signal))?;
+    }
+    app.write_status_snapshot();
+    // Kept for the whole run: dropping it removes the socket
+    #[cfg(unix)]
+    let _server = if serve { start_server(&mut app) } else { None };
+    if overview {
+        app.open_overview()?;
+    }
 
     while !app.should_quit {
-        terminal.draw(|frame| ui::render(&app, frame))?;
+        app.refresh_peek_capture();
+        if app.should_draw() {
+            terminal.draw(|frame| ui::render(&app, frame))?;
+            app.frame_drawn();
+        }
+        if let Some(title) = app.title_change() {
+            tui::set_title(&title)?;
+        }
         match app.handle_events()? {
             AppAction::None => {}
             AppAction::AttachTmux(name) => {
+                let view_state = app.view_state();
                 if tmux::is_inside_tmux() {
                     // Inside tmux: switch-client returns immediately, app keeps running
                     let _ = tmux::attach_session(&name);
-                    app.refresh_tmux_sessions();
                 } else {
                     // Outside tmux: attach blocks until detach
                     tui::restore()?;
                     drop(terminal);
 
+                    let attached_at = db::unix_now();
                     let _ = tmux::attach_session(&name);
 
                     terminal = tui::init()?;
-                    app.refresh_tmux_sessions();
+                    app.forget_title();
+                    app.credit_attached_time(&name, attached_at);
+                }
+                // The agent may have changed the session (branch, fields) while attached
+                app.refresh_external()?;
+                app.restore_view_state(view_state);
+                app.needs_redraw = true;
+            }
+            AppAction::AttachReadOnly(name) => {
+                // Another client shows the session; watch it from a new window here
+                if let Err(e) = tmux::open_read_only_window(&name) {
+                    app.status_message = Some(format!("Failed to open read-only view: {}", e));
                 }
+                app.needs_redraw = true;
             }
         }
     }
 
+    app.release_lock();
     tui::restore()?;
     Ok(())
 }
@@ -0,0 +1,100 @@
+use color_eyre::Result;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Where a session's notes file goes when the project doesn't say
+pub const DEFAULT_PATH: &str = "{worktree}/.workbench-notes.md";
+
+/// The notes file of a session: `template` with `{worktree}` and `{id}` filled in. A
+/// relative result is taken as relative to the worktree
+pub fn path_for(template: &str, worktree: &Path, session_id: i64) -> Result<PathBuf, String> {
+    let template = template.trim();
+    let template = if template.is_empty() { DEFAULT_PATH } else { template };
+    let mut path = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        path.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(format!("Unclosed {{ in \"{}\"", template));
+        };
+        match &rest[start + 1..start + end] {
+            "worktree" => path.push_str(&worktree.to_string_lossy()),
+            "id" => path.push_str(&session_id.to_string()),
+            other => return Err(format!("Unknown placeholder {{{}}} (use {{worktree}} or {{id}})", other)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    path.push_str(rest);
+    if path.ends_with('/') {
+        return Err("The path names a directory, not a file".to_string());
+    }
+    Ok(worktree.join(path))
+}
+
+/// Modification time of a file in unix milliseconds
+pub fn mtime(path: &Path) -> Option<i64> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+/// The notes file's text and modification time; None when there is no file to read
+pub fn read(path: &Path) -> Option<(String, i64)> {
+    let text = std::fs::read_to_string(path).ok()?;
+    Some((text, mtime(path)?))
+}
+
+/// Write the notes file, returning its new modification time
+pub fn write(path: &Path, text: &str) -> Result<i64> {
+    std::fs::write(path, text)?;
+    mtime(path).ok_or_else(|| color_eyre::eyre::eyre!("{} vanished after writing it", path.display()))
+}
+
+/// What an open notes editor does about the file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskChange {
+    /// The file is as the editor last saw it, or gone (saving writes it again)
+    None,
+    /// Changed on disk and not in the editor: show the new text
+    Reload,
+    /// Changed on both sides: keep the editor's text and say so
+    Conflict,
+}
+
+/// Compare the file's modification time now with the one the editor last synced with
+pub fn disk_change(synced_mtime: Option<i64>, disk_mtime: Option<i64>, edited: bool) -> DiskChange {
+    match disk_mtime {
+        Some(disk) if Some(disk) != synced_mtime => {
+            if edited {
+                DiskChange::Conflict
+            } else {
+                DiskChange::Reload
+            }
+        }
+        _ => DiskChange::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_for() {
+        let worktree = Path::new("/work/wb-fix-login");
+        assert_eq!(path_for("", worktree, 7).unwrap(), PathBuf::from("/work/wb-fix-login/.workbench-notes.md"));
+        assert_eq!(path_for("docs/NOTES.md", worktree, 7).unwrap(), PathBuf::from("/work/wb-fix-login/docs/NOTES.md"));
+        assert_eq!(path_for("/tmp/notes-{id}.md", worktree, 7).unwrap(), PathBuf::from("/tmp/notes-7.md"));
+        assert!(path_for("{worktree}/{name}.md", worktree, 7).unwrap_err().contains("{name}"));
+        assert!(path_for("{worktree/notes.md", worktree, 7).is_err());
+        assert!(path_for("{worktree}/", worktree, 7).is_err());
+    }
+
+    #[test]
+    fn test_disk_change() {
+        assert_eq!(disk_change(Some(100), Some(100), true), DiskChange::None);
+        assert_eq!(disk_change(Some(100), None, false), DiskChange::None);
+        assert_eq!(disk_change(Some(100), Some(200), false), DiskChange::Reload);
+        assert_eq!(disk_change(None, Some(200), false), DiskChange::Reload);
+        assert_eq!(disk_change(Some(100), Some(200), true), DiskChange::Conflict);
+    }
+}
@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+/// Workbench's directory for state that can be thrown away (logs, transcripts):
+/// `~/.local/state/workbench`, or under the data dir where there is no state dir (e.g. macOS)
+pub fn state_dir() -> Option<PathBuf> {
+    Some(dirs::state_dir().or_else(dirs::data_dir)?.join("workbench"))
+}
+
+/// A path from raw bytes, as git and tmux print it or the database stores it. On Unix the
+/// bytes are kept as they are, so a directory whose name isn't UTF-8 still works; elsewhere
+/// paths are always Unicode and invalid bytes are replaced
+pub fn from_bytes(bytes: &[u8]) -> PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// A path printed by a command on its own line: the trailing newline is dropped, but
+/// nothing else, since leading or trailing spaces are part of the name
+pub fn from_output_line(stdout: &[u8]) -> Option<PathBuf> {
+    let line = stdout.strip_suffix(b"\n").unwrap_or(stdout);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    (!line.is_empty()).then(|| from_bytes(line))
+}
@@ -0,0 +1,463 @@
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::app::STATUS_COLORS;
+use crate::db::{ColumnSort, Database, Field, FieldDefinition, StatusOverride, Status};
+
+/// The board setup a repo carries for everyone who opens it, at its root
+pub const FILE_NAME: &str = ".workbench.toml";
+
+/// Written above the settings by `workbench init`
+const HEADER: &str = "\
+# Board setup for workbench, offered to everyone who opens this repo.
+# Written by `workbench init`; edit freely. Leave out what the board may choose itself.
+
+";
+
+/// What `.workbench.toml` holds. Everything is optional; what it leaves out is left alone
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Project settings by key, e.g. `base_branch = "main"`
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    #[serde(default, rename = "column")]
+    pub columns: Vec<ColumnConfig>,
+    #[serde(default, rename = "field")]
+    pub fields: Vec<FieldDefinition>,
+}
+
+/// A column's display, as Settings > Columns sets it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnConfig {
+    /// `planned`, `in_progress`, `review` or `done`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checklist: Option<Vec<String>>,
+    /// `created` or `activity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+pub fn parse(text: &str) -> Result<RepoConfig, String> {
+    toml::from_str(text).map_err(|e| e.message().to_string())
+}
+
+/// The file `workbench init` writes
+pub fn to_toml(config: &RepoConfig) -> Result<String> {
+    Ok(format!("{}{}", HEADER, toml::to_string(config)?))
+}
+
+/// Fingerprint of the file's text, stored once it was offered so an unchanged file
+/// isn't offered again. FNV-1a, so it stays the same across builds
+pub fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// A project's current setup as a file: its non-empty `settings`, its fields and the
+/// column overrides that change anything
+pub fn from_project(fields: &[Field], overrides: &[StatusOverride], settings: BTreeMap<String, String>) -> RepoConfig {
+    let columns = Status::all()
+        .iter()
+        .filter_map(|status| overrides.iter().find(|o| o.status == *status))
+        .map(|o| ColumnConfig {
+            status: o.status.as_str().to_string(),
+            label: Some(o.label.clone()).filter(|l| !l.trim().is_empty()),
+            color: Some(o.color.clone()).filter(|c| !c.is_empty()),
+            checklist: Some(o.checklist.clone()).filter(|c| !c.is_empty()),
+            sort: Some(o.sort.as_str().to_string()).filter(|_| o.sort != ColumnSort::default()),
+        })
+        .filter(|c| c.label.is_some() || c.color.is_some() || c.checklist.is_some() || c.sort.is_some())
+        .collect();
+    RepoConfig {
+        settings: settings.into_iter().filter(|(_, value)| !value.trim().is_empty()).collect(),
+        columns,
+        fields: fields.iter().map(Into::into).collect(),
+    }
+}
+
+/// One thing applying the file would do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    CreateField(FieldDefinition),
+    UpdateField(i64, FieldDefinition),
+    Setting(String, String),
+    Label(Status, String),
+    Color(Status, String),
+    Checklist(Status, Vec<String>),
+    Sort(Status, ColumnSort),
+}
+
+/// An entry of the apply prompt
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// What it touches, e.g. `Field "Ticket"` or `Setting base_branch`
+    pub target: String,
+    /// The value the file asks for, as shown
+    pub value: String,
+    /// The project's own value when it was customized and differs: a conflict, only
+    /// applied when ticked
+    pub local: Option<String>,
+    pub action: Action,
+}
+
+impl Change {
+    pub fn is_conflict(&self) -> bool {
+        self.local.is_some()
+    }
+}
+
+/// What applying `config` would change in a project with these fields, column overrides
+/// and `settings` (every key the file may set, "" when unset), plus entries of the file
+/// that can't be applied. Values equal to the project's are left out; a value replacing
+/// an unset one is a plain change, one replacing a customized value is a conflict
+pub fn plan(
+    config: &RepoConfig,
+    fields: &[Field],
+    overrides: &[StatusOverride],
+    settings: &BTreeMap<String, String>,
+) -> (Vec<Change>, Vec<String>) {
+    let mut changes = Vec::new();
+    let mut problems = Vec::new();
+
+    for (key, value) in &config.settings {
+        let Some(local) = settings.get(key) else {
+            problems.push(format!("Unknown setting {}", key));
+            continue;
+        };
+        let (local, value) = (local.trim(), value.trim());
+        if local != value {
+            changes.push(Change {
+                target: format!("Setting {}", key),
+                value: shown(value),
+                local: Some(shown(local)).filter(|_| !local.is_empty()),
+                action: Action::Setting(key.clone(), value.to_string()),
+            });
+        }
+    }
+
+    for column in &config.columns {
+        let Some(status) = Status::from_str(&column.status) else {
+            problems.push(format!("Unknown column {} (use planned, in_progress, review or done)", column.status));
+            continue;
+        };
+        let own = overrides.iter().find(|o| o.status == status);
+        let target = |what: &str| format!("Column {} {}", status.as_str(), what);
+        if let Some(label) = column.label.as_deref().map(str::trim) {
+            let local = own.map_or("", |o| o.label.trim());
+            if local != label {
+                changes.push(Change {
+                    target: target("label"),
+                    value: shown(label),
+                    local: Some(shown(local)).filter(|_| !local.is_empty()),
+                    action: Action::Label(status, label.to_string()),
+                });
+            }
+        }
+        if let Some(color) = column.color.as_deref().map(str::trim) {
+            let local = own.map_or("", |o| o.color.as_str());
+            if !STATUS_COLORS.contains(&color) {
+                problems.push(format!("Unknown color {} for column {}", color, status.as_str()));
+            } else if local != color {
+                changes.push(Change {
+                    target: target("color"),
+                    value: shown(color),
+                    local: Some(shown(local)).filter(|_| !local.is_empty()),
+                    action: Action::Color(status, color.to_string()),
+                });
+            }
+        }
+        if let Some(ref checklist) = column.checklist {
+            let items: Vec<String> = checklist.iter().map(|i| i.trim().to_string()).filter(|i| !i.is_empty()).collect();
+            let local = own.map_or(&[][..], |o| &o.checklist[..]);
+            if local != items.as_slice() {
+                changes.push(Change {
+                    target: target("checklist"),
+                    value: shown(&items.join(", ")),
+                    local: Some(local.join(", ")).filter(|_| !local.is_empty()),
+                    action: Action::Checklist(status, items),
+                });
+            }
+        }
+        if let Some(sort) = column.sort.as_deref().map(str::trim) {
+            let local = own.map_or(ColumnSort::default(), |o| o.sort);
+            if !matches!(sort, "created" | "activity") {
+                problems.push(format!("Unknown sort {} for column {} (use created or activity)", sort, status.as_str()));
+            } else if ColumnSort::from_str(sort) != local {
+                changes.push(Change {
+                    target: target("sort"),
+                    value: sort.to_string(),
+                    local: Some(local.as_str().to_string()).filter(|_| local != ColumnSort::default()),
+                    action: Action::Sort(status, ColumnSort::from_str(sort)),
+                });
+            }
+        }
+    }
+
+    for (idx, definition) in config.fields.iter().enumerate() {
+        let name = definition.name.trim();
+        if name.is_empty() {
+            problems.push(format!("Field {} has no name", idx + 1));
+            continue;
+        }
+        let definition = FieldDefinition { name: name.to_string(), ..definition.clone() };
+        match fields.iter().find(|f| f.name.eq_ignore_ascii_case(name)) {
+            None => changes.push(Change {
+                target: format!("Field \"{}\"", name),
+                value: describe(&definition),
+                local: None,
+                action: Action::CreateField(definition),
+            }),
+            Some(field) => {
+                let own = FieldDefinition::from(field);
+                let wanted = FieldDefinition { name: field.name.clone(), ..definition };
+                if own != wanted {
+                    changes.push(Change {
+                        target: format!("Field \"{}\"", field.name),
+                        value: describe(&wanted),
+                        local: Some(describe(&own)),
+                        action: Action::UpdateField(field.id, wanted),
+                    });
+                }
+            }
+        }
+    }
+
+    (changes, problems)
+}
+
+/// A value as the prompt shows it
+fn shown(value: &str) -> String {
+    if value.is_empty() { "(default)".to_string() } else { value.to_string() }
+}
+
+/// A field's description and whatever differs from a new field's flags
+fn describe(definition: &FieldDefinition) -> String {
+    let mut parts = vec![match definition.description.trim() {
+        "" => "no description".to_string(),
+        description => format!("\"{}\"", description.lines().next().unwrap_or_default()),
+    }];
+    for (off, what) in [(!definition.visible, "hidden"), (!definition.in_ai, "not in AI"), (!definition.in_edit, "not edited")] {
+        if off {
+            parts.push(what.to_string());
+        }
+    }
+    if definition.wrap {
+        parts.push("wrapped".to_string());
+    }
+    parts.join(", ")
+}
+
+/// Make the changes in the project's database
+pub fn apply(db: &Database, project_id: i64, actions: &[Action]) -> Result<()> {
+    for action in actions {
+        match action {
+            Action::CreateField(definition) => {
+                db.import_fields(project_id, std::slice::from_ref(definition))?;
+            }
+            Action::UpdateField(field_id, definition) => {
+                db.update_field(*field_id, &definition.name, &definition.description)?;
+                let Some(field) = db.list_fields(project_id)?.into_iter().find(|f| f.id == *field_id) else { continue };
+                if field.visible != definition.visible {
+                    db.toggle_field_visibility(field.id)?;
+                }
+                if field.in_ai != definition.in_ai {
+                    db.toggle_field_in_ai(field.id)?;
+                }
+                if field.in_edit != definition.in_edit {
+                    db.toggle_field_in_edit(field.id)?;
+                }
+                if field.wrap != definition.wrap {
+                    db.toggle_field_wrap(field.id)?;
+                }
+            }
+            Action::Setting(key, value) => db.set_project_setting(project_id, key, value)?,
+            Action::Label(status, label) => db.set_status_label(project_id, *status, label)?,
+            Action::Color(status, color) => db.set_status_color(project_id, *status, color)?,
+            Action::Checklist(status, items) => db.set_status_checklist(project_id, *status, items)?,
+            Action::Sort(status, sort) => db.set_status_sort(project_id, *status, *sort)?,
+        }
+    }
+    Ok(())
+}
+
+/// What was applied, e.g. `2 fields, 1 setting, 1 column`
+pub fn summary(actions: &[Action]) -> String {
+    let fields = actions.iter().filter(|a| matches!(a, Action::CreateField(_) | Action::UpdateField(..))).count();
+    let settings = actions.iter().filter(|a| matches!(a, Action::Setting(..))).count();
+    let mut columns: Vec<Status> = actions
+        .iter()
+        .filter_map(|a| match a {
+            Action::Label(status, _) | Action::Color(status, _) | Action::Checklist(status, _) | Action::Sort(status, _) => {
+                Some(*status)
+            }
+            _ => None,
+        })
+        .collect();
+    columns.dedup();
+    [(fields, "field"), (settings, "setting"), (columns.len(), "column")]
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, what)| format!("{} {}{}", n, what, if *n == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(id: i64, name: &str, description: &str) -> Field {
+        Field { description: description.to_string(), ..Field::fixture(id, name) }
+    }
+
+    fn settings(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    const FILE: &str = r#"
+[settings]
+branch_prefix = "team/"
+base_branch = "main"
+stale_days = "7"
+colour = "red"
+
+[[column]]
+status = "review"
+label = "Code review"
+checklist = ["Tests pass", "PR open"]
+
+[[column]]
+status = "blocked"
+
+[[field]]
+name = "ticket"
+description = "Ticket key"
+
+[[field]]
+name = "Notes"
+wrap = true
+"#;
+
+    #[test]
+    fn test_plan() {
+        let config = parse(FILE).unwrap();
+        let fields = vec![field(1, "Ticket", "Jira key")];
+        let overrides = vec![StatusOverride {
+            status: Status::Review,
+            label: "Review".to_string(),
+            color: String::new(),
+            checklist: Vec::new(),
+            sort: ColumnSort::Created,
+            new_card_fields: Vec::new(),
+        }];
+        let local = settings(&[("branch_prefix", ""), ("base_branch", "develop"), ("stale_days", "7")]);
+        let (changes, problems) = plan(&config, &fields, &overrides, &local);
+
+        assert_eq!(problems, vec![
+            "Unknown setting colour".to_string(),
+            "Unknown column blocked (use planned, in_progress, review or done)".to_string(),
+        ]);
+        let summary: Vec<(&str, &str, Option<&str>)> =
+            changes.iter().map(|c| (c.target.as_str(), c.value.as_str(), c.local.as_deref())).collect();
+        assert_eq!(summary, vec![
+            // Unchanged stale_days is left out; the customized base branch is a conflict
+            ("Setting base_branch", "main", Some("develop")),
+            ("Setting branch_prefix", "team/", None),
+            ("Column review label", "Code review", Some("Review")),
+            ("Column review checklist", "Tests pass, PR open", None),
+            ("Field \"Ticket\"", "\"Ticket key\"", Some("\"Jira key\"")),
+            ("Field \"Notes\"", "no description, wrapped", None),
+        ]);
+        // The existing field keeps its own spelling
+        assert_eq!(
+            changes[4].action,
+            Action::UpdateField(1, FieldDefinition {
+                name: "Ticket".to_string(),
+                description: "Ticket key".to_string(),
+                visible: true,
+                in_ai: true,
+                in_edit: true,
+                wrap: false,
+            })
+        );
+        assert_eq!(summary_of(&changes), "2 fields, 2 settings, 1 column");
+
+        // Applied, there's nothing left to offer
+        let mut notes = field(2, "Notes", "");
+        notes.wrap = true;
+        let fields = vec![field(1, "Ticket", "Ticket key"), notes];
+        let overrides = vec![StatusOverride {
+            status: Status::Review,
+            label: "Code review".to_string(),
+            color: String::new(),
+            checklist: vec!["Tests pass".to_string(), "PR open".to_string()],
+            sort: ColumnSort::Created,
+            new_card_fields: Vec::new(),
+        }];
+        let local = settings(&[("branch_prefix", "team/"), ("base_branch", "main"), ("stale_days", "7")]);
+        assert!(plan(&config, &fields, &overrides, &local).0.is_empty());
+
+        assert!(parse("[settings]\nstartup = 1").is_err());
+        assert!(parse("[[columns]]\nstatus = \"done\"").is_err());
+    }
+
+    fn summary_of(changes: &[Change]) -> String {
+        summary(&changes.iter().map(|c| c.action.clone()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_from_project_round_trip() {
+        let mut fields = vec![field(1, "Ticket", "Ticket key"), field(2, "Notes", "Free text")];
+        fields[1].in_ai = false;
+        let overrides = vec![
+            StatusOverride {
+                status: Status::Done,
+                label: String::new(),
+                color: "green".to_string(),
+                checklist: Vec::new(),
+                sort: ColumnSort::Activity,
+                new_card_fields: Vec::new(),
+            },
+            StatusOverride {
+                status: Status::Planned,
+                label: String::new(),
+                color: String::new(),
+                checklist: Vec::new(),
+                sort: ColumnSort::Created,
+                new_card_fields: Vec::new(),
+            },
+        ];
+        let config = from_project(&fields, &overrides, settings(&[("base_branch", "main"), ("notes_path", "")]));
+        assert_eq!(config.settings, settings(&[("base_branch", "main")]));
+        // Only the column that changes anything
+        assert_eq!(config.columns, vec![ColumnConfig {
+            status: "done".to_string(),
+            color: Some("green".to_string()),
+            sort: Some("activity".to_string()),
+            ..Default::default()
+        }]);
+
+        let text = to_toml(&config).unwrap();
+        assert!(text.starts_with("# Board setup"));
+        assert_eq!(parse(&text).unwrap(), config);
+        let local = settings(&[("base_branch", "main"), ("notes_path", "")]);
+        assert!(plan(&config, &fields, &overrides, &local).0.is_empty());
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert_eq!(hash(FILE), hash(FILE));
+        assert_ne!(hash(FILE), hash(&FILE.replace("main", "master")));
+    }
+}
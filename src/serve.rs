@@ -0,0 +1,551 @@
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use crate::app;
+use crate::db::{self, Project, Session, Status};
+use crate::db_handle::{DbHandle, Queries};
+use crate::git::GitClient;
+use crate::tmux::{self, TmuxClient};
+
+/// Version of the socket protocol, in every request and response; bump on breaking changes
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Where the board of `project_id` listens with `--serve`: in the user's runtime dir
+/// (else the state dir), in a directory only they can enter
+pub fn socket_path(project_id: i64) -> Option<PathBuf> {
+    let dir = dirs::runtime_dir().map(|dir| dir.join("workbench")).or_else(crate::paths::state_dir)?;
+    Some(dir.join(format!("project-{}.sock", project_id)))
+}
+
+/// What requests act on: one project, with the board's tmux and git
+pub struct Board {
+    pub project: Project,
+    pub repo_root: Option<PathBuf>,
+    pub tmux: Arc<dyn TmuxClient>,
+    pub git: Arc<dyn GitClient>,
+}
+
+/// The listening socket; dropping it removes the socket file
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Server {
+    /// Listen on `path` and answer each client on a thread of its own, through `db`, a
+    /// connection apart from the board's so the board sees the writes as external changes
+    pub fn start(path: &Path, db: DbHandle, board: Board) -> Result<Self> {
+        let listener = bind(path)?;
+        let board = Arc::new(board);
+        thread::Builder::new().name("workbench-serve".to_string()).spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                let (db, board) = (db.clone(), Arc::clone(&board));
+                thread::spawn(move || serve_client(stream, &db, &board));
+            }
+        })?;
+        Ok(Self { path: path.to_path_buf() })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Bind the socket, readable and writable by the user alone. It goes in a directory only
+/// the user can enter, so it's never reachable before its own mode is set; a directory
+/// that already exists (the status file shares it) is made private first, and one that
+/// belongs to someone else is refused. A socket left behind by a board that is gone is
+/// replaced; one that still answers belongs to a running board
+fn bind(path: &Path) -> Result<UnixListener> {
+    if let Some(dir) = path.parent() {
+        std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+            .map_err(|e| eyre!("Could not make {} private: {}", dir.display(), e))?;
+        if std::fs::metadata(dir)?.permissions().mode() & 0o777 != 0o700 {
+            return Err(eyre!("{} is open to other users", dir.display()));
+        }
+    }
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(eyre!("Another board already serves {}", path.display()));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path).map_err(|e| eyre!("Could not listen on {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Answer one request per line until the client hangs up
+fn serve_client(stream: UnixStream, db: &DbHandle, board: &Arc<Board>) {
+    let Ok(mut writer) = stream.try_clone() else { return };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Only the queries go to the database thread; git and tmux run on this one
+        let response = handle_line(db, board, &line);
+        if writeln!(writer, "{}", response).is_err() {
+            break;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    v: u32,
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    v: u32,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<Failure>,
+}
+
+/// Why a request failed: a stable `code` for programs and a message for people
+#[derive(Debug, Serialize)]
+struct Failure {
+    code: &'static str,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<color_eyre::Report> for Failure {
+    fn from(e: color_eyre::Report) -> Self {
+        Failure::new("internal", e.to_string())
+    }
+}
+
+/// The response line to a request line
+fn handle_line(db: &dyn Queries, board: &Board, line: &str) -> String {
+    let (id, outcome) = match serde_json::from_str::<Request>(line) {
+        Err(e) => (Value::Null, Err(Failure::new("bad_request", e.to_string()))),
+        Ok(request) if request.v != PROTOCOL_VERSION => (
+            request.id,
+            Err(Failure::new(
+                "unsupported_version",
+                format!("Version {} requested; this board speaks version {}", request.v, PROTOCOL_VERSION),
+            )),
+        ),
+        Ok(request) => (request.id, dispatch(db, board, &request.method, request.params)),
+    };
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(failure) => (None, Some(failure)),
+    };
+    serde_json::to_string(&Response { v: PROTOCOL_VERSION, id, result, error }).expect("responses serialize")
+}
+
+fn dispatch(db: &dyn Queries, board: &Board, method: &str, params: Value) -> Result<Value, Failure> {
+    match method {
+        "list_sessions" => to_value(list_sessions(db, board)),
+        "create_session" => to_value(create_session(db, board, parse_params(params)?)),
+        "move_session" => to_value(move_session(db, board, parse_params(params)?)),
+        "get_field_values" => to_value(get_field_values(db, board, parse_params(params)?)),
+        "attach_info" => to_value(attach_info(db, board, parse_params(params)?)),
+        other => Err(Failure::new("unknown_method", format!("No method {}", other))),
+    }
+}
+
+fn to_value<T: Serialize>(result: Result<T, Failure>) -> Result<Value, Failure> {
+    result.map(|result| serde_json::to_value(result).expect("results serialize"))
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, Failure> {
+    let params = if params.is_null() { Value::Object(Default::default()) } else { params };
+    serde_json::from_value(params).map_err(|e| Failure::new("bad_params", e.to_string()))
+}
+
+/// A card as the protocol shows it
+#[derive(Serialize)]
+struct SessionInfo {
+    id: i64,
+    name: String,
+    /// `planned`, `in_progress`, `review` or `done`
+    status: &'static str,
+    /// The column's label in this project
+    column: String,
+    branch: Option<String>,
+    worktree: Option<String>,
+    tmux_session: Option<String>,
+    owner: Option<String>,
+    pinned: bool,
+    /// `YYYY-MM-DD`
+    due: Option<String>,
+}
+
+impl SessionInfo {
+    fn new(session: &Session, labels: &[(Status, String)]) -> Self {
+        let column = labels.iter().find(|(status, _)| *status == session.status).map_or("", |(_, label)| label);
+        Self {
+            id: session.id,
+            name: session.name.clone(),
+            status: session.status.as_str(),
+            column: column.to_string(),
+            branch: session.branch_name.clone(),
+            worktree: session.checkout_path.as_ref().map(|path| path.to_string_lossy().into_owned()),
+            tmux_session: session.tmux_window.clone(),
+            owner: session.owner.clone(),
+            pinned: session.pinned,
+            due: session.due.map(crate::due::format),
+        }
+    }
+}
+
+/// Each column with its label in the project
+fn column_labels(db: &dyn Queries, project_id: i64) -> Result<Vec<(Status, String)>> {
+    let overrides = db.list_status_overrides(project_id)?;
+    Ok(Status::all().iter().map(|s| (*s, db::resolve_status_label(&overrides, *s).to_string())).collect())
+}
+
+fn find_session(db: &dyn Queries, board: &Board, id: i64) -> Result<Session, Failure> {
+    db.get_live_session(board.project.id, id)?
+        .ok_or_else(|| Failure::new("not_found", format!("No session {} in {}", id, board.project.name)))
+}
+
+fn resolve_column(labels: &[(Status, String)], column: &str) -> Result<Status, Failure> {
+    app::resolve_column(labels, column.trim()).ok_or_else(|| {
+        let names: Vec<&str> = labels.iter().map(|(_, label)| label.as_str()).collect();
+        Failure::new("invalid", format!("Unknown column \"{}\" (columns: {})", column, names.join(", ")))
+    })
+}
+
+#[derive(Serialize)]
+struct Sessions {
+    sessions: Vec<SessionInfo>,
+}
+
+fn list_sessions(db: &dyn Queries, board: &Board) -> Result<Sessions, Failure> {
+    let labels = column_labels(db, board.project.id)?;
+    let sessions = db.list_sessions(board.project.id)?;
+    Ok(Sessions { sessions: sessions.iter().map(|s| SessionInfo::new(s, &labels)).collect() })
+}
+
+#[derive(Deserialize)]
+struct CreateParams {
+    name: String,
+    /// Column key or label; Planned when left out
+    #[serde(default)]
+    column: Option<String>,
+    #[serde(default)]
+    allow_duplicate: bool,
+}
+
+#[derive(Serialize)]
+struct Created {
+    session: SessionInfo,
+    /// Why the worktree couldn't be made; the card exists without one
+    worktree_error: Option<String>,
+}
+
+fn create_session(db: &dyn Queries, board: &Board, params: CreateParams) -> Result<Created, Failure> {
+    let name = params.name.trim();
+    if name.is_empty() {
+        return Err(Failure::new("invalid", "Session name cannot be empty"));
+    }
+    let labels = column_labels(db, board.project.id)?;
+    let status = match params.column {
+        Some(ref column) => resolve_column(&labels, column)?,
+        None => Status::Planned,
+    };
+    if !params.allow_duplicate && app::has_session_named(&db.list_sessions(board.project.id)?, name) {
+        return Err(Failure::new("duplicate", format!("A session named \"{}\" already exists", name)));
+    }
+    let (session, worktree_error) =
+        app::create_session_with_worktree(db, &*board.git, board.project.id, board.repo_root.as_deref(), name, status)?;
+    Ok(Created { session: SessionInfo::new(&session, &labels), worktree_error })
+}
+
+#[derive(Deserialize)]
+struct MoveParams {
+    id: i64,
+    column: String,
+}
+
+#[derive(Serialize)]
+struct Moved {
+    session: SessionInfo,
+    /// The tmux session started because the project auto-starts In Progress
+    started: Option<String>,
+    /// Why auto-start failed; the move is kept
+    start_error: Option<String>,
+}
+
+/// Move a card like `workbench move`: auto-start applies, entry checklists don't
+fn move_session(db: &dyn Queries, board: &Board, params: MoveParams) -> Result<Moved, Failure> {
+    let session = find_session(db, board, params.id)?;
+    let labels = column_labels(db, board.project.id)?;
+    let status = resolve_column(&labels, &params.column)?;
+    db.update_session_status(session.id, status)?;
+
+    let (mut started, mut start_error) = (None, None);
+    if status == Status::InProgress && session.status != status && app::autostart_enabled(db, board.project.id)? {
+        match app::autostart_session(db, &*board.tmux, &*board.git, &board.project, board.repo_root.as_deref(), &session) {
+            Ok(name) => started = Some(name),
+            Err(e) => start_error = Some(e.to_string()),
+        }
+    }
+    let session = find_session(db, board, session.id)?;
+    Ok(Moved { session: SessionInfo::new(&session, &labels), started, start_error })
+}
+
+#[derive(Deserialize)]
+struct SessionParams {
+    id: i64,
+}
+
+#[derive(Serialize)]
+struct FieldValue {
+    name: String,
+    value: String,
+    /// `manual`, `ai`, `import` or `default`; None when the field has no value
+    source: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct FieldValues {
+    fields: Vec<FieldValue>,
+}
+
+/// Every field of the project in display order, empty ones included
+fn get_field_values(db: &dyn Queries, board: &Board, params: SessionParams) -> Result<FieldValues, Failure> {
+    let session = find_session(db, board, params.id)?;
+    let values = db.get_all_session_field_values(session.id)?;
+    let fields = db
+        .list_fields(board.project.id)?
+        .into_iter()
+        .map(|field| {
+            let value = values.iter().find(|(id, _, _)| *id == field.id);
+            FieldValue {
+                name: field.name,
+                value: value.map(|(_, value, _)| value.clone()).unwrap_or_default(),
+                source: value.map(|(_, _, source)| source.as_str()),
+            }
+        })
+        .collect();
+    Ok(FieldValues { fields })
+}
+
+#[derive(Serialize)]
+struct AttachInfo {
+    /// The tmux session linked to the card, running or not
+    tmux_session: Option<String>,
+    running: bool,
+    /// Arguments that attach a terminal to it, when it runs
+    attach_command: Option<Vec<String>>,
+    /// Where a new terminal for the card starts
+    directory: String,
+}
+
+fn attach_info(db: &dyn Queries, board: &Board, params: SessionParams) -> Result<AttachInfo, Failure> {
+    let session = find_session(db, board, params.id)?;
+    let running = session.tmux_window.as_deref().is_some_and(|name| board.tmux.session_exists(name));
+    let attach_command = session
+        .tmux_window
+        .as_deref()
+        .filter(|_| running)
+        .map(|name| ["tmux", "attach-session", "-t", &tmux::session_target(name)].map(String::from).to_vec());
+    let directory = session.checkout_path.as_deref().filter(|path| path.is_dir()).unwrap_or(&board.project.path);
+    Ok(AttachInfo {
+        tmux_session: session.tmux_window.clone(),
+        running,
+        attach_command,
+        directory: directory.to_string_lossy().into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A server for a fresh project on a socket of its own, and its database
+    fn serve(name: &str) -> (Server, DbHandle, Project) {
+        let dir = std::env::temp_dir().join(format!("wb-serve-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let db = db::Database::open_in_memory().unwrap();
+        let project = db.get_or_create_project("api", Path::new("/nonexistent/api")).unwrap();
+        let db = DbHandle::spawn(db);
+        let board = Board {
+            project: project.clone(),
+            repo_root: None,
+            tmux: Arc::new(tmux::SystemTmux),
+            git: Arc::new(crate::git::SystemGit),
+        };
+        let server = Server::start(&dir.join("board.sock"), db.clone(), board).unwrap();
+        (server, db, project)
+    }
+
+    struct Client {
+        reader: BufReader<UnixStream>,
+        writer: UnixStream,
+    }
+
+    impl Client {
+        fn connect(server: &Server) -> Self {
+            let writer = UnixStream::connect(server.path()).unwrap();
+            Self { reader: BufReader::new(writer.try_clone().unwrap()), writer }
+        }
+
+        fn send_line(&mut self, line: &str) -> Value {
+            writeln!(self.writer, "{}", line).unwrap();
+            let mut response = String::new();
+            self.reader.read_line(&mut response).unwrap();
+            serde_json::from_str(&response).unwrap()
+        }
+
+        fn call(&mut self, method: &str, params: Value) -> Value {
+            self.send_line(&json!({ "v": 1, "id": 7, "method": method, "params": params }).to_string())
+        }
+    }
+
+    #[test]
+    fn test_sessions_over_the_socket() {
+        let (server, db, project) = serve("sessions");
+        let mode = std::fs::metadata(server.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mut client = Client::connect(&server);
+
+        let response = client.call("list_sessions", Value::Null);
+        assert_eq!(response, json!({ "v": 1, "id": 7, "result": { "sessions": [] } }));
+
+        let response = client.call("create_session", json!({ "name": "Review #412", "column": "review" }));
+        let created = &response["result"]["session"];
+        assert_eq!(created["name"], "Review #412");
+        assert_eq!(created["status"], "review");
+        assert_eq!(created["column"], "Review");
+        assert_eq!(response["result"]["worktree_error"], Value::Null);
+        let id = created["id"].as_i64().unwrap();
+
+        let response = client.call("create_session", json!({ "name": "review #412" }));
+        assert_eq!(response["error"]["code"], "duplicate");
+        let response = client.call("create_session", json!({ "name": "Fix login" }));
+        assert_eq!(response["result"]["session"]["status"], "planned");
+
+        // Moves land in the database the board reads
+        let response = client.call("move_session", json!({ "id": id, "column": "Done" }));
+        assert_eq!(response["result"]["session"]["status"], "done");
+        assert_eq!(response["result"]["started"], Value::Null);
+        assert_eq!(db.get_live_session(project.id, id).unwrap().unwrap().status, Status::Done);
+        let response = client.call("move_session", json!({ "id": id, "column": "Shipped" }));
+        assert_eq!(response["error"]["code"], "invalid");
+        let response = client.call("move_session", json!({ "id": 999, "column": "Done" }));
+        assert_eq!(response["error"]["code"], "not_found");
+
+        let names: Vec<Value> = client.call("list_sessions", json!({}))["result"]["sessions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].clone())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&json!("Review #412")) && names.contains(&json!("Fix login")));
+    }
+
+    #[test]
+    fn test_fields_and_attach_info() {
+        let (server, db, project) = serve("fields");
+        let project_id = project.id;
+        let session = db.call(move |db| {
+            let session = db.create_session(project_id, "Fix login").unwrap();
+            let ticket = db.create_field(project_id, "Ticket", "").unwrap();
+            db.create_field(project_id, "PR URL", "").unwrap();
+            db.set_session_field_value(session.id, ticket.id, "ABC-123", db::ValueSource::Ai).unwrap();
+            session
+        }).unwrap();
+        let mut client = Client::connect(&server);
+
+        let response = client.call("get_field_values", json!({ "id": session.id }));
+        assert_eq!(response["result"], json!({ "fields": [
+            { "name": "Ticket", "value": "ABC-123", "source": "ai" },
+            { "name": "PR URL", "value": "", "source": null },
+        ] }));
+
+        // Not started: nothing to attach to, and a terminal would start in the project
+        let response = client.call("attach_info", json!({ "id": session.id }));
+        assert_eq!(response["result"], json!({
+            "tmux_session": null,
+            "running": false,
+            "attach_command": null,
+            "directory": "/nonexistent/api",
+        }));
+        let response = client.call("attach_info", json!({}));
+        assert_eq!(response["error"]["code"], "bad_params");
+    }
+
+    #[test]
+    fn test_socket_dir_made_private() {
+        let dir = std::env::temp_dir().join(format!("wb-serve-private-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::DirBuilder::new().mode(0o755).create(&dir).unwrap();
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let listener = bind(&dir.join("board.sock")).unwrap();
+        assert_eq!(std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        assert_eq!(std::fs::metadata(dir.join("board.sock")).unwrap().permissions().mode() & 0o777, 0o600);
+        drop(listener);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_protocol_errors() {
+        let (server, db, project) = serve("errors");
+        let mut client = Client::connect(&server);
+
+        let response = client.send_line("not json");
+        assert_eq!(response["v"], 1);
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], "bad_request");
+
+        let response = client.send_line(r#"{"v": 2, "id": "a", "method": "list_sessions"}"#);
+        assert_eq!(response["id"], "a");
+        assert_eq!(response["error"]["code"], "unsupported_version");
+        assert_eq!(response.get("result"), None);
+
+        let response = client.call("delete_everything", Value::Null);
+        assert_eq!(response["error"]["code"], "unknown_method");
+
+        // The connection stays usable after errors, and another board can't take the socket
+        assert!(client.call("list_sessions", Value::Null)["result"].is_object());
+        let board = Board {
+            project,
+            repo_root: None,
+            tmux: Arc::new(tmux::SystemTmux),
+            git: Arc::new(crate::git::SystemGit),
+        };
+        assert!(Server::start(server.path(), db, board).is_err());
+
+        // Dropping the server removes its socket
+        let path = server.path().to_path_buf();
+        drop(server);
+        assert!(!path.exists());
+    }
+}
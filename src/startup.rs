@@ -0,0 +1,139 @@
+/// Placeholders a startup command template can use, as `{name}`
+pub const PLACEHOLDERS: &[&str] = &["name", "branch", "path", "project", "id"];
+
+/// What the placeholders of a startup command stand for, for one session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandValues {
+    pub name: String,
+    pub branch: String,
+    pub path: String,
+    pub project: String,
+    pub id: String,
+}
+
+impl CommandValues {
+    /// Stand-ins for previewing a template when no session is selected
+    pub fn sample() -> Self {
+        Self {
+            name: "Fix login redirect".to_string(),
+            branch: "wb/fix-login-redirect".to_string(),
+            path: "/path/to/worktree".to_string(),
+            project: "project".to_string(),
+            id: "1".to_string(),
+        }
+    }
+
+    fn get(&self, placeholder: &str) -> Option<&str> {
+        match placeholder {
+            "name" => Some(&self.name),
+            "branch" => Some(&self.branch),
+            "path" => Some(&self.path),
+            "project" => Some(&self.project),
+            "id" => Some(&self.id),
+            _ => None,
+        }
+    }
+}
+
+/// Fill in a startup command template. Values are shell-quoted where needed, so a
+/// session name with spaces stays one argument; `{{` and `}}` are literal braces.
+/// Fails on an unknown placeholder (naming the close match, if any) or a stray brace
+pub fn expand(template: &str, values: &CommandValues) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(format!("Unclosed {{{}", name)),
+                    }
+                }
+                let Some(value) = values.get(&name) else {
+                    return Err(unknown_placeholder(&name));
+                };
+                out.push_str(&shell_quote(value));
+            }
+            '}' => return Err("Stray } (write }} for a literal brace)".to_string()),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn unknown_placeholder(name: &str) -> String {
+    let known = PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ");
+    match PLACEHOLDERS.iter().find(|p| edit_distance(p, name) <= 2) {
+        Some(close) => format!("Unknown placeholder {{{}}}: did you mean {{{}}}?", name, close),
+        None => format!("Unknown placeholder {{{}}} (use {})", name, known),
+    }
+}
+
+/// Levenshtein distance, enough to spot a typo in a short placeholder name
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (diagonal + usize::from(ca != *cb)).min(row[j] + 1).min(above + 1);
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Quote `value` for sh unless it is made only of characters the shell leaves alone
+pub fn shell_quote(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./:@%+=,".contains(c);
+    if !value.is_empty() && value.chars().all(plain) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let values = CommandValues::sample();
+        assert_eq!(expand("claude", &values).unwrap(), "claude");
+        assert_eq!(
+            expand("git checkout {branch} && claude -p {name}", &values).unwrap(),
+            "git checkout wb/fix-login-redirect && claude -p 'Fix login redirect'"
+        );
+        assert_eq!(expand("echo {{id}} {id}", &values).unwrap(), "echo {id} 1");
+        let quoted = CommandValues { name: "it's".to_string(), ..CommandValues::sample() };
+        assert_eq!(expand("echo {name}", &quoted).unwrap(), "echo 'it'\\''s'");
+        let empty = CommandValues { branch: String::new(), ..CommandValues::sample() };
+        assert_eq!(expand("echo {branch}", &empty).unwrap(), "echo ''");
+    }
+
+    #[test]
+    fn test_expand_rejects_bad_templates() {
+        let values = CommandValues::sample();
+        assert_eq!(
+            expand("git checkout {brnach}", &values).unwrap_err(),
+            "Unknown placeholder {brnach}: did you mean {branch}?"
+        );
+        assert!(expand("echo {session}", &values).unwrap_err().contains("use {name}, {branch}"));
+        assert!(expand("echo {name", &values).unwrap_err().starts_with("Unclosed"));
+        assert!(expand("echo name}", &values).unwrap_err().starts_with("Stray"));
+    }
+}
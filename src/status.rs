@@ -0,0 +1,231 @@
+use color_eyre::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::agent;
+use crate::db::{self, Project, Session, Status, StatusOverride};
+use crate::due;
+use crate::tmux;
+
+/// Schema version of the snapshot, bump on breaking changes
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Read-only summary of a project's board for scripts and statuslines
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub v: u32,
+    pub project: ProjectInfo,
+    pub columns: Vec<ColumnCount>,
+    pub waiting: Vec<WaitingSession>,
+    pub agenda: Agenda,
+    /// Open cards with a due date, soonest first
+    pub due: Vec<DueSession>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectInfo {
+    /// Names the project's `--serve` socket
+    pub id: i64,
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnCount {
+    pub status: &'static str,
+    pub label: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WaitingSession {
+    pub id: i64,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// Open cards due today and overdue
+#[derive(Debug, Clone, Serialize)]
+pub struct Agenda {
+    pub today: usize,
+    pub overdue: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DueSession {
+    pub id: i64,
+    pub name: String,
+    /// `YYYY-MM-DD`
+    pub due: String,
+    pub overdue: bool,
+}
+
+impl Snapshot {
+    /// Build a snapshot from already-loaded sessions and the set of waiting tmux sessions;
+    /// `today` is the local day, counted like due dates
+    pub fn build(
+        project: &Project,
+        sessions: &[Session],
+        overrides: &[StatusOverride],
+        waiting_tmux: &HashSet<String>,
+        today: i64,
+    ) -> Self {
+        let columns = Status::all()
+            .iter()
+            .map(|status| ColumnCount {
+                status: status.as_str(),
+                label: db::resolve_status_label(overrides, *status).to_string(),
+                count: sessions.iter().filter(|s| s.status == *status).count(),
+            })
+            .collect();
+
+        let waiting = sessions
+            .iter()
+            .filter(|s| s.tmux_window.as_ref().is_some_and(|name| waiting_tmux.contains(name)))
+            .map(|s| WaitingSession {
+                id: s.id,
+                name: s.name.clone(),
+                owner: s.owner.clone(),
+            })
+            .collect();
+
+        let mut open_due: Vec<(&Session, i64)> = sessions
+            .iter()
+            .filter(|s| s.status != Status::Done)
+            .filter_map(|s| Some((s, s.due?)))
+            .collect();
+        open_due.sort_by_key(|(_, day)| *day);
+        let (due_today, overdue) = due::agenda(open_due.iter().map(|(_, day)| *day), today);
+        let due = open_due
+            .iter()
+            .map(|(s, day)| DueSession { id: s.id, name: s.name.clone(), due: due::format(*day), overdue: *day < today })
+            .collect();
+
+        Self {
+            v: SNAPSHOT_VERSION,
+            project: ProjectInfo {
+                id: project.id,
+                name: project.name.clone(),
+                path: project.path.to_string_lossy().into_owned(),
+            },
+            columns,
+            waiting,
+            agenda: Agenda { today: due_today, overdue },
+            due,
+        }
+    }
+
+    /// Build a snapshot by querying tmux directly (used outside the TUI)
+    pub fn collect(project: &Project, sessions: &[Session], overrides: &[StatusOverride], today: i64) -> Self {
+        let running = tmux::list_project_sessions(project.id);
+        let waiting_tmux: HashSet<String> = sessions
+            .iter()
+            .filter_map(|s| s.tmux_window.as_ref().filter(|name| running.contains(name)).map(|name| (name, s)))
+            .filter(|(name, s)| tmux::is_waiting_for_input(name, &agent::for_session(s)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        Self::build(project, sessions, overrides, &waiting_tmux, today)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Human-readable summary for `workbench status` without `--json`
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!("{} ({})", self.project.name, self.project.path)];
+        for column in &self.columns {
+            lines.push(format!("  {}: {}", column.label, column.count));
+        }
+        if !self.waiting.is_empty() {
+            lines.push(format!("Waiting for input ({}):", self.waiting.len()));
+            for session in &self.waiting {
+                match session.owner {
+                    Some(ref owner) => lines.push(format!("  {} ({})", session.name, owner)),
+                    None => lines.push(format!("  {}", session.name)),
+                }
+            }
+        }
+        if self.agenda.today > 0 || self.agenda.overdue > 0 {
+            lines.push(format!("Due today: {}, overdue: {}", self.agenda.today, self.agenda.overdue));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Environment variable with the terminal window title format; `off` leaves the title alone
+pub const TITLE_ENV: &str = "WORKBENCH_TITLE";
+
+/// Title shown while the board is open, e.g. `workbench: api 2⏳`
+pub const DEFAULT_TITLE_FORMAT: &str = "workbench: {project} {badge}";
+
+/// The title format from `WORKBENCH_TITLE`, the default when unset, or None when it's
+/// `off` or empty (some multiplexers fight over the title)
+pub fn title_format_from_env() -> Option<String> {
+    match std::env::var(TITLE_ENV) {
+        Ok(format) if format.trim().is_empty() || format.trim().eq_ignore_ascii_case("off") => None,
+        Ok(format) => Some(format),
+        Err(_) => Some(DEFAULT_TITLE_FORMAT.to_string()),
+    }
+}
+
+/// Fill in a title format: `{project}` is the project name, `{sessions}` the number of
+/// running sessions, `{waiting}` how many wait for input and `{badge}` that count with ⏳,
+/// or nothing when none wait. Trailing spaces and colons left by an empty badge are dropped
+pub fn window_title(format: &str, project: &str, sessions: usize, waiting: usize) -> String {
+    let badge = if waiting > 0 { format!("{}⏳", waiting) } else { String::new() };
+    format
+        .replace("{project}", project)
+        .replace("{sessions}", &sessions.to_string())
+        .replace("{waiting}", &waiting.to_string())
+        .replace("{badge}", &badge)
+        .trim_end_matches([' ', ':'])
+        .to_string()
+}
+
+/// Directory of the status files: `$XDG_RUNTIME_DIR/workbench`
+/// Falls back to the system temp dir where no runtime dir exists (e.g. macOS)
+pub fn status_dir() -> PathBuf {
+    dirs::runtime_dir().unwrap_or_else(std::env::temp_dir).join("workbench")
+}
+
+/// Location of a project's status file, named like its `--serve` socket so boards
+/// open on different projects don't overwrite each other
+pub fn status_file_path(project_id: i64) -> PathBuf {
+    status_dir().join(format!("project-{}.json", project_id))
+}
+
+/// Write the snapshot to its project's status file atomically (write to a temp file named
+/// for this process, then rename), so two boards on one project never share a temp file
+pub fn write_status_file(snapshot: &Snapshot) -> Result<()> {
+    let path = status_file_path(snapshot.project.id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    std::fs::write(&tmp_path, snapshot.to_json()?)?;
+    std::fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(window_title(DEFAULT_TITLE_FORMAT, "api", 3, 2), "workbench: api 2⏳");
+        assert_eq!(window_title(DEFAULT_TITLE_FORMAT, "api", 3, 0), "workbench: api");
+        assert_eq!(window_title("wb: {badge}", "api", 0, 0), "wb");
+        assert_eq!(window_title("{project} {sessions}/{waiting}", "api", 3, 1), "api 3/1");
+    }
+
+    #[test]
+    fn test_status_file_per_project() {
+        assert_eq!(status_file_path(3).file_name().unwrap(), "project-3.json");
+        assert_ne!(status_file_path(3), status_file_path(4));
+        assert_eq!(status_file_path(3).parent().unwrap(), status_dir());
+    }
+}
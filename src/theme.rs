@@ -0,0 +1,94 @@
+use ratatui::buffer::Buffer;
+use ratatui::style::{Color, Modifier};
+
+/// Environment variable that turns colors off (https://no-color.org)
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+/// Built-in themes. The UI is drawn in color; `Monochrome` rewrites the finished
+/// frame so meaning carried by color survives as text attributes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Color,
+    Monochrome,
+}
+
+impl Theme {
+    /// Monochrome when asked for on the command line or when `NO_COLOR` is set to anything non-empty
+    pub fn detect(no_color_flag: bool) -> Self {
+        let no_color_env = std::env::var_os(NO_COLOR_ENV).is_some_and(|v| !v.is_empty());
+        if no_color_flag || no_color_env {
+            Theme::Monochrome
+        } else {
+            Theme::Color
+        }
+    }
+
+    /// Apply the theme to a rendered frame
+    pub fn apply(self, buffer: &mut Buffer) {
+        if self == Theme::Color {
+            return;
+        }
+        for cell in buffer.content.iter_mut() {
+            let mut modifier = cell.modifier;
+            // Highlighted rows (selection, badges) use a background; popups only paint black
+            if !matches!(cell.bg, Color::Reset | Color::Black) {
+                modifier |= Modifier::REVERSED;
+            }
+            match cell.fg {
+                // Dim text is left plain: DarkGray is what goes invisible on 16-color terminals
+                Color::Reset | Color::DarkGray | Color::Gray | Color::White | Color::Black => {}
+                // Any accent (selected border, active/waiting badge, errors) becomes bold,
+                // and accented borders switch to heavy lines
+                _ => {
+                    modifier |= Modifier::BOLD;
+                    if let Some(heavy) = heavy_line(cell.symbol()) {
+                        cell.set_symbol(heavy);
+                    }
+                }
+            }
+            cell.fg = Color::Reset;
+            cell.bg = Color::Reset;
+            cell.modifier = modifier;
+        }
+    }
+}
+
+/// Heavy counterpart of a light box-drawing character
+fn heavy_line(symbol: &str) -> Option<&'static str> {
+    Some(match symbol {
+        "─" => "━",
+        "│" => "┃",
+        "┌" => "┏",
+        "┐" => "┓",
+        "└" => "┗",
+        "┘" => "┛",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::style::Style;
+
+    #[test]
+    fn test_monochrome_replaces_color_with_attributes() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 4, 1));
+        buffer[(0, 0)].set_symbol("│").set_style(Style::default().fg(Color::Yellow));
+        buffer[(1, 0)].set_symbol("│").set_style(Style::default().fg(Color::DarkGray));
+        buffer[(2, 0)].set_symbol("x").set_style(Style::default().fg(Color::Black).bg(Color::Yellow));
+        buffer[(3, 0)].set_symbol("y").set_style(Style::default().bg(Color::Black));
+
+        Theme::Monochrome.apply(&mut buffer);
+
+        assert_eq!(buffer[(0, 0)].symbol(), "┃");
+        assert_eq!(buffer[(0, 0)].modifier, Modifier::BOLD);
+        assert_eq!(buffer[(1, 0)].symbol(), "│");
+        assert_eq!(buffer[(1, 0)].modifier, Modifier::empty());
+        assert_eq!(buffer[(2, 0)].modifier, Modifier::REVERSED);
+        assert_eq!(buffer[(3, 0)].modifier, Modifier::empty());
+        assert!(buffer.content.iter().all(|c| c.fg == Color::Reset && c.bg == Color::Reset));
+    }
+}
@@ -1,13 +1,165 @@
 use color_eyre::{eyre::eyre, Result};
-use std::process::{Command, ExitStatus, Stdio};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::logging::Traced;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// Private server used by tests, so they never touch real sessions
+#[cfg(test)]
+const TEST_SOCKET: &str = "workbench-test";
+
+#[cfg(test)]
+thread_local! {
+    /// Socket of the `TestServer` the current test thread's tmux calls go to, if any
+    static SOCKET: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Tries of a tmux command that fails only because the server isn't ready
+const ATTEMPTS: u32 = 3;
+
+/// Wait before trying again, doubled after each try
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// What tmux says when it fails for want of a settled server (e.g. right after it was
+/// started), as opposed to a real answer like "can't find session"
+const TRANSIENT_ERRORS: &[&str] = &[
+    "server not ready",
+    "lost server",
+    "server exited unexpectedly",
+    "Resource temporarily unavailable",
+];
+
+#[cfg(not(test))]
+fn tmux() -> Command {
+    Command::new("tmux")
+}
+
+#[cfg(test)]
+fn tmux() -> Command {
+    let mut command = Command::new("tmux");
+    match SOCKET.with(|socket| socket.borrow().clone()) {
+        // Without the user's tmux.conf, which may change shells or hooks
+        Some(path) => command.arg("-f").arg("/dev/null").arg("-S").arg(path),
+        None => command.args(["-L", TEST_SOCKET]),
+    };
+    command
+}
+
+/// A test's own tmux server, so tests running alongside can't kill it under each other.
+/// tmux calls on this thread go to it until it's dropped, which kills the server with
+/// every session the test made, however the test ended
+#[cfg(test)]
+pub struct TestServer {
+    socket: PathBuf,
+    previous: Option<PathBuf>,
+}
+
+#[cfg(test)]
+impl TestServer {
+    pub fn start(test: &str) -> Self {
+        let socket = std::env::temp_dir().join(format!("{}-{}-{}", TEST_SOCKET, std::process::id(), test));
+        let previous = SOCKET.with(|s| s.replace(Some(socket.clone())));
+        Self { socket, previous }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = tmux().arg("kill-server").traced_output();
+        let _ = std::fs::remove_file(&self.socket);
+        SOCKET.with(|s| s.replace(self.previous.take()));
+    }
+}
+
+/// Startup command for a test's session. It runs without the user's rc files, which can
+/// be slow under load and leave other processes (and their working directories) in the
+/// pane for a while, and prints the marker `wait_ready` looks for once the pane settles
+#[cfg(test)]
+pub const TEST_STARTUP: &str = "echo wb-test-ready; exec sleep 600";
+
+/// Wait until a session started with `TEST_STARTUP` is settled
+#[cfg(test)]
+pub fn wait_ready(name: &str) -> bool {
+    wait_until(|| capture_pane_content(name, None).is_some_and(|content| content.contains("wb-test-ready")))
+}
+
+/// Poll `check` until it holds, for state a tmux server settles into. The deadline is
+/// only there so a broken test ends; it's generous for a loaded machine running the suite
+#[cfg(test)]
+pub fn wait_until(mut check: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !check() {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    true
+}
+
+/// Run `command`, running it again with a short backoff while it fails with one of the
+/// transient errors. Returns the output of the last try
+fn output_with_retry(command: &mut Command) -> std::io::Result<Output> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let output = command.traced_output()?;
+        if output.status.success() || attempt == ATTEMPTS || !is_transient(&output.stderr) {
+            return Ok(output);
+        }
+        tracing::warn!(attempt, stderr = %first_line(&output.stderr), "tmux not ready, trying again");
+        std::thread::sleep(backoff);
+        backoff *= 2;
+        attempt += 1;
+    }
+}
+
+fn is_transient(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    TRANSIENT_ERRORS.iter().any(|e| stderr.contains(e))
+}
+
+fn first_line(stderr: &[u8]) -> String {
+    String::from_utf8_lossy(stderr).lines().next().unwrap_or("").trim().to_string()
+}
+
+/// The error for a failed tmux command: `what`, with tmux's own explanation when it gave one
+fn failure(what: &str, output: &Output) -> color_eyre::Report {
+    match first_line(&output.stderr) {
+        line if line.is_empty() => eyre!("{}", what),
+        line => eyre!("{}: {}", what, line),
+    }
+}
+
+/// `-t` target for exactly this session. Without `=`, tmux falls back to prefix and
+/// pattern matches, so `workbench-1-2` could hit `workbench-1-23`
+pub fn session_target(name: &str) -> String {
+    format!("={}", name)
+}
+
+/// `-t` target for the active pane of exactly this session
+fn pane_target(name: &str) -> String {
+    format!("={}:", name)
+}
+
+/// Whether tmux can hold and target a session by exactly this name. tmux renames
+/// sessions containing `.` or `:`, and `:` splits pane targets
+pub fn validate_session_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['.', ':']) {
+        return Err(eyre!("tmux session name \"{}\" can't be targeted exactly (no '.' or ':')", name));
+    }
+    Ok(())
+}
 
 /// Check if tmux is installed and available
 pub fn is_available() -> bool {
-    Command::new("tmux")
+    tmux()
         .arg("-V")
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .status()
+        .traced_status()
         .map(|s| s.success())
         .unwrap_or(false)
 }
@@ -17,32 +169,159 @@ pub fn session_name(project_id: i64, session_id: i64) -> String {
     format!("workbench-{}-{}", project_id, session_id)
 }
 
+/// Project and session id of a workbench tmux session name, the inverse of `session_name`
+pub fn parse_session_name(name: &str) -> Option<(i64, i64)> {
+    let (project_id, session_id) = name.strip_prefix("workbench-")?.split_once('-')?;
+    Some((project_id.parse().ok()?, session_id.parse().ok()?))
+}
+
 /// Check if a tmux session with the given name exists
 pub fn session_exists(name: &str) -> bool {
-    Command::new("tmux")
-        .args(["has-session", "-t", name])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map(|s| s.success())
+    output_with_retry(tmux().args(["has-session", "-t", &session_target(name)]))
+        .map(|output| output.status.success())
         .unwrap_or(false)
 }
 
-/// Create a new tmux session with a shell in the specified working directory
-pub fn create_session(name: &str, working_dir: &str) -> Result<()> {
-    let status = Command::new("tmux")
-        .args([
-            "new-session",
-            "-d",           // detached
-            "-s", name,     // session name
-            "-c", working_dir, // start directory
-        ])
-        .status()?;
+/// Create a new tmux session in the specified working directory, with `env` set in
+/// the session's environment, running `startup` through the shell (else just a shell)
+pub fn create_session(name: &str, working_dir: &Path, env: &[(String, String)], startup: Option<&str>) -> Result<()> {
+    validate_session_name(name)?;
+    let mut command = tmux();
+    command.args([
+        "new-session",
+        "-d",           // detached
+        "-s", name,     // session name
+        "-c",           // start directory, passed as a path so its bytes survive
+    ]);
+    command.arg(working_dir);
+    if env.is_empty() {
+        command.args(startup);
+    }
+    // Keep the pane after its process exits so the exit status and final output survive.
+    // Set in the same call, before a quick startup command can exit
+    command.args([";", "set-option", "-w", "-t", &pane_target(name), "remain-on-exit", "on"]);
+    let output = output_with_retry(&mut command)?;
 
-    if status.success() {
+    if !output.status.success() {
+        return Err(failure("Failed to create tmux session", &output));
+    }
+    if !env.is_empty() {
+        // Values on the command line (`-e`) would show in `ps` to every user, so they
+        // are set from a file and the first process is restarted to pick them up
+        let commands: Vec<String> = env
+            .iter()
+            .map(|(key, value)| format!("set-environment -t {} {} {}", quote(&session_target(name)), key, quote(value)))
+            .collect();
+        source_commands(&commands, "Failed to set the tmux session's environment")?;
+        respawn_pane(name, startup)?;
+    }
+    Ok(())
+}
+
+/// A string as one argument in a tmux command file: double-quoted, with what tmux
+/// would expand escaped
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' | '"' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Run tmux commands from a file only this user can read, deleted afterwards, so what
+/// they carry never appears in a process list. `what` heads the error when tmux fails
+fn source_commands(commands: &[String], what: &str) -> Result<()> {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static FILES: AtomicU64 = AtomicU64::new(0);
+
+    let file_name = format!("workbench-tmux-{}-{}.conf", std::process::id(), FILES.fetch_add(1, Ordering::Relaxed));
+    let path = std::env::temp_dir().join(file_name);
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    let written = file.write_all(format!("{}\n", commands.join("\n")).as_bytes());
+    drop(file);
+    let output = written.and_then(|()| tmux().arg("source-file").arg(&path).traced_output());
+    let _ = std::fs::remove_file(&path);
+    let output = output?;
+    if output.status.success() {
         Ok(())
     } else {
-        Err(eyre!("Failed to create tmux session"))
+        Err(failure(what, &output))
+    }
+}
+
+/// Output of a startup command tried out in a throwaway session
+#[derive(Debug, Clone)]
+pub struct TrialRun {
+    pub output: String,
+    /// Whether it exited before the trial stopped waiting
+    pub finished: bool,
+    /// Exit status, when it finished and tmux could tell
+    pub exit_status: Option<i32>,
+}
+
+/// Run `startup` in a throwaway session (not a `workbench-` one, so the board never
+/// sees it), wait up to `timeout` for it to exit, capture the pane and kill the session
+pub fn trial_run(startup: &str, working_dir: &Path, timeout: Duration) -> Result<TrialRun> {
+    let name = format!("wbtrial-{}-{}", std::process::id(), crate::db::unix_now());
+    create_session(&name, working_dir, &[], Some(startup))?;
+    let started = Instant::now();
+    let (finished, exit_status) = loop {
+        let output = tmux()
+            .args(["display-message", "-t", &pane_target(&name), "-p", "#{pane_dead} #{pane_dead_status}"])
+            .traced_output()?;
+        let state = String::from_utf8_lossy(&output.stdout).into_owned();
+        // The status can be missing when tmux saw the pane close before reaping it
+        if let Some(status) = state.strip_prefix("1 ") {
+            break (true, status.trim().parse().ok());
+        }
+        if !output.status.success() || started.elapsed() >= timeout {
+            break (false, None);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    let output = capture_scrollback(&name).unwrap_or_default();
+    kill_session(&name);
+    Ok(TrialRun { output, finished, exit_status })
+}
+
+/// Set (or with None, remove) a variable in a running session's environment.
+/// Only new panes and respawned processes see it; running shells keep their copy
+pub fn set_environment(name: &str, key: &str, value: Option<&str>) -> Result<()> {
+    let target = quote(&session_target(name));
+    let command = match value {
+        Some(value) => format!("set-environment -t {} {} {}", target, key, quote(value)),
+        None => format!("set-environment -t {} -u {}", target, key),
+    };
+    source_commands(&[command], &format!("Failed to set {} in tmux session", key))
+}
+
+/// Restart a dead (retained) pane: with `command` if given, else with what it first ran
+pub fn respawn_pane(name: &str, command: Option<&str>) -> Result<()> {
+    let output = tmux()
+        .args(["respawn-pane", "-k", "-t", &pane_target(name)])
+        .args(command)
+        .traced_output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(failure("Failed to respawn tmux pane", &output))
     }
 }
 
@@ -51,49 +330,138 @@ pub fn is_inside_tmux() -> bool {
     std::env::var("TMUX").is_ok_and(|v| !v.is_empty())
 }
 
+/// switch-client if already inside tmux, otherwise attach-session
+fn attach_command(name: &str) -> Command {
+    let subcommand = if is_inside_tmux() { "switch-client" } else { "attach-session" };
+    let mut command = tmux();
+    command.args([subcommand, "-t", &session_target(name)]);
+    command
+}
+
 /// Attach to an existing tmux session (blocking)
 /// Uses switch-client if already inside tmux, otherwise uses attach-session
 pub fn attach_session(name: &str) -> Result<ExitStatus> {
-    let args = if is_inside_tmux() {
-        vec!["switch-client", "-t", name]
-    } else {
-        vec!["attach-session", "-t", name]
-    };
-
-    let status = Command::new("tmux")
-        .args(&args)
+    let status = attach_command(name)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
-        .status()?;
+        .traced_status()?;
 
     Ok(status)
 }
 
-/// List all workbench tmux sessions
-pub fn list_workbench_sessions() -> Vec<String> {
-    let output = Command::new("tmux")
-        .args(["list-sessions", "-F", "#{session_name}"])
-        .output();
+/// Replace this process with `tmux attach` (or switch-client inside tmux).
+/// Only returns if tmux couldn't be started
+#[cfg(unix)]
+pub fn exec_attach(name: &str) -> color_eyre::eyre::Report {
+    use std::os::unix::process::CommandExt;
+    tracing::debug!(name, "exec tmux attach");
+    eyre!("Failed to run tmux: {}", attach_command(name).exec())
+}
 
-    match output {
-        Ok(output) if output.status.success() => {
-            String::from_utf8_lossy(&output.stdout)
-                .lines()
-                .filter(|name| name.starts_with("workbench-"))
-                .map(String::from)
-                .collect()
+/// Without `exec`, run `tmux attach` as a child and exit with its status.
+/// Only returns if tmux couldn't be started or exited with a failure
+#[cfg(not(unix))]
+pub fn exec_attach(name: &str) -> color_eyre::eyre::Report {
+    tracing::debug!(name, "run tmux attach");
+    match attach_session(name) {
+        Ok(status) if status.success() => std::process::exit(0),
+        Ok(status) => eyre!("tmux attach exited with {}", status),
+        Err(e) => e,
+    }
+}
+
+/// Show a session read-only in a new window of the current tmux session, for one
+/// already attached elsewhere: a nested `attach -r` that can't type into it or resize it
+pub fn open_read_only_window(name: &str) -> Result<()> {
+    let attach = format!("env -u TMUX tmux attach-session -r -t {}", crate::startup::shell_quote(&session_target(name)));
+    let output = tmux().args(["new-window", "-n", &format!("{} (read-only)", name), &attach]).traced_output()?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(failure("Failed to open a read-only window", &output))
+    }
+}
+
+/// Per-session pane state gathered by the poller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaneState {
+    /// Last activity (unix seconds)
+    pub activity: i64,
+    /// tmux clients attached to the session, e.g. another terminal showing it
+    pub clients: u32,
+    /// Foreground command of the session's agent pane (else its active pane), e.g. "claude" or "zsh"
+    pub command: String,
+    /// Exit status once the pane's process has died (the pane is kept by `remain-on-exit`)
+    pub exit_status: Option<i32>,
+}
+
+/// State of every workbench tmux session in a single `list-panes` call.
+/// Fails when tmux can't list at all (e.g. no server running), as opposed to listing no sessions
+pub fn poll_workbench_panes() -> Result<HashMap<String, PaneState>> {
+    let output = output_with_retry(tmux().args([
+        "list-panes",
+        "-a",
+        "-F",
+        "#{session_name}\t#{pane_active}\t#{session_activity}\t#{session_attached}\t#{pane_dead}\t#{pane_dead_status}\t#{pane_current_command}",
+    ]));
+
+    let output = output.map_err(|e| eyre!("tmux not available: {}", e))?;
+    if output.status.success() {
+        Ok(parse_pane_states(&String::from_utf8_lossy(&output.stdout), &crate::agent::pane_commands()))
+    } else {
+        let stderr = first_line(&output.stderr);
+        Err(eyre!(if stderr.is_empty() { "tmux server not running".to_string() } else { stderr }))
+    }
+}
+
+/// Parse `list-panes` output, keeping one pane per workbench session: the one running
+/// an agent (any of `agents`), else the active one
+fn parse_pane_states(output: &str, agents: &[String]) -> HashMap<String, PaneState> {
+    let is_agent = |command: &str| agents.iter().any(|a| a == command);
+    let mut states: HashMap<String, PaneState> = HashMap::new();
+    for line in output.lines() {
+        let mut parts = line.splitn(7, '\t');
+        let (Some(name), Some(active), Some(activity), Some(clients), Some(dead), Some(dead_status), Some(command)) =
+            (parts.next(), parts.next(), parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        if !name.starts_with("workbench-") {
+            continue;
+        }
+        let state = PaneState {
+            activity: activity.parse().unwrap_or(0),
+            clients: clients.parse().unwrap_or(0),
+            command: command.to_string(),
+            exit_status: (dead == "1").then(|| dead_status.parse().unwrap_or(-1)),
+        };
+        let replaces = |kept: &PaneState| {
+            !is_agent(&kept.command) && (is_agent(&state.command) || active == "1")
+        };
+        match states.get(name) {
+            Some(kept) if !replaces(kept) => {}
+            _ => {
+                states.insert(name.to_string(), state);
+            }
         }
-        _ => Vec::new(),
     }
+    states
+}
+
+/// Whether a pane command is an interactive shell (nothing running in it)
+pub fn is_shell(command: &str) -> bool {
+    matches!(
+        command.trim_start_matches('-'),
+        "sh" | "bash" | "zsh" | "fish" | "dash" | "ksh" | "tcsh" | "csh" | "nu"
+    )
 }
 
 /// List tmux sessions for a specific project
 pub fn list_project_sessions(project_id: i64) -> Vec<String> {
     let prefix = format!("workbench-{}-", project_id);
-    let output = Command::new("tmux")
-        .args(["list-sessions", "-F", "#{session_name}"])
-        .output();
+    let output = output_with_retry(tmux().args(["list-sessions", "-F", "#{session_name}"]));
 
     match output {
         Ok(output) if output.status.success() => {
@@ -109,41 +477,155 @@ pub fn list_project_sessions(project_id: i64) -> Vec<String> {
 
 /// Kill a tmux session by name
 pub fn kill_session(name: &str) -> bool {
-    Command::new("tmux")
-        .args(["kill-session", "-t", name])
+    tmux()
+        .args(["kill-session", "-t", &session_target(name)])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
-        .status()
+        .traced_status()
         .map(|s| s.success())
         .unwrap_or(false)
 }
 
-/// Capture the content of a tmux pane
-pub fn capture_pane_content(name: &str) -> Option<String> {
-    let output = Command::new("tmux")
-        .args(["capture-pane", "-t", name, "-p"])
-        .output()
+/// A pane of a workbench session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pane {
+    /// tmux pane id, e.g. `%3`; unique on the server, so usable as a target on its own
+    pub id: String,
+    pub active: bool,
+    pub command: String,
+}
+
+/// Every pane of a session, in all its windows
+pub fn list_panes(name: &str) -> Vec<Pane> {
+    let output = output_with_retry(
+        tmux().args(["list-panes", "-s", "-t", &session_target(name), "-F", "#{pane_id}\t#{pane_active}\t#{pane_current_command}"]),
+    );
+    match output {
+        Ok(output) if output.status.success() => parse_panes(&String::from_utf8_lossy(&output.stdout)),
+        _ => Vec::new(),
+    }
+}
+
+fn parse_panes(output: &str) -> Vec<Pane> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            Some(Pane {
+                id: parts.next()?.to_string(),
+                active: parts.next()? == "1",
+                command: parts.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The pane workbench watches: the one running an agent (any of `agents`), else the active one
+pub fn preferred_pane<'a>(panes: &'a [Pane], agents: &[String]) -> Option<&'a Pane> {
+    panes
+        .iter()
+        .find(|p| agents.contains(&p.command))
+        .or_else(|| panes.iter().find(|p| p.active))
+        .or_else(|| panes.first())
+}
+
+/// `-t` target for the pane workbench watches. Single-pane sessions skip the lookup
+/// and target the session's active pane as they always have
+fn watched_pane_target(name: &str) -> String {
+    let panes = list_panes(name);
+    if panes.len() <= 1 {
+        return pane_target(name);
+    }
+    match preferred_pane(&panes, &crate::agent::pane_commands()) {
+        Some(pane) => pane.id.clone(),
+        None => pane_target(name),
+    }
+}
+
+/// The pane to show for a session: `chosen` while it still exists, else the watched pane
+pub fn resolve_pane<'a>(panes: &'a [Pane], chosen: Option<&str>) -> Option<&'a Pane> {
+    chosen
+        .and_then(|id| panes.iter().find(|p| p.id == id))
+        .or_else(|| preferred_pane(panes, &crate::agent::pane_commands()))
+}
+
+/// Capture the content of a session's pane: `pane` (a pane id) if given, else the pane
+/// running the agent, else the active one
+pub fn capture_pane_content(name: &str, pane: Option<&str>) -> Option<String> {
+    capture(name, pane, &[])
+}
+
+/// Like `capture_pane_content`, but lines the terminal wrapped are joined back into one
+/// (`-J`), so a long URL comes back whole. Trailing padding is trimmed
+pub fn capture_pane_joined(name: &str, pane: Option<&str>) -> Option<String> {
+    let content = capture(name, pane, &["-J"])?;
+    Some(content.lines().map(str::trim_end).collect::<Vec<_>>().join("\n"))
+}
+
+fn capture(name: &str, pane: Option<&str>, extra: &[&str]) -> Option<String> {
+    let target = match pane {
+        Some(id) => id.to_string(),
+        None => watched_pane_target(name),
+    };
+    let output = tmux()
+        .args(["capture-pane", "-t", &target, "-p"])
+        .args(extra)
+        .traced_output()
+        .ok()?;
+    if output.status.success() {
+        Some(capture_text(&output.stdout))
+    } else {
+        None
+    }
+}
+
+/// Decode captured pane output, dropping the blank lines tmux pads a short pane with.
+/// Invalid UTF-8 from the program becomes U+FFFD rather than failing the capture
+fn capture_text(stdout: &[u8]) -> String {
+    let text = String::from_utf8_lossy(stdout);
+    let trimmed = text.trim_end_matches(|c: char| c.is_whitespace());
+    let mut text = trimmed.to_string();
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    text
+}
+
+/// Capture the last `lines` lines of the watched pane's scrollback history
+pub fn capture_pane_history(name: &str, lines: usize) -> Option<String> {
+    let start = format!("-{}", lines);
+    let output = tmux()
+        .args(["capture-pane", "-t", &watched_pane_target(name), "-p", "-S", &start])
+        .traced_output()
         .ok()?;
     if output.status.success() {
-        Some(String::from_utf8_lossy(&output.stdout).to_string())
+        Some(capture_text(&output.stdout))
+    } else {
+        None
+    }
+}
+
+/// Capture the watched pane's whole scrollback history
+pub fn capture_scrollback(name: &str) -> Option<String> {
+    let output = tmux()
+        .args(["capture-pane", "-t", &watched_pane_target(name), "-p", "-S", "-"])
+        .traced_output()
+        .ok()?;
+    if output.status.success() {
+        Some(capture_text(&output.stdout))
     } else {
         None
     }
 }
 
 /// Get the current working directory of a tmux pane
-pub fn get_pane_cwd(name: &str) -> Option<String> {
-    let output = Command::new("tmux")
-        .args(["display-message", "-t", name, "-p", "#{pane_current_path}"])
-        .output()
+pub fn get_pane_cwd(name: &str) -> Option<PathBuf> {
+    let output = tmux()
+        .args(["display-message", "-t", &pane_target(name), "-p", "#{pane_current_path}"])
+        .traced_output()
         .ok()?;
     if output.status.success() {
-        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        if path.is_empty() {
-            None
-        } else {
-            Some(path)
-        }
+        crate::paths::from_output_line(&output.stdout)
     } else {
         None
     }
@@ -153,8 +635,10 @@ pub fn get_pane_cwd(name: &str) -> Option<String> {
 pub fn get_git_branch(name: &str) -> Option<String> {
     let cwd = get_pane_cwd(name)?;
     let output = Command::new("git")
-        .args(["-C", &cwd, "rev-parse", "--abbrev-ref", "HEAD"])
-        .output()
+        .arg("-C")
+        .arg(&cwd)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .traced_output()
         .ok()?;
     if output.status.success() {
         let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
@@ -168,28 +652,250 @@ pub fn get_git_branch(name: &str) -> Option<String> {
     }
 }
 
-/// Check if a tmux session is waiting for user input by examining pane content
-pub fn is_waiting_for_input(name: &str) -> bool {
-    let output = Command::new("tmux")
-        .args(["capture-pane", "-t", name, "-p"])
-        .output();
+/// Check if a tmux session is waiting for user input: the watched pane shows one of
+/// `agent`'s waiting patterns
+pub fn is_waiting_for_input(name: &str, agent: &crate::agent::Agent) -> bool {
+    capture_pane_content(name, None).is_some_and(|content| agent.is_waiting(&content))
+}
 
-    match output {
-        Ok(output) if output.status.success() => {
-            let content = String::from_utf8_lossy(&output.stdout);
-            // Check last few lines for Claude Code input prompts
-            let last_lines: String = content.lines().rev().take(5).collect::<Vec<_>>().join("\n");
-
-            // Common Claude Code input prompt patterns
-            last_lines.contains("Enter to select")
-                || last_lines.contains("Do you want to")
-                || last_lines.contains("yes/yes to all/no")
-                || last_lines.contains("Allow once")
-                || last_lines.contains("Allow always")
-                || last_lines.contains("(y/n)")
-                || last_lines.contains("[Y/n]")
-                || last_lines.contains("[y/N]")
+/// The tmux calls the board makes, so tests can drive it without a tmux server.
+/// `SystemTmux` runs the real commands
+pub trait TmuxClient: Send + Sync {
+    fn is_available(&self) -> bool;
+    fn session_exists(&self, name: &str) -> bool;
+    fn create_session(&self, name: &str, working_dir: &Path, env: &[(String, String)], startup: Option<&str>) -> Result<()>;
+    fn kill_session(&self, name: &str) -> bool;
+    fn respawn_pane(&self, name: &str, command: Option<&str>) -> Result<()>;
+    fn set_environment(&self, name: &str, key: &str, value: Option<&str>) -> Result<()>;
+    fn trial_run(&self, startup: &str, working_dir: &Path, timeout: Duration) -> Result<TrialRun>;
+    fn poll_workbench_panes(&self) -> Result<HashMap<String, PaneState>>;
+    fn list_project_sessions(&self, project_id: i64) -> Vec<String>;
+    fn list_panes(&self, name: &str) -> Vec<Pane>;
+    fn capture_pane_content(&self, name: &str, pane: Option<&str>) -> Option<String>;
+    fn capture_pane_joined(&self, name: &str, pane: Option<&str>) -> Option<String>;
+    fn capture_pane_history(&self, name: &str, lines: usize) -> Option<String>;
+    fn capture_scrollback(&self, name: &str) -> Option<String>;
+    fn pane_cwd(&self, name: &str) -> Option<PathBuf>;
+
+    /// Whether the watched pane shows one of `agent`'s waiting patterns
+    fn is_waiting_for_input(&self, name: &str, agent: &crate::agent::Agent) -> bool {
+        self.capture_pane_content(name, None).is_some_and(|content| agent.is_waiting(&content))
+    }
+}
+
+/// The tmux on `$PATH`
+pub struct SystemTmux;
+
+impl TmuxClient for SystemTmux {
+    fn is_available(&self) -> bool {
+        is_available()
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        session_exists(name)
+    }
+
+    fn create_session(&self, name: &str, working_dir: &Path, env: &[(String, String)], startup: Option<&str>) -> Result<()> {
+        create_session(name, working_dir, env, startup)
+    }
+
+    fn kill_session(&self, name: &str) -> bool {
+        kill_session(name)
+    }
+
+    fn respawn_pane(&self, name: &str, command: Option<&str>) -> Result<()> {
+        respawn_pane(name, command)
+    }
+
+    fn set_environment(&self, name: &str, key: &str, value: Option<&str>) -> Result<()> {
+        set_environment(name, key, value)
+    }
+
+    fn trial_run(&self, startup: &str, working_dir: &Path, timeout: Duration) -> Result<TrialRun> {
+        trial_run(startup, working_dir, timeout)
+    }
+
+    fn poll_workbench_panes(&self) -> Result<HashMap<String, PaneState>> {
+        poll_workbench_panes()
+    }
+
+    fn list_project_sessions(&self, project_id: i64) -> Vec<String> {
+        list_project_sessions(project_id)
+    }
+
+    fn list_panes(&self, name: &str) -> Vec<Pane> {
+        list_panes(name)
+    }
+
+    fn capture_pane_content(&self, name: &str, pane: Option<&str>) -> Option<String> {
+        capture_pane_content(name, pane)
+    }
+
+    fn capture_pane_joined(&self, name: &str, pane: Option<&str>) -> Option<String> {
+        capture_pane_joined(name, pane)
+    }
+
+    fn capture_pane_history(&self, name: &str, lines: usize) -> Option<String> {
+        capture_pane_history(name, lines)
+    }
+
+    fn capture_scrollback(&self, name: &str) -> Option<String> {
+        capture_scrollback(name)
+    }
+
+    fn pane_cwd(&self, name: &str) -> Option<PathBuf> {
+        get_pane_cwd(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fake tmux that fails with `stderr` on its first `failures` runs, then succeeds
+    #[cfg(unix)]
+    fn flaky_tmux(test_name: &str, failures: u32, stderr: &str) -> (PathBuf, Command) {
+        let body = format!(
+            "runs=$(cat \"$(dirname \"$0\")/runs\" 2>/dev/null || echo 0)\n\
+             echo $((runs + 1)) > \"$(dirname \"$0\")/runs\"\n\
+             if [ \"$runs\" -lt {failures} ]; then echo '{stderr}' >&2; exit 1; fi\necho ok",
+        );
+        let dir = crate::ai::fake_command(test_name, "tmux", &body);
+        let command = Command::new(dir.join("tmux"));
+        (dir, command)
+    }
+
+    #[cfg(unix)]
+    fn runs(dir: &Path) -> String {
+        std::fs::read_to_string(dir.join("runs")).unwrap().trim().to_string()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_retry_transient_failures() {
+        let (dir, mut command) = flaky_tmux("retry-ok", 2, "server not ready");
+        let output = output_with_retry(&mut command).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+        assert_eq!(runs(&dir), "3");
+        std::fs::remove_dir_all(dir).ok();
+
+        // Gives up after the last try, with tmux's words in the error
+        let (dir, mut command) = flaky_tmux("retry-fail", 5, "lost server");
+        let output = output_with_retry(&mut command).unwrap();
+        assert!(!output.status.success());
+        assert_eq!(runs(&dir), ATTEMPTS.to_string());
+        assert_eq!(failure("Failed to create tmux session", &output).to_string(), "Failed to create tmux session: lost server");
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_no_retry_on_real_failures() {
+        let (dir, mut command) = flaky_tmux("retry-real", 1, "duplicate session: workbench-1-2");
+        let output = output_with_retry(&mut command).unwrap();
+        assert!(!output.status.success());
+        assert_eq!(runs(&dir), "1");
+        assert_eq!(
+            failure("Failed to create tmux session", &output).to_string(),
+            "Failed to create tmux session: duplicate session: workbench-1-2"
+        );
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_parse_pane_states() {
+        let output = "workbench-1-2\t0\t100\t1\t0\t\tzsh\n\
+                      workbench-1-2\t1\t100\t1\t0\t\tclaude\n\
+                      workbench-1-3\t1\t90\t0\t0\t\t-zsh\n\
+                      workbench-1-4\t1\t80\t0\t1\t137\tclaude\n\
+                      workbench-1-5\t0\t70\t0\t0\t\tclaude\n\
+                      workbench-1-5\t1\t70\t0\t0\t\tvim\n\
+                      workbench-1-6\t1\t60\t0\t0\t\tzsh\n\
+                      workbench-1-6\t0\t60\t0\t0\t\taider\n\
+                      other\t1\t50\t0\t0\t\tvim\n\
+                      garbage\n";
+        let states = parse_pane_states(output, &["claude".to_string(), "aider".to_string()]);
+        assert_eq!(states.len(), 5);
+        assert_eq!(states["workbench-1-6"].command, "aider");
+        // A split with an editor focused still reports the agent's pane
+        assert_eq!(states["workbench-1-5"].command, "claude");
+        assert_eq!(
+            states["workbench-1-2"],
+            PaneState { activity: 100, clients: 1, command: "claude".to_string(), exit_status: None }
+        );
+        assert_eq!(states["workbench-1-4"].exit_status, Some(137));
+        assert_eq!(states["workbench-1-3"].command, "-zsh");
+        assert!(is_shell(&states["workbench-1-3"].command));
+        assert!(!is_shell("claude"));
+
+        assert_eq!(parse_session_name(&session_name(12, 345)), Some((12, 345)));
+        assert_eq!(parse_session_name("workbench-1"), None);
+        assert_eq!(parse_session_name("wbtrial-1-2"), None);
+    }
+
+    #[test]
+    fn test_preferred_pane() {
+        let panes = parse_panes("%1\t1\tnvim\n%2\t0\tclaude\n%3\t0\tzsh\n");
+        assert_eq!(panes.len(), 3);
+        let agents = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(preferred_pane(&panes, &agents(&["claude"])).map(|p| p.id.as_str()), Some("%2"));
+        assert_eq!(preferred_pane(&panes, &agents(&["aider"])).map(|p| p.id.as_str()), Some("%1"));
+        assert_eq!(preferred_pane(&panes, &[]).map(|p| p.id.as_str()), Some("%1"));
+        assert_eq!(preferred_pane(&[], &agents(&["claude"])), None);
+    }
+
+    #[test]
+    fn test_capture_text() {
+        assert_eq!(capture_text(b"$ ls\nfoo\n\n\n   \n\n"), "$ ls\nfoo\n");
+        assert_eq!(capture_text(b"\n\n\n"), "");
+        // A split multi-byte character doesn't lose the rest of the capture
+        assert_eq!(capture_text(b"caf\xc3\nok\n"), "caf\u{fffd}\nok\n");
+    }
+
+    #[test]
+    fn test_targets_exact_session() {
+        assert!(validate_session_name("workbench-1-2-v1.2").is_err());
+        assert!(validate_session_name("a:b").is_err());
+        assert!(validate_session_name("workbench-1-2").is_ok());
+        if !is_available() {
+            return;
         }
-        _ => false,
+
+        let _server = TestServer::start("exact-session");
+        let dir = std::env::temp_dir();
+        let (short, long) = ("wbtest-1".to_string(), "wbtest-12".to_string());
+        assert!(create_session("wbtest.2", &dir, &[], None).is_err());
+        // Values go through a file, taken literally
+        let value = r#"a "b" $HOME \x"#;
+        create_session(&long, &dir, &[("WB_TEST".to_string(), value.to_string())], Some(TEST_STARTUP)).unwrap();
+        let show = || {
+            let env = tmux().args(["show-environment", "-t", &session_target(&long), "WB_TEST"]).traced_output().unwrap();
+            String::from_utf8_lossy(&env.stdout).trim().to_string()
+        };
+        assert_eq!(show(), format!("WB_TEST={}", value));
+        set_environment(&long, "WB_TEST", Some("c")).unwrap();
+        assert_eq!(show(), "WB_TEST=c");
+        set_environment(&long, "WB_TEST", None).unwrap();
+        assert_eq!(show(), "");
+
+        // `short` is a prefix of `long`; nothing may resolve to it
+        assert!(!session_exists(&short));
+        assert!(capture_pane_content(&short, None).is_none());
+        assert!(!kill_session(&short));
+        assert!(session_exists(&long));
+        assert!(wait_ready(&long));
+        assert_eq!(list_panes(&long).len(), 1);
+        assert_eq!(get_pane_cwd(&long), std::fs::canonicalize(&dir).ok());
+
+        create_session(&short, &dir, &[], None).unwrap();
+        assert!(kill_session(&short));
+        assert!(session_exists(&long));
+        assert!(kill_session(&long));
+
+        let trial = trial_run("echo trial-ok; exit 3", &dir, Duration::from_secs(5)).unwrap();
+        assert!(trial.output.contains("trial-ok"));
+        assert!(trial.finished);
+        assert!(trial.exit_status.is_none_or(|code| code == 3));
     }
 }
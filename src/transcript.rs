@@ -0,0 +1,61 @@
+use color_eyre::Result;
+use std::path::{Path, PathBuf};
+
+/// Size cap for a saved transcript when the project doesn't set one, in KiB
+pub const DEFAULT_MAX_KB: u64 = 1024;
+
+/// Where the transcript of `session_id` taken at `timestamp` is written
+pub fn path_for(dir: &Path, project_id: i64, session_id: i64, timestamp: i64) -> PathBuf {
+    dir.join(project_id.to_string()).join(format!("{}-{}.txt", session_id, timestamp))
+}
+
+/// Keep at most `max_bytes` of a transcript. The end is kept, since that's where the
+/// agent finished, and a marker line says how much was cut
+pub fn truncate_tail(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut start = text.len() - max_bytes;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    // Start on a whole line when there is one
+    if let Some(newline) = text[start..].find('\n') {
+        start += newline + 1;
+    }
+    format!("[… {} bytes truncated …]\n{}", start, &text[start..])
+}
+
+/// Write a transcript on a background thread, so killing a session with a long
+/// scrollback doesn't stall the board. The file appears complete or not at all
+pub fn write_in_background(path: PathBuf, text: String, max_bytes: usize) {
+    std::thread::spawn(move || {
+        if let Err(e) = write(&path, &truncate_tail(&text, max_bytes)) {
+            tracing::error!(path = %path.display(), error = %e, "could not write transcript");
+        }
+    });
+}
+
+fn write(path: &Path, text: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = path.with_extension("txt.partial");
+    std::fs::write(&partial, text)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_tail() {
+        assert_eq!(truncate_tail("short", 10), "short");
+        let text = "first line\nsecond line\nlast line\n";
+        assert_eq!(truncate_tail(text, 15), "[… 23 bytes truncated …]\nlast line\n");
+        // Never splits a character
+        assert_eq!(truncate_tail("ééé", 3), "[… 4 bytes truncated …]\né");
+    }
+}
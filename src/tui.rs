@@ -3,20 +3,30 @@ use std::io::{self, stdout, Stdout};
 use crossterm::{
     event::{DisableBracketedPaste, EnableBracketedPaste},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle},
 };
 use ratatui::prelude::*;
 
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
+/// Save the window title on the terminal's title stack (xterm `CSI 22;0t`), so
+/// `restore` can put back whatever the shell had set
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
 pub fn init() -> io::Result<Tui> {
-    execute!(stdout(), EnterAlternateScreen, EnableBracketedPaste)?;
+    execute!(stdout(), Print(PUSH_TITLE), EnterAlternateScreen, EnableBracketedPaste)?;
     enable_raw_mode()?;
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 pub fn restore() -> io::Result<()> {
-    execute!(stdout(), LeaveAlternateScreen, DisableBracketedPaste)?;
+    execute!(stdout(), LeaveAlternateScreen, DisableBracketedPaste, Print(POP_TITLE))?;
     disable_raw_mode()?;
     Ok(())
 }
+
+pub fn set_title(title: &str) -> io::Result<()> {
+    execute!(stdout(), SetTitle(title))
+}
@@ -2,51 +2,360 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph},
     Frame,
 };
 
-use crate::app::{App, InputMode, View};
-use crate::db::{Session, Status};
+use crate::app::{format_duration, format_time_spent, snoozed_for, App, InputMode, ProjectSetting, SettingsTab, View, PROJECT_FLASH};
+use crate::card::{CardLine, MAX_WRAPPED_LINES};
+use crate::db::{Session, Status, ValueSource};
+use crate::git::{self, DirtyStatus};
 use crate::tmux;
 
 pub fn render(app: &App, frame: &mut Frame) {
     match app.view {
         View::Kanban => render_kanban_view(app, frame),
         View::Settings => render_settings_view(app, frame),
+        View::Queue => render_queue_view(app, frame),
+        View::Overview => render_overview_view(app, frame),
     }
+    if app.show_frame_stats {
+        render_frame_stats(app, frame);
+    }
+    app.theme.apply(frame.buffer_mut());
 }
 
-fn render_kanban_view(app: &App, frame: &mut Frame) {
-    let chunks = Layout::default()
+/// The board screen's header, columns and footer
+fn kanban_view_chunks(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // header
             Constraint::Min(0),    // kanban
             Constraint::Length(1), // footer
         ])
-        .split(frame.area());
+        .split(area)
+}
+
+fn render_kanban_view(app: &App, frame: &mut Frame) {
+    let chunks = kanban_view_chunks(frame.area());
 
     render_header(app, frame, chunks[0]);
     render_kanban(app, frame, chunks[1]);
     render_kanban_footer(app, frame, chunks[2]);
+    render_board_popups(app, frame);
+}
+
+fn render_queue_view(app: &App, frame: &mut Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // header
+            Constraint::Min(0),    // queue
+            Constraint::Length(1), // footer
+        ])
+        .split(frame.area());
+
+    render_header(app, frame, chunks[0]);
+    render_queue(app, frame, chunks[1]);
+
+    let (text, style) = match app.status_message {
+        Some(ref msg) => (msg.as_str(), Style::default().fg(Color::Green)),
+        None => (
+            "Tab/Esc: board | jk: nav | Enter: term | e: edit | m: move | c: comments | Space: peek",
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+    frame.render_widget(Paragraph::new(text).style(style), chunks[2]);
+    render_board_popups(app, frame);
+}
+
+fn render_queue(app: &App, frame: &mut Frame, area: Rect) {
+    let queue = app.queue();
+    let selected_id = app.selected_session().map(|s| s.id);
+
+    let items: Vec<ListItem> = queue
+        .iter()
+        .filter_map(|item| {
+            let session = app.sessions.iter().find(|s| s.id == item.session_id)?;
+            let selected = Some(session.id) == selected_id;
+            let marker = if selected { "> " } else { "  " };
+            let name_style = if selected {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            Some(ListItem::new(Line::from(vec![
+                Span::styled(marker, name_style),
+                Span::styled(format!("{:<14}", item.reason), Style::default().fg(Color::Cyan)),
+                Span::styled(session.name.clone(), name_style),
+                Span::styled(
+                    format!("  {}", app.status_label(session.status)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])))
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(format!(" Review Queue ({}) ", items.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("Nothing needs attention")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+/// Frames drawn so far and the recent rate, in the top right corner (F12)
+fn render_frame_stats(app: &App, frame: &mut Frame) {
+    // Counting this frame too, since it's being drawn
+    let text = format!(" frame {} · {:.1}/s ", app.frames + 1, app.frame_rate());
+    let area = frame.area();
+    let width = (text.chars().count() as u16).min(area.width);
+    let rect = Rect::new(area.x + area.width - width, area.y, width, 1.min(area.height));
+    frame.render_widget(Clear, rect);
+    frame.render_widget(Paragraph::new(text).style(Style::default().fg(Color::Black).bg(Color::Yellow)), rect);
+}
+
+fn render_overview_view(app: &App, frame: &mut Frame) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // header
+            Constraint::Min(0),    // projects
+            Constraint::Length(1), // footer
+        ])
+        .split(frame.area());
+
+    render_header(app, frame, chunks[0]);
+    render_overview(app, frame, chunks[1]);
+
+    let (text, style) = match app.status_message {
+        Some(ref msg) => (msg.as_str(), Style::default().fg(Color::Green)),
+        None => (
+            "V/Esc: board | jk: nav | Enter: attach / open project | o: open with card selected | *: pin | a: archive | A: show archived | q: quit",
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+    frame.render_widget(Paragraph::new(text).style(style), chunks[2]);
+    if app.input_mode == InputMode::ConfirmQuit {
+        render_confirm_quit_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmSharedAttach {
+        render_confirm_shared_attach_popup(app, frame);
+    }
+}
+
+fn render_overview(app: &App, frame: &mut Frame, area: Rect) {
+    let items: Vec<ListItem> = app
+        .overview_rows()
+        .iter()
+        .enumerate()
+        .map(|(i, (project, waiting))| {
+            let entry = &app.overview[*project];
+            let selected = i == app.selected_overview;
+            let marker = if selected { "> " } else { "  " };
+            let highlight = |style: Style| if selected { style.fg(Color::Yellow).add_modifier(Modifier::BOLD) } else { style };
+            let line = match waiting {
+                None => {
+                    let current = entry.project.id == app.project.id;
+                    let waiting_style = if entry.waiting.is_empty() {
+                        Style::default().fg(Color::DarkGray)
+                    } else {
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    };
+                    let name_style = match entry.project.archived {
+                        true => Style::default().fg(Color::DarkGray),
+                        false => Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+                    };
+                    let opened = match entry.project.last_opened_at {
+                        Some(at) => {
+                            let ago = std::time::Duration::from_secs((crate::db::unix_now() - at).max(0) as u64);
+                            format!("{:>6} ago", crate::app::format_duration(ago))
+                        }
+                        None => format!("{:>10}", "never"),
+                    };
+                    let mut notes = Vec::new();
+                    if current {
+                        notes.push("this board");
+                    }
+                    if entry.project.archived {
+                        notes.push("archived");
+                    }
+                    Line::from(vec![
+                        Span::styled(marker, highlight(Style::default())),
+                        Span::styled(if entry.project.pinned { "★ " } else { "  " }, highlight(Style::default())),
+                        Span::styled(format!("{:<24}", entry.project.name), highlight(name_style)),
+                        Span::styled(format!("{:>3} waiting", entry.waiting.len()), waiting_style),
+                        Span::styled(format!("{:>4} running", entry.active), Style::default().fg(Color::DarkGray)),
+                        Span::styled(opened, Style::default().fg(Color::DarkGray)),
+                        Span::styled(
+                            if notes.is_empty() { String::new() } else { format!("  ({})", notes.join(", ")) },
+                            Style::default().fg(Color::DarkGray),
+                        ),
+                    ])
+                }
+                Some(w) => Line::from(vec![
+                    Span::styled(marker, highlight(Style::default())),
+                    Span::styled("    ? ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+                    Span::styled(entry.waiting[*w].name.clone(), highlight(Style::default().fg(Color::White))),
+                ]),
+            };
+            ListItem::new(line)
+        })
+        .collect();
+
+    let waiting: usize = app.overview.iter().map(|entry| entry.waiting.len()).sum();
+    let block = Block::default()
+        .title(match app.show_archived_projects {
+            true => format!(" Overview ({} waiting, archived shown) ", waiting),
+            false => format!(" Overview ({} waiting) ", waiting),
+        })
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("No projects yet")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+/// Popups and overlays shared by the board and queue views
+fn render_board_popups(app: &App, frame: &mut Frame) {
+    // Popups opened from the peek overlay (e.g. spinning off a session) draw over it
+    if app.peek_active {
+        render_peek_overlay(app, frame);
+    }
 
     if app.input_mode == InputMode::NewSession {
-        render_input_popup(app, frame, "New Session");
+        match app.new_session_status {
+            Some(status) => render_input_popup(app, frame, &format!("New Session in {}", app.status_label(status)), "Name"),
+            None => render_input_popup(app, frame, "New Session", "Name"),
+        }
+    } else if app.input_mode == InputMode::NewCardField {
+        if let Some(ref prompt) = app.new_card_prompt {
+            let field = prompt.field_ids.get(prompt.index).and_then(|id| app.fields.iter().find(|f| f.id == *id));
+            let title = format!(
+                "{} for {} ({}/{})",
+                field.map_or("Field", |f| f.name.as_str()),
+                prompt.session_name,
+                prompt.index + 1,
+                prompt.field_ids.len()
+            );
+            render_input_popup(app, frame, &title, "Enter: next (empty leaves it unset) | Esc: skip the rest");
+        }
+    } else if app.input_mode == InputMode::PlanSessions {
+        render_plan_sessions_popup(app, frame);
+    } else if app.input_mode == InputMode::Notes {
+        render_notes_popup(app, frame);
     } else if app.input_mode == InputMode::EditSession {
         render_edit_session_popup(app, frame);
+        if app.show_ai_error {
+            render_ai_error_popup(app, frame);
+        }
+    } else if app.input_mode == InputMode::PickPromptTemplate {
+        render_edit_session_popup(app, frame);
+        render_pick_prompt_template_popup(app, frame);
+    } else if app.input_mode == InputMode::BranchChanges {
+        render_branch_changes_popup(app, frame);
+    } else if app.input_mode == InputMode::Land {
+        render_land_popup(app, frame);
+    } else if app.input_mode == InputMode::BoardChanges {
+        render_board_changes_popup(app, frame);
+    } else if app.input_mode == InputMode::ReloadProblems {
+        render_reload_problems_popup(app, frame);
+    } else if app.input_mode == InputMode::Transcripts {
+        render_transcripts_popup(app, frame);
+    } else if app.input_mode == InputMode::TranscriptView {
+        render_transcript_view(app, frame);
+    } else if app.input_mode == InputMode::PickUrl {
+        render_pick_url_popup(app, frame);
     } else if app.input_mode == InputMode::MoveSession {
-        render_move_popup(frame);
+        if let Some(session) = app.moving_session() {
+            render_move_popup(app, frame, session);
+        }
     } else if app.input_mode == InputMode::ConfirmDelete {
         render_confirm_delete_popup(app, frame);
     } else if app.input_mode == InputMode::ConfirmDeleteDirty {
         render_confirm_delete_dirty_popup(app, frame);
     } else if app.input_mode == InputMode::ViewComments || app.input_mode == InputMode::NewComment {
         render_comments_popup(app, frame);
-    }
-
-    if app.peek_active {
-        render_peek_overlay(app, frame);
+    } else if app.input_mode == InputMode::SessionEnv || app.input_mode == InputMode::NewEnvVar {
+        render_session_env_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmApplyEnv {
+        render_confirm_apply_env_popup(frame);
+    } else if app.input_mode == InputMode::ConfirmSeedFields {
+        render_confirm_seed_fields_popup(frame);
+    } else if app.input_mode == InputMode::ConfirmRelocate {
+        render_confirm_relocate_popup(app, frame);
+    } else if app.input_mode == InputMode::RepoConfig {
+        render_repo_config_popup(app, frame);
+    } else if app.input_mode == InputMode::WorktreeMaintenance {
+        render_worktree_maintenance_popup(app, frame);
+    } else if app.input_mode == InputMode::Snooze {
+        render_input_popup(app, frame, "Snooze", "3h, 1d, 2w or YYYY-MM-DD (empty = wake now)");
+    } else if app.input_mode == InputMode::DueDate {
+        render_input_popup(app, frame, "Due date", "YYYY-MM-DD, today, tomorrow, +3d or +2w (empty = none)");
+    } else if app.input_mode == InputMode::PickQuickField {
+        render_pick_quick_field_popup(app, frame);
+    } else if app.input_mode == InputMode::QuickFieldValue {
+        let field = app.fields.get(app.selected_quick_field);
+        let name = field.map_or("Field", |f| f.name.as_str());
+        let hint = field.map(|f| f.description.trim()).filter(|d| !d.is_empty() && !d.contains('\n'));
+        match app.marked_sessions.len() {
+            0 => render_input_popup(app, frame, name, hint.unwrap_or("Enter: save, Esc: cancel")),
+            marked => {
+                let title = format!("{} on {} marked card{}", name, marked, if marked == 1 { "" } else { "s" });
+                let label = match app.quick_field_only_empty {
+                    true => "Only where empty (Tab: all), Enter: save",
+                    false => "All, replacing values (Tab: only empty), Enter: save",
+                };
+                render_input_popup(app, frame, &title, label);
+            }
+        }
+    } else if app.input_mode == InputMode::BlockedBy {
+        let current = app.blocking_session_id.and_then(|id| app.blocker_names(id).ok()).unwrap_or_default();
+        let title = match current.is_empty() {
+            true => "Blocked by".to_string(),
+            false => format!("Blocked by {}", current.join(", ")),
+        };
+        render_input_popup(app, frame, &title, "Name or id of a card this one waits on (empty = clear)");
+    } else if app.input_mode == InputMode::CardActions {
+        render_card_actions_popup(app, frame);
+    } else if app.input_mode == InputMode::DeadPane {
+        render_dead_pane_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmRecreateWorktree {
+        render_confirm_recreate_worktree_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmAdoptTerminal {
+        render_confirm_adopt_terminal_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmRestartTerminal {
+        render_confirm_restart_terminal_popup(app, frame);
+    } else if app.input_mode == InputMode::MoveChecklist {
+        render_move_checklist_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmQuit {
+        render_confirm_quit_popup(app, frame);
+    } else if app.input_mode == InputMode::ConfirmSharedAttach {
+        render_confirm_shared_attach_popup(app, frame);
+    } else if app.input_mode == InputMode::BatchFillFields {
+        render_batch_fill_fields_popup(app, frame);
+    } else if app.input_mode == InputMode::BatchFill {
+        render_batch_fill_popup(app, frame);
+    } else if app.input_mode == InputMode::PickShortcut {
+        render_pick_shortcut_popup(app, frame);
+    } else if app.input_mode == InputMode::ShortcutValue {
+        let shortcut = app.picked_shortcut.and_then(|i| app.shortcuts.get(i));
+        let title = shortcut.map_or("Shortcut", |s| s.name.as_str());
+        let label = shortcut.and_then(|s| app.shortcut_field_name(s)).unwrap_or("Value");
+        render_input_popup(app, frame, title, label);
     }
 }
 
@@ -60,28 +369,246 @@ fn render_settings_view(app: &App, frame: &mut Frame) {
         ])
         .split(frame.area());
 
-    let header = Paragraph::new("Settings: Custom Fields")
-        .style(Style::default().fg(Color::Cyan))
+    let tabs = [
+        (SettingsTab::Fields, "Custom Fields"),
+        (SettingsTab::CardLayout, "Card Layout"),
+        (SettingsTab::Statuses, "Statuses"),
+        (SettingsTab::Project, "Project"),
+        (SettingsTab::Shortcuts, "Shortcuts"),
+        (SettingsTab::Watch, "Watch"),
+        (SettingsTab::Prompts, "Prompts"),
+        (SettingsTab::Trash, "Trash"),
+    ];
+    let mut spans = vec![Span::styled("Settings: ", Style::default().fg(Color::Cyan))];
+    for (tab, label) in tabs {
+        let style = if app.settings_tab == tab {
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        spans.push(Span::styled(format!(" {} ", label), style));
+    }
+    spans.push(Span::styled("  (Tab: switch)", Style::default().fg(Color::DarkGray)));
+    let header = Paragraph::new(Line::from(spans))
         .block(Block::default().borders(Borders::BOTTOM));
     frame.render_widget(header, chunks[0]);
 
-    render_fields_list(app, frame, chunks[1]);
-
-    let help = "q/Esc: back | n: new | e: edit | d: delete | v: toggle visible | jk: nav | JK: reorder";
-    let footer = Paragraph::new(help).style(Style::default().fg(Color::DarkGray));
+    let help = match app.settings_tab {
+        SettingsTab::Fields => {
+            let (list_area, preview_area) = preview_layout(chunks[1], card_height(app));
+            render_fields_list(app, frame, list_area);
+            render_card_preview(app, frame, preview_area);
+            "q/Esc: back | n: new | e: edit | d: delete | v/p/a/w: toggle on cards/in edit popup/in AI fill/wrapped | c: copy from project | Space: expand | jk: nav | JK: reorder | B: backup"
+        }
+        SettingsTab::CardLayout => {
+            let (list_area, preview_area) = preview_layout(chunks[1], card_height(app));
+            render_card_layout_list(app, frame, list_area);
+            render_card_preview(app, frame, preview_area);
+            "q/Esc: back | n: add line | d: remove | jk: nav | JK: reorder | r: reset | B: backup"
+        }
+        SettingsTab::Statuses => {
+            render_statuses_list(app, frame, chunks[1]);
+            "q/Esc: back | e: rename | c: cycle color | l: entry checklist | f: new-card fields | o: order by activity | r: reset | jk: nav | B: backup"
+        }
+        SettingsTab::Project => {
+            render_project_settings(app, frame, chunks[1]);
+            "q/Esc: back | e/Enter: edit | jk: nav | B: backup"
+        }
+        SettingsTab::Shortcuts => {
+            render_shortcuts_list(app, frame, chunks[1]);
+            "q/Esc: back | n: new | d: delete | jk: nav | B: backup"
+        }
+        SettingsTab::Watch => {
+            render_watch_rules(app, frame, chunks[1]);
+            "q/Esc: back | n: new | d: delete | jk: nav | B: backup"
+        }
+        SettingsTab::Prompts => {
+            render_prompt_templates_list(app, frame, chunks[1]);
+            "q/Esc: back | n: new | e/Enter: edit | d: delete | jk: nav | B: backup"
+        }
+        SettingsTab::Trash => {
+            render_trash_list(app, frame, chunks[1]);
+            "q/Esc: back | u: restore | d: delete forever | jk: nav | B: backup"
+        }
+    };
+    let footer = match app.status_message {
+        Some(ref msg) => Paragraph::new(msg.as_str()).style(Style::default().fg(Color::Green)),
+        None => Paragraph::new(help).style(Style::default().fg(Color::DarkGray)),
+    };
     frame.render_widget(footer, chunks[2]);
 
     match app.input_mode {
-        InputMode::NewFieldName => render_field_popup(app, frame, "New Field", "Name", &app.new_field_name),
-        InputMode::NewFieldDesc => render_field_popup(app, frame, "New Field", "Description", &app.new_field_desc),
-        InputMode::EditFieldName => render_field_popup(app, frame, "Edit Field", "Name", &app.new_field_name),
-        InputMode::EditFieldDesc => render_field_popup(app, frame, "Edit Field", "Description", &app.new_field_desc),
+        InputMode::NewFieldName => render_field_popup(app, frame, "New Field", "Description", true),
+        InputMode::NewFieldDesc => render_field_popup(app, frame, "New Field", "Description", false),
+        InputMode::EditFieldName => render_field_popup(app, frame, "Edit Field", "Description", true),
+        InputMode::EditFieldDesc => render_field_popup(app, frame, "Edit Field", "Description", false),
+        InputMode::PromptTemplateName | InputMode::PromptTemplateBody => {
+            let title = if app.editing_prompt_template_id.is_some() { "Edit Prompt" } else { "New Prompt" };
+            render_field_popup(app, frame, title, "Prompt", app.input_mode == InputMode::PromptTemplateName)
+        }
         InputMode::ConfirmDeleteField => render_confirm_delete_field_popup(app, frame),
+        InputMode::ConfirmPurgeSession => render_confirm_purge_session_popup(app, frame),
+        InputMode::CopyFieldsFrom => render_copy_fields_popup(app, frame),
+        InputMode::PickCardLine => render_pick_card_line_popup(app, frame),
+        InputMode::NewShortcut => render_input_popup(app, frame, "New Shortcut", "name | column | field (optional)"),
+        InputMode::NewWatchRule => {
+            render_input_popup(app, frame, "New Watch Rule", "regex | move <column>, set <field> or notify")
+        }
+        InputMode::EditStatusLabel => render_input_popup(app, frame, "Rename Status", "Label (empty = default)"),
+        InputMode::EditStatusChecklist => {
+            render_input_popup(app, frame, "Entry Checklist", "Items separated by ; (empty = move instantly)")
+        }
+        InputMode::EditStatusNewCardFields => {
+            render_input_popup(app, frame, "New Card Fields", "Field names separated by ; (empty = just the name)")
+        }
+        InputMode::EditProjectSetting | InputMode::StartupTrial
+            if ProjectSetting::all()[app.selected_project_setting] == ProjectSetting::StartupCommand =>
+        {
+            render_startup_command_popup(app, frame);
+            if app.input_mode == InputMode::StartupTrial {
+                render_startup_trial_popup(app, frame);
+            }
+        }
+        InputMode::EditProjectSetting => {
+            let setting = ProjectSetting::all()[app.selected_project_setting];
+            let label = match setting {
+                ProjectSetting::BranchPrefix => "Prefix for new branches, e.g. wb/",
+                ProjectSetting::BranchMaxLen => "Characters kept from the session name (empty = default)",
+                ProjectSetting::BaseBranch => "Start point (empty = auto-detect)",
+                ProjectSetting::StartupCommand => "Command for new terminals",
+                ProjectSetting::Autostart => "on: start the terminal when a card enters In Progress",
+                ProjectSetting::SkipBlocked => "on: w passes over cards waiting on another card",
+                ProjectSetting::StaleDays => "Flag a column whose oldest card is this old (0 = never)",
+                ProjectSetting::ArchiveDays => "Hide Done cards this long after they're done (0 = never); O shows them",
+                ProjectSetting::Transcripts => "on: save the scrollback when a session's terminal is killed",
+                ProjectSetting::TranscriptMaxKb => "Keep at most this much of each transcript (the end)",
+                ProjectSetting::WatchDryRun => "on: log watch rule matches without acting on them",
+                ProjectSetting::WatchCooldown => "Minutes before a rule fires again for the same session",
+                ProjectSetting::RedactPatterns => "Extra regexes, space-separated (\\s for spaces)",
+                ProjectSetting::AiContextChars => "Most recent pane output sent with an AI fill, in characters",
+                ProjectSetting::NotesPath => "Path template: {worktree}, {id} (empty = {worktree}/.workbench-notes.md)",
+                ProjectSetting::TrackTime => "off: don't record how long terminals are in use",
+                ProjectSetting::JiraUrl => "Base URL, e.g. https://acme.atlassian.net (empty = off)",
+                ProjectSetting::JiraAuth => "Token env var: VAR, or email:VAR for Jira Cloud",
+                ProjectSetting::JiraFields => "Fields for key, summary, URL, e.g. Ticket, Summary, Link",
+            };
+            render_input_popup(app, frame, setting.label(), label)
+        }
         _ => {}
     }
 }
 
+/// The startup command editor: the template, then what it expands to for the selected
+/// session (or sample values), or why it can't expand
+fn render_startup_command_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(70, 30, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Startup command (Enter: save, Ctrl+T: test run, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(inner);
+
+    let label = format!("Placeholders: {}", crate::startup::PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(" "));
+    let input = Paragraph::new(app.input_buffer.as_str())
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::default().borders(Borders::BOTTOM).title(label));
+    frame.render_widget(input, chunks[0]);
+
+    let source = match app.selected_session() {
+        Some(session) => format!("Preview for {}:", session.name),
+        None => "Preview (sample values):".to_string(),
+    };
+    let dim = Style::default().fg(Color::DarkGray);
+    let error = Style::default().fg(Color::Red);
+    let mut lines = match crate::startup::expand(app.input_buffer.trim(), &app.startup_command_values()) {
+        Ok(command) if command.is_empty() => vec![Line::styled("Empty: new terminals start a plain shell", dim)],
+        Ok(command) => vec![Line::styled(source, dim), Line::from(command)],
+        Err(e) => vec![Line::styled(format!("⚠ {}", e), error)],
+    };
+    if let Some(ref e) = app.input_error {
+        lines.push(Line::styled(format!("⚠ {}", e), error));
+    }
+    let preview = Paragraph::new(lines).wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(preview, chunks[1]);
+}
+
+fn render_startup_trial_popup(app: &App, frame: &mut Frame) {
+    let Some(ref view) = app.startup_trial else { return };
+    let area = centered_rect(80, 70, frame.area());
+    frame.render_widget(Clear, area);
+
+    let status = match view.result {
+        None => "running".to_string(),
+        Some(Ok(ref run)) => match (run.finished, run.exit_status) {
+            (false, _) => "still running, stopped".to_string(),
+            (true, Some(code)) => format!("exited {}", code),
+            (true, None) => "exited".to_string(),
+        },
+        Some(Err(_)) => "failed".to_string(),
+    };
+    let block = Block::default()
+        .title(format!(" Test run: {} (jk: scroll, Esc: close) ", status))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::styled(format!("$ {}", view.command), dim)];
+    match view.result {
+        None => lines.push(Line::styled("Running in a throwaway tmux session...", dim)),
+        Some(Err(ref e)) => lines.push(Line::styled(e.clone(), Style::default().fg(Color::Red))),
+        Some(Ok(ref run)) => lines.extend(run.output.lines().map(|line| Line::from(line.to_string()))),
+    }
+
+    let body = Paragraph::new(lines).block(block).scroll((view.scroll, 0));
+    frame.render_widget(body, area);
+}
+
+fn render_copy_fields_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(60, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Copy fields from (Enter: copy missing ones, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let items: Vec<ListItem> = app
+        .copy_sources
+        .iter()
+        .enumerate()
+        .map(|(idx, (project, fields))| {
+            let style = if idx == app.selected_copy_source {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let names = fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>().join(", ");
+            ListItem::new(vec![
+                Line::from(format!("{} ({} fields)  {}", project.name, fields.len(), project.path.display())),
+                Line::from(Span::styled(format!("   {}", names), Style::default().fg(Color::DarkGray))),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+/// Lines of a description shown under its field in settings
+const FIELD_DESCRIPTION_LINES: usize = 3;
+
 fn render_fields_list(app: &App, frame: &mut Frame, area: Rect) {
+    // Borders plus the indent under the field name
+    let desc_width = (area.width as usize).saturating_sub(2 + 3);
     let items: Vec<ListItem> = app
         .fields
         .iter()
@@ -99,13 +626,30 @@ fn render_fields_list(app: &App, frame: &mut Frame, area: Rect) {
                 Style::default().fg(Color::White)
             };
 
-            let visibility = if field.visible { "👁" } else { "  " };
-            let text = if field.description.is_empty() {
-                format!("{} {}", visibility, field.name)
-            } else {
-                format!("{} {} - {}", visibility, field.name, field.description)
+            // Where the field shows up: on cards, in the edit popup, in AI fill prompts; and
+            // whether it wraps on cards
+            let legend = |label: &str, on: bool| {
+                let style = match (on, is_selected) {
+                    (_, true) => Style::default(),
+                    (true, false) => Style::default().fg(Color::Green),
+                    (false, false) => Style::default().fg(Color::DarkGray),
+                };
+                Span::styled(format!("  {} {}", label, if on { "✓" } else { "✗" }), style)
             };
-            ListItem::new(text).style(style)
+            let mut lines = vec![Line::from(vec![
+                Span::raw(format!("{} {}", if field.visible { "👁" } else { "  " }, field.name)),
+                legend("card", field.visible),
+                legend("edit", field.in_edit),
+                legend("AI", field.in_ai),
+                legend("wrap", field.wrap),
+            ])];
+            let max_lines = (app.expanded_field_id != Some(field.id)).then_some(FIELD_DESCRIPTION_LINES);
+            let (desc_lines, truncated) = wrap_text(&field.description, desc_width, max_lines);
+            lines.extend(desc_lines.into_iter().map(|line| Line::from(format!("   {}", line))));
+            if truncated {
+                lines.push(Line::from("   … (Space: show all)"));
+            }
+            ListItem::new(lines).style(style)
         })
         .collect();
 
@@ -118,441 +662,2782 @@ fn render_fields_list(app: &App, frame: &mut Frame, area: Rect) {
     frame.render_widget(list, area);
 }
 
-fn render_field_popup(app: &App, frame: &mut Frame, title: &str, field_label: &str, value: &str) {
-    let area = centered_rect(50, 30, frame.area());
-    frame.render_widget(Clear, area);
-
-    let block = Block::default()
-        .title(format!(" {} ", title))
-        .borders(Borders::ALL)
-        .style(Style::default().bg(Color::Black));
-
-    let inner = block.inner(area);
-    frame.render_widget(block, area);
-
-    let inner_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Length(3)])
-        .split(inner);
-
-    // Show name field
-    let name_style = if field_label == "Name" {
-        Style::default().fg(Color::Yellow)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
-    let name_value = if field_label == "Name" { value } else { &app.new_field_name };
-    let name_input = Paragraph::new(name_value.to_string())
-        .style(name_style)
-        .block(Block::default().borders(Borders::BOTTOM).title("Name"));
-    frame.render_widget(name_input, inner_chunks[0]);
+/// Width of the preview card, as a board column on this terminal would give it
+fn preview_card_width(frame_width: u16) -> u16 {
+    (frame_width / Status::all().len() as u16).max(MIN_COLUMN_WIDTH) - 2
+}
 
-    // Show description field
-    let desc_style = if field_label == "Description" {
-        Style::default().fg(Color::Yellow)
+/// Split a settings tab into its list and the card preview: beside the list when
+/// there's room for both, below it otherwise
+fn preview_layout(area: Rect, card_height: u16) -> (Rect, Rect) {
+    let preview_width = preview_card_width(area.width) + 2;
+    let (direction, preview) = if area.width >= preview_width + 2 * MIN_COLUMN_WIDTH {
+        (Direction::Horizontal, Constraint::Length(preview_width))
     } else {
-        Style::default().fg(Color::DarkGray)
+        (Direction::Vertical, Constraint::Length(card_height + 2))
     };
-    let desc_value = if field_label == "Description" { value } else { &app.new_field_desc };
-    let desc_input = Paragraph::new(desc_value.to_string())
-        .style(desc_style)
-        .block(Block::default().borders(Borders::BOTTOM).title("Description"));
-    frame.render_widget(desc_input, inner_chunks[1]);
-}
-
-fn render_header(app: &App, frame: &mut Frame, area: Rect) {
-    let header = Paragraph::new(format!("Project: {} ({})", app.project.name, app.project.path))
-        .style(Style::default().fg(Color::Cyan))
-        .block(Block::default().borders(Borders::BOTTOM));
-    frame.render_widget(header, area);
-}
-
-fn render_kanban(app: &App, frame: &mut Frame, area: Rect) {
-    let statuses = Status::all();
-    let columns = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints(vec![Constraint::Ratio(1, statuses.len() as u32); statuses.len()])
+    let rects = Layout::default()
+        .direction(direction)
+        .constraints([Constraint::Min(0), preview])
         .split(area);
+    (rects[0], rects[1])
+}
 
-    for (col_idx, status) in statuses.iter().enumerate() {
-        let sessions = app.sessions_by_status(*status);
-        let is_selected_column = col_idx == app.selected_column;
-
-        let border_style = if is_selected_column {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default().fg(Color::DarkGray)
-        };
+/// A card as the board would draw it with the current field order and visibility,
+/// using the longest-named session (or sample data on an empty board)
+fn render_card_preview(app: &App, frame: &mut Frame, area: Rect) {
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let (name, content) = match app.sessions.iter().max_by_key(|s| s.name.chars().count()) {
+        Some(session) => (session.name.clone(), session_card_content(app, session)),
+        None => (
+            "Fix login redirect loop".to_string(),
+            CardContent {
+                branch: Some(format!("{}fix-login-redirect-loop", crate::git::DEFAULT_BRANCH_PREFIX)),
+                values: app.fields.iter().map(|f| format!("Sample {}", f.name.to_lowercase())).collect(),
+                ai: vec![false; app.fields.len()],
+                age: (2 * 86400, 3 * 3600),
+                dirty: Some(DirtyStatus { staged: 1, unstaged: 2, untracked: 0 }),
+                last_output: Some("All 42 tests passed".to_string()),
+            },
+        ),
+    };
 
-        // Render column header
-        let title = format!(" {} ({}) ", status.label(), sessions.len());
-        let column_block = Block::default()
-            .title(title)
-            .borders(Borders::ALL)
-            .border_style(border_style);
-        let inner_area = column_block.inner(columns[col_idx]);
-        frame.render_widget(column_block, columns[col_idx]);
+    let width = preview_card_width(frame.area().width).min(inner.width);
+    let entries = card_lines(app, &content, width.saturating_sub(2) as usize);
+    let height = card_rows(app, &entries);
+    let block = Block::default()
+        .title(format!(" Preview ({} lines) ", height))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(block, area);
 
-        // Calculate card heights and render each card
-        let visible_fields = app.fields.iter().filter(|f| f.visible).count();
-        let card_height = 4 + visible_fields as u16; // base height + visible fields
-        let mut y_offset = 0u16;
+    let card_area = Rect { width, height: height.min(inner.height), ..inner };
+    let card_block = Block::default()
+        .title(Span::styled(
+            format!(" {} ", name),
+            Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let card_inner = card_block.inner(card_area);
+    frame.render_widget(card_block, card_area);
+    let body = fit_card_body(entries, card_inner.height as usize);
+    frame.render_widget(Paragraph::new(body), card_inner);
+}
 
-        for (row_idx, session) in sessions.iter().enumerate() {
-            if y_offset >= inner_area.height {
-                break; // No more room
+/// Word-wrap `text` to `width` columns, keeping its line breaks. With `max_lines`, also
+/// returns whether lines were cut off
+fn wrap_text(text: &str, width: usize, max_lines: Option<usize>) -> (Vec<String>, bool) {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.trim().lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let line_len = line.chars().count();
+            if line_len > 0 && line_len + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
             }
-
-            let card_area = Rect {
-                x: inner_area.x,
-                y: inner_area.y + y_offset,
-                width: inner_area.width,
-                height: card_height.min(inner_area.height - y_offset),
-            };
-
-            render_session_card(app, frame, session, is_selected_column, row_idx, card_area);
-            y_offset += card_height;
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    if text.trim().is_empty() {
+        lines.clear();
+    }
+    match max_lines {
+        Some(max) if lines.len() > max => {
+            lines.truncate(max);
+            (lines, true)
         }
+        _ => (lines, false),
     }
 }
 
-fn render_session_card(app: &App, frame: &mut Frame, session: &Session, is_selected_column: bool, row_idx: usize, area: Rect) {
-    let is_selected = is_selected_column && row_idx == app.selected_row;
+fn render_card_layout_list(app: &App, frame: &mut Frame, area: Rect) {
+    let items: Vec<ListItem> = app
+        .card_layout
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let style = if idx == app.selected_card_line {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{}. {}", idx + 1, line.label(&app.fields))).style(style)
+        })
+        .collect();
 
-    let border_style = if is_selected {
-        Style::default().fg(Color::Yellow)
-    } else {
-        Style::default().fg(Color::DarkGray)
+    let block = Block::default()
+        .title(format!(" Card lines ({}/{}) ", app.card_layout.len(), crate::card::MAX_CARD_LINES))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("Cards show only their title. Press n to add a line.")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+fn render_pick_card_line_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(40, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Add card line (Enter: add, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let items: Vec<ListItem> = CardLine::choices(&app.fields)
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let style = if idx == app.selected_card_line_choice {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(line.label(&app.fields)).style(style)
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_pick_prompt_template_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(40, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Insert prompt (Enter: insert, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let items: Vec<ListItem> = app
+        .prompt_templates
+        .iter()
+        .enumerate()
+        .map(|(idx, template)| {
+            let style = if idx == app.selected_prompt_template {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(template.name.as_str()).style(style)
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_statuses_list(app: &App, frame: &mut Frame, area: Rect) {
+    let items: Vec<ListItem> = Status::all()
+        .iter()
+        .enumerate()
+        .map(|(idx, status)| {
+            let is_selected = idx == app.selected_status;
+            let label = app.status_label(*status);
+            let renamed = if label != status.label() {
+                format!(" (was {})", status.label())
+            } else {
+                String::new()
+            };
+            let color = app.status_color(*status).unwrap_or("default");
+
+            let style = if is_selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(status_color(app, *status).unwrap_or(Color::White))
+            };
+            let checklist = match app.status_checklist(*status).len() {
+                0 => String::new(),
+                n => format!("  checklist: {} item{}", n, if n == 1 { "" } else { "s" }),
+            };
+            let sort = match app.column_sort(*status) {
+                crate::db::ColumnSort::Activity => "  ↻ by activity",
+                crate::db::ColumnSort::Created => "",
+            };
+            let asks: Vec<&str> = app.new_card_fields(*status).iter().map(|f| f.name.as_str()).collect();
+            let asks = if asks.is_empty() { String::new() } else { format!("  new cards ask: {}", asks.join(", ")) };
+            ListItem::new(format!("{}{}  [{}]{}{}{}", label, renamed, color, checklist, sort, asks)).style(style)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(" Statuses ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let list = List::new(items).block(block);
+    frame.render_widget(list, area);
+}
+
+fn render_shortcuts_list(app: &App, frame: &mut Frame, area: Rect) {
+    let items: Vec<ListItem> = app
+        .shortcuts
+        .iter()
+        .enumerate()
+        .map(|(idx, shortcut)| {
+            let problem = app.shortcut_problem(shortcut);
+            let style = if idx == app.selected_shortcut {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else if problem.is_some() {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(shortcut_summary(app, idx, shortcut, problem)).style(style)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(" Shortcuts (N on the board) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("No shortcuts; press n to add one, e.g. Review PR | review | PR URL")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+fn render_watch_rules(app: &App, frame: &mut Frame, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(10)])
+        .split(area);
+
+    let items: Vec<ListItem> = app
+        .watch_rules
+        .iter()
+        .enumerate()
+        .map(|(idx, rule)| {
+            let action = match rule.action {
+                crate::db::WatchAction::Move(status) => format!("move to {}", app.status_label(status)),
+                crate::db::WatchAction::SetField(field_id) => match app.fields.iter().find(|f| f.id == field_id) {
+                    Some(field) => format!("set {} to group 1", field.name),
+                    None => "set a deleted field (disabled)".to_string(),
+                },
+                crate::db::WatchAction::Notify => "notify".to_string(),
+            };
+            let style = if idx == app.selected_watch_rule {
+                Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{}: /{}/ → {}", idx + 1, rule.pattern, action)).style(style)
+        })
+        .collect();
+
+    let dry_run = app.project_setting(ProjectSetting::WatchDryRun).as_deref() == Some("on");
+    let title = if dry_run {
+        " Watch rules: DRY RUN, matches are only logged (Project tab to change) "
+    } else {
+        " Watch rules: matched against new pane output "
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("No watch rules; press n to add one, e.g. All tasks complete | move Review")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, chunks[0]);
+    } else {
+        frame.render_widget(List::new(items).block(block), chunks[0]);
+    }
+
+    let log: Vec<ListItem> = app
+        .watch_log
+        .iter()
+        .map(|event| {
+            let dry = if event.kind == "watch_dry_run" { " (dry run)" } else { "" };
+            ListItem::new(format!("{}{} {}", event.created_at, dry, event.detail))
+        })
+        .collect();
+    let block = Block::default()
+        .title(" Recent automatic actions ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(List::new(log).block(block).style(Style::default().fg(Color::Gray)), chunks[1]);
+}
+
+/// `1: Review PR → Review, asks for PR URL` (with the reason when disabled)
+fn shortcut_summary(app: &App, idx: usize, shortcut: &crate::db::Shortcut, problem: Option<&str>) -> String {
+    let mut text = format!("{}: {} → {}", idx + 1, shortcut.name, app.status_label(shortcut.status));
+    if let Some(field) = app.shortcut_field_name(shortcut) {
+        text.push_str(&format!(", asks for {}", field));
+    }
+    if let Some(problem) = problem {
+        text.push_str(&format!("  (disabled: {})", problem));
+    }
+    text
+}
+
+fn render_prompt_templates_list(app: &App, frame: &mut Frame, area: Rect) {
+    // Borders plus the indent under the template name
+    let body_width = (area.width as usize).saturating_sub(2 + 3);
+    let items: Vec<ListItem> = app
+        .prompt_templates
+        .iter()
+        .enumerate()
+        .map(|(idx, template)| {
+            let style = if idx == app.selected_prompt_template {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let (body, truncated) = wrap_text(&template.body, body_width, Some(1));
+            let mut preview = body.into_iter().next().unwrap_or_default();
+            if truncated {
+                preview.push('…');
+            }
+            ListItem::new(vec![Line::from(template.name.clone()), Line::from(format!("   {}", preview))]).style(style)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(" Prompt templates (Ctrl+T in the AI edit mode) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("No templates; press n to add one, e.g. Summarise the PR for {ticket} in {session_name}")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+fn render_trash_list(app: &App, frame: &mut Frame, area: Rect) {
+    let now = crate::db::unix_now();
+    let items: Vec<ListItem> = app
+        .trashed_sessions
+        .iter()
+        .enumerate()
+        .map(|(idx, trashed)| {
+            let age = std::time::Duration::from_secs(now.saturating_sub(trashed.deleted_at).max(0) as u64);
+            let style = if idx == app.selected_trash {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let text = format!(
+                "{}  [{}]  deleted {} ago",
+                trashed.session.name,
+                app.status_label(trashed.session.status),
+                format_duration(age)
+            );
+            ListItem::new(text).style(style)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(format!(" Trash (purged after {} days) ", crate::app::TRASH_RETENTION_DAYS))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    if items.is_empty() {
+        let para = Paragraph::new("Trash is empty")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(block);
+        frame.render_widget(para, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+fn render_project_settings(app: &App, frame: &mut Frame, area: Rect) {
+    let items: Vec<ListItem> = ProjectSetting::all()
+        .iter()
+        .enumerate()
+        .map(|(idx, setting)| {
+            let value = match setting {
+                ProjectSetting::BranchPrefix => app
+                    .project_setting(*setting)
+                    .unwrap_or_else(|| format!("{} (default)", crate::git::DEFAULT_BRANCH_PREFIX)),
+                ProjectSetting::BranchMaxLen => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| format!("{} (default)", crate::git::DEFAULT_BRANCH_MAX_LEN)),
+                ProjectSetting::BaseBranch => match app.project_setting(*setting).filter(|v| !v.is_empty()) {
+                    Some(branch) => branch,
+                    None => match app.detected_base_branch {
+                        Some(ref detected) => format!("{} (auto-detected)", detected),
+                        None => "current HEAD (no origin/HEAD found)".to_string(),
+                    },
+                },
+                ProjectSetting::StartupCommand => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "(shell)".to_string()),
+                ProjectSetting::Autostart | ProjectSetting::SkipBlocked => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "off".to_string()),
+                ProjectSetting::Transcripts => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "off".to_string()),
+                ProjectSetting::TranscriptMaxKb => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| format!("{} (default)", crate::transcript::DEFAULT_MAX_KB)),
+                ProjectSetting::WatchDryRun => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "off".to_string()),
+                ProjectSetting::WatchCooldown => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| format!("{} (default)", crate::app::DEFAULT_WATCH_COOLDOWN_MINS)),
+                ProjectSetting::AiContextChars => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| format!("{} (default)", crate::context::DEFAULT_MAX_CHARS)),
+                ProjectSetting::NotesPath => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| format!("{} (default)", crate::notes::DEFAULT_PATH)),
+                ProjectSetting::TrackTime => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "on".to_string()),
+                ProjectSetting::StaleDays => match app.stale_days {
+                    0 => "never".to_string(),
+                    days if app.project_setting(*setting).is_some_and(|v| !v.is_empty()) => days.to_string(),
+                    days => format!("{} (default)", days),
+                },
+                ProjectSetting::ArchiveDays => match app.archive_days {
+                    0 => "never".to_string(),
+                    days if app.project_setting(*setting).is_some_and(|v| !v.is_empty()) => days.to_string(),
+                    days => format!("{} (default)", days),
+                },
+                ProjectSetting::RedactPatterns => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "(built-in only)".to_string()),
+                ProjectSetting::JiraUrl | ProjectSetting::JiraAuth | ProjectSetting::JiraFields => app
+                    .project_setting(*setting)
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| "(not set)".to_string()),
+            };
+            let style = if idx == app.selected_project_setting {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{}: {}", setting.label(), value)).style(style)
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(" Project (applies to new sessions) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+/// Parse the project's color override for a status into a terminal color
+fn status_color(app: &App, status: Status) -> Option<Color> {
+    app.status_color(status).and_then(|c| c.parse().ok())
+}
+
+/// Two-part popup over `new_field_name` and `new_field_desc`, shared by fields and prompt templates
+fn render_field_popup(app: &App, frame: &mut Frame, title: &str, desc_label: &str, editing_name: bool) {
+    let area = centered_rect(50, 30, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" {} ", title))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let inner_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(3), Constraint::Length(1)])
+        .split(inner);
+
+    // Show name field
+    let name_style = if editing_name {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let name_input = Paragraph::new(app.new_field_name.as_str())
+        .style(name_style)
+        .block(Block::default().borders(Borders::BOTTOM).title("Name"));
+    frame.render_widget(name_input, inner_chunks[0]);
+
+    // Show description field
+    let desc_style = if editing_name {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default().fg(Color::Yellow)
+    };
+    let desc_title = if editing_name {
+        desc_label.to_string()
+    } else {
+        format!("{} (Shift+Enter: new line, Enter: save)", desc_label)
+    };
+    let desc_input = Paragraph::new(app.new_field_desc.as_str())
+        .style(desc_style)
+        .wrap(ratatui::widgets::Wrap { trim: false })
+        .block(Block::default().borders(Borders::BOTTOM).title(desc_title));
+    frame.render_widget(desc_input, inner_chunks[1]);
+
+    if let Some(ref error) = app.input_error {
+        let error_line = Paragraph::new(error.as_str()).style(Style::default().fg(Color::Red));
+        frame.render_widget(error_line, inner_chunks[2]);
+    }
+}
+
+/// Short form of a column label for the header counts: its initials ("In Progress" -> "IP")
+fn column_abbrev(label: &str) -> String {
+    label.split_whitespace().filter_map(|word| word.chars().next()).flat_map(char::to_uppercase).collect()
+}
+
+fn render_header(app: &App, frame: &mut Frame, area: Rect) {
+    let now = crate::db::unix_now();
+    let snoozed = app.sessions.iter().filter(|s| snoozed_for(s, now).is_some()).count();
+    let waiting = app.sessions.iter().filter(|s| app.is_waiting_for_input(s)).count();
+    let active = app.sessions.iter().filter(|s| app.has_active_terminal(s)).count();
+    let shown: usize = Status::all().iter().map(|status| app.sessions_by_status(*status).len()).sum();
+    let counts = Status::all()
+        .iter()
+        .map(|status| format!("{}:{}", column_abbrev(app.status_label(*status)), app.sessions_by_status(*status).len()))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // Narrow terminals drop the path first, then the counts
+    let build = |show_path: bool, show_counts: bool| {
+        let sep = || Span::styled("  ·  ", Style::default().fg(Color::DarkGray));
+        let project = match show_path {
+            true => format!("Project: {} ({})", app.project.name, app.project.path.display()),
+            false => format!("Project: {}", app.project.name),
+        };
+        // Just after a project switch the name stands out
+        let project_style = match app.project_flash.is_some_and(|at| at.elapsed() < PROJECT_FLASH) {
+            true => Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD),
+            false => Style::default().fg(Color::Cyan),
+        };
+        let mut spans = vec![Span::styled(project, project_style)];
+        if show_counts {
+            spans.push(sep());
+            spans.push(Span::styled(counts.clone(), Style::default().fg(Color::Cyan)));
+            if waiting > 0 {
+                spans.push(Span::styled(format!("  ? {} waiting", waiting), Style::default().fg(Color::Yellow)));
+            }
+            if active > 0 {
+                spans.push(Span::styled(format!("  $ {} active", active), Style::default().fg(Color::Green)));
+            }
+        }
+        // Hidden snoozed cards and the "mine" toggle filter the board
+        let mut undo = Vec::new();
+        if snoozed > 0 && !app.show_snoozed {
+            undo.push("Z: show snoozed");
+        }
+        if app.mine_only {
+            undo.push("M: everyone's");
+        }
+        if app.pinned_only {
+            undo.push("P: all cards");
+        }
+        if app.due_this_week {
+            undo.push("W: any due date");
+        }
+        // Archived Done cards aren't loaded at all unless asked for
+        let archived = if app.show_archived { 0 } else { app.archived_count };
+        if archived > 0 {
+            undo.push("O: archived");
+        }
+        let text = if !undo.is_empty() {
+            Some(format!("filtered: {}/{} ({})", shown, app.sessions.len() + archived, undo.join(", ")))
+        } else if snoozed > 0 {
+            Some(format!("{} snoozed (showing)", snoozed))
+        } else {
+            None
+        };
+        if let Some(text) = text {
+            spans.push(sep());
+            spans.push(Span::styled(text, Style::default().fg(Color::Cyan)));
+        }
+        if let Some(ref error) = app.tmux_error {
+            spans.push(sep());
+            spans.push(Span::styled(format!("⚠ {}", error), Style::default().fg(Color::Red)));
+        }
+        // Another board has the project open; edits from both would overwrite each other
+        if let Some(ref holder) = app.lock_holder {
+            let text = match app.read_only {
+                true => format!("🔒 read-only, also open on {} (U: allow edits)", holder.hostname),
+                false => format!("⚠ also open on {} (U: read-only)", holder.hostname),
+            };
+            spans.push(sep());
+            spans.push(Span::styled(text, Style::default().fg(Color::Black).bg(Color::Yellow)));
+        } else if app.read_only {
+            spans.push(sep());
+            spans.push(Span::styled("🔒 read-only (U: allow edits)", Style::default().fg(Color::Yellow)));
+        }
+        Line::from(spans)
+    };
+    let line = [(true, true), (false, true), (false, false)]
+        .into_iter()
+        .map(|(path, counts)| build(path, counts))
+        .find(|line| line.width() <= area.width as usize)
+        .unwrap_or_else(|| build(false, false));
+
+    let header = Paragraph::new(vec![line, agenda_line(app)])
+        .block(Block::default().borders(Borders::BOTTOM));
+    frame.render_widget(header, area);
+}
+
+/// The agenda strip under the header: open cards due today and overdue. Empty while no
+/// open card has a due date
+fn agenda_line(app: &App) -> Line<'static> {
+    let due: Vec<i64> = app.sessions.iter().filter(|s| s.status != Status::Done).filter_map(|s| s.due).collect();
+    if due.is_empty() {
+        return Line::default();
+    }
+    let (due_today, overdue) = crate::due::agenda(due, app.today);
+    let mut spans = vec![Span::styled(format!("today: {} due", due_today), Style::default().fg(Color::Yellow))];
+    spans.push(Span::styled(", ", Style::default().fg(Color::DarkGray)));
+    let overdue_style = match overdue {
+        0 => Style::default().fg(Color::DarkGray),
+        _ => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    };
+    spans.push(Span::styled(format!("overdue: {}", overdue), overdue_style));
+    if app.sort_by_due {
+        spans.push(Span::styled("  ·  sorted by due date", Style::default().fg(Color::DarkGray)));
+    }
+    Line::from(spans)
+}
+
+/// Below this many cells per column, the board stacks columns vertically
+const MIN_COLUMN_WIDTH: u16 = 30;
+
+/// Whether a board this wide stacks its columns instead of showing them side by side
+pub fn board_is_stacked(width: u16, columns: usize) -> bool {
+    columns > 0 && width / (columns as u16) < MIN_COLUMN_WIDTH
+}
+
+/// Where each board column goes: side by side when there's room, otherwise stacked
+/// with only the selected column expanded and the rest as one-line headers.
+/// Returns (area, expanded) per column
+fn kanban_layout(area: Rect, columns: usize, selected: usize) -> Vec<(Rect, bool)> {
+    if columns == 0 {
+        return Vec::new();
+    }
+    if !board_is_stacked(area.width, columns) {
+        let rects = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+            .split(area);
+        return rects.iter().map(|rect| (*rect, true)).collect();
+    }
+
+    let constraints: Vec<Constraint> = (0..columns)
+        .map(|idx| if idx == selected { Constraint::Min(0) } else { Constraint::Length(1) })
+        .collect();
+    let rects = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+    rects.iter().enumerate().map(|(idx, rect)| (*rect, idx == selected)).collect()
+}
+
+fn render_kanban(app: &App, frame: &mut Frame, area: Rect) {
+    let statuses = Status::all();
+    let columns = kanban_layout(area, statuses.len(), app.selected_column);
+
+    for (col_idx, status) in statuses.iter().enumerate() {
+        let sessions = app.sessions_by_status(*status);
+        let is_selected_column = col_idx == app.selected_column;
+        // Zoomed, the selected column takes the whole board and the others are hidden
+        if app.zoomed && !is_selected_column {
+            continue;
+        }
+        let (column_area, expanded) = if app.zoomed { (area, true) } else { columns[col_idx] };
+
+        let border_style = if is_selected_column {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+
+        // Render column header
+        let title_style = status_color(app, *status)
+            .map(|c| Style::default().fg(c))
+            .unwrap_or(border_style);
+        let title = column_title(app, col_idx, *status, sessions.len(), title_style);
+
+        if !expanded {
+            // Collapsed column in the stacked layout: just its header
+            let mut header = vec![Span::styled("▸", border_style)];
+            header.extend(title.spans);
+            header.push(Span::styled("(h/l to expand)", Style::default().fg(Color::DarkGray)));
+            frame.render_widget(Paragraph::new(Line::from(header)), column_area);
+            continue;
+        }
+
+        let column_block = Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(border_style);
+        let inner_area = column_block.inner(column_area);
+        frame.render_widget(column_block, column_area);
+
+        if app.zoomed {
+            render_zoomed_cards(app, frame, &sessions, inner_area);
+            continue;
+        }
+
+        let mut y_offset = 0u16;
+        for (row_idx, entries, height) in column_cards(app, &sessions, inner_area) {
+            let card_area = Rect {
+                x: inner_area.x,
+                y: inner_area.y + y_offset,
+                width: inner_area.width,
+                height: height.min(inner_area.height - y_offset),
+            };
+
+            let body = fit_card_body(entries, card_area.height.saturating_sub(2) as usize);
+            render_session_card(app, frame, sessions[row_idx], is_selected_column, row_idx, card_area, body);
+            y_offset += height;
+        }
+    }
+}
+
+/// A column's cards that fit in `area`, top to bottom, as (row, body entries, height).
+/// Wrapped fields make some cards taller than others
+fn column_cards(app: &App, sessions: &[&Session], area: Rect) -> Vec<(usize, Vec<Vec<Line<'static>>>, u16)> {
+    let body_width = area.width.saturating_sub(2) as usize;
+    let mut shown = Vec::new();
+    let mut y_offset = 0u16;
+    for (row_idx, session) in sessions.iter().enumerate() {
+        if y_offset >= area.height {
+            break; // No more room
+        }
+        let entries = card_lines(app, &session_card_content(app, session), body_width);
+        let height = card_rows(app, &entries);
+        shown.push((row_idx, entries, height));
+        y_offset += height;
+    }
+    shown
+}
+
+/// Cards of the zoomed column, each as tall as its details need. Scrolls so the
+/// selected card is always in view
+fn render_zoomed_cards(app: &App, frame: &mut Frame, sessions: &[&Session], area: Rect) {
+    let mut y_offset = 0u16;
+    for (row_idx, body, height) in zoomed_cards(app, sessions, area) {
+        let card_area = Rect {
+            x: area.x,
+            y: area.y + y_offset,
+            width: area.width,
+            height: height.min(area.height - y_offset),
+        };
+        render_session_card(app, frame, sessions[row_idx], true, row_idx, card_area, body);
+        y_offset += height;
+    }
+}
+
+/// The zoomed column's cards that fit in `area`, top to bottom, as (row, details, height)
+fn zoomed_cards(app: &App, sessions: &[&Session], area: Rect) -> Vec<(usize, Vec<Line<'static>>, u16)> {
+    let width = area.width.saturating_sub(2) as usize;
+    let cards: Vec<Vec<Line<'static>>> = sessions.iter().map(|s| session_detail_lines(app, s, width)).collect();
+    let heights: Vec<u16> = cards.iter().map(|lines| lines.len() as u16 + 2).collect();
+
+    // First card to draw: back up from the selected one while it still fits
+    let selected = app.selected_row.min(sessions.len().saturating_sub(1));
+    let mut first = selected;
+    let mut used = heights.get(selected).copied().unwrap_or(0);
+    while first > 0 && used + heights[first - 1] <= area.height {
+        first -= 1;
+        used += heights[first];
+    }
+
+    let mut shown = Vec::new();
+    let mut y_offset = 0u16;
+    for (row_idx, body) in cards.into_iter().enumerate().skip(first) {
+        if y_offset >= area.height {
+            break;
+        }
+        shown.push((row_idx, body, heights[row_idx]));
+        y_offset += heights[row_idx];
+    }
+    shown
+}
+
+/// Cards on screen in reading order (left to right, then top to bottom) in a terminal
+/// of `area`, laid out as `render_kanban` draws them
+pub fn visible_card_order(app: &App, area: Rect) -> Vec<i64> {
+    let board = kanban_view_chunks(area)[1];
+    let statuses = Status::all();
+    let columns = kanban_layout(board, statuses.len(), app.selected_column);
+
+    let mut visible: Vec<Vec<i64>> = Vec::new();
+    for (col_idx, status) in statuses.iter().enumerate() {
+        if app.zoomed && col_idx != app.selected_column {
+            continue;
+        }
+        let (column_area, expanded) = if app.zoomed { (board, true) } else { columns[col_idx] };
+        if !expanded {
+            continue;
+        }
+        let inner = Block::default().borders(Borders::ALL).inner(column_area);
+        let sessions = app.sessions_by_status(*status);
+        let rows: Vec<usize> = match app.zoomed {
+            true => zoomed_cards(app, &sessions, inner).into_iter().map(|(row, _, _)| row).collect(),
+            false => column_cards(app, &sessions, inner).into_iter().map(|(row, _, _)| row).collect(),
+        };
+        visible.push(rows.into_iter().map(|row| sessions[row].id).collect());
+    }
+
+    let rows = visible.iter().map(Vec::len).max().unwrap_or(0);
+    (0..rows)
+        .flat_map(|row| visible.iter().filter_map(move |column| column.get(row).copied()))
+        .collect()
+}
+
+/// A field as `Name: value`, word-wrapped to `width` instead of cut off. AI-written
+/// values are marked with a faint ✨
+fn wrapped_field_lines(name: &str, value: &str, ai: bool, width: usize) -> Vec<Line<'static>> {
+    let prefix = format!("{}: {}", name, if ai { "✨ " } else { "" });
+    let is_url = value.starts_with("http://") || value.starts_with("https://");
+    let value_style = if is_url {
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::UNDERLINED)
+    } else {
+        Style::default().fg(Color::White)
+    };
+    let (wrapped, _) = wrap_text(&format!("{}{}", prefix, value), width, None);
+    wrapped
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| match line.strip_prefix(&prefix).filter(|_| idx == 0) {
+            Some(rest) => Line::from(vec![
+                Span::styled(prefix.clone(), Style::default().fg(Color::DarkGray)),
+                Span::styled(rest.to_string(), value_style),
+            ]),
+            None => Line::styled(line, value_style),
+        })
+        .collect()
+}
+
+/// Everything worth knowing about a session, whatever the card layout says: branch,
+/// every filled visible field in full, ages, and the worktree and pane state when known.
+/// Shared by zoomed cards and the move popup
+fn session_detail_lines(app: &App, session: &Session, width: usize) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+    if let Some(ref branch) = session.branch_name {
+        lines.push(Line::styled(format!("⎇ {}", branch), Style::default().fg(Color::Blue)));
+    }
+    for field in app.fields.iter().filter(|f| f.visible) {
+        let value = app.field_value(session.id, field.id);
+        if !value.trim().is_empty() {
+            let ai = app.field_source(session.id, field.id) == ValueSource::Ai;
+            lines.extend(wrapped_field_lines(&field.name, value, ai, width));
+        }
+    }
+    let now = crate::db::unix_now();
+    lines.push(Line::styled(
+        format!(
+            "⏱ {} old, {} in {}",
+            format_duration(std::time::Duration::from_secs((now - session.created_at).max(0) as u64)),
+            format_duration(std::time::Duration::from_secs((now - session.status_changed_at).max(0) as u64)),
+            app.status_label(session.status),
+        ),
+        dim,
+    ));
+    let spent = app.time_spent.get(&session.id).copied().unwrap_or(0);
+    if spent > 0 {
+        lines.push(Line::styled(format!("≈ {} in use", format_time_spent(spent)), dim));
+    }
+    if let Some(details) = app.card_details.get(&session.id) {
+        if let Some(ref dirty) = details.dirty {
+            let text = if dirty.is_dirty() {
+                format!("✎ {} staged, {} modified, {} untracked", dirty.staged, dirty.unstaged, dirty.untracked)
+            } else {
+                "✓ clean".to_string()
+            };
+            lines.push(Line::styled(text, dim));
+        }
+        if let Some(ref output) = details.last_output {
+            lines.push(Line::styled(format!("› {}", output), dim));
+        }
+    }
+    lines
+}
+
+/// `Planned (7, oldest 21d)`, with the age in red once it reaches the stale threshold.
+/// `↻` marks a column sorted by activity
+fn column_title(app: &App, col_idx: usize, status: Status, count: usize, style: Style) -> Line<'static> {
+    let label = match app.column_sort(status) {
+        crate::db::ColumnSort::Activity => format!("↻ {}", app.status_label(status)),
+        crate::db::ColumnSort::Created => app.status_label(status).to_string(),
+    };
+    let Some(since) = app.column_oldest.get(col_idx).copied().flatten().filter(|_| count > 0) else {
+        return Line::from(Span::styled(format!(" {} ({}) ", label, count), style));
+    };
+    let age = (crate::db::unix_now() - since).max(0) as u64;
+    let stale = app.stale_days > 0 && age >= app.stale_days * 24 * 60 * 60;
+    let age_style = if stale { Style::default().fg(Color::Red).add_modifier(Modifier::BOLD) } else { style };
+    Line::from(vec![
+        Span::styled(format!(" {} ({}, ", label, count), style),
+        Span::styled(format!("oldest {}", format_duration(std::time::Duration::from_secs(age))), age_style),
+        Span::styled(") ", style),
+    ])
+}
+
+/// Rows a card takes on the board, from the project's card layout
+fn card_height(app: &App) -> u16 {
+    crate::card::card_height(&app.card_layout, &app.fields)
+}
+
+/// Rows a card with these body entries takes: the layout's rows, plus the extra lines
+/// of its wrapped fields
+fn card_rows(app: &App, entries: &[Vec<Line<'static>>]) -> u16 {
+    card_height(app) + entries.iter().map(|entry| entry.len().saturating_sub(1) as u16).sum::<u16>()
+}
+
+/// A card's entries as at most `rows` lines. When they don't all fit, whole entries are
+/// dropped from the end and the last row counts them
+fn fit_card_body(entries: Vec<Vec<Line<'static>>>, rows: usize) -> Vec<Line<'static>> {
+    if entries.iter().map(Vec::len).sum::<usize>() <= rows {
+        return entries.concat();
+    }
+    let total = entries.len();
+    let mut lines = Vec::new();
+    let mut kept = 0;
+    for entry in entries {
+        if lines.len() + entry.len() >= rows {
+            break;
+        }
+        lines.extend(entry);
+        kept += 1;
+    }
+    if rows > 0 {
+        lines.push(Line::styled(format!("+{} more", total - kept), Style::default().fg(Color::DarkGray)));
+    }
+    lines
+}
+
+/// What a card can show, gathered from a session or made up for the settings preview
+struct CardContent {
+    branch: Option<String>,
+    /// Indexed like `app.fields`
+    values: Vec<String>,
+    /// Indexed like `values`: written by AI fill and not vouched for since
+    ai: Vec<bool>,
+    /// Seconds since creation and since the last column change
+    age: (u64, u64),
+    dirty: Option<DirtyStatus>,
+    last_output: Option<String>,
+}
+
+fn session_card_content(app: &App, session: &Session) -> CardContent {
+    // Branch name - prefer stored branch_name, fall back to tmux detection
+    let branch = session.branch_name.clone().or_else(|| {
+        session.tmux_window.as_ref().and_then(|tmux_name| {
+            if app.active_tmux_sessions.contains(tmux_name) {
+                tmux::get_git_branch(tmux_name)
+            } else {
+                None
+            }
+        })
+    });
+
+    let values: Vec<String> = app.fields.iter().map(|f| app.field_value(session.id, f.id).to_string()).collect();
+    let ai = app
+        .fields
+        .iter()
+        .zip(&values)
+        .map(|(f, value)| !value.is_empty() && app.field_source(session.id, f.id) == ValueSource::Ai)
+        .collect();
+
+    let now = crate::db::unix_now();
+    let details = app.card_details.get(&session.id);
+    CardContent {
+        branch,
+        values,
+        ai,
+        age: ((now - session.created_at).max(0) as u64, (now - session.status_changed_at).max(0) as u64),
+        dirty: details.and_then(|d| d.dirty.clone()),
+        last_output: details.and_then(|d| d.last_output.clone()),
+    }
+}
+
+fn field_line(name: &str, value: &str, ai: bool) -> Line<'static> {
+    let is_url = value.starts_with("http://") || value.starts_with("https://");
+    // Truncate long values to fit card width
+    let max_len = 25;
+    let display_value: String = if value.chars().count() > max_len {
+        format!("{}…", value.chars().take(max_len).collect::<String>())
+    } else {
+        value.to_string()
+    };
+    let value_style = if is_url {
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::UNDERLINED)
+    } else {
+        Style::default().fg(Color::White)
+    };
+    Line::from(vec![
+        Span::styled(format!("{}: {}", name, if ai { "✨ " } else { "" }), Style::default().fg(Color::DarkGray)),
+        Span::styled(display_value, value_style),
+    ])
+}
+
+/// A field's value on a card: one line cut off, or up to `MAX_WRAPPED_LINES` of `width`
+/// when the field wraps
+fn card_field_lines(field: &crate::db::Field, value: &str, ai: bool, width: usize) -> Vec<Line<'static>> {
+    if !field.wrap {
+        return vec![field_line(&field.name, value, ai)];
+    }
+    let mut lines = wrapped_field_lines(&field.name, value, ai, width);
+    if lines.len() > MAX_WRAPPED_LINES {
+        lines.truncate(MAX_WRAPPED_LINES);
+        if let Some(last) = lines.last_mut() {
+            last.spans.push(Span::raw("…"));
+        }
+    }
+    lines
+}
+
+/// A card's body as the project's card layout lists it, one entry per line of the
+/// layout (a wrapped field's entry can span several). Lines with nothing to show (no
+/// branch, empty field) are skipped
+fn card_lines(app: &App, content: &CardContent, width: usize) -> Vec<Vec<Line<'static>>> {
+    let mut lines: Vec<Vec<Line>> = Vec::new();
+    let fields = app.fields.iter().zip(content.values.iter().zip(&content.ai));
+    for line in &app.card_layout {
+        match line {
+            CardLine::Branch => {
+                if let Some(ref branch) = content.branch {
+                    lines.push(vec![Line::from(vec![
+                        Span::styled("⎇ ", Style::default().fg(Color::Blue)),
+                        Span::styled(branch.clone(), Style::default().fg(Color::Blue)),
+                    ])]);
+                }
+            }
+            CardLine::VisibleFields => {
+                for (field, (value, ai)) in fields.clone().filter(|(f, (v, _))| f.visible && !v.is_empty()) {
+                    lines.push(card_field_lines(field, value, *ai, width));
+                }
+            }
+            CardLine::Field(id) => {
+                if let Some((field, (value, ai))) = fields.clone().find(|(f, (v, _))| f.id == *id && !v.is_empty()) {
+                    lines.push(card_field_lines(field, value, *ai, width));
+                }
+            }
+            CardLine::Age => {
+                let (created, in_column) = content.age;
+                lines.push(vec![Line::styled(
+                    format!(
+                        "⏱ {} old, {} in column",
+                        format_duration(std::time::Duration::from_secs(created)),
+                        format_duration(std::time::Duration::from_secs(in_column)),
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                )]);
+            }
+            CardLine::LastOutput => {
+                if let Some(ref output) = content.last_output {
+                    lines.push(vec![Line::styled(format!("› {}", output), Style::default().fg(Color::DarkGray))]);
+                }
+            }
+            CardLine::Dirty => match content.dirty {
+                Some(ref dirty) if dirty.is_dirty() => {
+                    let counts: Vec<String> = [
+                        (dirty.staged, "staged"),
+                        (dirty.unstaged, "modified"),
+                        (dirty.untracked, "untracked"),
+                    ]
+                    .iter()
+                    .filter(|(n, _)| *n > 0)
+                    .map(|(n, label)| format!("{} {}", n, label))
+                    .collect();
+                    lines.push(vec![Line::styled(
+                        format!("✎ {}", counts.join(", ")),
+                        Style::default().fg(Color::Yellow),
+                    )]);
+                }
+                Some(_) => lines.push(vec![Line::styled("✓ clean", Style::default().fg(Color::DarkGray))]),
+                None => {}
+            },
+        }
+    }
+    lines
+}
+
+fn render_session_card(
+    app: &App,
+    frame: &mut Frame,
+    session: &Session,
+    is_selected_column: bool,
+    row_idx: usize,
+    area: Rect,
+    body: Vec<Line<'static>>,
+) {
+    let is_selected = is_selected_column && row_idx == app.selected_row;
+    let is_grabbed = app.input_mode == InputMode::Grab && app.grabbed_session_id == Some(session.id);
+
+    let blocked = app.blocked_by(session.id);
+    let unblocked = app.just_unblocked.contains(&session.id);
+    let is_marked = app.marked_sessions.contains(&session.id);
+    let border_style = if is_grabbed {
+        Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+    } else if is_selected {
+        Style::default().fg(Color::Yellow)
+    } else if is_marked {
+        Style::default().fg(Color::Cyan)
+    } else if blocked.is_some() {
+        Style::default().fg(Color::Red)
+    } else if unblocked {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
+    let name_style = Style::default().fg(Color::White).add_modifier(Modifier::BOLD);
+
+    // Build card title with indicator; pinned cards are starred
+    let name = match session.pinned {
+        true => format!("★ {}", session.name),
+        false => session.name.clone(),
+    };
+    // Cards waiting on open cards are locked, with how many
+    let name = match blocked {
+        Some(ids) => format!("🔒{} {}", ids.len(), name),
+        None if unblocked => format!("🔓 {}", name),
+        None => name,
+    };
+    let name = if is_marked { format!("● {}", name) } else { name };
+    let title = if app.is_waiting_for_input(session) {
+        match app.waiting_duration(session) {
+            Some(waited) => format!(" ? {} {} ", format_duration(waited), name),
+            None => format!(" ? {} ", name),
+        }
+    } else if app.has_active_terminal(session) {
+        format!(" $ {} ", name)
+    } else if let Some(code) = app.exit_status(session) {
+        format!(" exited {} {} ", code, name)
+    } else {
+        format!(" {} ", name)
+    };
+
+    let title = match app.hint_targets.iter().position(|id| *id == session.id) {
+        Some(idx) if app.input_mode == InputMode::Hints => format!(" [{}]{}", idx + 1, title),
+        _ => title,
+    };
+
+    // Snoozed cards only show with Z; mark them with the time left
+    let snoozed = snoozed_for(session, crate::db::unix_now());
+    let title = match snoozed {
+        Some(left) => format!(" z {}{}", format_duration(left), title),
+        None => title,
+    };
+
+    let title_style = if snoozed.is_some() {
+        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+    } else if app.is_waiting_for_input(session) {
+        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+    } else if app.has_active_terminal(session) {
+        Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+    } else if let Some(code) = app.exit_status(session) {
+        Style::default().fg(if code == 0 { Color::DarkGray } else { Color::Red }).add_modifier(Modifier::BOLD)
+    } else {
+        name_style
+    };
+
+    // Foreground command of a live pane; shells are dimmed since the agent has exited
+    let mut title_spans = vec![Span::styled(title, title_style)];
+    if let Some(owner) = session.owner.as_deref().filter(|o| !o.trim().is_empty()) {
+        let style = Style::default().fg(owner_color(owner)).add_modifier(Modifier::BOLD);
+        title_spans.push(Span::styled(format!("{} ", owner_initials(owner)), style));
+    }
+    if app.recent_session_ids.contains(&session.id) {
+        title_spans.push(Span::styled("↺ ", Style::default().fg(Color::DarkGray)));
+    }
+    // Only sessions that don't run the default agent name theirs
+    if let Some(agent) = session.agent.as_deref().filter(|a| *a != crate::agent::DEFAULT) {
+        title_spans.push(Span::styled(format!("[{}] ", agent), Style::default().fg(Color::LightBlue)));
+    }
+    // The worktree is on another branch than stored, or on none
+    if let Some(head) = app.branch_out_of_sync(session) {
+        let text = match head {
+            git::Head::Branch(branch) => format!("⇄ {} ", branch),
+            git::Head::Detached(_) => format!("{} ", head),
+        };
+        title_spans.push(Span::styled(text, Style::default().fg(Color::Magenta)));
+    }
+    // Due dates of open cards, red once overdue
+    if let Some(day) = session.due.filter(|_| session.status != Status::Done) {
+        let style = match day.cmp(&app.today) {
+            std::cmp::Ordering::Less => Style::default().fg(Color::White).bg(Color::Red),
+            std::cmp::Ordering::Equal => Style::default().fg(Color::Yellow),
+            std::cmp::Ordering::Greater => Style::default().fg(Color::DarkGray),
+        };
+        title_spans.push(Span::styled(format!("⏰{}", crate::due::badge(day, app.today)), style));
+        title_spans.push(Span::raw(" "));
+    }
+    if let Some(command) = app.pane_command(session).filter(|_| app.has_active_terminal(session)) {
+        let command_style = if tmux::is_shell(command) {
+            Style::default().fg(Color::DarkGray)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        title_spans.push(Span::styled(format!("{} ", command), command_style));
+    }
+
+    let card_block = Block::default()
+        .title(Line::from(title_spans))
+        .borders(Borders::ALL)
+        .border_type(if is_grabbed { BorderType::Double } else { BorderType::Plain })
+        .border_style(border_style);
+
+    let inner = card_block.inner(area);
+    frame.render_widget(card_block, area);
+
+    frame.render_widget(Paragraph::new(body), inner);
+}
+
+/// Badge for a card's owner: up to two initials ("Tom Eehan" -> "TE", "tom" -> "T")
+fn owner_initials(owner: &str) -> String {
+    column_abbrev(owner).chars().take(2).collect()
+}
+
+/// Colors owner badges are drawn in (yellow and green already mean waiting and active)
+const OWNER_COLORS: &[Color] = &[Color::Cyan, Color::Magenta, Color::Blue, Color::LightRed, Color::LightCyan, Color::LightMagenta];
+
+/// The same owner always gets the same color
+fn owner_color(owner: &str) -> Color {
+    let hash = owner.trim().to_lowercase().bytes().fold(0usize, |acc, b| acc.wrapping_mul(31).wrapping_add(b as usize));
+    OWNER_COLORS[hash % OWNER_COLORS.len()]
+}
+
+fn render_kanban_footer(app: &App, frame: &mut Frame, area: Rect) {
+    let text = if let Some(ref msg) = app.status_message {
+        msg.clone()
+    } else if app.input_mode == InputMode::Grab {
+        "GRAB | h/l: move card between columns | Enter/Esc: drop".to_string()
+    } else if app.input_mode == InputMode::Hints {
+        "1-9: attach to card | any other key: cancel".to_string()
+    } else {
+        "q: quit | n: new | N: shortcut | I: plan many | e: edit | c: comments | E: env | Space: peek | F: follow | '/f: jump | hjkl: nav | w: next waiting | Tab: queue | V: overview | m: move | []: step column | g: grab | .: actions | z/Z: snooze/show snoozed | *: pin | P: pinned only | C: due date | S: sort by due | W: due this week | b: blocked by | v: mark | ;: set field | O: archived | M: mine | =: zoom | d: del | D: commits | L: land | T: transcripts | p: notes | r: refresh | R: outside changes | Ctrl+R: reload settings | s: settings | Enter: term | Ctrl+O: last term | Ctrl+^: other project".to_string()
+    };
+    let style = if app.status_message.is_some() {
+        Style::default().fg(Color::Green)
+    } else if matches!(app.input_mode, InputMode::Grab | InputMode::Hints) {
+        Style::default().fg(Color::Magenta)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let footer = Paragraph::new(text).style(style);
+    frame.render_widget(footer, area);
+}
+
+fn render_peek_overlay(app: &App, frame: &mut Frame) {
+    let session = app.peek_session();
+    // Split sessions show the picked pane (`]`/`[`), else the agent's; single panes as before
+    let capture = app.peek_capture.as_ref().filter(|c| session.is_some_and(|s| s.id == c.session_id));
+    let content: Vec<Line> = match capture {
+        Some(capture) => capture
+            .lines
+            .iter()
+            .zip(&capture.links)
+            .map(|(line, links)| link_line(line, links))
+            .collect(),
+        None if app.follow_active => vec![Line::from("(no active sessions to follow)")],
+        None => return,
+    };
+    let pane_label = capture.map_or("", |c| c.pane_label.as_str());
+    let pane_keys = if pane_label.is_empty() { "" } else { " | ]/[: pane" };
+    let link_keys = if capture.is_some_and(|c| !c.urls.is_empty()) { " | o: links" } else { "" };
+
+    let title = match (session, app.follow_target) {
+        (Some(session), Some((_, reason))) if app.follow_active => {
+            format!(
+                " Following: {}{} ({}) | F: stop | Space: normal peek{}{} | n: spin off ",
+                session.name, pane_label, reason.label(), pane_keys, link_keys
+            )
+        }
+        (None, _) => " Following ".to_string(),
+        (Some(session), _) => format!(
+            " {}{}{}{} | n: spin off follow-up ",
+            session.name, pane_label, pane_keys, link_keys
+        ),
+    };
+
+    let area = centered_rect(80, 70, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // The capture joins wrapped lines, so wrap here; a long link stays underlined throughout
+    let para = Paragraph::new(content)
+        .style(Style::default().fg(Color::White))
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(para, inner);
+}
+
+/// A line of pane output with its links underlined
+fn link_line(line: &str, links: &[std::ops::Range<usize>]) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for range in links {
+        spans.push(Span::raw(line[pos..range.start].to_string()));
+        spans.push(Span::styled(
+            line[range.clone()].to_string(),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
+        ));
+        pos = range.end;
+    }
+    spans.push(Span::raw(line[pos..].to_string()));
+    Line::from(spans)
+}
+
+/// Longest link shown in the picker; longer ones are cut for display but opened in full
+const URL_DISPLAY_MAX_CHARS: usize = 100;
+
+fn render_pick_url_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Open link (1-9 or Enter: open, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let items: Vec<ListItem> = app
+        .url_choices
+        .iter()
+        .enumerate()
+        .map(|(idx, url)| {
+            let mut shown: String = url.chars().take(URL_DISPLAY_MAX_CHARS).collect();
+            if url.chars().count() > URL_DISPLAY_MAX_CHARS {
+                shown.push('…');
+            }
+            let number = if idx < 9 { format!("{}.", idx + 1) } else { "  ".to_string() };
+            let style = if idx == app.selected_url {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{} {}", number, shown)).style(style)
+        })
+        .collect();
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_input_popup(app: &App, frame: &mut Frame, title: &str, label: &str) {
+    let area = centered_rect(50, 20, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" {} ", title))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0)])
+        .split(inner);
+
+    let input = Paragraph::new(app.input_buffer.as_str())
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::default().borders(Borders::BOTTOM).title(label));
+
+    frame.render_widget(input, chunks[0]);
+
+    if let Some(ref error) = app.input_error {
+        let warning = Paragraph::new(format!("⚠ {}", error))
+            .style(Style::default().fg(Color::Red))
+            .wrap(ratatui::widgets::Wrap { trim: true });
+        frame.render_widget(warning, chunks[1]);
+    } else if app.duplicate_name_warning {
+        let warning = Paragraph::new("⚠ A session with this name exists — Enter again to create anyway")
+            .style(Style::default().fg(Color::Red))
+            .wrap(ratatui::widgets::Wrap { trim: true });
+        frame.render_widget(warning, chunks[1]);
+    }
+}
+
+/// Many new cards typed at once, one per line
+fn render_plan_sessions_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(60, 60, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" New Sessions (one per line, optional @column #tag) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1), Constraint::Length(1)])
+        .split(inner);
+
+    // Keep the line being typed in view
+    let lines: Vec<Line> = app.input_buffer.split('\n').map(|line| Line::from(line.to_string())).collect();
+    let scroll = lines.len().saturating_sub(chunks[0].height as usize) as u16;
+    let input = Paragraph::new(lines).style(Style::default().fg(Color::Yellow)).scroll((scroll, 0));
+    frame.render_widget(input, chunks[0]);
+
+    let count = app.input_buffer.lines().filter(|line| !line.trim().is_empty()).count();
+    let status = if app.confirm_discard_plan {
+        Line::styled(
+            format!("Discard {} line{}? (y)es / (n)o", count, if count == 1 { "" } else { "s" }),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )
+    } else if let Some(ref error) = app.input_error {
+        Line::styled(format!("⚠ {}", error), Style::default().fg(Color::Red))
+    } else {
+        Line::styled(format!("{} card{}", count, if count == 1 { "" } else { "s" }), Style::default().fg(Color::DarkGray))
+    };
+    frame.render_widget(Paragraph::new(status), chunks[1]);
+    frame.render_widget(
+        Paragraph::new("Enter: new line | Ctrl+S: create all | Esc: discard").style(Style::default().fg(Color::DarkGray)),
+        chunks[2],
+    );
+}
+
+/// A session's scratchpad, and the file it is kept in
+fn render_notes_popup(app: &App, frame: &mut Frame) {
+    let Some(editor) = app.notes_editor.as_ref() else { return };
+    let area = centered_rect(70, 70, frame.area());
+    frame.render_widget(Clear, area);
+
+    let location = match editor.path {
+        Some(ref path) => path.display().to_string(),
+        None => "workbench only, no worktree".to_string(),
+    };
+    let block = Block::default()
+        .title(format!(" Notes: {} ({}) ", editor.session_name, location))
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1), Constraint::Length(1)])
+        .split(inner);
+
+    // Keep the line being typed in view
+    let lines: Vec<Line> = editor.text.split('\n').map(|line| Line::from(line.to_string())).collect();
+    let scroll = lines.len().saturating_sub(chunks[0].height as usize) as u16;
+    frame.render_widget(Paragraph::new(lines).scroll((scroll, 0)), chunks[0]);
+
+    let status = if editor.confirm_discard {
+        Line::styled("Discard your edits? (y)es / (n)o", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    } else if editor.conflict {
+        Line::styled(
+            "⚠ The file changed on disk too: Ctrl+S overwrites it, Ctrl+R takes the file's version",
+            Style::default().fg(Color::Red),
+        )
+    } else if editor.edited() {
+        Line::styled("Edited", Style::default().fg(Color::Yellow))
+    } else {
+        Line::styled("Saved", Style::default().fg(Color::DarkGray))
+    };
+    frame.render_widget(Paragraph::new(status), chunks[1]);
+    frame.render_widget(
+        Paragraph::new("Enter: new line | Ctrl+S: save | Ctrl+R: reload file | Esc: close").style(Style::default().fg(Color::DarkGray)),
+        chunks[2],
+    );
+}
+
+fn render_edit_session_popup(app: &App, frame: &mut Frame) {
+    use crate::app::{AiContext, EditMode, JiraFetch, AGENT_ROW, FIRST_FIELD_ROW, OWNER_ROW};
+
+    let jira_key_field = app.jira_setup().map(|(_, fields)| fields.key);
+    let edit_fields = app.edit_field_indices();
+    let num_fields = edit_fields.len();
+    let is_ai_mode = app.edit_mode == EditMode::AI;
+
+    // In AI mode, add extra row for AI input
+    let total_display_rows = FIRST_FIELD_ROW + num_fields + usize::from(is_ai_mode);
+    let popup_height = std::cmp::min(20 + (total_display_rows * 3) as u16, 80);
+    let area = centered_rect(60, popup_height, frame.area());
+    frame.render_widget(Clear, area);
+
+    let mode_str = if app.ai_running {
+        "AI ⏳ Running..."
+    } else {
+        match app.edit_mode {
+            EditMode::Manual => "Manual",
+            EditMode::AI => "AI ✨",
+        }
+    };
+    let help = if app.ai_running {
+        "Please wait..."
+    } else if is_ai_mode {
+        "Shift+Tab: mode, Ctrl+T: prompts, Enter: run AI"
+    } else if !app.ai_available && jira_key_field.is_some() {
+        "Tab/↑↓: nav, Ctrl+T: Jira, Enter: save"
+    } else if !app.ai_available {
+        "Tab/↑↓: nav, Enter: save"
+    } else if jira_key_field.is_some() {
+        "Shift+Tab: mode, Tab/↑↓: nav, Ctrl+T: Jira, Enter: save"
+    } else {
+        "Shift+Tab: mode, Tab/↑↓: nav, Enter: save"
+    };
+    let title = format!(" Edit Session [{}] ({}) ", mode_str, help);
+
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // Create constraints for each row (plus a one-line context toggle in AI mode)
+    let mut constraints: Vec<Constraint> = (0..total_display_rows)
+        .map(|_| Constraint::Length(3))
+        .collect();
+    if is_ai_mode {
+        constraints.insert(1, Constraint::Length(1));
+    }
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(inner);
+
+    let mut row_offset = 0;
+
+    // In AI mode, render AI input field first
+    if is_ai_mode {
+        let (ai_style, ai_title) = if app.ai_running {
+            (Style::default().fg(Color::Yellow), "⏳ Running AI... please wait".to_string())
+        } else if let Some(ref err) = app.ai_error {
+            let first_line = err.lines().next().unwrap_or("");
+            let summary = if first_line.chars().count() > 40 || err.lines().count() > 1 {
+                format!("{}… (Ctrl+E: details)", first_line.chars().take(40).collect::<String>())
+            } else {
+                first_line.to_string()
+            };
+            (Style::default().fg(Color::Red), format!("❌ Error: {}", summary))
+        } else {
+            (Style::default().fg(Color::Magenta), "✨ AI Prompt (describe what to fill)".to_string())
+        };
+        let ai_block = Block::default()
+            .borders(Borders::BOTTOM)
+            .title(ai_title)
+            .border_style(ai_style);
+        let ai_input = Paragraph::new(app.ai_input.as_str())
+            .style(ai_style)
+            .block(ai_block);
+        if !rows.is_empty() {
+            frame.render_widget(ai_input, rows[0]);
+        }
+
+        // Context selector: which pane content is sent along with the prompt
+        let history_label = format!("last {} lines", app.ai_history_lines);
+        let options = [
+            (AiContext::None, "none"),
+            (AiContext::VisiblePane, "visible pane"),
+            (AiContext::History, history_label.as_str()),
+        ];
+        let mut spans = vec![Span::styled("Context (←→): ", Style::default().fg(Color::DarkGray))];
+        for (i, (context, label)) in options.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" | ", Style::default().fg(Color::DarkGray)));
+            }
+            let style = if app.ai_context == *context {
+                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            spans.push(Span::styled(*label, style));
+        }
+        if app.ai_context == AiContext::History {
+            spans.push(Span::styled("  (PgUp/PgDn: ±lines)", Style::default().fg(Color::DarkGray)));
+        }
+        if app.ai_context != AiContext::None {
+            spans.push(Span::styled(
+                format!("  ≤{} chars (Ctrl+↑↓)", app.ai_context_limit()),
+                Style::default().fg(if app.ai_context_chars.is_some() { Color::Magenta } else { Color::DarkGray }),
+            ));
+        }
+        if rows.len() > 1 {
+            frame.render_widget(Paragraph::new(Line::from(spans)), rows[1]);
+        }
+        row_offset = 2;
+    }
+
+    // Render name field
+    let name_row = row_offset;
+    let name_selected = !is_ai_mode && app.edit_row == 0;
+    let name_style = if is_ai_mode {
+        Style::default().fg(Color::DarkGray) // Locked in AI mode
+    } else if name_selected {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let name_value = if name_selected && !is_ai_mode {
+        app.input_buffer.as_str()
+    } else {
+        app.edit_session_name.as_str()
+    };
+    let name_prefix = if is_ai_mode { "  " } else if name_selected { "> " } else { "  " };
+    let name_block = Block::default()
+        .borders(Borders::BOTTOM)
+        .title(format!("{}Name", name_prefix))
+        .border_style(name_style);
+    let name_input = Paragraph::new(name_value)
+        .style(name_style)
+        .block(name_block);
+    if name_row < rows.len() {
+        frame.render_widget(name_input, rows[name_row]);
+    }
+
+    // Render owner
+    let owner_selected = !is_ai_mode && app.edit_row == OWNER_ROW;
+    let owner_style = if owner_selected {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let owner_value = if owner_selected { app.input_buffer.as_str() } else { app.edit_owner.as_str() };
+    let owner_prefix = if owner_selected { "> " } else { "  " };
+    let owner_input = Paragraph::new(owner_value)
+        .style(owner_style)
+        .block(Block::default().borders(Borders::BOTTOM).title(format!("{}Owner", owner_prefix)).border_style(owner_style));
+    if row_offset + OWNER_ROW < rows.len() {
+        frame.render_widget(owner_input, rows[row_offset + OWNER_ROW]);
+    }
+
+    // Render agent, a choice rather than text
+    let agent_selected = !is_ai_mode && app.edit_row == AGENT_ROW;
+    let agent_style = if agent_selected {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+    let agent_title = if agent_selected { "> Agent (←→)" } else { "  Agent" };
+    let agent_input = Paragraph::new(format!("‹ {} ›", app.edit_agent))
+        .style(agent_style)
+        .block(Block::default().borders(Borders::BOTTOM).title(agent_title).border_style(agent_style));
+    if row_offset + AGENT_ROW < rows.len() {
+        frame.render_widget(agent_input, rows[row_offset + AGENT_ROW]);
+    }
+
+    // Render custom fields, leaving out those kept out of the popup
+    for (row, &i) in edit_fields.iter().enumerate() {
+        let field = &app.fields[i];
+        let row_idx = row_offset + FIRST_FIELD_ROW + row;
+        if row_idx >= rows.len() {
+            break;
+        }
+        let is_selected = !is_ai_mode && app.edit_row == row + FIRST_FIELD_ROW;
+        let style = if is_ai_mode {
+            Style::default().fg(Color::DarkGray) // Locked in AI mode
+        } else if is_selected {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let value = if is_selected && !is_ai_mode {
+            app.input_buffer.as_str()
+        } else {
+            app.edit_field_values.get(i).map(|s| s.as_str()).unwrap_or("")
+        };
+        let prefix = if is_ai_mode { "  " } else if is_selected { "> " } else { "  " };
+        let mut title = vec![Span::styled(format!("{}{}", prefix, field.name), style)];
+        // Until typed over or vouched for, AI-written values keep their mark
+        let stored = app.edit_field_values.get(i).map(|s| s.as_str()).unwrap_or("");
+        let ai_written = app.edit_field_sources.get(i) == Some(&ValueSource::Ai);
+        if ai_written && !stored.is_empty() && value == stored {
+            let hint = if is_selected { "  ✨ AI (Ctrl+V: mark verified)" } else { "  ✨ AI" };
+            title.push(Span::styled(hint, Style::default().fg(Color::DarkGray)));
+        }
+        if jira_key_field == Some(i) && !is_ai_mode {
+            let (status, color) = match app.jira_fetch {
+                Some(JiraFetch::Running(ref key)) => (format!("⏳ fetching {}...", key), Color::Yellow),
+                Some(JiraFetch::Done(ref key)) => (format!("✓ filled from {}", key), Color::Green),
+                Some(JiraFetch::Failed(ref err)) => (format!("❌ {}", err), Color::Red),
+                None => ("Ctrl+T: fetch from Jira".to_string(), Color::DarkGray),
+            };
+            title.push(Span::styled(format!("  {}", status), Style::default().fg(color)));
+        }
+        let field_block = Block::default()
+            .borders(Borders::BOTTOM)
+            .title(Line::from(title))
+            .border_style(style);
+        let field_input = Paragraph::new(value)
+            .style(style)
+            .block(field_block);
+        frame.render_widget(field_input, rows[row_idx]);
+    }
+
+    // Earlier values of the selected field, dropped down over the rows below it
+    if !app.edit_completions.is_empty() && !is_ai_mode {
+        let below = rows.get(app.edit_row).map_or(inner.y, |row| row.y + 2);
+        let height = (app.edit_completions.len() as u16 + 2).min(inner.bottom().saturating_sub(below));
+        let area = Rect { x: inner.x + 2, y: below, width: inner.width.saturating_sub(4).min(40), height };
+        frame.render_widget(Clear, area);
+        let lines: Vec<Line> = app
+            .edit_completions
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                if i == app.selected_completion {
+                    Line::from(Span::styled(format!("> {}", value), Style::default().fg(Color::Black).bg(Color::Yellow)))
+                } else {
+                    Line::from(format!("  {}", value))
+                }
+            })
+            .collect();
+        let block = Block::default()
+            .title(" j/k: pick, Enter: use ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .style(Style::default().bg(Color::Black));
+        frame.render_widget(Paragraph::new(lines).style(Style::default().fg(Color::White)).block(block), area);
+    }
+}
+
+fn render_ai_error_popup(app: &App, frame: &mut Frame) {
+    let Some(ref err) = app.ai_error else { return };
+
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" AI Error (Esc: close) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let para = Paragraph::new(err.as_str())
+        .style(Style::default().fg(Color::White))
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_delete_field_popup(app: &App, frame: &mut Frame) {
+    let field_name = app.deleting_field_id
+        .and_then(|id| app.fields.iter().find(|f| f.id == id))
+        .map(|f| f.name.as_str())
+        .unwrap_or("this field");
+
+    let area = centered_rect(40, 20, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Delete Field ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = format!("Delete \"{}\"?\n\n(y)es / (n)o", field_name);
+    let para = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_purge_session_popup(app: &App, frame: &mut Frame) {
+    let session_name = app.purging_session_id
+        .and_then(|id| app.trashed_sessions.iter().find(|t| t.session.id == id))
+        .map(|t| t.session.name.as_str())
+        .unwrap_or("this session");
+
+    let area = centered_rect(40, 20, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Delete Forever ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = format!("Permanently delete \"{}\"?\nThis cannot be undone.\n\n(y)es / (n)o", session_name);
+    let para = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_seed_fields_popup(frame: &mut Frame) {
+    let area = centered_rect(50, 25, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Welcome ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = "This project has no custom fields yet.\n\n\
+                Create default fields for this project?\n\
+                (Ticket, Summary, PR URL)\n\n\
+                (y)es / (n)o";
+    let para = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_relocate_popup(app: &App, frame: &mut Frame) {
+    let Some(ref old) = app.relocate_candidate else { return };
+
+    let area = centered_rect(60, 30, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Project Moved? ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = format!(
+        "\"{}\" used to live at\n{}\nwhich no longer exists.\n\n\
+         Relink it (and its sessions) to\n{}?\n\n\
+         (y)es / (n)o, start a new project",
+        old.name, old.path.display(), app.project.path.display()
+    );
+    let para = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(para, inner);
+}
+
+fn render_repo_config_popup(app: &App, frame: &mut Frame) {
+    let Some(ref prompt) = app.repo_config else { return };
+
+    let area = centered_rect(70, 60, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" {} ", crate::repo_config::FILE_NAME))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(0),
+            Constraint::Length(prompt.problems.len() as u16),
+            Constraint::Length(2),
+        ])
+        .split(inner);
+
+    let intro = Paragraph::new(format!(
+        "This repo's board setup differs from {}. Conflicts replace values set here,\nso they're only applied when ticked.",
+        app.project.name
+    ))
+    .style(Style::default().fg(Color::White));
+    frame.render_widget(intro, chunks[0]);
+
+    let items: Vec<ListItem> = prompt
+        .changes
+        .iter()
+        .enumerate()
+        .map(|(i, change)| {
+            let ticked = prompt.ticked.get(i).copied().unwrap_or(false);
+            let text = match change.local {
+                Some(ref local) => format!("[{}] {}: {} (here: {})", if ticked { "x" } else { " " }, change.target, change.value, local),
+                None => format!("[{}] {}: {}", if ticked { "x" } else { " " }, change.target, change.value),
+            };
+            let style = if i == prompt.selected {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else if change.is_conflict() {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(text).style(style)
+        })
+        .collect();
+    // Keeps the selection in view when the plan is longer than the popup
+    let mut state = ListState::default().with_selected(Some(prompt.selected));
+    frame.render_stateful_widget(List::new(items), chunks[1], &mut state);
+
+    let problems: Vec<Line> = prompt
+        .problems
+        .iter()
+        .map(|problem| Line::styled(format!("⚠ {} (left out)", problem), Style::default().fg(Color::Red)))
+        .collect();
+    frame.render_widget(Paragraph::new(problems), chunks[2]);
+
+    let help = Paragraph::new("Space: tick | Enter: apply the ticked changes\nEsc: leave the project as it is (asked again when the file changes)")
+        .style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(help, chunks[3]);
+}
+
+fn render_confirm_delete_popup(app: &App, frame: &mut Frame) {
+    let session_name = app.deleting_session_id
+        .and_then(|id| app.sessions.iter().find(|s| s.id == id))
+        .map(|s| s.name.as_str())
+        .unwrap_or("this session");
+
+    let area = centered_rect(40, 20, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Delete Session ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let text = format!("Move \"{}\" to trash?\n\n(y)es / (n)o", session_name);
+    let para = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_session_env_popup(app: &App, frame: &mut Frame) {
+    let session_name = app.env_session_id
+        .and_then(|id| app.sessions.iter().find(|s| s.id == id))
+        .map(|s| s.name.as_str())
+        .unwrap_or("Session");
+
+    let area = centered_rect(70, 60, frame.area());
+    frame.render_widget(Clear, area);
+
+    let is_input = app.input_mode == InputMode::NewEnvVar;
+    let help = if is_input {
+        "Enter: save | Esc: cancel"
+    } else if app.env_revealed {
+        "n: new | e: edit | d: delete | v: hide | q/Esc: close"
+    } else {
+        "n: new | e: edit | d: delete | v: reveal | q/Esc: close"
+    };
+
+    let block = Block::default()
+        .title(format!(" Environment: {} ({}) ", session_name, help))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(if is_input { 3 } else { 1 })])
+        .split(inner);
+
+    if app.session_env.is_empty() {
+        let empty = Paragraph::new("No variables. Press 'n' to add one, e.g. AWS_PROFILE=staging.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(ratatui::layout::Alignment::Center);
+        frame.render_widget(empty, chunks[0]);
+    } else {
+        let items: Vec<ListItem> = app.session_env
+            .iter()
+            .enumerate()
+            .map(|(idx, (key, value))| {
+                let style = if idx == app.selected_env && !is_input {
+                    Style::default().fg(Color::Black).bg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+                let shown = if !app.env_revealed && crate::app::is_secret_env(key, value) {
+                    "•••".to_string()
+                } else {
+                    value.clone()
+                };
+                ListItem::new(format!("{}={}", key, shown)).style(style)
+            })
+            .collect();
+        frame.render_widget(List::new(items), chunks[0]);
+    }
+
+    if is_input {
+        let (title, style) = match app.input_error {
+            Some(ref e) => (e.as_str(), Style::default().fg(Color::Red)),
+            None => ("KEY=VALUE", Style::default().fg(Color::Yellow)),
+        };
+        let input = Paragraph::new(app.input_buffer.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .block(Block::default().borders(Borders::TOP).title(Span::styled(title, style)));
+        frame.render_widget(input, chunks[1]);
+    } else {
+        let note = Paragraph::new("Set when the tmux session starts")
+            .style(Style::default().fg(Color::DarkGray));
+        frame.render_widget(note, chunks[1]);
+    }
+}
+
+fn render_confirm_apply_env_popup(frame: &mut Frame) {
+    let area = centered_rect(50, 25, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Apply environment ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let lines = vec![
+        Line::from("Apply the changed variables to the running tmux session?"),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Shells already running won't pick them up; new panes and respawned agents will.",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(""),
+        Line::from("(y)es / (n)o"),
+    ];
+    let para = Paragraph::new(lines)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: true });
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_quit_popup(app: &App, frame: &mut Frame) {
+    let names = app.waiting_session_names();
+
+    let area = centered_rect(50, 30, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Quit ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let count = names.len();
+    let mut lines = vec![
+        Line::from(format!(
+            "{} session{} waiting for input — quit anyway?",
+            count,
+            if count == 1 { " is" } else { "s are" }
+        )),
+        Line::from(""),
+    ];
+    for name in names {
+        lines.push(Line::from(Span::styled(format!("? {}", name), Style::default().fg(Color::Yellow))));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("(y)es / (n)o"));
+
+    let para = Paragraph::new(lines)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_shared_attach_popup(app: &App, frame: &mut Frame) {
+    let tmux_name = app.shared_attach.as_deref().unwrap_or_default();
+    let name = app
+        .sessions
+        .iter()
+        .find(|s| s.tmux_window.as_deref() == Some(tmux_name))
+        .map_or(tmux_name, |s| s.name.as_str());
+    let clients = app.pane_states.get(tmux_name).map_or(0, |pane| pane.clients);
+
+    let area = centered_rect(50, 30, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Already Attached ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let lines = vec![
+        Line::from(Span::styled(name.to_string(), Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(format!(
+            "{} other client{} showing this session.",
+            clients,
+            if clients == 1 { " is" } else { "s are" }
+        )),
+        Line::from("Switching here resizes it for everyone."),
+        Line::from(""),
+        Line::from("(s)witch anyway / (r)ead-only window / (n)o"),
+    ];
+
+    let para = Paragraph::new(lines)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_confirm_delete_dirty_popup(app: &App, frame: &mut Frame) {
+    let session_name = app.deleting_session_id
+        .and_then(|id| app.sessions.iter().find(|s| s.id == id))
+        .map(|s| s.name.as_str())
+        .unwrap_or("this session");
+
+    let area = centered_rect(50, 35, frame.area());
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" ⚠ Delete Dirty Worktree ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let mut lines = vec![
+        format!("Delete \"{}\"?", session_name),
+        String::new(),
+        "Worktree has uncommitted changes:".to_string(),
+    ];
+
+    if let Some(ref dirty) = app.deleting_dirty_status {
+        if dirty.staged > 0 {
+            lines.push(format!("  {} staged change{}", dirty.staged, if dirty.staged == 1 { "" } else { "s" }));
+        }
+        if dirty.unstaged > 0 {
+            lines.push(format!("  {} unstaged change{}", dirty.unstaged, if dirty.unstaged == 1 { "" } else { "s" }));
+        }
+        if dirty.untracked > 0 {
+            lines.push(format!("  {} untracked file{}", dirty.untracked, if dirty.untracked == 1 { "" } else { "s" }));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push("The changes are lost when it is purged from the trash!".to_string());
+    lines.push(String::new());
+    lines.push("(y)es / (n)o".to_string());
+
+    let text = lines.join("\n");
+    let para = Paragraph::new(text)
+        .style(Style::default().fg(Color::Yellow))
+        .alignment(ratatui::layout::Alignment::Center);
+    frame.render_widget(para, inner);
+}
+
+fn render_comments_popup(app: &App, frame: &mut Frame) {
+    let session_name = app.comments_session_id
+        .and_then(|id| app.sessions.iter().find(|s| s.id == id))
+        .map(|s| s.name.as_str())
+        .unwrap_or("Session");
+
+    let area = centered_rect(70, 70, frame.area());
+    frame.render_widget(Clear, area);
+
+    let is_new_comment = app.input_mode == InputMode::NewComment;
+    let help = if is_new_comment {
+        "Enter: save | Esc: cancel"
+    } else {
+        "n: new | jk: scroll | q/Esc: close"
+    };
+
+    let block = Block::default()
+        .title(format!(" Comments: {} ({}) ", session_name, help))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // Split into comments list and input area (if adding new comment)
+    let chunks = if is_new_comment {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(inner)
+    } else {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0)])
+            .split(inner)
+    };
+
+    // Render comments list
+    if app.comments.is_empty() {
+        let empty = Paragraph::new("No comments yet. Press 'n' to add one.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(ratatui::layout::Alignment::Center);
+        frame.render_widget(empty, chunks[0]);
+    } else {
+        let items: Vec<ListItem> = app.comments
+            .iter()
+            .enumerate()
+            .map(|(idx, comment)| {
+                let is_selected = idx == app.comments_scroll;
+                let style = if is_selected {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::White)
+                };
+
+                // Format timestamp (just show date/time portion)
+                let timestamp = &comment.created_at;
+                let header = format!("─ {} ─", timestamp);
+
+                let lines = vec![
+                    Line::from(Span::styled(header, Style::default().fg(Color::DarkGray))),
+                    Line::from(Span::styled(&comment.text, style)),
+                    Line::from(""),
+                ];
+                ListItem::new(lines)
+            })
+            .collect();
+
+        let list = List::new(items);
+        frame.render_widget(list, chunks[0]);
+    }
+
+    // Render input area if adding new comment
+    if is_new_comment {
+        let input = Paragraph::new(app.new_comment_text.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .block(Block::default().borders(Borders::TOP).title("New Comment"));
+        frame.render_widget(input, chunks[1]);
+    }
+}
+
+fn render_branch_changes_popup(app: &App, frame: &mut Frame) {
+    let Some(ref view) = app.branch_changes else { return };
+    let area = centered_rect(80, 70, frame.area());
+    frame.render_widget(Clear, area);
+
+    let title = match view.base {
+        Some(ref base) => format!(" {}: {} vs {} (jk: scroll, q/Esc: close) ", view.session_name, view.branch, base),
+        None => format!(" {}: {} (q/Esc: close) ", view.session_name, view.branch),
     };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
 
-    let name_style = if is_selected {
-        Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
-    } else {
-        Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+    let dim = Style::default().fg(Color::DarkGray);
+    let heading = Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD);
+    let lines: Vec<Line> = match view.result {
+        None => vec![Line::styled("Reading git history...", dim)],
+        Some(Err(ref e)) => e.lines().map(|line| Line::styled(line.to_string(), Style::default().fg(Color::Red))).collect(),
+        Some(Ok(ref changes)) if changes.log.is_empty() => vec![Line::styled("No commits yet", dim)],
+        Some(Ok(ref changes)) => {
+            let mut lines = vec![Line::styled(format!("Commits ({})", changes.log.len()), heading)];
+            lines.extend(changes.log.iter().map(|line| Line::from(line.as_str())));
+            lines.push(Line::from(""));
+            lines.push(Line::styled("Files", heading));
+            lines.extend(changes.diff_stat.iter().map(|line| Line::from(line.as_str())));
+            lines
+        }
     };
 
-    let detail_style = Style::default().fg(Color::DarkGray);
+    let body = Paragraph::new(lines).block(block).scroll((view.scroll, 0));
+    frame.render_widget(body, area);
+}
 
-    // Build card title with indicator
-    let title = if app.is_waiting_for_input(session) {
-        format!(" ? {} ", session.name)
-    } else if app.has_active_terminal(session) {
-        format!(" $ {} ", session.name)
-    } else {
-        format!(" {} ", session.name)
-    };
+fn render_land_popup(app: &App, frame: &mut Frame) {
+    use crate::app::StepOutcome;
 
-    let title_style = if app.is_waiting_for_input(session) {
-        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
-    } else if app.has_active_terminal(session) {
-        Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+    let Some(ref view) = app.land else { return };
+    let area = centered_rect(70, 60, frame.area());
+    frame.render_widget(Clear, area);
+
+    let help = if view.finished() {
+        "Enter/Esc: close"
+    } else if view.failed() {
+        "r: retry | s: skip this step | Esc: stop here"
     } else {
-        name_style
+        "Enter: run | Esc: cancel"
     };
+    let block = Block::default()
+        .title(format!(" Land {} ({}) ", view.session_name, help))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
 
-    let card_block = Block::default()
-        .title(Span::styled(title, title_style))
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+    for (step, command, outcome) in &view.steps {
+        let (mark, style) = match outcome {
+            StepOutcome::Pending => ("·", Style::default().fg(Color::White)),
+            StepOutcome::Done(_) => ("✓", Style::default().fg(Color::Green)),
+            StepOutcome::Failed(_) => ("✗", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            StepOutcome::Skipped => ("↷", dim),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{} {}", mark, step.label()), style),
+            Span::styled(format!("  {}", command), dim),
+        ]));
+        match outcome {
+            StepOutcome::Done(text) => lines.push(Line::styled(format!("    {}", text), dim)),
+            StepOutcome::Failed(error) => {
+                lines.extend(error.lines().map(|line| Line::styled(format!("    {}", line), Style::default().fg(Color::Red))));
+            }
+            StepOutcome::Skipped => lines.push(Line::styled("    skipped", dim)),
+            StepOutcome::Pending => {}
+        }
+    }
+    frame.render_widget(Paragraph::new(lines).block(block).wrap(ratatui::widgets::Wrap { trim: false }), area);
+}
+
+fn render_reload_problems_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(70, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let mut lines: Vec<Line> = app
+        .reload_problems
+        .iter()
+        .map(|problem| Line::styled(format!("⚠ {}", problem), Style::default().fg(Color::Red)))
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::styled(
+        "The values in use before the reload stay in effect; fix them in Settings > Project.",
+        Style::default().fg(Color::DarkGray),
+    ));
+    lines.push(Line::styled(
+        format!("Need a restart to change: {}.", crate::app::RESTART_ONLY.join(", ")),
+        Style::default().fg(Color::DarkGray),
+    ));
+    let block = Block::default()
+        .title(" Settings reloaded with problems (Esc: close) ")
         .borders(Borders::ALL)
-        .border_style(border_style);
+        .border_style(Style::default().fg(Color::Red))
+        .style(Style::default().bg(Color::Black));
+    frame.render_widget(Paragraph::new(lines).block(block).wrap(ratatui::widgets::Wrap { trim: false }), area);
+}
 
-    let inner = card_block.inner(area);
-    frame.render_widget(card_block, area);
+fn render_board_changes_popup(app: &App, frame: &mut Frame) {
+    use crate::changes::Change;
 
-    // Build card content
-    let mut lines: Vec<Line> = Vec::new();
+    let height = (app.board_changes.len() as u16 + 2).min(frame.area().height);
+    let area = centered_rect(60, 100, frame.area());
+    let area = Rect { y: area.y + (area.height.saturating_sub(height)) / 2, height, ..area };
+    frame.render_widget(Clear, area);
 
-    // Branch name - prefer stored branch_name, fall back to tmux detection
-    let branch_to_display = session.branch_name.clone().or_else(|| {
-        session.tmux_window.as_ref().and_then(|tmux_name| {
-            if app.active_tmux_sessions.contains(tmux_name) {
-                tmux::get_git_branch(tmux_name)
-            } else {
-                None
-            }
+    let lines: Vec<Line> = app
+        .board_changes
+        .iter()
+        .map(|change| match change {
+            Change::Added { name, .. } => Line::styled(format!("+ {}", name), Style::default().fg(Color::Green)),
+            Change::Removed { name, .. } => Line::styled(format!("- {}", name), Style::default().fg(Color::Red)),
+            Change::Moved { name, from, to, .. } => Line::from(format!(
+                "  {}: {} → {}",
+                name,
+                app.status_label(*from),
+                app.status_label(*to)
+            )),
+            Change::Renamed { from, to, .. } => Line::from(format!("  {} renamed to {}", from, to)),
         })
-    });
+        .collect();
+    let block = Block::default()
+        .title(" Changed outside workbench (Esc: close) ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-    if let Some(branch) = branch_to_display {
-        lines.push(Line::from(vec![
-            Span::styled("⎇ ", Style::default().fg(Color::Blue)),
-            Span::styled(branch, Style::default().fg(Color::Blue)),
-        ]));
-    }
+fn render_transcripts_popup(app: &App, frame: &mut Frame) {
+    let height = (app.transcripts.len() as u16 + 2).min(frame.area().height);
+    let area = centered_rect(60, 100, frame.area());
+    let area = Rect { y: area.y + (area.height.saturating_sub(height)) / 2, height, ..area };
+    frame.render_widget(Clear, area);
 
-    // Custom field values (only visible fields)
-    for field in app.fields.iter().filter(|f| f.visible) {
-        let value = app.db.get_session_field_value(session.id, field.id).unwrap_or_default();
-        if !value.is_empty() {
-            let is_url = value.starts_with("http://") || value.starts_with("https://");
-            // Truncate long values to fit card width
-            let max_len = 25;
-            let display_value: String = if value.chars().count() > max_len {
-                format!("{}…", value.chars().take(max_len).collect::<String>())
-            } else {
-                value
+    let items: Vec<ListItem> = app
+        .transcripts
+        .iter()
+        .enumerate()
+        .map(|(idx, (transcript, size))| {
+            let size = match size {
+                Some(bytes) => format!("{} KiB", bytes.div_ceil(1024)),
+                None => "missing".to_string(),
             };
-            let value_style = if is_url {
-                Style::default().fg(Color::Cyan).add_modifier(Modifier::UNDERLINED)
+            let style = if idx == app.selected_transcript {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
             } else {
                 Style::default().fg(Color::White)
             };
-            lines.push(Line::from(vec![
-                Span::styled(format!("{}: ", field.name), detail_style),
-                Span::styled(display_value, value_style),
-            ]));
-        }
-    }
-
-    let content = Paragraph::new(lines);
-    frame.render_widget(content, inner);
-}
-
-fn render_kanban_footer(app: &App, frame: &mut Frame, area: Rect) {
-    let text = if let Some(ref msg) = app.status_message {
-        msg.clone()
-    } else {
-        "q: quit | n: new | e: edit | c: comments | Space: peek | hjkl: nav | m: move | d: del | r: refresh | s: settings | Enter: term".to_string()
-    };
-    let style = if app.status_message.is_some() {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
-    let footer = Paragraph::new(text).style(style);
-    frame.render_widget(footer, area);
+            ListItem::new(format!("{}  {:>10}", transcript.created_at, size)).style(style)
+        })
+        .collect();
+    let block = Block::default()
+        .title(format!(" Transcripts: {} (Enter: open, q/Esc: close) ", app.transcripts_session_name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+    frame.render_widget(List::new(items).block(block), area);
 }
 
-fn render_peek_overlay(app: &App, frame: &mut Frame) {
-    let Some(session) = app.selected_session() else { return };
-    let Some(ref tmux_name) = session.tmux_window else { return };
+fn render_transcript_view(app: &App, frame: &mut Frame) {
+    let Some(ref view) = app.transcript_view else { return };
+    let area = centered_rect(90, 90, frame.area());
+    frame.render_widget(Clear, area);
 
-    let content = tmux::capture_pane_content(tmux_name)
-        .unwrap_or_else(|| "(no content)".to_string());
+    let block = Block::default()
+        .title(format!(
+            " {} | line {}/{} (jk/PgUp/PgDn/g/G: scroll, q/Esc: back) ",
+            view.title,
+            (view.scroll as usize + 1).min(view.lines.len()),
+            view.lines.len()
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
+    let lines: Vec<Line> = view.lines.iter().map(|line| Line::from(line.as_str())).collect();
+    let body = Paragraph::new(lines)
+        .block(block)
+        .style(Style::default().fg(Color::White))
+        .scroll((view.scroll, 0));
+    frame.render_widget(body, area);
+}
 
+fn render_worktree_maintenance_popup(app: &App, frame: &mut Frame) {
     let area = centered_rect(80, 70, frame.area());
     frame.render_widget(Clear, area);
 
+    let help = if app.confirm_worktree_prune {
+        "remove selected worktrees? (y)es / (n)o"
+    } else {
+        "Space: select | a: select suggested | d/Enter: remove | q/Esc: close"
+    };
     let block = Block::default()
-        .title(format!(" {} ", session.name))
+        .title(format!(" Worktrees ({}) ", help))
         .borders(Borders::ALL)
+        .border_style(Style::default().fg(if app.confirm_worktree_prune { Color::Red } else { Color::Cyan }))
         .style(Style::default().bg(Color::Black));
+
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let para = Paragraph::new(content)
-        .style(Style::default().fg(Color::White));
-    frame.render_widget(para, inner);
+    if app.worktree_entries.is_empty() {
+        let empty = Paragraph::new("No session worktrees.")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(ratatui::layout::Alignment::Center);
+        frame.render_widget(empty, inner);
+        return;
+    }
+
+    let items: Vec<ListItem> = app.worktree_entries
+        .iter()
+        .enumerate()
+        .map(|(idx, entry)| {
+            let marker = if entry.selected { "[x]" } else { "[ ]" };
+            let size = entry.size.map(format_size).unwrap_or_else(|| "…".to_string());
+            let hint = if entry.candidate { "  done + merged, safe to prune" } else { "" };
+            let style = if idx == app.selected_worktree {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else if entry.candidate {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let line = Line::from(vec![
+                Span::raw(format!("{} {:>9}  {}", marker, size, entry.session_name)),
+                Span::styled(format!("  {}", entry.path.display()), Style::default().fg(Color::DarkGray)),
+                Span::raw(hint),
+            ]);
+            ListItem::new(line).style(style)
+        })
+        .collect();
+
+    frame.render_widget(List::new(items), inner);
 }
 
-fn render_input_popup(app: &App, frame: &mut Frame, title: &str) {
-    let area = centered_rect(50, 20, frame.area());
+fn render_batch_fill_fields_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(50, 40, frame.area());
     frame.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(format!(" {} ", title))
+        .title(" Batch AI fill: fields (Space: toggle | Enter: start | Esc: cancel) ")
         .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let input = Paragraph::new(app.input_buffer.as_str())
-        .style(Style::default().fg(Color::Yellow))
-        .block(Block::default().borders(Borders::BOTTOM).title("Name"));
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
 
-    frame.render_widget(input, inner);
-}
+    let items: Vec<ListItem> = app.fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let checked = app.batch_fill_selected.get(idx).copied().unwrap_or(false);
+            let style = if idx == app.selected_batch_field {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("{} {}", if checked { "[x]" } else { "[ ]" }, field.name)).style(style)
+        })
+        .collect();
+    frame.render_widget(List::new(items), chunks[0]);
 
-fn render_edit_session_popup(app: &App, frame: &mut Frame) {
-    use crate::app::EditMode;
+    let field_ids: Vec<i64> = app.fields
+        .iter()
+        .zip(&app.batch_fill_selected)
+        .filter(|(_, selected)| **selected)
+        .map(|(f, _)| f.id)
+        .collect();
+    let count = app.batch_fill_candidates(&field_ids).len();
+    let summary = Paragraph::new(format!(
+        "{} session{} with empty values",
+        count,
+        if count == 1 { "" } else { "s" }
+    ))
+    .style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(summary, chunks[1]);
+}
 
-    let num_fields = app.fields.len();
-    let is_ai_mode = app.edit_mode == EditMode::AI;
+fn render_batch_fill_popup(app: &App, frame: &mut Frame) {
+    use crate::app::BatchFillState;
 
-    // In AI mode, add extra row for AI input
-    let total_display_rows = if is_ai_mode { 2 + num_fields } else { 1 + num_fields };
-    let popup_height = std::cmp::min(20 + (total_display_rows * 3) as u16, 80);
-    let area = centered_rect(60, popup_height, frame.area());
+    let area = centered_rect(70, 60, frame.area());
     frame.render_widget(Clear, area);
 
-    let mode_str = if app.ai_running {
-        "AI ⏳ Running..."
-    } else {
-        match app.edit_mode {
-            EditMode::Manual => "Manual",
-            EditMode::AI => "AI ✨",
-        }
-    };
-    let help = if app.ai_running {
-        "Please wait..."
-    } else if is_ai_mode {
-        "Shift+Tab: mode, Enter: run AI"
+    let running = app.batch_fill_running();
+    let (updated, failed) = app.batch_fill_summary();
+    let title = if running {
+        " Batch AI fill (c/Esc: cancel) ".to_string()
     } else {
-        "Shift+Tab: mode, Tab/↑↓: nav, Enter: save"
+        format!(" Batch AI fill done: {} updated, {} failed (Enter/Esc: close) ", updated, failed)
     };
-    let title = format!(" Edit Session [{}] ({}) ", mode_str, help);
-
     let block = Block::default()
         .title(title)
         .borders(Borders::ALL)
+        .border_style(Style::default().fg(if !running && failed > 0 { Color::Red } else { Color::Cyan }))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    // Create constraints for each row
-    let constraints: Vec<Constraint> = (0..total_display_rows)
-        .map(|_| Constraint::Length(3))
-        .collect();
-    let rows = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(constraints)
-        .split(inner);
-
-    let mut row_offset = 0;
-
-    // In AI mode, render AI input field first
-    if is_ai_mode {
-        let (ai_style, ai_title) = if app.ai_running {
-            (Style::default().fg(Color::Yellow), "⏳ Running AI... please wait".to_string())
-        } else if let Some(ref err) = app.ai_error {
-            (Style::default().fg(Color::Red), format!("❌ Error: {}", err.chars().take(40).collect::<String>()))
-        } else {
-            (Style::default().fg(Color::Magenta), "✨ AI Prompt (describe what to fill)".to_string())
-        };
-        let ai_block = Block::default()
-            .borders(Borders::BOTTOM)
-            .title(ai_title)
-            .border_style(ai_style);
-        let ai_input = Paragraph::new(app.ai_input.as_str())
-            .style(ai_style)
-            .block(ai_block);
-        if !rows.is_empty() {
-            frame.render_widget(ai_input, rows[0]);
-        }
-        row_offset = 1;
-    }
-
-    // Render name field
-    let name_row = row_offset;
-    let name_selected = !is_ai_mode && app.edit_row == 0;
-    let name_style = if is_ai_mode {
-        Style::default().fg(Color::DarkGray) // Locked in AI mode
-    } else if name_selected {
-        Style::default().fg(Color::Yellow)
-    } else {
-        Style::default().fg(Color::DarkGray)
-    };
-    let name_value = if name_selected && !is_ai_mode {
-        app.input_buffer.as_str()
-    } else {
-        app.edit_session_name.as_str()
-    };
-    let name_prefix = if is_ai_mode { "  " } else if name_selected { "> " } else { "  " };
-    let name_block = Block::default()
-        .borders(Borders::BOTTOM)
-        .title(format!("{}Name", name_prefix))
-        .border_style(name_style);
-    let name_input = Paragraph::new(name_value)
-        .style(name_style)
-        .block(name_block);
-    if name_row < rows.len() {
-        frame.render_widget(name_input, rows[name_row]);
+    let items: Vec<ListItem> = app.batch_fill_entries
+        .iter()
+        .map(|entry| {
+            let (status, color) = match &entry.state {
+                BatchFillState::Pending => ("…".to_string(), Color::DarkGray),
+                BatchFillState::Running => ("running".to_string(), Color::Yellow),
+                BatchFillState::Updated(0) => ("nothing found".to_string(), Color::DarkGray),
+                BatchFillState::Updated(n) => (format!("✓ {} field{}", n, if *n == 1 { "" } else { "s" }), Color::Green),
+                BatchFillState::Failed(e) => (format!("✗ {}", e), Color::Red),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<40} ", entry.session_name), Style::default().fg(Color::White)),
+                Span::styled(status, Style::default().fg(color)),
+            ]))
+        })
+        .collect();
+    frame.render_widget(List::new(items), inner);
+}
+
+/// Format a byte count for display, e.g. "4.1 GB"
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
     }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
 
-    // Render custom fields
-    for (i, field) in app.fields.iter().enumerate() {
-        let row_idx = row_offset + 1 + i;
-        if row_idx >= rows.len() {
-            break;
-        }
-        let is_selected = !is_ai_mode && app.edit_row == i + 1;
-        let style = if is_ai_mode {
-            Style::default().fg(Color::DarkGray) // Locked in AI mode
-        } else if is_selected {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default().fg(Color::DarkGray)
-        };
-        let value = if is_selected && !is_ai_mode {
-            app.input_buffer.as_str()
+/// The card being moved (name, column, visible fields) above the numbered destinations
+fn render_move_popup(app: &App, frame: &mut Frame, session: &Session) {
+    let screen = frame.area();
+    let base = centered_rect(40, 25, screen);
+    let details = session_detail_lines(app, session, base.width.saturating_sub(2) as usize);
+
+    let statuses = Status::all();
+    // Name, details and a blank line, then the destinations, plus borders
+    let needed = (1 + details.len() + 1 + statuses.len() + 2) as u16;
+    let height = needed.max(base.height).min(screen.height);
+    let area = Rect { y: screen.y + (screen.height - height) / 2, height, ..base };
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Move to ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![Line::styled(
+        session.name.clone(),
+        Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+    )];
+    lines.extend(details);
+    lines.push(Line::from(""));
+    for (i, status) in statuses.iter().enumerate() {
+        let text = format!("{}: {}", i + 1, app.status_label(*status));
+        let style = if *status == session.status {
+            dim
         } else {
-            app.edit_field_values.get(i).map(|s| s.as_str()).unwrap_or("")
+            Style::default().fg(status_color(app, *status).unwrap_or(Color::White))
         };
-        let prefix = if is_ai_mode { "  " } else if is_selected { "> " } else { "  " };
-        let title = format!("{}{}", prefix, field.name);
-        let field_block = Block::default()
-            .borders(Borders::BOTTOM)
-            .title(title)
-            .border_style(style);
-        let field_input = Paragraph::new(value)
-            .style(style)
-            .block(field_block);
-        frame.render_widget(field_input, rows[row_idx]);
+        lines.push(Line::styled(text, style));
     }
-}
 
-fn render_confirm_delete_field_popup(app: &App, frame: &mut Frame) {
-    let field_name = app.deleting_field_id
-        .and_then(|id| app.fields.iter().find(|f| f.id == id))
-        .map(|f| f.name.as_str())
-        .unwrap_or("this field");
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
 
-    let area = centered_rect(40, 20, frame.area());
+fn render_dead_pane_popup(app: &App, frame: &mut Frame) {
+    let Some(code) = app.selected_session().and_then(|s| app.exit_status(s)) else {
+        return;
+    };
+    let area = centered_rect(45, 20, frame.area());
     frame.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(" Delete Field ")
+        .title(" Session Exited ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let text = format!("Delete \"{}\"?\n\n(y)es / (n)o", field_name);
-    let para = Paragraph::new(text)
-        .style(Style::default().fg(Color::White))
-        .alignment(ratatui::layout::Alignment::Center);
-    frame.render_widget(para, inner);
+    let status_color = if code == 0 { Color::Green } else { Color::Red };
+    let text = vec![
+        Line::from(vec![
+            Span::raw("The agent exited with status "),
+            Span::styled(code.to_string(), Style::default().fg(status_color).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(""),
+        Line::from(Span::styled(
+            "r/Enter: respawn and attach | x: clean up | Esc: cancel",
+            Style::default().fg(Color::DarkGray),
+        )),
+        Line::from(Span::styled(
+            "Space on the card peeks at the final output",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    frame.render_widget(Paragraph::new(text), inner);
 }
 
-fn render_confirm_delete_popup(app: &App, frame: &mut Frame) {
-    let session_name = app.deleting_session_id
+fn render_confirm_recreate_worktree_popup(app: &App, frame: &mut Frame) {
+    let Some(session) = app.recreate_worktree_session_id
         .and_then(|id| app.sessions.iter().find(|s| s.id == id))
-        .map(|s| s.name.as_str())
-        .unwrap_or("this session");
-
-    let area = centered_rect(40, 20, frame.area());
+    else {
+        return;
+    };
+    let (Some(path), Some(branch)) = (&session.checkout_path, &session.branch_name) else {
+        return;
+    };
+    let area = centered_rect(60, 30, frame.area());
     frame.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(" Delete Session ")
+        .title(" Worktree Missing ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red))
+        .border_style(Style::default().fg(Color::Yellow))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let text = format!("Delete \"{}\"?\n\n(y)es / (n)o", session_name);
+    let text = format!(
+        "The worktree of \"{}\" is gone:\n{}\n\n\
+         Recreate it on {} before starting?\n\n\
+         (y)es / (n)o, start in the project directory / Esc: cancel",
+        session.name, path.display(), branch
+    );
     let para = Paragraph::new(text)
         .style(Style::default().fg(Color::White))
-        .alignment(ratatui::layout::Alignment::Center);
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: false });
     frame.render_widget(para, inner);
 }
 
-fn render_confirm_delete_dirty_popup(app: &App, frame: &mut Frame) {
-    let session_name = app.deleting_session_id
-        .and_then(|id| app.sessions.iter().find(|s| s.id == id))
-        .map(|s| s.name.as_str())
-        .unwrap_or("this session");
-
-    let area = centered_rect(50, 35, frame.area());
+fn render_confirm_adopt_terminal_popup(app: &App, frame: &mut Frame) {
+    let Some((session_id, ref tmux_name)) = app.adopt_terminal else {
+        return;
+    };
+    let name = app.sessions.iter().find(|s| s.id == session_id).map_or("this session", |s| s.name.as_str());
+    let area = centered_rect(60, 30, frame.area());
     frame.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(" ⚠ Delete Dirty Worktree ")
+        .title(" Terminal Already Running ")
         .borders(Borders::ALL)
         .border_style(Style::default().fg(Color::Yellow))
         .style(Style::default().bg(Color::Black));
@@ -560,142 +3445,182 @@ fn render_confirm_delete_dirty_popup(app: &App, frame: &mut Frame) {
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let mut lines = vec![
-        format!("Delete \"{}\"?", session_name),
-        String::new(),
-        "Worktree has uncommitted changes:".to_string(),
+    let lines = vec![
+        Line::from(format!("tmux session {} is running in the directory of \"{}\" but isn't linked to it.", tmux_name, name)),
+        Line::from(""),
+        Line::from("Adopt it as this card's terminal?"),
+        Line::from(""),
+        Line::from("(a)dopt / (c)reate a new one / Esc: cancel"),
     ];
+    let para = Paragraph::new(lines)
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: false });
+    frame.render_widget(para, inner);
+}
 
-    if let Some(ref dirty) = app.deleting_dirty_status {
-        if dirty.staged > 0 {
-            lines.push(format!("  {} staged change{}", dirty.staged, if dirty.staged == 1 { "" } else { "s" }));
-        }
-        if dirty.unstaged > 0 {
-            lines.push(format!("  {} unstaged change{}", dirty.unstaged, if dirty.unstaged == 1 { "" } else { "s" }));
-        }
-        if dirty.untracked > 0 {
-            lines.push(format!("  {} untracked file{}", dirty.untracked, if dirty.untracked == 1 { "" } else { "s" }));
-        }
-    }
+fn render_confirm_restart_terminal_popup(app: &App, frame: &mut Frame) {
+    let Some(session) = app.restart_session_id.and_then(|id| app.sessions.iter().find(|s| s.id == id)) else {
+        return;
+    };
+    let area = centered_rect(50, 30, frame.area());
+    frame.render_widget(Clear, area);
 
-    lines.push(String::new());
-    lines.push("This will PERMANENTLY delete all changes!".to_string());
-    lines.push(String::new());
-    lines.push("(y)es / (n)o".to_string());
+    let block = Block::default()
+        .title(" Restart Terminal ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .style(Style::default().bg(Color::Black));
 
-    let text = lines.join("\n");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let choices = if app.can_resume(session) {
+        "(f)resh / (r)esume / Esc: cancel"
+    } else {
+        "(f)resh start / Esc: cancel"
+    };
+    let text = format!(
+        "Kill the terminal of \"{}\" and start it again with the same directory, environment and command?\n\n{}",
+        session.name, choices
+    );
     let para = Paragraph::new(text)
-        .style(Style::default().fg(Color::Yellow))
-        .alignment(ratatui::layout::Alignment::Center);
+        .style(Style::default().fg(Color::White))
+        .alignment(ratatui::layout::Alignment::Center)
+        .wrap(ratatui::widgets::Wrap { trim: false });
     frame.render_widget(para, inner);
 }
 
-fn render_comments_popup(app: &App, frame: &mut Frame) {
-    let session_name = app.comments_session_id
-        .and_then(|id| app.sessions.iter().find(|s| s.id == id))
-        .map(|s| s.name.as_str())
-        .unwrap_or("Session");
-
-    let area = centered_rect(70, 70, frame.area());
+fn render_pick_quick_field_popup(app: &App, frame: &mut Frame) {
+    let session_id = app.quick_field.map(|(id, _)| id);
+    let height = (app.fields.len() as u16 + 2).min(frame.area().height);
+    let area = centered_rect(50, 100, frame.area());
+    let area = Rect { y: area.y + (area.height.saturating_sub(height)) / 2, height, ..area };
     frame.render_widget(Clear, area);
 
-    let is_new_comment = app.input_mode == InputMode::NewComment;
-    let help = if is_new_comment {
-        "Enter: save | Esc: cancel"
-    } else {
-        "n: new | jk: scroll | q/Esc: close"
-    };
+    let items: Vec<ListItem> = app
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| {
+            let value = session_id.map_or("", |id| app.field_value(id, field.id));
+            let number = if idx < 9 { format!("{}.", idx + 1) } else { "  ".to_string() };
+            let style = if idx == app.selected_quick_field {
+                Style::default().fg(Color::Black).bg(Color::Yellow)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            let value = value.lines().next().unwrap_or("").to_string();
+            ListItem::new(format!("{} {}: {}", number, field.name, value)).style(style)
+        })
+        .collect();
+    let block = Block::default()
+        .title(" Set field (1-9/Enter: pick, Esc: cancel) ")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    frame.render_widget(List::new(items).block(block), area);
+}
+
+fn render_pick_shortcut_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(50, 30, frame.area());
+    frame.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(format!(" Comments: {} ({}) ", session_name, help))
+        .title(" New from shortcut (1-9) ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan))
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    // Split into comments list and input area (if adding new comment)
-    let chunks = if is_new_comment {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0), Constraint::Length(3)])
-            .split(inner)
-    } else {
-        Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(0)])
-            .split(inner)
-    };
+    let items: Vec<ListItem> = app
+        .shortcuts
+        .iter()
+        .take(9)
+        .enumerate()
+        .map(|(idx, shortcut)| {
+            let problem = app.shortcut_problem(shortcut);
+            let style = if problem.is_some() {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(shortcut_summary(app, idx, shortcut, problem)).style(style)
+        })
+        .collect();
 
-    // Render comments list
-    if app.comments.is_empty() {
-        let empty = Paragraph::new("No comments yet. Press 'n' to add one.")
-            .style(Style::default().fg(Color::DarkGray))
-            .alignment(ratatui::layout::Alignment::Center);
-        frame.render_widget(empty, chunks[0]);
-    } else {
-        let items: Vec<ListItem> = app.comments
-            .iter()
-            .enumerate()
-            .map(|(idx, comment)| {
-                let is_selected = idx == app.comments_scroll;
-                let style = if is_selected {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                };
+    frame.render_widget(List::new(items), inner);
+}
 
-                // Format timestamp (just show date/time portion)
-                let timestamp = &comment.created_at;
-                let header = format!("─ {} ─", timestamp);
+fn render_move_checklist_popup(app: &App, frame: &mut Frame) {
+    let Some(pending) = app.checklist_move else { return };
+    let name = app.sessions.iter().find(|s| s.id == pending.session_id).map_or("Card", |s| s.name.as_str());
+    let area = centered_rect(50, 40, frame.area());
+    frame.render_widget(Clear, area);
 
-                let lines = vec![
-                    Line::from(Span::styled(header, Style::default().fg(Color::DarkGray))),
-                    Line::from(Span::styled(&comment.text, style)),
-                    Line::from(""),
-                ];
-                ListItem::new(lines)
-            })
-            .collect();
+    let block = Block::default()
+        .title(format!(" {} → {} ", name, app.status_label(pending.to)))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan))
+        .style(Style::default().bg(Color::Black));
 
-        let list = List::new(items);
-        frame.render_widget(list, chunks[0]);
-    }
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(2)])
+        .split(inner);
 
-    // Render input area if adding new comment
-    if is_new_comment {
-        let input = Paragraph::new(app.new_comment_text.as_str())
-            .style(Style::default().fg(Color::Yellow))
-            .block(Block::default().borders(Borders::TOP).title("New Comment"));
-        frame.render_widget(input, chunks[1]);
-    }
+    let items: Vec<ListItem> = app
+        .status_checklist(pending.to)
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let checked = app.checklist_checked.get(i).copied().unwrap_or(false);
+            let style = if i == app.selected_checklist_item {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else if checked {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(format!("[{}] {}", if checked { "x" } else { " " }, item)).style(style)
+        })
+        .collect();
+    frame.render_widget(List::new(items), chunks[0]);
+
+    let help = Paragraph::new("Space: check | Enter: move when all checked\no: move anyway (noted as skipped) | Esc: cancel")
+        .style(Style::default().fg(Color::DarkGray));
+    frame.render_widget(help, chunks[1]);
 }
 
-fn render_move_popup(frame: &mut Frame) {
-    let area = centered_rect(30, 25, frame.area());
+fn render_card_actions_popup(app: &App, frame: &mut Frame) {
+    let area = centered_rect(35, 25, frame.area());
     frame.render_widget(Clear, area);
 
     let block = Block::default()
-        .title(" Move to ")
+        .title(" Actions (j/k, Enter) ")
         .borders(Borders::ALL)
         .style(Style::default().bg(Color::Black));
 
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let statuses = Status::all();
-    let items: Vec<ListItem> = statuses
+    let items: Vec<ListItem> = app
+        .card_actions
         .iter()
         .enumerate()
-        .map(|(i, status)| {
-            let text = format!("{}: {}", i + 1, status.label());
-            ListItem::new(text).style(Style::default().fg(Color::White))
+        .map(|(i, action)| {
+            let style = if i == app.selected_card_action {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            ListItem::new(action.label()).style(style)
         })
         .collect();
 
-    let list = List::new(items);
-    frame.render_widget(list, inner);
+    frame.render_widget(List::new(items), inner);
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -717,3 +3642,125 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn layout_at(width: u16, selected: usize) -> Vec<(Rect, bool)> {
+        let mut terminal = Terminal::new(TestBackend::new(width, 40)).unwrap();
+        let mut layout = Vec::new();
+        terminal
+            .draw(|frame| layout = kanban_layout(frame.area(), 4, selected))
+            .unwrap();
+        layout
+    }
+
+    #[test]
+    fn test_wrap_text() {
+        let (lines, truncated) = wrap_text("Ticket id like ABC-123.\nNever a URL.", 12, None);
+        assert_eq!(lines, vec!["Ticket id", "like", "ABC-123.", "Never a URL."]);
+        assert!(!truncated);
+
+        let (lines, truncated) = wrap_text("one two three four five six", 9, Some(2));
+        assert_eq!(lines, vec!["one two", "three"]);
+        assert!(truncated);
+
+        assert_eq!(wrap_text("  ", 10, Some(3)), (Vec::new(), false));
+    }
+
+    #[test]
+    fn test_kanban_layout_side_by_side_when_wide() {
+        for width in [120, 160, 240] {
+            let layout = layout_at(width, 2);
+            assert!(layout.iter().all(|(_, expanded)| *expanded), "width {}", width);
+            assert!(layout.iter().all(|(rect, _)| rect.height == 40));
+            assert_eq!(layout.iter().map(|(rect, _)| rect.width).sum::<u16>(), width);
+        }
+    }
+
+    #[test]
+    fn test_kanban_layout_stacks_when_narrow() {
+        for width in [40, 80, 119] {
+            let layout = layout_at(width, 1);
+            let expanded: Vec<bool> = layout.iter().map(|(_, e)| *e).collect();
+            assert_eq!(expanded, vec![false, true, false, false], "width {}", width);
+            assert!(layout.iter().all(|(rect, _)| rect.width == width));
+            assert_eq!(layout[0].0.height, 1);
+            assert_eq!(layout[1].0.height, 40 - 3);
+            assert_eq!(layout[2].0.y, 1 + 37);
+        }
+    }
+
+    #[test]
+    fn test_column_abbrev() {
+        assert_eq!(column_abbrev("In Progress"), "IP");
+        assert_eq!(column_abbrev("review"), "R");
+        assert_eq!(column_abbrev("  "), "");
+    }
+
+    #[test]
+    fn test_owner_badge() {
+        assert_eq!(owner_initials("Tom Eehan"), "TE");
+        assert_eq!(owner_initials("ada lovelace byron"), "AL");
+        assert_eq!(owner_color("Tom Eehan"), owner_color(" tom eehan"));
+    }
+
+    #[test]
+    fn test_wrapped_field_lines() {
+        let lines = wrapped_field_lines("Summary", "Fix the flaky login test on CI", false, 16);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec!["Summary: Fix the", "flaky login test", "on CI"]);
+        assert_eq!(lines[0].spans[0].content, "Summary: ");
+    }
+
+    #[test]
+    fn test_card_field_wraps_or_cuts() {
+        let mut field = crate::db::Field {
+            id: 1,
+            name: "Summary".to_string(),
+            description: String::new(),
+            display_order: 0,
+            visible: true,
+            in_ai: true,
+            in_edit: true,
+            wrap: false,
+        };
+        let value = "Fix the flaky login test on CI and the retry loop behind it";
+        assert_eq!(card_field_lines(&field, value, false, 16).len(), 1);
+
+        field.wrap = true;
+        let lines = card_field_lines(&field, value, false, 16);
+        let text: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec!["Summary: Fix the", "flaky login test", "on CI and the…"]);
+        assert_eq!(card_field_lines(&field, "Short", false, 16).len(), 1);
+    }
+
+    #[test]
+    fn test_fit_card_body() {
+        let entry = |text: &str, height: usize| vec![Line::from(text.to_string()); height];
+        let entries = vec![entry("branch", 1), entry("summary", 3), entry("ticket", 1), entry("age", 1)];
+        assert_eq!(fit_card_body(entries.clone(), 6).len(), 6);
+
+        // Whole entries are dropped, never half a wrapped field
+        let text: Vec<String> = fit_card_body(entries.clone(), 4).iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec!["branch", "+3 more"]);
+        let text: Vec<String> = fit_card_body(entries.clone(), 5).iter().map(|l| l.to_string()).collect();
+        assert_eq!(text, vec!["branch", "summary", "summary", "summary", "+2 more"]);
+        assert!(fit_card_body(entries, 0).is_empty());
+    }
+
+    #[test]
+    fn test_preview_beside_or_below() {
+        let (list, preview) = preview_layout(Rect::new(0, 3, 160, 40), 6);
+        assert_eq!(preview.width, 160 / 4);
+        assert_eq!((list.width, preview.x), (120, 120));
+        assert_eq!(preview.height, 40);
+
+        let (list, preview) = preview_layout(Rect::new(0, 3, 80, 40), 6);
+        assert_eq!((list.width, preview.width), (80, 80));
+        assert_eq!((list.height, preview.height), (32, 8));
+        assert_eq!(preview.y, 3 + 32);
+    }
+}